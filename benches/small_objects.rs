@@ -0,0 +1,129 @@
+//! Throughput of zero-byte and tiny files (directory markers, `.keep` files, small JSON blobs) on the bundled adapters.
+//!
+//! Run with `cargo bench --bench small_objects`. S3 is included with `--features s3` when `S3_BUCKET` is set
+//! (see `s3::Config::from_env`), writing under a `flysystem-bench/` prefix and deleting the files afterwards.
+
+use std::path::PathBuf;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use flysystem::{adapters::MemoryAdapter, BatchMode, Filesystem};
+use tokio::runtime::Runtime;
+
+/// The sizes of the files written and read, in bytes.
+const SIZES: [usize; 3] = [0, 64, 4096];
+
+/// How many files each `write_many` call writes.
+const FILES: usize = 100;
+
+fn paths(prefix: &str, size: usize) -> Vec<PathBuf> {
+	(0..FILES)
+		.map(|i| PathBuf::from(format!("{prefix}/{size}/{i}.bin")))
+		.collect()
+}
+
+fn bench(
+	c: &mut Criterion,
+	runtime: &Runtime,
+	adapter: &str,
+	filesystem: &Filesystem,
+	prefix: &str,
+) {
+	let mut group = c.benchmark_group(adapter);
+
+	for size in SIZES {
+		let content = vec![b'x'; size];
+		let path = PathBuf::from(format!("{prefix}/{size}.bin"));
+
+		group.throughput(Throughput::Elements(1));
+		group.bench_with_input(BenchmarkId::new("write", size), &content, |b, content| {
+			b.to_async(runtime)
+				.iter(|| async { filesystem.write(&path, content).await.unwrap() });
+		});
+		group.bench_with_input(BenchmarkId::new("read", size), &path, |b, path| {
+			b.to_async(runtime)
+				.iter(|| async { filesystem.read::<Vec<u8>>(path).await.unwrap() });
+		});
+
+		let batch = paths(&format!("{prefix}/many"), size);
+		group.throughput(Throughput::Elements(FILES as u64));
+		group.bench_with_input(
+			BenchmarkId::new("write_many", size),
+			&content,
+			|b, content| {
+				b.to_async(runtime).iter(|| async {
+					let written = filesystem
+						.write_many(
+							batch.iter().map(|path| (path, content)),
+							BatchMode::CollectAll,
+						)
+						.await;
+					assert!(written.failed.is_empty());
+				});
+			},
+		);
+
+		let deleted = runtime
+			.block_on(filesystem.delete_many(batch.iter().chain([&path]), BatchMode::CollectAll));
+		assert!(deleted.failed.is_empty());
+	}
+
+	group.finish();
+}
+
+fn memory(c: &mut Criterion) {
+	let runtime = Runtime::new().unwrap();
+
+	bench(
+		c,
+		&runtime,
+		"memory",
+		&Filesystem::from_adapter(MemoryAdapter::default()),
+		"flysystem-bench",
+	);
+}
+
+#[cfg(feature = "local")]
+fn local(c: &mut Criterion) {
+	use flysystem::adapters::{local::Config, LocalAdapter};
+
+	let runtime = Runtime::new().unwrap();
+	let location = std::env::temp_dir().join(format!("flysystem-bench-{}", std::process::id()));
+	let filesystem = runtime
+		.block_on(Filesystem::new::<LocalAdapter>(Config {
+			location: location.clone(),
+			lazy_root_creation: true,
+			validation: flysystem::adapters::Validation::default(),
+			public_url: None,
+		}))
+		.unwrap();
+
+	bench(c, &runtime, "local", &filesystem, "flysystem-bench");
+	std::fs::remove_dir_all(location).unwrap();
+}
+
+#[cfg(not(feature = "local"))]
+const fn local(_: &mut Criterion) {}
+
+#[cfg(feature = "s3")]
+fn s3(c: &mut Criterion) {
+	use flysystem::adapters::{s3::ConfigBuilder, S3Adapter};
+
+	if std::env::var("S3_BUCKET").is_err() {
+		return;
+	}
+
+	let runtime = Runtime::new().unwrap();
+	let filesystem = runtime
+		.block_on(Filesystem::new::<S3Adapter>(
+			ConfigBuilder::from_env().build().unwrap(),
+		))
+		.unwrap();
+
+	bench(c, &runtime, "s3", &filesystem, "flysystem-bench");
+}
+
+#[cfg(not(feature = "s3"))]
+const fn s3(_: &mut Criterion) {}
+
+criterion_group!(benches, memory, local, s3);
+criterion_main!(benches);
@@ -0,0 +1,53 @@
+//! The standard [`Workload`]s on the memory adapter, and the local one with the `local` feature.
+//!
+//! Run with `cargo bench --features bench --bench workloads`. Use the `flysystem-bench` binary to compare other adapters.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use flysystem::{
+	adapters::MemoryAdapter,
+	bench::{Bench, Workload},
+	Filesystem,
+};
+use tokio::runtime::Runtime;
+
+fn memory(c: &mut Criterion) {
+	let runtime = Runtime::new().unwrap();
+
+	Bench::new("flysystem-bench")
+		.with_workloads(Workload::standard())
+		.criterion(
+			c,
+			&runtime,
+			"memory",
+			&Filesystem::from_adapter(MemoryAdapter::default()),
+		)
+		.unwrap();
+}
+
+#[cfg(feature = "local")]
+fn local(c: &mut Criterion) {
+	use flysystem::adapters::{local::Config, LocalAdapter, Validation};
+
+	let runtime = Runtime::new().unwrap();
+	let location = std::env::temp_dir().join(format!("flysystem-bench-{}", std::process::id()));
+	let filesystem = runtime
+		.block_on(Filesystem::new::<LocalAdapter>(Config {
+			location: location.clone(),
+			lazy_root_creation: true,
+			validation: Validation::default(),
+			public_url: None,
+		}))
+		.unwrap();
+
+	Bench::new("flysystem-bench")
+		.with_workloads(Workload::standard())
+		.criterion(c, &runtime, "local", &filesystem)
+		.unwrap();
+	std::fs::remove_dir_all(location).unwrap();
+}
+
+#[cfg(not(feature = "local"))]
+const fn local(_: &mut Criterion) {}
+
+criterion_group!(benches, memory, local);
+criterion_main!(benches);
@@ -0,0 +1,800 @@
+use bytes::Bytes;
+use flate2::read::DeflateDecoder;
+use mime::Mime;
+use std::{
+	collections::{BTreeSet, HashMap},
+	fmt::Debug,
+	future::Future,
+	io::{Error, ErrorKind, Read, Result},
+	path::{Path, PathBuf},
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::{Adapter, AdapterInit, AdapterLayer};
+use crate::{contents::Contents, Visibility};
+
+/// The size of a tar block.
+const BLOCK: u64 = 512;
+
+/// The largest possible end of central directory record, including its comment.
+const MAX_EOCD_LEN: u64 = 22 + u16::MAX as u64;
+
+/// Something an archive can be read from in ranges, like a remote URL.
+pub trait RangeSource: Debug + Send + Sync {
+	/// Get the total size of the archive.
+	fn size(&self) -> impl Future<Output = Result<u64>> + Send;
+
+	/// Read `len` bytes starting at `offset`.
+	fn read_range(&self, offset: u64, len: u64) -> impl Future<Output = Result<Vec<u8>>> + Send;
+}
+
+impl RangeSource for Bytes {
+	async fn size(&self) -> Result<u64> {
+		Ok(self.len() as u64)
+	}
+
+	async fn read_range(&self, offset: u64, len: u64) -> Result<Vec<u8>> {
+		let start = usize::try_from(offset)
+			.unwrap_or(usize::MAX)
+			.min(self.len());
+		let end = usize::try_from(offset.saturating_add(len))
+			.unwrap_or(usize::MAX)
+			.min(self.len());
+
+		Ok(self[start..end].to_vec())
+	}
+}
+
+/// An archive served over HTTP by a server supporting range requests (like S3 or most CDNs).
+#[derive(Debug, Clone)]
+pub struct HttpSource {
+	url: url::Url,
+	client: reqwest::Client,
+}
+
+impl HttpSource {
+	#[must_use]
+	pub fn new(url: url::Url) -> Self {
+		Self {
+			url,
+			client: reqwest::Client::new(),
+		}
+	}
+}
+
+impl RangeSource for HttpSource {
+	async fn size(&self) -> Result<u64> {
+		let response = self
+			.client
+			.head(self.url.clone())
+			.send()
+			.await
+			.and_then(reqwest::Response::error_for_status)
+			.map_err(Error::other)?;
+
+		response
+			.headers()
+			.get(reqwest::header::CONTENT_LENGTH)
+			.and_then(|length| length.to_str().ok()?.parse().ok())
+			.ok_or_else(|| Error::other("The server did not return a Content-Length header."))
+	}
+
+	async fn read_range(&self, offset: u64, len: u64) -> Result<Vec<u8>> {
+		if len == 0 {
+			return Ok(Vec::new());
+		}
+
+		let response = self
+			.client
+			.get(self.url.clone())
+			.header(
+				reqwest::header::RANGE,
+				format!("bytes={offset}-{}", offset + len - 1),
+			)
+			.send()
+			.await
+			.and_then(reqwest::Response::error_for_status)
+			.map_err(Error::other)?;
+
+		if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+			return Err(Error::new(
+				ErrorKind::Unsupported,
+				"The server does not support range requests.",
+			));
+		}
+
+		Ok(response.bytes().await.map_err(Error::other)?.to_vec())
+	}
+}
+
+/// The error returned (wrapped in an `Unsupported` [`io::Error`](Error)) when reading a range of a compressed entry.
+///
+/// That would mean inflating everything before it, so only stored (uncompressed) entries can be read in ranges.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{} is compressed, so it can't be read in ranges.", path.display())]
+pub struct CompressedRangeError {
+	/// The path of the compressed entry.
+	pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Compression {
+	Stored,
+	Deflate,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Location {
+	/// The data starts at this offset.
+	Data(u64),
+	/// The data follows the zip local file header at this offset, whose length is only known once read.
+	ZipLocalHeader(u64),
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+	location: Location,
+	compression: Compression,
+	compressed_size: u64,
+	size: u64,
+	last_modified: SystemTime,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+pub struct Config {
+	pub url: url::Url,
+}
+
+/// A read-only adapter exposing the files inside a tar or zip archive, fetching only the ranges it needs instead of the whole archive.
+///
+/// Opening the archive indexes it: zip archives need two reads (their central directory is at the end),
+/// while tar archives need one read per entry, since each header has to be read to find the next one.
+/// Compressed tarballs (like `.tar.gz`) can't be read in ranges, so they aren't supported.
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub struct ArchiveAdapter<S> {
+	source: S,
+	entries: HashMap<PathBuf, Entry>,
+	directories: BTreeSet<PathBuf>,
+}
+
+impl AdapterInit for ArchiveAdapter<HttpSource> {
+	type Config = Config;
+	type Error = Error;
+
+	async fn new(config: Self::Config) -> Result<Self> {
+		Self::open(HttpSource::new(config.url)).await
+	}
+}
+
+impl<S: RangeSource> ArchiveAdapter<S> {
+	/// Index the archive available from `source`.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the archive can't be read, or isn't a valid tar or zip archive.
+	pub async fn open(source: S) -> Result<Self> {
+		let size = source.size().await?;
+		let header = source.read_range(0, BLOCK).await?;
+
+		let entries = if header.starts_with(b"PK") {
+			index_zip(&source, size).await?
+		} else if header.get(257..262) == Some(b"ustar") || is_tar_header(&header) {
+			index_tar(&source, size).await?
+		} else {
+			return Err(Error::new(
+				ErrorKind::InvalidData,
+				"The archive is not a tar or zip archive.",
+			));
+		};
+
+		let directories = entries
+			.keys()
+			.flat_map(|path| path.ancestors().skip(1))
+			.map(Path::to_path_buf)
+			.collect();
+
+		Ok(Self {
+			source,
+			entries,
+			directories,
+		})
+	}
+
+	fn entry(&self, path: &Path) -> Result<&Entry> {
+		self.entries
+			.get(path)
+			.ok_or_else(|| Error::from(ErrorKind::NotFound))
+	}
+
+	async fn data_offset(&self, entry: &Entry) -> Result<u64> {
+		match entry.location {
+			Location::Data(offset) => Ok(offset),
+			Location::ZipLocalHeader(offset) => {
+				let header = self.source.read_range(offset, 30).await?;
+				if header.get(0..4) != Some(b"PK\x03\x04") {
+					return Err(Error::new(
+						ErrorKind::InvalidData,
+						"The zip local file header is corrupted.",
+					));
+				}
+
+				Ok(offset + 30 + u64::from(le_u16(&header, 26)?) + u64::from(le_u16(&header, 28)?))
+			},
+		}
+	}
+}
+
+fn read_only() -> Error {
+	Error::new(ErrorKind::Unsupported, "The archive adapter is read-only.")
+}
+
+impl<S: RangeSource> Adapter for ArchiveAdapter<S> {
+	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		Ok(self.entries.contains_key(path))
+	}
+
+	async fn directory_exists(&self, path: &Path) -> Result<bool> {
+		Ok(self.directories.contains(path))
+	}
+
+	async fn write(&self, _: &Path, _: &[u8]) -> Result<()> {
+		Err(read_only())
+	}
+
+	async fn read(&self, path: &Path) -> Result<Contents> {
+		let entry = self.entry(path)?;
+		let data = self
+			.source
+			.read_range(self.data_offset(entry).await?, entry.compressed_size)
+			.await?;
+
+		match entry.compression {
+			Compression::Stored => Ok(Contents::from(data)),
+			Compression::Deflate => {
+				let mut contents =
+					Vec::with_capacity(usize::try_from(entry.size).unwrap_or_default());
+				DeflateDecoder::new(data.as_slice()).read_to_end(&mut contents)?;
+
+				Ok(Contents::from(contents))
+			},
+		}
+	}
+
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		let entry = self.entry(path)?;
+
+		if matches!(entry.compression, Compression::Stored) {
+			let len = len.min(entry.size.saturating_sub(offset));
+			let offset = self.data_offset(entry).await? + offset.min(entry.size);
+
+			return Ok(Contents::from(self.source.read_range(offset, len).await?));
+		}
+
+		Err(Error::new(
+			ErrorKind::Unsupported,
+			CompressedRangeError {
+				path: path.to_path_buf(),
+			},
+		))
+	}
+
+	async fn delete(&self, _: &Path) -> Result<()> {
+		Err(read_only())
+	}
+
+	async fn delete_directory(&self, _: &Path) -> Result<()> {
+		Err(read_only())
+	}
+
+	async fn create_directory(&self, _: &Path) -> Result<()> {
+		Err(read_only())
+	}
+
+	async fn set_visibility(&self, _: &Path, _: Visibility) -> Result<()> {
+		Err(read_only())
+	}
+
+	async fn visibility(&self, path: &Path) -> Result<Visibility> {
+		self.entry(path)?;
+
+		Ok(Visibility::Public)
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		self.entry(path)?;
+
+		Ok(mime_guess::from_path(path).first_or_octet_stream())
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		Ok(self.entry(path)?.last_modified)
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		Ok(self.entry(path)?.size)
+	}
+
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		if !self.directories.contains(path) {
+			return Err(Error::from(ErrorKind::NotFound));
+		}
+
+		let mut contents: Vec<PathBuf> = self
+			.entries
+			.keys()
+			.filter(|entry| {
+				entry
+					.strip_prefix(path)
+					.is_ok_and(|relative| deep || relative.components().count() == 1)
+			})
+			.cloned()
+			.collect();
+		contents.sort();
+
+		Ok(contents)
+	}
+
+	async fn r#move(&self, _: &Path, _: &Path) -> Result<()> {
+		Err(read_only())
+	}
+
+	async fn copy(&self, _: &Path, _: &Path) -> Result<()> {
+		Err(read_only())
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		Ok(sha256::digest(self.read(path).await?.data))
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		vec![AdapterLayer::new("archive").with("entries", self.entries.len())]
+	}
+}
+
+fn invalid(message: &str) -> Error {
+	Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+fn le_u16(data: &[u8], offset: usize) -> Result<u16> {
+	data.get(offset..offset + 2)
+		.map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+		.ok_or_else(|| invalid("The zip archive is truncated."))
+}
+
+fn le_u32(data: &[u8], offset: usize) -> Result<u32> {
+	data.get(offset..offset + 4)
+		.map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+		.ok_or_else(|| invalid("The zip archive is truncated."))
+}
+
+fn le_u64(data: &[u8], offset: usize) -> Result<u64> {
+	data.get(offset..offset + 8)
+		.and_then(|bytes| bytes.try_into().ok())
+		.map(u64::from_le_bytes)
+		.ok_or_else(|| invalid("The zip archive is truncated."))
+}
+
+/// Parse an octal tar header field, which is NUL or space terminated.
+fn octal(field: &[u8]) -> Option<u64> {
+	let field = std::str::from_utf8(field).ok()?;
+	let field = field.trim_matches(|c: char| c == '\0' || c == ' ');
+
+	if field.is_empty() {
+		return Some(0);
+	}
+
+	u64::from_str_radix(field, 8).ok()
+}
+
+/// Check a tar header's checksum, to recognize pre-POSIX archives without the `ustar` magic.
+fn is_tar_header(header: &[u8]) -> bool {
+	let Some(expected) = header.get(148..156).and_then(octal) else {
+		return false;
+	};
+
+	let sum: u64 = header
+		.iter()
+		.enumerate()
+		.map(|(i, byte)| {
+			if (148..156).contains(&i) {
+				32
+			} else {
+				u64::from(*byte)
+			}
+		})
+		.sum();
+
+	header.len() == 512 && sum == expected
+}
+
+fn tar_string(field: &[u8]) -> String {
+	let end = field
+		.iter()
+		.position(|byte| *byte == 0)
+		.unwrap_or(field.len());
+
+	String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+async fn index_tar<S: RangeSource>(source: &S, size: u64) -> Result<HashMap<PathBuf, Entry>> {
+	let mut entries = HashMap::new();
+	let mut offset = 0;
+	let mut long_name = None;
+
+	while offset + BLOCK <= size {
+		let header = source.read_range(offset, BLOCK).await?;
+		if header.iter().all(|byte| *byte == 0) {
+			break;
+		}
+
+		let entry_size =
+			octal(&header[124..136]).ok_or_else(|| invalid("The tar header is corrupted."))?;
+		let data_offset = offset + BLOCK;
+		offset = data_offset + entry_size.div_ceil(BLOCK) * BLOCK;
+
+		match header[156] {
+			// GNU long name, the data holds the name of the next entry
+			b'L' => {
+				let name = source.read_range(data_offset, entry_size).await?;
+				long_name = Some(tar_string(&name));
+			},
+			// regular files
+			b'0' | b'\0' | b'7' => {
+				let name = long_name.take().unwrap_or_else(|| {
+					let (prefix, name) =
+						(tar_string(&header[345..500]), tar_string(&header[0..100]));
+
+					if header.get(257..262) == Some(b"ustar") && !prefix.is_empty() {
+						format!("{prefix}/{name}")
+					} else {
+						name
+					}
+				});
+
+				entries.insert(
+					crate::normalize(Path::new(&name)),
+					Entry {
+						location: Location::Data(data_offset),
+						compression: Compression::Stored,
+						compressed_size: entry_size,
+						size: entry_size,
+						last_modified: UNIX_EPOCH
+							+ Duration::from_secs(octal(&header[136..148]).unwrap_or_default()),
+					},
+				);
+			},
+			// directories, links and extended headers don't hold files
+			_ => long_name = None,
+		}
+	}
+
+	Ok(entries)
+}
+
+async fn index_zip<S: RangeSource>(source: &S, size: u64) -> Result<HashMap<PathBuf, Entry>> {
+	let tail_offset = size.saturating_sub(MAX_EOCD_LEN);
+	let tail = source.read_range(tail_offset, size - tail_offset).await?;
+
+	let eocd = tail
+		.windows(4)
+		.rposition(|window| window == b"PK\x05\x06")
+		.ok_or_else(|| invalid("The zip archive has no end of central directory record."))?;
+
+	let (mut directory_size, mut directory_offset) = (
+		u64::from(le_u32(&tail, eocd + 12)?),
+		u64::from(le_u32(&tail, eocd + 16)?),
+	);
+
+	// zip64 archives store the real values in a separate record, pointed to by a locator right before the regular one
+	if directory_offset == u64::from(u32::MAX)
+		&& eocd >= 20
+		&& tail[eocd - 20..].starts_with(b"PK\x06\x07")
+	{
+		let record_offset = le_u64(&tail, eocd - 20 + 8)?;
+		let record = source.read_range(record_offset, 56).await?;
+
+		directory_size = le_u64(&record, 40)?;
+		directory_offset = le_u64(&record, 48)?;
+	}
+
+	let directory = source.read_range(directory_offset, directory_size).await?;
+	let mut entries = HashMap::new();
+	let mut position = 0;
+
+	while directory.get(position..position + 4) == Some(b"PK\x01\x02") {
+		let name_len = usize::from(le_u16(&directory, position + 28)?);
+		let extra_len = usize::from(le_u16(&directory, position + 30)?);
+		let comment_len = usize::from(le_u16(&directory, position + 32)?);
+
+		let name_start = position + 46;
+		let name = directory
+			.get(name_start..name_start + name_len)
+			.map(|name| String::from_utf8_lossy(name).into_owned())
+			.ok_or_else(|| invalid("The zip central directory is truncated."))?;
+		let extra = directory
+			.get(name_start + name_len..name_start + name_len + extra_len)
+			.unwrap_or_default();
+
+		let mut size = u64::from(le_u32(&directory, position + 24)?);
+		let mut compressed_size = u64::from(le_u32(&directory, position + 20)?);
+		let mut local_header = u64::from(le_u32(&directory, position + 42)?);
+		apply_zip64_extra(extra, &mut size, &mut compressed_size, &mut local_header)?;
+
+		let compression = match le_u16(&directory, position + 10)? {
+			0 => Some(Compression::Stored),
+			8 => Some(Compression::Deflate),
+			_ => None,
+		};
+
+		if let (Some(compression), false) = (compression, name.ends_with('/')) {
+			entries.insert(
+				crate::normalize(Path::new(&name)),
+				Entry {
+					location: Location::ZipLocalHeader(local_header),
+					compression,
+					compressed_size,
+					size,
+					last_modified: dos_time(
+						le_u16(&directory, position + 14)?,
+						le_u16(&directory, position + 12)?,
+					),
+				},
+			);
+		}
+
+		position = name_start + name_len + extra_len + comment_len;
+	}
+
+	Ok(entries)
+}
+
+/// Replace the sizes and offset that overflowed 32 bits with the ones from the zip64 extra field.
+fn apply_zip64_extra(
+	mut extra: &[u8],
+	size: &mut u64,
+	compressed_size: &mut u64,
+	local_header: &mut u64,
+) -> Result<()> {
+	while extra.len() >= 4 {
+		let (id, len) = (le_u16(extra, 0)?, usize::from(le_u16(extra, 2)?));
+		let data = extra.get(4..4 + len).unwrap_or_default();
+
+		if id == 1 {
+			let mut fields = data.chunks_exact(8).map(|field| le_u64(field, 0));
+
+			for value in [size, compressed_size, local_header] {
+				if *value == u64::from(u32::MAX) {
+					if let Some(field) = fields.next() {
+						*value = field?;
+					}
+				}
+			}
+
+			return Ok(());
+		}
+
+		extra = extra.get(4 + len..).unwrap_or_default();
+	}
+
+	Ok(())
+}
+
+/// Convert an MS-DOS date and time (as stored in zip archives) into a timestamp, treating it as UTC.
+fn dos_time(date: u16, time: u16) -> SystemTime {
+	let year = i64::from(date >> 9) + 1980;
+	let month = i64::from((date >> 5) & 0xf).clamp(1, 12);
+	let day = i64::from(date & 0x1f).max(1);
+
+	// days since the epoch, from Howard Hinnant's `days_from_civil`
+	let y = if month <= 2 { year - 1 } else { year };
+	let era = y.div_euclid(400);
+	let year_of_era = y - era * 400;
+	let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+	let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+	let days = era * 146_097 + day_of_era - 719_468;
+
+	let seconds = i64::from(time >> 11) * 3600
+		+ i64::from((time >> 5) & 0x3f) * 60
+		+ i64::from(time & 0x1f) * 2;
+
+	UNIX_EPOCH + Duration::from_secs(u64::try_from(days * 86400 + seconds).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Write;
+
+	use super::*;
+
+	fn tar() -> Bytes {
+		let mut builder = tar::Builder::new(Vec::new());
+
+		for (path, contents) in [
+			("data/readme.txt", "Hello, world!".to_string()),
+			("data/nested/numbers.csv", "1,2,3".to_string()),
+			(
+				&format!("data/{}/long.txt", "a".repeat(120)),
+				"long name".to_string(),
+			),
+		] {
+			let mut header = tar::Header::new_gnu();
+			header.set_size(contents.len() as u64);
+			header.set_mode(0o644);
+			header.set_mtime(1_700_000_000);
+			builder
+				.append_data(&mut header, path, contents.as_bytes())
+				.unwrap();
+		}
+
+		Bytes::from(builder.into_inner().unwrap())
+	}
+
+	fn zip() -> Bytes {
+		let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+
+		for (path, contents, method) in [
+			(
+				"data/readme.txt",
+				"Hello, world!",
+				zip::CompressionMethod::Stored,
+			),
+			(
+				"data/nested/numbers.csv",
+				"1,2,3,4,5,6,7,8,9,10",
+				zip::CompressionMethod::Deflated,
+			),
+		] {
+			writer
+				.start_file(
+					path,
+					zip::write::SimpleFileOptions::default()
+						.compression_method(method)
+						.last_modified_time(
+							zip::DateTime::from_date_and_time(2023, 11, 14, 22, 13, 20).unwrap(),
+						),
+				)
+				.unwrap();
+			writer.write_all(contents.as_bytes()).unwrap();
+		}
+
+		Bytes::from(writer.finish().unwrap().into_inner())
+	}
+
+	#[tokio::test]
+	async fn test_tar() {
+		let adapter = ArchiveAdapter::open(tar()).await.unwrap();
+
+		assert_eq!(
+			adapter
+				.read(Path::new("data/readme.txt"))
+				.await
+				.unwrap()
+				.data,
+			b"Hello, world!"
+		);
+		assert_eq!(
+			adapter
+				.read_range(Path::new("data/readme.txt"), 7, 100)
+				.await
+				.unwrap()
+				.data,
+			b"world!"
+		);
+		assert_eq!(
+			adapter
+				.read(&Path::new("data").join("a".repeat(120)).join("long.txt"))
+				.await
+				.unwrap()
+				.data,
+			b"long name"
+		);
+		assert_eq!(
+			adapter
+				.last_modified(Path::new("data/nested/numbers.csv"))
+				.await
+				.unwrap(),
+			UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+		);
+		assert_eq!(
+			adapter
+				.list_contents(Path::new("data"), false)
+				.await
+				.unwrap(),
+			vec![PathBuf::from("data/readme.txt")]
+		);
+		assert!(adapter
+			.directory_exists(Path::new("data/nested"))
+			.await
+			.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_zip() {
+		let adapter = ArchiveAdapter::open(zip()).await.unwrap();
+
+		assert_eq!(
+			adapter
+				.read(Path::new("data/readme.txt"))
+				.await
+				.unwrap()
+				.data,
+			b"Hello, world!"
+		);
+		assert_eq!(
+			adapter
+				.read(Path::new("data/nested/numbers.csv"))
+				.await
+				.unwrap()
+				.data,
+			b"1,2,3,4,5,6,7,8,9,10"
+		);
+		assert_eq!(
+			adapter
+				.file_size(Path::new("data/nested/numbers.csv"))
+				.await
+				.unwrap(),
+			20
+		);
+		assert_eq!(
+			adapter
+				.last_modified(Path::new("data/readme.txt"))
+				.await
+				.unwrap(),
+			UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+		);
+		assert_eq!(
+			adapter
+				.list_contents(Path::new("data"), true)
+				.await
+				.unwrap(),
+			vec![
+				PathBuf::from("data/nested/numbers.csv"),
+				PathBuf::from("data/readme.txt")
+			]
+		);
+		assert_eq!(
+			adapter
+				.read_range(Path::new("data/readme.txt"), 7, 5)
+				.await
+				.unwrap()
+				.data,
+			b"world"
+		);
+
+		let error = adapter
+			.read_range(Path::new("data/nested/numbers.csv"), 2, 4)
+			.await
+			.unwrap_err();
+		assert_eq!(error.kind(), ErrorKind::Unsupported);
+		assert_eq!(
+			error
+				.get_ref()
+				.and_then(|error| error.downcast_ref::<CompressedRangeError>()),
+			Some(&CompressedRangeError {
+				path: PathBuf::from("data/nested/numbers.csv")
+			})
+		);
+
+		assert_eq!(
+			adapter
+				.write(Path::new("data/readme.txt"), b"")
+				.await
+				.unwrap_err()
+				.kind(),
+			ErrorKind::Unsupported
+		);
+	}
+
+	#[tokio::test]
+	async fn test_not_an_archive() {
+		assert_eq!(
+			ArchiveAdapter::open(Bytes::from_static(b"Hello, world!"))
+				.await
+				.unwrap_err()
+				.kind(),
+			ErrorKind::InvalidData
+		);
+	}
+}
@@ -0,0 +1,718 @@
+use bytes::BufMut;
+use futures::{channel::oneshot, io::AsyncRead, stream::BoxStream};
+use mime::Mime;
+use std::{
+	collections::{BTreeMap, HashMap},
+	io::{Error, ErrorKind, Result},
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex, MutexGuard, PoisonError},
+	time::{Duration, SystemTime},
+};
+use url::Url;
+
+use super::{ensure_capacity, Adapter, AdapterLayer, TemporaryUrlOptions};
+use crate::{
+	contents::Contents, versioning::FileVersion, DirectoryStat, PathModel, StorageEntry,
+	Visibility, WriteOptions,
+};
+
+/// Counters describing how well a [`CachedAdapter`] is doing, to help size it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+	/// Reads served from the cache.
+	pub hits: u64,
+	/// Reads that had to go to the underlying adapter.
+	pub misses: u64,
+	/// Files dropped from the cache to make room for others.
+	pub evictions: u64,
+	/// Misses that waited for a read of the same file already in flight instead of reading it again (they're counted as misses too).
+	pub coalesced: u64,
+	/// How many files are currently cached.
+	pub entries: usize,
+	/// How many bytes are currently cached.
+	pub size: u64,
+}
+
+impl CacheStats {
+	/// The share of reads served from the cache, between 0 and 1.
+	#[must_use]
+	#[allow(clippy::cast_precision_loss)]
+	pub fn hit_rate(&self) -> f64 {
+		match self.hits + self.misses {
+			0 => 0.0,
+			total => self.hits as f64 / total as f64,
+		}
+	}
+}
+
+/// The outcome of a read, shared with the reads of the same file that waited for it.
+type Shared = std::result::Result<Contents, (ErrorKind, String)>;
+
+/// A read of the underlying adapter in progress, along with the reads of the same file waiting for it.
+#[derive(Debug)]
+struct Flight {
+	id: u64,
+	waiters: Vec<oneshot::Sender<Shared>>,
+}
+
+#[derive(Debug, Default)]
+struct Cache {
+	entries: HashMap<PathBuf, (Contents, u64)>,
+	/// The cached paths by when they were last used, oldest first.
+	recency: BTreeMap<u64, PathBuf>,
+	flights: HashMap<PathBuf, Flight>,
+	tick: u64,
+	stats: CacheStats,
+}
+
+impl Cache {
+	fn get(&mut self, path: &Path) -> Option<Contents> {
+		let contents = self.cached(path);
+		if contents.is_none() {
+			self.stats.misses += 1;
+		}
+
+		contents
+	}
+
+	/// Get a file if it's cached, without counting a miss otherwise (for reads that don't fill the cache).
+	fn cached(&mut self, path: &Path) -> Option<Contents> {
+		self.tick += 1;
+		let (contents, last_used) = self.entries.get_mut(path)?;
+
+		self.recency.remove(last_used);
+		self.recency.insert(self.tick, path.to_path_buf());
+		*last_used = self.tick;
+		self.stats.hits += 1;
+
+		Some(contents.clone())
+	}
+
+	fn insert(&mut self, path: &Path, contents: Contents, capacity: u64) {
+		let len = contents.len() as u64;
+		if len > capacity {
+			return;
+		}
+
+		self.remove(path);
+		while self.stats.size + len > capacity {
+			let Some((_, oldest)) = self.recency.pop_first() else {
+				break;
+			};
+
+			self.remove(&oldest);
+			self.stats.evictions += 1;
+		}
+
+		self.tick += 1;
+		self.recency.insert(self.tick, path.to_path_buf());
+		self.entries
+			.insert(path.to_path_buf(), (contents, self.tick));
+		self.stats.size += len;
+		self.stats.entries = self.entries.len();
+	}
+
+	/// Lead the read of a file that missed, getting the ID of its flight, or wait for the read already in flight.
+	fn join(&mut self, path: &Path) -> std::result::Result<u64, oneshot::Receiver<Shared>> {
+		if let Some(flight) = self.flights.get_mut(path) {
+			let (sender, receiver) = oneshot::channel();
+			flight.waiters.push(sender);
+			self.stats.coalesced += 1;
+
+			return Err(receiver);
+		}
+
+		self.tick += 1;
+		self.flights.insert(
+			path.to_path_buf(),
+			Flight {
+				id: self.tick,
+				waiters: Vec::new(),
+			},
+		);
+
+		Ok(self.tick)
+	}
+
+	/// Cache the result of a flight and hand it to the reads waiting for it, unless the file changed in the meantime.
+	fn land(&mut self, path: &Path, id: u64, result: &Result<Contents>, capacity: u64) {
+		if self.flights.get(path).is_none_or(|flight| flight.id != id) {
+			return;
+		}
+
+		let flight = self
+			.flights
+			.remove(path)
+			.expect("The flight was just found.");
+		if let Ok(contents) = result {
+			self.insert(path, contents.clone(), capacity);
+		}
+
+		for waiter in flight.waiters {
+			let _ = waiter.send(match result {
+				Ok(contents) => Ok(contents.clone()),
+				Err(error) => Err((error.kind(), error.to_string())),
+			});
+		}
+	}
+
+	fn remove(&mut self, path: &Path) {
+		// reads waiting for a read started before the change retry once it's dropped
+		self.flights.remove(path);
+
+		if let Some((contents, last_used)) = self.entries.remove(path) {
+			self.recency.remove(&last_used);
+			self.stats.size -= contents.len() as u64;
+			self.stats.entries = self.entries.len();
+		}
+	}
+
+	fn remove_prefix(&mut self, prefix: &Path) {
+		self.flights.retain(|path, _| !path.starts_with(prefix));
+
+		let paths: Vec<PathBuf> = self
+			.entries
+			.keys()
+			.filter(|path| path.starts_with(prefix))
+			.cloned()
+			.collect();
+
+		for path in paths {
+			self.remove(&path);
+		}
+	}
+}
+
+/// Abandons a flight if its leading read is dropped before it completes, so the reads waiting for it retry on their own.
+struct Landing<'a> {
+	cache: &'a Mutex<Cache>,
+	path: &'a Path,
+	id: u64,
+}
+
+impl Drop for Landing<'_> {
+	fn drop(&mut self) {
+		let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+		if cache
+			.flights
+			.get(self.path)
+			.is_some_and(|flight| flight.id == self.id)
+		{
+			cache.flights.remove(self.path);
+		}
+	}
+}
+
+/// An adapter keeping the contents of recently read files in memory, up to a total size.
+///
+/// The cache and its statistics are shared between clones, so a clone can be kept around to check [`CachedAdapter::stats`]
+/// after handing the adapter to a [`Filesystem`](crate::Filesystem). Changes made through the adapter invalidate the affected files,
+/// but changes made to the storage by others aren't noticed.
+///
+/// Concurrent misses for the same file are coalesced into a single read of the underlying adapter (see [`CacheStats::coalesced`]),
+/// so a popular file that isn't cached yet doesn't get read hundreds of times at once.
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct CachedAdapter<A> {
+	inner: A,
+	capacity: u64,
+	cache: Arc<Mutex<Cache>>,
+}
+
+impl<A: Adapter> CachedAdapter<A> {
+	/// Cache up to `capacity` bytes of file contents read from `inner`.
+	pub fn new(inner: A, capacity: u64) -> Self {
+		Self {
+			inner,
+			capacity,
+			cache: Arc::default(),
+		}
+	}
+
+	/// Get the cache's counters.
+	#[must_use]
+	pub fn stats(&self) -> CacheStats {
+		self.cache().stats
+	}
+
+	fn cache(&self) -> MutexGuard<'_, Cache> {
+		self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
+
+impl<A: Adapter> Adapter for CachedAdapter<A> {
+	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		self.inner.file_exists(path).await
+	}
+
+	async fn directory_exists(&self, path: &Path) -> Result<bool> {
+		self.inner.directory_exists(path).await
+	}
+
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.cache().remove(path);
+
+		self.inner.write(path, content).await
+	}
+
+	async fn write_with(&self, path: &Path, content: &[u8], options: &WriteOptions) -> Result<()> {
+		self.cache().remove(path);
+
+		self.inner.write_with(path, content, options).await
+	}
+
+	async fn write_atomic(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.cache().remove(path);
+
+		self.inner.write_atomic(path, content).await
+	}
+
+	async fn write_stream(
+		&self,
+		path: &Path,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		self.cache().remove(path);
+
+		self.inner.write_stream(path, reader).await
+	}
+
+	async fn write_range(&self, path: &Path, offset: u64, data: &[u8]) -> Result<()> {
+		self.cache().remove(path);
+
+		self.inner.write_range(path, offset, data).await
+	}
+
+	async fn append(&self, path: &Path, data: &[u8]) -> Result<()> {
+		self.cache().remove(path);
+
+		self.inner.append(path, data).await
+	}
+
+	fn native_range_writes(&self) -> bool {
+		self.inner.native_range_writes()
+	}
+
+	async fn read(&self, path: &Path) -> Result<Contents> {
+		loop {
+			let joined = {
+				let mut cache = self.cache();
+				if let Some(contents) = cache.get(path) {
+					return Ok(contents);
+				}
+
+				cache.join(path)
+			};
+
+			match joined {
+				Ok(id) => {
+					let landing = Landing {
+						cache: &self.cache,
+						path,
+						id,
+					};
+					let result = self.inner.read(path).await;
+					self.cache().land(path, id, &result, self.capacity);
+					drop(landing);
+
+					return result;
+				},
+				Err(receiver) => {
+					if let Ok(result) = receiver.await {
+						return result.map_err(|(kind, message)| Error::new(kind, message));
+					}
+					// the leading read was dropped, or the file changed while it was in flight
+				},
+			}
+		}
+	}
+
+	async fn read_stream(&self, path: &Path) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+		self.inner.read_stream(path).await
+	}
+
+	async fn read_into(&self, path: &Path, buf: &mut (dyn BufMut + Send)) -> Result<()> {
+		let cached = self.cache().cached(path);
+		let Some(contents) = cached else {
+			return self.inner.read_into(path, buf).await;
+		};
+
+		ensure_capacity(buf, contents.len() as u64)?;
+		buf.put_slice(&contents);
+
+		Ok(())
+	}
+
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		let cached = self.cache().cached(path);
+		let Some(contents) = cached else {
+			return self.inner.read_range(path, offset, len).await;
+		};
+
+		let start = usize::try_from(offset)
+			.unwrap_or(usize::MAX)
+			.min(contents.len());
+		let end = usize::try_from(offset.saturating_add(len))
+			.unwrap_or(usize::MAX)
+			.min(contents.len());
+
+		Ok(Contents::from(contents[start..end].to_vec()))
+	}
+
+	async fn list_versions(&self, prefix: &Path) -> Result<Vec<FileVersion>> {
+		self.inner.list_versions(prefix).await
+	}
+
+	async fn read_version(&self, path: &Path, version_id: &str) -> Result<Contents> {
+		self.inner.read_version(path, version_id).await
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
+		self.cache().remove(path);
+
+		self.inner.delete(path).await
+	}
+
+	async fn delete_many(&self, paths: &[PathBuf]) -> Vec<(PathBuf, Error)> {
+		{
+			let mut cache = self.cache();
+			for path in paths {
+				cache.remove(path);
+			}
+		}
+
+		self.inner.delete_many(paths).await
+	}
+
+	async fn write_many(&self, files: &[(&Path, &[u8])]) -> Vec<(PathBuf, Error)> {
+		{
+			let mut cache = self.cache();
+			for (path, _) in files {
+				cache.remove(path);
+			}
+		}
+
+		self.inner.write_many(files).await
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
+		self.cache().remove_prefix(path);
+
+		self.inner.delete_directory(path).await
+	}
+
+	async fn create_directory(&self, path: &Path) -> Result<()> {
+		self.inner.create_directory(path).await
+	}
+
+	async fn set_mime_type(&self, path: &Path, mime_type: &Mime) -> Result<()> {
+		self.inner.set_mime_type(path, mime_type).await
+	}
+
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
+		self.inner.set_visibility(path, visibility).await
+	}
+
+	async fn set_visibility_many(
+		&self,
+		paths: &[PathBuf],
+		visibility: Visibility,
+	) -> Vec<(PathBuf, Error)> {
+		self.inner.set_visibility_many(paths, visibility).await
+	}
+
+	async fn visibility(&self, path: &Path) -> Result<Visibility> {
+		self.inner.visibility(path).await
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		self.inner.mime_type(path).await
+	}
+
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		self.inner.metadata(path).await
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		self.inner.last_modified(path).await
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		self.inner.file_size(path).await
+	}
+
+	async fn directory_stat(&self, path: &Path) -> Result<DirectoryStat> {
+		self.inner.directory_stat(path).await
+	}
+
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		self.inner.list_contents(path, deep).await
+	}
+
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		self.inner.list_contents_with_metadata(path, deep).await
+	}
+
+	fn list_stream<'a>(&'a self, path: &Path, deep: bool) -> BoxStream<'a, Result<PathBuf>> {
+		self.inner.list_stream(path, deep)
+	}
+
+	fn list_stream_after<'a>(
+		&'a self,
+		path: &Path,
+		deep: bool,
+		after: Option<&Path>,
+	) -> BoxStream<'a, Result<PathBuf>> {
+		self.inner.list_stream_after(path, deep, after)
+	}
+
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		{
+			let mut cache = self.cache();
+			cache.remove_prefix(source);
+			cache.remove_prefix(destination);
+		}
+
+		self.inner.r#move(source, destination).await
+	}
+
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.cache().remove(destination);
+
+		self.inner.copy(source, destination).await
+	}
+
+	async fn move_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		{
+			let mut cache = self.cache();
+			cache.remove_prefix(source);
+			cache.remove_prefix(destination);
+		}
+
+		self.inner.move_directory(source, destination).await
+	}
+
+	async fn copy_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.cache().remove_prefix(destination);
+
+		self.inner.copy_directory(source, destination).await
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		self.inner.checksum(path).await
+	}
+
+	async fn public_url(&self, path: &Path) -> Result<Url> {
+		self.inner.public_url(path).await
+	}
+
+	async fn temporary_url(&self, path: &Path, expires_in: Duration) -> Result<Url> {
+		self.inner.temporary_url(path, expires_in).await
+	}
+
+	async fn temporary_url_with(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+		options: &TemporaryUrlOptions,
+	) -> Result<Url> {
+		self.inner
+			.temporary_url_with(path, expires_in, options)
+			.await
+	}
+
+	fn path_model(&self) -> PathModel {
+		self.inner.path_model()
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		AdapterLayer::new("cached")
+			.with("capacity", self.capacity)
+			.wrapping(&self.inner)
+	}
+
+	async fn prefetch(&self, path: &Path) -> Result<()> {
+		Adapter::read(self, path).await.map(drop)
+	}
+
+	async fn flush(&self) -> Result<()> {
+		self.inner.flush().await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{adapters::MemoryAdapter, Filesystem};
+
+	#[tokio::test]
+	async fn test_cache_stats() {
+		let adapter = CachedAdapter::new(MemoryAdapter::default(), 10);
+		let cache = adapter.clone();
+		let filesystem = Filesystem::from_adapter(adapter);
+
+		filesystem.write(Path::new("a.txt"), b"aaaa").await.unwrap();
+		filesystem.write(Path::new("b.txt"), b"bbbb").await.unwrap();
+		filesystem.write(Path::new("c.txt"), b"cccc").await.unwrap();
+
+		for path in ["a.txt", "a.txt", "b.txt", "a.txt", "c.txt"] {
+			filesystem.read::<String>(Path::new(path)).await.unwrap();
+		}
+
+		// caching c.txt evicted b.txt, the least recently used file
+		assert_eq!(
+			cache.stats(),
+			CacheStats {
+				hits: 2,
+				misses: 3,
+				evictions: 1,
+				coalesced: 0,
+				entries: 2,
+				size: 8,
+			}
+		);
+		assert!((cache.stats().hit_rate() - 0.4).abs() < f64::EPSILON);
+	}
+
+	#[tokio::test]
+	async fn test_coalesces_concurrent_misses() {
+		let path = Path::new("viral.jpg");
+		let mut cache = Cache::default();
+
+		// the first miss reads the file, the next ones wait for it
+		let id = cache.join(path).unwrap();
+		let waiters: Vec<_> = (0..3).map(|_| cache.join(path).unwrap_err()).collect();
+		cache.land(path, id, &Ok(Contents::from(b"cat".to_vec())), 1024);
+
+		for waiter in waiters {
+			assert_eq!(*waiter.await.unwrap().unwrap(), *b"cat");
+		}
+		assert_eq!(cache.stats.coalesced, 3);
+		assert!(cache.get(path).is_some());
+
+		// a write while the file is in flight sends the waiting reads back to the adapter, and the stale contents aren't cached
+		cache.remove(path);
+		let id = cache.join(path).unwrap();
+		let waiter = cache.join(path).unwrap_err();
+		cache.remove(path);
+		cache.land(path, id, &Ok(Contents::from(b"dog".to_vec())), 1024);
+
+		assert!(waiter.await.is_err());
+		assert!(cache.get(path).is_none());
+	}
+
+	#[tokio::test]
+	async fn test_writes_invalidate_cache() {
+		let adapter = CachedAdapter::new(MemoryAdapter::default(), 1024);
+		let cache = adapter.clone();
+		let filesystem = Filesystem::from_adapter(adapter);
+
+		filesystem
+			.write(Path::new("config.json"), b"{}")
+			.await
+			.unwrap();
+		filesystem
+			.read::<String>(Path::new("config.json"))
+			.await
+			.unwrap();
+		filesystem
+			.write(Path::new("config.json"), b"{\"debug\":true}")
+			.await
+			.unwrap();
+
+		assert_eq!(
+			filesystem
+				.read::<String>(Path::new("config.json"))
+				.await
+				.unwrap(),
+			"{\"debug\":true}"
+		);
+		assert_eq!(cache.stats().misses, 2);
+
+		filesystem
+			.write_atomic(Path::new("config.json"), b"{}")
+			.await
+			.unwrap();
+		assert_eq!(
+			filesystem
+				.read::<String>(Path::new("config.json"))
+				.await
+				.unwrap(),
+			"{}"
+		);
+	}
+
+	#[tokio::test]
+	async fn test_partial_reads() {
+		let adapter = CachedAdapter::new(MemoryAdapter::default(), 1024);
+		let cache = adapter.clone();
+		let filesystem = Filesystem::from_adapter(adapter);
+
+		filesystem
+			.write(Path::new("video.mp4"), b"ftypmoov")
+			.await
+			.unwrap();
+
+		// uncached files are read from the underlying adapter, without caching them
+		assert_eq!(
+			*filesystem
+				.read_range(Path::new("video.mp4"), 4, 4)
+				.await
+				.unwrap(),
+			*b"moov"
+		);
+		assert_eq!(cache.stats(), CacheStats::default());
+
+		// cached files are served from the cache
+		filesystem
+			.read::<Vec<u8>>(Path::new("video.mp4"))
+			.await
+			.unwrap();
+		assert_eq!(
+			*filesystem
+				.read_range(Path::new("video.mp4"), 0, 4)
+				.await
+				.unwrap(),
+			*b"ftyp"
+		);
+		let mut buf = Vec::new();
+		filesystem
+			.read_into(Path::new("video.mp4"), &mut buf)
+			.await
+			.unwrap();
+		assert_eq!(buf, b"ftypmoov");
+
+		let stats = cache.stats();
+		assert_eq!((stats.hits, stats.misses), (2, 1));
+	}
+
+	#[tokio::test]
+	async fn test_prefetch() {
+		let adapter = CachedAdapter::new(MemoryAdapter::default(), 1024);
+		let cache = adapter.clone();
+		let filesystem = Filesystem::from_adapter(adapter);
+
+		filesystem.write(Path::new("app.js"), b"js").await.unwrap();
+		filesystem
+			.write(Path::new("app.css"), b"css")
+			.await
+			.unwrap();
+
+		let report = filesystem
+			.prefetch(["app.js", "app.css", "missing.js"], 4)
+			.await;
+		assert_eq!(report.succeeded.len(), 2);
+		assert_eq!(report.failed[0].0, Path::new("missing.js"));
+
+		filesystem
+			.read::<String>(Path::new("app.js"))
+			.await
+			.unwrap();
+		let stats = cache.stats();
+		assert_eq!((stats.hits, stats.misses, stats.entries), (1, 3, 2));
+	}
+}
@@ -0,0 +1,206 @@
+use futures::stream::BoxStream;
+use mime::Mime;
+use std::{
+	collections::HashMap,
+	io::{Error, Result},
+	path::{Path, PathBuf},
+	time::SystemTime,
+};
+
+use super::{Adapter, AdapterLayer};
+use crate::{
+	codec::Codec, contents::Contents, versioning::FileVersion, DirectoryStat, PathModel,
+	StorageEntry, Visibility, WriteOptions,
+};
+
+/// An adapter passing the contents of files through a [`Codec`] before storing them in another one, and back once read.
+///
+/// Paths and metadata are stored as is. Since codecs can change the size of files arbitrarily, getting the size of a file
+/// (or listing its versions) decodes it and listings with metadata leave sizes out. Checksums are the SHA-256 of the decoded contents.
+/// Partial reads and writes decode and rewrite the whole file, and public or temporary URLs aren't supported,
+/// since they'd serve the encoded contents.
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct CodecAdapter<A, C> {
+	inner: A,
+	codec: C,
+}
+
+impl<A: Adapter, C: Codec> CodecAdapter<A, C> {
+	/// Encode the files stored in `inner` with the given codec.
+	pub const fn new(inner: A, codec: C) -> Self {
+		Self { inner, codec }
+	}
+}
+
+impl<A: Adapter, C: Codec> Adapter for CodecAdapter<A, C> {
+	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		self.inner.file_exists(path).await
+	}
+
+	async fn directory_exists(&self, path: &Path) -> Result<bool> {
+		self.inner.directory_exists(path).await
+	}
+
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.inner.write(path, &self.codec.encode(content)?).await
+	}
+
+	async fn write_with(&self, path: &Path, content: &[u8], options: &WriteOptions) -> Result<()> {
+		self.inner
+			.write_with(path, &self.codec.encode(content)?, options)
+			.await
+	}
+
+	async fn write_atomic(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.inner
+			.write_atomic(path, &self.codec.encode(content)?)
+			.await
+	}
+
+	async fn read(&self, path: &Path) -> Result<Contents> {
+		let contents = self.inner.read(path).await?;
+
+		Ok(Contents::from(self.codec.decode(&contents)?))
+	}
+
+	async fn list_versions(&self, prefix: &Path) -> Result<Vec<FileVersion>> {
+		let mut versions = self.inner.list_versions(prefix).await?;
+		for version in versions.iter_mut().filter(|version| !version.deleted) {
+			version.size = self
+				.read_version(&version.path, &version.version_id)
+				.await?
+				.len() as u64;
+		}
+
+		Ok(versions)
+	}
+
+	async fn read_version(&self, path: &Path, version_id: &str) -> Result<Contents> {
+		let contents = self.inner.read_version(path, version_id).await?;
+
+		Ok(Contents::from(self.codec.decode(&contents)?))
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
+		self.inner.delete(path).await
+	}
+
+	async fn delete_many(&self, paths: &[PathBuf]) -> Vec<(PathBuf, Error)> {
+		self.inner.delete_many(paths).await
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
+		self.inner.delete_directory(path).await
+	}
+
+	async fn create_directory(&self, path: &Path) -> Result<()> {
+		self.inner.create_directory(path).await
+	}
+
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
+		self.inner.set_visibility(path, visibility).await
+	}
+
+	async fn set_visibility_many(
+		&self,
+		paths: &[PathBuf],
+		visibility: Visibility,
+	) -> Vec<(PathBuf, Error)> {
+		self.inner.set_visibility_many(paths, visibility).await
+	}
+
+	async fn visibility(&self, path: &Path) -> Result<Visibility> {
+		self.inner.visibility(path).await
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		self.inner.mime_type(path).await
+	}
+
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		self.inner.metadata(path).await
+	}
+
+	async fn set_mime_type(&self, path: &Path, mime_type: &Mime) -> Result<()> {
+		self.inner.set_mime_type(path, mime_type).await
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		self.inner.last_modified(path).await
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		Ok(self.read(path).await?.len() as u64)
+	}
+
+	async fn directory_stat(&self, path: &Path) -> Result<DirectoryStat> {
+		self.inner.directory_stat(path).await
+	}
+
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		self.inner.list_contents(path, deep).await
+	}
+
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		let mut entries = self.inner.list_contents_with_metadata(path, deep).await?;
+		for entry in &mut entries {
+			entry.size = None;
+		}
+
+		Ok(entries)
+	}
+
+	fn list_stream<'a>(&'a self, path: &Path, deep: bool) -> BoxStream<'a, Result<PathBuf>> {
+		self.inner.list_stream(path, deep)
+	}
+
+	fn list_stream_after<'a>(
+		&'a self,
+		path: &Path,
+		deep: bool,
+		after: Option<&Path>,
+	) -> BoxStream<'a, Result<PathBuf>> {
+		self.inner.list_stream_after(path, deep, after)
+	}
+
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.inner.r#move(source, destination).await
+	}
+
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.inner.copy(source, destination).await
+	}
+
+	async fn move_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.inner.move_directory(source, destination).await
+	}
+
+	async fn copy_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.inner.copy_directory(source, destination).await
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		Ok(sha256::digest(self.read(path).await?.data))
+	}
+
+	fn path_model(&self) -> PathModel {
+		self.inner.path_model()
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		AdapterLayer::new("codec").wrapping(&self.inner)
+	}
+
+	async fn prefetch(&self, path: &Path) -> Result<()> {
+		self.inner.prefetch(path).await
+	}
+
+	async fn flush(&self) -> Result<()> {
+		self.inner.flush().await
+	}
+}
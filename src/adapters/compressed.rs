@@ -0,0 +1,397 @@
+use flate2::{read::GzDecoder, write::GzEncoder};
+use futures::{stream::BoxStream, StreamExt, TryStreamExt};
+use mime::Mime;
+use std::{
+	borrow::Cow,
+	collections::HashMap,
+	ffi::OsString,
+	io::{Error, ErrorKind, Read, Result, Write},
+	path::{Path, PathBuf},
+	time::SystemTime,
+};
+
+use super::{write_atomic_with, Adapter, AdapterLayer};
+use crate::{
+	codec::Codec, contents::Contents, DirectoryStat, PathModel, StorageEntry, Visibility,
+	WriteOptions,
+};
+
+/// The algorithm a [`CompressedAdapter`] compresses files with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+	#[default]
+	Gzip,
+	#[cfg(feature = "zstd")]
+	Zstd,
+}
+
+impl Compression {
+	/// The extension of the files compressed with this algorithm, without the leading dot.
+	#[must_use]
+	pub const fn extension(self) -> &'static str {
+		match self {
+			Self::Gzip => "gz",
+			#[cfg(feature = "zstd")]
+			Self::Zstd => "zst",
+		}
+	}
+
+	/// The `Content-Encoding` of the files compressed with this algorithm.
+	#[must_use]
+	pub const fn content_encoding(self) -> &'static str {
+		match self {
+			Self::Gzip => "gzip",
+			#[cfg(feature = "zstd")]
+			Self::Zstd => "zstd",
+		}
+	}
+
+	fn compress(self, data: &[u8]) -> Result<Vec<u8>> {
+		match self {
+			Self::Gzip => {
+				let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+				encoder.write_all(data)?;
+				encoder.finish()
+			},
+			#[cfg(feature = "zstd")]
+			Self::Zstd => zstd::encode_all(data, 0),
+		}
+	}
+
+	fn decompress(self, data: &[u8]) -> Result<Vec<u8>> {
+		let mut contents = Vec::new();
+
+		match self {
+			Self::Gzip => GzDecoder::new(data).read_to_end(&mut contents)?,
+			#[cfg(feature = "zstd")]
+			Self::Zstd => return zstd::decode_all(data),
+		};
+
+		Ok(contents)
+	}
+}
+
+impl Codec for Compression {
+	fn encode(&self, contents: &[u8]) -> Result<Vec<u8>> {
+		self.compress(contents)
+	}
+
+	fn decode(&self, contents: &[u8]) -> Result<Vec<u8>> {
+		self.decompress(contents)
+			.map_err(|error| Error::new(ErrorKind::InvalidData, error))
+	}
+}
+
+/// Where a [`CompressedAdapter`] stores compressed files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Naming {
+	/// Store files under their own path, so nothing tells them apart from uncompressed ones.
+	#[default]
+	Unchanged,
+	/// Add the [extension](Compression::extension) of the algorithm to the path (like `app.log.gz`), hiding it again from listings.
+	Extension,
+}
+
+/// An adapter compressing the contents of files before storing them in another one, like for archives of text logs.
+///
+/// MIME types are those of the uncompressed files (`text/plain` for `app.log`, not `application/gzip`), and checksums are
+/// the SHA-256 of the uncompressed contents. Getting the size of a file decompresses it, so listings with metadata leave sizes out.
+/// Partial reads and writes decompress and rewrite the whole file, and public or temporary URLs aren't supported,
+/// since they'd serve the compressed contents.
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct CompressedAdapter<A> {
+	inner: A,
+	compression: Compression,
+	naming: Naming,
+}
+
+impl<A: Adapter> CompressedAdapter<A> {
+	/// Compress the files stored in `inner` with the given algorithm, under their own path.
+	pub const fn new(inner: A, compression: Compression) -> Self {
+		Self {
+			inner,
+			compression,
+			naming: Naming::Unchanged,
+		}
+	}
+
+	/// Choose where compressed files are stored.
+	#[must_use]
+	pub const fn with_naming(mut self, naming: Naming) -> Self {
+		self.naming = naming;
+		self
+	}
+
+	/// Get the path a file is stored under.
+	fn stored<'a>(&self, path: &'a Path) -> Cow<'a, Path> {
+		match self.naming {
+			Naming::Unchanged => Cow::Borrowed(path),
+			Naming::Extension => {
+				let mut stored = OsString::from(path);
+				stored.push(".");
+				stored.push(self.compression.extension());
+
+				Cow::Owned(PathBuf::from(stored))
+			},
+		}
+	}
+
+	/// Get the path of a stored file as seen through this adapter.
+	fn logical(&self, mut path: PathBuf) -> PathBuf {
+		if self.naming == Naming::Extension
+			&& path.extension().and_then(|extension| extension.to_str())
+				== Some(self.compression.extension())
+		{
+			path.set_extension("");
+		}
+
+		path
+	}
+}
+
+impl<A: Adapter> Adapter for CompressedAdapter<A> {
+	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		self.inner.file_exists(&self.stored(path)).await
+	}
+
+	async fn directory_exists(&self, path: &Path) -> Result<bool> {
+		self.inner.directory_exists(path).await
+	}
+
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.write_with(path, content, &WriteOptions::default())
+			.await
+	}
+
+	async fn write_with(&self, path: &Path, content: &[u8], options: &WriteOptions) -> Result<()> {
+		let mut options = options.clone();
+		// keep the type of the uncompressed file, instead of the one guessed from the stored path
+		options.content_type = options
+			.content_type
+			.or_else(|| Some(mime_guess::from_path(path).first_or_octet_stream()));
+
+		self.inner
+			.write_with(
+				&self.stored(path),
+				&self.compression.compress(content)?,
+				&options,
+			)
+			.await
+	}
+
+	async fn write_atomic(&self, path: &Path, content: &[u8]) -> Result<()> {
+		let options = WriteOptions {
+			content_type: Some(mime_guess::from_path(path).first_or_octet_stream()),
+			..WriteOptions::default()
+		};
+
+		write_atomic_with(
+			&self.inner,
+			&self.stored(path),
+			&self.compression.compress(content)?,
+			&options,
+		)
+		.await
+	}
+
+	async fn read(&self, path: &Path) -> Result<Contents> {
+		let contents = self.inner.read(&self.stored(path)).await?;
+
+		Ok(Contents::from(self.compression.decode(&contents)?))
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
+		self.inner.delete(&self.stored(path)).await
+	}
+
+	async fn delete_many(&self, paths: &[PathBuf]) -> Vec<(PathBuf, Error)> {
+		let stored: Vec<PathBuf> = paths
+			.iter()
+			.map(|path| self.stored(path).into_owned())
+			.collect();
+
+		self.inner
+			.delete_many(&stored)
+			.await
+			.into_iter()
+			.map(|(path, error)| (self.logical(path), error))
+			.collect()
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
+		self.inner.delete_directory(path).await
+	}
+
+	async fn create_directory(&self, path: &Path) -> Result<()> {
+		self.inner.create_directory(path).await
+	}
+
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
+		self.inner
+			.set_visibility(&self.stored(path), visibility)
+			.await
+	}
+
+	async fn visibility(&self, path: &Path) -> Result<Visibility> {
+		self.inner.visibility(&self.stored(path)).await
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		let stored = self.stored(path);
+		let mime_type = self.inner.mime_type(&stored).await?;
+
+		// adapters guessing types from paths see the extension of the compressed file
+		if self.naming == Naming::Extension
+			&& mime_guess::from_path(&stored).first().as_ref() == Some(&mime_type)
+		{
+			return Ok(mime_guess::from_path(path).first_or_octet_stream());
+		}
+
+		Ok(mime_type)
+	}
+
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		self.inner.metadata(&self.stored(path)).await
+	}
+
+	async fn set_mime_type(&self, path: &Path, mime_type: &Mime) -> Result<()> {
+		self.inner
+			.set_mime_type(&self.stored(path), mime_type)
+			.await
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		self.inner.last_modified(&self.stored(path)).await
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		Ok(self.read(path).await?.len() as u64)
+	}
+
+	async fn directory_stat(&self, path: &Path) -> Result<DirectoryStat> {
+		self.inner.directory_stat(path).await
+	}
+
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		Ok(self
+			.inner
+			.list_contents(path, deep)
+			.await?
+			.into_iter()
+			.map(|entry| self.logical(entry))
+			.collect())
+	}
+
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		let mut entries = self.inner.list_contents_with_metadata(path, deep).await?;
+		for entry in &mut entries {
+			if entry.is_dir {
+				continue;
+			}
+
+			entry.path = self.logical(std::mem::take(&mut entry.path));
+			entry.size = None;
+			entry.mime = Some(mime_guess::from_path(&entry.path).first_or_octet_stream());
+		}
+
+		Ok(entries)
+	}
+
+	fn list_stream<'a>(&'a self, path: &Path, deep: bool) -> BoxStream<'a, Result<PathBuf>> {
+		self.inner
+			.list_stream(path, deep)
+			.map_ok(|entry| self.logical(entry))
+			.boxed()
+	}
+
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.inner
+			.r#move(&self.stored(source), &self.stored(destination))
+			.await
+	}
+
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.inner
+			.copy(&self.stored(source), &self.stored(destination))
+			.await
+	}
+
+	async fn move_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.inner.move_directory(source, destination).await
+	}
+
+	async fn copy_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.inner.copy_directory(source, destination).await
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		Ok(sha256::digest(self.read(path).await?.data))
+	}
+
+	fn path_model(&self) -> PathModel {
+		self.inner.path_model()
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		AdapterLayer::new("compressed")
+			.with("encoding", self.compression.content_encoding())
+			.wrapping(&self.inner)
+	}
+
+	async fn prefetch(&self, path: &Path) -> Result<()> {
+		self.inner.prefetch(&self.stored(path)).await
+	}
+
+	async fn flush(&self) -> Result<()> {
+		self.inner.flush().await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::adapters::MemoryAdapter;
+
+	#[tokio::test]
+	async fn test_compresses_contents() {
+		let adapter = CompressedAdapter::new(MemoryAdapter::default(), Compression::Gzip)
+			.with_naming(Naming::Extension);
+		let log = "GET /index.html 200\n".repeat(100);
+
+		adapter
+			.write(Path::new("logs/app.log"), log.as_bytes())
+			.await
+			.unwrap();
+
+		let stored = adapter
+			.inner
+			.read(Path::new("logs/app.log.gz"))
+			.await
+			.unwrap();
+		assert!(stored.len() < log.len());
+		assert_eq!(
+			adapter.read(Path::new("logs/app.log")).await.unwrap().data,
+			log.as_bytes()
+		);
+		assert_eq!(
+			adapter.file_size(Path::new("logs/app.log")).await.unwrap(),
+			log.len() as u64
+		);
+		assert_eq!(
+			adapter.mime_type(Path::new("logs/app.log")).await.unwrap(),
+			mime::TEXT_PLAIN
+		);
+		assert_eq!(
+			adapter
+				.list_contents(Path::new("logs"), false)
+				.await
+				.unwrap(),
+			vec![PathBuf::from("logs/app.log")]
+		);
+	}
+}
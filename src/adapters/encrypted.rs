@@ -0,0 +1,745 @@
+use aes_gcm::{
+	aead::{Aead, AeadCore, KeyInit, OsRng},
+	Aes256Gcm, Key, Nonce,
+};
+use futures::stream::BoxStream;
+use mime::Mime;
+use std::{
+	collections::HashMap,
+	fmt::{self, Debug},
+	io::{Error, ErrorKind, Result},
+	ops::Deref,
+	path::{Component, Path, PathBuf},
+	sync::{Arc, PoisonError, RwLock},
+	time::SystemTime,
+};
+
+use super::{write_atomic_with, Adapter, AdapterLayer};
+use crate::{
+	codec::Codec, contents::Contents, versioning::FileVersion, DirectoryStat, PathModel,
+	StorageEntry, Visibility, WriteOptions,
+};
+
+/// Encrypts the contents of files before they're stored by an [`EncryptedAdapter`], and decrypts them once read.
+pub trait Cipher: Debug + Send + Sync {
+	/// Encrypt the contents of a file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the contents can't be encrypted.
+	fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>>;
+
+	/// Decrypt the contents of a file, failing with an [`InvalidData`](ErrorKind::InvalidData) error if they were tampered with.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the contents can't be decrypted.
+	fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>>;
+
+	/// How many bytes longer the ciphertext is than the plaintext, so file sizes can be reported without decrypting.
+	fn overhead(&self) -> u64;
+}
+
+/// The AES-256-GCM cipher, storing a random 96-bit nonce before every ciphertext and the 128-bit tag after it.
+#[derive(Clone)]
+pub struct AesGcmCipher {
+	cipher: Aes256Gcm,
+}
+
+impl AesGcmCipher {
+	const NONCE_LEN: usize = 12;
+	const TAG_LEN: usize = 16;
+
+	/// Create a cipher from a 256-bit key.
+	#[must_use]
+	pub fn new(key: &[u8; 32]) -> Self {
+		Self {
+			cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)),
+		}
+	}
+}
+
+impl Debug for AesGcmCipher {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		// the key mustn't end up in logs
+		f.debug_struct("AesGcmCipher").finish_non_exhaustive()
+	}
+}
+
+impl Cipher for AesGcmCipher {
+	fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+		let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+		let ciphertext = self
+			.cipher
+			.encrypt(&nonce, plaintext)
+			.map_err(|_| Error::other("Failed to encrypt the file."))?;
+
+		let mut contents = nonce.to_vec();
+		contents.extend_from_slice(&ciphertext);
+
+		Ok(contents)
+	}
+
+	fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+		if ciphertext.len() < Self::NONCE_LEN + Self::TAG_LEN {
+			return Err(Error::new(
+				ErrorKind::InvalidData,
+				"The encrypted file is truncated.",
+			));
+		}
+
+		let (nonce, ciphertext) = ciphertext.split_at(Self::NONCE_LEN);
+		self.cipher
+			.decrypt(Nonce::from_slice(nonce), ciphertext)
+			.map_err(|_| {
+				Error::new(
+					ErrorKind::InvalidData,
+					"The file couldn't be decrypted, it was either tampered with or encrypted with another key.",
+				)
+			})
+	}
+
+	fn overhead(&self) -> u64 {
+		(Self::NONCE_LEN + Self::TAG_LEN) as u64
+	}
+}
+
+/// The metadata key the ID of the key a file was encrypted with is stored under.
+const KEY_ID_METADATA: &str = "encryption-key-id";
+
+/// Resolves the key files are encrypted with by their path, so each tenant can have its own.
+///
+/// The ID of the key is recorded in the metadata of every file (on adapters storing it), so files are decrypted with the key
+/// they were encrypted with. Deleting a key renders every file encrypted with it unreadable (crypto-shredding).
+/// A single [`Cipher`] is a provider too, encrypting every file with the same key without recording anything.
+pub trait KeyProvider: Debug + Send + Sync {
+	/// The cipher of the keys.
+	type Cipher: Cipher + ?Sized;
+
+	/// Get the ID of the key new files at `path` are encrypted with, or `None` if there's a single key.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if no key can be used for the path.
+	fn key_id(&self, path: &Path) -> Result<Option<String>>;
+
+	/// Get the cipher of a key, by the ID [`key_id`](Self::key_id) returned.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the key doesn't exist (or was deleted).
+	fn cipher(&self, key_id: Option<&str>) -> Result<impl Deref<Target = Self::Cipher> + '_>;
+}
+
+impl<C: Cipher> KeyProvider for C {
+	type Cipher = Self;
+
+	fn key_id(&self, _: &Path) -> Result<Option<String>> {
+		Ok(None)
+	}
+
+	fn cipher(&self, _: Option<&str>) -> Result<impl Deref<Target = Self> + '_> {
+		Ok(self)
+	}
+}
+
+/// A key per tenant, using the directory at the root of a path (like `acme` in `acme/invoices/1.pdf`) as the tenant and the ID of its key.
+///
+/// Keys are shared between clones, so a clone can be kept around to add or delete them after handing the adapter to a [`Filesystem`](crate::Filesystem).
+#[derive(Debug, Clone, Default)]
+pub struct TenantKeys {
+	keys: Arc<RwLock<HashMap<String, Arc<AesGcmCipher>>>>,
+}
+
+impl TenantKeys {
+	/// Set the 256-bit key of a tenant, used for the files written from now on.
+	pub fn insert(&self, tenant: &str, key: &[u8; 32]) {
+		self.keys
+			.write()
+			.unwrap_or_else(PoisonError::into_inner)
+			.insert(tenant.to_string(), Arc::new(AesGcmCipher::new(key)));
+	}
+
+	/// Delete the key of a tenant, so its files can't be decrypted anymore. Returns whether the tenant had a key.
+	pub fn remove(&self, tenant: &str) -> bool {
+		self.keys
+			.write()
+			.unwrap_or_else(PoisonError::into_inner)
+			.remove(tenant)
+			.is_some()
+	}
+}
+
+impl KeyProvider for TenantKeys {
+	type Cipher = AesGcmCipher;
+
+	fn key_id(&self, path: &Path) -> Result<Option<String>> {
+		let mut components = path.components();
+
+		match (components.next(), components.next()) {
+			(Some(Component::Normal(tenant)), Some(_)) => {
+				Ok(Some(tenant.to_string_lossy().into_owned()))
+			},
+			_ => Err(Error::new(
+				ErrorKind::InvalidInput,
+				format!("{} doesn't belong to a tenant.", path.display()),
+			)),
+		}
+	}
+
+	fn cipher(&self, key_id: Option<&str>) -> Result<impl Deref<Target = AesGcmCipher> + '_> {
+		let key_id = key_id.unwrap_or_default();
+
+		self.keys
+			.read()
+			.unwrap_or_else(PoisonError::into_inner)
+			.get(key_id)
+			.cloned()
+			.ok_or_else(|| {
+				Error::new(
+					ErrorKind::PermissionDenied,
+					format!("The key {key_id} doesn't exist, so its files can't be encrypted or decrypted."),
+				)
+			})
+	}
+}
+
+impl Codec for AesGcmCipher {
+	fn encode(&self, contents: &[u8]) -> Result<Vec<u8>> {
+		self.encrypt(contents)
+	}
+
+	fn decode(&self, contents: &[u8]) -> Result<Vec<u8>> {
+		self.decrypt(contents)
+	}
+}
+
+/// An adapter encrypting the contents of files before storing them in another one, so the storage provider never sees them.
+///
+/// Paths, MIME types and other metadata are stored as is. File sizes are those of the decrypted contents,
+/// and checksums are the SHA-256 of the decrypted contents (since the ciphertext changes on every write).
+/// Partial reads and writes decrypt and rewrite the whole file, and public or temporary URLs aren't supported,
+/// since they'd serve the encrypted contents.
+///
+/// Files can be encrypted with a single [`Cipher`], or with the key a [`KeyProvider`] picks for their path.
+/// Files copied or moved to a path with another key are decrypted and encrypted again with it.
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct EncryptedAdapter<A, K = AesGcmCipher> {
+	inner: A,
+	keys: K,
+}
+
+impl<A: Adapter, K: KeyProvider> EncryptedAdapter<A, K> {
+	/// Encrypt the files stored in `inner` with the given cipher, or with the keys of the given provider.
+	pub const fn new(inner: A, keys: K) -> Self {
+		Self { inner, keys }
+	}
+
+	/// Encrypt the contents of a new file, recording the ID of its key in the write options.
+	fn encrypt(&self, path: &Path, content: &[u8], options: &mut WriteOptions) -> Result<Vec<u8>> {
+		let key_id = self.keys.key_id(path)?;
+		let encrypted = self.keys.cipher(key_id.as_deref())?.encrypt(content)?;
+
+		if let Some(key_id) = key_id {
+			options.metadata.insert(KEY_ID_METADATA.to_string(), key_id);
+		}
+
+		Ok(encrypted)
+	}
+
+	/// Decrypt the contents of a file with the key it was encrypted with.
+	async fn decrypt(&self, path: &Path, contents: &[u8]) -> Result<Vec<u8>> {
+		let key_id = self.stored_key_id(path).await?;
+
+		self.keys.cipher(key_id.as_deref())?.decrypt(contents)
+	}
+
+	/// Get the ID of the key a file was encrypted with, falling back to the one for its path when it wasn't recorded.
+	async fn stored_key_id(&self, path: &Path) -> Result<Option<String>> {
+		let Some(key_id) = self.keys.key_id(path)? else {
+			return Ok(None);
+		};
+
+		match self.inner.metadata(path).await {
+			Ok(mut metadata) => Ok(Some(metadata.remove(KEY_ID_METADATA).unwrap_or(key_id))),
+			Err(error) if error.kind() == ErrorKind::Unsupported => Ok(Some(key_id)),
+			Err(error) => Err(error),
+		}
+	}
+
+	/// Whether a file needs to be encrypted again when it's copied or moved to `destination`, since its key differs from the one for that path.
+	async fn needs_reencryption(&self, source: &Path, destination: &Path) -> Result<bool> {
+		let key_id = self.keys.key_id(destination)?;
+
+		Ok(self.stored_key_id(source).await? != key_id)
+	}
+
+	/// Copy a file to `destination`, encrypting it again with the key for that path if needed.
+	async fn copy_file(&self, source: &Path, destination: &Path) -> Result<()> {
+		if self.needs_reencryption(source, destination).await? {
+			self.reencrypt(source, destination).await
+		} else {
+			self.inner.copy(source, destination).await
+		}
+	}
+
+	/// Decrypt a file and write it to `destination` encrypted with the key for that path, keeping its visibility and metadata.
+	async fn reencrypt(&self, source: &Path, destination: &Path) -> Result<()> {
+		let contents = self.read(source).await?;
+		let mut options = WriteOptions {
+			visibility: self.inner.visibility(source).await.ok(),
+			..WriteOptions::default()
+		};
+		// adapters storing metadata also store the MIME type, instead of guessing it from the extension
+		match self.metadata(source).await {
+			Ok(metadata) => {
+				options.metadata = metadata;
+				options.content_type = Some(self.inner.mime_type(source).await?);
+			},
+			Err(error) if error.kind() == ErrorKind::Unsupported => {},
+			Err(error) => return Err(error),
+		}
+
+		self.write_with(destination, &contents.data, &options).await
+	}
+
+	/// The files of a directory, if any of them needs to be encrypted again when the directory is copied or moved to `destination`.
+	async fn files_to_reencrypt(
+		&self,
+		source: &Path,
+		destination: &Path,
+	) -> Result<Option<Vec<PathBuf>>> {
+		let files: Vec<PathBuf> = self
+			.inner
+			.list_contents_with_metadata(source, true)
+			.await?
+			.into_iter()
+			.filter(|entry| !entry.is_dir)
+			.map(|entry| crate::relative_to(&entry.path, source).to_path_buf())
+			.collect();
+
+		for file in &files {
+			if self
+				.needs_reencryption(&source.join(file), &destination.join(file))
+				.await?
+			{
+				return Ok(Some(files));
+			}
+		}
+
+		Ok(None)
+	}
+
+	fn plaintext_size(&self, path: &Path, size: u64) -> Result<u64> {
+		let key_id = self.keys.key_id(path)?;
+
+		Ok(size.saturating_sub(self.keys.cipher(key_id.as_deref())?.overhead()))
+	}
+}
+
+impl<A: Adapter, K: KeyProvider> Adapter for EncryptedAdapter<A, K> {
+	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		self.inner.file_exists(path).await
+	}
+
+	async fn directory_exists(&self, path: &Path) -> Result<bool> {
+		self.inner.directory_exists(path).await
+	}
+
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+		let mut options = WriteOptions::default();
+		let encrypted = self.encrypt(path, content, &mut options)?;
+
+		if options.metadata.is_empty() {
+			return self.inner.write(path, &encrypted).await;
+		}
+
+		self.inner.write_with(path, &encrypted, &options).await
+	}
+
+	async fn write_with(&self, path: &Path, content: &[u8], options: &WriteOptions) -> Result<()> {
+		let mut options = options.clone();
+		let encrypted = self.encrypt(path, content, &mut options)?;
+
+		self.inner.write_with(path, &encrypted, &options).await
+	}
+
+	async fn write_atomic(&self, path: &Path, content: &[u8]) -> Result<()> {
+		let mut options = WriteOptions::default();
+		let encrypted = self.encrypt(path, content, &mut options)?;
+
+		if options.metadata.is_empty() {
+			return self.inner.write_atomic(path, &encrypted).await;
+		}
+
+		// the key id has to be written along with the file
+		write_atomic_with(&self.inner, path, &encrypted, &options).await
+	}
+
+	async fn read(&self, path: &Path) -> Result<Contents> {
+		let contents = self.inner.read(path).await?;
+
+		Ok(Contents::from(self.decrypt(path, &contents).await?))
+	}
+
+	async fn list_versions(&self, prefix: &Path) -> Result<Vec<FileVersion>> {
+		let mut versions = self.inner.list_versions(prefix).await?;
+		for version in versions.iter_mut().filter(|version| !version.deleted) {
+			version.size = self.plaintext_size(&version.path, version.size)?;
+		}
+
+		Ok(versions)
+	}
+
+	async fn read_version(&self, path: &Path, version_id: &str) -> Result<Contents> {
+		let contents = self.inner.read_version(path, version_id).await?;
+
+		Ok(Contents::from(self.decrypt(path, &contents).await?))
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
+		self.inner.delete(path).await
+	}
+
+	async fn delete_many(&self, paths: &[PathBuf]) -> Vec<(PathBuf, Error)> {
+		self.inner.delete_many(paths).await
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
+		self.inner.delete_directory(path).await
+	}
+
+	async fn create_directory(&self, path: &Path) -> Result<()> {
+		self.inner.create_directory(path).await
+	}
+
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
+		self.inner.set_visibility(path, visibility).await
+	}
+
+	async fn set_visibility_many(
+		&self,
+		paths: &[PathBuf],
+		visibility: Visibility,
+	) -> Vec<(PathBuf, Error)> {
+		self.inner.set_visibility_many(paths, visibility).await
+	}
+
+	async fn visibility(&self, path: &Path) -> Result<Visibility> {
+		self.inner.visibility(path).await
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		self.inner.mime_type(path).await
+	}
+
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		let mut metadata = self.inner.metadata(path).await?;
+		metadata.remove(KEY_ID_METADATA);
+
+		Ok(metadata)
+	}
+
+	async fn set_mime_type(&self, path: &Path, mime_type: &Mime) -> Result<()> {
+		self.inner.set_mime_type(path, mime_type).await
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		self.inner.last_modified(path).await
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		self.plaintext_size(path, self.inner.file_size(path).await?)
+	}
+
+	async fn directory_stat(&self, path: &Path) -> Result<DirectoryStat> {
+		self.inner.directory_stat(path).await
+	}
+
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		self.inner.list_contents(path, deep).await
+	}
+
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		let mut entries = self.inner.list_contents_with_metadata(path, deep).await?;
+		for entry in &mut entries {
+			// files whose key can't be resolved are listed without a size, instead of failing the listing
+			entry.size = entry
+				.size
+				.and_then(|size| self.plaintext_size(&entry.path, size).ok());
+		}
+
+		Ok(entries)
+	}
+
+	fn list_stream<'a>(&'a self, path: &Path, deep: bool) -> BoxStream<'a, Result<PathBuf>> {
+		self.inner.list_stream(path, deep)
+	}
+
+	fn list_stream_after<'a>(
+		&'a self,
+		path: &Path,
+		deep: bool,
+		after: Option<&Path>,
+	) -> BoxStream<'a, Result<PathBuf>> {
+		self.inner.list_stream_after(path, deep, after)
+	}
+
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		if !self.needs_reencryption(source, destination).await? {
+			return self.inner.r#move(source, destination).await;
+		}
+
+		self.reencrypt(source, destination).await?;
+		self.inner.delete(source).await
+	}
+
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.copy_file(source, destination).await
+	}
+
+	async fn move_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		let Some(files) = self.files_to_reencrypt(source, destination).await? else {
+			return self.inner.move_directory(source, destination).await;
+		};
+
+		for file in files {
+			self.copy_file(&source.join(&file), &destination.join(&file))
+				.await?;
+		}
+		self.inner.delete_directory(source).await
+	}
+
+	async fn copy_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		let Some(files) = self.files_to_reencrypt(source, destination).await? else {
+			return self.inner.copy_directory(source, destination).await;
+		};
+
+		for file in files {
+			self.copy_file(&source.join(&file), &destination.join(&file))
+				.await?;
+		}
+
+		Ok(())
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		Ok(sha256::digest(self.read(path).await?.data))
+	}
+
+	fn path_model(&self) -> PathModel {
+		self.inner.path_model()
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		AdapterLayer::new("encrypted").wrapping(&self.inner)
+	}
+
+	async fn prefetch(&self, path: &Path) -> Result<()> {
+		self.inner.prefetch(path).await
+	}
+
+	async fn flush(&self) -> Result<()> {
+		self.inner.flush().await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::adapters::MemoryAdapter;
+
+	#[tokio::test]
+	async fn test_encrypts_contents() {
+		let adapter = EncryptedAdapter::new(MemoryAdapter::default(), AesGcmCipher::new(&[7; 32]));
+
+		adapter
+			.write(Path::new("secret.txt"), b"Hello, world!")
+			.await
+			.unwrap();
+		adapter
+			.append(Path::new("secret.txt"), b" Bye!")
+			.await
+			.unwrap();
+
+		let stored = adapter.inner.read(Path::new("secret.txt")).await.unwrap();
+		assert!(!stored.windows(5).any(|window| window == b"Hello"));
+		assert_eq!(
+			adapter.read(Path::new("secret.txt")).await.unwrap().data,
+			b"Hello, world! Bye!"
+		);
+		assert_eq!(
+			adapter.file_size(Path::new("secret.txt")).await.unwrap(),
+			18
+		);
+		assert_eq!(
+			adapter.checksum(Path::new("secret.txt")).await.unwrap(),
+			sha256::digest(b"Hello, world! Bye!".as_slice())
+		);
+
+		// another key can't decrypt the file
+		let other = EncryptedAdapter::new(adapter.inner, AesGcmCipher::new(&[8; 32]));
+		assert_eq!(
+			other
+				.read(Path::new("secret.txt"))
+				.await
+				.unwrap_err()
+				.kind(),
+			ErrorKind::InvalidData
+		);
+	}
+
+	#[tokio::test]
+	async fn test_version_sizes() {
+		let adapter =
+			EncryptedAdapter::new(MemoryAdapter::versioned(), AesGcmCipher::new(&[7; 32]));
+
+		adapter
+			.write(Path::new("secret.txt"), b"Hello, world!")
+			.await
+			.unwrap();
+		adapter.delete(Path::new("secret.txt")).await.unwrap();
+
+		let versions = adapter
+			.list_versions(Path::new("secret.txt"))
+			.await
+			.unwrap();
+		assert_eq!(
+			versions
+				.iter()
+				.map(|version| (version.deleted, version.size))
+				.collect::<Vec<_>>(),
+			vec![(true, 0), (false, 13)]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_tenant_keys() {
+		let keys = TenantKeys::default();
+		keys.insert("acme", &[1; 32]);
+		keys.insert("globex", &[2; 32]);
+		let adapter = EncryptedAdapter::new(MemoryAdapter::default(), keys.clone());
+
+		for path in ["acme/invoice.pdf", "globex/invoice.pdf"] {
+			adapter.write(Path::new(path), b"%PDF").await.unwrap();
+		}
+		assert_eq!(
+			Adapter::metadata(&adapter.inner, Path::new("acme/invoice.pdf"))
+				.await
+				.unwrap()[KEY_ID_METADATA],
+			"acme"
+		);
+		assert_eq!(
+			adapter
+				.write(Path::new("invoice.pdf"), b"%PDF")
+				.await
+				.unwrap_err()
+				.kind(),
+			ErrorKind::InvalidInput
+		);
+
+		// files moved to another tenant are encrypted with its key, and the key id isn't exposed
+		adapter
+			.r#move(Path::new("acme/invoice.pdf"), Path::new("globex/acme.pdf"))
+			.await
+			.unwrap();
+		adapter
+			.write(Path::new("acme/report.pdf"), b"%PDF")
+			.await
+			.unwrap();
+		adapter
+			.copy_directory(Path::new("acme"), Path::new("globex/archive"))
+			.await
+			.unwrap();
+		assert!(!adapter
+			.file_exists(Path::new("acme/invoice.pdf"))
+			.await
+			.unwrap());
+		assert_eq!(
+			Adapter::metadata(&adapter.inner, Path::new("globex/acme.pdf"))
+				.await
+				.unwrap()[KEY_ID_METADATA],
+			"globex"
+		);
+		assert!(!adapter
+			.metadata(Path::new("globex/acme.pdf"))
+			.await
+			.unwrap()
+			.contains_key(KEY_ID_METADATA));
+
+		// deleting a tenant's key shreds its files
+		assert!(keys.remove("acme"));
+		assert_eq!(
+			adapter
+				.read(Path::new("acme/report.pdf"))
+				.await
+				.unwrap_err()
+				.kind(),
+			ErrorKind::PermissionDenied
+		);
+		for path in [
+			"globex/acme.pdf",
+			"globex/invoice.pdf",
+			"globex/archive/report.pdf",
+		] {
+			assert_eq!(adapter.read(Path::new(path)).await.unwrap().data, b"%PDF");
+		}
+	}
+
+	#[cfg(feature = "local")]
+	#[tokio::test]
+	async fn test_tenant_keys_without_metadata() {
+		use crate::adapters::{local::Config, AdapterInit, LocalAdapter, Validation};
+
+		let location = std::env::temp_dir().join("flysystem_encrypted_tenant_keys");
+		let keys = TenantKeys::default();
+		keys.insert("acme", &[1; 32]);
+		keys.insert("globex", &[2; 32]);
+		let adapter = EncryptedAdapter::new(
+			LocalAdapter::new(Config {
+				location: location.clone(),
+				lazy_root_creation: true,
+				validation: Validation::Lazy,
+				public_url: None,
+			})
+			.await
+			.unwrap(),
+			keys,
+		);
+
+		for path in ["acme/invoice.pdf", "acme/reports/q1.pdf"] {
+			adapter.write(Path::new(path), b"%PDF").await.unwrap();
+		}
+
+		// without metadata, the key is the one for the path, so files must be encrypted again with it
+		adapter
+			.copy(
+				Path::new("acme/invoice.pdf"),
+				Path::new("globex/invoice.pdf"),
+			)
+			.await
+			.unwrap();
+		adapter
+			.move_directory(Path::new("acme/reports"), Path::new("globex/reports"))
+			.await
+			.unwrap();
+		assert!(!adapter
+			.directory_exists(Path::new("acme/reports"))
+			.await
+			.unwrap());
+		for path in [
+			"acme/invoice.pdf",
+			"globex/invoice.pdf",
+			"globex/reports/q1.pdf",
+		] {
+			assert_eq!(adapter.read(Path::new(path)).await.unwrap().data, b"%PDF");
+		}
+
+		std::fs::remove_dir_all(location).unwrap();
+	}
+}
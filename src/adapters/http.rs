@@ -0,0 +1,374 @@
+use futures::{io::AsyncRead, TryStreamExt};
+use mime::Mime;
+use reqwest::{header, header::HeaderMap, Method, Response, StatusCode};
+use std::{
+	io::{Error, ErrorKind, Result},
+	path::{Component, Path, PathBuf},
+	time::SystemTime,
+};
+use url::Url;
+
+use super::{Adapter, AdapterInit, AdapterLayer};
+use crate::{contents::Contents, Visibility};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+pub struct Config {
+	/// The URL files are served under, like `https://cdn.example.com/assets/`.
+	pub url: Url,
+}
+
+/// A read-only adapter serving files from a base URL, like static assets on a CDN.
+///
+/// Reads are `GET` requests and lookups (size, MIME type, last modified time) are `HEAD` requests.
+/// HTTP has no way to list or modify files, so listings, directories and every write fail with an `Unsupported` error.
+/// Files are considered public, and their public URL is the one they're read from.
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct HttpAdapter {
+	url: Url,
+	client: reqwest::Client,
+}
+
+impl AdapterInit for HttpAdapter {
+	type Config = Config;
+	type Error = Error;
+
+	async fn new(config: Self::Config) -> Result<Self> {
+		if config.url.cannot_be_a_base() {
+			return Err(Error::new(
+				ErrorKind::InvalidInput,
+				format!("{} is not a valid base URL.", config.url),
+			));
+		}
+
+		Ok(Self {
+			url: config.url,
+			client: reqwest::Client::new(),
+		})
+	}
+}
+
+impl HttpAdapter {
+	/// Get the URL of a file.
+	fn url(&self, path: &Path) -> Url {
+		let mut url = self.url.clone();
+
+		{
+			let mut segments = url
+				.path_segments_mut()
+				.expect("The URL was checked to be a base when creating the adapter.");
+			segments.pop_if_empty();
+
+			for component in path.components() {
+				if let Component::Normal(name) = component {
+					segments.push(&name.to_string_lossy());
+				}
+			}
+		}
+
+		url
+	}
+
+	async fn send(&self, method: Method, path: &Path) -> Result<Response> {
+		let response = self
+			.client
+			.request(method, self.url(path))
+			.send()
+			.await
+			.map_err(Error::other)?;
+
+		check(response, path)
+	}
+
+	/// Get the headers a file is served with.
+	async fn head(&self, path: &Path) -> Result<HeaderMap> {
+		Ok(self.send(Method::HEAD, path).await?.headers().clone())
+	}
+}
+
+fn read_only() -> Error {
+	Error::new(ErrorKind::Unsupported, "The HTTP adapter is read-only.")
+}
+
+fn no_listings() -> Error {
+	Error::new(
+		ErrorKind::Unsupported,
+		"The HTTP adapter can't list or look up directories.",
+	)
+}
+
+/// Get a header a file was served with, failing if the server didn't send it.
+fn header<'a>(headers: &'a HeaderMap, name: &header::HeaderName) -> Result<&'a str> {
+	headers
+		.get(name)
+		.and_then(|value| value.to_str().ok())
+		.ok_or_else(|| Error::other(format!("The server did not return a {name} header.")))
+}
+
+/// Turn unsuccessful responses into errors.
+fn check(response: Response, path: &Path) -> Result<Response> {
+	let status = response.status();
+	if status.is_success() {
+		return Ok(response);
+	}
+
+	let kind = match status {
+		StatusCode::NOT_FOUND | StatusCode::GONE => ErrorKind::NotFound,
+		StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ErrorKind::PermissionDenied,
+		_ => ErrorKind::Other,
+	};
+
+	Err(Error::new(
+		kind,
+		format!("The server answered {status} for {}.", path.display()),
+	))
+}
+
+impl Adapter for HttpAdapter {
+	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		match self.head(path).await {
+			Ok(_) => Ok(true),
+			Err(error) if error.kind() == ErrorKind::NotFound => Ok(false),
+			Err(error) => Err(error),
+		}
+	}
+
+	async fn directory_exists(&self, _: &Path) -> Result<bool> {
+		Err(no_listings())
+	}
+
+	async fn write(&self, _: &Path, _: &[u8]) -> Result<()> {
+		Err(read_only())
+	}
+
+	async fn append(&self, _: &Path, _: &[u8]) -> Result<()> {
+		Err(read_only())
+	}
+
+	async fn write_range(&self, _: &Path, _: u64, _: &[u8]) -> Result<()> {
+		Err(read_only())
+	}
+
+	async fn read(&self, path: &Path) -> Result<Contents> {
+		let response = self.send(Method::GET, path).await?;
+
+		Ok(Contents::from(
+			response.bytes().await.map_err(Error::other)?.to_vec(),
+		))
+	}
+
+	async fn read_stream(&self, path: &Path) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+		let response = self.send(Method::GET, path).await?;
+
+		Ok(Box::new(
+			response
+				.bytes_stream()
+				.map_err(Error::other)
+				.into_async_read(),
+		))
+	}
+
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		if len == 0 {
+			return Ok(Contents::from(Vec::new()));
+		}
+
+		let response = self
+			.client
+			.get(self.url(path))
+			.header(
+				header::RANGE,
+				format!("bytes={offset}-{}", offset.saturating_add(len) - 1),
+			)
+			.send()
+			.await
+			.map_err(Error::other)?;
+
+		// the range starts past the end of the file
+		if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+			return Ok(Contents::from(Vec::new()));
+		}
+
+		let partial = response.status() == StatusCode::PARTIAL_CONTENT;
+		let contents = check(response, path)?.bytes().await.map_err(Error::other)?;
+
+		if partial {
+			return Ok(Contents::from(contents.to_vec()));
+		}
+
+		// the server ignored the range and sent the whole file
+		let start = usize::try_from(offset)
+			.unwrap_or(usize::MAX)
+			.min(contents.len());
+		let end = usize::try_from(offset.saturating_add(len))
+			.unwrap_or(usize::MAX)
+			.min(contents.len());
+
+		Ok(Contents::from(contents[start..end].to_vec()))
+	}
+
+	async fn delete(&self, _: &Path) -> Result<()> {
+		Err(read_only())
+	}
+
+	async fn delete_directory(&self, _: &Path) -> Result<()> {
+		Err(read_only())
+	}
+
+	async fn create_directory(&self, _: &Path) -> Result<()> {
+		Err(read_only())
+	}
+
+	async fn set_visibility(&self, _: &Path, _: Visibility) -> Result<()> {
+		Err(read_only())
+	}
+
+	async fn visibility(&self, path: &Path) -> Result<Visibility> {
+		self.head(path).await?;
+
+		Ok(Visibility::Public)
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		let headers = self.head(path).await?;
+
+		Ok(header(&headers, &header::CONTENT_TYPE)
+			.ok()
+			.and_then(|content_type| content_type.parse().ok())
+			.unwrap_or_else(|| mime_guess::from_path(path).first_or_octet_stream()))
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		let headers = self.head(path).await?;
+
+		httpdate::parse_http_date(header(&headers, &header::LAST_MODIFIED)?)
+			.map_err(|e| Error::new(ErrorKind::InvalidData, e))
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		let headers = self.head(path).await?;
+
+		header(&headers, &header::CONTENT_LENGTH)?
+			.parse()
+			.map_err(|e| Error::new(ErrorKind::InvalidData, e))
+	}
+
+	async fn list_contents(&self, _: &Path, _: bool) -> Result<Vec<PathBuf>> {
+		Err(no_listings())
+	}
+
+	async fn r#move(&self, _: &Path, _: &Path) -> Result<()> {
+		Err(read_only())
+	}
+
+	async fn copy(&self, _: &Path, _: &Path) -> Result<()> {
+		Err(read_only())
+	}
+
+	/// Get the `ETag` the file is served with, or hash its contents if there's none.
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		let headers = self.head(path).await?;
+
+		match header(&headers, &header::ETAG) {
+			Ok(etag) => Ok(etag.to_string()),
+			Err(_) => Ok(sha256::digest(self.read(path).await?.data)),
+		}
+	}
+
+	async fn public_url(&self, path: &Path) -> Result<Url> {
+		Ok(self.url(path))
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		vec![AdapterLayer::new("http").with("url", &self.url)]
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use tokio::{
+		io::{AsyncReadExt, AsyncWriteExt},
+		net::TcpListener,
+	};
+
+	use super::*;
+	use crate::Filesystem;
+
+	/// A tiny static file server, serving `assets/app.css` (and honoring ranges on it).
+	async fn server() -> Url {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let url = Url::parse(&format!(
+			"http://{}/assets/",
+			listener.local_addr().unwrap()
+		))
+		.unwrap();
+
+		tokio::spawn(async move {
+			while let Ok((mut socket, _)) = listener.accept().await {
+				let mut buf = [0u8; 4096];
+				let n = socket.read(&mut buf).await.unwrap();
+				let request = String::from_utf8_lossy(&buf[..n]).to_lowercase();
+				let line = request
+					.lines()
+					.next()
+					.unwrap()
+					.trim_end_matches(" http/1.1");
+
+				let body = "Hello, world!";
+				let response = match line {
+					"get /assets/css/app.css" if request.contains("range: bytes=7-11") => {
+						format!("HTTP/1.1 206 Partial Content\r\ncontent-length: 5\r\nconnection: close\r\n\r\n{}", &body[7..12])
+					},
+					"get /assets/css/app.css" | "head /assets/css/app.css" => format!(
+						"HTTP/1.1 200 OK\r\ncontent-type: text/css\r\ncontent-length: {}\r\nlast-modified: Tue, 01 Oct 2024 10:00:00 GMT\r\netag: \"abc\"\r\nconnection: close\r\n\r\n{}",
+						body.len(),
+						if line.starts_with("get") { body } else { "" }
+					),
+					_ => "HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n".to_string(),
+				};
+
+				socket.write_all(response.as_bytes()).await.unwrap();
+			}
+		});
+
+		url
+	}
+
+	#[tokio::test]
+	async fn test_http_adapter() {
+		let url = server().await;
+		let filesystem = Filesystem::new::<HttpAdapter>(Config { url: url.clone() })
+			.await
+			.unwrap();
+		let path = Path::new("css/app.css");
+
+		assert_eq!(
+			filesystem.read::<String>(path).await.unwrap(),
+			"Hello, world!"
+		);
+		assert_eq!(
+			Vec::from(filesystem.read_range(path, 7, 5).await.unwrap()),
+			b"world"
+		);
+		assert_eq!(filesystem.file_size(path).await.unwrap(), 13);
+		assert_eq!(filesystem.mime_type(path).await.unwrap(), mime::TEXT_CSS);
+		assert_eq!(
+			filesystem.last_modified(path).await.unwrap(),
+			httpdate::parse_http_date("Tue, 01 Oct 2024 10:00:00 GMT").unwrap()
+		);
+		assert_eq!(
+			filesystem.public_url(path).await.unwrap(),
+			url.join("css/app.css").unwrap()
+		);
+
+		assert!(!filesystem
+			.file_exists(Path::new("missing.css"))
+			.await
+			.unwrap());
+		assert_eq!(
+			filesystem.write(path, b"Hello!").await.unwrap_err().kind(),
+			ErrorKind::Unsupported
+		);
+	}
+}
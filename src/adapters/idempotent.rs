@@ -0,0 +1,336 @@
+use bytes::BufMut;
+use futures::{io::AsyncRead, stream::BoxStream, StreamExt, TryStreamExt};
+use mime::Mime;
+use std::{
+	collections::HashMap,
+	ffi::OsStr,
+	io::{Error, Result},
+	path::{Path, PathBuf},
+	time::{Duration, SystemTime},
+};
+use url::Url;
+
+use super::{Adapter, AdapterLayer, TemporaryUrlOptions};
+use crate::{
+	contents::Contents, versioning::FileVersion, DirectoryStat, PathModel, StorageEntry,
+	Visibility, WriteOptions,
+};
+
+/// The directory holding a marker for every idempotency key that was already applied.
+pub const MARKER_DIRECTORY: &str = ".flysystem-idempotency";
+
+/// An adapter skipping writes whose [idempotency key](WriteOptions::idempotency_key) was already applied,
+/// so replaying an operation (after a crash, or on retry) doesn't apply it twice.
+///
+/// Applied keys are recorded as empty marker files in the [`MARKER_DIRECTORY`] of the inner adapter, so they survive restarts.
+/// Markers are hidden from listings, and writes without a key are always applied.
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct IdempotentAdapter<A> {
+	inner: A,
+}
+
+impl<A: Adapter> IdempotentAdapter<A> {
+	/// Deduplicate the keyed writes to an adapter.
+	pub const fn new(inner: A) -> Self {
+		Self { inner }
+	}
+
+	/// Check if the operation with the given key was already applied.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the inner adapter fails to check for the key's marker.
+	pub async fn was_applied(&self, key: &str) -> Result<bool> {
+		self.inner.file_exists(&marker(key)).await
+	}
+}
+
+/// Get the path of the marker recording an idempotency key, hashed so any key makes a valid file name.
+fn marker(key: &str) -> PathBuf {
+	Path::new(MARKER_DIRECTORY).join(sha256::digest(key))
+}
+
+fn is_marker(path: &Path) -> bool {
+	path.components()
+		.any(|component| component.as_os_str() == OsStr::new(MARKER_DIRECTORY))
+}
+
+impl<A: Adapter> Adapter for IdempotentAdapter<A> {
+	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		self.inner.file_exists(path).await
+	}
+
+	async fn directory_exists(&self, path: &Path) -> Result<bool> {
+		self.inner.directory_exists(path).await
+	}
+
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.inner.write(path, content).await
+	}
+
+	async fn write_with(&self, path: &Path, content: &[u8], options: &WriteOptions) -> Result<()> {
+		let Some(key) = &options.idempotency_key else {
+			return self.inner.write_with(path, content, options).await;
+		};
+
+		if self.was_applied(key).await? {
+			return Ok(());
+		}
+
+		self.inner.write_with(path, content, options).await?;
+		self.inner.write(&marker(key), &[]).await
+	}
+
+	async fn write_atomic(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.inner.write_atomic(path, content).await
+	}
+
+	async fn write_stream(
+		&self,
+		path: &Path,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		self.inner.write_stream(path, reader).await
+	}
+
+	async fn write_range(&self, path: &Path, offset: u64, data: &[u8]) -> Result<()> {
+		self.inner.write_range(path, offset, data).await
+	}
+
+	async fn append(&self, path: &Path, data: &[u8]) -> Result<()> {
+		self.inner.append(path, data).await
+	}
+
+	fn native_range_writes(&self) -> bool {
+		self.inner.native_range_writes()
+	}
+
+	async fn read(&self, path: &Path) -> Result<Contents> {
+		self.inner.read(path).await
+	}
+
+	async fn read_stream(&self, path: &Path) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+		self.inner.read_stream(path).await
+	}
+
+	async fn read_into(&self, path: &Path, buf: &mut (dyn BufMut + Send)) -> Result<()> {
+		self.inner.read_into(path, buf).await
+	}
+
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		self.inner.read_range(path, offset, len).await
+	}
+
+	async fn list_versions(&self, prefix: &Path) -> Result<Vec<FileVersion>> {
+		let mut versions = self.inner.list_versions(prefix).await?;
+		versions.retain(|version| !is_marker(&version.path));
+
+		Ok(versions)
+	}
+
+	async fn read_version(&self, path: &Path, version_id: &str) -> Result<Contents> {
+		self.inner.read_version(path, version_id).await
+	}
+
+	async fn restore_version(&self, path: &Path, version_id: &str) -> Result<()> {
+		self.inner.restore_version(path, version_id).await
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
+		self.inner.delete(path).await
+	}
+
+	async fn delete_many(&self, paths: &[PathBuf]) -> Vec<(PathBuf, Error)> {
+		self.inner.delete_many(paths).await
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
+		self.inner.delete_directory(path).await
+	}
+
+	async fn create_directory(&self, path: &Path) -> Result<()> {
+		self.inner.create_directory(path).await
+	}
+
+	async fn set_mime_type(&self, path: &Path, mime_type: &Mime) -> Result<()> {
+		self.inner.set_mime_type(path, mime_type).await
+	}
+
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
+		self.inner.set_visibility(path, visibility).await
+	}
+
+	async fn set_visibility_many(
+		&self,
+		paths: &[PathBuf],
+		visibility: Visibility,
+	) -> Vec<(PathBuf, Error)> {
+		self.inner.set_visibility_many(paths, visibility).await
+	}
+
+	async fn visibility(&self, path: &Path) -> Result<Visibility> {
+		self.inner.visibility(path).await
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		self.inner.mime_type(path).await
+	}
+
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		self.inner.metadata(path).await
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		self.inner.last_modified(path).await
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		self.inner.file_size(path).await
+	}
+
+	async fn directory_stat(&self, path: &Path) -> Result<DirectoryStat> {
+		self.inner.directory_stat(path).await
+	}
+
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		let mut contents = self.inner.list_contents(path, deep).await?;
+		contents.retain(|entry| !is_marker(entry));
+
+		Ok(contents)
+	}
+
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		let mut entries = self.inner.list_contents_with_metadata(path, deep).await?;
+		entries.retain(|entry| !is_marker(&entry.path));
+
+		Ok(entries)
+	}
+
+	fn list_stream<'a>(&'a self, path: &Path, deep: bool) -> BoxStream<'a, Result<PathBuf>> {
+		self.inner
+			.list_stream(path, deep)
+			.try_filter(|entry| std::future::ready(!is_marker(entry)))
+			.boxed()
+	}
+
+	fn list_stream_after<'a>(
+		&'a self,
+		path: &Path,
+		deep: bool,
+		after: Option<&Path>,
+	) -> BoxStream<'a, Result<PathBuf>> {
+		self.inner
+			.list_stream_after(path, deep, after)
+			.try_filter(|entry| std::future::ready(!is_marker(entry)))
+			.boxed()
+	}
+
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.inner.r#move(source, destination).await
+	}
+
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.inner.copy(source, destination).await
+	}
+
+	async fn move_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.inner.move_directory(source, destination).await
+	}
+
+	async fn copy_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.inner.copy_directory(source, destination).await
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		self.inner.checksum(path).await
+	}
+
+	async fn public_url(&self, path: &Path) -> Result<Url> {
+		self.inner.public_url(path).await
+	}
+
+	async fn temporary_url(&self, path: &Path, expires_in: Duration) -> Result<Url> {
+		self.inner.temporary_url(path, expires_in).await
+	}
+
+	async fn temporary_url_with(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+		options: &TemporaryUrlOptions,
+	) -> Result<Url> {
+		self.inner
+			.temporary_url_with(path, expires_in, options)
+			.await
+	}
+
+	fn path_model(&self) -> PathModel {
+		self.inner.path_model()
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		AdapterLayer::new("idempotent").wrapping(&self.inner)
+	}
+
+	async fn prefetch(&self, path: &Path) -> Result<()> {
+		self.inner.prefetch(path).await
+	}
+
+	async fn flush(&self) -> Result<()> {
+		self.inner.flush().await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{adapters::MemoryAdapter, Filesystem};
+
+	#[tokio::test]
+	async fn test_replayed_writes_are_skipped() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default()).idempotent();
+		let options = WriteOptions {
+			idempotency_key: Some("upload-42".to_string()),
+			..WriteOptions::default()
+		};
+
+		filesystem
+			.write_with(Path::new("uploads/avatar.png"), b"first", options.clone())
+			.await
+			.unwrap();
+		filesystem
+			.write_with(Path::new("uploads/avatar.png"), b"replayed", options)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			filesystem
+				.read::<String>(Path::new("uploads/avatar.png"))
+				.await
+				.unwrap(),
+			"first"
+		);
+		assert!(filesystem
+			.list_contents(Path::new(MARKER_DIRECTORY), false)
+			.await
+			.unwrap()
+			.is_empty());
+
+		filesystem
+			.write(Path::new("uploads/avatar.png"), b"unkeyed")
+			.await
+			.unwrap();
+		assert_eq!(
+			filesystem
+				.read::<String>(Path::new("uploads/avatar.png"))
+				.await
+				.unwrap(),
+			"unkeyed"
+		);
+	}
+}
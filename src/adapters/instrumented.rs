@@ -0,0 +1,546 @@
+use bytes::BufMut;
+use futures::{io::AsyncRead, stream::BoxStream};
+use mime::Mime;
+use std::{
+	collections::HashMap,
+	fmt::Debug,
+	future::Future,
+	io::{Error, Result},
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::{Duration, Instant, SystemTime},
+};
+use tracing::{field, Instrument};
+use url::Url;
+
+use super::{Adapter, AdapterLayer, TemporaryUrlOptions};
+use crate::{
+	contents::Contents, versioning::FileVersion, DirectoryStat, PathModel, Precondition, Redacted,
+	StorageEntry, Visibility, WriteOptions,
+};
+
+/// A call made to an adapter wrapped by an [`InstrumentedAdapter`], as reported to its [`MetricsRecorder`].
+#[derive(Debug)]
+pub struct AdapterCall<'a> {
+	/// The name of the wrapped adapter, like `s3` or `local`.
+	pub adapter: &'static str,
+	/// The name of the operation, like `write` or `list_contents`.
+	pub operation: &'static str,
+	pub path: &'a Path,
+	pub duration: Duration,
+	/// How many bytes were written or read, for the operations transferring contents.
+	pub bytes: Option<u64>,
+	pub error: Option<&'a Error>,
+}
+
+/// Receives every call made through an [`InstrumentedAdapter`], to turn them into counters and histograms
+/// (like a request counter and a latency histogram labeled by adapter and operation).
+pub trait MetricsRecorder: Debug + Send + Sync {
+	/// Record a completed call. This runs on the caller's task, so it shouldn't block.
+	fn record(&self, call: &AdapterCall<'_>);
+}
+
+/// An adapter wrapping every call to another one in a `tracing` span, and reporting it to an optional [`MetricsRecorder`].
+///
+/// Spans are named `flysystem` and carry the `operation`, `path`, `adapter`, `duration_ms`, `bytes` and `error` fields.
+/// Streamed listings are only traced until the stream is created.
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct InstrumentedAdapter<A> {
+	inner: A,
+	name: &'static str,
+	recorder: Option<Arc<dyn MetricsRecorder>>,
+}
+
+impl<A: Adapter> InstrumentedAdapter<A> {
+	/// Instrument the calls made to `inner`.
+	pub fn new(inner: A) -> Self {
+		let name = inner.layers().first().map_or("unknown", |layer| layer.name);
+
+		Self {
+			inner,
+			name,
+			recorder: None,
+		}
+	}
+
+	/// Report every call to the given recorder.
+	#[must_use]
+	pub fn with_recorder<R: MetricsRecorder + 'static>(mut self, recorder: R) -> Self {
+		self.recorder = Some(Arc::new(recorder));
+		self
+	}
+
+	async fn instrument<T>(
+		&self,
+		operation: &'static str,
+		path: &Path,
+		bytes: impl FnOnce(&T) -> Option<u64> + Send,
+		future: impl Future<Output = Result<T>> + Send,
+	) -> Result<T> {
+		let span = tracing::info_span!(
+			"flysystem",
+			operation,
+			path = %path.display(),
+			adapter = self.name,
+			duration_ms = field::Empty,
+			bytes = field::Empty,
+			error = field::Empty,
+		);
+
+		let start = Instant::now();
+		let result = future.instrument(span.clone()).await;
+		let duration = start.elapsed();
+		let bytes = result.as_ref().ok().and_then(bytes);
+
+		span.record("duration_ms", duration.as_secs_f64() * 1000.0);
+		if let Some(bytes) = bytes {
+			span.record("bytes", bytes);
+		}
+		if let Err(error) = &result {
+			span.record("error", field::display(Redacted(error)));
+		}
+
+		if let Some(recorder) = &self.recorder {
+			recorder.record(&AdapterCall {
+				adapter: self.name,
+				operation,
+				path,
+				duration,
+				bytes,
+				error: result.as_ref().err(),
+			});
+		}
+
+		result
+	}
+}
+
+const fn none<T>(_: &T) -> Option<u64> {
+	None
+}
+
+impl<A: Adapter> Adapter for InstrumentedAdapter<A> {
+	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		self.instrument("file_exists", path, none, self.inner.file_exists(path))
+			.await
+	}
+
+	async fn directory_exists(&self, path: &Path) -> Result<bool> {
+		self.instrument(
+			"directory_exists",
+			path,
+			none,
+			self.inner.directory_exists(path),
+		)
+		.await
+	}
+
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.instrument(
+			"write",
+			path,
+			|()| Some(content.len() as u64),
+			self.inner.write(path, content),
+		)
+		.await
+	}
+
+	async fn write_with(&self, path: &Path, content: &[u8], options: &WriteOptions) -> Result<()> {
+		self.instrument(
+			"write",
+			path,
+			|()| Some(content.len() as u64),
+			self.inner.write_with(path, content, options),
+		)
+		.await
+	}
+
+	async fn write_if(
+		&self,
+		path: &Path,
+		content: &[u8],
+		precondition: &Precondition,
+	) -> Result<()> {
+		self.instrument(
+			"write_if",
+			path,
+			|()| Some(content.len() as u64),
+			self.inner.write_if(path, content, precondition),
+		)
+		.await
+	}
+
+	async fn write_atomic(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.instrument(
+			"write_atomic",
+			path,
+			|()| Some(content.len() as u64),
+			self.inner.write_atomic(path, content),
+		)
+		.await
+	}
+
+	async fn write_stream(
+		&self,
+		path: &Path,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		self.instrument(
+			"write_stream",
+			path,
+			none,
+			self.inner.write_stream(path, reader),
+		)
+		.await
+	}
+
+	async fn write_range(&self, path: &Path, offset: u64, data: &[u8]) -> Result<()> {
+		self.instrument(
+			"write_range",
+			path,
+			|()| Some(data.len() as u64),
+			self.inner.write_range(path, offset, data),
+		)
+		.await
+	}
+
+	async fn append(&self, path: &Path, data: &[u8]) -> Result<()> {
+		self.instrument(
+			"append",
+			path,
+			|()| Some(data.len() as u64),
+			self.inner.append(path, data),
+		)
+		.await
+	}
+
+	fn native_range_writes(&self) -> bool {
+		self.inner.native_range_writes()
+	}
+
+	async fn read(&self, path: &Path) -> Result<Contents> {
+		self.instrument(
+			"read",
+			path,
+			|contents: &Contents| Some(contents.data.len() as u64),
+			self.inner.read(path),
+		)
+		.await
+	}
+
+	async fn read_stream(&self, path: &Path) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+		self.instrument("read_stream", path, none, self.inner.read_stream(path))
+			.await
+	}
+
+	async fn read_into(&self, path: &Path, buf: &mut (dyn BufMut + Send)) -> Result<()> {
+		self.instrument("read_into", path, none, self.inner.read_into(path, buf))
+			.await
+	}
+
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		self.instrument(
+			"read_range",
+			path,
+			|contents: &Contents| Some(contents.data.len() as u64),
+			self.inner.read_range(path, offset, len),
+		)
+		.await
+	}
+
+	async fn list_versions(&self, prefix: &Path) -> Result<Vec<FileVersion>> {
+		self.instrument(
+			"list_versions",
+			prefix,
+			none,
+			self.inner.list_versions(prefix),
+		)
+		.await
+	}
+
+	async fn read_version(&self, path: &Path, version_id: &str) -> Result<Contents> {
+		self.instrument(
+			"read_version",
+			path,
+			|contents: &Contents| Some(contents.data.len() as u64),
+			self.inner.read_version(path, version_id),
+		)
+		.await
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
+		self.instrument("delete", path, none, self.inner.delete(path))
+			.await
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
+		self.instrument(
+			"delete_directory",
+			path,
+			none,
+			self.inner.delete_directory(path),
+		)
+		.await
+	}
+
+	async fn create_directory(&self, path: &Path) -> Result<()> {
+		self.instrument(
+			"create_directory",
+			path,
+			none,
+			self.inner.create_directory(path),
+		)
+		.await
+	}
+
+	async fn set_mime_type(&self, path: &Path, mime_type: &Mime) -> Result<()> {
+		self.instrument(
+			"set_mime_type",
+			path,
+			none,
+			self.inner.set_mime_type(path, mime_type),
+		)
+		.await
+	}
+
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
+		self.instrument(
+			"set_visibility",
+			path,
+			none,
+			self.inner.set_visibility(path, visibility),
+		)
+		.await
+	}
+
+	async fn set_visibility_many(
+		&self,
+		paths: &[PathBuf],
+		visibility: Visibility,
+	) -> Vec<(PathBuf, Error)> {
+		self.inner.set_visibility_many(paths, visibility).await
+	}
+
+	async fn visibility(&self, path: &Path) -> Result<Visibility> {
+		self.instrument("visibility", path, none, self.inner.visibility(path))
+			.await
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		self.instrument("mime_type", path, none, self.inner.mime_type(path))
+			.await
+	}
+
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		self.instrument("metadata", path, none, self.inner.metadata(path))
+			.await
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		self.instrument("last_modified", path, none, self.inner.last_modified(path))
+			.await
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		self.instrument("file_size", path, none, self.inner.file_size(path))
+			.await
+	}
+
+	async fn directory_stat(&self, path: &Path) -> Result<DirectoryStat> {
+		self.instrument(
+			"directory_stat",
+			path,
+			none,
+			self.inner.directory_stat(path),
+		)
+		.await
+	}
+
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		self.instrument(
+			"list_contents",
+			path,
+			none,
+			self.inner.list_contents(path, deep),
+		)
+		.await
+	}
+
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		self.instrument(
+			"list_contents_with_metadata",
+			path,
+			none,
+			self.inner.list_contents_with_metadata(path, deep),
+		)
+		.await
+	}
+
+	fn list_stream<'a>(&'a self, path: &Path, deep: bool) -> BoxStream<'a, Result<PathBuf>> {
+		let _span = tracing::info_span!(
+			"flysystem",
+			operation = "list_stream",
+			path = %path.display(),
+			adapter = self.name,
+		)
+		.entered();
+
+		self.inner.list_stream(path, deep)
+	}
+
+	fn list_stream_after<'a>(
+		&'a self,
+		path: &Path,
+		deep: bool,
+		after: Option<&Path>,
+	) -> BoxStream<'a, Result<PathBuf>> {
+		let _span = tracing::info_span!(
+			"flysystem",
+			operation = "list_stream_after",
+			path = %path.display(),
+			adapter = self.name,
+		)
+		.entered();
+
+		self.inner.list_stream_after(path, deep, after)
+	}
+
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.instrument("move", source, none, self.inner.r#move(source, destination))
+			.await
+	}
+
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.instrument("copy", source, none, self.inner.copy(source, destination))
+			.await
+	}
+
+	async fn move_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.instrument(
+			"move_directory",
+			source,
+			none,
+			self.inner.move_directory(source, destination),
+		)
+		.await
+	}
+
+	async fn copy_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.instrument(
+			"copy_directory",
+			source,
+			none,
+			self.inner.copy_directory(source, destination),
+		)
+		.await
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		self.instrument("checksum", path, none, self.inner.checksum(path))
+			.await
+	}
+
+	async fn public_url(&self, path: &Path) -> Result<Url> {
+		self.instrument("public_url", path, none, self.inner.public_url(path))
+			.await
+	}
+
+	async fn temporary_url(&self, path: &Path, expires_in: Duration) -> Result<Url> {
+		self.instrument(
+			"temporary_url",
+			path,
+			none,
+			self.inner.temporary_url(path, expires_in),
+		)
+		.await
+	}
+
+	async fn temporary_url_with(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+		options: &TemporaryUrlOptions,
+	) -> Result<Url> {
+		self.instrument(
+			"temporary_url",
+			path,
+			none,
+			self.inner.temporary_url_with(path, expires_in, options),
+		)
+		.await
+	}
+
+	fn path_model(&self) -> PathModel {
+		self.inner.path_model()
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		AdapterLayer::new("instrumented").wrapping(&self.inner)
+	}
+
+	async fn prefetch(&self, path: &Path) -> Result<()> {
+		self.instrument("prefetch", path, none, self.inner.prefetch(path))
+			.await
+	}
+
+	async fn flush(&self) -> Result<()> {
+		self.instrument("flush", Path::new(""), none, self.inner.flush())
+			.await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{io::ErrorKind, sync::Mutex};
+
+	use super::*;
+	use crate::adapters::MemoryAdapter;
+
+	type Call = (&'static str, &'static str, Option<u64>, Option<ErrorKind>);
+
+	#[derive(Debug, Default)]
+	struct Calls(Mutex<Vec<Call>>);
+
+	impl MetricsRecorder for Arc<Calls> {
+		fn record(&self, call: &AdapterCall<'_>) {
+			self.0.lock().unwrap().push((
+				call.adapter,
+				call.operation,
+				call.bytes,
+				call.error.map(Error::kind),
+			));
+		}
+	}
+
+	#[tokio::test]
+	async fn test_records_calls() {
+		let calls = Arc::new(Calls::default());
+		let adapter =
+			InstrumentedAdapter::new(MemoryAdapter::default()).with_recorder(calls.clone());
+
+		adapter
+			.write(Path::new("file.txt"), b"Hello, world!")
+			.await
+			.unwrap();
+		adapter.read(Path::new("file.txt")).await.unwrap();
+		adapter.read(Path::new("missing.txt")).await.unwrap_err();
+
+		assert_eq!(
+			*calls.0.lock().unwrap(),
+			vec![
+				("memory", "write", Some(13), None),
+				("memory", "read", Some(13), None),
+				("memory", "read", None, Some(ErrorKind::NotFound)),
+			]
+		);
+		assert_eq!(
+			adapter.layers().first().map(|layer| layer.name),
+			Some("instrumented")
+		);
+	}
+}
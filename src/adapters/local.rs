@@ -1,31 +1,138 @@
 use async_recursion::async_recursion;
+use bytes::BufMut;
+use futures::{
+	future::BoxFuture, io::AsyncRead, stream::BoxStream, AsyncReadExt as _, FutureExt, StreamExt,
+	TryStreamExt,
+};
 use mime::Mime;
 use std::{
-	fs::Permissions,
 	io::{self, Result},
-	os::unix::fs::PermissionsExt,
 	path::{Path, PathBuf},
 	time::SystemTime,
 };
-use tokio::fs;
+use tokio::{
+	fs,
+	io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+};
+use url::Url;
+
+use super::{
+	ensure_capacity, ensure_not_nested, Adapter, AdapterInit, AdapterLayer, PublicUrlGenerator,
+	Validation,
+};
+use crate::{
+	contents::Contents, precondition, public_url::BaseUrl, DirectoryStat, Precondition, Resource,
+	StorageEntry, Visibility,
+};
+
+/// How many bytes are read at once when streaming a file.
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
 
-use super::{Adapter, AdapterInit};
-use crate::{contents::Contents, Resource, Visibility};
+/// How many files are written or deleted at once by batch operations.
+const BATCH_CONCURRENCY: usize = 16;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
 pub struct Config {
 	pub location: PathBuf,
+	#[cfg_attr(feature = "config", serde(default))]
 	pub lazy_root_creation: bool,
+	/// Whether to check (and create, with `lazy_root_creation`) the root when the adapter is created, or when first writing to it.
+	#[cfg_attr(feature = "config", serde(default))]
+	pub validation: Validation,
+	/// The URL the root is served from (like `https://example.com/storage/`), used to generate public URLs.
+	/// The adapter can't generate public URLs without it.
+	#[cfg_attr(feature = "config", serde(default))]
+	pub public_url: Option<Url>,
+}
+
+/// How much space a file takes, which can be less than its size for sparse files (like VM images).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiskUsage {
+	/// The logical size of the file.
+	pub size: u64,
+	/// The bytes allocated on disk for the file.
+	pub allocated: u64,
+}
+
+impl DiskUsage {
+	/// Check if the file has holes, taking less space than its size.
+	#[must_use]
+	pub const fn is_sparse(&self) -> bool {
+		self.allocated < self.size
+	}
 }
 
 #[derive(Debug, Clone)]
 #[allow(clippy::module_name_repetitions)]
 pub struct LocalAdapter {
 	location: PathBuf,
+	lazy_root_creation: bool,
+	public_url: Option<BaseUrl>,
 }
 
 impl LocalAdapter {
+	/// Get the logical and allocated size of a file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the file's metadata can't be read.
+	pub async fn disk_usage(&self, path: &Path) -> Result<DiskUsage> {
+		let metadata = fs::metadata(self.location.join(path)).await?;
+
+		Ok(DiskUsage {
+			size: metadata.len(),
+			allocated: platform::allocated(&metadata),
+		})
+	}
+
+	/// Copy a sparse file, seeking over zeroed chunks instead of writing them so they stay holes.
+	async fn copy_sparse(source: &Path, destination: &Path) -> Result<()> {
+		let mut source = fs::File::open(source).await?;
+		let metadata = source.metadata().await?;
+		let mut destination = fs::File::create(destination).await?;
+		let mut chunk = vec![0; STREAM_CHUNK_SIZE];
+		let mut offset = 0;
+
+		loop {
+			let n = source.read(&mut chunk).await?;
+			if n == 0 {
+				break;
+			}
+
+			if chunk[..n].iter().any(|byte| *byte != 0) {
+				destination.seek(io::SeekFrom::Start(offset)).await?;
+				destination.write_all(&chunk[..n]).await?;
+			}
+			offset += n as u64;
+		}
+
+		// a trailing hole is only part of the file once its length covers it
+		destination.set_len(metadata.len()).await?;
+		destination.set_permissions(metadata.permissions()).await
+	}
+
+	async fn ensure_root(location: &Path, lazy_root_creation: bool) -> Result<()> {
+		if fs::try_exists(location).await? {
+			return Ok(());
+		}
+
+		if !lazy_root_creation {
+			return Err(io::Error::new(
+				io::ErrorKind::NotFound,
+				format!(
+					"The root at {} does not exist. You can manually create it or enable lazy root creation.",
+					location.display()
+				),
+			));
+		}
+
+		fs::create_dir_all(location).await
+	}
+
 	async fn create_parent_if_not_exists(&self, path: &Path) -> Result<()> {
+		Self::ensure_root(&self.location, self.lazy_root_creation).await?;
+
 		if let Some(parent) = path.parent() {
 			if !parent.exists() {
 				fs::create_dir_all(parent).await?;
@@ -54,6 +161,73 @@ impl LocalAdapter {
 
 		Ok(paths)
 	}
+
+	#[async_recursion]
+	async fn get_entries_deep(path: &Path, deep: bool) -> Result<Vec<StorageEntry>> {
+		let mut entries = Vec::new();
+		let mut dir = fs::read_dir(path).await?;
+
+		while let Some(entry) = dir.next_entry().await? {
+			let metadata = entry.metadata().await?;
+
+			if metadata.is_dir() {
+				if deep {
+					entries.append(&mut Self::get_entries_deep(&entry.path(), deep).await?);
+				}
+				continue;
+			}
+
+			let path = entry.path();
+			entries.push(StorageEntry {
+				is_dir: false,
+				size: Some(metadata.len()),
+				last_modified: Some(metadata.modified()?),
+				mime: Some(mime_guess::from_path(&path).first_or_octet_stream()),
+				visibility: platform::visibility(Resource::File, &metadata),
+				path,
+			});
+		}
+
+		Ok(entries)
+	}
+
+	#[async_recursion]
+	async fn copy_directory_deep(source: &Path, destination: &Path) -> Result<()> {
+		fs::create_dir_all(destination).await?;
+		let mut dir = fs::read_dir(source).await?;
+
+		while let Some(entry) = dir.next_entry().await? {
+			let target = destination.join(entry.file_name());
+
+			if entry.file_type().await?.is_dir() {
+				Self::copy_directory_deep(&entry.path(), &target).await?;
+			} else {
+				fs::copy(entry.path(), target).await.map_err(storage_full)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Resolve the source and destination of a directory move or copy, failing if the source isn't a directory.
+	async fn directory_transfer(
+		&self,
+		source: &Path,
+		destination: &Path,
+	) -> Result<(PathBuf, PathBuf)> {
+		ensure_not_nested(source, destination)?;
+		let (source, destination) = (self.location.join(source), self.location.join(destination));
+
+		if !source.is_dir() {
+			return Err(io::Error::new(
+				io::ErrorKind::NotFound,
+				format!("The path {} is not a directory.", source.display()),
+			));
+		}
+
+		self.create_parent_if_not_exists(&destination).await?;
+		Ok((source, destination))
+	}
 }
 
 impl AdapterInit for LocalAdapter {
@@ -61,28 +235,29 @@ impl AdapterInit for LocalAdapter {
 	type Error = io::Error;
 
 	async fn new(config: Self::Config) -> Result<Self> {
-		if !config.location.exists() {
-			if !config.lazy_root_creation {
-				return Err(io::Error::new(
-                    io::ErrorKind::NotFound,
-                    format!(
-                        "The root at {:?} does not exist. You can manually create it or enable lazy root creation.",
-                        config.location
-                    ),
-                ));
-			}
-
-			fs::create_dir_all(&config.location).await?;
+		if config.validation == Validation::Eager {
+			Self::ensure_root(&config.location, config.lazy_root_creation).await?;
 		}
 
 		Ok(Self {
 			location: config.location,
+			lazy_root_creation: config.lazy_root_creation,
+			public_url: config.public_url.map(BaseUrl::new),
 		})
 	}
 }
 
+impl PublicUrlGenerator for LocalAdapter {
+	type Error = io::Error;
+
+	async fn public_url(&self, path: &Path) -> Result<String> {
+		Adapter::public_url(self, path).await.map(String::from)
+	}
+}
+
 impl Adapter for LocalAdapter {
 	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		let path = self.location.join(path);
 		if !path.is_file() {
 			return Ok(false);
 		}
@@ -91,6 +266,7 @@ impl Adapter for LocalAdapter {
 	}
 
 	async fn directory_exists(&self, path: &Path) -> Result<bool> {
+		let path = self.location.join(path);
 		if !path.is_dir() {
 			return Ok(false);
 		}
@@ -98,11 +274,127 @@ impl Adapter for LocalAdapter {
 		Ok(path.exists())
 	}
 
-	async fn write(&mut self, path: &Path, content: &[u8]) -> Result<()> {
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+		let path = self.location.join(path);
+
+		// the parent directory usually exists already, so it's only checked (and created) when the write fails
+		match fs::write(&path, content).await {
+			Err(error) if error.kind() == io::ErrorKind::NotFound => {
+				self.create_parent_if_not_exists(&path).await?;
+
+				fs::write(path, content).await.map_err(storage_full)
+			},
+			result => result.map_err(storage_full),
+		}
+	}
+
+	/// Files that must not exist yet are created exclusively (with `O_EXCL`), so only one writer can create them.
+	/// Other preconditions are checked against the file's checksum or modification time right before writing it.
+	async fn write_if(
+		&self,
+		path: &Path,
+		content: &[u8],
+		precondition: &Precondition,
+	) -> Result<()> {
+		if *precondition != Precondition::NotExists {
+			precondition.check(self, path).await?;
+
+			return self.write(path, content).await;
+		}
+
+		let full_path = self.location.join(path);
+		self.create_parent_if_not_exists(&full_path).await?;
+
+		let mut file = match fs::OpenOptions::new()
+			.write(true)
+			.create_new(true)
+			.open(&full_path)
+			.await
+		{
+			Ok(file) => file,
+			Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+				return Err(precondition::failed(path));
+			},
+			Err(error) => return Err(error),
+		};
+
+		let result = async {
+			file.write_all(content).await?;
+			file.flush().await
+		}
+		.await;
+
+		if result.is_err() {
+			drop(file);
+			let _ = fs::remove_file(&full_path).await;
+		}
+
+		result.map_err(storage_full)
+	}
+
+	async fn write_stream(
+		&self,
+		path: &Path,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		let path = self.location.join(path);
+		self.create_parent_if_not_exists(&path).await?;
+
+		let mut file = fs::File::create(&path).await?;
+		let mut buf = vec![0; 64 * 1024];
+
+		let result = async {
+			loop {
+				let n = reader.read(&mut buf).await?;
+				if n == 0 {
+					break file.flush().await;
+				}
+
+				file.write_all(&buf[..n]).await?;
+			}
+		}
+		.await;
+
+		if result.is_err() {
+			drop(file);
+			let _ = fs::remove_file(&path).await;
+		}
+
+		result.map_err(storage_full)
+	}
+
+	async fn write_range(&self, path: &Path, offset: u64, data: &[u8]) -> Result<()> {
+		let path = self.location.join(path);
+		self.create_parent_if_not_exists(&path).await?;
+
+		let mut file = fs::OpenOptions::new()
+			.write(true)
+			.create(true)
+			.truncate(false)
+			.open(path)
+			.await?;
+
+		file.seek(io::SeekFrom::Start(offset)).await?;
+		file.write_all(data).await.map_err(storage_full)?;
+		file.flush().await.map_err(storage_full)
+	}
+
+	async fn append(&self, path: &Path, data: &[u8]) -> Result<()> {
 		let path = self.location.join(path);
 		self.create_parent_if_not_exists(&path).await?;
 
-		fs::write(path, content).await
+		let mut file = fs::OpenOptions::new()
+			.append(true)
+			.create(true)
+			.open(path)
+			.await?;
+
+		file.write_all(data).await.map_err(storage_full)?;
+		file.flush().await.map_err(storage_full)
+	}
+
+	fn native_range_writes(&self) -> bool {
+		true
 	}
 
 	async fn read(&self, path: &Path) -> Result<Contents> {
@@ -111,40 +403,111 @@ impl Adapter for LocalAdapter {
 		Ok(Contents::from(fs::read(path).await?))
 	}
 
-	async fn delete(&mut self, path: &Path) -> Result<()> {
+	async fn read_stream(&self, path: &Path) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+		let file = fs::File::open(self.location.join(path)).await?;
+
+		let chunks = futures::stream::try_unfold(file, |mut file| async move {
+			let mut chunk = vec![0; STREAM_CHUNK_SIZE];
+			let n = file.read(&mut chunk).await?;
+			chunk.truncate(n);
+
+			Ok::<_, io::Error>((n > 0).then_some((chunk, file)))
+		});
+
+		Ok(Box::new(Box::pin(chunks).into_async_read()))
+	}
+
+	/// Read the file without keeping its contents, so it's in the OS page cache for the next reads.
+	async fn prefetch(&self, path: &Path) -> Result<()> {
+		let mut file = fs::File::open(self.location.join(path)).await?;
+		tokio::io::copy(&mut file, &mut tokio::io::sink()).await?;
+
+		Ok(())
+	}
+
+	async fn read_into(&self, path: &Path, buf: &mut (dyn BufMut + Send)) -> Result<()> {
+		let path = self.location.join(path);
+		let mut file = fs::File::open(path).await?;
+		let len = file.metadata().await?.len();
+
+		ensure_capacity(buf, len)?;
+		while file.read_buf(buf).await? != 0 {}
+
+		Ok(())
+	}
+
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		let path = self.location.join(path);
+		let mut file = fs::File::open(path).await?;
+		let mut data = Vec::new();
+
+		file.seek(io::SeekFrom::Start(offset)).await?;
+		file.take(len).read_to_end(&mut data).await?;
+
+		Ok(Contents::from(data))
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
 		let path = self.location.join(path);
 
 		fs::remove_file(path).await
 	}
 
-	async fn delete_directory(&mut self, path: &Path) -> Result<()> {
+	async fn delete_many(&self, paths: &[PathBuf]) -> Vec<(PathBuf, io::Error)> {
+		futures::stream::iter(paths.iter().cloned())
+			.map(|path| async move {
+				let result = self.delete(&path).await;
+				result.err().map(|error| (path, error))
+			})
+			.buffer_unordered(BATCH_CONCURRENCY)
+			.filter_map(futures::future::ready)
+			.collect()
+			.await
+	}
+
+	async fn write_many(&self, files: &[(&Path, &[u8])]) -> Vec<(PathBuf, io::Error)> {
+		// boxed so the future doesn't hold a closure borrowing the files, which trips up the `Send` check
+		let writes: Vec<BoxFuture<'_, Option<(PathBuf, io::Error)>>> = files
+			.iter()
+			.map(|&(path, content)| {
+				async move {
+					let result = self.write(path, content).await;
+					result.err().map(|error| (path.to_path_buf(), error))
+				}
+				.boxed()
+			})
+			.collect();
+
+		futures::stream::iter(writes)
+			.buffer_unordered(BATCH_CONCURRENCY)
+			.filter_map(futures::future::ready)
+			.collect()
+			.await
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
 		let path = self.location.join(path);
 
 		fs::remove_dir_all(path).await
 	}
 
-	async fn create_directory(&mut self, path: &Path) -> Result<()> {
+	async fn create_directory(&self, path: &Path) -> Result<()> {
 		let path = self.location.join(path);
 
-		fs::create_dir_all(path).await
+		fs::create_dir_all(path).await.map_err(storage_full)
 	}
 
-	async fn set_visibility(&mut self, path: &Path, visibility: Visibility) -> Result<()> {
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
 		let path = self.location.join(path);
-		let permissions = Permissions::from_mode(visibility_to_unix((&path).into(), visibility));
-
-		fs::set_permissions(path, permissions).await?;
 
-		Ok(())
+		platform::set_visibility(&path, (&path).into(), visibility).await
 	}
 
 	async fn visibility(&self, path: &Path) -> Result<Visibility> {
 		let path = self.location.join(path);
+		let metadata = fs::metadata(&path).await?;
 
-		Ok(unix_to_visibility(
-			(&path).into(),
-			fs::metadata(path).await?.permissions().mode(),
-		))
+		platform::visibility((&path).into(), &metadata).ok_or_else(platform::unsupported_visibility)
 	}
 
 	async fn mime_type(&self, path: &Path) -> Result<Mime> {
@@ -167,20 +530,106 @@ impl Adapter for LocalAdapter {
 		Ok(metadata.len())
 	}
 
+	async fn directory_stat(&self, path: &Path) -> Result<DirectoryStat> {
+		// the modification time of a directory only changes when entries are added to or removed from it directly,
+		// not when the files in it (or its subdirectories) change
+		let path = self.location.join(path);
+		let metadata = fs::metadata(&path).await?;
+
+		if !metadata.is_dir() {
+			return Err(io::Error::new(
+				io::ErrorKind::NotFound,
+				format!("The path {} is not a directory.", path.display()),
+			));
+		}
+
+		let mut dir = fs::read_dir(&path).await?;
+		let mut children = 0;
+		while dir.next_entry().await?.is_some() {
+			children += 1;
+		}
+
+		Ok(DirectoryStat {
+			last_modified: Some(metadata.modified()?),
+			children,
+		})
+	}
+
 	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
 		let path = self.location.join(path);
 
 		if !path.is_dir() {
 			return Err(io::Error::new(
 				io::ErrorKind::NotFound,
-				format!("The path {path:?} is not a directory."),
+				format!("The path {} is not a directory.", path.display()),
 			));
 		}
 
 		Self::get_files_deep(&path, deep).await
 	}
 
-	async fn r#move(&mut self, source: &Path, destination: &Path) -> Result<()> {
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		let path = self.location.join(path);
+
+		if !path.is_dir() {
+			return Err(io::Error::new(
+				io::ErrorKind::NotFound,
+				format!("The path {} is not a directory.", path.display()),
+			));
+		}
+
+		Self::get_entries_deep(&path, deep).await
+	}
+
+	fn list_stream<'a>(&'a self, path: &Path, deep: bool) -> BoxStream<'a, Result<PathBuf>> {
+		let path = self.location.join(path);
+
+		if !path.is_dir() {
+			let error = io::Error::new(
+				io::ErrorKind::NotFound,
+				format!("The path {} is not a directory.", path.display()),
+			);
+
+			return futures::stream::once(async { Err(error) }).boxed();
+		}
+
+		// directories are only read once the previous ones were consumed
+		futures::stream::try_unfold(
+			(vec![path], None),
+			move |(mut pending, mut current): (Vec<PathBuf>, Option<fs::ReadDir>)| async move {
+				loop {
+					let Some(dir) = current.as_mut() else {
+						let Some(next) = pending.pop() else {
+							return Ok(None);
+						};
+
+						current = Some(fs::read_dir(next).await?);
+						continue;
+					};
+
+					let Some(entry) = dir.next_entry().await? else {
+						current = None;
+						continue;
+					};
+
+					if !entry.file_type().await?.is_dir() {
+						return Ok(Some((entry.path(), (pending, current))));
+					}
+
+					if deep {
+						pending.push(entry.path());
+					}
+				}
+			},
+		)
+		.boxed()
+	}
+
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
 		let source = self.location.join(source);
 		let destination = self.location.join(destination);
 
@@ -189,144 +638,529 @@ impl Adapter for LocalAdapter {
 		fs::rename(source, destination).await
 	}
 
-	async fn copy(&mut self, source: &Path, destination: &Path) -> Result<()> {
-		let source = self.location.join(source);
-		let destination = self.location.join(destination);
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		let source = self.location.join(source);
+		let destination = self.location.join(destination);
+
+		if self.disk_usage(&source).await?.is_sparse() {
+			return Self::copy_sparse(&source, &destination)
+				.await
+				.map_err(storage_full);
+		}
+
+		fs::copy(source, destination).await.map_err(storage_full)?;
+
+		Ok(())
+	}
+
+	async fn move_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		let (source, destination) = self.directory_transfer(source, destination).await?;
+
+		fs::rename(source, destination).await
+	}
+
+	async fn copy_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		let (source, destination) = self.directory_transfer(source, destination).await?;
+
+		Self::copy_directory_deep(&source, &destination).await
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		Ok(sha256::digest(self.read(path).await?.data))
+	}
+
+	async fn public_url(&self, path: &Path) -> Result<Url> {
+		let Some(public_url) = &self.public_url else {
+			return Err(io::Error::new(
+				io::ErrorKind::Unsupported,
+				"Generating public URLs requires a public URL to be configured.",
+			));
+		};
+
+		public_url.url(path.strip_prefix(&self.location).unwrap_or(path))
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		vec![AdapterLayer::new("local").with("location", self.location.display())]
+	}
+}
+
+impl From<&PathBuf> for Resource {
+	fn from(path: &PathBuf) -> Self {
+		if path.is_file() {
+			Self::File
+		} else {
+			Self::Directory
+		}
+	}
+}
+
+/// Report exceeded disk quotas (`EDQUOT`) like full disks (`ENOSPC`), so callers only have to handle [`io::ErrorKind::StorageFull`].
+fn storage_full(error: io::Error) -> io::Error {
+	if error.kind() == io::ErrorKind::QuotaExceeded {
+		return io::Error::new(io::ErrorKind::StorageFull, error);
+	}
+
+	error
+}
+
+/// What differs between platforms: visibility is stored in mode bits on Unix, and isn't supported elsewhere.
+///
+/// Windows has no equivalent of mode bits (the read-only attribute would also block writes, and ACLs need the Windows APIs),
+/// so visibilities are reported as unknown when listing and as unsupported otherwise, like on object stores.
+mod platform {
+	use std::{fs::Metadata, io, path::Path};
+
+	use crate::{Resource, Visibility};
+
+	#[cfg(unix)]
+	const fn visibility_to_unix(resource: Resource, visibility: Visibility) -> u32 {
+		match (resource, visibility) {
+			(Resource::File, Visibility::Public) => 0o644,
+			(Resource::File, Visibility::Private) => 0o600,
+			(Resource::Directory, Visibility::Public) => 0o755,
+			(Resource::Directory, Visibility::Private) => 0o700,
+		}
+	}
+
+	#[cfg(unix)]
+	const fn unix_to_visibility(resource: Resource, unix: u32) -> Visibility {
+		match (resource, unix) {
+			(Resource::Directory, 0o700) | (Resource::File, 0o600) => Visibility::Private,
+			_ => Visibility::Public,
+		}
+	}
+
+	/// Get the visibility of a file or directory, if the platform supports it.
+	#[cfg(unix)]
+	#[allow(clippy::unnecessary_wraps)]
+	pub fn visibility(resource: Resource, metadata: &Metadata) -> Option<Visibility> {
+		use std::os::unix::fs::PermissionsExt;
+
+		Some(unix_to_visibility(
+			resource,
+			metadata.permissions().mode() & 0o777,
+		))
+	}
+
+	#[cfg(not(unix))]
+	pub const fn visibility(_: Resource, _: &Metadata) -> Option<Visibility> {
+		None
+	}
+
+	/// Set the visibility of the file or directory at `path`.
+	#[cfg(unix)]
+	pub async fn set_visibility(
+		path: &Path,
+		resource: Resource,
+		visibility: Visibility,
+	) -> io::Result<()> {
+		use std::{fs::Permissions, os::unix::fs::PermissionsExt};
+
+		let permissions = Permissions::from_mode(visibility_to_unix(resource, visibility));
+
+		tokio::fs::set_permissions(path, permissions).await
+	}
+
+	#[cfg(not(unix))]
+	pub async fn set_visibility(path: &Path, _: Resource, _: Visibility) -> io::Result<()> {
+		tokio::fs::metadata(path).await?;
+
+		Err(unsupported_visibility())
+	}
+
+	pub fn unsupported_visibility() -> io::Error {
+		io::Error::new(
+			io::ErrorKind::Unsupported,
+			"Visibility is only supported on Unix.",
+		)
+	}
+
+	/// Get the bytes allocated on disk for a file.
+	#[cfg(unix)]
+	pub fn allocated(metadata: &Metadata) -> u64 {
+		use std::os::unix::fs::MetadataExt;
+
+		// st_blocks is always counted in 512-byte units
+		metadata.blocks() * 512
+	}
+
+	/// Get the bytes allocated on disk for a file, assumed to be its size since there's no portable way to detect holes.
+	#[cfg(not(unix))]
+	pub fn allocated(metadata: &Metadata) -> u64 {
+		metadata.len()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use futures::TryStreamExt;
+	use std::env;
+
+	use super::*;
+
+	#[tokio::test]
+	async fn test_file_exists() {
+		let adapter = LocalAdapter::new(Config {
+			location: PathBuf::from("/tmp/flysystem_tests/"),
+			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
+		})
+		.await
+		.unwrap();
+
+		std::fs::write("/tmp/flysystem_tests/test_file_exists.txt", "Hello, world!").unwrap();
+
+		assert!(adapter
+			.file_exists(Path::new("/tmp/flysystem_tests/test_file_exists.txt"))
+			.await
+			.unwrap());
+		assert!(!adapter
+			.file_exists(Path::new("non-existent-file.txt"))
+			.await
+			.unwrap());
+
+		std::fs::remove_file("/tmp/flysystem_tests/test_file_exists.txt").unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_directory_exists() {
+		let adapter = LocalAdapter::new(Config {
+			location: PathBuf::from("/tmp/flysystem_tests/"),
+			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
+		})
+		.await
+		.unwrap();
+
+		std::fs::create_dir("/tmp/flysystem_tests/test").unwrap();
+
+		assert!(adapter
+			.directory_exists(Path::new("/tmp/flysystem_tests/test"))
+			.await
+			.unwrap());
+		assert!(!adapter
+			.directory_exists(Path::new("non-existent-directory"))
+			.await
+			.unwrap());
+
+		std::fs::remove_dir("/tmp/flysystem_tests/test").unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_write() {
+		let adapter = LocalAdapter::new(Config {
+			location: PathBuf::from("/tmp/flysystem_tests/"),
+			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
+		})
+		.await
+		.unwrap();
+
+		adapter
+			.write(Path::new("test_write.txt"), b"Hello, world!")
+			.await
+			.unwrap();
+
+		assert_eq!(
+			std::fs::read_to_string("/tmp/flysystem_tests/test_write.txt").unwrap(),
+			"Hello, world!"
+		);
+
+		std::fs::remove_file("/tmp/flysystem_tests/test_write.txt").unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_write_if() {
+		let adapter = LocalAdapter::new(Config {
+			location: PathBuf::from("/tmp/flysystem_tests/"),
+			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
+		})
+		.await
+		.unwrap();
+		let path = Path::new("test_write_if/lock.json");
+
+		adapter
+			.write_if(path, b"worker-1", &Precondition::NotExists)
+			.await
+			.unwrap();
+		let error = adapter
+			.write_if(path, b"worker-2", &Precondition::NotExists)
+			.await
+			.unwrap_err();
+		assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+
+		let checksum = adapter.checksum(path).await.unwrap();
+		adapter
+			.write_if(
+				path,
+				b"worker-1, again",
+				&Precondition::MatchesChecksum(checksum),
+			)
+			.await
+			.unwrap();
+		assert_eq!(
+			std::fs::read_to_string("/tmp/flysystem_tests/test_write_if/lock.json").unwrap(),
+			"worker-1, again"
+		);
+
+		std::fs::remove_dir_all("/tmp/flysystem_tests/test_write_if").unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_read() {
+		let adapter = LocalAdapter::new(Config {
+			location: PathBuf::from("/tmp/flysystem_tests/"),
+			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
+		})
+		.await
+		.unwrap();
+
+		std::fs::write("/tmp/flysystem_tests/test_read.txt", "Hello, world!").unwrap();
+
+		assert_eq!(
+			adapter.read(Path::new("test_read.txt")).await.unwrap().data,
+			b"Hello, world!"
+		);
+
+		std::fs::remove_file("/tmp/flysystem_tests/test_read.txt").unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_write_stream() {
+		let adapter = LocalAdapter::new(Config {
+			location: PathBuf::from("/tmp/flysystem_tests/"),
+			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
+		})
+		.await
+		.unwrap();
+
+		adapter
+			.write_stream(
+				Path::new("test_write_stream/file.txt"),
+				&mut &b"Hello, world!"[..],
+			)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			std::fs::read_to_string("/tmp/flysystem_tests/test_write_stream/file.txt").unwrap(),
+			"Hello, world!"
+		);
+
+		let mut failing = futures::stream::iter(vec![
+			Ok(b"Hello".to_vec()),
+			Err(io::Error::other("connection reset")),
+		])
+		.into_async_read();
+		assert!(adapter
+			.write_stream(Path::new("test_write_stream/partial.txt"), &mut failing)
+			.await
+			.is_err());
+		assert!(!Path::new("/tmp/flysystem_tests/test_write_stream/partial.txt").exists());
+
+		std::fs::remove_dir_all("/tmp/flysystem_tests/test_write_stream").unwrap();
+	}
+
+	#[cfg(unix)]
+	#[tokio::test]
+	async fn test_copy_preserves_sparseness() {
+		let adapter = LocalAdapter::new(Config {
+			location: PathBuf::from("/tmp/flysystem_tests/"),
+			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
+		})
+		.await
+		.unwrap();
 
-		fs::copy(source, destination).await?;
+		let file = std::fs::File::create("/tmp/flysystem_tests/test_sparse.img").unwrap();
+		file.set_len(16 * 1024 * 1024).unwrap();
+		std::os::unix::fs::FileExt::write_at(&file, b"boot", 1024 * 1024).unwrap();
 
-		Ok(())
-	}
+		adapter
+			.copy(
+				Path::new("test_sparse.img"),
+				Path::new("test_sparse_copy.img"),
+			)
+			.await
+			.unwrap();
 
-	async fn checksum(&self, path: &Path) -> Result<String> {
-		Ok(sha256::digest(self.read(path).await?.data))
-	}
-}
+		let usage = adapter
+			.disk_usage(Path::new("test_sparse_copy.img"))
+			.await
+			.unwrap();
+		assert_eq!(usage.size, 16 * 1024 * 1024);
+		assert!(usage.is_sparse());
+		assert_eq!(
+			adapter
+				.read_range(Path::new("test_sparse_copy.img"), 1024 * 1024, 4)
+				.await
+				.unwrap()
+				.data,
+			b"boot"
+		);
 
-impl From<&PathBuf> for Resource {
-	fn from(path: &PathBuf) -> Self {
-		if path.is_file() {
-			Self::File
-		} else {
-			Self::Directory
-		}
+		std::fs::remove_file("/tmp/flysystem_tests/test_sparse.img").unwrap();
+		std::fs::remove_file("/tmp/flysystem_tests/test_sparse_copy.img").unwrap();
 	}
-}
 
-const fn visibility_to_unix(resource: Resource, visibility: Visibility) -> u32 {
-	match (resource, visibility) {
-		(Resource::File, Visibility::Public) => 0o644,
-		(Resource::File, Visibility::Private) => 0o600,
-		(Resource::Directory, Visibility::Public) => 0o755,
-		(Resource::Directory, Visibility::Private) => 0o700,
-	}
-}
+	#[tokio::test]
+	async fn test_write_range() {
+		let adapter = LocalAdapter::new(Config {
+			location: PathBuf::from("/tmp/flysystem_tests/"),
+			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
+		})
+		.await
+		.unwrap();
 
-const fn unix_to_visibility(resource: Resource, unix: u32) -> Visibility {
-	match (resource, unix) {
-		(Resource::Directory, 0o700) | (Resource::File, 0o600) => Visibility::Private,
-		_ => Visibility::Public,
-	}
-}
+		std::fs::write("/tmp/flysystem_tests/test_write_range.bin", "Hello, world!").unwrap();
 
-#[cfg(test)]
-mod tests {
-	use std::os::unix::fs::PermissionsExt;
+		adapter
+			.write_range(Path::new("test_write_range.bin"), 7, b"there")
+			.await
+			.unwrap();
 
-	use super::*;
+		assert!(adapter.native_range_writes());
+		assert_eq!(
+			std::fs::read_to_string("/tmp/flysystem_tests/test_write_range.bin").unwrap(),
+			"Hello, there!"
+		);
+
+		std::fs::remove_file("/tmp/flysystem_tests/test_write_range.bin").unwrap();
+	}
 
 	#[tokio::test]
-	async fn test_file_exists() {
+	async fn test_append() {
 		let adapter = LocalAdapter::new(Config {
 			location: PathBuf::from("/tmp/flysystem_tests/"),
 			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
 		})
 		.await
 		.unwrap();
 
-		std::fs::write("/tmp/flysystem_tests/test_file_exists.txt", "Hello, world!").unwrap();
-
-		assert!(adapter
-			.file_exists(Path::new("/tmp/flysystem_tests/test_file_exists.txt"))
+		adapter
+			.append(Path::new("test_append/app.log"), b"started\n")
 			.await
-			.unwrap());
-		assert!(!adapter
-			.file_exists(Path::new("non-existent-file.txt"))
+			.unwrap();
+		adapter
+			.append(Path::new("test_append/app.log"), b"stopped\n")
 			.await
-			.unwrap());
+			.unwrap();
 
-		std::fs::remove_file("/tmp/flysystem_tests/test_file_exists.txt").unwrap();
+		assert_eq!(
+			std::fs::read_to_string("/tmp/flysystem_tests/test_append/app.log").unwrap(),
+			"started\nstopped\n"
+		);
+
+		std::fs::remove_dir_all("/tmp/flysystem_tests/test_append").unwrap();
 	}
 
 	#[tokio::test]
-	async fn test_directory_exists() {
+	async fn test_read_stream() {
 		let adapter = LocalAdapter::new(Config {
 			location: PathBuf::from("/tmp/flysystem_tests/"),
 			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
 		})
 		.await
 		.unwrap();
 
-		std::fs::create_dir("/tmp/flysystem_tests/test").unwrap();
+		let contents = "Hello, world! ".repeat(10_000);
+		std::fs::write("/tmp/flysystem_tests/test_read_stream.txt", &contents).unwrap();
 
-		assert!(adapter
-			.directory_exists(Path::new("/tmp/flysystem_tests/test"))
+		let mut read = String::new();
+		adapter
+			.read_stream(Path::new("test_read_stream.txt"))
 			.await
-			.unwrap());
-		assert!(!adapter
-			.directory_exists(Path::new("non-existent-directory"))
+			.unwrap()
+			.read_to_string(&mut read)
 			.await
-			.unwrap());
+			.unwrap();
 
-		std::fs::remove_dir("/tmp/flysystem_tests/test").unwrap();
+		assert_eq!(read, contents);
+
+		std::fs::remove_file("/tmp/flysystem_tests/test_read_stream.txt").unwrap();
 	}
 
 	#[tokio::test]
-	async fn test_write() {
-		let mut adapter = LocalAdapter::new(Config {
+	async fn test_read_into() {
+		let adapter = LocalAdapter::new(Config {
 			location: PathBuf::from("/tmp/flysystem_tests/"),
 			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
 		})
 		.await
 		.unwrap();
 
+		std::fs::write("/tmp/flysystem_tests/test_read_into.txt", "Hello, world!").unwrap();
+
+		let mut buf = b"> ".to_vec();
 		adapter
-			.write(Path::new("test_write.txt"), b"Hello, world!")
+			.read_into(Path::new("test_read_into.txt"), &mut buf)
 			.await
 			.unwrap();
 
-		assert_eq!(
-			std::fs::read_to_string("/tmp/flysystem_tests/test_write.txt").unwrap(),
-			"Hello, world!"
-		);
+		assert_eq!(buf, b"> Hello, world!");
 
-		std::fs::remove_file("/tmp/flysystem_tests/test_write.txt").unwrap();
+		std::fs::remove_file("/tmp/flysystem_tests/test_read_into.txt").unwrap();
 	}
 
 	#[tokio::test]
-	async fn test_read() {
+	async fn test_read_range() {
 		let adapter = LocalAdapter::new(Config {
 			location: PathBuf::from("/tmp/flysystem_tests/"),
 			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
 		})
 		.await
 		.unwrap();
 
-		std::fs::write("/tmp/flysystem_tests/test_read.txt", "Hello, world!").unwrap();
+		std::fs::write("/tmp/flysystem_tests/test_read_range.txt", "Hello, world!").unwrap();
 
 		assert_eq!(
-			adapter.read(Path::new("test_read.txt")).await.unwrap().data,
-			b"Hello, world!"
+			adapter
+				.read_range(Path::new("test_read_range.txt"), 7, 5)
+				.await
+				.unwrap()
+				.data,
+			b"world"
+		);
+		assert_eq!(
+			adapter
+				.read_range(Path::new("test_read_range.txt"), 7, 100)
+				.await
+				.unwrap()
+				.data,
+			b"world!"
 		);
 
-		std::fs::remove_file("/tmp/flysystem_tests/test_read.txt").unwrap();
+		std::fs::remove_file("/tmp/flysystem_tests/test_read_range.txt").unwrap();
 	}
 
 	#[tokio::test]
 	async fn test_delete() {
-		let mut adapter = LocalAdapter::new(Config {
+		let adapter = LocalAdapter::new(Config {
 			location: PathBuf::from("/tmp/flysystem_tests/"),
 			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
 		})
 		.await
 		.unwrap();
@@ -338,11 +1172,42 @@ mod tests {
 		assert!(!Path::new("/tmp/flysystem_tests/test.txt").exists());
 	}
 
+	#[tokio::test]
+	async fn test_write_and_delete_many() {
+		let adapter = LocalAdapter::new(Config {
+			location: PathBuf::from("/tmp/flysystem_tests/"),
+			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
+		})
+		.await
+		.unwrap();
+
+		let paths: Vec<PathBuf> = (0..20)
+			.map(|i| PathBuf::from(format!("test_many/{i}.txt")))
+			.collect();
+		let files: Vec<(&Path, &[u8])> = paths
+			.iter()
+			.map(|path| (path.as_path(), b"Hello, world!".as_slice()))
+			.collect();
+		assert!(adapter.write_many(&files).await.is_empty());
+		assert!(Path::new("/tmp/flysystem_tests/test_many/19.txt").exists());
+
+		let mut missing = paths.clone();
+		missing.push(PathBuf::from("test_many/missing.txt"));
+		let failed = adapter.delete_many(&missing).await;
+		assert_eq!(failed.len(), 1);
+		assert_eq!(failed[0].0, PathBuf::from("test_many/missing.txt"));
+		assert!(!Path::new("/tmp/flysystem_tests/test_many/0.txt").exists());
+	}
+
 	#[tokio::test]
 	async fn test_delete_directory() {
-		let mut adapter = LocalAdapter::new(Config {
+		let adapter = LocalAdapter::new(Config {
 			location: PathBuf::from("/tmp/flysystem_tests/"),
 			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
 		})
 		.await
 		.unwrap();
@@ -359,9 +1224,11 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_create_directory() {
-		let mut adapter = LocalAdapter::new(Config {
+		let adapter = LocalAdapter::new(Config {
 			location: PathBuf::from("/tmp/flysystem_tests/"),
 			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
 		})
 		.await
 		.unwrap();
@@ -380,9 +1247,11 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_create_directory_with_parents() {
-		let mut adapter = LocalAdapter::new(Config {
+		let adapter = LocalAdapter::new(Config {
 			location: PathBuf::from("/tmp/flysystem_tests/"),
 			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
 		})
 		.await
 		.unwrap();
@@ -401,11 +1270,16 @@ mod tests {
 		std::fs::remove_dir_all("/tmp/flysystem_tests/test_create_directory_with_parents").unwrap();
 	}
 
+	#[cfg(unix)]
 	#[tokio::test]
 	async fn test_set_visibility() {
-		let mut adapter = LocalAdapter::new(Config {
+		use std::os::unix::fs::PermissionsExt;
+
+		let adapter = LocalAdapter::new(Config {
 			location: PathBuf::from("/tmp/flysystem_tests/"),
 			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
 		})
 		.await
 		.unwrap();
@@ -426,17 +1300,21 @@ mod tests {
 				.unwrap()
 				.permissions()
 				.mode() & 0o777,
-			0o777
+			// private files are only accessible by their owner
+			0o600
 		);
 
 		std::fs::remove_file("/tmp/flysystem_tests/test_set_visibility.txt").unwrap();
 	}
 
+	#[cfg(unix)]
 	#[tokio::test]
 	async fn test_visibility() {
 		let adapter = LocalAdapter::new(Config {
 			location: PathBuf::from("/tmp/flysystem_tests/"),
 			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
 		})
 		.await
 		.unwrap();
@@ -459,6 +1337,8 @@ mod tests {
 		let adapter = LocalAdapter::new(Config {
 			location: PathBuf::from("/tmp/flysystem_tests/"),
 			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
 		})
 		.await
 		.unwrap();
@@ -478,6 +1358,8 @@ mod tests {
 		let adapter = LocalAdapter::new(Config {
 			location: PathBuf::from("/tmp/flysystem_tests/"),
 			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
 		})
 		.await
 		.unwrap();
@@ -506,6 +1388,8 @@ mod tests {
 		let adapter = LocalAdapter::new(Config {
 			location: PathBuf::from("/tmp/flysystem_tests/"),
 			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
 		})
 		.await
 		.unwrap();
@@ -523,11 +1407,50 @@ mod tests {
 		std::fs::remove_file("/tmp/flysystem_tests/test_file_size.txt").unwrap();
 	}
 
+	#[tokio::test]
+	async fn test_directory_stat() {
+		let adapter = LocalAdapter::new(Config {
+			location: PathBuf::from("/tmp/flysystem_tests/"),
+			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
+		})
+		.await
+		.unwrap();
+
+		std::fs::create_dir_all("/tmp/flysystem_tests/test_directory_stat/nested").unwrap();
+		std::fs::write("/tmp/flysystem_tests/test_directory_stat/a.txt", "a").unwrap();
+		std::fs::write("/tmp/flysystem_tests/test_directory_stat/nested/b.txt", "b").unwrap();
+
+		let stat = adapter
+			.directory_stat(Path::new("test_directory_stat"))
+			.await
+			.unwrap();
+		assert_eq!(stat.children, 2);
+		assert_eq!(
+			stat.last_modified,
+			Some(
+				std::fs::metadata("/tmp/flysystem_tests/test_directory_stat")
+					.unwrap()
+					.modified()
+					.unwrap()
+			)
+		);
+		assert!(adapter
+			.directory_stat(Path::new("test_directory_stat/a.txt"))
+			.await
+			.is_err());
+
+		std::fs::remove_dir_all("/tmp/flysystem_tests/test_directory_stat").unwrap();
+	}
+
 	#[tokio::test]
 	async fn test_list_contents() {
 		let adapter = LocalAdapter::new(Config {
 			location: PathBuf::from("/tmp/flysystem_tests/"),
 			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
 		})
 		.await
 		.unwrap();
@@ -555,11 +1478,15 @@ mod tests {
 				"/tmp/flysystem_tests/test_list_contents/test_file.txt"
 			)]
 		);
+		// the order of deep listings depends on the order the OS returns directory entries in
+		let mut deep_contents = adapter
+			.list_contents(Path::new("test_list_contents"), true)
+			.await
+			.unwrap();
+		deep_contents.sort();
+
 		assert_eq!(
-			adapter
-				.list_contents(Path::new("test_list_contents"), true)
-				.await
-				.unwrap(),
+			deep_contents,
 			vec![
 				PathBuf::from("/tmp/flysystem_tests/test_list_contents/test_file.txt"),
 				PathBuf::from(
@@ -571,11 +1498,102 @@ mod tests {
 		std::fs::remove_dir_all("/tmp/flysystem_tests/test_list_contents").unwrap();
 	}
 
+	#[cfg(unix)]
+	#[tokio::test]
+	async fn test_list_contents_with_metadata() {
+		let adapter = LocalAdapter::new(Config {
+			location: PathBuf::from("/tmp/flysystem_tests/"),
+			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
+		})
+		.await
+		.unwrap();
+
+		adapter
+			.write(Path::new("test_list_metadata/report.csv"), b"a,b")
+			.await
+			.unwrap();
+		adapter
+			.set_visibility(
+				Path::new("test_list_metadata/report.csv"),
+				Visibility::Private,
+			)
+			.await
+			.unwrap();
+
+		let entries = adapter
+			.list_contents_with_metadata(Path::new("test_list_metadata"), false)
+			.await
+			.unwrap();
+
+		assert_eq!(entries.len(), 1);
+		assert_eq!(
+			entries[0].path,
+			PathBuf::from("/tmp/flysystem_tests/test_list_metadata/report.csv")
+		);
+		assert_eq!(entries[0].size, Some(3));
+		assert_eq!(entries[0].mime, Some(mime::TEXT_CSV));
+		assert_eq!(entries[0].visibility, Some(Visibility::Private));
+
+		std::fs::remove_dir_all("/tmp/flysystem_tests/test_list_metadata").unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_list_stream() {
+		let adapter = LocalAdapter::new(Config {
+			location: PathBuf::from("/tmp/flysystem_tests/"),
+			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
+		})
+		.await
+		.unwrap();
+
+		std::fs::create_dir_all("/tmp/flysystem_tests/test_list_stream/nested").unwrap();
+		std::fs::write("/tmp/flysystem_tests/test_list_stream/a.txt", "a").unwrap();
+		std::fs::write("/tmp/flysystem_tests/test_list_stream/nested/b.txt", "b").unwrap();
+
+		let mut shallow: Vec<PathBuf> = adapter
+			.list_stream(Path::new("test_list_stream"), false)
+			.try_collect()
+			.await
+			.unwrap();
+		shallow.sort();
+		assert_eq!(
+			shallow,
+			vec![PathBuf::from("/tmp/flysystem_tests/test_list_stream/a.txt")]
+		);
+
+		let mut deep: Vec<PathBuf> = adapter
+			.list_stream(Path::new("test_list_stream"), true)
+			.try_collect()
+			.await
+			.unwrap();
+		deep.sort();
+		assert_eq!(
+			deep,
+			adapter
+				.list_contents(Path::new("test_list_stream"), true)
+				.await
+				.map(|mut contents| {
+					contents.sort();
+					contents
+				})
+				.unwrap()
+		);
+		assert_eq!(deep.len(), 2);
+
+		std::fs::remove_dir_all("/tmp/flysystem_tests/test_list_stream").unwrap();
+	}
+
 	#[tokio::test]
 	async fn test_move() {
-		let mut adapter = LocalAdapter::new(Config {
+		let adapter = LocalAdapter::new(Config {
 			location: PathBuf::from("/tmp/flysystem_tests/"),
 			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
 		})
 		.await
 		.unwrap();
@@ -598,9 +1616,11 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_copy() {
-		let mut adapter = LocalAdapter::new(Config {
+		let adapter = LocalAdapter::new(Config {
 			location: PathBuf::from("/tmp/flysystem_tests/"),
 			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
 		})
 		.await
 		.unwrap();
@@ -623,11 +1643,54 @@ mod tests {
 		std::fs::remove_file("/tmp/flysystem_tests/test_copy_destination.txt").unwrap();
 	}
 
+	#[tokio::test]
+	async fn test_move_and_copy_directory() {
+		let adapter = LocalAdapter::new(Config {
+			location: PathBuf::from("/tmp/flysystem_tests/"),
+			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
+		})
+		.await
+		.unwrap();
+
+		std::fs::create_dir_all("/tmp/flysystem_tests/test_move_directory/nested/empty").unwrap();
+		std::fs::write("/tmp/flysystem_tests/test_move_directory/nested/a.txt", "a").unwrap();
+
+		adapter
+			.move_directory(
+				Path::new("test_move_directory/nested"),
+				Path::new("test_move_directory/archive/moved"),
+			)
+			.await
+			.unwrap();
+		assert!(!Path::new("/tmp/flysystem_tests/test_move_directory/nested").exists());
+		assert!(Path::new("/tmp/flysystem_tests/test_move_directory/archive/moved/empty").is_dir());
+
+		adapter
+			.copy_directory(
+				Path::new("test_move_directory/archive"),
+				Path::new("test_move_directory/copy"),
+			)
+			.await
+			.unwrap();
+		assert_eq!(
+			std::fs::read_to_string("/tmp/flysystem_tests/test_move_directory/copy/moved/a.txt")
+				.unwrap(),
+			"a"
+		);
+		assert!(Path::new("/tmp/flysystem_tests/test_move_directory/archive/moved/a.txt").exists());
+
+		std::fs::remove_dir_all("/tmp/flysystem_tests/test_move_directory").unwrap();
+	}
+
 	#[tokio::test]
 	async fn test_checksum() {
 		let client = LocalAdapter::new(Config {
 			location: PathBuf::from("/tmp/flysystem_tests/"),
 			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
 		})
 		.await
 		.unwrap();
@@ -650,6 +1713,8 @@ mod tests {
 		let adapter = LocalAdapter::new(Config {
 			location: PathBuf::from("/tmp/flysystem_tests/non_existent_root/"),
 			lazy_root_creation: false,
+			validation: Validation::Eager,
+			public_url: None,
 		})
 		.await;
 
@@ -661,6 +1726,8 @@ mod tests {
 		let adapter = LocalAdapter::new(Config {
 			location: PathBuf::from("/tmp/flysystem_tests/non_existent_root/"),
 			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
 		})
 		.await;
 
@@ -668,4 +1735,85 @@ mod tests {
 
 		std::fs::remove_dir("/tmp/flysystem_tests/non_existent_root").unwrap();
 	}
+
+	#[tokio::test]
+	async fn test_lazy_validation() {
+		let location = env::temp_dir().join("flysystem_tests_lazy_validation");
+		let _ = std::fs::remove_dir_all(&location);
+
+		let config = Config {
+			location: location.clone(),
+			lazy_root_creation: false,
+			validation: Validation::Lazy,
+			public_url: None,
+		};
+
+		let adapter = LocalAdapter::new(config.clone()).await.unwrap();
+		assert!(!location.exists());
+		assert_eq!(
+			adapter
+				.write(&location.join("file.txt"), b"Hello, world!")
+				.await
+				.unwrap_err()
+				.kind(),
+			io::ErrorKind::NotFound
+		);
+
+		assert_eq!(
+			LocalAdapter::new(Config {
+				validation: Validation::Eager,
+				..config
+			})
+			.await
+			.unwrap_err()
+			.kind(),
+			io::ErrorKind::NotFound
+		);
+	}
+
+	#[test]
+	fn test_storage_full() {
+		// ENOSPC and EDQUOT on Linux
+		for code in [28, 122] {
+			assert_eq!(
+				storage_full(io::Error::from_raw_os_error(code)).kind(),
+				io::ErrorKind::StorageFull
+			);
+		}
+
+		assert_eq!(
+			storage_full(io::Error::from(io::ErrorKind::NotFound)).kind(),
+			io::ErrorKind::NotFound
+		);
+	}
+
+	#[tokio::test]
+	async fn test_public_url() {
+		let filesystem = crate::Filesystem::new::<LocalAdapter>(Config {
+			location: PathBuf::from("/tmp/flysystem_tests/"),
+			lazy_root_creation: true,
+			validation: Validation::Lazy,
+			public_url: Some(Url::parse("https://example.com/storage").unwrap()),
+		})
+		.await
+		.unwrap();
+
+		assert_eq!(
+			filesystem
+				.public_url(Path::new("avatars/1.png"))
+				.await
+				.unwrap()
+				.as_str(),
+			"https://example.com/storage/avatars/1.png"
+		);
+		assert!(matches!(
+			filesystem
+				.temporary_url(
+					Path::new("avatars/1.png"),
+					std::time::Duration::from_mins(5)
+				)
+				.await,
+			Err(crate::FlysystemError::TemporaryUrlNotSupported { .. })
+		));
+	}
 }
@@ -3,39 +3,193 @@ use std::{
 	convert::Infallible,
 	io::{Error, ErrorKind, Result},
 	path::{Path, PathBuf},
+	sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
 	time::SystemTime,
 };
-
-use async_recursion::async_recursion;
-
-use super::{Adapter, AdapterInit};
-use crate::{contents::Contents, Visibility};
+#[cfg(feature = "simulation")]
+use std::{sync::Mutex, time::Duration};
+
+#[cfg(feature = "snapshots")]
+use serde::{Deserialize, Serialize};
+
+use bytes::BufMut;
+use mime::Mime;
+
+use super::{ensure_capacity, ensure_not_nested, Adapter, AdapterInit, AdapterLayer};
+use crate::{
+	clock::{Clock, SystemClock},
+	contents::Contents,
+	precondition,
+	versioning::FileVersion,
+	Precondition, StorageEntry, Visibility, WriteOptions,
+};
 
 #[derive(Debug, Clone)]
 struct File {
 	content: Vec<u8>,
 	visibility: Visibility,
 	last_modified: SystemTime,
+	mime_type: Option<Mime>,
+	cache_control: Option<String>,
+	metadata: HashMap<String, String>,
 }
 
-impl File {
-	fn updated_now(mut self) -> Self {
-		self.last_modified = SystemTime::now();
-
-		self
-	}
+/// A retained version of a file, or a deletion marker if it has no contents.
+#[derive(Debug, Clone)]
+struct Version {
+	id: u64,
+	file: Option<File>,
+	last_modified: SystemTime,
 }
 
 #[derive(Debug, Clone)]
-#[allow(clippy::module_name_repetitions)]
-pub struct MemoryAdapter {
+struct State {
 	files: HashMap<PathBuf, File>,
 	directory: HashMap<PathBuf, Vec<PathBuf>>,
+	versions: Option<HashMap<PathBuf, Vec<Version>>>,
+	next_version: u64,
+	clock: Arc<dyn Clock>,
+	capacity: Option<u64>,
 }
 
-impl MemoryAdapter {
-	#[async_recursion]
-	async fn get_files_deep(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+impl Default for State {
+	fn default() -> Self {
+		Self {
+			files: HashMap::new(),
+			directory: HashMap::new(),
+			versions: None,
+			next_version: 0,
+			clock: Arc::new(SystemClock),
+			capacity: None,
+		}
+	}
+}
+
+impl State {
+	/// Record a new version of a file (or its deletion) when versioning is enabled.
+	fn record_version(&mut self, path: &Path, file: Option<File>) {
+		let Some(versions) = &mut self.versions else {
+			return;
+		};
+
+		self.next_version += 1;
+		versions
+			.entry(path.to_path_buf())
+			.or_default()
+			.push(Version {
+				id: self.next_version,
+				last_modified: file
+					.as_ref()
+					.map_or_else(|| self.clock.now(), |file| file.last_modified),
+				file,
+			});
+	}
+
+	/// Fail with `StorageFull` if storing `size` bytes at `path` (replacing the file there, if any) would exceed the capacity.
+	fn ensure_room(&self, path: &Path, size: usize) -> Result<()> {
+		let Some(capacity) = self.capacity else {
+			return Ok(());
+		};
+
+		let used: u64 = self
+			.files
+			.iter()
+			.filter(|(file_path, _)| *file_path != path)
+			.map(|(_, file)| file.content.len() as u64)
+			.sum();
+		if used + size as u64 > capacity {
+			return Err(Error::new(
+				ErrorKind::StorageFull,
+				"The memory adapter is out of capacity.",
+			));
+		}
+
+		Ok(())
+	}
+
+	fn insert(&mut self, path: &Path, file: File) {
+		// skip cloning the file when there are no versions to keep
+		if self.versions.is_some() {
+			self.record_version(path, Some(file.clone()));
+		}
+		// overwriting a file leaves its directory entry as is
+		if self.files.insert(path.to_path_buf(), file).is_some() {
+			return;
+		}
+
+		if let Some(parent) = path.parent() {
+			if !self.directory.contains_key(parent) {
+				self.create_directory(parent);
+			}
+			self.directory
+				.entry(parent.to_path_buf())
+				.or_default()
+				.push(path.to_path_buf());
+		}
+	}
+
+	fn remove(&mut self, path: &Path) -> Result<()> {
+		if self.files.remove(path).is_none() {
+			return Err(Error::from(ErrorKind::NotFound));
+		}
+		self.record_version(path, None);
+
+		self.directory
+			.entry(path.parent().unwrap().to_path_buf())
+			.or_default()
+			.retain(|file_path| file_path != path);
+
+		Ok(())
+	}
+
+	fn copy(&mut self, source: &Path, destination: &Path) -> Result<()> {
+		let mut file = self
+			.files
+			.get(source)
+			.ok_or_else(|| Error::from(ErrorKind::NotFound))?
+			.clone();
+		file.last_modified = self.clock.now();
+
+		self.insert(destination, file);
+
+		Ok(())
+	}
+
+	/// Move every file under `source` to the same relative path under `destination`, keeping empty directories around.
+	fn move_directory(&mut self, source: &Path, destination: &Path) -> Result<()> {
+		let files: Vec<PathBuf> = self
+			.files
+			.keys()
+			.filter(|path| path.starts_with(source))
+			.cloned()
+			.collect();
+
+		for file in files {
+			let relative = file.strip_prefix(source).unwrap_or(&file);
+			self.copy(&file, &destination.join(relative))?;
+			self.remove(&file)?;
+		}
+
+		let directories: Vec<PathBuf> = self
+			.directory
+			.keys()
+			.filter(|path| path.starts_with(source))
+			.cloned()
+			.collect();
+
+		for directory in directories {
+			self.directory.remove(&directory);
+
+			let relative = directory.strip_prefix(source).unwrap_or(&directory);
+			self.directory
+				.entry(destination.join(relative))
+				.or_default();
+		}
+
+		Ok(())
+	}
+
+	fn files_deep(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
 		let mut contents = self
 			.directory
 			.get(path)
@@ -45,13 +199,474 @@ impl MemoryAdapter {
 		if deep {
 			for directory in self.directory.keys() {
 				if directory.starts_with(path) && directory != path {
-					contents.extend(self.get_files_deep(directory, deep).await?);
+					contents.extend(self.files_deep(directory, false)?);
 				}
 			}
 		}
 
 		Ok(contents)
 	}
+
+	fn versions_under(&self, prefix: &Path) -> Result<Vec<FileVersion>> {
+		let Some(versions) = &self.versions else {
+			return Err(Error::new(
+				ErrorKind::Unsupported,
+				"Versioning is not enabled on this adapter.",
+			));
+		};
+
+		Ok(versions
+			.iter()
+			.filter(|(path, _)| path.starts_with(prefix))
+			.flat_map(|(path, versions)| {
+				// newest first, like S3
+				versions
+					.iter()
+					.rev()
+					.enumerate()
+					.map(|(i, version)| FileVersion {
+						path: path.clone(),
+						version_id: version.id.to_string(),
+						last_modified: version.last_modified,
+						size: version
+							.file
+							.as_ref()
+							.map_or(0, |file| file.content.len() as u64),
+						deleted: version.file.is_none(),
+						is_latest: i == 0,
+					})
+			})
+			.collect())
+	}
+
+	fn remove_directory(&mut self, path: &Path) -> Result<()> {
+		self.directory
+			.remove(path)
+			.ok_or_else(|| Error::from(ErrorKind::NotFound))?;
+		self.directory
+			.retain(|directory, _| !directory.starts_with(path));
+
+		let deleted: Vec<PathBuf> = self
+			.files
+			.keys()
+			.filter(|file_path| file_path.starts_with(path))
+			.cloned()
+			.collect();
+
+		for file_path in deleted {
+			self.files.remove(&file_path);
+			self.record_version(&file_path, None);
+		}
+
+		Ok(())
+	}
+
+	fn create_directory(&mut self, path: &Path) {
+		let mut current_path = PathBuf::new();
+		for component in path.components() {
+			current_path.push(component);
+			self.directory.entry(current_path.clone()).or_default();
+		}
+	}
+
+	fn entries(&self, path: &Path, deep: bool) -> Result<Vec<StorageEntry>> {
+		let paths = self.files_deep(path, deep)?;
+
+		Ok(paths
+			.into_iter()
+			.filter_map(|path| {
+				let file = self.files.get(&path)?;
+
+				Some(StorageEntry {
+					is_dir: false,
+					size: Some(file.content.len() as u64),
+					last_modified: Some(file.last_modified),
+					mime: Some(
+						file.mime_type.clone().unwrap_or_else(|| {
+							mime_guess::from_path(&path).first_or_octet_stream()
+						}),
+					),
+					visibility: Some(file.visibility),
+					path,
+				})
+			})
+			.collect())
+	}
+}
+
+/// The files and directories of an adapter, as saved by [`MemoryAdapter::snapshot`].
+#[cfg(feature = "snapshots")]
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredState {
+	directories: Vec<String>,
+	files: Vec<StoredFile>,
+}
+
+#[cfg(feature = "snapshots")]
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredFile {
+	path: String,
+	content: Vec<u8>,
+	public: bool,
+	last_modified: SystemTime,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	mime_type: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	cache_control: Option<String>,
+	#[serde(default, skip_serializing_if = "HashMap::is_empty")]
+	metadata: HashMap<String, String>,
+}
+
+/// How a [simulated](MemoryAdapter::simulated) adapter misbehaves, driven by a seeded random number generator so failing runs can be replayed.
+///
+/// Every operation waits a random latency before touching the files, so concurrent operations complete in a seed-dependent order.
+/// Runs are only fully reproducible on a single-threaded runtime with paused time (like `#[tokio::test(start_paused = true)]`).
+#[cfg(feature = "simulation")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Simulation {
+	seed: u64,
+	min_latency: Duration,
+	max_latency: Duration,
+	failure_rate: f64,
+	lose_unflushed_writes: bool,
+}
+
+#[cfg(feature = "simulation")]
+impl Simulation {
+	/// Simulate with the given seed, without any latency, failures or data loss until they're configured.
+	#[must_use]
+	pub const fn new(seed: u64) -> Self {
+		Self {
+			seed,
+			min_latency: Duration::ZERO,
+			max_latency: Duration::ZERO,
+			failure_rate: 0.0,
+			lose_unflushed_writes: false,
+		}
+	}
+
+	/// Wait between `min` and `max` before every operation.
+	#[must_use]
+	pub fn latency(mut self, min: Duration, max: Duration) -> Self {
+		self.min_latency = min;
+		self.max_latency = max.max(min);
+		self
+	}
+
+	/// Fail this share of operations (between `0.0` and `1.0`) before they do anything.
+	#[must_use]
+	pub const fn failure_rate(mut self, rate: f64) -> Self {
+		self.failure_rate = rate.clamp(0.0, 1.0);
+		self
+	}
+
+	/// Only keep the changes made up to the last [flush](Adapter::flush) when the adapter [crashes](MemoryAdapter::crash),
+	/// like writes that were never `fsync`ed.
+	#[must_use]
+	pub const fn lose_unflushed_writes(mut self) -> Self {
+		self.lose_unflushed_writes = true;
+		self
+	}
+}
+
+/// The state of a simulated adapter.
+#[cfg(feature = "simulation")]
+#[derive(Debug)]
+struct Simulator {
+	simulation: Simulation,
+	rng: Mutex<u64>,
+	/// The files as of the last flush, when unflushed writes are lost on crashes.
+	durable: Mutex<Option<State>>,
+}
+
+#[cfg(feature = "simulation")]
+impl Clone for Simulator {
+	fn clone(&self) -> Self {
+		Self {
+			simulation: self.simulation.clone(),
+			rng: Mutex::new(*self.rng.lock().unwrap_or_else(PoisonError::into_inner)),
+			durable: Mutex::new(
+				self.durable
+					.lock()
+					.unwrap_or_else(PoisonError::into_inner)
+					.clone(),
+			),
+		}
+	}
+}
+
+#[cfg(feature = "simulation")]
+impl Simulator {
+	/// A random number in `[0, 1)`, from a `SplitMix64` generator.
+	fn random(&self) -> f64 {
+		let mut rng = self.rng.lock().unwrap_or_else(PoisonError::into_inner);
+		*rng = rng.wrapping_add(0x9E37_79B9_7F4A_7C15);
+		let mut z = *rng;
+		drop(rng);
+
+		z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+		z ^= z >> 31;
+
+		#[allow(clippy::cast_precision_loss)]
+		let random = (z >> 11) as f64 / (1u64 << 53) as f64;
+		random
+	}
+
+	/// Wait a random latency, then fail at random.
+	async fn run(&self) -> Result<()> {
+		let Simulation {
+			min_latency,
+			max_latency,
+			failure_rate,
+			..
+		} = self.simulation;
+
+		let latency = min_latency
+			+ max_latency
+				.saturating_sub(min_latency)
+				.mul_f64(self.random());
+		if !latency.is_zero() {
+			tokio::time::sleep(latency).await;
+		}
+
+		if failure_rate > 0.0 && self.random() < failure_rate {
+			return Err(Error::other("Simulated failure."));
+		}
+
+		Ok(())
+	}
+}
+
+/// An adapter keeping files in memory, mostly useful for tests.
+///
+/// Clones are independent copies of the files, taken when cloning.
+#[derive(Debug, Default)]
+#[allow(clippy::module_name_repetitions)]
+pub struct MemoryAdapter {
+	state: RwLock<State>,
+	#[cfg(feature = "simulation")]
+	simulator: Option<Simulator>,
+}
+
+impl Clone for MemoryAdapter {
+	fn clone(&self) -> Self {
+		Self {
+			state: RwLock::new(self.state().clone()),
+			#[cfg(feature = "simulation")]
+			simulator: self.simulator.clone(),
+		}
+	}
+}
+
+impl MemoryAdapter {
+	/// Get the `Cache-Control` header a file was written with, if any.
+	#[must_use]
+	pub fn cache_control(&self, path: &Path) -> Option<String> {
+		self.with_file(path, |file| file.cache_control.clone())
+			.ok()
+			.flatten()
+	}
+
+	/// Get the custom metadata a file was written with.
+	#[must_use]
+	pub fn metadata(&self, path: &Path) -> Option<HashMap<String, String>> {
+		self.with_file(path, |file| file.metadata.clone()).ok()
+	}
+
+	/// Create an adapter that keeps every version of its files, like a versioned bucket.
+	#[must_use]
+	pub fn versioned() -> Self {
+		Self {
+			state: RwLock::new(State {
+				versions: Some(HashMap::new()),
+				..State::default()
+			}),
+			#[cfg(feature = "simulation")]
+			simulator: None,
+		}
+	}
+
+	/// Inject latencies, failures and data loss, to test how an application copes with them before it meets a real backend.
+	///
+	/// The files the adapter already has are considered flushed.
+	#[cfg(feature = "simulation")]
+	#[must_use]
+	pub fn simulated(mut self, simulation: Simulation) -> Self {
+		let durable = simulation
+			.lose_unflushed_writes
+			.then(|| self.state().clone());
+
+		self.simulator = Some(Simulator {
+			rng: Mutex::new(simulation.seed),
+			durable: Mutex::new(durable),
+			simulation,
+		});
+		self
+	}
+
+	/// Simulate a crash, losing every change made since the last [flush](Adapter::flush) if the adapter
+	/// is [simulated](MemoryAdapter::simulated) with [`Simulation::lose_unflushed_writes`]. Does nothing otherwise.
+	#[cfg(feature = "simulation")]
+	pub fn crash(&self) {
+		let Some(simulator) = &self.simulator else {
+			return;
+		};
+		let durable = simulator
+			.durable
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.clone();
+
+		if let Some(durable) = durable {
+			let mut state = self.state_mut();
+			let clock = state.clock.clone();
+			*state = State { clock, ..durable };
+		}
+	}
+
+	/// Wait and fail as the simulation (if any) dictates, before running an operation.
+	#[allow(clippy::unused_async)]
+	async fn simulate(&self) -> Result<()> {
+		#[cfg(feature = "simulation")]
+		if let Some(simulator) = &self.simulator {
+			return simulator.run().await;
+		}
+
+		Ok(())
+	}
+
+	/// Make writes fail with [`ErrorKind::StorageFull`] once the files would take more than `bytes`, like a full disk.
+	#[must_use]
+	pub fn with_max_capacity(self, bytes: u64) -> Self {
+		self.state_mut().capacity = Some(bytes);
+		self
+	}
+
+	/// Save the files and directories of the adapter, to [restore](MemoryAdapter::restore) them later (like a test fixture).
+	///
+	/// Past versions of files aren't included.
+	///
+	/// # Panics
+	///
+	/// Never, since paths are saved as (lossy) strings.
+	#[cfg(feature = "snapshots")]
+	#[must_use]
+	pub fn snapshot(&self) -> Vec<u8> {
+		let state = self.state();
+
+		let mut directories: Vec<String> = state
+			.directory
+			.keys()
+			.map(|path| path.to_string_lossy().into_owned())
+			.collect();
+		let mut files: Vec<StoredFile> = state
+			.files
+			.iter()
+			.map(|(path, file)| StoredFile {
+				path: path.to_string_lossy().into_owned(),
+				content: file.content.clone(),
+				public: file.visibility == Visibility::Public,
+				last_modified: file.last_modified,
+				mime_type: file.mime_type.as_ref().map(ToString::to_string),
+				cache_control: file.cache_control.clone(),
+				metadata: file.metadata.clone(),
+			})
+			.collect();
+		drop(state);
+
+		directories.sort_unstable();
+		files.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+
+		serde_json::to_vec(&StoredState { directories, files })
+			.expect("Snapshots only contain strings, bytes and timestamps.")
+	}
+
+	/// Replace every file and directory of the adapter with the ones of a [snapshot](MemoryAdapter::snapshot).
+	///
+	/// The adapter keeps its clock and capacity, and versioned adapters start a new history from the restored files.
+	///
+	/// # Errors
+	///
+	/// This function will return an `InvalidData` error if the snapshot is malformed.
+	#[cfg(feature = "snapshots")]
+	pub fn restore(&self, snapshot: &[u8]) -> Result<()> {
+		let stored: StoredState = serde_json::from_slice(snapshot)
+			.map_err(|error| Error::new(ErrorKind::InvalidData, error))?;
+
+		let mut state = self.state_mut();
+		let mut restored = State {
+			versions: state.versions.as_ref().map(|_| HashMap::new()),
+			clock: state.clock.clone(),
+			capacity: state.capacity,
+			..State::default()
+		};
+
+		for directory in stored.directories {
+			restored.create_directory(Path::new(&directory));
+		}
+		for file in stored.files {
+			let mime_type = file
+				.mime_type
+				.map(|mime_type| mime_type.parse())
+				.transpose()
+				.map_err(|error| Error::new(ErrorKind::InvalidData, error))?;
+
+			restored.insert(
+				Path::new(&file.path),
+				File {
+					content: file.content,
+					visibility: if file.public {
+						Visibility::Public
+					} else {
+						Visibility::Private
+					},
+					last_modified: file.last_modified,
+					mime_type,
+					cache_control: file.cache_control,
+					metadata: file.metadata,
+				},
+			);
+		}
+
+		*state = restored;
+		drop(state);
+
+		Ok(())
+	}
+
+	/// Timestamp files with the given clock instead of the system's, so tests can control time.
+	#[must_use]
+	pub fn with_clock<C: Clock + 'static>(self, clock: C) -> Self {
+		self.state_mut().clock = Arc::new(clock);
+		self
+	}
+
+	fn state(&self) -> RwLockReadGuard<'_, State> {
+		self.state.read().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	fn state_mut(&self) -> RwLockWriteGuard<'_, State> {
+		self.state.write().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	/// Run `f` on the file at `path`, failing with `NotFound` if there's none.
+	fn with_file<T>(&self, path: &Path, f: impl FnOnce(&File) -> T) -> Result<T> {
+		self.state()
+			.files
+			.get(path)
+			.map(f)
+			.ok_or_else(|| Error::from(ErrorKind::NotFound))
+	}
+
+	/// Run `f` on the file at `path` to change it, failing with `NotFound` if there's none.
+	fn with_file_mut<T>(&self, path: &Path, f: impl FnOnce(&mut File) -> T) -> Result<T> {
+		self.state_mut()
+			.files
+			.get_mut(path)
+			.map(f)
+			.ok_or_else(|| Error::from(ErrorKind::NotFound))
+	}
 }
 
 impl AdapterInit for MemoryAdapter {
@@ -59,158 +674,369 @@ impl AdapterInit for MemoryAdapter {
 	type Error = Infallible;
 
 	async fn new((): Self::Config) -> std::result::Result<Self, Self::Error> {
-		Ok(Self {
-			files: HashMap::new(),
-			directory: HashMap::new(),
-		})
+		Ok(Self::default())
 	}
 }
 
 impl Adapter for MemoryAdapter {
 	async fn file_exists(&self, path: &Path) -> Result<bool> {
-		Ok(self.files.contains_key(path))
+		self.simulate().await?;
+
+		Ok(self.state().files.contains_key(path))
 	}
 
 	async fn directory_exists(&self, path: &Path) -> Result<bool> {
-		Ok(self.directory.contains_key(path))
+		self.simulate().await?;
+
+		Ok(self.state().directory.contains_key(path))
+	}
+
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.write_with(path, content, &WriteOptions::default())
+			.await
+	}
+
+	async fn write_with(&self, path: &Path, content: &[u8], options: &WriteOptions) -> Result<()> {
+		self.simulate().await?;
+
+		let mut state = self.state_mut();
+		state.ensure_room(path, content.len())?;
+
+		let file = File {
+			visibility: options.visibility.unwrap_or(Visibility::Public),
+			last_modified: state.clock.now(),
+			content: content.as_ref().to_vec(),
+			mime_type: options.content_type.clone(),
+			cache_control: options.cache_control.clone(),
+			metadata: options.metadata.clone(),
+		};
+		state.insert(path, file);
+		drop(state);
+
+		Ok(())
 	}
 
-	async fn write(&mut self, path: &Path, content: &[u8]) -> Result<()> {
-		self.files.insert(
-			path.to_path_buf(),
+	/// Check the precondition and write the file under the same lock, so no other write can slip in between.
+	async fn write_if(
+		&self,
+		path: &Path,
+		content: &[u8],
+		precondition: &Precondition,
+	) -> Result<()> {
+		self.simulate().await?;
+
+		let mut state = self.state_mut();
+
+		let holds = match (precondition, state.files.get(path)) {
+			(Precondition::NotExists, file) => file.is_none(),
+			(Precondition::MatchesChecksum(checksum), Some(file)) => {
+				sha256::digest(file.content.as_slice()) == *checksum
+			},
+			(Precondition::NotModifiedSince(time), Some(file)) => file.last_modified <= *time,
+			(_, None) => false,
+		};
+		if !holds {
+			return Err(precondition::failed(path));
+		}
+		state.ensure_room(path, content.len())?;
+
+		let now = state.clock.now();
+		state.insert(
+			path,
 			File {
 				visibility: Visibility::Public,
-				last_modified: SystemTime::now(),
-				content: content.as_ref().to_vec(),
+				last_modified: now,
+				content: content.to_vec(),
+				mime_type: None,
+				cache_control: None,
+				metadata: HashMap::new(),
 			},
 		);
+		drop(state);
 
-		if let Some(parent) = path.parent() {
-			self.directory
-				.entry(parent.to_path_buf())
-				.or_default()
-				.push(path.to_path_buf());
+		Ok(())
+	}
+
+	/// Write the file directly, since writes happen under a lock and readers never see them half-done.
+	async fn write_atomic(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.write(path, content).await
+	}
+
+	/// Write every file under a single lock, sharing the timestamp.
+	async fn write_many(&self, files: &[(&Path, &[u8])]) -> Vec<(PathBuf, Error)> {
+		if let Err(error) = self.simulate().await {
+			return files
+				.iter()
+				.map(|(path, _)| {
+					(
+						path.to_path_buf(),
+						Error::new(error.kind(), error.to_string()),
+					)
+				})
+				.collect();
+		}
+
+		let mut state = self.state_mut();
+		let now = state.clock.now();
+		let mut failed = Vec::new();
+
+		for &(path, content) in files {
+			if let Err(error) = state.ensure_room(path, content.len()) {
+				failed.push((path.to_path_buf(), error));
+				continue;
+			}
+
+			state.insert(
+				path,
+				File {
+					visibility: Visibility::Public,
+					last_modified: now,
+					content: content.to_vec(),
+					mime_type: None,
+					cache_control: None,
+					metadata: HashMap::new(),
+				},
+			);
+		}
+		drop(state);
+
+		failed
+	}
+
+	async fn append(&self, path: &Path, data: &[u8]) -> Result<()> {
+		self.simulate().await?;
+
+		let mut state = self.state_mut();
+		let now = state.clock.now();
+		let existing = state.files.get(path).map_or(0, |file| file.content.len());
+		state.ensure_room(path, existing + data.len())?;
+
+		if let Some(file) = state.files.get_mut(path) {
+			file.content.extend_from_slice(data);
+			file.last_modified = now;
+
+			let file = file.clone();
+			state.record_version(path, Some(file));
+		} else {
+			state.insert(
+				path,
+				File {
+					visibility: Visibility::Public,
+					last_modified: now,
+					content: data.to_vec(),
+					mime_type: None,
+					cache_control: None,
+					metadata: HashMap::new(),
+				},
+			);
 		}
+		drop(state);
 
 		Ok(())
 	}
 
 	async fn read(&self, path: &Path) -> Result<Contents> {
-		let file = self
-			.files
-			.get(path)
-			.ok_or_else(|| Error::from(ErrorKind::NotFound))?;
+		self.simulate().await?;
 
-		Ok(Contents::from(file.content.clone()))
+		self.with_file(path, |file| Contents::from(file.content.clone()))
 	}
 
-	async fn delete(&mut self, path: &Path) -> Result<()> {
-		if self.files.remove(path).is_none() {
-			return Err(Error::from(ErrorKind::NotFound));
-		}
+	async fn read_into(&self, path: &Path, buf: &mut (dyn BufMut + Send)) -> Result<()> {
+		self.simulate().await?;
 
-		self.directory
-			.entry(path.parent().unwrap().to_path_buf())
-			.or_default()
-			.retain(|file_path| file_path != path);
+		self.with_file(path, |file| {
+			ensure_capacity(buf, file.content.len() as u64)?;
+			buf.put_slice(&file.content);
 
-		Ok(())
+			Ok(())
+		})?
 	}
 
-	async fn delete_directory(&mut self, path: &Path) -> Result<()> {
-		self.directory
-			.remove(path)
-			.ok_or_else(|| Error::from(ErrorKind::NotFound))?;
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		self.simulate().await?;
 
-		self.files
-			.retain(|file_path, _| !file_path.starts_with(path));
+		self.with_file(path, |file| {
+			let start = usize::try_from(offset)
+				.unwrap_or(usize::MAX)
+				.min(file.content.len());
+			let end = usize::try_from(offset.saturating_add(len))
+				.unwrap_or(usize::MAX)
+				.min(file.content.len());
 
-		Ok(())
+			Contents::from(file.content[start..end].to_vec())
+		})
 	}
 
-	async fn create_directory(&mut self, path: &Path) -> Result<()> {
-		let mut current_path = PathBuf::new();
-		for component in path.components() {
-			current_path.push(component);
-			self.directory.entry(current_path.clone()).or_default();
-		}
+	async fn list_versions(&self, prefix: &Path) -> Result<Vec<FileVersion>> {
+		self.simulate().await?;
 
-		Ok(())
+		self.state().versions_under(prefix)
 	}
 
-	async fn set_visibility(&mut self, path: &Path, visibility: Visibility) -> Result<()> {
-		let Some(file) = self.files.get_mut(path) else {
-			return Err(Error::from(ErrorKind::NotFound));
-		};
+	async fn read_version(&self, path: &Path, version_id: &str) -> Result<Contents> {
+		self.simulate().await?;
+
+		self.state()
+			.versions
+			.as_ref()
+			.and_then(|versions| versions.get(path))
+			.and_then(|versions| {
+				versions
+					.iter()
+					.find(|version| version.id.to_string() == version_id)
+			})
+			.and_then(|version| version.file.as_ref())
+			.map(|file| Contents::from(file.content.clone()))
+			.ok_or_else(|| Error::from(ErrorKind::NotFound))
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
+		self.simulate().await?;
+
+		self.state_mut().remove(path)
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
+		self.simulate().await?;
+
+		self.state_mut().remove_directory(path)
+	}
+
+	async fn create_directory(&self, path: &Path) -> Result<()> {
+		self.simulate().await?;
 
-		file.visibility = visibility;
+		self.state_mut().create_directory(path);
 
 		Ok(())
 	}
 
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
+		self.simulate().await?;
+
+		self.with_file_mut(path, |file| file.visibility = visibility)
+	}
+
 	async fn visibility(&self, path: &Path) -> Result<Visibility> {
-		let file = self
-			.files
-			.get(path)
-			.ok_or_else(|| Error::from(ErrorKind::NotFound))?;
+		self.simulate().await?;
+
+		self.with_file(path, |file| file.visibility)
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		self.simulate().await?;
+
+		Ok(self
+			.with_file(path, |file| file.mime_type.clone())
+			.ok()
+			.flatten()
+			.unwrap_or_else(|| mime_guess::from_path(path).first_or_octet_stream()))
+	}
+
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		self.simulate().await?;
+
+		self.with_file(path, |file| file.metadata.clone())
+	}
+
+	async fn set_mime_type(&self, path: &Path, mime_type: &Mime) -> Result<()> {
+		self.simulate().await?;
+
+		self.with_file_mut(path, |file| file.mime_type = Some(mime_type.clone()))
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<std::time::SystemTime> {
+		self.simulate().await?;
+
+		self.with_file(path, |file| file.last_modified)
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		self.simulate().await?;
 
-		Ok(file.visibility)
+		self.with_file(path, |file| file.content.len() as u64)
 	}
 
-	async fn mime_type(&self, path: &Path) -> Result<mime::Mime> {
-		Ok(mime_guess::from_path(path).first_or_octet_stream())
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		self.simulate().await?;
+
+		self.state().files_deep(path, deep)
 	}
 
-	async fn last_modified(&self, path: &Path) -> Result<std::time::SystemTime> {
-		let file = self
-			.files
-			.get(path)
-			.ok_or_else(|| Error::from(ErrorKind::NotFound))?;
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		self.simulate().await?;
 
-		Ok(file.last_modified)
+		self.state().entries(path, deep)
 	}
 
-	async fn file_size(&self, path: &Path) -> Result<u64> {
-		let file = self
-			.files
-			.get(path)
-			.ok_or_else(|| Error::from(ErrorKind::NotFound))?;
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.simulate().await?;
 
-		Ok(file.content.len() as u64)
-	}
+		let mut state = self.state_mut();
+		if !state.files.contains_key(source) && state.directory.contains_key(source) {
+			return state.move_directory(source, destination);
+		}
 
-	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
-		self.get_files_deep(path, deep).await
+		state.copy(source, destination)?;
+		state.remove(source)
 	}
 
-	async fn r#move(&mut self, source: &Path, destination: &Path) -> Result<()> {
-		self.copy(source, destination).await?;
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.simulate().await?;
 
-		self.delete(source).await
+		let mut state = self.state_mut();
+		let size = state.files.get(source).map_or(0, |file| file.content.len());
+		state.ensure_room(destination, size)?;
+
+		state.copy(source, destination)
 	}
 
-	async fn copy(&mut self, source: &Path, destination: &Path) -> Result<()> {
-		let file = self
-			.files
-			.get(source)
-			.ok_or_else(|| Error::from(ErrorKind::NotFound))?;
+	async fn move_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.simulate().await?;
 
-		self.files
-			.insert(destination.to_path_buf(), file.clone().updated_now());
+		ensure_not_nested(source, destination)?;
 
-		if let Some(parent) = destination.parent() {
-			self.directory
-				.entry(parent.to_path_buf())
-				.or_default()
-				.push(destination.to_path_buf());
+		let mut state = self.state_mut();
+		if !state.directory.contains_key(source) {
+			return Err(Error::from(ErrorKind::NotFound));
 		}
 
-		Ok(())
+		state.move_directory(source, destination)
 	}
 
 	async fn checksum(&self, path: &Path) -> Result<String> {
 		Ok(sha256::digest(self.read(path).await?.data))
 	}
+
+	/// Mark every change made so far as durable, when [simulating](MemoryAdapter::simulated) data loss on crashes.
+	async fn flush(&self) -> Result<()> {
+		self.simulate().await?;
+
+		#[cfg(feature = "simulation")]
+		if let Some(simulator) = &self.simulator {
+			let mut durable = simulator
+				.durable
+				.lock()
+				.unwrap_or_else(PoisonError::into_inner);
+			if durable.is_some() {
+				*durable = Some(self.state().clone());
+			}
+		}
+
+		Ok(())
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		#[cfg(feature = "simulation")]
+		if let Some(simulator) = &self.simulator {
+			return vec![AdapterLayer::new("memory").with("seed", simulator.simulation.seed)];
+		}
+
+		vec![AdapterLayer::new("memory")]
+	}
 }
 
 #[cfg(test)]
@@ -219,7 +1045,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_file_exists() {
-		let mut client = MemoryAdapter::new(()).await.unwrap();
+		let client = MemoryAdapter::new(()).await.unwrap();
 
 		assert!(!client
 			.file_exists(Path::new("test_file_exists.txt"))
@@ -244,7 +1070,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_directory_exists() {
-		let mut client = MemoryAdapter::new(()).await.unwrap();
+		let client = MemoryAdapter::new(()).await.unwrap();
 
 		assert!(!client
 			.directory_exists(Path::new("test_directory_exists"))
@@ -269,7 +1095,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_write() {
-		let mut client = MemoryAdapter::new(()).await.unwrap();
+		let client = MemoryAdapter::new(()).await.unwrap();
 
 		assert!(!client
 			.file_exists(Path::new("test_write.txt"))
@@ -289,9 +1115,43 @@ mod tests {
 		client.delete(Path::new("test_write.txt")).await.unwrap();
 	}
 
+	#[tokio::test]
+	async fn test_write_many() {
+		let client = MemoryAdapter::new(()).await.unwrap();
+
+		let failed = client
+			.write_many(&[
+				(Path::new("markers/.keep"), b""),
+				(Path::new("markers/tiny.json"), b"{}"),
+			])
+			.await;
+		assert!(failed.is_empty());
+
+		assert_eq!(
+			client.file_size(Path::new("markers/.keep")).await.unwrap(),
+			0
+		);
+		assert_eq!(
+			client
+				.read(Path::new("markers/tiny.json"))
+				.await
+				.unwrap()
+				.data,
+			b"{}"
+		);
+		assert_eq!(
+			client
+				.list_contents(Path::new("markers"), false)
+				.await
+				.unwrap()
+				.len(),
+			2
+		);
+	}
+
 	#[tokio::test]
 	async fn test_read() {
-		let mut client = MemoryAdapter::new(()).await.unwrap();
+		let client = MemoryAdapter::new(()).await.unwrap();
 
 		client
 			.write(Path::new("test_read.txt"), b"Hello, world!")
@@ -306,9 +1166,132 @@ mod tests {
 		client.delete(Path::new("test_read.txt")).await.unwrap();
 	}
 
+	#[tokio::test]
+	async fn test_read_into() {
+		let client = MemoryAdapter::new(()).await.unwrap();
+
+		client
+			.write(Path::new("test_read_into.txt"), b"Hello, world!")
+			.await
+			.unwrap();
+
+		let mut buf = b"> ".to_vec();
+		client
+			.read_into(Path::new("test_read_into.txt"), &mut buf)
+			.await
+			.unwrap();
+
+		assert_eq!(buf, b"> Hello, world!");
+
+		let mut too_small = [0u8; 4];
+		assert!(client
+			.read_into(
+				Path::new("test_read_into.txt"),
+				&mut too_small.as_mut_slice()
+			)
+			.await
+			.is_err());
+
+		client
+			.delete(Path::new("test_read_into.txt"))
+			.await
+			.unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_write_range() {
+		let client = MemoryAdapter::new(()).await.unwrap();
+
+		client
+			.write(Path::new("test_write_range.txt"), b"Hello, world!")
+			.await
+			.unwrap();
+		client
+			.write_range(Path::new("test_write_range.txt"), 7, b"there!!")
+			.await
+			.unwrap();
+		client
+			.write_range(Path::new("test_write_range.txt"), 16, b"?")
+			.await
+			.unwrap();
+
+		assert!(!client.native_range_writes());
+		assert_eq!(
+			client
+				.read(Path::new("test_write_range.txt"))
+				.await
+				.unwrap()
+				.data,
+			b"Hello, there!!\0\0?"
+		);
+	}
+
+	#[tokio::test]
+	async fn test_append() {
+		let client = MemoryAdapter::new(()).await.unwrap();
+
+		client
+			.append(Path::new("logs/app.log"), b"started\n")
+			.await
+			.unwrap();
+		client
+			.append(Path::new("logs/app.log"), b"stopped\n")
+			.await
+			.unwrap();
+
+		assert_eq!(
+			client.read(Path::new("logs/app.log")).await.unwrap().data,
+			b"started\nstopped\n"
+		);
+		assert_eq!(
+			client
+				.list_contents(Path::new("logs"), false)
+				.await
+				.unwrap(),
+			vec![PathBuf::from("logs/app.log")]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_read_range() {
+		let client = MemoryAdapter::new(()).await.unwrap();
+
+		client
+			.write(Path::new("test_read_range.txt"), b"Hello, world!")
+			.await
+			.unwrap();
+
+		assert_eq!(
+			client
+				.read_range(Path::new("test_read_range.txt"), 7, 5)
+				.await
+				.unwrap()
+				.data,
+			b"world"
+		);
+		assert_eq!(
+			client
+				.read_range(Path::new("test_read_range.txt"), 7, 100)
+				.await
+				.unwrap()
+				.data,
+			b"world!"
+		);
+		assert!(client
+			.read_range(Path::new("test_read_range.txt"), 100, 5)
+			.await
+			.unwrap()
+			.is_empty());
+
+		client
+			.delete(Path::new("test_read_range.txt"))
+			.await
+			.unwrap();
+	}
+
 	#[tokio::test]
 	async fn test_delete() {
-		let mut client = MemoryAdapter::new(()).await.unwrap();
+		let client = MemoryAdapter::new(()).await.unwrap();
 
 		client
 			.write(Path::new("test_delete.txt"), b"Hello, world!")
@@ -325,7 +1308,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_delete_directory() {
-		let mut client = MemoryAdapter::new(()).await.unwrap();
+		let client = MemoryAdapter::new(()).await.unwrap();
 
 		client
 			.create_directory(Path::new("test_delete_directory"))
@@ -350,7 +1333,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_create_directory() {
-		let mut client = MemoryAdapter::new(()).await.unwrap();
+		let client = MemoryAdapter::new(()).await.unwrap();
 
 		assert!(!client
 			.directory_exists(Path::new("test_create_directory"))
@@ -375,7 +1358,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_create_directory_with_parents() {
-		let mut client = MemoryAdapter::new(()).await.unwrap();
+		let client = MemoryAdapter::new(()).await.unwrap();
 
 		assert!(!client
 			.directory_exists(Path::new("test_create_directory_with_parents/test_dir"))
@@ -400,7 +1383,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_set_visibility() {
-		let mut client = MemoryAdapter::new(()).await.unwrap();
+		let client = MemoryAdapter::new(()).await.unwrap();
 
 		client
 			.write(Path::new("test_set_visibility.txt"), &[])
@@ -436,7 +1419,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_visibility() {
-		let mut client = MemoryAdapter::new(()).await.unwrap();
+		let client = MemoryAdapter::new(()).await.unwrap();
 
 		client
 			.write(Path::new("test_visibility.txt"), &[])
@@ -472,7 +1455,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_mime_type() {
-		let mut client = MemoryAdapter::new(()).await.unwrap();
+		let client = MemoryAdapter::new(()).await.unwrap();
 
 		client
 			.write(Path::new("test_mime.txt"), b"Hello, world!")
@@ -489,7 +1472,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_last_modified() {
-		let mut client = MemoryAdapter::new(()).await.unwrap();
+		let client = MemoryAdapter::new(()).await.unwrap();
 
 		client
 			.write(Path::new("test_last_modified.txt"), &[])
@@ -516,7 +1499,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_file_size() {
-		let mut client = MemoryAdapter::new(()).await.unwrap();
+		let client = MemoryAdapter::new(()).await.unwrap();
 
 		client
 			.write(Path::new("test_file_size.txt"), b"Hello, world!")
@@ -539,7 +1522,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_list_contents() {
-		let mut client = MemoryAdapter::new(()).await.unwrap();
+		let client = MemoryAdapter::new(()).await.unwrap();
 
 		client
 			.write(
@@ -580,9 +1563,74 @@ mod tests {
 			.unwrap();
 	}
 
+	#[tokio::test]
+	async fn test_list_contents_after_overwrite() {
+		let client = MemoryAdapter::new(()).await.unwrap();
+
+		client
+			.write(Path::new("notes/todo.txt"), b"one")
+			.await
+			.unwrap();
+		client
+			.write(Path::new("notes/todo.txt"), b"two")
+			.await
+			.unwrap();
+
+		assert_eq!(
+			client
+				.list_contents(Path::new("notes"), false)
+				.await
+				.unwrap(),
+			vec![PathBuf::from("notes/todo.txt")]
+		);
+		assert_eq!(
+			client
+				.list_contents_with_metadata(Path::new("notes"), true)
+				.await
+				.unwrap()
+				.len(),
+			1
+		);
+	}
+
+	#[tokio::test]
+	async fn test_list_contents_with_metadata() {
+		let client = MemoryAdapter::new(()).await.unwrap();
+
+		client
+			.write(Path::new("reports/q1.csv"), b"a,b")
+			.await
+			.unwrap();
+		client
+			.set_visibility(Path::new("reports/q1.csv"), Visibility::Private)
+			.await
+			.unwrap();
+
+		let entries = client
+			.list_contents_with_metadata(Path::new("reports"), false)
+			.await
+			.unwrap();
+
+		assert_eq!(entries.len(), 1);
+		assert_eq!(entries[0].path, PathBuf::from("reports/q1.csv"));
+		assert!(!entries[0].is_dir);
+		assert_eq!(entries[0].size, Some(3));
+		assert_eq!(entries[0].mime, Some(mime::TEXT_CSV));
+		assert_eq!(entries[0].visibility, Some(Visibility::Private));
+		assert_eq!(
+			entries[0].last_modified,
+			Some(
+				client
+					.last_modified(Path::new("reports/q1.csv"))
+					.await
+					.unwrap()
+			)
+		);
+	}
+
 	#[tokio::test]
 	async fn test_move() {
-		let mut client = MemoryAdapter::new(()).await.unwrap();
+		let client = MemoryAdapter::new(()).await.unwrap();
 
 		client
 			.write(Path::new("test_move.txt"), b"Hello, world!")
@@ -619,7 +1667,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_copy() {
-		let mut client = MemoryAdapter::new(()).await.unwrap();
+		let client = MemoryAdapter::new(()).await.unwrap();
 
 		client
 			.write(Path::new("test_copy.txt"), b"Hello, world!")
@@ -656,7 +1704,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_checksum() {
-		let mut client = MemoryAdapter::new(()).await.unwrap();
+		let client = MemoryAdapter::new(()).await.unwrap();
 
 		client
 			.write(Path::new("test_checksum.txt"), b"Hello, world!")
@@ -673,4 +1721,208 @@ mod tests {
 
 		client.delete(Path::new("test_checksum.txt")).await.unwrap();
 	}
+
+	#[tokio::test]
+	async fn test_move_directory() {
+		let client = MemoryAdapter::new(()).await.unwrap();
+
+		client
+			.write(Path::new("drafts/post.md"), b"# Hello")
+			.await
+			.unwrap();
+		client
+			.write(Path::new("drafts/images/cover.png"), b"png")
+			.await
+			.unwrap();
+
+		client
+			.r#move(Path::new("drafts"), Path::new("published/2024"))
+			.await
+			.unwrap();
+
+		assert!(!client.directory_exists(Path::new("drafts")).await.unwrap());
+		assert!(!client
+			.directory_exists(Path::new("drafts/images"))
+			.await
+			.unwrap());
+		assert_eq!(
+			client
+				.read(Path::new("published/2024/images/cover.png"))
+				.await
+				.unwrap()
+				.data,
+			b"png"
+		);
+		assert_eq!(
+			client
+				.list_contents(Path::new("published/2024"), false)
+				.await
+				.unwrap(),
+			vec![PathBuf::from("published/2024/post.md")]
+		);
+		assert_eq!(
+			client
+				.r#move(Path::new("drafts"), Path::new("elsewhere"))
+				.await
+				.unwrap_err()
+				.kind(),
+			ErrorKind::NotFound
+		);
+	}
+
+	#[tokio::test]
+	async fn test_write_with() {
+		let client = MemoryAdapter::new(()).await.unwrap();
+		let options = WriteOptions {
+			visibility: Some(Visibility::Private),
+			content_type: Some(mime::APPLICATION_JSON),
+			cache_control: Some("max-age=60".to_string()),
+			metadata: HashMap::from([("owner".to_string(), "alice".to_string())]),
+			..WriteOptions::default()
+		};
+
+		client
+			.write_with(Path::new("test_write_with.txt"), b"{}", &options)
+			.await
+			.unwrap();
+
+		let path = Path::new("test_write_with.txt");
+		assert_eq!(client.visibility(path).await.unwrap(), Visibility::Private);
+		assert_eq!(
+			client.mime_type(path).await.unwrap(),
+			mime::APPLICATION_JSON
+		);
+		assert_eq!(client.cache_control(path).as_deref(), Some("max-age=60"));
+		assert_eq!(client.metadata(path), Some(options.metadata.clone()));
+	}
+
+	#[cfg(feature = "simulation")]
+	#[tokio::test(start_paused = true)]
+	async fn test_simulated() {
+		/// Write two versions of a file concurrently, returning the one that won.
+		async fn race(seed: u64) -> String {
+			let client = MemoryAdapter::default().simulated(
+				Simulation::new(seed).latency(Duration::from_millis(1), Duration::from_millis(50)),
+			);
+			let path = Path::new("race.txt");
+
+			let (first, second) =
+				tokio::join!(client.write(path, b"first"), client.write(path, b"second"));
+			first.unwrap();
+			second.unwrap();
+
+			String::from_utf8(client.read(path).await.unwrap().data).unwrap()
+		}
+
+		// the same seed always orders the writes the same way, and some seed orders them the other way
+		let winner = race(1).await;
+		assert_eq!(race(1).await, winner);
+		let mut outcomes = Vec::new();
+		for seed in 2..20 {
+			outcomes.push(race(seed).await);
+		}
+		assert!(outcomes.iter().any(|outcome| *outcome != winner));
+
+		let client = MemoryAdapter::default().simulated(Simulation::new(7).failure_rate(1.0));
+		assert!(client.write(Path::new("a.txt"), b"a").await.is_err());
+
+		let client = MemoryAdapter::default().simulated(Simulation::new(7).lose_unflushed_writes());
+		client.write(Path::new("synced.txt"), b"a").await.unwrap();
+		client.flush().await.unwrap();
+		client.write(Path::new("unsynced.txt"), b"b").await.unwrap();
+		client.crash();
+		assert!(client.file_exists(Path::new("synced.txt")).await.unwrap());
+		assert!(!client.file_exists(Path::new("unsynced.txt")).await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_max_capacity() {
+		let client = MemoryAdapter::default().with_max_capacity(10);
+
+		client.write(Path::new("a.txt"), b"12345").await.unwrap();
+		// overwriting a file only counts the difference
+		client.write(Path::new("a.txt"), b"123456").await.unwrap();
+		assert_eq!(
+			client
+				.write(Path::new("b.txt"), b"12345")
+				.await
+				.unwrap_err()
+				.kind(),
+			ErrorKind::StorageFull
+		);
+		assert_eq!(
+			client
+				.append(Path::new("a.txt"), b"12345")
+				.await
+				.unwrap_err()
+				.kind(),
+			ErrorKind::StorageFull
+		);
+		assert!(client
+			.copy(Path::new("a.txt"), Path::new("c.txt"))
+			.await
+			.is_err());
+		assert!(!client.file_exists(Path::new("b.txt")).await.unwrap());
+
+		client.delete(Path::new("a.txt")).await.unwrap();
+		client.write(Path::new("b.txt"), b"12345").await.unwrap();
+	}
+
+	#[cfg(feature = "snapshots")]
+	#[tokio::test]
+	async fn test_snapshot() {
+		let client = MemoryAdapter::default();
+		client
+			.write_with(
+				Path::new("fixtures/user.json"),
+				b"{}",
+				&WriteOptions {
+					visibility: Some(Visibility::Private),
+					content_type: Some(mime::APPLICATION_JSON),
+					..WriteOptions::default()
+				},
+			)
+			.await
+			.unwrap();
+		client.create_directory(Path::new("empty")).await.unwrap();
+		let snapshot = client.snapshot();
+
+		client
+			.delete(Path::new("fixtures/user.json"))
+			.await
+			.unwrap();
+		client.write(Path::new("other.txt"), b"a").await.unwrap();
+		client.restore(&snapshot).unwrap();
+
+		assert_eq!(
+			client
+				.read(Path::new("fixtures/user.json"))
+				.await
+				.unwrap()
+				.data,
+			b"{}"
+		);
+		assert_eq!(
+			client
+				.visibility(Path::new("fixtures/user.json"))
+				.await
+				.unwrap(),
+			Visibility::Private
+		);
+		assert_eq!(
+			client
+				.mime_type(Path::new("fixtures/user.json"))
+				.await
+				.unwrap(),
+			mime::APPLICATION_JSON
+		);
+		assert!(client.directory_exists(Path::new("empty")).await.unwrap());
+		assert!(!client.file_exists(Path::new("other.txt")).await.unwrap());
+		assert_eq!(client.snapshot(), snapshot);
+
+		assert_eq!(
+			client.restore(b"not a snapshot").unwrap_err().kind(),
+			ErrorKind::InvalidData
+		);
+	}
 }
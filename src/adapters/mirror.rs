@@ -0,0 +1,453 @@
+use futures::{future::BoxFuture, io::AsyncRead, stream::BoxStream, AsyncReadExt};
+use mime::Mime;
+use std::{
+	collections::HashMap,
+	io::{Error, ErrorKind, Result},
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex, MutexGuard, PoisonError},
+	time::{Duration, SystemTime},
+};
+use url::Url;
+
+use super::{Adapter, AdapterLayer, TemporaryUrlOptions};
+use crate::{
+	contents::Contents, trait_object_hackyness::AdapterObject, versioning::FileVersion,
+	DirectoryStat, PathModel, StorageEntry, Visibility, WriteOptions,
+};
+
+/// How a [`MirrorAdapter`] handles a mirror failing to apply a change the primary applied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MirrorWrites {
+	/// Stop at the first mirror failing, returning its error. Mirrors after it aren't changed.
+	#[default]
+	FailFast,
+	/// Keep going, and only fail if the primary does. Mirror failures are kept for [`MirrorAdapter::take_failures`].
+	BestEffort,
+}
+
+/// A change a mirror failed to apply, after the primary applied it.
+#[derive(Debug, thiserror::Error)]
+#[error("Mirror {mirror} failed to apply a change to {}: {error}", path.display())]
+pub struct MirrorError {
+	/// The index of the mirror, in the order they were added.
+	pub mirror: usize,
+	/// The path the change was made to.
+	pub path: PathBuf,
+	/// Why the mirror failed.
+	#[source]
+	pub error: Error,
+}
+
+impl From<MirrorError> for Error {
+	fn from(error: MirrorError) -> Self {
+		Self::new(error.error.kind(), error)
+	}
+}
+
+/// An adapter applying every change to a primary adapter and to a list of mirrors (like a second bucket in another region).
+///
+/// Changes are applied to the primary first, then to each mirror in the order they were added. If the primary fails,
+/// mirrors are left untouched. Deleting a file a mirror doesn't have isn't a failure. Streams are buffered,
+/// so they can be written to every adapter.
+///
+/// Reads go to the primary, falling back to the mirrors (in order) when it doesn't have the file.
+/// Metadata and listings always come from the primary.
+///
+/// Failures recorded in [`MirrorWrites::BestEffort`] mode are shared between clones, so a clone can be kept around
+/// to check [`MirrorAdapter::take_failures`] after handing the adapter to a [`Filesystem`](crate::Filesystem).
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct MirrorAdapter<A> {
+	primary: A,
+	mirrors: Vec<Arc<dyn AdapterObject>>,
+	writes: MirrorWrites,
+	failures: Arc<Mutex<Vec<MirrorError>>>,
+}
+
+impl<A: Adapter> MirrorAdapter<A> {
+	/// Apply changes to (and read from) `primary`, and to the mirrors added later.
+	pub fn new(primary: A) -> Self {
+		Self {
+			primary,
+			mirrors: Vec::new(),
+			writes: MirrorWrites::default(),
+			failures: Arc::default(),
+		}
+	}
+
+	/// Add a mirror, getting every change applied to the primary.
+	#[must_use]
+	pub fn with_mirror<M: Adapter + 'static>(mut self, mirror: M) -> Self {
+		self.mirrors.push(Arc::new(mirror));
+		self
+	}
+
+	/// Handle mirror failures with the given strategy, instead of [`MirrorWrites::FailFast`].
+	#[must_use]
+	pub const fn with_writes(mut self, writes: MirrorWrites) -> Self {
+		self.writes = writes;
+		self
+	}
+
+	/// Take the changes mirrors failed to apply in [`MirrorWrites::BestEffort`] mode, oldest first, so they can be retried or reconciled.
+	#[must_use]
+	pub fn take_failures(&self) -> Vec<MirrorError> {
+		std::mem::take(&mut *self.mirror_failures())
+	}
+
+	fn mirror_failures(&self) -> MutexGuard<'_, Vec<MirrorError>> {
+		self.failures.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	/// Apply a change the primary applied to every mirror.
+	async fn mirror<'a>(
+		&'a self,
+		path: &Path,
+		change: impl Fn(&'a dyn AdapterObject) -> BoxFuture<'a, Result<()>> + Send,
+	) -> Result<()> {
+		for (index, mirror) in self.mirrors.iter().enumerate() {
+			let Err(error) = change(mirror.as_ref()).await else {
+				continue;
+			};
+
+			let error = MirrorError {
+				mirror: index,
+				path: path.to_path_buf(),
+				error,
+			};
+
+			match self.writes {
+				MirrorWrites::FailFast => return Err(error.into()),
+				MirrorWrites::BestEffort => self.mirror_failures().push(error),
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Read from the primary, or from the first mirror having the file if the primary doesn't.
+	async fn read_mirrors<'a, T>(
+		&'a self,
+		primary: Result<T>,
+		read: impl Fn(&'a dyn AdapterObject) -> BoxFuture<'a, Result<T>> + Send,
+	) -> Result<T> {
+		let error = match primary {
+			Err(error) if error.kind() == ErrorKind::NotFound => error,
+			primary => return primary,
+		};
+
+		for mirror in &self.mirrors {
+			match read(mirror.as_ref()).await {
+				Err(error) if error.kind() == ErrorKind::NotFound => {},
+				result => return result,
+			}
+		}
+
+		Err(error)
+	}
+}
+
+/// Treat deleting something that's already missing as a success.
+fn deleted(result: Result<()>) -> Result<()> {
+	match result {
+		Err(error) if error.kind() == ErrorKind::NotFound => Ok(()),
+		result => result,
+	}
+}
+
+impl<A: Adapter> Adapter for MirrorAdapter<A> {
+	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		self.primary.file_exists(path).await
+	}
+
+	async fn directory_exists(&self, path: &Path) -> Result<bool> {
+		self.primary.directory_exists(path).await
+	}
+
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.primary.write(path, content).await?;
+		self.mirror(path, |mirror| mirror.write(path, content))
+			.await
+	}
+
+	async fn write_with(&self, path: &Path, content: &[u8], options: &WriteOptions) -> Result<()> {
+		self.primary.write_with(path, content, options).await?;
+		self.mirror(path, |mirror| mirror.write_with(path, content, options))
+			.await
+	}
+
+	async fn write_atomic(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.primary.write_atomic(path, content).await?;
+		self.mirror(path, |mirror| mirror.write_atomic(path, content))
+			.await
+	}
+
+	async fn write_stream(
+		&self,
+		path: &Path,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		let mut content = Vec::new();
+		reader.read_to_end(&mut content).await?;
+
+		Adapter::write(self, path, &content).await
+	}
+
+	async fn write_range(&self, path: &Path, offset: u64, data: &[u8]) -> Result<()> {
+		self.primary.write_range(path, offset, data).await?;
+		self.mirror(path, |mirror| mirror.write_range(path, offset, data))
+			.await
+	}
+
+	async fn append(&self, path: &Path, data: &[u8]) -> Result<()> {
+		self.primary.append(path, data).await?;
+		self.mirror(path, |mirror| mirror.append(path, data)).await
+	}
+
+	fn native_range_writes(&self) -> bool {
+		self.primary.native_range_writes()
+	}
+
+	async fn read(&self, path: &Path) -> Result<Contents> {
+		let primary = self.primary.read(path).await;
+		self.read_mirrors(primary, |mirror| mirror.read(path)).await
+	}
+
+	async fn read_stream(&self, path: &Path) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+		let primary = self.primary.read_stream(path).await;
+		self.read_mirrors(primary, |mirror| mirror.read_stream(path))
+			.await
+	}
+
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		let primary = self.primary.read_range(path, offset, len).await;
+		self.read_mirrors(primary, |mirror| mirror.read_range(path, offset, len))
+			.await
+	}
+
+	async fn list_versions(&self, prefix: &Path) -> Result<Vec<FileVersion>> {
+		self.primary.list_versions(prefix).await
+	}
+
+	async fn read_version(&self, path: &Path, version_id: &str) -> Result<Contents> {
+		self.primary.read_version(path, version_id).await
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
+		self.primary.delete(path).await?;
+		self.mirror(path, |mirror| {
+			Box::pin(async move { deleted(mirror.delete(path).await) })
+		})
+		.await
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
+		self.primary.delete_directory(path).await?;
+		self.mirror(path, |mirror| {
+			Box::pin(async move { deleted(mirror.delete_directory(path).await) })
+		})
+		.await
+	}
+
+	async fn create_directory(&self, path: &Path) -> Result<()> {
+		self.primary.create_directory(path).await?;
+		self.mirror(path, |mirror| mirror.create_directory(path))
+			.await
+	}
+
+	async fn set_mime_type(&self, path: &Path, mime_type: &Mime) -> Result<()> {
+		self.primary.set_mime_type(path, mime_type).await?;
+		self.mirror(path, |mirror| mirror.set_mime_type(path, mime_type))
+			.await
+	}
+
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
+		self.primary.set_visibility(path, visibility).await?;
+		self.mirror(path, |mirror| mirror.set_visibility(path, visibility))
+			.await
+	}
+
+	async fn visibility(&self, path: &Path) -> Result<Visibility> {
+		self.primary.visibility(path).await
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		self.primary.mime_type(path).await
+	}
+
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		self.primary.metadata(path).await
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		self.primary.last_modified(path).await
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		self.primary.file_size(path).await
+	}
+
+	async fn directory_stat(&self, path: &Path) -> Result<DirectoryStat> {
+		self.primary.directory_stat(path).await
+	}
+
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		self.primary.list_contents(path, deep).await
+	}
+
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		self.primary.list_contents_with_metadata(path, deep).await
+	}
+
+	fn list_stream<'a>(&'a self, path: &Path, deep: bool) -> BoxStream<'a, Result<PathBuf>> {
+		self.primary.list_stream(path, deep)
+	}
+
+	fn list_stream_after<'a>(
+		&'a self,
+		path: &Path,
+		deep: bool,
+		after: Option<&Path>,
+	) -> BoxStream<'a, Result<PathBuf>> {
+		self.primary.list_stream_after(path, deep, after)
+	}
+
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.primary.r#move(source, destination).await?;
+		self.mirror(destination, |mirror| mirror.r#move(source, destination))
+			.await
+	}
+
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.primary.copy(source, destination).await?;
+		self.mirror(destination, |mirror| mirror.copy(source, destination))
+			.await
+	}
+
+	async fn move_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.primary.move_directory(source, destination).await?;
+		self.mirror(destination, |mirror| {
+			mirror.move_directory(source, destination)
+		})
+		.await
+	}
+
+	async fn copy_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.primary.copy_directory(source, destination).await?;
+		self.mirror(destination, |mirror| {
+			mirror.copy_directory(source, destination)
+		})
+		.await
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		self.primary.checksum(path).await
+	}
+
+	async fn public_url(&self, path: &Path) -> Result<Url> {
+		self.primary.public_url(path).await
+	}
+
+	async fn temporary_url(&self, path: &Path, expires_in: Duration) -> Result<Url> {
+		self.primary.temporary_url(path, expires_in).await
+	}
+
+	async fn temporary_url_with(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+		options: &TemporaryUrlOptions,
+	) -> Result<Url> {
+		self.primary
+			.temporary_url_with(path, expires_in, options)
+			.await
+	}
+
+	fn path_model(&self) -> PathModel {
+		self.primary.path_model()
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		let mirrors: Vec<String> = self
+			.mirrors
+			.iter()
+			.map(|mirror| {
+				let stack: Vec<String> = mirror.layers().iter().map(ToString::to_string).collect();
+				stack.join(" > ")
+			})
+			.collect();
+
+		AdapterLayer::new("mirror")
+			.with("mirrors", format!("[{}]", mirrors.join(", ")))
+			.with("writes", format!("{:?}", self.writes))
+			.wrapping(&self.primary)
+	}
+
+	async fn prefetch(&self, path: &Path) -> Result<()> {
+		self.primary.prefetch(path).await
+	}
+
+	async fn flush(&self) -> Result<()> {
+		self.primary.flush().await?;
+
+		for mirror in &self.mirrors {
+			mirror.flush().await?;
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::ErrorKind;
+
+	use super::{Adapter, MirrorAdapter, MirrorError, MirrorWrites, Path};
+	use crate::adapters::{MemoryAdapter, ReadOnlyAdapter};
+
+	#[tokio::test]
+	async fn test_mirror() {
+		let adapter = MirrorAdapter::new(MemoryAdapter::default())
+			.with_mirror(MemoryAdapter::default())
+			.with_mirror(ReadOnlyAdapter::new(MemoryAdapter::default()));
+
+		// the read-only mirror fails, after the primary and the first mirror were written to
+		let error = adapter
+			.write(Path::new("a.txt"), b"Hello!")
+			.await
+			.unwrap_err();
+		assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+		assert_eq!(
+			error
+				.get_ref()
+				.and_then(|error| error.downcast_ref::<MirrorError>())
+				.unwrap()
+				.mirror,
+			1
+		);
+		assert!(adapter.mirrors[0]
+			.file_exists(Path::new("a.txt"))
+			.await
+			.unwrap());
+
+		let adapter = adapter.with_writes(MirrorWrites::BestEffort);
+		adapter.write(Path::new("b.txt"), b"Hi!").await.unwrap();
+		adapter.delete(Path::new("a.txt")).await.unwrap();
+		let failures = adapter.take_failures();
+		assert_eq!(failures.len(), 2);
+		assert_eq!(failures[0].path, Path::new("b.txt"));
+		assert!(adapter.take_failures().is_empty());
+
+		// reads fall back to the mirrors when the primary doesn't have the file
+		adapter.primary.delete(Path::new("b.txt")).await.unwrap();
+		assert_eq!(adapter.read(Path::new("b.txt")).await.unwrap().data, b"Hi!");
+		assert_eq!(
+			adapter.read(Path::new("a.txt")).await.unwrap_err().kind(),
+			ErrorKind::NotFound
+		);
+	}
+}
@@ -1,27 +1,110 @@
+use bytes::BufMut;
+use futures::{io::AsyncRead, stream::BoxStream, AsyncReadExt, StreamExt, TryStreamExt};
 use mime::Mime;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
 use std::{
+	cmp::Ordering,
+	collections::{HashMap, HashSet},
 	error::Error,
-	fmt::Debug,
+	fmt::{self, Debug},
 	future::Future,
-	io::Result,
+	io::{self, ErrorKind, Result},
 	path::{Path, PathBuf},
 	time::{Duration, SystemTime},
 };
 use url::Url;
 
+#[cfg(feature = "archive")]
+pub mod archive;
+pub mod cached;
+pub mod codec;
+#[cfg(feature = "compression")]
+pub mod compressed;
+#[cfg(feature = "encryption")]
+pub mod encrypted;
+#[cfg(feature = "http-adapter")]
+pub mod http;
+pub mod idempotent;
+#[cfg(feature = "instrumentation")]
+pub mod instrumented;
 #[cfg(feature = "local")]
 pub mod local;
 pub mod memory;
+pub mod mirror;
+pub mod object_lock;
+#[cfg(feature = "object_store")]
+pub mod object_store;
+pub mod overlay;
+pub mod path_prefixed;
+pub mod read_only;
+pub mod replicated;
+#[cfg(feature = "retry")]
+pub mod retry;
+pub mod revisioned;
 #[cfg(feature = "s3")]
 pub mod s3;
+pub mod sharded;
+pub mod striped;
+#[cfg(feature = "trash")]
+pub mod trash;
+#[cfg(feature = "webdav")]
+pub mod webdav;
 
+#[cfg(feature = "archive")]
+pub use archive::{ArchiveAdapter, CompressedRangeError};
+pub use cached::{CacheStats, CachedAdapter};
+pub use codec::CodecAdapter;
+#[cfg(feature = "compression")]
+pub use compressed::{CompressedAdapter, Compression, Naming};
+#[cfg(feature = "encryption")]
+pub use encrypted::{AesGcmCipher, Cipher, EncryptedAdapter, KeyProvider, TenantKeys};
+#[cfg(feature = "http-adapter")]
+pub use http::HttpAdapter;
+pub use idempotent::IdempotentAdapter;
+#[cfg(feature = "instrumentation")]
+pub use instrumented::{AdapterCall, InstrumentedAdapter, MetricsRecorder};
 #[cfg(feature = "local")]
-pub use local::LocalAdapter;
+pub use local::{DiskUsage, LocalAdapter};
 pub use memory::MemoryAdapter;
+pub use mirror::{MirrorAdapter, MirrorError, MirrorWrites};
+pub use object_lock::{ObjectLockAdapter, Retention, RetentionMode};
+#[cfg(feature = "object_store")]
+pub use object_store::ObjectStoreAdapter;
+pub use overlay::OverlayAdapter;
+pub use path_prefixed::PathPrefixedAdapter;
+pub use read_only::ReadOnlyAdapter;
+pub use replicated::{ReadRouting, ReplicaHealth, ReplicatedAdapter};
+#[cfg(feature = "retry")]
+pub use retry::{RetryAdapter, RetryPolicy};
+pub use revisioned::RevisionedAdapter;
 #[cfg(feature = "s3")]
 pub use s3::S3Adapter;
+pub use sharded::{ShardedAdapter, Sharding};
+pub use striped::StripedAdapter;
+#[cfg(feature = "trash")]
+pub use trash::{Resolution, RestoreError, Tombstone, TrashAdapter};
+#[cfg(feature = "webdav")]
+pub use webdav::WebDavAdapter;
 
-use crate::{contents::Contents, Visibility};
+use crate::{
+	contents::Contents, versioning::FileVersion, DirectoryStat, PathModel, Precondition,
+	StorageEntry, Visibility, WriteOptions,
+};
+
+/// When an adapter checks that its storage is reachable and correctly configured.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+	feature = "config",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(rename_all = "lowercase")
+)]
+pub enum Validation {
+	/// Don't check anything until the first operation, so creating the adapter is cheap and never fails because of the storage.
+	#[default]
+	Lazy,
+	/// Check when the adapter is created, so services can fail fast at startup.
+	Eager,
+}
 
 pub trait AdapterInit: Adapter + Sized + 'static {
 	type Config: Clone + Send + Sized;
@@ -34,6 +117,10 @@ pub trait AdapterInit: Adapter + Sized + 'static {
 }
 
 /// A storage adapter.
+///
+/// Adapters report failures as [`io::Error`]s, which [`Filesystem`](crate::Filesystem) turns into [`FlysystemError`](crate::FlysystemError)s
+/// along with the path and the operation that failed. Adapters that know better can return a `FlysystemError` wrapped in an `io::Error`,
+/// which is passed through as is.
 pub trait Adapter: Debug + Send + Sync {
 	// /// The configuration this adapter requires.
 	// type Config: Clone + Send + Sized;
@@ -48,62 +135,656 @@ pub trait Adapter: Debug + Send + Sync {
 	fn directory_exists(&self, path: &Path) -> impl Future<Output = Result<bool>> + Send;
 
 	/// Write to a file.
-	fn write(&mut self, path: &Path, content: &[u8]) -> impl Future<Output = Result<()>> + Send;
+	///
+	/// Adapters should fail with a [`StorageFull`](ErrorKind::StorageFull) error when the storage is full or a quota is exceeded,
+	/// so callers can tell it apart from other failures.
+	fn write(&self, path: &Path, content: &[u8]) -> impl Future<Output = Result<()>> + Send;
+
+	/// Write to a file with the given options.
+	///
+	/// The default writes the file and then sets its visibility, so adapters that can store the MIME type,
+	/// cache control or metadata of files should override this.
+	fn write_with(
+		&self,
+		path: &Path,
+		content: &[u8],
+		options: &WriteOptions,
+	) -> impl Future<Output = Result<()>> + Send {
+		async move {
+			self.write(path, content).await?;
+
+			if let Some(visibility) = options.visibility {
+				self.set_visibility(path, visibility).await?;
+			}
+
+			Ok(())
+		}
+	}
+
+	/// Write to a file only if `precondition` holds, failing with [`FlysystemError::PreconditionFailed`](crate::FlysystemError::PreconditionFailed)
+	/// (wrapped in an [`io::Error`]) otherwise.
+	///
+	/// The default checks the precondition and then writes the file, so a concurrent write can slip in between.
+	/// Adapters should override this to write conditionally in a single step.
+	fn write_if(
+		&self,
+		path: &Path,
+		content: &[u8],
+		precondition: &Precondition,
+	) -> impl Future<Output = Result<()>> + Send {
+		async move {
+			precondition.check(self, path).await?;
+
+			self.write(path, content).await
+		}
+	}
+
+	/// Write a file so readers never see it half-written.
+	///
+	/// The default writes to a temporary sibling and moves it into place, which is atomic where moves are (like renames on a local disk).
+	/// Adapters whose writes are already atomic should override this to write directly.
+	fn write_atomic(&self, path: &Path, content: &[u8]) -> impl Future<Output = Result<()>> + Send {
+		async move {
+			let temp = crate::atomic::temp_path(path);
+			self.write(&temp, content).await?;
+
+			if let Err(error) = self.r#move(&temp, path).await {
+				let _ = self.delete(&temp).await;
+				return Err(error);
+			}
+
+			Ok(())
+		}
+	}
+
+	/// Write a file from a reader, consuming it until the end.
+	///
+	/// Adapters should override this to write the file as it's read instead of buffering it in memory first,
+	/// and must not leave a partial file behind if the reader fails.
+	fn write_stream(
+		&self,
+		path: &Path,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> impl Future<Output = Result<()>> + Send {
+		async move {
+			let mut contents = Vec::new();
+			reader.read_to_end(&mut contents).await?;
+
+			self.write(path, &contents).await
+		}
+	}
+
+	/// Overwrite part of a file starting at `offset`, growing it (padded with zeros) if needed and creating it if it doesn't exist.
+	///
+	/// Adapters that can update files in place should override this (and [`Adapter::native_range_writes`]),
+	/// since the default rewrites the whole file.
+	fn write_range(
+		&self,
+		path: &Path,
+		offset: u64,
+		data: &[u8],
+	) -> impl Future<Output = Result<()>> + Send {
+		async move {
+			let mut contents = match self.read(path).await {
+				Ok(contents) => contents.data,
+				Err(error) if error.kind() == ErrorKind::NotFound => Vec::new(),
+				Err(error) => return Err(error),
+			};
+			splice(&mut contents, offset, data)?;
+
+			self.write(path, &contents).await
+		}
+	}
+
+	/// Add `data` to the end of a file, creating it if it doesn't exist.
+	///
+	/// Adapters that can append to files in place should override this, since the default rewrites the whole file.
+	fn append(&self, path: &Path, data: &[u8]) -> impl Future<Output = Result<()>> + Send {
+		async move {
+			let mut contents = match self.read(path).await {
+				Ok(contents) => contents.data,
+				Err(error) if error.kind() == ErrorKind::NotFound => Vec::new(),
+				Err(error) => return Err(error),
+			};
+			contents.extend_from_slice(data);
+
+			self.write(path, &contents).await
+		}
+	}
+
+	/// Check if [`Adapter::write_range`] updates files in place, instead of rewriting them whole.
+	fn native_range_writes(&self) -> bool {
+		false
+	}
+
+	/// Check how this adapter stores directories, so [`Filesystem`](crate::Filesystem) can bridge the differences.
+	///
+	/// Adapters wrapping another one should return its path model. The default is [`PathModel::Hierarchical`].
+	fn path_model(&self) -> PathModel {
+		PathModel::Hierarchical
+	}
 
 	/// Read a file.
 	fn read(&self, path: &Path) -> impl Future<Output = Result<Contents>> + Send;
 
+	/// Open a file for reading, without buffering it in memory.
+	///
+	/// Adapters should override this to stream the file, instead of reading all of it before returning a reader.
+	fn read_stream(
+		&self,
+		path: &Path,
+	) -> impl Future<Output = Result<Box<dyn AsyncRead + Send + Unpin>>> + Send {
+		async move {
+			let contents = self.read(path).await?;
+
+			Ok(Box::new(futures::io::Cursor::new(contents.data))
+				as Box<dyn AsyncRead + Send + Unpin>)
+		}
+	}
+
+	/// Read a file into a caller-provided buffer, appending to its existing contents.
+	///
+	/// Adapters should override this to write directly into the buffer instead of allocating the whole file first.
+	fn read_into(
+		&self,
+		path: &Path,
+		buf: &mut (dyn BufMut + Send),
+	) -> impl Future<Output = Result<()>> + Send {
+		async move {
+			let contents = self.read(path).await?;
+			ensure_capacity(buf, contents.len() as u64)?;
+			buf.put_slice(&contents);
+
+			Ok(())
+		}
+	}
+
+	/// Read `len` bytes of a file starting at `offset`, or fewer if the file ends first.
+	///
+	/// Adapters should override this to only fetch the requested range instead of the whole file.
+	/// Adapters storing files compressed may instead fail with an `Unsupported` error, rather than silently reading the whole file.
+	fn read_range(
+		&self,
+		path: &Path,
+		offset: u64,
+		len: u64,
+	) -> impl Future<Output = Result<Contents>> + Send {
+		async move {
+			let contents = self.read(path).await?.data;
+			let start = usize::try_from(offset)
+				.unwrap_or(usize::MAX)
+				.min(contents.len());
+			let end = usize::try_from(offset.saturating_add(len))
+				.unwrap_or(usize::MAX)
+				.min(contents.len());
+
+			Ok(Contents::from(contents[start..end].to_vec()))
+		}
+	}
+
+	/// List every version of every file whose path starts with `prefix`, including the markers left by deletions.
+	///
+	/// Each file's versions should be listed newest first, with its current one marked as [`FileVersion::is_latest`].
+	/// Only adapters backed by versioned storage support this.
+	fn list_versions(
+		&self,
+		prefix: &Path,
+	) -> impl Future<Output = Result<Vec<FileVersion>>> + Send {
+		async move {
+			Err(io::Error::new(
+				ErrorKind::Unsupported,
+				format!(
+					"Listing the versions of {} requires a versioned adapter.",
+					prefix.display()
+				),
+			))
+		}
+	}
+
+	/// Read a specific version of a file.
+	///
+	/// Only adapters backed by versioned storage support this.
+	fn read_version(
+		&self,
+		path: &Path,
+		version_id: &str,
+	) -> impl Future<Output = Result<Contents>> + Send {
+		async move {
+			Err(io::Error::new(
+				ErrorKind::Unsupported,
+				format!(
+					"Reading version {version_id} of {} requires a versioned adapter.",
+					path.display()
+				),
+			))
+		}
+	}
+
+	/// Make a previous version of a file its current one, by writing it as a new version (so the versions after it are kept).
+	///
+	/// By default, the version is read and written back, losing its MIME type and metadata.
+	/// Adapters should override this to copy the version server-side instead.
+	fn restore_version(
+		&self,
+		path: &Path,
+		version_id: &str,
+	) -> impl Future<Output = Result<()>> + Send {
+		async move {
+			let contents = self.read_version(path, version_id).await?;
+
+			self.write(path, &contents).await
+		}
+	}
+
 	/// Delete a file.
-	fn delete(&mut self, path: &Path) -> impl Future<Output = Result<()>> + Send;
+	fn delete(&self, path: &Path) -> impl Future<Output = Result<()>> + Send;
+
+	/// Delete many files, returning the ones that failed along with their errors.
+	///
+	/// Adapters should override this to delete the files concurrently or in batches instead of one by one.
+	fn delete_many(
+		&self,
+		paths: &[PathBuf],
+	) -> impl Future<Output = Vec<(PathBuf, io::Error)>> + Send {
+		async move {
+			let mut failed = Vec::new();
+
+			for path in paths {
+				if let Err(error) = self.delete(path).await {
+					failed.push((path.clone(), error));
+				}
+			}
+
+			failed
+		}
+	}
+
+	/// Write many files, returning the ones that failed along with their errors.
+	///
+	/// Adapters should override this to write the files concurrently or in batches instead of one by one.
+	fn write_many(
+		&self,
+		files: &[(&Path, &[u8])],
+	) -> impl Future<Output = Vec<(PathBuf, io::Error)>> + Send {
+		async move {
+			let mut failed = Vec::new();
+
+			for (path, content) in files {
+				if let Err(error) = self.write(path, content).await {
+					failed.push((path.to_path_buf(), error));
+				}
+			}
+
+			failed
+		}
+	}
 
 	/// Delete a directory.
-	fn delete_directory(&mut self, path: &Path) -> impl Future<Output = Result<()>> + Send;
+	fn delete_directory(&self, path: &Path) -> impl Future<Output = Result<()>> + Send;
 
 	/// Create a directory.
-	fn create_directory(&mut self, path: &Path) -> impl Future<Output = Result<()>> + Send;
+	fn create_directory(&self, path: &Path) -> impl Future<Output = Result<()>> + Send;
 
 	/// Set the visibility of a file.
 	fn set_visibility(
-		&mut self,
+		&self,
 		path: &Path,
 		visibility: Visibility,
 	) -> impl Future<Output = Result<()>> + Send;
 
+	/// Set the visibility of many files, returning the ones that failed along with their errors.
+	///
+	/// Adapters should override this to update the files concurrently or in batches instead of one by one.
+	fn set_visibility_many(
+		&self,
+		paths: &[PathBuf],
+		visibility: Visibility,
+	) -> impl Future<Output = Vec<(PathBuf, io::Error)>> + Send {
+		async move {
+			let mut failed = Vec::new();
+
+			for path in paths {
+				if let Err(error) = self.set_visibility(path, visibility).await {
+					failed.push((path.clone(), error));
+				}
+			}
+
+			failed
+		}
+	}
+
 	/// Get the visibility of a file.
 	fn visibility(&self, path: &Path) -> impl Future<Output = Result<Visibility>> + Send;
 
 	/// Get the MIME type of a file.
 	fn mime_type(&self, path: &Path) -> impl Future<Output = Result<Mime>> + Send;
 
+	/// Get the custom metadata a file was written with (like `x-amz-meta-*` headers on S3), without reading it.
+	///
+	/// Only adapters storing [`WriteOptions::metadata`](crate::WriteOptions::metadata) alongside the file support this.
+	fn metadata(
+		&self,
+		path: &Path,
+	) -> impl Future<Output = Result<HashMap<String, String>>> + Send {
+		async move {
+			Err(io::Error::new(
+				ErrorKind::Unsupported,
+				format!(
+					"The metadata of {} can't be retrieved, since the adapter doesn't store it.",
+					path.display()
+				),
+			))
+		}
+	}
+
+	/// Change the MIME type a file is served with.
+	///
+	/// Only adapters storing it alongside the file (instead of guessing it from the extension) support this.
+	fn set_mime_type(
+		&self,
+		path: &Path,
+		mime_type: &Mime,
+	) -> impl Future<Output = Result<()>> + Send {
+		async move {
+			Err(io::Error::new(
+				ErrorKind::Unsupported,
+				format!(
+					"The MIME type of {} can't be set to {mime_type}, since the adapter doesn't store it.",
+					path.display()
+				),
+			))
+		}
+	}
+
 	/// Get the last modified time of a file.
 	fn last_modified(&self, path: &Path) -> impl Future<Output = Result<SystemTime>> + Send;
 
 	/// Get the size of a file.
 	fn file_size(&self, path: &Path) -> impl Future<Output = Result<u64>> + Send;
 
+	/// Get when a directory was last modified and how many entries it directly contains.
+	///
+	/// The default lists the directory recursively, taking the latest modification time of the files in it,
+	/// so adapters that can tell natively should override this.
+	fn directory_stat(&self, path: &Path) -> impl Future<Output = Result<DirectoryStat>> + Send {
+		async move {
+			let entries = self.list_contents_with_metadata(path, true).await?;
+
+			let children = entries
+				.iter()
+				.filter_map(|entry| entry.path.strip_prefix(path).ok()?.components().next())
+				.collect::<HashSet<_>>()
+				.len();
+
+			Ok(DirectoryStat {
+				last_modified: entries.iter().filter_map(|entry| entry.last_modified).max(),
+				children,
+			})
+		}
+	}
+
 	/// List the contents of a directory.
+	///
+	/// Entries can come in any order (like the order the storage returns them in), [`Filesystem`](crate::Filesystem) sorts them.
 	fn list_contents(
 		&self,
 		path: &Path,
 		deep: bool,
 	) -> impl Future<Output = Result<Vec<PathBuf>>> + Send;
 
+	/// List the contents of a directory along with their metadata.
+	///
+	/// The default asks for the metadata of every listed file, so adapters returning it while listing should override this.
+	fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> impl Future<Output = Result<Vec<StorageEntry>>> + Send {
+		async move {
+			let mut entries = Vec::new();
+
+			for path in self.list_contents(path, deep).await? {
+				if !self.file_exists(&path).await? {
+					entries.push(StorageEntry::directory(path));
+					continue;
+				}
+
+				entries.push(StorageEntry {
+					is_dir: false,
+					size: Some(self.file_size(&path).await?),
+					last_modified: Some(self.last_modified(&path).await?),
+					mime: Some(self.mime_type(&path).await?),
+					visibility: Some(self.visibility(&path).await?),
+					path,
+				});
+			}
+
+			Ok(entries)
+		}
+	}
+
+	/// List the contents of a directory lazily, so huge directories don't have to fit in memory.
+	///
+	/// The default buffers the whole listing first, so adapters that can list in pages should override this.
+	fn list_stream<'a>(&'a self, path: &Path, deep: bool) -> BoxStream<'a, Result<PathBuf>> {
+		let path = path.to_path_buf();
+
+		futures::stream::once(async move { self.list_contents(&path, deep).await })
+			.map_ok(|paths| futures::stream::iter(paths.into_iter().map(Ok)))
+			.try_flatten()
+			.boxed()
+	}
+
+	/// List the contents of a directory lazily, ordered by the bytes of their path relative to `path`,
+	/// skipping the entries up to and including `after` (also relative to `path`), so an interrupted listing can be resumed.
+	///
+	/// The default buffers and sorts the whole listing first, so adapters that list in sorted pages (like S3) should override this.
+	fn list_stream_after<'a>(
+		&'a self,
+		path: &Path,
+		deep: bool,
+		after: Option<&Path>,
+	) -> BoxStream<'a, Result<PathBuf>> {
+		let (path, after) = (path.to_path_buf(), after.map(Path::to_path_buf));
+
+		futures::stream::once(async move {
+			let mut entries = self.list_contents(&path, deep).await?;
+			entries.retain(|entry| {
+				after
+					.as_deref()
+					.is_none_or(|after| key_order(crate::relative_to(entry, &path), after).is_gt())
+			});
+			entries.sort_unstable_by(|a, b| {
+				key_order(crate::relative_to(a, &path), crate::relative_to(b, &path))
+			});
+
+			Ok::<_, io::Error>(entries)
+		})
+		.map_ok(|paths| futures::stream::iter(paths.into_iter().map(Ok)))
+		.try_flatten()
+		.boxed()
+	}
+
 	/// Move a file.
-	fn r#move(
-		&mut self,
+	fn r#move(&self, source: &Path, destination: &Path) -> impl Future<Output = Result<()>> + Send;
+
+	/// Copy a file.
+	fn copy(&self, source: &Path, destination: &Path) -> impl Future<Output = Result<()>> + Send;
+
+	/// Move a directory, along with everything in it.
+	///
+	/// The default moves the files under it one by one and then deletes what's left of it,
+	/// so adapters that can move directories natively should override this.
+	fn move_directory(
+		&self,
 		source: &Path,
 		destination: &Path,
-	) -> impl Future<Output = Result<()>> + Send;
+	) -> impl Future<Output = Result<()>> + Send {
+		async move {
+			for (file, target) in directory_files(self, source, destination).await? {
+				self.r#move(&file, &target).await?;
+			}
 
-	/// Copy a file.
-	fn copy(
-		&mut self,
+			self.delete_directory(source).await
+		}
+	}
+
+	/// Copy a directory, along with everything in it.
+	///
+	/// The default copies the files under it one by one, so adapters that can copy directories natively should override this.
+	fn copy_directory(
+		&self,
 		source: &Path,
 		destination: &Path,
-	) -> impl Future<Output = Result<()>> + Send;
+	) -> impl Future<Output = Result<()>> + Send {
+		async move {
+			for (file, target) in directory_files(self, source, destination).await? {
+				self.copy(&file, &target).await?;
+			}
+
+			Ok(())
+		}
+	}
 
 	/// Get the checksum of a file.
 	fn checksum(&self, path: &Path) -> impl Future<Output = Result<String>> + Send;
+
+	/// Get the public URL of a file.
+	///
+	/// Only adapters implementing [`PublicUrlGenerator`] support this.
+	fn public_url(&self, path: &Path) -> impl Future<Output = Result<Url>> + Send {
+		async move {
+			Err(io::Error::new(
+				ErrorKind::Unsupported,
+				format!(
+					"The adapter can't generate a public URL for {}.",
+					path.display()
+				),
+			))
+		}
+	}
+
+	/// Get a URL granting access to a file until `expires_in` elapses.
+	///
+	/// Only adapters implementing [`TemporaryUrlGenerator`] support this.
+	fn temporary_url(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+	) -> impl Future<Output = Result<Url>> + Send {
+		async move {
+			let _ = expires_in;
+			Err(io::Error::new(
+				ErrorKind::Unsupported,
+				format!(
+					"The adapter can't generate a temporary URL for {}.",
+					path.display()
+				),
+			))
+		}
+	}
+
+	/// Get a URL granting access to a file until `expires_in` elapses, overriding the headers it's served with
+	/// (like a `Content-Disposition` downloading it with a friendly name).
+	///
+	/// The default only supports empty options, generating a regular [temporary URL](Adapter::temporary_url).
+	fn temporary_url_with(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+		options: &TemporaryUrlOptions,
+	) -> impl Future<Output = Result<Url>> + Send {
+		async move {
+			if !options.is_empty() {
+				return Err(io::Error::new(
+					ErrorKind::Unsupported,
+					"The adapter can't override the response headers of temporary URLs.",
+				));
+			}
+
+			self.temporary_url(path, expires_in).await
+		}
+	}
+
+	/// Describe this adapter and the ones it wraps, outermost first, so a stack of decorators can be inspected at runtime.
+	///
+	/// Adapters wrapping another one should report themselves [on top of it](AdapterLayer::wrapping).
+	/// The default reports a single layer, named after the adapter's type.
+	fn layers(&self) -> Vec<AdapterLayer> {
+		let name = std::any::type_name::<Self>();
+		let name = name.split('<').next().unwrap_or(name);
+
+		vec![AdapterLayer::new(name.rsplit("::").next().unwrap_or(name))]
+	}
+
+	/// Pull a file into the caches this adapter keeps (like the ones of [`CachedAdapter`] or the OS page cache), ahead of reads.
+	///
+	/// Adapters wrapping another one should prefetch from it too. The default does nothing, for adapters without caches.
+	fn prefetch(&self, path: &Path) -> impl Future<Output = Result<()>> + Send {
+		let _ = path;
+
+		async { Ok(()) }
+	}
+
+	/// Wait for the work this adapter does in the background (like pending replications) to complete.
+	///
+	/// Adapters wrapping another one should flush it too. The default does nothing.
+	fn flush(&self) -> impl Future<Output = Result<()>> + Send {
+		async { Ok(()) }
+	}
+}
+
+/// An adapter of a filesystem's stack, as reported by [`Adapter::layers`], like `cached(capacity=1048576)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdapterLayer {
+	/// A short name for the adapter, like `s3` or `read_only`.
+	pub name: &'static str,
+	/// The notable settings of the adapter, as key-value pairs. Credentials are never included.
+	pub settings: Vec<(&'static str, String)>,
+}
+
+impl AdapterLayer {
+	/// Describe an adapter without any settings.
+	#[must_use]
+	pub const fn new(name: &'static str) -> Self {
+		Self {
+			name,
+			settings: Vec::new(),
+		}
+	}
+
+	/// Add a setting to the description.
+	#[must_use]
+	pub fn with(mut self, key: &'static str, value: impl fmt::Display) -> Self {
+		self.settings.push((key, value.to_string()));
+		self
+	}
+
+	/// Get the layers of an adapter wrapping `inner`, this one on top.
+	#[must_use]
+	pub fn wrapping(self, inner: &impl Adapter) -> Vec<Self> {
+		let mut layers = vec![self];
+		layers.extend(inner.layers());
+		layers
+	}
+}
+
+impl fmt::Display for AdapterLayer {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(self.name)?;
+		if self.settings.is_empty() {
+			return Ok(());
+		}
+
+		f.write_str("(")?;
+		for (i, (key, value)) in self.settings.iter().enumerate() {
+			if i > 0 {
+				f.write_str(", ")?;
+			}
+			write!(f, "{key}={value}")?;
+		}
+		f.write_str(")")
+	}
 }
 
 pub trait PublicUrlGenerator {
@@ -124,3 +805,242 @@ pub trait TemporaryUrlGenerator {
 		expires_in: Duration,
 	) -> impl Future<Output = Result<Url>> + Send;
 }
+
+/// Headers overriding the ones a file is served with through a [temporary URL](Adapter::temporary_url_with),
+/// without changing the stored file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TemporaryUrlOptions {
+	/// The `Content-Disposition` header, like `attachment; filename="report.pdf"`.
+	pub content_disposition: Option<String>,
+	/// The `Content-Type` header.
+	pub content_type: Option<Mime>,
+	/// The `Cache-Control` header.
+	pub cache_control: Option<String>,
+}
+
+impl TemporaryUrlOptions {
+	/// Download the file as `file_name` instead of the last segment of its path, which may be an opaque ID.
+	#[must_use]
+	pub fn attachment(file_name: &str) -> Self {
+		Self {
+			content_disposition: Some(content_disposition(file_name)),
+			..Self::default()
+		}
+	}
+
+	/// Check if no header is overridden.
+	#[must_use]
+	pub const fn is_empty(&self) -> bool {
+		self.content_disposition.is_none()
+			&& self.content_type.is_none()
+			&& self.cache_control.is_none()
+	}
+}
+
+/// Conditions an upload through a [temporary upload URL](TemporaryUploadUrlGenerator) has to meet, or the storage rejects it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UploadConstraints {
+	/// The content type the file has to be uploaded with.
+	pub content_type: Option<Mime>,
+	/// The exact size of the file, in bytes.
+	pub content_length: Option<u64>,
+}
+
+/// A URL clients (like browsers) can upload a file to directly, without it going through the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemporaryUpload {
+	/// The HTTP method to upload the file with.
+	pub method: String,
+	pub url: Url,
+	/// The headers the upload has to be sent with, since they're part of its signature.
+	pub headers: Vec<(String, String)>,
+}
+
+pub trait TemporaryUploadUrlGenerator {
+	/// Get a URL a file can be uploaded to until `expires_in` elapses, as long as the upload meets the constraints.
+	fn temporary_upload_url(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+		constraints: &UploadConstraints,
+	) -> impl Future<Output = Result<TemporaryUpload>> + Send;
+}
+
+/// Write-once-read-many protection for individual files.
+///
+/// While a file is under retention or a legal hold, it can't be overwritten or deleted.
+pub trait ObjectLock {
+	/// Protect a file until the retention date passes.
+	fn set_retention(
+		&self,
+		path: &Path,
+		retention: Retention,
+	) -> impl Future<Output = Result<()>> + Send;
+
+	/// Get the retention of a file, if it has one.
+	fn retention(&self, path: &Path) -> impl Future<Output = Result<Option<Retention>>> + Send;
+
+	/// Place or remove a legal hold on a file, protecting it indefinitely regardless of its retention.
+	fn set_legal_hold(&self, path: &Path, enabled: bool)
+		-> impl Future<Output = Result<()>> + Send;
+
+	/// Check if a file is under a legal hold.
+	fn legal_hold(&self, path: &Path) -> impl Future<Output = Result<bool>> + Send;
+}
+
+/// Marks adapters that can generate temporary URLs, so generic code can require them at compile time.
+pub trait SupportsTemporaryUrls: Adapter + TemporaryUrlGenerator {}
+
+impl<A: Adapter + TemporaryUrlGenerator> SupportsTemporaryUrls for A {}
+
+/// Marks adapters that can generate temporary upload URLs, so generic code can require them at compile time.
+pub trait SupportsTemporaryUploadUrls: Adapter + TemporaryUploadUrlGenerator {}
+
+impl<A: Adapter + TemporaryUploadUrlGenerator> SupportsTemporaryUploadUrls for A {}
+
+/// Marks adapters that can protect files with object lock, so generic code can require them at compile time.
+pub trait SupportsObjectLock: Adapter + ObjectLock {}
+
+impl<A: Adapter + ObjectLock> SupportsObjectLock for A {}
+
+/// Marks adapters implementing [`Adapter::list_versions`] and [`Adapter::read_version`] instead of returning `Unsupported` errors,
+/// so generic code can require them at compile time.
+///
+/// Whether versions are actually kept still depends on the storage (like versioning being enabled on the bucket).
+pub trait SupportsVersioning: Adapter {}
+
+impl SupportsVersioning for MemoryAdapter {}
+#[cfg(feature = "s3")]
+impl SupportsVersioning for S3Adapter {}
+impl<A: SupportsVersioning> SupportsVersioning for IdempotentAdapter<A> {}
+impl<A: SupportsVersioning> SupportsVersioning for ObjectLockAdapter<A> {}
+impl<A: SupportsVersioning> SupportsVersioning for PathPrefixedAdapter<A> {}
+impl<A: SupportsVersioning> SupportsVersioning for ReadOnlyAdapter<A> {}
+#[cfg(feature = "retry")]
+impl<A: SupportsVersioning> SupportsVersioning for RetryAdapter<A> {}
+
+/// Get the `Content-Disposition` header downloading the response as `file_name`,
+/// with a plain ASCII fallback for clients not supporting encoded names.
+pub(crate) fn content_disposition(file_name: &str) -> String {
+	let fallback: String = file_name
+		.chars()
+		.map(|c| {
+			if c.is_ascii_graphic() && c != '"' && c != '\\' || c == ' ' {
+				c
+			} else {
+				'_'
+			}
+		})
+		.collect();
+
+	format!(
+		"attachment; filename=\"{fallback}\"; filename*=UTF-8''{}",
+		utf8_percent_encode(file_name, NON_ALPHANUMERIC)
+	)
+}
+
+/// Compare two paths by their bytes, the order storages like S3 list keys in (so `a.txt` comes before `a/b.txt`).
+pub(crate) fn key_order(a: &Path, b: &Path) -> Ordering {
+	a.as_os_str().cmp(b.as_os_str())
+}
+
+/// Overwrite the bytes of `contents` starting at `offset` with `data`, padding it with zeros if it's shorter than `offset`.
+pub(crate) fn splice(contents: &mut Vec<u8>, offset: u64, data: &[u8]) -> Result<()> {
+	let start = usize::try_from(offset).map_err(|e| io::Error::new(ErrorKind::FileTooLarge, e))?;
+	let end = start
+		.checked_add(data.len())
+		.ok_or_else(|| io::Error::from(ErrorKind::FileTooLarge))?;
+
+	if contents.len() < end {
+		contents.resize(end, 0);
+	}
+	contents[start..end].copy_from_slice(data);
+
+	Ok(())
+}
+
+/// Write a file with options so readers never see it half-written, for decorators that can't pass their options to [`Adapter::write_atomic`].
+///
+/// Like its default, this writes to a temporary sibling and moves it into place.
+pub(crate) async fn write_atomic_with<A: Adapter + ?Sized>(
+	adapter: &A,
+	path: &Path,
+	content: &[u8],
+	options: &WriteOptions,
+) -> Result<()> {
+	let temp = crate::atomic::temp_path(path);
+	adapter.write_with(&temp, content, options).await?;
+
+	if let Err(error) = adapter.r#move(&temp, path).await {
+		let _ = adapter.delete(&temp).await;
+		return Err(error);
+	}
+
+	Ok(())
+}
+
+/// Make sure the buffer can hold `len` more bytes, since writing past its capacity would panic.
+pub(crate) fn ensure_capacity(buf: &(dyn BufMut + Send), len: u64) -> Result<()> {
+	if (buf.remaining_mut() as u64) < len {
+		return Err(io::Error::new(
+			ErrorKind::InvalidInput,
+			"The buffer is too small to hold the file contents.",
+		));
+	}
+
+	Ok(())
+}
+
+/// List the files under the directory at `source`, along with where they end up when it's moved or copied to `destination`.
+async fn directory_files<A: Adapter + ?Sized>(
+	adapter: &A,
+	source: &Path,
+	destination: &Path,
+) -> Result<Vec<(PathBuf, PathBuf)>> {
+	ensure_not_nested(source, destination)?;
+	if !adapter.directory_exists(source).await? {
+		return Err(io::Error::new(
+			ErrorKind::NotFound,
+			format!("The directory {} doesn't exist.", source.display()),
+		));
+	}
+
+	adapter
+		.list_contents_with_metadata(source, true)
+		.await?
+		.into_iter()
+		.filter(|entry| !entry.is_dir)
+		.map(|entry| {
+			// bail out instead of skipping files, since moves delete the source directory afterwards
+			let relative = entry.path.strip_prefix(source).map_err(|_| {
+				io::Error::new(
+					ErrorKind::InvalidData,
+					format!(
+						"The adapter listed {} outside of {}.",
+						entry.path.display(),
+						source.display()
+					),
+				)
+			})?;
+
+			let target = destination.join(relative);
+
+			Ok((entry.path, target))
+		})
+		.collect()
+}
+
+/// Fail if `destination` is `source` or inside it, since a directory can't be moved or copied into itself.
+pub(crate) fn ensure_not_nested(source: &Path, destination: &Path) -> Result<()> {
+	if destination.starts_with(source) {
+		return Err(io::Error::new(
+			ErrorKind::InvalidInput,
+			format!(
+				"The directory {} can't be moved or copied into itself.",
+				source.display()
+			),
+		));
+	}
+
+	Ok(())
+}
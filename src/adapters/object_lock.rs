@@ -0,0 +1,676 @@
+use bytes::BufMut;
+use futures::{io::AsyncRead, stream::BoxStream};
+use mime::Mime;
+use std::{
+	collections::{BTreeSet, HashMap},
+	io::{Error, ErrorKind, Result},
+	path::{Path, PathBuf},
+	sync::{Arc, Mutex, MutexGuard, PoisonError},
+	time::{Duration, SystemTime},
+};
+use url::Url;
+
+use super::{Adapter, AdapterLayer, ObjectLock, TemporaryUrlOptions};
+use crate::{
+	clock::{Clock, SystemClock},
+	contents::Contents,
+	versioning::FileVersion,
+	DirectoryStat, PathModel, StorageEntry, Visibility, WriteOptions,
+};
+
+/// How strictly a retention protects a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetentionMode {
+	/// The retention can be shortened or removed by privileged users.
+	Governance,
+	/// The retention can't be shortened or removed by anyone until it expires.
+	Compliance,
+}
+
+/// A retention period protecting a file from being overwritten or deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Retention {
+	pub mode: RetentionMode,
+	pub retain_until: SystemTime,
+}
+
+impl Retention {
+	/// Check if the retention still protects the file.
+	#[must_use]
+	pub fn is_active(&self) -> bool {
+		self.is_active_at(SystemTime::now())
+	}
+
+	/// Check if the retention protects the file at the given time.
+	#[must_use]
+	pub fn is_active_at(&self, now: SystemTime) -> bool {
+		self.retain_until > now
+	}
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Lock {
+	retention: Option<Retention>,
+	legal_hold: bool,
+}
+
+impl Lock {
+	fn is_active_at(&self, now: SystemTime) -> bool {
+		self.legal_hold
+			|| self
+				.retention
+				.is_some_and(|retention| retention.is_active_at(now))
+	}
+}
+
+/// An adapter emulating object lock for backends that don't support it natively, by refusing to overwrite or delete locked files.
+///
+/// Locks are kept in memory and shared between clones, so a clone can be kept around to manage them after handing the adapter to a [`Filesystem`](crate::Filesystem).
+/// An [immutability window](Self::with_immutability_window) can also protect every file for a while after it's written.
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct ObjectLockAdapter<A> {
+	inner: A,
+	locks: Arc<Mutex<HashMap<PathBuf, Lock>>>,
+	clock: Arc<dyn Clock>,
+	immutability_window: Option<Duration>,
+}
+
+impl<A: Adapter> ObjectLockAdapter<A> {
+	/// Add object lock emulation to an adapter.
+	pub fn new(inner: A) -> Self {
+		Self {
+			inner,
+			locks: Arc::default(),
+			clock: Arc::new(SystemClock),
+			immutability_window: None,
+		}
+	}
+
+	/// Refuse to overwrite or delete files until they're older than `window` (going by their last modified time),
+	/// protecting freshly written data from being reprocessed or cleaned up by mistake.
+	#[must_use]
+	pub const fn with_immutability_window(mut self, window: Duration) -> Self {
+		self.immutability_window = Some(window);
+		self
+	}
+
+	/// Check retention periods against the given clock instead of the system's, so tests can control time.
+	#[must_use]
+	pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+		self.clock = Arc::new(clock);
+		self
+	}
+
+	fn locks(&self) -> MutexGuard<'_, HashMap<PathBuf, Lock>> {
+		self.locks.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	/// Fail if the file at `path` (or any file under it, when it's a directory) is locked.
+	fn ensure_unlocked(&self, path: &Path) -> Result<()> {
+		let now = self.clock.now();
+		let locked = self
+			.locks()
+			.iter()
+			.find(|(locked, lock)| locked.starts_with(path) && lock.is_active_at(now))
+			.map(|(locked, _)| locked.clone());
+
+		if let Some(locked) = locked {
+			return Err(Error::new(
+				ErrorKind::PermissionDenied,
+				format!(
+					"The file {} is locked and can't be modified.",
+					locked.display()
+				),
+			));
+		}
+
+		Ok(())
+	}
+
+	/// Fail if the file at `path` is locked, or was last modified within the immutability window.
+	async fn ensure_modifiable(&self, path: &Path) -> Result<()> {
+		self.ensure_unlocked(path)?;
+
+		let Some(window) = self.immutability_window else {
+			return Ok(());
+		};
+
+		match self.inner.last_modified(path).await {
+			Ok(last_modified) => self.ensure_settled(path, last_modified, window),
+			Err(error) if error.kind() == ErrorKind::NotFound => Ok(()),
+			Err(error) => Err(error),
+		}
+	}
+
+	/// Fail if any file under the directory at `path` is locked, or was last modified within the immutability window.
+	async fn ensure_directory_modifiable(&self, path: &Path) -> Result<()> {
+		self.ensure_unlocked(path)?;
+
+		let Some(window) = self.immutability_window else {
+			return Ok(());
+		};
+
+		let entries = match self.inner.list_contents_with_metadata(path, true).await {
+			Ok(entries) => entries,
+			Err(error) if error.kind() == ErrorKind::NotFound => return Ok(()),
+			Err(error) => return Err(error),
+		};
+
+		for entry in entries.iter().filter(|entry| !entry.is_dir) {
+			if let Some(last_modified) = entry.last_modified {
+				self.ensure_settled(&entry.path, last_modified, window)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	fn ensure_settled(
+		&self,
+		path: &Path,
+		last_modified: SystemTime,
+		window: Duration,
+	) -> Result<()> {
+		if self.clock.now() < last_modified + window {
+			return Err(Error::new(
+				ErrorKind::PermissionDenied,
+				format!(
+					"The file {} was written less than {}s ago and can't be modified yet.",
+					path.display(),
+					window.as_secs()
+				),
+			));
+		}
+
+		Ok(())
+	}
+}
+
+impl<A: Adapter> ObjectLock for ObjectLockAdapter<A> {
+	async fn set_retention(&self, path: &Path, retention: Retention) -> Result<()> {
+		let now = self.clock.now();
+		let mut locks = self.locks();
+		let lock = locks.entry(path.to_path_buf()).or_default();
+
+		if lock.retention.is_some_and(|current| {
+			current.mode == RetentionMode::Compliance
+				&& current.is_active_at(now)
+				&& (retention.mode != RetentionMode::Compliance
+					|| retention.retain_until < current.retain_until)
+		}) {
+			return Err(Error::new(
+				ErrorKind::PermissionDenied,
+				"A compliance retention can't be shortened or removed.",
+			));
+		}
+
+		lock.retention = Some(retention);
+		drop(locks);
+
+		Ok(())
+	}
+
+	async fn retention(&self, path: &Path) -> Result<Option<Retention>> {
+		Ok(self.locks().get(path).and_then(|lock| lock.retention))
+	}
+
+	async fn set_legal_hold(&self, path: &Path, enabled: bool) -> Result<()> {
+		self.locks()
+			.entry(path.to_path_buf())
+			.or_default()
+			.legal_hold = enabled;
+
+		Ok(())
+	}
+
+	async fn legal_hold(&self, path: &Path) -> Result<bool> {
+		Ok(self.locks().get(path).is_some_and(|lock| lock.legal_hold))
+	}
+}
+
+impl<A: Adapter> Adapter for ObjectLockAdapter<A> {
+	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		self.inner.file_exists(path).await
+	}
+
+	async fn directory_exists(&self, path: &Path) -> Result<bool> {
+		self.inner.directory_exists(path).await
+	}
+
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.ensure_modifiable(path).await?;
+
+		self.inner.write(path, content).await
+	}
+
+	async fn write_with(&self, path: &Path, content: &[u8], options: &WriteOptions) -> Result<()> {
+		self.ensure_modifiable(path).await?;
+
+		self.inner.write_with(path, content, options).await
+	}
+
+	async fn write_atomic(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.ensure_modifiable(path).await?;
+
+		self.inner.write_atomic(path, content).await
+	}
+
+	async fn write_stream(
+		&self,
+		path: &Path,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		self.ensure_modifiable(path).await?;
+
+		self.inner.write_stream(path, reader).await
+	}
+
+	async fn write_range(&self, path: &Path, offset: u64, data: &[u8]) -> Result<()> {
+		self.ensure_modifiable(path).await?;
+
+		self.inner.write_range(path, offset, data).await
+	}
+
+	async fn append(&self, path: &Path, data: &[u8]) -> Result<()> {
+		self.ensure_modifiable(path).await?;
+
+		self.inner.append(path, data).await
+	}
+
+	fn native_range_writes(&self) -> bool {
+		self.inner.native_range_writes()
+	}
+
+	async fn read(&self, path: &Path) -> Result<Contents> {
+		self.inner.read(path).await
+	}
+
+	async fn read_stream(&self, path: &Path) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+		self.inner.read_stream(path).await
+	}
+
+	async fn read_into(&self, path: &Path, buf: &mut (dyn BufMut + Send)) -> Result<()> {
+		self.inner.read_into(path, buf).await
+	}
+
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		self.inner.read_range(path, offset, len).await
+	}
+
+	async fn list_versions(&self, prefix: &Path) -> Result<Vec<FileVersion>> {
+		self.inner.list_versions(prefix).await
+	}
+
+	async fn read_version(&self, path: &Path, version_id: &str) -> Result<Contents> {
+		self.inner.read_version(path, version_id).await
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
+		self.ensure_modifiable(path).await?;
+		self.inner.delete(path).await?;
+
+		self.locks().remove(path);
+		Ok(())
+	}
+
+	async fn delete_many(&self, paths: &[PathBuf]) -> Vec<(PathBuf, Error)> {
+		let mut failed = Vec::new();
+		let mut unlocked = Vec::new();
+		for path in paths {
+			match self.ensure_modifiable(path).await {
+				Ok(()) => unlocked.push(path.clone()),
+				Err(error) => failed.push((path.clone(), error)),
+			}
+		}
+
+		let mut deleted: BTreeSet<PathBuf> = unlocked.iter().cloned().collect();
+		for (path, error) in self.inner.delete_many(&unlocked).await {
+			deleted.remove(&path);
+			failed.push((path, error));
+		}
+
+		self.locks().retain(|locked, _| !deleted.contains(locked));
+		failed
+	}
+
+	async fn write_many(&self, files: &[(&Path, &[u8])]) -> Vec<(PathBuf, Error)> {
+		let mut failed = Vec::new();
+		let mut unlocked = Vec::new();
+		for &(path, content) in files {
+			match self.ensure_modifiable(path).await {
+				Ok(()) => unlocked.push((path, content)),
+				Err(error) => failed.push((path.to_path_buf(), error)),
+			}
+		}
+
+		failed.extend(self.inner.write_many(&unlocked).await);
+		failed
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
+		self.ensure_directory_modifiable(path).await?;
+		self.inner.delete_directory(path).await?;
+
+		self.locks().retain(|locked, _| !locked.starts_with(path));
+		Ok(())
+	}
+
+	async fn create_directory(&self, path: &Path) -> Result<()> {
+		self.inner.create_directory(path).await
+	}
+
+	async fn set_mime_type(&self, path: &Path, mime_type: &Mime) -> Result<()> {
+		self.inner.set_mime_type(path, mime_type).await
+	}
+
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
+		self.inner.set_visibility(path, visibility).await
+	}
+
+	async fn set_visibility_many(
+		&self,
+		paths: &[PathBuf],
+		visibility: Visibility,
+	) -> Vec<(PathBuf, Error)> {
+		self.inner.set_visibility_many(paths, visibility).await
+	}
+
+	async fn visibility(&self, path: &Path) -> Result<Visibility> {
+		self.inner.visibility(path).await
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		self.inner.mime_type(path).await
+	}
+
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		self.inner.metadata(path).await
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		self.inner.last_modified(path).await
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		self.inner.file_size(path).await
+	}
+
+	async fn directory_stat(&self, path: &Path) -> Result<DirectoryStat> {
+		self.inner.directory_stat(path).await
+	}
+
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		self.inner.list_contents(path, deep).await
+	}
+
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		self.inner.list_contents_with_metadata(path, deep).await
+	}
+
+	fn list_stream<'a>(&'a self, path: &Path, deep: bool) -> BoxStream<'a, Result<PathBuf>> {
+		self.inner.list_stream(path, deep)
+	}
+
+	fn list_stream_after<'a>(
+		&'a self,
+		path: &Path,
+		deep: bool,
+		after: Option<&Path>,
+	) -> BoxStream<'a, Result<PathBuf>> {
+		self.inner.list_stream_after(path, deep, after)
+	}
+
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.ensure_modifiable(source).await?;
+		self.ensure_modifiable(destination).await?;
+
+		self.inner.r#move(source, destination).await
+	}
+
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.ensure_modifiable(destination).await?;
+
+		self.inner.copy(source, destination).await
+	}
+
+	async fn move_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.ensure_directory_modifiable(source).await?;
+		self.ensure_directory_modifiable(destination).await?;
+		self.inner.move_directory(source, destination).await?;
+
+		self.locks().retain(|locked, _| !locked.starts_with(source));
+		Ok(())
+	}
+
+	async fn copy_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.ensure_directory_modifiable(destination).await?;
+
+		self.inner.copy_directory(source, destination).await
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		self.inner.checksum(path).await
+	}
+
+	async fn public_url(&self, path: &Path) -> Result<Url> {
+		self.inner.public_url(path).await
+	}
+
+	async fn temporary_url(&self, path: &Path, expires_in: Duration) -> Result<Url> {
+		self.inner.temporary_url(path, expires_in).await
+	}
+
+	async fn temporary_url_with(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+		options: &TemporaryUrlOptions,
+	) -> Result<Url> {
+		self.inner
+			.temporary_url_with(path, expires_in, options)
+			.await
+	}
+
+	fn path_model(&self) -> PathModel {
+		self.inner.path_model()
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		AdapterLayer::new("object_lock").wrapping(&self.inner)
+	}
+
+	async fn prefetch(&self, path: &Path) -> Result<()> {
+		self.inner.prefetch(path).await
+	}
+
+	async fn flush(&self) -> Result<()> {
+		self.inner.flush().await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::*;
+	use crate::{adapters::MemoryAdapter, clock::MockClock, Filesystem};
+
+	#[tokio::test]
+	async fn test_legal_hold() {
+		let adapter = ObjectLockAdapter::new(MemoryAdapter::default());
+		let locks = adapter.clone();
+		let filesystem = Filesystem::from_adapter(adapter);
+
+		filesystem
+			.write(Path::new("evidence/report.pdf"), b"%PDF")
+			.await
+			.unwrap();
+		locks
+			.set_legal_hold(Path::new("evidence/report.pdf"), true)
+			.await
+			.unwrap();
+
+		for error in [
+			filesystem
+				.write(Path::new("evidence/report.pdf"), b"tampered")
+				.await,
+			filesystem.delete(Path::new("evidence/report.pdf")).await,
+			filesystem.delete_directory(Path::new("evidence")).await,
+			filesystem
+				.r#move(Path::new("evidence/report.pdf"), Path::new("report.pdf"))
+				.await,
+		] {
+			assert_eq!(error.unwrap_err().kind(), ErrorKind::PermissionDenied);
+		}
+
+		locks
+			.set_legal_hold(Path::new("evidence/report.pdf"), false)
+			.await
+			.unwrap();
+		filesystem
+			.delete(Path::new("evidence/report.pdf"))
+			.await
+			.unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_retention() {
+		let adapter = ObjectLockAdapter::new(MemoryAdapter::default());
+		let locks = adapter.clone();
+		let filesystem = Filesystem::from_adapter(adapter);
+
+		filesystem
+			.write(Path::new("ledger.csv"), b"1,2,3")
+			.await
+			.unwrap();
+
+		let retention = Retention {
+			mode: RetentionMode::Compliance,
+			retain_until: SystemTime::now() + Duration::from_hours(1),
+		};
+		locks
+			.set_retention(Path::new("ledger.csv"), retention)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			locks.retention(Path::new("ledger.csv")).await.unwrap(),
+			Some(retention)
+		);
+		assert_eq!(
+			filesystem
+				.delete(Path::new("ledger.csv"))
+				.await
+				.unwrap_err()
+				.kind(),
+			ErrorKind::PermissionDenied
+		);
+		assert_eq!(
+			locks
+				.set_retention(
+					Path::new("ledger.csv"),
+					Retention {
+						mode: RetentionMode::Governance,
+						retain_until: SystemTime::now(),
+					},
+				)
+				.await
+				.unwrap_err()
+				.kind(),
+			ErrorKind::PermissionDenied
+		);
+
+		filesystem
+			.write(Path::new("other.csv"), b"4,5,6")
+			.await
+			.unwrap();
+		locks
+			.set_retention(
+				Path::new("other.csv"),
+				Retention {
+					mode: RetentionMode::Governance,
+					retain_until: SystemTime::now() - Duration::from_secs(1),
+				},
+			)
+			.await
+			.unwrap();
+		filesystem.delete(Path::new("other.csv")).await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_retention_expires() {
+		let clock = MockClock::default();
+		let adapter = ObjectLockAdapter::new(MemoryAdapter::default()).with_clock(clock.clone());
+		let locks = adapter.clone();
+		let filesystem = Filesystem::from_adapter(adapter);
+
+		filesystem
+			.write(Path::new("ledger.csv"), b"1,2,3")
+			.await
+			.unwrap();
+		locks
+			.set_retention(
+				Path::new("ledger.csv"),
+				Retention {
+					mode: RetentionMode::Compliance,
+					retain_until: clock.now() + Duration::from_hours(24),
+				},
+			)
+			.await
+			.unwrap();
+
+		clock.advance(Duration::from_hours(23));
+		assert_eq!(
+			filesystem
+				.delete(Path::new("ledger.csv"))
+				.await
+				.unwrap_err()
+				.kind(),
+			ErrorKind::PermissionDenied
+		);
+
+		clock.advance(Duration::from_hours(1));
+		filesystem.delete(Path::new("ledger.csv")).await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_immutability_window() {
+		let clock = MockClock::default();
+		let adapter = ObjectLockAdapter::new(MemoryAdapter::default().with_clock(clock.clone()))
+			.with_clock(clock.clone())
+			.with_immutability_window(Duration::from_hours(1));
+		let filesystem = Filesystem::from_adapter(adapter);
+
+		filesystem
+			.write(Path::new("ingest/batch.json"), b"[]")
+			.await
+			.unwrap();
+
+		clock.advance(Duration::from_mins(30));
+		for error in [
+			filesystem
+				.write(Path::new("ingest/batch.json"), b"[1]")
+				.await,
+			filesystem.delete(Path::new("ingest/batch.json")).await,
+			filesystem.delete_directory(Path::new("ingest")).await,
+		] {
+			assert_eq!(error.unwrap_err().kind(), ErrorKind::PermissionDenied);
+		}
+		// new files can still be written
+		filesystem
+			.write(Path::new("ingest/other.json"), b"[]")
+			.await
+			.unwrap();
+
+		clock.advance(Duration::from_mins(30));
+		filesystem
+			.delete(Path::new("ingest/batch.json"))
+			.await
+			.unwrap();
+	}
+}
@@ -0,0 +1,511 @@
+use futures::{io::AsyncRead, AsyncReadExt, StreamExt, TryStreamExt};
+use mime::Mime;
+use object_store::{
+	path::Path as ObjectPath, Attribute, Attributes, GetOptions, ObjectMeta, ObjectStore,
+	PutOptions, PutPayload,
+};
+use std::{
+	collections::{BTreeMap, HashMap},
+	convert::Infallible,
+	io::{Error, ErrorKind, Result},
+	path::{Path, PathBuf},
+	str::FromStr,
+	sync::Arc,
+	time::SystemTime,
+};
+
+use super::{Adapter, AdapterInit, AdapterLayer};
+use crate::{contents::Contents, PathModel, StorageEntry, Visibility, WriteOptions};
+
+/// The size of the parts streamed writes are uploaded in (the minimum most object stores accept).
+const PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// An adapter storing files on any [`ObjectStore`], so the stores (and credentials) configured for the Arrow/DataFusion ecosystem can be reused.
+///
+/// Object stores have no directories, so creating one does nothing and a directory exists as long as files are stored under it.
+/// They have no ACLs either, so visibility is unsupported. MIME types, cache control and metadata are stored as object attributes,
+/// and only work on stores supporting them.
+///
+/// ```rust,ignore
+/// let store = object_store::aws::AmazonS3Builder::from_env().with_bucket_name("my-bucket").build()?;
+/// let filesystem = Filesystem::new::<ObjectStoreAdapter>(Arc::new(store)).await?;
+/// ```
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct ObjectStoreAdapter {
+	store: Arc<dyn ObjectStore>,
+}
+
+impl AdapterInit for ObjectStoreAdapter {
+	type Config = Arc<dyn ObjectStore>;
+	type Error = Infallible;
+
+	async fn new(store: Self::Config) -> std::result::Result<Self, Self::Error> {
+		Ok(Self { store })
+	}
+}
+
+impl ObjectStoreAdapter {
+	/// Get the store files are stored on, to use it directly.
+	#[must_use]
+	pub fn store(&self) -> Arc<dyn ObjectStore> {
+		Arc::clone(&self.store)
+	}
+
+	async fn head(&self, path: &Path) -> Result<ObjectMeta> {
+		self.store.head(&location(path)?).await.map_err(io_error)
+	}
+
+	/// Get the attributes of a file, without reading it.
+	async fn attributes(&self, path: &Path) -> Result<Attributes> {
+		let options = GetOptions {
+			head: true,
+			..GetOptions::default()
+		};
+
+		Ok(self
+			.store
+			.get_opts(&location(path)?, options)
+			.await
+			.map_err(io_error)?
+			.attributes)
+	}
+
+	/// Upload a stream in parts, aborting the upload if the reader fails.
+	async fn write_parts(
+		&self,
+		location: &ObjectPath,
+		first: Vec<u8>,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		let mut upload = self.store.put_multipart(location).await.map_err(io_error)?;
+
+		let mut part = first;
+		let result = loop {
+			if let Err(error) = upload.put_part(PutPayload::from(part)).await {
+				break Err(io_error(error));
+			}
+
+			part = match read_part(reader).await {
+				Ok(part) if part.is_empty() => break Ok(()),
+				Ok(part) => part,
+				Err(error) => break Err(error),
+			};
+		};
+
+		if let Err(error) = result {
+			upload.abort().await.ok();
+			return Err(error);
+		}
+
+		upload.complete().await.map_err(io_error)?;
+		Ok(())
+	}
+}
+
+/// Get the location of a file on the store.
+fn location(path: &Path) -> Result<ObjectPath> {
+	let path = path
+		.to_str()
+		.ok_or_else(|| Error::new(ErrorKind::InvalidData, "path is not valid utf-8"))?;
+
+	ObjectPath::parse(path).map_err(|e| Error::new(ErrorKind::InvalidInput, e))
+}
+
+/// Get the prefix listing the directory at `path`, or `None` for the root.
+fn prefix(path: &Path) -> Result<Option<ObjectPath>> {
+	let location = location(path)?;
+
+	Ok((location.as_ref() != "").then_some(location))
+}
+
+/// Read up to [`PART_SIZE`] bytes, returning fewer only once the reader ends.
+async fn read_part(reader: &mut (dyn AsyncRead + Send + Unpin)) -> Result<Vec<u8>> {
+	let mut part = Vec::with_capacity(PART_SIZE);
+	reader.take(PART_SIZE as u64).read_to_end(&mut part).await?;
+
+	Ok(part)
+}
+
+fn entry(meta: &ObjectMeta) -> StorageEntry {
+	StorageEntry {
+		path: PathBuf::from(meta.location.as_ref()),
+		is_dir: false,
+		size: Some(meta.size as u64),
+		last_modified: Some(meta.last_modified.into()),
+		mime: None,
+		visibility: None,
+	}
+}
+
+fn unsupported_visibility() -> Error {
+	Error::new(
+		ErrorKind::Unsupported,
+		"Object stores don't support visibility.",
+	)
+}
+
+/// Map an object store error to the closest [`ErrorKind`].
+fn io_error(error: object_store::Error) -> Error {
+	let kind = match &error {
+		object_store::Error::NotFound { .. } => ErrorKind::NotFound,
+		object_store::Error::AlreadyExists { .. } | object_store::Error::Precondition { .. } => {
+			ErrorKind::AlreadyExists
+		},
+		object_store::Error::PermissionDenied { .. }
+		| object_store::Error::Unauthenticated { .. } => ErrorKind::PermissionDenied,
+		object_store::Error::InvalidPath { .. } => ErrorKind::InvalidInput,
+		object_store::Error::NotSupported { .. } | object_store::Error::NotImplemented => {
+			ErrorKind::Unsupported
+		},
+		_ => ErrorKind::Other,
+	};
+
+	Error::new(kind, error)
+}
+
+impl Adapter for ObjectStoreAdapter {
+	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		match self.head(path).await {
+			Ok(_) => Ok(true),
+			Err(error) if error.kind() == ErrorKind::NotFound => Ok(false),
+			Err(error) => Err(error),
+		}
+	}
+
+	async fn directory_exists(&self, path: &Path) -> Result<bool> {
+		let listing = self
+			.store
+			.list_with_delimiter(prefix(path)?.as_ref())
+			.await
+			.map_err(io_error)?;
+
+		Ok(!listing.objects.is_empty() || !listing.common_prefixes.is_empty())
+	}
+
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.write_with(path, content, &WriteOptions::default())
+			.await
+	}
+
+	async fn write_with(&self, path: &Path, content: &[u8], options: &WriteOptions) -> Result<()> {
+		if options.visibility.is_some() {
+			return Err(unsupported_visibility());
+		}
+
+		let mut attributes = Attributes::new();
+		if let Some(content_type) = &options.content_type {
+			attributes.insert(Attribute::ContentType, content_type.to_string().into());
+		}
+		if let Some(cache_control) = &options.cache_control {
+			attributes.insert(Attribute::CacheControl, cache_control.clone().into());
+		}
+		for (key, value) in &options.metadata {
+			attributes.insert(
+				Attribute::Metadata(key.clone().into()),
+				value.clone().into(),
+			);
+		}
+
+		self.store
+			.put_opts(
+				&location(path)?,
+				PutPayload::from(content.to_vec()),
+				PutOptions::from(attributes),
+			)
+			.await
+			.map_err(io_error)?;
+
+		Ok(())
+	}
+
+	/// Write a file from a reader, uploading it in parts once it's larger than a single one.
+	async fn write_stream(
+		&self,
+		path: &Path,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		let location = location(path)?;
+		let first = read_part(reader).await?;
+
+		if first.len() < PART_SIZE {
+			self.store
+				.put(&location, PutPayload::from(first))
+				.await
+				.map_err(io_error)?;
+
+			return Ok(());
+		}
+
+		self.write_parts(&location, first, reader).await
+	}
+
+	async fn read(&self, path: &Path) -> Result<Contents> {
+		let result = self.store.get(&location(path)?).await.map_err(io_error)?;
+
+		Ok(result.bytes().await.map_err(io_error)?.to_vec().into())
+	}
+
+	async fn read_stream(&self, path: &Path) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+		let result = self.store.get(&location(path)?).await.map_err(io_error)?;
+
+		Ok(Box::new(
+			result
+				.into_stream()
+				.map_err(|error| Error::other(io_error(error)))
+				.into_async_read(),
+		))
+	}
+
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		let location = location(path)?;
+		let start = usize::try_from(offset).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+		let end = usize::try_from(offset.saturating_add(len)).unwrap_or(usize::MAX);
+
+		if start == end {
+			return Ok(Vec::new().into());
+		}
+
+		match self.store.get_range(&location, start..end).await {
+			Ok(bytes) => Ok(bytes.to_vec().into()),
+			// stores reject ranges starting past the end of the file, instead of returning nothing
+			Err(error) => match self.store.head(&location).await {
+				Ok(meta) if start >= meta.size => Ok(Vec::new().into()),
+				_ => Err(io_error(error)),
+			},
+		}
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
+		self.store.delete(&location(path)?).await.map_err(io_error)
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
+		let prefix = prefix(path)?;
+		let locations = self
+			.store
+			.list(prefix.as_ref())
+			.map_ok(|meta| meta.location)
+			.boxed();
+
+		self.store
+			.delete_stream(locations)
+			.try_collect::<Vec<_>>()
+			.await
+			.map_err(io_error)?;
+
+		Ok(())
+	}
+
+	/// Object stores have no directories, so this does nothing.
+	async fn create_directory(&self, _path: &Path) -> Result<()> {
+		Ok(())
+	}
+
+	async fn set_visibility(&self, _path: &Path, _visibility: Visibility) -> Result<()> {
+		Err(unsupported_visibility())
+	}
+
+	async fn visibility(&self, _path: &Path) -> Result<Visibility> {
+		Err(unsupported_visibility())
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		let attributes = self.attributes(path).await?;
+
+		attributes.get(&Attribute::ContentType).map_or_else(
+			|| Ok(mime_guess::from_path(path).first_or_octet_stream()),
+			|content_type| {
+				Mime::from_str(content_type).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+			},
+		)
+	}
+
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		Ok(self
+			.attributes(path)
+			.await?
+			.iter()
+			.filter_map(|(key, value)| match key {
+				Attribute::Metadata(key) => Some((key.to_string(), value.to_string())),
+				_ => None,
+			})
+			.collect())
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		Ok(self.head(path).await?.last_modified.into())
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		Ok(self.head(path).await?.size as u64)
+	}
+
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		Ok(self
+			.list_contents_with_metadata(path, deep)
+			.await?
+			.into_iter()
+			.map(|entry| entry.path)
+			.collect())
+	}
+
+	/// List the files under a directory. Shallow listings include its subdirectories too, taken from the common prefixes of the keys.
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		let prefix = prefix(path)?;
+		let mut entries = BTreeMap::new();
+
+		if deep {
+			let mut listing = self.store.list(prefix.as_ref());
+			while let Some(meta) = listing.try_next().await.map_err(io_error)? {
+				entries.insert(PathBuf::from(meta.location.as_ref()), entry(&meta));
+			}
+		} else {
+			let listing = self
+				.store
+				.list_with_delimiter(prefix.as_ref())
+				.await
+				.map_err(io_error)?;
+
+			for directory in listing.common_prefixes {
+				let path = PathBuf::from(directory.as_ref());
+				entries.insert(path.clone(), StorageEntry::directory(path));
+			}
+			for meta in &listing.objects {
+				entries.insert(PathBuf::from(meta.location.as_ref()), entry(meta));
+			}
+		}
+
+		Ok(entries.into_values().collect())
+	}
+
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.store
+			.rename(&location(source)?, &location(destination)?)
+			.await
+			.map_err(io_error)
+	}
+
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.store
+			.copy(&location(source)?, &location(destination)?)
+			.await
+			.map_err(io_error)
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		self.head(path).await?.e_tag.ok_or_else(|| {
+			Error::new(
+				ErrorKind::Unsupported,
+				"The object store did not return an ETag.",
+			)
+		})
+	}
+
+	fn path_model(&self) -> PathModel {
+		PathModel::FlatKeys
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		vec![AdapterLayer::new("object_store").with("store", self.store.to_string())]
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use object_store::memory::InMemory;
+
+	use super::*;
+	use crate::Filesystem;
+
+	#[tokio::test]
+	async fn test_object_store() {
+		let store: Arc<dyn ObjectStore> = Arc::new(InMemory::new());
+		let filesystem = Filesystem::new::<ObjectStoreAdapter>(Arc::clone(&store))
+			.await
+			.unwrap();
+
+		filesystem
+			.write_with(
+				Path::new("reports/2024/q1.csv"),
+				b"id,total",
+				WriteOptions {
+					content_type: Some(mime::TEXT_CSV),
+					metadata: HashMap::from([("author".to_string(), "ana".to_string())]),
+					..WriteOptions::default()
+				},
+			)
+			.await
+			.unwrap();
+		filesystem
+			.write(Path::new("reports/summary.txt"), b"Hello, world!")
+			.await
+			.unwrap();
+
+		// files written through flysystem are visible to other users of the store
+		let stored = store
+			.get(&ObjectPath::from("reports/summary.txt"))
+			.await
+			.unwrap();
+		assert_eq!(&*stored.bytes().await.unwrap(), b"Hello, world!");
+
+		assert_eq!(
+			filesystem
+				.mime_type(Path::new("reports/2024/q1.csv"))
+				.await
+				.unwrap(),
+			mime::TEXT_CSV
+		);
+		assert_eq!(
+			filesystem
+				.metadata(Path::new("reports/2024/q1.csv"))
+				.await
+				.unwrap(),
+			HashMap::from([("author".to_string(), "ana".to_string())])
+		);
+		assert_eq!(
+			Vec::from(
+				filesystem
+					.read_range(Path::new("reports/summary.txt"), 7, 100)
+					.await
+					.unwrap()
+			),
+			b"world!"
+		);
+
+		assert!(filesystem
+			.directory_exists(Path::new("reports/2024"))
+			.await
+			.unwrap());
+		assert_eq!(
+			filesystem
+				.list_contents(Path::new("reports"), false)
+				.await
+				.unwrap(),
+			vec![
+				PathBuf::from("reports/2024"),
+				PathBuf::from("reports/summary.txt")
+			]
+		);
+
+		filesystem
+			.r#move(
+				Path::new("reports/summary.txt"),
+				Path::new("archive/summary.txt"),
+			)
+			.await
+			.unwrap();
+		filesystem
+			.delete_directory(Path::new("reports"))
+			.await
+			.unwrap();
+		assert_eq!(
+			filesystem.list_contents(Path::new(""), true).await.unwrap(),
+			vec![PathBuf::from("archive/summary.txt")]
+		);
+	}
+}
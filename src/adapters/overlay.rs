@@ -0,0 +1,540 @@
+use bytes::BufMut;
+use futures::io::AsyncRead;
+use mime::Mime;
+use std::{
+	collections::{BTreeSet, HashMap},
+	io::{Error, ErrorKind, Result},
+	path::{Path, PathBuf},
+	sync::{Mutex, MutexGuard, PoisonError},
+	time::{Duration, SystemTime},
+};
+use url::Url;
+
+use super::{Adapter, AdapterLayer, TemporaryUrlOptions};
+use crate::{
+	contents::Contents, trait_object_hackyness::AdapterObject, PathModel, Visibility, WriteOptions,
+};
+
+/// An adapter layering a writable adapter on top of any number of read-only ones, like `OverlayFS`.
+///
+/// Reads are served by the first layer holding the file, starting with the write layer and then the read layers in the order they were added.
+/// Writes always go to the write layer, and changing a file that only exists on a read layer copies it up first.
+/// Deleting a file from a read layer hides it (a "whiteout") instead, which is only kept in memory.
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub struct OverlayAdapter<A> {
+	upper: A,
+	layers: Vec<Box<dyn AdapterObject>>,
+	whiteouts: Mutex<BTreeSet<PathBuf>>,
+}
+
+impl<A: Adapter> OverlayAdapter<A> {
+	/// Create an overlay writing to `upper`.
+	pub fn new(upper: A) -> Self {
+		Self {
+			upper,
+			layers: Vec::new(),
+			whiteouts: Mutex::default(),
+		}
+	}
+
+	/// Add a read layer, below the ones already added.
+	#[must_use]
+	pub fn with_layer<L: Adapter + 'static>(mut self, layer: L) -> Self {
+		self.layers.push(Box::new(layer));
+		self
+	}
+
+	fn whiteouts(&self) -> MutexGuard<'_, BTreeSet<PathBuf>> {
+		self.whiteouts
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+	}
+
+	/// Check if a path (or one of its parents) was deleted from the read layers.
+	fn is_whited_out(&self, path: &Path) -> bool {
+		let whiteouts = self.whiteouts();
+		path.ancestors()
+			.any(|ancestor| whiteouts.contains(ancestor))
+	}
+
+	/// Find the layer the file at `path` should be read from.
+	async fn resolve(&self, path: &Path) -> Result<&dyn AdapterObject> {
+		if self.upper.file_exists(path).await? {
+			return Ok(&self.upper);
+		}
+
+		if !self.is_whited_out(path) {
+			for layer in &self.layers {
+				if layer.file_exists(path).await? {
+					return Ok(layer.as_ref());
+				}
+			}
+		}
+
+		Err(Error::from(ErrorKind::NotFound))
+	}
+
+	/// Check if the file at `path` can be seen on any of the read layers.
+	async fn in_layers(&self, path: &Path) -> Result<bool> {
+		if self.is_whited_out(path) {
+			return Ok(false);
+		}
+
+		for layer in &self.layers {
+			if layer.file_exists(path).await? {
+				return Ok(true);
+			}
+		}
+
+		Ok(false)
+	}
+
+	/// Copy the file at `path` to the write layer, if it's only on a read layer.
+	async fn copy_up(&self, path: &Path) -> Result<()> {
+		if self.upper.file_exists(path).await? {
+			return Ok(());
+		}
+
+		let layer = self.resolve(path).await?;
+		let (contents, visibility) = (layer.read(path).await?, layer.visibility(path).await?);
+
+		self.upper.write(path, &contents.data).await?;
+		self.upper.set_visibility(path, visibility).await
+	}
+}
+
+impl<A: Adapter> Adapter for OverlayAdapter<A> {
+	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		Ok(self.upper.file_exists(path).await? || self.in_layers(path).await?)
+	}
+
+	async fn directory_exists(&self, path: &Path) -> Result<bool> {
+		if self.upper.directory_exists(path).await? {
+			return Ok(true);
+		}
+
+		if !self.is_whited_out(path) {
+			for layer in &self.layers {
+				if layer.directory_exists(path).await? {
+					return Ok(true);
+				}
+			}
+		}
+
+		Ok(false)
+	}
+
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.upper.write(path, content).await?;
+
+		self.whiteouts().remove(path);
+		Ok(())
+	}
+
+	async fn write_with(&self, path: &Path, content: &[u8], options: &WriteOptions) -> Result<()> {
+		self.upper.write_with(path, content, options).await?;
+
+		self.whiteouts().remove(path);
+		Ok(())
+	}
+
+	async fn write_atomic(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.upper.write_atomic(path, content).await?;
+
+		self.whiteouts().remove(path);
+		Ok(())
+	}
+
+	async fn write_stream(
+		&self,
+		path: &Path,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		self.upper.write_stream(path, reader).await?;
+
+		self.whiteouts().remove(path);
+		Ok(())
+	}
+
+	async fn write_range(&self, path: &Path, offset: u64, data: &[u8]) -> Result<()> {
+		if self.in_layers(path).await? {
+			self.copy_up(path).await?;
+		}
+
+		self.upper.write_range(path, offset, data).await
+	}
+
+	async fn append(&self, path: &Path, data: &[u8]) -> Result<()> {
+		if self.in_layers(path).await? {
+			self.copy_up(path).await?;
+		}
+
+		self.upper.append(path, data).await
+	}
+
+	fn native_range_writes(&self) -> bool {
+		self.upper.native_range_writes()
+	}
+
+	async fn read(&self, path: &Path) -> Result<Contents> {
+		self.resolve(path).await?.read(path).await
+	}
+
+	async fn read_stream(&self, path: &Path) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+		self.resolve(path).await?.read_stream(path).await
+	}
+
+	async fn read_into(&self, path: &Path, buf: &mut (dyn BufMut + Send)) -> Result<()> {
+		self.resolve(path).await?.read_into(path, buf).await
+	}
+
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		self.resolve(path)
+			.await?
+			.read_range(path, offset, len)
+			.await
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
+		let in_upper = self.upper.file_exists(path).await?;
+		let in_layers = self.in_layers(path).await?;
+
+		if !in_upper && !in_layers {
+			return Err(Error::from(ErrorKind::NotFound));
+		}
+
+		if in_upper {
+			self.upper.delete(path).await?;
+		}
+		if in_layers {
+			self.whiteouts().insert(path.to_path_buf());
+		}
+
+		Ok(())
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
+		if self.upper.directory_exists(path).await? {
+			self.upper.delete_directory(path).await?;
+		}
+
+		let mut whiteouts = self.whiteouts();
+		whiteouts.retain(|whiteout| !whiteout.starts_with(path));
+		whiteouts.insert(path.to_path_buf());
+		drop(whiteouts);
+		Ok(())
+	}
+
+	async fn create_directory(&self, path: &Path) -> Result<()> {
+		self.upper.create_directory(path).await
+	}
+
+	async fn set_mime_type(&self, path: &Path, mime_type: &Mime) -> Result<()> {
+		self.copy_up(path).await?;
+
+		self.upper.set_mime_type(path, mime_type).await
+	}
+
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
+		self.copy_up(path).await?;
+
+		self.upper.set_visibility(path, visibility).await
+	}
+
+	async fn visibility(&self, path: &Path) -> Result<Visibility> {
+		self.resolve(path).await?.visibility(path).await
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		self.resolve(path).await?.mime_type(path).await
+	}
+
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		self.resolve(path).await?.metadata(path).await
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		self.resolve(path).await?.last_modified(path).await
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		self.resolve(path).await?.file_size(path).await
+	}
+
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		if !Adapter::directory_exists(self, path).await? {
+			return Err(Error::from(ErrorKind::NotFound));
+		}
+
+		let mut contents = BTreeSet::new();
+
+		if self.upper.directory_exists(path).await? {
+			contents.extend(
+				self.upper
+					.list_contents(path, deep)
+					.await?
+					.iter()
+					.map(|entry| path.join(crate::relative_to(entry, path))),
+			);
+		}
+
+		for layer in &self.layers {
+			if !layer.directory_exists(path).await? {
+				continue;
+			}
+
+			contents.extend(
+				layer
+					.list_contents(path, deep)
+					.await?
+					.iter()
+					.map(|entry| path.join(crate::relative_to(entry, path)))
+					.filter(|entry| !self.is_whited_out(entry)),
+			);
+		}
+
+		Ok(contents.into_iter().collect())
+	}
+
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		if !Adapter::file_exists(self, source).await?
+			&& Adapter::directory_exists(self, source).await?
+		{
+			return Err(Error::new(
+				ErrorKind::Unsupported,
+				"Moving directories isn't supported by the overlay adapter.",
+			));
+		}
+
+		if self.upper.file_exists(source).await? && !self.in_layers(source).await? {
+			self.upper.r#move(source, destination).await?;
+			self.whiteouts().remove(destination);
+
+			return Ok(());
+		}
+
+		Adapter::copy(self, source, destination).await?;
+		Adapter::delete(self, source).await
+	}
+
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		if self.upper.file_exists(source).await? {
+			self.upper.copy(source, destination).await?;
+		} else {
+			let contents = Adapter::read(self, source).await?;
+			self.upper.write(destination, &contents.data).await?;
+		}
+
+		self.whiteouts().remove(destination);
+		Ok(())
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		self.resolve(path).await?.checksum(path).await
+	}
+
+	async fn public_url(&self, path: &Path) -> Result<Url> {
+		self.resolve(path).await?.public_url(path).await
+	}
+
+	async fn temporary_url(&self, path: &Path, expires_in: Duration) -> Result<Url> {
+		self.resolve(path)
+			.await?
+			.temporary_url(path, expires_in)
+			.await
+	}
+
+	async fn temporary_url_with(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+		options: &TemporaryUrlOptions,
+	) -> Result<Url> {
+		self.resolve(path)
+			.await?
+			.temporary_url_with(path, expires_in, options)
+			.await
+	}
+
+	fn path_model(&self) -> PathModel {
+		self.upper.path_model()
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		let read_layers: Vec<String> = self
+			.layers
+			.iter()
+			.map(|layer| {
+				let stack: Vec<String> = layer.layers().iter().map(ToString::to_string).collect();
+				stack.join(" > ")
+			})
+			.collect();
+
+		AdapterLayer::new("overlay")
+			.with("read_layers", format!("[{}]", read_layers.join(", ")))
+			.wrapping(&self.upper)
+	}
+
+	async fn prefetch(&self, path: &Path) -> Result<()> {
+		self.resolve(path).await?.prefetch(path).await
+	}
+
+	async fn flush(&self) -> Result<()> {
+		self.upper.flush().await?;
+
+		for layer in &self.layers {
+			layer.flush().await?;
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Adapter, OverlayAdapter, Path, PathBuf, Visibility};
+	use crate::{adapters::MemoryAdapter, Filesystem};
+
+	async fn base() -> MemoryAdapter {
+		let base = MemoryAdapter::default();
+		base.write(Path::new("assets/logo.svg"), b"<svg/>")
+			.await
+			.unwrap();
+		base.write(Path::new("assets/theme.css"), b"body {}")
+			.await
+			.unwrap();
+
+		base
+	}
+
+	#[tokio::test]
+	async fn test_reads_fall_through_layers() {
+		let defaults = MemoryAdapter::default();
+		defaults
+			.write(Path::new("assets/theme.css"), b"shadowed")
+			.await
+			.unwrap();
+		defaults
+			.write(Path::new("assets/fonts/inter.woff2"), b"font")
+			.await
+			.unwrap();
+
+		let filesystem = Filesystem::from_adapter(
+			OverlayAdapter::new(MemoryAdapter::default())
+				.with_layer(base().await)
+				.with_layer(defaults),
+		);
+
+		assert_eq!(
+			filesystem
+				.read::<String>(Path::new("assets/theme.css"))
+				.await
+				.unwrap(),
+			"body {}"
+		);
+		assert_eq!(
+			filesystem
+				.read::<String>(Path::new("assets/fonts/inter.woff2"))
+				.await
+				.unwrap(),
+			"font"
+		);
+		assert_eq!(
+			filesystem
+				.list_contents(Path::new("assets"), true)
+				.await
+				.unwrap(),
+			vec![
+				PathBuf::from("assets/fonts/inter.woff2"),
+				PathBuf::from("assets/logo.svg"),
+				PathBuf::from("assets/theme.css"),
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_writes_go_to_upper_layer() {
+		let filesystem = Filesystem::from_adapter(
+			OverlayAdapter::new(MemoryAdapter::default()).with_layer(base().await),
+		);
+
+		filesystem
+			.write(Path::new("assets/theme.css"), b"body { color: red }")
+			.await
+			.unwrap();
+		filesystem
+			.set_visibility(Path::new("assets/logo.svg"), Visibility::Private)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			filesystem
+				.read::<String>(Path::new("assets/theme.css"))
+				.await
+				.unwrap(),
+			"body { color: red }"
+		);
+		assert_eq!(
+			filesystem
+				.read::<String>(Path::new("assets/logo.svg"))
+				.await
+				.unwrap(),
+			"<svg/>"
+		);
+		assert_eq!(
+			filesystem
+				.visibility(Path::new("assets/logo.svg"))
+				.await
+				.unwrap(),
+			Visibility::Private
+		);
+	}
+
+	#[tokio::test]
+	async fn test_deletes_hide_lower_files() {
+		let filesystem = Filesystem::from_adapter(
+			OverlayAdapter::new(MemoryAdapter::default()).with_layer(base().await),
+		);
+
+		filesystem
+			.write(Path::new("assets/theme.css"), b"override")
+			.await
+			.unwrap();
+		filesystem
+			.delete(Path::new("assets/theme.css"))
+			.await
+			.unwrap();
+		filesystem
+			.r#move(Path::new("assets/logo.svg"), Path::new("logo.svg"))
+			.await
+			.unwrap();
+
+		assert!(!filesystem
+			.file_exists(Path::new("assets/theme.css"))
+			.await
+			.unwrap());
+		assert!(!filesystem
+			.file_exists(Path::new("assets/logo.svg"))
+			.await
+			.unwrap());
+		assert!(filesystem.file_exists(Path::new("logo.svg")).await.unwrap());
+
+		filesystem
+			.delete_directory(Path::new("assets"))
+			.await
+			.unwrap();
+		filesystem
+			.write(Path::new("assets/theme.css"), b"fresh")
+			.await
+			.unwrap();
+
+		assert_eq!(
+			filesystem
+				.list_contents(Path::new("assets"), true)
+				.await
+				.unwrap(),
+			vec![PathBuf::from("assets/theme.css")]
+		);
+	}
+}
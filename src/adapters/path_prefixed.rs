@@ -0,0 +1,485 @@
+use bytes::BufMut;
+use futures::{io::AsyncRead, stream::BoxStream, StreamExt, TryStreamExt};
+use mime::Mime;
+use std::{
+	collections::HashMap,
+	io::{Error, Result},
+	path::{Component, Path, PathBuf},
+	time::{Duration, SystemTime},
+};
+use url::Url;
+
+use super::{Adapter, AdapterLayer, TemporaryUrlOptions};
+use crate::{
+	contents::Contents, versioning::FileVersion, DirectoryStat, FlysystemError, PathModel,
+	Precondition, StorageEntry, Visibility, WriteOptions,
+};
+
+/// An adapter confining every operation to a directory of another adapter, like `tenants/<id>/`.
+///
+/// Paths are relative to the prefix (which is stripped from listings again), and paths using `..` to escape it
+/// are rejected with a [`FlysystemError::PathTraversal`] error. See [`Filesystem::scoped`](crate::Filesystem::scoped).
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct PathPrefixedAdapter<A> {
+	inner: A,
+	prefix: PathBuf,
+}
+
+impl<A: Adapter> PathPrefixedAdapter<A> {
+	/// Confine `inner` to the directory at `prefix`.
+	pub fn new(inner: A, prefix: impl Into<PathBuf>) -> Self {
+		Self {
+			inner,
+			prefix: prefix.into(),
+		}
+	}
+
+	/// Get the directory every path is relative to.
+	#[must_use]
+	pub fn prefix(&self) -> &Path {
+		&self.prefix
+	}
+
+	/// Get the path of a file on the inner adapter, resolving `..` without leaving the prefix.
+	fn prefixed(&self, path: &Path) -> Result<PathBuf> {
+		let mut resolved = PathBuf::new();
+
+		for component in path.components() {
+			match component {
+				Component::Normal(name) => resolved.push(name),
+				Component::ParentDir => {
+					if !resolved.pop() {
+						return Err(FlysystemError::PathTraversal {
+							path: path.to_path_buf(),
+						}
+						.into());
+					}
+				},
+				Component::RootDir | Component::CurDir | Component::Prefix(_) => {},
+			}
+		}
+
+		if resolved.as_os_str().is_empty() {
+			return Ok(self.prefix.clone());
+		}
+
+		Ok(self.prefix.join(resolved))
+	}
+}
+
+impl<A: Adapter> Adapter for PathPrefixedAdapter<A> {
+	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		self.inner.file_exists(&self.prefixed(path)?).await
+	}
+
+	async fn directory_exists(&self, path: &Path) -> Result<bool> {
+		self.inner.directory_exists(&self.prefixed(path)?).await
+	}
+
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+		let path = self.prefixed(path)?;
+
+		self.inner.write(&path, content).await
+	}
+
+	async fn write_with(&self, path: &Path, content: &[u8], options: &WriteOptions) -> Result<()> {
+		let path = self.prefixed(path)?;
+
+		self.inner.write_with(&path, content, options).await
+	}
+
+	async fn write_if(
+		&self,
+		path: &Path,
+		content: &[u8],
+		precondition: &Precondition,
+	) -> Result<()> {
+		let path = self.prefixed(path)?;
+
+		self.inner.write_if(&path, content, precondition).await
+	}
+
+	async fn write_atomic(&self, path: &Path, content: &[u8]) -> Result<()> {
+		let path = self.prefixed(path)?;
+
+		self.inner.write_atomic(&path, content).await
+	}
+
+	async fn write_stream(
+		&self,
+		path: &Path,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		let path = self.prefixed(path)?;
+
+		self.inner.write_stream(&path, reader).await
+	}
+
+	async fn write_range(&self, path: &Path, offset: u64, data: &[u8]) -> Result<()> {
+		let path = self.prefixed(path)?;
+
+		self.inner.write_range(&path, offset, data).await
+	}
+
+	async fn append(&self, path: &Path, data: &[u8]) -> Result<()> {
+		self.inner.append(&self.prefixed(path)?, data).await
+	}
+
+	fn native_range_writes(&self) -> bool {
+		self.inner.native_range_writes()
+	}
+
+	async fn read(&self, path: &Path) -> Result<Contents> {
+		self.inner.read(&self.prefixed(path)?).await
+	}
+
+	async fn read_stream(&self, path: &Path) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+		self.inner.read_stream(&self.prefixed(path)?).await
+	}
+
+	async fn read_into(&self, path: &Path, buf: &mut (dyn BufMut + Send)) -> Result<()> {
+		self.inner.read_into(&self.prefixed(path)?, buf).await
+	}
+
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		self.inner
+			.read_range(&self.prefixed(path)?, offset, len)
+			.await
+	}
+
+	async fn list_versions(&self, prefix: &Path) -> Result<Vec<FileVersion>> {
+		let mut versions = self.inner.list_versions(&self.prefixed(prefix)?).await?;
+
+		for version in &mut versions {
+			if let Ok(path) = version.path.strip_prefix(&self.prefix) {
+				version.path = path.to_path_buf();
+			}
+		}
+
+		Ok(versions)
+	}
+
+	async fn read_version(&self, path: &Path, version_id: &str) -> Result<Contents> {
+		self.inner
+			.read_version(&self.prefixed(path)?, version_id)
+			.await
+	}
+
+	async fn restore_version(&self, path: &Path, version_id: &str) -> Result<()> {
+		self.inner
+			.restore_version(&self.prefixed(path)?, version_id)
+			.await
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
+		let path = self.prefixed(path)?;
+
+		self.inner.delete(&path).await
+	}
+
+	async fn delete_many(&self, paths: &[PathBuf]) -> Vec<(PathBuf, Error)> {
+		let mut failed = Vec::new();
+		let mut originals = HashMap::new();
+
+		for path in paths {
+			match self.prefixed(path) {
+				Ok(prefixed) => {
+					originals.insert(prefixed, path.clone());
+				},
+				Err(error) => failed.push((path.clone(), error)),
+			}
+		}
+
+		let prefixed: Vec<PathBuf> = originals.keys().cloned().collect();
+		for (path, error) in self.inner.delete_many(&prefixed).await {
+			let original = originals.remove(&path).unwrap_or(path);
+			failed.push((original, error));
+		}
+
+		failed
+	}
+
+	async fn write_many(&self, files: &[(&Path, &[u8])]) -> Vec<(PathBuf, Error)> {
+		let mut failed = Vec::new();
+		let mut prefixed = Vec::new();
+
+		for &(path, content) in files {
+			match self.prefixed(path) {
+				Ok(key) => prefixed.push((key, path, content)),
+				Err(error) => failed.push((path.to_path_buf(), error)),
+			}
+		}
+
+		let mut originals: HashMap<&Path, &Path> = prefixed
+			.iter()
+			.map(|(key, path, _)| (key.as_path(), *path))
+			.collect();
+		let files: Vec<(&Path, &[u8])> = prefixed
+			.iter()
+			.map(|(key, _, content)| (key.as_path(), *content))
+			.collect();
+		for (path, error) in self.inner.write_many(&files).await {
+			let original = originals
+				.remove(path.as_path())
+				.map_or(path, Path::to_path_buf);
+			failed.push((original, error));
+		}
+
+		failed
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
+		let path = self.prefixed(path)?;
+
+		self.inner.delete_directory(&path).await
+	}
+
+	async fn create_directory(&self, path: &Path) -> Result<()> {
+		let path = self.prefixed(path)?;
+
+		self.inner.create_directory(&path).await
+	}
+
+	async fn set_mime_type(&self, path: &Path, mime_type: &Mime) -> Result<()> {
+		let path = self.prefixed(path)?;
+
+		self.inner.set_mime_type(&path, mime_type).await
+	}
+
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
+		let path = self.prefixed(path)?;
+
+		self.inner.set_visibility(&path, visibility).await
+	}
+
+	async fn set_visibility_many(
+		&self,
+		paths: &[PathBuf],
+		visibility: Visibility,
+	) -> Vec<(PathBuf, Error)> {
+		let mut failed = Vec::new();
+		let mut originals = HashMap::new();
+
+		for path in paths {
+			match self.prefixed(path) {
+				Ok(prefixed) => {
+					originals.insert(prefixed, path.clone());
+				},
+				Err(error) => failed.push((path.clone(), error)),
+			}
+		}
+
+		let prefixed: Vec<PathBuf> = originals.keys().cloned().collect();
+		for (path, error) in self.inner.set_visibility_many(&prefixed, visibility).await {
+			let original = originals.remove(&path).unwrap_or(path);
+			failed.push((original, error));
+		}
+
+		failed
+	}
+
+	async fn visibility(&self, path: &Path) -> Result<Visibility> {
+		self.inner.visibility(&self.prefixed(path)?).await
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		self.inner.mime_type(&self.prefixed(path)?).await
+	}
+
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		self.inner.metadata(&self.prefixed(path)?).await
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		self.inner.last_modified(&self.prefixed(path)?).await
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		self.inner.file_size(&self.prefixed(path)?).await
+	}
+
+	async fn directory_stat(&self, path: &Path) -> Result<DirectoryStat> {
+		self.inner.directory_stat(&self.prefixed(path)?).await
+	}
+
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		let prefixed = self.prefixed(path)?;
+		let directory = prefixed.strip_prefix(&self.prefix).unwrap_or(path);
+
+		Ok(self
+			.inner
+			.list_contents(&prefixed, deep)
+			.await?
+			.iter()
+			.map(|entry| directory.join(crate::relative_to(entry, &prefixed)))
+			.collect())
+	}
+
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		let prefixed = self.prefixed(path)?;
+		let directory = prefixed.strip_prefix(&self.prefix).unwrap_or(path);
+
+		let mut entries = self
+			.inner
+			.list_contents_with_metadata(&prefixed, deep)
+			.await?;
+		for entry in &mut entries {
+			entry.path = directory.join(crate::relative_to(&entry.path, &prefixed));
+		}
+
+		Ok(entries)
+	}
+
+	fn list_stream<'a>(&'a self, path: &Path, deep: bool) -> BoxStream<'a, Result<PathBuf>> {
+		let prefixed = match self.prefixed(path) {
+			Ok(prefixed) => prefixed,
+			Err(error) => return futures::stream::once(async { Err(error) }).boxed(),
+		};
+		let directory = prefixed
+			.strip_prefix(&self.prefix)
+			.unwrap_or(path)
+			.to_path_buf();
+
+		self.inner
+			.list_stream(&prefixed, deep)
+			.map_ok(move |entry| directory.join(crate::relative_to(&entry, &prefixed)))
+			.boxed()
+	}
+
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		let (source, destination) = (self.prefixed(source)?, self.prefixed(destination)?);
+
+		self.inner.r#move(&source, &destination).await
+	}
+
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		let (source, destination) = (self.prefixed(source)?, self.prefixed(destination)?);
+
+		self.inner.copy(&source, &destination).await
+	}
+
+	async fn move_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		let (source, destination) = (self.prefixed(source)?, self.prefixed(destination)?);
+
+		self.inner.move_directory(&source, &destination).await
+	}
+
+	async fn copy_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		let (source, destination) = (self.prefixed(source)?, self.prefixed(destination)?);
+
+		self.inner.copy_directory(&source, &destination).await
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		self.inner.checksum(&self.prefixed(path)?).await
+	}
+
+	async fn public_url(&self, path: &Path) -> Result<Url> {
+		self.inner.public_url(&self.prefixed(path)?).await
+	}
+
+	async fn temporary_url(&self, path: &Path, expires_in: Duration) -> Result<Url> {
+		self.inner
+			.temporary_url(&self.prefixed(path)?, expires_in)
+			.await
+	}
+
+	async fn temporary_url_with(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+		options: &TemporaryUrlOptions,
+	) -> Result<Url> {
+		self.inner
+			.temporary_url_with(&self.prefixed(path)?, expires_in, options)
+			.await
+	}
+
+	fn path_model(&self) -> PathModel {
+		self.inner.path_model()
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		AdapterLayer::new("path_prefixed")
+			.with("prefix", self.prefix.display())
+			.wrapping(&self.inner)
+	}
+
+	async fn prefetch(&self, path: &Path) -> Result<()> {
+		self.inner.prefetch(&self.prefixed(path)?).await
+	}
+
+	async fn flush(&self) -> Result<()> {
+		self.inner.flush().await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{adapters::MemoryAdapter, Filesystem};
+	use std::io::ErrorKind;
+
+	#[test]
+	fn test_prefixed() {
+		let adapter = PathPrefixedAdapter::new(MemoryAdapter::default(), "tenants/1");
+
+		assert_eq!(
+			adapter.prefixed(Path::new("docs/../avatar.png")).unwrap(),
+			PathBuf::from("tenants/1/avatar.png")
+		);
+		assert_eq!(
+			adapter.prefixed(Path::new("/avatar.png")).unwrap(),
+			PathBuf::from("tenants/1/avatar.png")
+		);
+		assert_eq!(
+			adapter
+				.prefixed(Path::new("docs/../../2/avatar.png"))
+				.unwrap_err()
+				.kind(),
+			ErrorKind::PermissionDenied
+		);
+	}
+
+	#[tokio::test]
+	async fn test_scoped_filesystem() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default());
+		filesystem
+			.write(Path::new("tenants/2/secret.txt"), b"hunter2")
+			.await
+			.unwrap();
+
+		let tenant = filesystem.scoped("tenants/1");
+		tenant
+			.write(Path::new("docs/invoice.pdf"), b"%PDF")
+			.await
+			.unwrap();
+
+		assert_eq!(
+			tenant
+				.read::<String>(Path::new("docs/invoice.pdf"))
+				.await
+				.unwrap(),
+			"%PDF"
+		);
+		assert_eq!(
+			tenant
+				.list_contents(Path::new("docs"), false)
+				.await
+				.unwrap(),
+			vec![PathBuf::from("docs/invoice.pdf")]
+		);
+		assert!(matches!(
+			tenant
+				.read::<String>(Path::new("../2/secret.txt"))
+				.await
+				.unwrap_err(),
+			FlysystemError::PathTraversal { path } if path == Path::new("../2/secret.txt")
+		));
+	}
+}
@@ -0,0 +1,307 @@
+use bytes::BufMut;
+use futures::{io::AsyncRead, stream::BoxStream};
+use mime::Mime;
+use std::{
+	collections::HashMap,
+	io::{Error, ErrorKind, Result},
+	path::{Path, PathBuf},
+	time::{Duration, SystemTime},
+};
+use url::Url;
+
+use super::{Adapter, AdapterLayer, TemporaryUrlOptions};
+use crate::{
+	contents::Contents, versioning::FileVersion, DirectoryStat, PathModel, Precondition,
+	StorageEntry, Visibility, WriteOptions,
+};
+
+/// An adapter only allowing reads, failing every operation that would change the storage with a `PermissionDenied` error.
+///
+/// Useful to hand a [`Filesystem`](crate::Filesystem) to code that must never write, see [`Filesystem::read_only`](crate::Filesystem::read_only).
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct ReadOnlyAdapter<A> {
+	inner: A,
+}
+
+impl<A: Adapter> ReadOnlyAdapter<A> {
+	/// Only allow reading from an adapter.
+	pub const fn new(inner: A) -> Self {
+		Self { inner }
+	}
+}
+
+fn denied(path: &Path) -> Error {
+	Error::new(
+		ErrorKind::PermissionDenied,
+		format!(
+			"The filesystem is read-only, so {} can't be modified.",
+			path.display()
+		),
+	)
+}
+
+impl<A: Adapter> Adapter for ReadOnlyAdapter<A> {
+	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		self.inner.file_exists(path).await
+	}
+
+	async fn directory_exists(&self, path: &Path) -> Result<bool> {
+		self.inner.directory_exists(path).await
+	}
+
+	async fn write(&self, path: &Path, _: &[u8]) -> Result<()> {
+		Err(denied(path))
+	}
+
+	async fn write_with(&self, path: &Path, _: &[u8], _: &WriteOptions) -> Result<()> {
+		Err(denied(path))
+	}
+
+	async fn write_if(&self, path: &Path, _: &[u8], _: &Precondition) -> Result<()> {
+		Err(denied(path))
+	}
+
+	async fn write_atomic(&self, path: &Path, _: &[u8]) -> Result<()> {
+		Err(denied(path))
+	}
+
+	async fn write_stream(
+		&self,
+		path: &Path,
+		_: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		Err(denied(path))
+	}
+
+	async fn write_range(&self, path: &Path, _: u64, _: &[u8]) -> Result<()> {
+		Err(denied(path))
+	}
+
+	async fn append(&self, path: &Path, _: &[u8]) -> Result<()> {
+		Err(denied(path))
+	}
+
+	async fn read(&self, path: &Path) -> Result<Contents> {
+		self.inner.read(path).await
+	}
+
+	async fn read_stream(&self, path: &Path) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+		self.inner.read_stream(path).await
+	}
+
+	async fn read_into(&self, path: &Path, buf: &mut (dyn BufMut + Send)) -> Result<()> {
+		self.inner.read_into(path, buf).await
+	}
+
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		self.inner.read_range(path, offset, len).await
+	}
+
+	async fn list_versions(&self, prefix: &Path) -> Result<Vec<FileVersion>> {
+		self.inner.list_versions(prefix).await
+	}
+
+	async fn read_version(&self, path: &Path, version_id: &str) -> Result<Contents> {
+		self.inner.read_version(path, version_id).await
+	}
+
+	async fn restore_version(&self, path: &Path, _: &str) -> Result<()> {
+		Err(denied(path))
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
+		Err(denied(path))
+	}
+
+	async fn delete_many(&self, paths: &[PathBuf]) -> Vec<(PathBuf, Error)> {
+		paths
+			.iter()
+			.map(|path| (path.clone(), denied(path)))
+			.collect()
+	}
+
+	async fn write_many(&self, files: &[(&Path, &[u8])]) -> Vec<(PathBuf, Error)> {
+		files
+			.iter()
+			.map(|(path, _)| (path.to_path_buf(), denied(path)))
+			.collect()
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
+		Err(denied(path))
+	}
+
+	async fn create_directory(&self, path: &Path) -> Result<()> {
+		Err(denied(path))
+	}
+
+	async fn set_mime_type(&self, path: &Path, _: &Mime) -> Result<()> {
+		Err(denied(path))
+	}
+
+	async fn set_visibility(&self, path: &Path, _: Visibility) -> Result<()> {
+		Err(denied(path))
+	}
+
+	async fn set_visibility_many(&self, paths: &[PathBuf], _: Visibility) -> Vec<(PathBuf, Error)> {
+		paths
+			.iter()
+			.map(|path| (path.clone(), denied(path)))
+			.collect()
+	}
+
+	async fn visibility(&self, path: &Path) -> Result<Visibility> {
+		self.inner.visibility(path).await
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		self.inner.mime_type(path).await
+	}
+
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		self.inner.metadata(path).await
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		self.inner.last_modified(path).await
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		self.inner.file_size(path).await
+	}
+
+	async fn directory_stat(&self, path: &Path) -> Result<DirectoryStat> {
+		self.inner.directory_stat(path).await
+	}
+
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		self.inner.list_contents(path, deep).await
+	}
+
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		self.inner.list_contents_with_metadata(path, deep).await
+	}
+
+	fn list_stream<'a>(&'a self, path: &Path, deep: bool) -> BoxStream<'a, Result<PathBuf>> {
+		self.inner.list_stream(path, deep)
+	}
+
+	fn list_stream_after<'a>(
+		&'a self,
+		path: &Path,
+		deep: bool,
+		after: Option<&Path>,
+	) -> BoxStream<'a, Result<PathBuf>> {
+		self.inner.list_stream_after(path, deep, after)
+	}
+
+	async fn r#move(&self, source: &Path, _: &Path) -> Result<()> {
+		Err(denied(source))
+	}
+
+	async fn copy(&self, _: &Path, destination: &Path) -> Result<()> {
+		Err(denied(destination))
+	}
+
+	async fn move_directory(&self, source: &Path, _: &Path) -> Result<()> {
+		Err(denied(source))
+	}
+
+	async fn copy_directory(&self, _: &Path, destination: &Path) -> Result<()> {
+		Err(denied(destination))
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		self.inner.checksum(path).await
+	}
+
+	async fn public_url(&self, path: &Path) -> Result<Url> {
+		self.inner.public_url(path).await
+	}
+
+	async fn temporary_url(&self, path: &Path, expires_in: Duration) -> Result<Url> {
+		self.inner.temporary_url(path, expires_in).await
+	}
+
+	async fn temporary_url_with(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+		options: &TemporaryUrlOptions,
+	) -> Result<Url> {
+		self.inner
+			.temporary_url_with(path, expires_in, options)
+			.await
+	}
+
+	fn path_model(&self) -> PathModel {
+		self.inner.path_model()
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		AdapterLayer::new("read_only").wrapping(&self.inner)
+	}
+
+	async fn prefetch(&self, path: &Path) -> Result<()> {
+		self.inner.prefetch(path).await
+	}
+
+	async fn flush(&self) -> Result<()> {
+		self.inner.flush().await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{adapters::MemoryAdapter, Filesystem};
+
+	#[tokio::test]
+	async fn test_read_only() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default());
+		filesystem
+			.write(Path::new("config.toml"), b"debug = true")
+			.await
+			.unwrap();
+
+		let filesystem = filesystem.read_only();
+
+		assert_eq!(
+			filesystem
+				.read::<String>(Path::new("config.toml"))
+				.await
+				.unwrap(),
+			"debug = true"
+		);
+
+		for error in [
+			filesystem
+				.write(Path::new("config.toml"), b"debug = false")
+				.await,
+			filesystem.delete(Path::new("config.toml")).await,
+			filesystem
+				.copy(Path::new("config.toml"), Path::new("copy.toml"))
+				.await,
+			filesystem.create_directory(Path::new("plugins")).await,
+		] {
+			assert_eq!(error.unwrap_err().kind(), ErrorKind::PermissionDenied);
+		}
+
+		assert_eq!(
+			filesystem
+				.read::<String>(Path::new("config.toml"))
+				.await
+				.unwrap(),
+			"debug = true"
+		);
+		assert!(!filesystem
+			.file_exists(Path::new("copy.toml"))
+			.await
+			.unwrap());
+	}
+}
@@ -0,0 +1,561 @@
+use futures::{future::BoxFuture, io::AsyncRead, stream::BoxStream};
+use mime::Mime;
+use std::{
+	collections::HashMap,
+	io::{Error, ErrorKind, Result},
+	path::{Path, PathBuf},
+	sync::{
+		atomic::{AtomicUsize, Ordering},
+		Arc, Mutex, MutexGuard, PoisonError,
+	},
+	time::{Duration, Instant, SystemTime},
+};
+use url::Url;
+
+use super::{Adapter, AdapterLayer, TemporaryUrlOptions};
+use crate::{
+	clock::{Clock, SystemClock},
+	contents::Contents,
+	trait_object_hackyness::AdapterObject,
+	versioning::FileVersion,
+	DirectoryStat, PathModel, StorageEntry, Visibility, WriteOptions,
+};
+
+/// How many reads in a row a replica can fail before it's skipped.
+const FAILURE_THRESHOLD: u32 = 3;
+
+/// How long a replica that keeps failing is skipped for, before reads are sent to it again.
+const COOLDOWN: Duration = Duration::from_secs(30);
+
+/// How a [`ReplicatedAdapter`] picks the replica serving a read.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReadRouting {
+	/// Take turns between the replicas.
+	RoundRobin,
+	/// Prefer the replica that has been serving reads the fastest, trying the ones without measurements first.
+	#[default]
+	LowestLatency,
+}
+
+/// How a replica has been doing, as reported by [`ReplicatedAdapter::health`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReplicaHealth {
+	/// Reads served by the replica.
+	pub reads: u64,
+	/// Reads the replica failed, other than for not having the file.
+	pub failures: u64,
+	/// How many reads it failed since the last one it served.
+	pub consecutive_failures: u32,
+	/// A moving average of how long it takes to serve reads.
+	pub latency: Option<Duration>,
+	/// Until when it's skipped, after failing too many reads in a row.
+	pub down_until: Option<SystemTime>,
+}
+
+impl ReplicaHealth {
+	/// Check if reads are sent to the replica at the given time.
+	#[must_use]
+	pub fn is_up_at(&self, now: SystemTime) -> bool {
+		self.down_until.is_none_or(|until| until <= now)
+	}
+}
+
+#[derive(Debug, Clone)]
+struct Replica {
+	adapter: Arc<dyn AdapterObject>,
+	/// The directory the replica mirrors, or an empty path if it mirrors everything.
+	prefix: PathBuf,
+}
+
+/// An adapter sending writes to a primary adapter, and reads to a pool of replicas (like a local mirror of hot S3 prefixes).
+///
+/// Only the contents of files are read from replicas: a replica not holding a file (or failing) falls back to the next one,
+/// and eventually to the primary. Metadata and listings always come from the primary. Replicas are expected to be kept in sync
+/// by something else (like [`Filesystem::sync`](crate::Filesystem::sync)), reads can be stale until they are.
+///
+/// Replicas failing several reads in a row are skipped for a while. Their health is shared between clones,
+/// so a clone can be kept around to check [`ReplicatedAdapter::health`] after handing the adapter to a [`Filesystem`](crate::Filesystem).
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct ReplicatedAdapter<A> {
+	primary: A,
+	replicas: Vec<Replica>,
+	routing: ReadRouting,
+	health: Arc<Mutex<Vec<ReplicaHealth>>>,
+	turn: Arc<AtomicUsize>,
+	clock: Arc<dyn Clock>,
+}
+
+impl<A: Adapter> ReplicatedAdapter<A> {
+	/// Send writes (and reads, until replicas are added) to `primary`.
+	pub fn new(primary: A) -> Self {
+		Self {
+			primary,
+			replicas: Vec::new(),
+			routing: ReadRouting::default(),
+			health: Arc::default(),
+			turn: Arc::default(),
+			clock: Arc::new(SystemClock),
+		}
+	}
+
+	/// Add a replica mirroring every file of the primary.
+	#[must_use]
+	pub fn with_replica<R: Adapter + 'static>(self, replica: R) -> Self {
+		self.with_replica_under(PathBuf::new(), replica)
+	}
+
+	/// Add a replica mirroring the files under `prefix`, at the same paths. Reads of other files never go to it.
+	#[must_use]
+	pub fn with_replica_under<R: Adapter + 'static>(
+		mut self,
+		prefix: impl Into<PathBuf>,
+		replica: R,
+	) -> Self {
+		self.replicas.push(Replica {
+			adapter: Arc::new(replica),
+			prefix: prefix.into(),
+		});
+		self.replica_health().push(ReplicaHealth::default());
+		self
+	}
+
+	/// Pick replicas with the given strategy, instead of [`ReadRouting::LowestLatency`].
+	#[must_use]
+	pub const fn with_routing(mut self, routing: ReadRouting) -> Self {
+		self.routing = routing;
+		self
+	}
+
+	/// Check cooldowns against the given clock instead of the system's, so tests can control time.
+	#[must_use]
+	pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+		self.clock = Arc::new(clock);
+		self
+	}
+
+	/// Get the health of each replica, in the order they were added.
+	#[must_use]
+	pub fn health(&self) -> Vec<ReplicaHealth> {
+		self.replica_health().clone()
+	}
+
+	fn replica_health(&self) -> MutexGuard<'_, Vec<ReplicaHealth>> {
+		self.health.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+
+	/// Get the replicas a read of `path` can go to, in the order they should be tried.
+	fn candidates(&self, path: &Path) -> Vec<usize> {
+		let now = self.clock.now();
+		let health = self.replica_health();
+		let mut candidates: Vec<usize> = (0..self.replicas.len())
+			.filter(|&index| {
+				path.starts_with(&self.replicas[index].prefix) && health[index].is_up_at(now)
+			})
+			.collect();
+
+		match self.routing {
+			ReadRouting::RoundRobin if !candidates.is_empty() => {
+				let turn = self.turn.fetch_add(1, Ordering::Relaxed) % candidates.len();
+				candidates.rotate_left(turn);
+			},
+			ReadRouting::RoundRobin => {},
+			ReadRouting::LowestLatency => {
+				candidates.sort_by_key(|&index| health[index].latency);
+			},
+		}
+
+		candidates
+	}
+
+	/// Read from the first replica serving `path`, or `None` if none of them did.
+	async fn read_replicas<'a, T>(
+		&'a self,
+		path: &Path,
+		read: impl Fn(&'a dyn AdapterObject) -> BoxFuture<'a, Result<T>> + Send,
+	) -> Option<T> {
+		for index in self.candidates(path) {
+			let started = Instant::now();
+			let result = read(self.replicas[index].adapter.as_ref()).await;
+			self.record(index, result.as_ref().map(|_| started.elapsed()));
+
+			if let Ok(value) = result {
+				return Some(value);
+			}
+		}
+
+		None
+	}
+
+	/// Record how long a replica took to serve a read, or how it failed.
+	fn record(&self, index: usize, result: std::result::Result<Duration, &Error>) {
+		let mut replicas = self.replica_health();
+		let health = &mut replicas[index];
+
+		match result {
+			Ok(elapsed) => {
+				health.reads += 1;
+				health.consecutive_failures = 0;
+				health.down_until = None;
+				health.latency = Some(
+					health
+						.latency
+						.map_or(elapsed, |latency| (latency * 4 + elapsed) / 5),
+				);
+			},
+			// the replica doesn't mirror the file (yet), which isn't its fault
+			Err(error) if error.kind() == ErrorKind::NotFound => {},
+			Err(_) => {
+				health.failures += 1;
+				health.consecutive_failures += 1;
+				if health.consecutive_failures >= FAILURE_THRESHOLD {
+					health.down_until = Some(self.clock.now() + COOLDOWN);
+				}
+			},
+		}
+		drop(replicas);
+	}
+}
+
+impl<A: Adapter> Adapter for ReplicatedAdapter<A> {
+	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		self.primary.file_exists(path).await
+	}
+
+	async fn directory_exists(&self, path: &Path) -> Result<bool> {
+		self.primary.directory_exists(path).await
+	}
+
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.primary.write(path, content).await
+	}
+
+	async fn write_with(&self, path: &Path, content: &[u8], options: &WriteOptions) -> Result<()> {
+		self.primary.write_with(path, content, options).await
+	}
+
+	async fn write_atomic(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.primary.write_atomic(path, content).await
+	}
+
+	async fn write_stream(
+		&self,
+		path: &Path,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		self.primary.write_stream(path, reader).await
+	}
+
+	async fn write_range(&self, path: &Path, offset: u64, data: &[u8]) -> Result<()> {
+		self.primary.write_range(path, offset, data).await
+	}
+
+	async fn append(&self, path: &Path, data: &[u8]) -> Result<()> {
+		self.primary.append(path, data).await
+	}
+
+	fn native_range_writes(&self) -> bool {
+		self.primary.native_range_writes()
+	}
+
+	async fn read(&self, path: &Path) -> Result<Contents> {
+		if let Some(contents) = self.read_replicas(path, |replica| replica.read(path)).await {
+			return Ok(contents);
+		}
+
+		self.primary.read(path).await
+	}
+
+	async fn read_stream(&self, path: &Path) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+		if let Some(reader) = self
+			.read_replicas(path, |replica| replica.read_stream(path))
+			.await
+		{
+			return Ok(reader);
+		}
+
+		self.primary.read_stream(path).await
+	}
+
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		if let Some(contents) = self
+			.read_replicas(path, |replica| replica.read_range(path, offset, len))
+			.await
+		{
+			return Ok(contents);
+		}
+
+		self.primary.read_range(path, offset, len).await
+	}
+
+	async fn list_versions(&self, prefix: &Path) -> Result<Vec<FileVersion>> {
+		self.primary.list_versions(prefix).await
+	}
+
+	async fn read_version(&self, path: &Path, version_id: &str) -> Result<Contents> {
+		self.primary.read_version(path, version_id).await
+	}
+
+	async fn restore_version(&self, path: &Path, version_id: &str) -> Result<()> {
+		self.primary.restore_version(path, version_id).await
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
+		self.primary.delete(path).await
+	}
+
+	async fn delete_many(&self, paths: &[PathBuf]) -> Vec<(PathBuf, Error)> {
+		self.primary.delete_many(paths).await
+	}
+
+	async fn write_many(&self, files: &[(&Path, &[u8])]) -> Vec<(PathBuf, Error)> {
+		self.primary.write_many(files).await
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
+		self.primary.delete_directory(path).await
+	}
+
+	async fn create_directory(&self, path: &Path) -> Result<()> {
+		self.primary.create_directory(path).await
+	}
+
+	async fn set_mime_type(&self, path: &Path, mime_type: &Mime) -> Result<()> {
+		self.primary.set_mime_type(path, mime_type).await
+	}
+
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
+		self.primary.set_visibility(path, visibility).await
+	}
+
+	async fn set_visibility_many(
+		&self,
+		paths: &[PathBuf],
+		visibility: Visibility,
+	) -> Vec<(PathBuf, Error)> {
+		self.primary.set_visibility_many(paths, visibility).await
+	}
+
+	async fn visibility(&self, path: &Path) -> Result<Visibility> {
+		self.primary.visibility(path).await
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		self.primary.mime_type(path).await
+	}
+
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		self.primary.metadata(path).await
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		self.primary.last_modified(path).await
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		self.primary.file_size(path).await
+	}
+
+	async fn directory_stat(&self, path: &Path) -> Result<DirectoryStat> {
+		self.primary.directory_stat(path).await
+	}
+
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		self.primary.list_contents(path, deep).await
+	}
+
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		self.primary.list_contents_with_metadata(path, deep).await
+	}
+
+	fn list_stream<'a>(&'a self, path: &Path, deep: bool) -> BoxStream<'a, Result<PathBuf>> {
+		self.primary.list_stream(path, deep)
+	}
+
+	fn list_stream_after<'a>(
+		&'a self,
+		path: &Path,
+		deep: bool,
+		after: Option<&Path>,
+	) -> BoxStream<'a, Result<PathBuf>> {
+		self.primary.list_stream_after(path, deep, after)
+	}
+
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.primary.r#move(source, destination).await
+	}
+
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.primary.copy(source, destination).await
+	}
+
+	async fn move_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.primary.move_directory(source, destination).await
+	}
+
+	async fn copy_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.primary.copy_directory(source, destination).await
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		self.primary.checksum(path).await
+	}
+
+	async fn public_url(&self, path: &Path) -> Result<Url> {
+		self.primary.public_url(path).await
+	}
+
+	async fn temporary_url(&self, path: &Path, expires_in: Duration) -> Result<Url> {
+		self.primary.temporary_url(path, expires_in).await
+	}
+
+	async fn temporary_url_with(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+		options: &TemporaryUrlOptions,
+	) -> Result<Url> {
+		self.primary
+			.temporary_url_with(path, expires_in, options)
+			.await
+	}
+
+	fn path_model(&self) -> PathModel {
+		self.primary.path_model()
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		let replicas: Vec<String> = self
+			.replicas
+			.iter()
+			.map(|replica| {
+				let stack: Vec<String> = replica
+					.adapter
+					.layers()
+					.iter()
+					.map(ToString::to_string)
+					.collect();
+				stack.join(" > ")
+			})
+			.collect();
+
+		AdapterLayer::new("replicated")
+			.with("replicas", format!("[{}]", replicas.join(", ")))
+			.wrapping(&self.primary)
+	}
+
+	async fn prefetch(&self, path: &Path) -> Result<()> {
+		self.primary.prefetch(path).await?;
+
+		for replica in &self.replicas {
+			if !path.starts_with(&replica.prefix) {
+				continue;
+			}
+
+			match replica.adapter.prefetch(path).await {
+				// the replica doesn't mirror the file (yet), so reads of it go elsewhere
+				Err(error) if error.kind() != ErrorKind::NotFound => return Err(error),
+				_ => {},
+			}
+		}
+
+		Ok(())
+	}
+
+	async fn flush(&self) -> Result<()> {
+		self.primary.flush().await?;
+
+		for replica in &self.replicas {
+			replica.adapter.flush().await?;
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{Adapter, Path, ReadRouting, ReplicatedAdapter};
+	use crate::{adapters::MemoryAdapter, Filesystem};
+
+	#[tokio::test]
+	async fn test_reads_prefer_replicas() {
+		let primary = MemoryAdapter::default();
+		primary
+			.write(Path::new("hot/index.html"), b"primary")
+			.await
+			.unwrap();
+		primary
+			.write(Path::new("cold/archive.zip"), b"primary")
+			.await
+			.unwrap();
+		let mirror = MemoryAdapter::default();
+		mirror
+			.write(Path::new("hot/index.html"), b"mirror")
+			.await
+			.unwrap();
+
+		let adapter = ReplicatedAdapter::new(primary).with_replica_under("hot", mirror);
+		let replicas = adapter.clone();
+		let filesystem = Filesystem::from_adapter(adapter);
+
+		assert_eq!(
+			filesystem
+				.read::<String>(Path::new("hot/index.html"))
+				.await
+				.unwrap(),
+			"mirror"
+		);
+		assert_eq!(
+			filesystem
+				.read::<String>(Path::new("cold/archive.zip"))
+				.await
+				.unwrap(),
+			"primary"
+		);
+
+		filesystem
+			.write(Path::new("hot/new.html"), b"fresh")
+			.await
+			.unwrap();
+		// the mirror doesn't have it yet, so it's read from the primary
+		assert_eq!(
+			filesystem
+				.read::<String>(Path::new("hot/new.html"))
+				.await
+				.unwrap(),
+			"fresh"
+		);
+
+		let health = replicas.health()[0];
+		assert_eq!((health.reads, health.failures), (1, 0));
+		assert!(health.latency.is_some());
+	}
+
+	#[tokio::test]
+	async fn test_round_robin() {
+		let replica = |contents: &'static [u8]| async move {
+			let replica = MemoryAdapter::default();
+			replica
+				.write(Path::new("file.txt"), contents)
+				.await
+				.unwrap();
+			replica
+		};
+
+		let adapter = ReplicatedAdapter::new(MemoryAdapter::default())
+			.with_replica(replica(b"a").await)
+			.with_replica(replica(b"b").await)
+			.with_routing(ReadRouting::RoundRobin);
+
+		let mut served = Vec::new();
+		for _ in 0..4 {
+			served.push(adapter.read(Path::new("file.txt")).await.unwrap().data);
+		}
+		assert_eq!(served, [b"a", b"b", b"a", b"b"]);
+	}
+}
@@ -0,0 +1,432 @@
+use bytes::BufMut;
+use futures::{io::AsyncRead, stream::BoxStream};
+use mime::Mime;
+use std::{
+	collections::{hash_map::RandomState, HashMap},
+	future::Future,
+	hash::{BuildHasher, Hasher},
+	io::{Error, ErrorKind, Result},
+	path::{Path, PathBuf},
+	time::{Duration, SystemTime},
+};
+use url::Url;
+
+use super::{Adapter, AdapterLayer, TemporaryUrlOptions};
+use crate::{
+	contents::Contents, versioning::FileVersion, DirectoryStat, PathModel, Precondition,
+	StorageEntry, Visibility, WriteOptions,
+};
+
+/// When and how often a [`RetryAdapter`] retries failed operations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+	/// How many times an operation is attempted in total, including the first one.
+	pub max_attempts: u32,
+	/// How long to wait before the first retry. The wait doubles on every retry after that.
+	pub initial_backoff: Duration,
+	/// The longest wait between two attempts.
+	pub max_backoff: Duration,
+	/// Wait a random duration up to the backoff instead of the backoff itself,
+	/// so clients failing at the same time don't all retry at the same time too.
+	pub jitter: bool,
+	/// The kinds of errors worth retrying.
+	///
+	/// The default only covers network failures. Errors the S3 adapter gets back from the service (like `503 Slow Down`)
+	/// are reported as [`ErrorKind::Other`], which has to be added to retry them.
+	pub retryable: Vec<ErrorKind>,
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		Self {
+			max_attempts: 3,
+			initial_backoff: Duration::from_millis(100),
+			max_backoff: Duration::from_secs(5),
+			jitter: true,
+			retryable: vec![
+				ErrorKind::TimedOut,
+				ErrorKind::Interrupted,
+				ErrorKind::ConnectionRefused,
+				ErrorKind::ConnectionReset,
+				ErrorKind::ConnectionAborted,
+				ErrorKind::BrokenPipe,
+				ErrorKind::UnexpectedEof,
+			],
+		}
+	}
+}
+
+impl RetryPolicy {
+	/// Check if an error is worth retrying.
+	#[must_use]
+	pub fn is_retryable(&self, error: &Error) -> bool {
+		self.retryable.contains(&error.kind())
+	}
+
+	/// Get how long to wait after the given (1-based) failed attempt, before jitter.
+	#[must_use]
+	pub fn backoff(&self, attempt: u32) -> Duration {
+		let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+
+		self.initial_backoff
+			.saturating_mul(factor)
+			.min(self.max_backoff)
+	}
+
+	fn delay(&self, attempt: u32) -> Duration {
+		let backoff = self.backoff(attempt);
+		if !self.jitter {
+			return backoff;
+		}
+
+		let random = RandomState::new().build_hasher().finish();
+		backoff.mul_f64(
+			f64::from(u32::try_from(random >> 32).unwrap_or(u32::MAX)) / f64::from(u32::MAX),
+		)
+	}
+}
+
+/// An adapter retrying the operations of another one that fail with a transient error (like a dropped connection),
+/// waiting longer and longer between attempts.
+///
+/// Operations that can't be safely repeated aren't retried: appends, writes from a reader (which is consumed by the first attempt),
+/// reads into a buffer, and listings streamed with [`Adapter::list_stream`]. Moves and deletions are retried, so one that went
+/// through before its response was lost fails with a `NotFound` error on the next attempt.
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct RetryAdapter<A> {
+	inner: A,
+	policy: RetryPolicy,
+}
+
+impl<A: Adapter> RetryAdapter<A> {
+	/// Retry the failed operations of `inner` according to `policy`.
+	pub const fn new(inner: A, policy: RetryPolicy) -> Self {
+		Self { inner, policy }
+	}
+
+	/// Get the policy operations are retried with.
+	pub const fn policy(&self) -> &RetryPolicy {
+		&self.policy
+	}
+
+	async fn retry<T, F>(&self, mut operation: impl FnMut() -> F) -> Result<T>
+	where
+		F: Future<Output = Result<T>> + Send,
+	{
+		let mut attempt = 1;
+
+		loop {
+			let error = match operation().await {
+				Err(error)
+					if attempt < self.policy.max_attempts && self.policy.is_retryable(&error) =>
+				{
+					error
+				},
+				result => return result,
+			};
+
+			let delay = self.policy.delay(attempt);
+			// waiting is pointless if the next attempt would start after the deadline
+			#[cfg(feature = "deadlines")]
+			if crate::deadline::remaining().is_some_and(|remaining| remaining <= delay) {
+				return Err(error);
+			}
+			drop(error);
+
+			tokio::time::sleep(delay).await;
+			attempt += 1;
+		}
+	}
+}
+
+impl<A: Adapter> Adapter for RetryAdapter<A> {
+	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		self.retry(|| self.inner.file_exists(path)).await
+	}
+
+	async fn directory_exists(&self, path: &Path) -> Result<bool> {
+		self.retry(|| self.inner.directory_exists(path)).await
+	}
+
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.retry(|| self.inner.write(path, content)).await
+	}
+
+	async fn write_with(&self, path: &Path, content: &[u8], options: &WriteOptions) -> Result<()> {
+		self.retry(|| self.inner.write_with(path, content, options))
+			.await
+	}
+
+	async fn write_if(
+		&self,
+		path: &Path,
+		content: &[u8],
+		precondition: &Precondition,
+	) -> Result<()> {
+		self.retry(|| self.inner.write_if(path, content, precondition))
+			.await
+	}
+
+	async fn write_atomic(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.retry(|| self.inner.write_atomic(path, content)).await
+	}
+
+	async fn write_stream(
+		&self,
+		path: &Path,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		self.inner.write_stream(path, reader).await
+	}
+
+	async fn write_range(&self, path: &Path, offset: u64, data: &[u8]) -> Result<()> {
+		self.retry(|| self.inner.write_range(path, offset, data))
+			.await
+	}
+
+	async fn append(&self, path: &Path, data: &[u8]) -> Result<()> {
+		self.inner.append(path, data).await
+	}
+
+	fn native_range_writes(&self) -> bool {
+		self.inner.native_range_writes()
+	}
+
+	async fn read(&self, path: &Path) -> Result<Contents> {
+		self.retry(|| self.inner.read(path)).await
+	}
+
+	async fn read_stream(&self, path: &Path) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+		self.retry(|| self.inner.read_stream(path)).await
+	}
+
+	async fn read_into(&self, path: &Path, buf: &mut (dyn BufMut + Send)) -> Result<()> {
+		self.inner.read_into(path, buf).await
+	}
+
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		self.retry(|| self.inner.read_range(path, offset, len))
+			.await
+	}
+
+	async fn list_versions(&self, prefix: &Path) -> Result<Vec<FileVersion>> {
+		self.retry(|| self.inner.list_versions(prefix)).await
+	}
+
+	async fn read_version(&self, path: &Path, version_id: &str) -> Result<Contents> {
+		self.retry(|| self.inner.read_version(path, version_id))
+			.await
+	}
+
+	async fn restore_version(&self, path: &Path, version_id: &str) -> Result<()> {
+		self.retry(|| self.inner.restore_version(path, version_id))
+			.await
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
+		self.retry(|| self.inner.delete(path)).await
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
+		self.retry(|| self.inner.delete_directory(path)).await
+	}
+
+	async fn create_directory(&self, path: &Path) -> Result<()> {
+		self.retry(|| self.inner.create_directory(path)).await
+	}
+
+	async fn set_mime_type(&self, path: &Path, mime_type: &Mime) -> Result<()> {
+		self.retry(|| self.inner.set_mime_type(path, mime_type))
+			.await
+	}
+
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
+		self.retry(|| self.inner.set_visibility(path, visibility))
+			.await
+	}
+
+	async fn set_visibility_many(
+		&self,
+		paths: &[PathBuf],
+		visibility: Visibility,
+	) -> Vec<(PathBuf, Error)> {
+		self.inner.set_visibility_many(paths, visibility).await
+	}
+
+	async fn visibility(&self, path: &Path) -> Result<Visibility> {
+		self.retry(|| self.inner.visibility(path)).await
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		self.retry(|| self.inner.mime_type(path)).await
+	}
+
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		self.retry(|| self.inner.metadata(path)).await
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		self.retry(|| self.inner.last_modified(path)).await
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		self.retry(|| self.inner.file_size(path)).await
+	}
+
+	async fn directory_stat(&self, path: &Path) -> Result<DirectoryStat> {
+		self.retry(|| self.inner.directory_stat(path)).await
+	}
+
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		self.retry(|| self.inner.list_contents(path, deep)).await
+	}
+
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		self.retry(|| self.inner.list_contents_with_metadata(path, deep))
+			.await
+	}
+
+	fn list_stream<'a>(&'a self, path: &Path, deep: bool) -> BoxStream<'a, Result<PathBuf>> {
+		self.inner.list_stream(path, deep)
+	}
+
+	fn list_stream_after<'a>(
+		&'a self,
+		path: &Path,
+		deep: bool,
+		after: Option<&Path>,
+	) -> BoxStream<'a, Result<PathBuf>> {
+		self.inner.list_stream_after(path, deep, after)
+	}
+
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.retry(|| self.inner.r#move(source, destination)).await
+	}
+
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.retry(|| self.inner.copy(source, destination)).await
+	}
+
+	async fn move_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.retry(|| self.inner.move_directory(source, destination))
+			.await
+	}
+
+	async fn copy_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.retry(|| self.inner.copy_directory(source, destination))
+			.await
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		self.retry(|| self.inner.checksum(path)).await
+	}
+
+	async fn public_url(&self, path: &Path) -> Result<Url> {
+		self.retry(|| self.inner.public_url(path)).await
+	}
+
+	async fn temporary_url(&self, path: &Path, expires_in: Duration) -> Result<Url> {
+		self.retry(|| self.inner.temporary_url(path, expires_in))
+			.await
+	}
+
+	async fn temporary_url_with(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+		options: &TemporaryUrlOptions,
+	) -> Result<Url> {
+		self.retry(|| self.inner.temporary_url_with(path, expires_in, options))
+			.await
+	}
+
+	fn path_model(&self) -> PathModel {
+		self.inner.path_model()
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		AdapterLayer::new("retry")
+			.with("max_attempts", self.policy.max_attempts)
+			.wrapping(&self.inner)
+	}
+
+	async fn prefetch(&self, path: &Path) -> Result<()> {
+		self.retry(|| self.inner.prefetch(path)).await
+	}
+
+	async fn flush(&self) -> Result<()> {
+		self.inner.flush().await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::atomic::{AtomicU32, Ordering};
+
+	use super::*;
+	use crate::adapters::MemoryAdapter;
+
+	fn policy() -> RetryPolicy {
+		RetryPolicy {
+			initial_backoff: Duration::from_millis(1),
+			..RetryPolicy::default()
+		}
+	}
+
+	#[test]
+	fn test_backoff() {
+		let policy = RetryPolicy {
+			max_backoff: Duration::from_millis(300),
+			..RetryPolicy::default()
+		};
+
+		assert_eq!(policy.backoff(1), Duration::from_millis(100));
+		assert_eq!(policy.backoff(2), Duration::from_millis(200));
+		assert_eq!(policy.backoff(3), Duration::from_millis(300));
+		assert_eq!(policy.backoff(50), Duration::from_millis(300));
+		assert!(policy.delay(2) <= Duration::from_millis(200));
+	}
+
+	#[tokio::test]
+	async fn test_retries_transient_errors() {
+		let adapter = RetryAdapter::new(MemoryAdapter::default(), policy());
+		let attempts = AtomicU32::new(0);
+
+		let result = adapter
+			.retry(|| async {
+				match attempts.fetch_add(1, Ordering::SeqCst) {
+					0 | 1 => Err(Error::from(ErrorKind::ConnectionReset)),
+					_ => Ok("done"),
+				}
+			})
+			.await;
+		assert_eq!(result.unwrap(), "done");
+		assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+		// giving up after the last attempt
+		attempts.store(0, Ordering::SeqCst);
+		let result: Result<()> = adapter
+			.retry(|| async {
+				attempts.fetch_add(1, Ordering::SeqCst);
+				Err(Error::from(ErrorKind::TimedOut))
+			})
+			.await;
+		assert_eq!(result.unwrap_err().kind(), ErrorKind::TimedOut);
+		assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+		// permanent errors aren't retried
+		assert_eq!(
+			adapter
+				.read(Path::new("missing.txt"))
+				.await
+				.unwrap_err()
+				.kind(),
+			ErrorKind::NotFound
+		);
+	}
+}
@@ -0,0 +1,398 @@
+use bytes::BufMut;
+use futures::{io::AsyncRead, stream::BoxStream, AsyncReadExt};
+use mime::Mime;
+use std::{
+	collections::HashMap,
+	io::{Error, ErrorKind, Result},
+	path::{Path, PathBuf},
+	time::{Duration, SystemTime},
+};
+use url::Url;
+
+use super::{splice, write_atomic_with, Adapter, AdapterLayer, TemporaryUrlOptions};
+use crate::{
+	contents::Contents, versioning::FileVersion, DirectoryStat, PathModel, StorageEntry,
+	Visibility, WriteOptions,
+};
+
+/// The metadata key the revision of a file is stored under.
+pub const REVISION_METADATA: &str = "revision";
+
+/// Get the revision stored in the metadata of a file, if any.
+pub(crate) fn revision(metadata: &HashMap<String, String>) -> Option<u64> {
+	metadata.get(REVISION_METADATA)?.parse().ok()
+}
+
+/// An adapter numbering the revisions of files in their metadata (under [`REVISION_METADATA`]).
+///
+/// Revisions start at 1 and are incremented every time a file is written, so callers can cheaply check that it didn't change since they read it.
+/// Files are only numbered on adapters storing metadata, writes to other ones are passed through as is.
+/// Getting the next revision costs a metadata lookup per write, and the lookup and the write aren't atomic, so concurrent writers can
+/// end up with the same revision. Streams are buffered, and appends, range writes, copies and moves rewrite the whole file,
+/// so the revision (and the rest of the metadata) stays consistent.
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct RevisionedAdapter<A> {
+	inner: A,
+}
+
+impl<A: Adapter> RevisionedAdapter<A> {
+	/// Number the revisions of the files stored on `inner`.
+	pub const fn new(inner: A) -> Self {
+		Self { inner }
+	}
+
+	/// Get the revision the file at `path` gets when it's written next, or `None` if the adapter doesn't store metadata.
+	async fn next_revision(&self, path: &Path) -> Result<Option<u64>> {
+		match self.inner.metadata(path).await {
+			Ok(metadata) => Ok(Some(revision(&metadata).unwrap_or_default() + 1)),
+			Err(error) if error.kind() == ErrorKind::NotFound => Ok(Some(1)),
+			Err(error) if error.kind() == ErrorKind::Unsupported => Ok(None),
+			Err(error) => Err(error),
+		}
+	}
+
+	async fn write_revision(
+		&self,
+		path: &Path,
+		content: &[u8],
+		options: &WriteOptions,
+	) -> Result<()> {
+		let Some(revision) = self.next_revision(path).await? else {
+			return self.inner.write_with(path, content, options).await;
+		};
+
+		let mut options = options.clone();
+		options
+			.metadata
+			.insert(REVISION_METADATA.to_string(), revision.to_string());
+
+		self.inner.write_with(path, content, &options).await
+	}
+
+	/// Get the options rewriting the file at `path` with, so it keeps its MIME type, visibility and metadata.
+	async fn current_options(&self, path: &Path) -> Result<WriteOptions> {
+		Ok(WriteOptions {
+			visibility: Some(self.inner.visibility(path).await?),
+			content_type: Some(self.inner.mime_type(path).await?),
+			metadata: self.inner.metadata(path).await?,
+			..WriteOptions::default()
+		})
+	}
+}
+
+impl<A: Adapter> Adapter for RevisionedAdapter<A> {
+	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		self.inner.file_exists(path).await
+	}
+
+	async fn directory_exists(&self, path: &Path) -> Result<bool> {
+		self.inner.directory_exists(path).await
+	}
+
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.write_revision(path, content, &WriteOptions::default())
+			.await
+	}
+
+	async fn write_with(&self, path: &Path, content: &[u8], options: &WriteOptions) -> Result<()> {
+		self.write_revision(path, content, options).await
+	}
+
+	async fn write_atomic(&self, path: &Path, content: &[u8]) -> Result<()> {
+		let Some(revision) = self.next_revision(path).await? else {
+			return self.inner.write_atomic(path, content).await;
+		};
+
+		let mut options = WriteOptions::default();
+		options
+			.metadata
+			.insert(REVISION_METADATA.to_string(), revision.to_string());
+
+		write_atomic_with(&self.inner, path, content, &options).await
+	}
+
+	async fn write_stream(
+		&self,
+		path: &Path,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		let mut content = Vec::new();
+		reader.read_to_end(&mut content).await?;
+
+		self.write_revision(path, &content, &WriteOptions::default())
+			.await
+	}
+
+	async fn write_range(&self, path: &Path, offset: u64, data: &[u8]) -> Result<()> {
+		if self.next_revision(path).await?.is_none() {
+			return self.inner.write_range(path, offset, data).await;
+		}
+
+		let mut contents = self.inner.read(path).await?.data;
+		splice(&mut contents, offset, data)?;
+
+		let options = self.current_options(path).await?;
+		self.write_revision(path, &contents, &options).await
+	}
+
+	async fn append(&self, path: &Path, data: &[u8]) -> Result<()> {
+		if self.next_revision(path).await?.is_none() {
+			return self.inner.append(path, data).await;
+		}
+
+		let (mut contents, options) = match self.inner.read(path).await {
+			Ok(contents) => (contents.data, self.current_options(path).await?),
+			Err(error) if error.kind() == ErrorKind::NotFound => {
+				(Vec::new(), WriteOptions::default())
+			},
+			Err(error) => return Err(error),
+		};
+		contents.extend_from_slice(data);
+
+		self.write_revision(path, &contents, &options).await
+	}
+
+	async fn read(&self, path: &Path) -> Result<Contents> {
+		self.inner.read(path).await
+	}
+
+	async fn read_stream(&self, path: &Path) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+		self.inner.read_stream(path).await
+	}
+
+	async fn read_into(&self, path: &Path, buf: &mut (dyn BufMut + Send)) -> Result<()> {
+		self.inner.read_into(path, buf).await
+	}
+
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		self.inner.read_range(path, offset, len).await
+	}
+
+	async fn list_versions(&self, prefix: &Path) -> Result<Vec<FileVersion>> {
+		self.inner.list_versions(prefix).await
+	}
+
+	async fn read_version(&self, path: &Path, version_id: &str) -> Result<Contents> {
+		self.inner.read_version(path, version_id).await
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
+		self.inner.delete(path).await
+	}
+
+	async fn delete_many(&self, paths: &[PathBuf]) -> Vec<(PathBuf, Error)> {
+		self.inner.delete_many(paths).await
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
+		self.inner.delete_directory(path).await
+	}
+
+	async fn create_directory(&self, path: &Path) -> Result<()> {
+		self.inner.create_directory(path).await
+	}
+
+	async fn set_mime_type(&self, path: &Path, mime_type: &Mime) -> Result<()> {
+		self.inner.set_mime_type(path, mime_type).await
+	}
+
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
+		self.inner.set_visibility(path, visibility).await
+	}
+
+	async fn set_visibility_many(
+		&self,
+		paths: &[PathBuf],
+		visibility: Visibility,
+	) -> Vec<(PathBuf, Error)> {
+		self.inner.set_visibility_many(paths, visibility).await
+	}
+
+	async fn visibility(&self, path: &Path) -> Result<Visibility> {
+		self.inner.visibility(path).await
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		self.inner.mime_type(path).await
+	}
+
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		self.inner.metadata(path).await
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		self.inner.last_modified(path).await
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		self.inner.file_size(path).await
+	}
+
+	async fn directory_stat(&self, path: &Path) -> Result<DirectoryStat> {
+		self.inner.directory_stat(path).await
+	}
+
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		self.inner.list_contents(path, deep).await
+	}
+
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		self.inner.list_contents_with_metadata(path, deep).await
+	}
+
+	fn list_stream<'a>(&'a self, path: &Path, deep: bool) -> BoxStream<'a, Result<PathBuf>> {
+		self.inner.list_stream(path, deep)
+	}
+
+	fn list_stream_after<'a>(
+		&'a self,
+		path: &Path,
+		deep: bool,
+		after: Option<&Path>,
+	) -> BoxStream<'a, Result<PathBuf>> {
+		self.inner.list_stream_after(path, deep, after)
+	}
+
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		if self.next_revision(destination).await?.is_none() {
+			return self.inner.r#move(source, destination).await;
+		}
+
+		Adapter::copy(self, source, destination).await?;
+		self.inner.delete(source).await
+	}
+
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		if self.next_revision(destination).await?.is_none() {
+			return self.inner.copy(source, destination).await;
+		}
+
+		let contents = self.inner.read(source).await?;
+		let mut options = self.current_options(source).await?;
+		options.metadata.remove(REVISION_METADATA);
+
+		self.write_revision(destination, &contents, &options).await
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		self.inner.checksum(path).await
+	}
+
+	async fn public_url(&self, path: &Path) -> Result<Url> {
+		self.inner.public_url(path).await
+	}
+
+	async fn temporary_url(&self, path: &Path, expires_in: Duration) -> Result<Url> {
+		self.inner.temporary_url(path, expires_in).await
+	}
+
+	async fn temporary_url_with(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+		options: &TemporaryUrlOptions,
+	) -> Result<Url> {
+		self.inner
+			.temporary_url_with(path, expires_in, options)
+			.await
+	}
+
+	fn path_model(&self) -> PathModel {
+		self.inner.path_model()
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		AdapterLayer::new("revisioned").wrapping(&self.inner)
+	}
+
+	async fn prefetch(&self, path: &Path) -> Result<()> {
+		self.inner.prefetch(path).await
+	}
+
+	async fn flush(&self) -> Result<()> {
+		self.inner.flush().await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{adapters::MemoryAdapter, Filesystem};
+
+	#[tokio::test]
+	async fn test_atomic_writes() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default()).with_revisions();
+
+		filesystem
+			.write_atomic(Path::new("config.json"), b"{}")
+			.await
+			.unwrap();
+		filesystem
+			.write_atomic(Path::new("config.json"), b"{\"debug\":true}")
+			.await
+			.unwrap();
+
+		assert_eq!(
+			filesystem
+				.stat(Path::new("config.json"))
+				.await
+				.unwrap()
+				.revision,
+			Some(2)
+		);
+		// the temporary file was moved into place
+		assert_eq!(
+			filesystem.list_contents(Path::new(""), true).await.unwrap(),
+			vec![PathBuf::from("config.json")]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_revisions() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default()).with_revisions();
+
+		filesystem
+			.write(Path::new("config.json"), b"{}")
+			.await
+			.unwrap();
+		assert_eq!(
+			filesystem
+				.stat(Path::new("config.json"))
+				.await
+				.unwrap()
+				.revision,
+			Some(1)
+		);
+
+		filesystem
+			.write(Path::new("config.json"), b"{\"debug\":true}")
+			.await
+			.unwrap();
+		filesystem
+			.append(Path::new("config.json"), b"\n")
+			.await
+			.unwrap();
+		let stat = filesystem.file("config.json").stat().await.unwrap();
+		assert_eq!(stat.revision, Some(3));
+		assert_eq!(stat.size, 15);
+
+		// the destination of a copy gets a new revision of its own
+		filesystem
+			.copy(Path::new("config.json"), Path::new("backup.json"))
+			.await
+			.unwrap();
+		assert_eq!(
+			filesystem
+				.stat(Path::new("backup.json"))
+				.await
+				.unwrap()
+				.revision,
+			Some(1)
+		);
+	}
+}
@@ -1,86 +1,1473 @@
+use aws_config::BehaviorVersion;
 use aws_sdk_s3::{
-	config::Credentials,
-	error::SdkError,
-	operation::get_object_acl::GetObjectAclOutput,
+	config::{
+		interceptors::{
+			BeforeDeserializationInterceptorContextRef, BeforeTransmitInterceptorContextMut,
+		},
+		ConfigBag, Credentials as AwsCredentials, Intercept, RuntimeComponents,
+	},
+	error::{ProvideErrorMetadata, SdkError},
+	operation::{
+		get_object::GetObjectOutput,
+		get_object_acl::GetObjectAclOutput,
+		head_object::HeadObjectOutput,
+		list_objects_v2::{
+			builders::ListObjectsV2FluentBuilder, ListObjectsV2Error, ListObjectsV2Output,
+		},
+	},
 	presigning::PresigningConfig,
 	primitives::ByteStream,
-	types::{Delete, ObjectCannedAcl, ObjectIdentifier, Permission},
+	types::{
+		CompletedMultipartUpload, CompletedPart, Delete, MetadataDirective, ObjectCannedAcl,
+		ObjectIdentifier, ObjectLockLegalHold, ObjectLockLegalHoldStatus, ObjectLockRetention,
+		ObjectLockRetentionMode, Permission, StorageClass,
+	},
 	Client,
 };
+use aws_smithy_runtime_api::{
+	box_error::BoxError,
+	client::orchestrator::{HttpRequest, HttpResponse},
+};
+use aws_smithy_types::DateTime;
 use aws_types::region::Region;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::BufMut;
+use futures::{
+	channel::mpsc, future::BoxFuture, io::AsyncRead, stream::BoxStream, AsyncReadExt, FutureExt,
+	SinkExt, StreamExt, TryStreamExt,
+};
+use md5::{Digest, Md5};
 use mime::Mime;
 use std::{
-	convert::Infallible,
+	collections::{BTreeMap, HashMap},
+	fmt::{self, Debug},
+	future::Future,
 	io::{Error, ErrorKind, Result},
 	path::{Path, PathBuf},
 	str::FromStr,
-	time::{Duration, SystemTime},
+	sync::{Arc, Mutex, PoisonError},
+	time::{Duration, Instant, SystemTime},
 };
 use url::Url;
 
-use super::{Adapter, AdapterInit, TemporaryUrlGenerator};
-use crate::{contents::Contents, Visibility};
+use super::{
+	ensure_capacity, key_order, Adapter, AdapterInit, AdapterLayer, ObjectLock, PublicUrlGenerator,
+	Retention, RetentionMode, TemporaryUpload, TemporaryUploadUrlGenerator, TemporaryUrlGenerator,
+	TemporaryUrlOptions, UploadConstraints, Validation,
+};
+use crate::{
+	contents::Contents, precondition, public_url::BaseUrl, versioning::FileVersion, PathModel,
+	Precondition, StorageEntry, Visibility, WriteOptions, REDACTED,
+};
+
+/// How many ACLs are updated at once when setting the visibility of many files.
+const ACL_CONCURRENCY: usize = 16;
+
+/// How many objects a single `DeleteObjects` request can delete.
+const DELETE_BATCH_SIZE: usize = 1000;
+
+/// How many objects are uploaded at once by [`Adapter::write_many`].
+const WRITE_CONCURRENCY: usize = 16;
+
+/// The size of the parts large files are uploaded in, unless [`Config::part_size`] is set. Smaller files are uploaded with a single request.
+pub const PART_SIZE: u64 = 8 * 1024 * 1024;
+
+/// How many parts of a file are uploaded at once, unless [`Config::part_concurrency`] is set.
+pub const PART_CONCURRENCY: usize = 4;
+
+/// The smallest and largest parts S3 accepts (except for the last part, which can be smaller).
+const PART_SIZE_LIMITS: std::ops::RangeInclusive<u64> = 5 * 1024 * 1024..=5 * 1024 * 1024 * 1024;
+
+/// A hook observing and changing the requests sent to S3, like adding tracing headers or provider-specific query parameters.
+pub trait RequestHook: Debug + Send + Sync {
+	/// Change a request before it's signed and sent, so added headers and query parameters are signed too.
+	/// This is called again for each retry.
+	///
+	/// # Errors
+	///
+	/// Returning an error fails the request.
+	fn before_send(&self, request: &mut HttpRequest) -> Result<()> {
+		let _ = request;
+
+		Ok(())
+	}
+
+	/// Observe the response to a request.
+	fn after_response(&self, request: Option<&HttpRequest>, response: &HttpResponse) {
+		let _ = (request, response);
+	}
+}
+
+/// Runs a [`RequestHook`] as part of the SDK's request pipeline.
+#[derive(Debug)]
+struct HookInterceptor(Arc<dyn RequestHook>);
+
+impl Intercept for HookInterceptor {
+	fn name(&self) -> &'static str {
+		"FlysystemRequestHook"
+	}
+
+	fn modify_before_signing(
+		&self,
+		context: &mut BeforeTransmitInterceptorContextMut<'_>,
+		_: &RuntimeComponents,
+		_: &mut ConfigBag,
+	) -> std::result::Result<(), BoxError> {
+		Ok(self.0.before_send(context.request_mut())?)
+	}
+
+	fn read_after_transmit(
+		&self,
+		context: &BeforeDeserializationInterceptorContextRef<'_>,
+		_: &RuntimeComponents,
+		_: &mut ConfigBag,
+	) -> std::result::Result<(), BoxError> {
+		self.0
+			.after_response(context.inner().request(), context.response());
+
+		Ok(())
+	}
+}
+
+/// The region used when none is configured (and none comes from a profile).
+pub const DEFAULT_REGION: &str = "us-east-1";
+
+/// How to connect to an S3 bucket. Use [`Config::builder`] (or [`ConfigBuilder::from_env`]) to build one that's validated up front.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(
+	feature = "config",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(default)
+)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Config {
+	pub bucket: String,
+	/// The region of the bucket, defaulting to [`DEFAULT_REGION`] when empty.
+	pub region: String,
+	/// The URL of an S3-compatible store (like `MinIO` or R2), or empty to use AWS.
+	pub endpoint: String,
+	/// Where the credentials signing requests come from. Requests are sent unsigned by default, for public buckets.
+	pub credentials: Credentials,
+	/// Route requests through S3 Transfer Acceleration, which must be enabled on the bucket.
+	/// This requires virtual-hosted-style addressing, so it can't be used with a custom `endpoint`.
+	pub accelerate: bool,
+	/// Use the dual-stack (IPv4 and IPv6) endpoints.
+	pub dual_stack: bool,
+	/// How far clients' clocks may be behind ours. Temporary URLs become valid this long before they're generated
+	/// (and expire this much later), so clients whose clock is behind don't see them as not yet valid.
+	pub clock_skew: Duration,
+	/// Update the `Content-Type` of moved files whose new extension maps to a different MIME type (like renaming `.tmp` to `.pdf`).
+	/// This replaces the rest of the object's metadata too.
+	pub detect_mime_on_move: bool,
+	/// Make `create_directory` a no-op instead of writing a zero-byte `prefix/` marker object, for tooling that treats markers as files.
+	/// Directories then only exist while they contain files, so an empty one created this way isn't reported by `directory_exists`.
+	pub skip_directory_markers: bool,
+	/// Hooks run on every request sent to S3, in order. They can't be configured from a config file.
+	#[cfg_attr(feature = "config", serde(skip))]
+	pub hooks: Vec<Arc<dyn RequestHook>>,
+	/// Retry reads of files this adapter just wrote when they're not found yet, for S3-compatible stores without read-after-write consistency.
+	pub read_after_write: Option<ReadAfterWrite>,
+	/// Whether to check that the bucket exists and is accessible when the adapter is created.
+	pub validation: Validation,
+	/// The size of the parts files larger than it are uploaded in (with a multipart upload), defaulting to [`PART_SIZE`].
+	/// S3 requires parts to be between 5 MiB and 5 GiB.
+	pub part_size: Option<u64>,
+	/// How many parts of a file are uploaded at once, defaulting to [`PART_CONCURRENCY`]. Each one is held in memory while it's uploaded.
+	pub part_concurrency: Option<usize>,
+	/// The base URL files are publicly served from (like `https://cdn.example.com/` or the bucket's own URL), used to generate public URLs.
+	/// The adapter can't generate public URLs without it.
+	pub public_url: Option<String>,
+	/// How S3 encrypts the files this adapter writes, including multipart uploads and copies. The bucket's default encryption applies if it's not set.
+	pub encryption: Option<ServerSideEncryption>,
+	/// The storage class files are written in (like `STANDARD_IA` or `GLACIER_IR`), or the bucket's default (usually `STANDARD`) if it's not set.
+	/// [`WriteOptions::storage_class`] overrides it for a single file.
+	pub storage_class: Option<String>,
+}
+
+/// How S3 encrypts the objects an [`S3Adapter`] writes. Customer-provided keys are masked in `Debug` output.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(
+	feature = "config",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(tag = "type", rename_all = "snake_case")
+)]
+pub enum ServerSideEncryption {
+	/// Keys managed by S3 (SSE-S3).
+	S3,
+	/// Keys managed by KMS (SSE-KMS).
+	Kms {
+		/// The ID or ARN of the KMS key, or `None` for the `aws/s3` managed key.
+		key_id: Option<String>,
+		/// Use an S3 Bucket Key, cutting down on KMS requests (and their cost).
+		bucket_key: bool,
+	},
+	/// A key provided by the application (SSE-C), as 32 base64-encoded bytes.
+	///
+	/// S3 doesn't store the key, so it's sent along with every read, lookup and copy too.
+	/// Temporary URLs can't send it, so files encrypted this way can't be read through them.
+	Customer { key: String },
+}
+
+impl Debug for ServerSideEncryption {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::S3 => f.write_str("S3"),
+			Self::Kms { key_id, bucket_key } => f
+				.debug_struct("Kms")
+				.field("key_id", key_id)
+				.field("bucket_key", bucket_key)
+				.finish(),
+			Self::Customer { .. } => f.debug_struct("Customer").field("key", &REDACTED).finish(),
+		}
+	}
+}
+
+/// The encryption settings sent with requests, worked out once from the config.
+#[derive(Clone, Default)]
+struct Encryption {
+	algorithm: Option<aws_sdk_s3::types::ServerSideEncryption>,
+	kms_key_id: Option<String>,
+	bucket_key: Option<bool>,
+	customer_key: Option<String>,
+	customer_key_md5: Option<String>,
+}
+
+impl Debug for Encryption {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Encryption")
+			.field("algorithm", &self.algorithm)
+			.field("kms_key_id", &self.kms_key_id)
+			.field("bucket_key", &self.bucket_key)
+			.field(
+				"customer_key",
+				&self.customer_key.as_ref().map(|_| REDACTED),
+			)
+			.finish_non_exhaustive()
+	}
+}
+
+impl Encryption {
+	fn new(encryption: Option<ServerSideEncryption>) -> Self {
+		match encryption {
+			None => Self::default(),
+			Some(ServerSideEncryption::S3) => Self {
+				algorithm: Some(aws_sdk_s3::types::ServerSideEncryption::Aes256),
+				..Self::default()
+			},
+			Some(ServerSideEncryption::Kms { key_id, bucket_key }) => Self {
+				algorithm: Some(aws_sdk_s3::types::ServerSideEncryption::AwsKms),
+				kms_key_id: key_id,
+				bucket_key: bucket_key.then_some(true),
+				..Self::default()
+			},
+			Some(ServerSideEncryption::Customer { key }) => Self {
+				customer_key_md5: STANDARD
+					.decode(&key)
+					.ok()
+					.map(|bytes| STANDARD.encode(Md5::digest(bytes))),
+				customer_key: Some(key),
+				..Self::default()
+			},
+		}
+	}
+
+	/// The algorithm of the customer-provided key, which is the only one S3 supports.
+	fn customer_algorithm(&self) -> Option<String> {
+		self.customer_key.as_ref().map(|_| "AES256".to_string())
+	}
+}
+
+/// Where an [`S3Adapter`] gets the credentials signing its requests from.
+///
+/// Every source but [`Credentials::Anonymous`] and [`Credentials::Static`] refreshes temporary credentials before they expire.
+/// Secret keys and session tokens are masked in `Debug` output.
+#[derive(Clone, Default, PartialEq, Eq)]
+#[cfg_attr(
+	feature = "config",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(tag = "source", rename_all = "snake_case")
+)]
+pub enum Credentials {
+	/// Send unsigned requests, for public buckets.
+	#[default]
+	Anonymous,
+	/// A fixed access key and secret key, with the session token of temporary credentials.
+	Static {
+		access_key: String,
+		secret_key: String,
+		session_token: Option<String>,
+	},
+	/// Load the credentials (along with the region and endpoint, unless they're configured) from this profile of the AWS shared config
+	/// (`~/.aws/config` and `~/.aws/credentials`), including SSO and `credential_process` profiles.
+	Profile { name: String },
+	/// Read the credentials from `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`.
+	Environment,
+	/// Assume an IAM role (like one in another account), with the credentials of the default chain.
+	AssumeRole {
+		role_arn: String,
+		/// The name of the role session, shown in `CloudTrail`. A random one is used if it's not set.
+		session_name: Option<String>,
+		/// The external ID the role's trust policy requires, if any.
+		external_id: Option<String>,
+		/// How long the assumed credentials last before they're refreshed, one hour if it's not set.
+		duration: Option<Duration>,
+	},
+	/// The default AWS chain: the environment, the shared config's default profile, a web identity token (like IRSA on EKS),
+	/// then the ECS container and EC2 instance metadata credentials.
+	DefaultChain,
+}
+
+impl Debug for Credentials {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Anonymous => f.write_str("Anonymous"),
+			Self::Static {
+				access_key,
+				session_token,
+				..
+			} => f
+				.debug_struct("Static")
+				.field("access_key", access_key)
+				.field("secret_key", &REDACTED)
+				.field("session_token", &session_token.as_ref().map(|_| REDACTED))
+				.finish(),
+			Self::Profile { name } => f.debug_struct("Profile").field("name", name).finish(),
+			Self::Environment => f.write_str("Environment"),
+			Self::AssumeRole {
+				role_arn,
+				session_name,
+				external_id,
+				duration,
+			} => f
+				.debug_struct("AssumeRole")
+				.field("role_arn", role_arn)
+				.field("session_name", session_name)
+				.field("external_id", external_id)
+				.field("duration", duration)
+				.finish(),
+			Self::DefaultChain => f.write_str("DefaultChain"),
+		}
+	}
+}
+
+/// How reads of freshly written files are retried on stores that are only eventually consistent (like some Ceph deployments).
+///
+/// Only files written (or copied or moved) by the same adapter instance (or its clones) are retried, so lookups of files that
+/// really don't exist stay fast.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+	feature = "config",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(default)
+)]
+pub struct ReadAfterWrite {
+	/// How long after a write a missing file is assumed to not be visible yet.
+	pub window: Duration,
+	/// How many times to retry.
+	pub retries: u32,
+	/// How long to wait before the first retry. The delay doubles after each retry.
+	pub delay: Duration,
+}
+
+impl Default for ReadAfterWrite {
+	fn default() -> Self {
+		Self {
+			window: Duration::from_secs(10),
+			retries: 5,
+			delay: Duration::from_millis(100),
+		}
+	}
+}
+
+/// The parameters of a [raw listing](S3Adapter::list_raw).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListOptions {
+	/// Only list the keys starting with this prefix. Unlike paths, it doesn't have to end at a `/`.
+	pub prefix: Option<String>,
+	/// Group the keys containing this delimiter (after the prefix) into common prefixes, instead of listing them all.
+	pub delimiter: Option<String>,
+	/// Only list the keys after this one, in UTF-8 binary order.
+	pub start_after: Option<String>,
+	/// How many keys to fetch per page, up to (and by default) 1000.
+	pub max_keys: Option<i32>,
+	/// Whether to return the owner of every object.
+	pub fetch_owner: bool,
+}
+
+/// An entry of a [raw listing](S3Adapter::list_raw).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListEntry {
+	Object(ObjectEntry),
+	/// A prefix grouping the keys containing the delimiter, including it (like `photos/`).
+	CommonPrefix(String),
+}
+
+/// An object returned by a [raw listing](S3Adapter::list_raw).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectEntry {
+	pub key: String,
+	pub size: Option<u64>,
+	pub last_modified: Option<SystemTime>,
+	pub e_tag: Option<String>,
+	/// The storage class of the object, like `STANDARD` or `GLACIER`.
+	pub storage_class: Option<String>,
+	/// The canonical ID of the owner of the object, only returned with [`ListOptions::fetch_owner`].
+	pub owner_id: Option<String>,
+	/// The display name of the owner of the object, only returned with [`ListOptions::fetch_owner`] (and only in some regions).
+	pub owner_display_name: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct S3Adapter {
+	client: Client,
+	bucket: String,
+	clock_skew: Duration,
+	detect_mime_on_move: bool,
+	skip_directory_markers: bool,
+	read_after_write: Option<ReadAfterWrite>,
+	recent_writes: Arc<Mutex<HashMap<PathBuf, Instant>>>,
+	public_url: Option<BaseUrl>,
+	part_size: u64,
+	part_concurrency: usize,
+	encryption: Encryption,
+	storage_class: Option<String>,
+}
+
+impl S3Adapter {
+	/// The storage class to write a file in, if any.
+	fn storage_class(&self, options: &WriteOptions) -> Option<StorageClass> {
+		options
+			.storage_class
+			.as_ref()
+			.or(self.storage_class.as_ref())
+			.map(|storage_class| StorageClass::from(storage_class.as_str()))
+	}
+
+	/// Sign requests for `expires_in`, accounting for clients' clocks being behind.
+	fn presigning_config(&self, expires_in: Duration) -> Result<PresigningConfig> {
+		PresigningConfig::builder()
+			.start_time(SystemTime::now() - self.clock_skew)
+			.expires_in(expires_in + self.clock_skew)
+			.build()
+			.map_err(|e| Error::new(ErrorKind::InvalidInput, e))
+	}
+
+	/// Remember that `path` was just written, so reads of it are retried if the store isn't consistent yet.
+	fn record_write(&self, path: &Path) {
+		let Some(read_after_write) = self.read_after_write else {
+			return;
+		};
+
+		let mut recent_writes = self
+			.recent_writes
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner);
+
+		recent_writes.retain(|_, written_at| written_at.elapsed() < read_after_write.window);
+		recent_writes.insert(path.to_path_buf(), Instant::now());
+	}
+
+	fn recently_written(&self, path: &Path, window: Duration) -> bool {
+		self.recent_writes
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.get(path)
+			.is_some_and(|written_at| written_at.elapsed() < window)
+	}
+
+	/// Look up `path`, retrying while it's not found if this adapter wrote it recently (see [`ReadAfterWrite`]).
+	async fn consistent<T, F>(&self, path: &Path, lookup: impl Fn() -> F + Send + Sync) -> Result<T>
+	where
+		F: Future<Output = Result<T>> + Send,
+	{
+		let mut result = lookup().await;
+		let Some(read_after_write) = self.read_after_write else {
+			return result;
+		};
+
+		let mut delay = read_after_write.delay;
+		for _ in 0..read_after_write.retries {
+			match &result {
+				Err(error)
+					if error.kind() == ErrorKind::NotFound
+						&& self.recently_written(path, read_after_write.window) => {},
+				_ => break,
+			}
+
+			tokio::time::sleep(delay).await;
+			delay = delay.saturating_mul(2);
+			result = lookup().await;
+		}
+
+		result
+	}
+
+	async fn head_object(&self, path: &Path) -> Result<HeadObjectOutput> {
+		let key = path
+			.to_str()
+			.ok_or_else(|| Error::new(ErrorKind::InvalidData, "path is not valid utf-8"))?;
+
+		self.consistent(path, || async {
+			let request = self
+				.client
+				.head_object()
+				.bucket(&self.bucket)
+				.key(key)
+				.set_sse_customer_algorithm(self.encryption.customer_algorithm())
+				.set_sse_customer_key(self.encryption.customer_key.clone())
+				.set_sse_customer_key_md5(self.encryption.customer_key_md5.clone())
+				.send()
+				.await;
+
+			match request {
+				Ok(request) => Ok(request),
+				Err(SdkError::ServiceError(error)) => {
+					if error.err().is_not_found() {
+						return Err(Error::from(ErrorKind::NotFound));
+					}
+
+					Err(Error::other(error.into_err()))
+				},
+				Err(e) => Err(Error::other(e)),
+			}
+		})
+		.await
+	}
+
+	async fn get_object(&self, path: &Path, version_id: Option<&str>) -> Result<GetObjectOutput> {
+		let key = path
+			.to_str()
+			.ok_or_else(|| Error::new(ErrorKind::InvalidData, "path is not valid utf-8"))?;
+
+		self.consistent(path, || self.get_object_once(key, version_id))
+			.await
+	}
+
+	async fn get_object_once(
+		&self,
+		key: &str,
+		version_id: Option<&str>,
+	) -> Result<GetObjectOutput> {
+		let request = self
+			.client
+			.get_object()
+			.bucket(&self.bucket)
+			.key(key)
+			.set_version_id(version_id.map(str::to_string))
+			.set_sse_customer_algorithm(self.encryption.customer_algorithm())
+			.set_sse_customer_key(self.encryption.customer_key.clone())
+			.set_sse_customer_key_md5(self.encryption.customer_key_md5.clone())
+			.send()
+			.await;
+
+		match request {
+			Ok(request) => Ok(request),
+			Err(SdkError::ServiceError(error)) => {
+				if error.err().is_no_such_key() {
+					return Err(Error::from(ErrorKind::NotFound));
+				}
+
+				Err(Error::other(error.into_err()))
+			},
+			Err(e) => Err(Error::other(e)),
+		}
+	}
+}
+
+impl S3Adapter {
+	/// Write a file with a multipart upload, starting with the `first` part and reading the others from `reader`.
+	/// The upload is aborted if any part fails, so the uploaded ones don't linger (and get billed).
+	async fn write_multipart(
+		&self,
+		path: &Path,
+		options: &WriteOptions,
+		first: Vec<u8>,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		let key = path
+			.to_str()
+			.ok_or_else(|| Error::new(ErrorKind::InvalidData, "path is not valid utf-8"))?;
+		let content_type = options
+			.content_type
+			.clone()
+			.unwrap_or_else(|| mime_guess::from_path(path).first_or_octet_stream());
+
+		let upload = self
+			.client
+			.create_multipart_upload()
+			.bucket(&self.bucket)
+			.key(key)
+			.content_type(content_type.to_string())
+			.set_acl(options.visibility.map(ObjectCannedAcl::from))
+			.set_cache_control(options.cache_control.clone())
+			.set_metadata((!options.metadata.is_empty()).then(|| options.metadata.clone()))
+			.set_storage_class(self.storage_class(options))
+			.set_server_side_encryption(self.encryption.algorithm.clone())
+			.set_ssekms_key_id(self.encryption.kms_key_id.clone())
+			.set_bucket_key_enabled(self.encryption.bucket_key)
+			.set_sse_customer_algorithm(self.encryption.customer_algorithm())
+			.set_sse_customer_key(self.encryption.customer_key.clone())
+			.set_sse_customer_key_md5(self.encryption.customer_key_md5.clone())
+			.send()
+			.await
+			.map_err(write_error)?;
+		let upload_id = upload
+			.upload_id
+			.ok_or_else(|| Error::other("S3 didn't return an upload ID."))?;
+
+		let result = self.upload_parts(key, &upload_id, first, reader).await;
+		if result.is_err() {
+			let _ = self
+				.client
+				.abort_multipart_upload()
+				.bucket(&self.bucket)
+				.key(key)
+				.upload_id(&upload_id)
+				.send()
+				.await;
+
+			return result;
+		}
+		self.record_write(path);
+
+		Ok(())
+	}
+
+	/// Upload the parts of a multipart upload (several at once), starting with `first`, then complete it.
+	async fn upload_parts(
+		&self,
+		key: &str,
+		upload_id: &str,
+		first: Vec<u8>,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		// parts are read while the previous ones upload, handed over one at a time so memory stays bounded
+		let (mut sender, receiver) = mpsc::channel(0);
+		let read = async move {
+			let (mut part, mut part_number) = (first, 1);
+			while !part.is_empty() {
+				if sender.send((part_number, part)).await.is_err() {
+					// the uploads stopped at a failure
+					break;
+				}
+
+				part_number += 1;
+				part = read_part(reader, self.part_size).await?;
+			}
+
+			Ok(())
+		};
+		let upload = receiver
+			.map(|(part_number, part)| self.upload_part(key, upload_id, part_number, part))
+			.buffer_unordered(self.part_concurrency)
+			.try_collect::<Vec<_>>();
+
+		let ((), mut parts) = futures::try_join!(read, upload)?;
+		parts.sort_by_key(CompletedPart::part_number);
+
+		self.client
+			.complete_multipart_upload()
+			.bucket(&self.bucket)
+			.key(key)
+			.upload_id(upload_id)
+			.multipart_upload(
+				CompletedMultipartUpload::builder()
+					.set_parts(Some(parts))
+					.build(),
+			)
+			.send()
+			.await
+			.map_err(write_error)?;
+
+		Ok(())
+	}
+
+	async fn upload_part(
+		&self,
+		key: &str,
+		upload_id: &str,
+		part_number: usize,
+		part: Vec<u8>,
+	) -> Result<CompletedPart> {
+		let part_number =
+			i32::try_from(part_number).map_err(|e| Error::new(ErrorKind::FileTooLarge, e))?;
+
+		let response = self
+			.client
+			.upload_part()
+			.bucket(&self.bucket)
+			.key(key)
+			.upload_id(upload_id)
+			.part_number(part_number)
+			.body(ByteStream::from(part))
+			.set_sse_customer_algorithm(self.encryption.customer_algorithm())
+			.set_sse_customer_key(self.encryption.customer_key.clone())
+			.set_sse_customer_key_md5(self.encryption.customer_key_md5.clone())
+			.send()
+			.await
+			.map_err(write_error)?;
+
+		Ok(CompletedPart::builder()
+			.set_e_tag(response.e_tag)
+			.part_number(part_number)
+			.build())
+	}
+
+	/// Upload a file in a single request, only if its `ETag` matches `if_match` or doesn't match `if_none_match` when they're set.
+	async fn put_object(
+		&self,
+		path: &Path,
+		body: ByteStream,
+		options: &WriteOptions,
+		if_match: Option<String>,
+		if_none_match: Option<String>,
+	) -> Result<()> {
+		let content_type = options
+			.content_type
+			.clone()
+			.unwrap_or_else(|| mime_guess::from_path(path).first_or_octet_stream());
+
+		self.client
+			.put_object()
+			.bucket(&self.bucket)
+			.key(
+				path.to_str()
+					.ok_or_else(|| Error::new(ErrorKind::InvalidData, "path is not valid utf-8"))?,
+			)
+			.body(body)
+			.content_type(content_type.to_string())
+			.set_acl(options.visibility.map(ObjectCannedAcl::from))
+			.set_cache_control(options.cache_control.clone())
+			.set_metadata((!options.metadata.is_empty()).then(|| options.metadata.clone()))
+			.set_storage_class(self.storage_class(options))
+			.set_server_side_encryption(self.encryption.algorithm.clone())
+			.set_ssekms_key_id(self.encryption.kms_key_id.clone())
+			.set_bucket_key_enabled(self.encryption.bucket_key)
+			.set_sse_customer_algorithm(self.encryption.customer_algorithm())
+			.set_sse_customer_key(self.encryption.customer_key.clone())
+			.set_sse_customer_key_md5(self.encryption.customer_key_md5.clone())
+			.set_if_match(if_match)
+			.set_if_none_match(if_none_match)
+			.send()
+			.await
+			.map_err(write_error)?;
+		self.record_write(path);
+
+		Ok(())
+	}
+
+	/// Copy an object (or one of its versions), replacing its metadata with the given content type if there is one.
+	async fn copy_object(
+		&self,
+		source: &Path,
+		source_version: Option<&str>,
+		destination: &Path,
+		content_type: Option<String>,
+	) -> Result<()> {
+		let mut copy_source = format!(
+			"{}/{}",
+			self.bucket,
+			source
+				.to_str()
+				.ok_or_else(|| Error::new(ErrorKind::InvalidData, "path is not valid utf-8",))?
+		);
+		if let Some(version_id) = source_version {
+			copy_source.push_str("?versionId=");
+			copy_source.push_str(version_id);
+		}
+
+		let request = self
+			.client
+			.copy_object()
+			.copy_source(copy_source)
+			.bucket(&self.bucket)
+			.key(
+				destination
+					.to_str()
+					.ok_or_else(|| Error::new(ErrorKind::InvalidData, "path is not valid utf-8"))?,
+			)
+			.set_metadata_directive(content_type.is_some().then_some(MetadataDirective::Replace))
+			.set_content_type(content_type)
+			.set_storage_class(self.storage_class(&WriteOptions::default()))
+			.set_server_side_encryption(self.encryption.algorithm.clone())
+			.set_ssekms_key_id(self.encryption.kms_key_id.clone())
+			.set_bucket_key_enabled(self.encryption.bucket_key)
+			.set_sse_customer_algorithm(self.encryption.customer_algorithm())
+			.set_sse_customer_key(self.encryption.customer_key.clone())
+			.set_sse_customer_key_md5(self.encryption.customer_key_md5.clone())
+			.set_copy_source_sse_customer_algorithm(self.encryption.customer_algorithm())
+			.set_copy_source_sse_customer_key(self.encryption.customer_key.clone())
+			.set_copy_source_sse_customer_key_md5(self.encryption.customer_key_md5.clone())
+			.send()
+			.await;
+
+		match request {
+			Ok(_) => {
+				self.record_write(destination);
+				Ok(())
+			},
+			Err(SdkError::ServiceError(error))
+				if error.err().meta().code() == Some("NoSuchKey") =>
+			{
+				Err(Error::from(ErrorKind::NotFound))
+			},
+			Err(e) => Err(write_error(e)),
+		}
+	}
+
+	/// The content type to give a file moved from `source` to `destination`, if it should change.
+	fn moved_content_type(&self, source: &Path, destination: &Path) -> Option<String> {
+		let mime_type = mime_guess::from_path(destination).first_or_octet_stream();
+
+		(self.detect_mime_on_move
+			&& mime_guess::from_path(source).first_or_octet_stream() != mime_type)
+			.then(|| mime_type.to_string())
+	}
+
+	/// Check that the bucket exists and the credentials can access it.
+	async fn head_bucket(&self) -> Result<()> {
+		match self.client.head_bucket().bucket(&self.bucket).send().await {
+			Ok(_) => Ok(()),
+			Err(SdkError::ServiceError(error)) => {
+				if error.err().is_not_found() {
+					return Err(Error::new(
+						ErrorKind::NotFound,
+						format!("The bucket {} does not exist.", self.bucket),
+					));
+				}
+
+				if error.raw().status().as_u16() == 403 {
+					return Err(Error::new(
+						ErrorKind::PermissionDenied,
+						format!("The credentials can't access the bucket {}.", self.bucket),
+					));
+				}
+
+				Err(Error::other(error.into_err()))
+			},
+			Err(e) => Err(Error::other(e)),
+		}
+	}
+
+	/// Build the request listing the objects under `path`, grouping them by directory for shallow listings.
+	fn list_request(&self, path: &Path, deep: bool) -> Result<ListObjectsV2FluentBuilder> {
+		let path = path
+			.to_str()
+			.ok_or_else(|| Error::new(ErrorKind::InvalidData, "path is not valid utf-8"))?;
+		let request =
+			self.client
+				.list_objects_v2()
+				.bucket(&self.bucket)
+				.prefix(if path.is_empty() {
+					String::new()
+				} else {
+					format!("{path}/")
+				});
+
+		Ok(if deep {
+			request
+		} else {
+			request.delimiter('/')
+		})
+	}
+
+	/// List the raw keys of the bucket, with full control over the `ListObjectsV2` parameters.
+	///
+	/// An escape hatch for custom traversals (like listing by a custom delimiter, or resuming from a key):
+	/// keys are returned as is, including directory markers, and aren't relative to any directory.
+	/// Pages are only fetched once the previous one was consumed.
+	#[must_use]
+	pub fn list_raw(&self, options: &ListOptions) -> BoxStream<'_, Result<ListEntry>> {
+		let request = self
+			.client
+			.list_objects_v2()
+			.bucket(&self.bucket)
+			.set_prefix(options.prefix.clone())
+			.set_delimiter(options.delimiter.clone())
+			.set_start_after(options.start_after.clone())
+			.set_max_keys(options.max_keys)
+			.fetch_owner(options.fetch_owner);
+
+		futures::stream::unfold(request.into_paginator().send(), |mut pages| async move {
+			let page = pages.next().await?;
+
+			Some((raw_page(page), pages))
+		})
+		.map_ok(|entries| futures::stream::iter(entries.into_iter().map(Ok)))
+		.try_flatten()
+		.boxed()
+	}
+}
+
+type ListPage =
+	std::result::Result<ListObjectsV2Output, SdkError<ListObjectsV2Error, HttpResponse>>;
+
+/// Get a page of a listing, or `None` if there's nothing to list.
+fn listed(page: ListPage) -> Result<Option<ListObjectsV2Output>> {
+	match page {
+		Ok(page) => Ok(Some(page)),
+		Err(SdkError::ServiceError(error)) => {
+			if error.err().meta().code() == Some("NoSuchKey") {
+				return Ok(None);
+			}
+
+			Err(Error::other(error.into_err()))
+		},
+		Err(e) => Err(Error::other(e)),
+	}
+}
+
+/// Get the files and directories of a page of a listing, skipping the markers of empty directories.
+fn list_page(page: ListPage) -> Result<Vec<StorageEntry>> {
+	let Some(page) = listed(page)? else {
+		return Ok(Vec::new());
+	};
+
+	let files = page.contents().iter().filter_map(|object| {
+		let path = PathBuf::from(object.key().filter(|key| !key.ends_with('/'))?);
+
+		Some(StorageEntry {
+			is_dir: false,
+			size: object.size().and_then(|size| u64::try_from(size).ok()),
+			last_modified: object
+				.last_modified()
+				.and_then(|date| SystemTime::try_from(*date).ok()),
+			// listings don't include the content type or ACL of objects
+			mime: None,
+			visibility: None,
+			path,
+		})
+	});
+	let directories = page
+		.common_prefixes()
+		.iter()
+		.filter_map(|prefix| prefix.prefix())
+		.map(|prefix| StorageEntry::directory(PathBuf::from(prefix.trim_end_matches('/'))));
+
+	Ok(files.chain(directories).collect())
+}
+
+/// Get the entries of a page of a raw listing, in the order S3 returned them (objects first, then common prefixes).
+fn raw_page(page: ListPage) -> Result<Vec<ListEntry>> {
+	let Some(page) = listed(page)? else {
+		return Ok(Vec::new());
+	};
+
+	let objects = page.contents().iter().filter_map(|object| {
+		Some(ListEntry::Object(ObjectEntry {
+			key: object.key()?.to_string(),
+			size: object.size().and_then(|size| u64::try_from(size).ok()),
+			last_modified: object
+				.last_modified()
+				.and_then(|date| SystemTime::try_from(*date).ok()),
+			e_tag: object.e_tag().map(str::to_string),
+			storage_class: object
+				.storage_class()
+				.map(|class| class.as_str().to_string()),
+			owner_id: object
+				.owner()
+				.and_then(|owner| owner.id())
+				.map(str::to_string),
+			owner_display_name: object
+				.owner()
+				.and_then(|owner| owner.display_name())
+				.map(str::to_string),
+		}))
+	});
+	let prefixes = page
+		.common_prefixes()
+		.iter()
+		.filter_map(|prefix| prefix.prefix())
+		.map(|prefix| ListEntry::CommonPrefix(prefix.to_string()));
+
+	Ok(objects.chain(prefixes).collect())
+}
+
+impl S3Adapter {
+	async fn put_object_acl(&self, path: &Path, visibility: Visibility) -> Result<()> {
+		let response = self
+			.client
+			.put_object_acl()
+			.bucket(&self.bucket)
+			.key(
+				path.to_str()
+					.ok_or_else(|| Error::new(ErrorKind::InvalidData, "path is not valid utf-8"))?,
+			)
+			.acl(visibility.into())
+			.send()
+			.await;
+
+		match response {
+			Ok(_) => Ok(()),
+			Err(SdkError::ServiceError(error)) => {
+				if error.err().is_no_such_key() {
+					return Err(Error::from(ErrorKind::NotFound));
+				}
+
+				Err(Error::other(error.into_err()))
+			},
+			Err(e) => Err(Error::other(e)),
+		}
+	}
+}
+
+impl Config {
+	/// Start building the config of a bucket.
+	#[must_use]
+	pub fn builder(bucket: impl Into<String>) -> ConfigBuilder {
+		ConfigBuilder(Self {
+			bucket: bucket.into(),
+			..Self::default()
+		})
+	}
+
+	/// Check that the config can be used to connect to a bucket.
+	fn validate(&self) -> Result<()> {
+		if self.bucket.is_empty() {
+			return Err(Error::new(
+				ErrorKind::InvalidInput,
+				"No bucket is configured.",
+			));
+		}
+
+		match &self.credentials {
+			Credentials::Static {
+				access_key,
+				secret_key,
+				..
+			} if access_key.is_empty() || secret_key.is_empty() => {
+				return Err(Error::new(
+					ErrorKind::InvalidInput,
+					"The access key and secret key must be set together.",
+				));
+			},
+			Credentials::Profile { name } if name.is_empty() => {
+				return Err(Error::new(
+					ErrorKind::InvalidInput,
+					"The profile to load credentials from has no name.",
+				));
+			},
+			Credentials::AssumeRole { role_arn, .. } if role_arn.is_empty() => {
+				return Err(Error::new(
+					ErrorKind::InvalidInput,
+					"The role to assume has no ARN.",
+				));
+			},
+			_ => {},
+		}
+
+		if let Some(public_url) = &self.public_url {
+			parse_http_url(public_url, "public URL")?;
+		}
+
+		match &self.encryption {
+			Some(ServerSideEncryption::Kms {
+				key_id: Some(key_id),
+				..
+			}) if key_id.is_empty() => {
+				return Err(Error::new(
+					ErrorKind::InvalidInput,
+					"The KMS key to encrypt files with has no ID.",
+				));
+			},
+			Some(ServerSideEncryption::Customer { key })
+				if !STANDARD.decode(key).is_ok_and(|key| key.len() == 32) =>
+			{
+				return Err(Error::new(
+					ErrorKind::InvalidInput,
+					"The customer-provided encryption key must be 32 base64-encoded bytes.",
+				));
+			},
+			_ => {},
+		}
+
+		if self.storage_class.as_ref().is_some_and(String::is_empty) {
+			return Err(Error::new(
+				ErrorKind::InvalidInput,
+				"The storage class is empty.",
+			));
+		}
+
+		if self
+			.part_size
+			.is_some_and(|part_size| !PART_SIZE_LIMITS.contains(&part_size))
+		{
+			return Err(Error::new(
+				ErrorKind::InvalidInput,
+				"The part size must be between 5 MiB and 5 GiB.",
+			));
+		}
+
+		if self.part_concurrency == Some(0) {
+			return Err(Error::new(
+				ErrorKind::InvalidInput,
+				"At least one part must be uploaded at a time.",
+			));
+		}
+
+		if self.endpoint.is_empty() {
+			return Ok(());
+		}
+
+		if self.accelerate {
+			return Err(Error::new(
+				ErrorKind::InvalidInput,
+				"Transfer acceleration can't be used with a custom endpoint.",
+			));
+		}
+
+		parse_http_url(&self.endpoint, "endpoint")?;
+
+		Ok(())
+	}
+}
+
+/// Parse a configured URL, making sure it's an HTTP(S) one.
+fn parse_http_url(url: &str, name: &str) -> Result<Url> {
+	let parsed = Url::parse(url).map_err(|e| {
+		Error::new(
+			ErrorKind::InvalidInput,
+			format!("The {name} {url} is not a valid URL: {e}"),
+		)
+	})?;
+	if !matches!(parsed.scheme(), "http" | "https") || !parsed.has_host() {
+		return Err(Error::new(
+			ErrorKind::InvalidInput,
+			format!("The {name} {url} is not an HTTP(S) URL."),
+		));
+	}
+
+	Ok(parsed)
+}
+
+/// Builds a validated [`Config`].
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct ConfigBuilder(Config);
+
+impl ConfigBuilder {
+	/// Start from the environment: the bucket comes from `S3_BUCKET`, the region from `S3_REGION` (or `AWS_REGION`),
+	/// the endpoint from `S3_ENDPOINT` (or `AWS_ENDPOINT_URL`) and the credentials from `S3_ACCESS_KEY` and `S3_SECRET_KEY`
+	/// (or `AWS_ACCESS_KEY_ID` and `AWS_SECRET_ACCESS_KEY`). Unset variables are left to their defaults.
+	#[must_use]
+	pub fn from_env() -> Self {
+		let var = |names: &[&str]| names.iter().find_map(|name| std::env::var(name).ok());
+
+		let access_key = var(&["S3_ACCESS_KEY", "AWS_ACCESS_KEY_ID"]);
+		let secret_key = var(&["S3_SECRET_KEY", "AWS_SECRET_ACCESS_KEY"]);
+		let credentials = if access_key.is_some() || secret_key.is_some() {
+			Credentials::Static {
+				access_key: access_key.unwrap_or_default(),
+				secret_key: secret_key.unwrap_or_default(),
+				session_token: var(&["AWS_SESSION_TOKEN"]),
+			}
+		} else {
+			Credentials::Anonymous
+		};
+
+		Self(Config {
+			bucket: var(&["S3_BUCKET"]).unwrap_or_default(),
+			region: var(&["S3_REGION", "AWS_REGION"]).unwrap_or_default(),
+			endpoint: var(&["S3_ENDPOINT", "AWS_ENDPOINT_URL"]).unwrap_or_default(),
+			credentials,
+			..Config::default()
+		})
+	}
+
+	/// Set the bucket.
+	#[must_use]
+	pub fn bucket(mut self, bucket: impl Into<String>) -> Self {
+		self.0.bucket = bucket.into();
+		self
+	}
+
+	/// Set the region, instead of [`DEFAULT_REGION`].
+	#[must_use]
+	pub fn region(mut self, region: impl Into<String>) -> Self {
+		self.0.region = region.into();
+		self
+	}
+
+	/// Connect to an S3-compatible store at this URL instead of AWS.
+	#[must_use]
+	pub fn endpoint(mut self, endpoint: impl Into<String>) -> Self {
+		self.0.endpoint = endpoint.into();
+		self
+	}
+
+	/// Sign requests with these static credentials.
+	#[must_use]
+	pub fn credentials(
+		mut self,
+		access_key: impl Into<String>,
+		secret_key: impl Into<String>,
+	) -> Self {
+		self.0.credentials = Credentials::Static {
+			access_key: access_key.into(),
+			secret_key: secret_key.into(),
+			session_token: None,
+		};
+		self
+	}
+
+	/// Get the credentials signing requests from the given source, like an assumed role or the default chain.
+	#[must_use]
+	pub fn credentials_from(mut self, credentials: Credentials) -> Self {
+		self.0.credentials = credentials;
+		self
+	}
+
+	/// Send unsigned requests, for public buckets.
+	#[must_use]
+	pub fn anonymous(mut self) -> Self {
+		self.0.credentials = Credentials::Anonymous;
+		self
+	}
+
+	/// Load the region, credentials and endpoint from a profile of the AWS shared config. See [`Credentials::Profile`].
+	#[must_use]
+	pub fn profile(mut self, profile: impl Into<String>) -> Self {
+		self.0.credentials = Credentials::Profile {
+			name: profile.into(),
+		};
+		self
+	}
+
+	/// Route requests through S3 Transfer Acceleration. See [`Config::accelerate`].
+	#[must_use]
+	pub const fn accelerate(mut self, accelerate: bool) -> Self {
+		self.0.accelerate = accelerate;
+		self
+	}
+
+	/// Use the dual-stack (IPv4 and IPv6) endpoints.
+	#[must_use]
+	pub const fn dual_stack(mut self, dual_stack: bool) -> Self {
+		self.0.dual_stack = dual_stack;
+		self
+	}
+
+	/// Account for clients' clocks being behind by up to `clock_skew`. See [`Config::clock_skew`].
+	#[must_use]
+	pub const fn clock_skew(mut self, clock_skew: Duration) -> Self {
+		self.0.clock_skew = clock_skew;
+		self
+	}
+
+	/// Update the `Content-Type` of moved files. See [`Config::detect_mime_on_move`].
+	#[must_use]
+	pub const fn detect_mime_on_move(mut self, detect_mime_on_move: bool) -> Self {
+		self.0.detect_mime_on_move = detect_mime_on_move;
+		self
+	}
+
+	/// Don't write directory marker objects. See [`Config::skip_directory_markers`].
+	#[must_use]
+	pub const fn skip_directory_markers(mut self, skip_directory_markers: bool) -> Self {
+		self.0.skip_directory_markers = skip_directory_markers;
+		self
+	}
 
-#[derive(Debug, Clone, Default)]
-pub struct Config {
-	pub bucket: String,
-	pub region: String,
-	pub endpoint: String,
-	pub access_key: String,
-	pub secret_key: String,
-}
+	/// Run a hook on every request sent to S3, after the hooks added before it.
+	#[must_use]
+	pub fn hook(mut self, hook: impl RequestHook + 'static) -> Self {
+		self.0.hooks.push(Arc::new(hook));
+		self
+	}
 
-#[derive(Debug, Clone)]
-#[allow(clippy::module_name_repetitions)]
-pub struct S3Adapter {
-	client: Client,
-	bucket: String,
+	/// Retry reads of freshly written files. See [`Config::read_after_write`].
+	#[must_use]
+	pub const fn read_after_write(mut self, read_after_write: ReadAfterWrite) -> Self {
+		self.0.read_after_write = Some(read_after_write);
+		self
+	}
+
+	/// Set when to check that the bucket is reachable.
+	#[must_use]
+	pub const fn validation(mut self, validation: Validation) -> Self {
+		self.0.validation = validation;
+		self
+	}
+
+	/// Upload files larger than `part_size` in parts of that size. See [`Config::part_size`].
+	#[must_use]
+	pub const fn part_size(mut self, part_size: u64) -> Self {
+		self.0.part_size = Some(part_size);
+		self
+	}
+
+	/// Upload up to `part_concurrency` parts of a file at once.
+	#[must_use]
+	pub const fn part_concurrency(mut self, part_concurrency: usize) -> Self {
+		self.0.part_concurrency = Some(part_concurrency);
+		self
+	}
+
+	/// Generate public URLs relative to `public_url`. See [`Config::public_url`].
+	#[must_use]
+	pub fn public_url(mut self, public_url: impl Into<String>) -> Self {
+		self.0.public_url = Some(public_url.into());
+		self
+	}
+
+	/// Encrypt the files written with `encryption`. See [`Config::encryption`].
+	#[must_use]
+	pub fn encryption(mut self, encryption: ServerSideEncryption) -> Self {
+		self.0.encryption = Some(encryption);
+		self
+	}
+
+	/// Encrypt the files written with a KMS key (or the `aws/s3` managed key if `key_id` is `None`), using an S3 Bucket Key.
+	#[must_use]
+	pub fn kms_encryption(self, key_id: Option<String>) -> Self {
+		self.encryption(ServerSideEncryption::Kms {
+			key_id,
+			bucket_key: true,
+		})
+	}
+
+	/// Write files in `storage_class` (like `STANDARD_IA`). See [`Config::storage_class`].
+	#[must_use]
+	pub fn storage_class(mut self, storage_class: impl Into<String>) -> Self {
+		self.0.storage_class = Some(storage_class.into());
+		self
+	}
+
+	/// Validate and build the config.
+	///
+	/// # Errors
+	///
+	/// This function will return an error (with `ErrorKind::InvalidInput`) if no bucket is set, if only one of the access key and secret key is set
+	/// (or the profile or role to assume is missing),
+	/// if the endpoint or public URL isn't an HTTP(S) URL (or the endpoint is combined with transfer acceleration),
+	/// if the part size or concurrency is out of bounds, if the KMS key ID is empty, if the customer-provided key isn't 32 base64-encoded bytes
+	/// or if the storage class is empty.
+	pub fn build(mut self) -> Result<Config> {
+		self.0.validate()?;
+
+		if self.0.region.is_empty() && !matches!(self.0.credentials, Credentials::Profile { .. }) {
+			self.0.region = DEFAULT_REGION.to_string();
+		}
+
+		Ok(self.0)
+	}
 }
 
 impl AdapterInit for S3Adapter {
-	type Error = Infallible;
+	type Error = Error;
 	type Config = Config;
 
-	async fn new(config: Self::Config) -> std::result::Result<Self, Self::Error> {
-		let cred = Credentials::new(config.access_key, config.secret_key, None, None, "custom");
+	async fn new(config: Self::Config) -> Result<Self> {
+		config.validate()?;
+
+		let from_profile = matches!(config.credentials, Credentials::Profile { .. });
+		let region = (!config.region.is_empty()).then(|| Region::new(config.region));
+
+		// without credentials, requests are sent unsigned
+		let mut builder = match config.credentials {
+			Credentials::Anonymous => aws_sdk_s3::Config::builder(),
+			Credentials::Static {
+				access_key,
+				secret_key,
+				session_token,
+			} => aws_sdk_s3::Config::builder().credentials_provider(AwsCredentials::new(
+				access_key,
+				secret_key,
+				session_token,
+				None,
+				"custom",
+			)),
+			Credentials::Profile { name } => aws_sdk_s3::config::Builder::from(
+				&aws_config::defaults(BehaviorVersion::latest())
+					.profile_name(name)
+					.load()
+					.await,
+			),
+			Credentials::Environment => aws_sdk_s3::Config::builder().credentials_provider(
+				aws_config::environment::EnvironmentVariableCredentialsProvider::new(),
+			),
+			Credentials::AssumeRole {
+				role_arn,
+				session_name,
+				external_id,
+				duration,
+			} => {
+				let mut provider = aws_config::sts::AssumeRoleProvider::builder(role_arn);
+				if let Some(session_name) = session_name {
+					provider = provider.session_name(session_name);
+				}
+				if let Some(external_id) = external_id {
+					provider = provider.external_id(external_id);
+				}
+				if let Some(duration) = duration {
+					provider = provider.session_length(duration);
+				}
+				if let Some(region) = &region {
+					provider = provider.region(region.clone());
+				}
+
+				aws_sdk_s3::Config::builder().credentials_provider(provider.build().await)
+			},
+			Credentials::DefaultChain => aws_sdk_s3::Config::builder().credentials_provider(
+				aws_config::default_provider::credentials::default_provider().await,
+			),
+		};
+
+		if !config.endpoint.is_empty() {
+			builder = builder.endpoint_url(config.endpoint);
+		}
+		if let Some(region) = region {
+			builder = builder.region(region);
+		} else if !from_profile {
+			builder = builder.region(Region::new(DEFAULT_REGION));
+		}
+
+		for hook in config.hooks {
+			builder = builder.interceptor(HookInterceptor(hook));
+		}
+
+		let public_url = config
+			.public_url
+			.map(|public_url| parse_http_url(&public_url, "public URL"))
+			.transpose()?
+			.map(BaseUrl::new);
 
-		Ok(Self {
+		let adapter = Self {
 			bucket: config.bucket,
+			clock_skew: config.clock_skew,
+			detect_mime_on_move: config.detect_mime_on_move,
+			skip_directory_markers: config.skip_directory_markers,
+			read_after_write: config.read_after_write,
+			recent_writes: Arc::default(),
+			public_url,
+			part_size: config.part_size.unwrap_or(PART_SIZE),
+			part_concurrency: config.part_concurrency.unwrap_or(PART_CONCURRENCY),
+			encryption: Encryption::new(config.encryption),
+			storage_class: config.storage_class,
 			client: Client::from_conf(
-				aws_sdk_s3::Config::builder()
-					.force_path_style(true)
-					.credentials_provider(cred)
-					.endpoint_url(config.endpoint)
-					.region(Region::new(config.region))
+				builder
+					.force_path_style(!config.accelerate)
+					.accelerate(config.accelerate)
+					.use_dual_stack(config.dual_stack)
 					.build(),
 			),
-		})
+		};
+
+		if config.validation == Validation::Eager {
+			adapter.head_bucket().await?;
+		}
+
+		Ok(adapter)
 	}
 }
 
 impl Adapter for S3Adapter {
 	async fn file_exists(&self, path: &Path) -> Result<bool> {
-		let request = self
-			.client
-			.head_object()
-			.bucket(&self.bucket)
-			.key(
-				path.to_str()
-					.ok_or_else(|| Error::new(ErrorKind::InvalidData, "path is not valid utf-8"))?,
-			)
-			.send()
-			.await;
-
-		match request {
+		match self.head_object(path).await {
 			Ok(_) => Ok(true),
-			Err(SdkError::ServiceError(error)) => {
-				if error.err().is_not_found() {
-					return Ok(false);
-				}
-
-				Err(Error::new(ErrorKind::Other, error.into_err()))
-			},
-			Err(e) => Err(Error::new(ErrorKind::Other, e)),
+			Err(error) if error.kind() == ErrorKind::NotFound => Ok(false),
+			Err(error) => Err(error),
 		}
 	}
 
@@ -108,35 +1495,157 @@ impl Adapter for S3Adapter {
 					return Ok(false);
 				}
 
-				Err(Error::new(ErrorKind::Other, error.into_err()))
+				Err(Error::other(error.into_err()))
 			},
-			Err(e) => Err(Error::new(ErrorKind::Other, e)),
+			Err(e) => Err(Error::other(e)),
 		}
 	}
 
-	async fn write(&mut self, path: &Path, content: &[u8]) -> Result<()> {
-		self.client
-			.put_object()
-			.bucket(&self.bucket)
-			.key(
-				path.to_str()
-					.ok_or_else(|| Error::new(ErrorKind::InvalidData, "path is not valid utf-8"))?,
-			)
-			.body(ByteStream::from(content.as_ref().to_vec()))
-			.content_type(
-				mime_guess::from_path(path)
-					.first_or_octet_stream()
-					.to_string(),
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.write_with(path, content, &WriteOptions::default())
+			.await
+	}
+
+	async fn write_with(&self, path: &Path, content: &[u8], options: &WriteOptions) -> Result<()> {
+		if content.len() as u64 > self.part_size {
+			let mut reader = futures::io::Cursor::new(content);
+			let first = read_part(&mut reader, self.part_size).await?;
+
+			return self
+				.write_multipart(path, options, first, &mut reader)
+				.await;
+		}
+
+		// zero-byte files (like directory markers) don't need a buffer at all
+		let body = if content.is_empty() {
+			ByteStream::from_static(&[])
+		} else {
+			ByteStream::from(content.to_vec())
+		};
+
+		self.put_object(path, body, options, None, None).await
+	}
+
+	/// Write with an `If-None-Match` or `If-Match` header, so S3 rejects the write if the file was created or changed in the meantime.
+	/// Files not modified since a given time are looked up first, then written only if their `ETag` still matches.
+	///
+	/// Conditional writes are always uploaded in a single request, which S3 limits to 5 GiB.
+	async fn write_if(
+		&self,
+		path: &Path,
+		content: &[u8],
+		precondition: &Precondition,
+	) -> Result<()> {
+		let (if_match, if_none_match) = match precondition {
+			Precondition::NotExists => (None, Some("*".to_string())),
+			Precondition::MatchesChecksum(etag) => (Some(etag.clone()), None),
+			Precondition::NotModifiedSince(time) => {
+				let head = match self.head_object(path).await {
+					Ok(head) => head,
+					Err(error) if error.kind() == ErrorKind::NotFound => {
+						return Err(precondition::failed(path));
+					},
+					Err(error) => return Err(error),
+				};
+
+				let last_modified = head
+					.last_modified
+					.ok_or_else(|| Error::other("S3 did not return a Last-Modified header."))
+					.and_then(|time| SystemTime::try_from(time).map_err(Error::other))?;
+				if last_modified > *time {
+					return Err(precondition::failed(path));
+				}
+
+				(head.e_tag, None)
+			},
+		};
+
+		match self
+			.put_object(
+				path,
+				ByteStream::from(content.to_vec()),
+				&WriteOptions::default(),
+				if_match,
+				if_none_match,
 			)
-			.send()
 			.await
-			.map_err(|e| Error::new(ErrorKind::Other, e))?;
+		{
+			Err(error) if error.kind() == ErrorKind::AlreadyExists => {
+				Err(precondition::failed(path))
+			},
+			result => result,
+		}
+	}
 
-		Ok(())
+	/// Write the file directly, since objects only become visible once fully uploaded.
+	async fn write_atomic(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.write(path, content).await
+	}
+
+	/// Write a file from a reader, with a multipart upload if it's larger than a single part.
+	async fn write_stream(
+		&self,
+		path: &Path,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		let first = read_part(reader, self.part_size).await?;
+		if (first.len() as u64) < self.part_size {
+			// the whole file fits in the part that was read, which is uploaded as is instead of being copied
+			return self
+				.put_object(
+					path,
+					ByteStream::from(first),
+					&WriteOptions::default(),
+					None,
+					None,
+				)
+				.await;
+		}
+
+		self.write_multipart(path, &WriteOptions::default(), first, reader)
+			.await
 	}
 
 	async fn read(&self, path: &Path) -> Result<Contents> {
-		let request = match self
+		let request = self.get_object(path, None).await?;
+
+		Ok(Contents::from_bytestream(request.body).await?)
+	}
+
+	async fn read_stream(&self, path: &Path) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+		let request = self.get_object(path, None).await?;
+
+		let chunks = futures::stream::try_unfold(request.body, |mut body| async move {
+			let chunk = body.try_next().await.map_err(Error::other)?;
+
+			Ok(chunk.map(|chunk| (chunk, body)))
+		});
+
+		Ok(Box::new(Box::pin(chunks).into_async_read()))
+	}
+
+	async fn read_into(&self, path: &Path, buf: &mut (dyn BufMut + Send)) -> Result<()> {
+		let mut request = self.get_object(path, None).await?;
+
+		#[allow(clippy::cast_sign_loss)]
+		if let Some(content_length) = request.content_length {
+			ensure_capacity(buf, content_length as u64)?;
+		}
+
+		while let Some(chunk) = request.body.try_next().await.map_err(Error::other)? {
+			ensure_capacity(buf, chunk.len() as u64)?;
+			buf.put_slice(&chunk);
+		}
+
+		Ok(())
+	}
+
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		if len == 0 {
+			return Ok(Contents::from(Vec::new()));
+		}
+
+		let request = self
 			.client
 			.get_object()
 			.bucket(&self.bucket)
@@ -144,24 +1653,100 @@ impl Adapter for S3Adapter {
 				path.to_str()
 					.ok_or_else(|| Error::new(ErrorKind::InvalidData, "path is not valid utf-8"))?,
 			)
+			.range(format!("bytes={offset}-{}", offset.saturating_add(len - 1)))
+			.set_sse_customer_algorithm(self.encryption.customer_algorithm())
+			.set_sse_customer_key(self.encryption.customer_key.clone())
+			.set_sse_customer_key_md5(self.encryption.customer_key_md5.clone())
 			.send()
-			.await
-		{
-			Ok(request) => request,
+			.await;
+
+		match request {
+			Ok(request) => Ok(Contents::from_bytestream(request.body).await?),
 			Err(SdkError::ServiceError(error)) => {
 				if error.err().is_no_such_key() {
 					return Err(Error::from(ErrorKind::NotFound));
 				}
 
-				return Err(Error::new(ErrorKind::Other, error.into_err()));
+				// S3 rejects ranges starting past the end of the object.
+				if error.err().meta().code() == Some("InvalidRange") {
+					return Ok(Contents::from(Vec::new()));
+				}
+
+				Err(Error::other(error.into_err()))
 			},
-			Err(e) => return Err(Error::new(ErrorKind::Other, e)),
+			Err(e) => Err(Error::other(e)),
+		}
+	}
+
+	async fn list_versions(&self, prefix: &Path) -> Result<Vec<FileVersion>> {
+		let prefix = prefix
+			.to_str()
+			.ok_or_else(|| Error::new(ErrorKind::InvalidData, "path is not valid utf-8"))?;
+		let to_system_time = |date: Option<&DateTime>| {
+			date.map_or(Ok(SystemTime::UNIX_EPOCH), |date| {
+				SystemTime::try_from(*date).map_err(Error::other)
+			})
 		};
 
+		let mut versions = Vec::new();
+		let (mut key_marker, mut version_id_marker) = (None, None);
+
+		loop {
+			let response = self
+				.client
+				.list_object_versions()
+				.bucket(&self.bucket)
+				.prefix(prefix)
+				.set_key_marker(key_marker.take())
+				.set_version_id_marker(version_id_marker.take())
+				.send()
+				.await
+				.map_err(Error::other)?;
+
+			for version in response.versions() {
+				versions.push(FileVersion {
+					path: PathBuf::from(version.key().unwrap_or_default()),
+					version_id: version.version_id().unwrap_or("null").to_string(),
+					last_modified: to_system_time(version.last_modified())?,
+					#[allow(clippy::cast_sign_loss)]
+					size: version.size().unwrap_or_default() as u64,
+					deleted: false,
+					is_latest: version.is_latest().unwrap_or_default(),
+				});
+			}
+
+			for marker in response.delete_markers() {
+				versions.push(FileVersion {
+					path: PathBuf::from(marker.key().unwrap_or_default()),
+					version_id: marker.version_id().unwrap_or("null").to_string(),
+					last_modified: to_system_time(marker.last_modified())?,
+					size: 0,
+					deleted: true,
+					is_latest: marker.is_latest().unwrap_or_default(),
+				});
+			}
+
+			if response.is_truncated() != Some(true) {
+				return Ok(versions);
+			}
+
+			key_marker = response.next_key_marker;
+			version_id_marker = response.next_version_id_marker;
+		}
+	}
+
+	async fn read_version(&self, path: &Path, version_id: &str) -> Result<Contents> {
+		let request = self.get_object(path, Some(version_id)).await?;
+
 		Ok(Contents::from_bytestream(request.body).await?)
 	}
 
-	async fn delete_directory(&mut self, path: &Path) -> Result<()> {
+	/// Copy the version over the current object server-side, keeping its MIME type and metadata.
+	async fn restore_version(&self, path: &Path, version_id: &str) -> Result<()> {
+		self.copy_object(path, Some(version_id), path, None).await
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
 		let matching_files = self.list_contents(path, true).await?;
 
 		self.client
@@ -185,12 +1770,16 @@ impl Adapter for S3Adapter {
 			)
 			.send()
 			.await
-			.map_err(|e| Error::new(ErrorKind::Other, e))?;
+			.map_err(Error::other)?;
 
 		Ok(())
 	}
 
-	async fn create_directory(&mut self, path: &Path) -> Result<()> {
+	async fn create_directory(&self, path: &Path) -> Result<()> {
+		if self.skip_directory_markers {
+			return Ok(());
+		}
+
 		self.client
 			.put_object()
 			.bucket(&self.bucket)
@@ -202,131 +1791,440 @@ impl Adapter for S3Adapter {
 					)?
 			))
 			.body(ByteStream::default())
+			.set_server_side_encryption(self.encryption.algorithm.clone())
+			.set_ssekms_key_id(self.encryption.kms_key_id.clone())
+			.set_bucket_key_enabled(self.encryption.bucket_key)
+			.set_sse_customer_algorithm(self.encryption.customer_algorithm())
+			.set_sse_customer_key(self.encryption.customer_key.clone())
+			.set_sse_customer_key_md5(self.encryption.customer_key_md5.clone())
 			.send()
 			.await
-			.map_err(|e| Error::new(ErrorKind::Other, e))?;
+			.map_err(write_error)?;
 
 		Ok(())
 	}
 
+	/// Change the `Content-Type` of a file, by copying it onto itself.
+	///
+	/// This replaces the rest of the object's metadata too, and resets its ACL to the bucket's default.
+	async fn set_mime_type(&self, path: &Path, mime_type: &Mime) -> Result<()> {
+		self.copy_object(path, None, path, Some(mime_type.to_string()))
+			.await
+	}
+
 	/// Set the visibility of a file.
 	///
 	/// Note that some S3 providers (like Minio) don't implement this feature.
-	async fn set_visibility(&mut self, path: &Path, visibility: Visibility) -> Result<()> {
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
+		self.put_object_acl(path, visibility).await
+	}
+
+	async fn set_visibility_many(
+		&self,
+		paths: &[PathBuf],
+		visibility: Visibility,
+	) -> Vec<(PathBuf, Error)> {
+		futures::stream::iter(paths.iter().cloned())
+			.map(|path| async move {
+				let result = self.put_object_acl(&path, visibility).await;
+				result.err().map(|error| (path, error))
+			})
+			.buffer_unordered(ACL_CONCURRENCY)
+			.filter_map(futures::future::ready)
+			.collect()
+			.await
+	}
+
+	async fn visibility(&self, path: &Path) -> Result<Visibility> {
 		let response = self
 			.client
-			.put_object_acl()
+			.get_object_acl()
 			.bucket(&self.bucket)
 			.key(
 				path.to_str()
 					.ok_or_else(|| Error::new(ErrorKind::InvalidData, "path is not valid utf-8"))?,
 			)
-			.acl(visibility.into())
 			.send()
 			.await;
 
 		match response {
-			Ok(_) => Ok(()),
+			Ok(response) => Ok(response.into()),
 			Err(SdkError::ServiceError(error)) => {
 				if error.err().is_no_such_key() {
 					return Err(Error::from(ErrorKind::NotFound));
 				}
 
-				Err(Error::new(ErrorKind::Other, error.into_err()))
+				Err(Error::other(error.into_err()))
+			},
+			Err(e) => Err(Error::other(e)),
+		}
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		self.head_object(path)
+			.await?
+			.content_type()
+			.map(Mime::from_str)
+			.ok_or_else(|| Error::from(ErrorKind::NotFound))?
+			.map_err(Error::other)
+	}
+
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		Ok(self.head_object(path).await?.metadata.unwrap_or_default())
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		let response = self.head_object(path).await?;
+
+		SystemTime::try_from(
+			response
+				.last_modified
+				.ok_or_else(|| Error::other("S3 did not return a Last-Modified header."))?,
+		)
+		.map_err(Error::other)
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		let response = self.head_object(path).await?;
+
+		#[allow(clippy::cast_sign_loss)]
+		Ok(response
+			.content_length
+			.ok_or_else(|| Error::other("S3 did not return a Content-Length header"))? as u64)
+	}
+
+	/// Delete a file from the filesystem.
+	///
+	/// Note that some S3 providers will return a success response even if the file does not exist.
+	async fn delete(&self, path: &Path) -> Result<()> {
+		self.client
+			.delete_object()
+			.bucket(&self.bucket)
+			.key(
+				path.to_str()
+					.ok_or_else(|| Error::new(ErrorKind::InvalidData, "path is not valid utf-8"))?,
+			)
+			.send()
+			.await
+			.map_err(Error::other)?;
+
+		// a deleted file shouldn't be waited for
+		self.recent_writes
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.remove(path);
+
+		Ok(())
+	}
+
+	async fn delete_many(&self, paths: &[PathBuf]) -> Vec<(PathBuf, Error)> {
+		let mut failed = Vec::new();
+
+		for chunk in paths.chunks(DELETE_BATCH_SIZE) {
+			let mut objects = Vec::with_capacity(chunk.len());
+			for path in chunk {
+				match path.to_str() {
+					Some(key) => {
+						objects.push(ObjectIdentifier::builder().key(key).build().unwrap());
+					},
+					None => failed.push((
+						path.clone(),
+						Error::new(ErrorKind::InvalidData, "path is not valid utf-8"),
+					)),
+				}
+			}
+			if objects.is_empty() {
+				continue;
+			}
+
+			let response = self
+				.client
+				.delete_objects()
+				.bucket(&self.bucket)
+				.delete(
+					Delete::builder()
+						.set_objects(Some(objects.clone()))
+						.quiet(true)
+						.build()
+						.unwrap(),
+				)
+				.send()
+				.await;
+
+			match response {
+				// quiet mode only reports the objects that couldn't be deleted
+				Ok(output) => failed.extend(output.errors().iter().map(|error| {
+					(
+						PathBuf::from(error.key().unwrap_or_default()),
+						Error::other(format!(
+							"{}: {}",
+							error.code().unwrap_or("DeleteError"),
+							error.message().unwrap_or_default()
+						)),
+					)
+				})),
+				Err(error) => {
+					let message = error.to_string();
+					failed.extend(objects.iter().map(|object| {
+						(PathBuf::from(object.key()), Error::other(message.clone()))
+					}));
+				},
+			}
+		}
+
+		// deleted files shouldn't be waited for
+		let mut recent_writes = self
+			.recent_writes
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner);
+		for path in paths {
+			recent_writes.remove(path);
+		}
+
+		failed
+	}
+
+	async fn write_many(&self, files: &[(&Path, &[u8])]) -> Vec<(PathBuf, Error)> {
+		// boxed so the future doesn't hold a closure borrowing the files, which trips up the `Send` check
+		let writes: Vec<BoxFuture<'_, Option<(PathBuf, Error)>>> = files
+			.iter()
+			.map(|&(path, content)| {
+				async move {
+					let result = self.write(path, content).await;
+					result.err().map(|error| (path.to_path_buf(), error))
+				}
+				.boxed()
+			})
+			.collect();
+
+		futures::stream::iter(writes)
+			.buffer_unordered(WRITE_CONCURRENCY)
+			.filter_map(futures::future::ready)
+			.collect()
+			.await
+	}
+
+	/// List the files under a directory. Shallow listings include its subdirectories too, taken from the common prefixes of the keys.
+	///
+	/// Directory marker objects (`prefix/`) are never listed as files.
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<std::path::PathBuf>> {
+		Ok(self
+			.list_contents_with_metadata(path, deep)
+			.await?
+			.into_iter()
+			.map(|entry| entry.path)
+			.collect())
+	}
+
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		let mut entries = BTreeMap::new();
+		let mut pages = self.list_request(path, deep)?.into_paginator().send();
+
+		while let Some(page) = pages.next().await {
+			for entry in list_page(page)? {
+				entries.insert(entry.path.clone(), entry);
+			}
+		}
+
+		Ok(entries.into_values().collect())
+	}
+
+	fn list_stream<'a>(&'a self, path: &Path, deep: bool) -> BoxStream<'a, Result<PathBuf>> {
+		let pages = match self.list_request(path, deep) {
+			Ok(request) => request.into_paginator().send(),
+			Err(error) => return futures::stream::once(async { Err(error) }).boxed(),
+		};
+
+		// pages are only fetched once the previous one was consumed
+		futures::stream::unfold(pages, |mut pages| async move {
+			let page = pages.next().await?;
+
+			Some((list_page(page), pages))
+		})
+		.map_ok(|entries| futures::stream::iter(entries.into_iter().map(|entry| Ok(entry.path))))
+		.try_flatten()
+		.boxed()
+	}
+
+	fn list_stream_after<'a>(
+		&'a self,
+		path: &Path,
+		deep: bool,
+		after: Option<&Path>,
+	) -> BoxStream<'a, Result<PathBuf>> {
+		let after = after.map(|after| path.join(after));
+		let request =
+			self.list_request(path, deep).and_then(|request| {
+				let Some(after) = &after else {
+					return Ok(request);
+				};
+
+				Ok(request.start_after(after.to_str().ok_or_else(|| {
+					Error::new(ErrorKind::InvalidData, "path is not valid utf-8")
+				})?))
+			});
+		let pages = match request {
+			Ok(request) => request.into_paginator().send(),
+			Err(error) => return futures::stream::once(async { Err(error) }).boxed(),
+		};
+
+		futures::stream::unfold(pages, |mut pages| async move {
+			let page = pages.next().await?;
+
+			Some((list_page(page), pages))
+		})
+		.map_ok(move |mut entries| {
+			// pages come in key order, but list their files and directories separately
+			entries.retain(|entry| {
+				after
+					.as_deref()
+					.is_none_or(|after| key_order(&entry.path, after).is_gt())
+			});
+			entries.sort_unstable_by(|a, b| key_order(&a.path, &b.path));
+
+			futures::stream::iter(entries.into_iter().map(|entry| Ok(entry.path)))
+		})
+		.try_flatten()
+		.boxed()
+	}
+
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		let content_type = self.moved_content_type(source, destination);
+
+		match self
+			.copy_object(source, None, destination, content_type)
+			.await
+		{
+			Err(error)
+				if error.kind() == ErrorKind::NotFound && self.directory_exists(source).await? =>
+			{
+				// S3 has no directories, so every object under the prefix has to be moved
+				for file in self.list_contents(source, true).await? {
+					let relative = file.strip_prefix(source).unwrap_or(&file);
+					let destination = destination.join(relative);
+					let content_type = self.moved_content_type(&file, &destination);
+
+					self.copy_object(&file, None, &destination, content_type)
+						.await?;
+					self.delete(&file).await?;
+				}
+
+				return Ok(());
 			},
-			Err(e) => Err(Error::new(ErrorKind::Other, e)),
+			result => result?,
 		}
+		self.delete(source).await?;
+
+		Ok(())
 	}
 
-	async fn visibility(&self, path: &Path) -> Result<Visibility> {
-		let response = self
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.copy_object(source, None, destination, None).await
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		self.head_object(path)
+			.await?
+			.e_tag
+			.ok_or_else(|| Error::other("S3 did not return an ETag header"))
+	}
+
+	async fn public_url(&self, path: &Path) -> Result<Url> {
+		let Some(public_url) = &self.public_url else {
+			return Err(Error::new(
+				ErrorKind::Unsupported,
+				"Generating public URLs requires a public URL to be configured.",
+			));
+		};
+
+		public_url.url(path)
+	}
+
+	async fn temporary_url(&self, path: &Path, expires_in: Duration) -> Result<Url> {
+		self.temporary_url_with(path, expires_in, &TemporaryUrlOptions::default())
+			.await
+	}
+
+	async fn temporary_url_with(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+		options: &TemporaryUrlOptions,
+	) -> Result<Url> {
+		let request = self
 			.client
-			.get_object_acl()
+			.get_object()
 			.bucket(&self.bucket)
 			.key(
 				path.to_str()
 					.ok_or_else(|| Error::new(ErrorKind::InvalidData, "path is not valid utf-8"))?,
 			)
-			.send()
+			.set_response_content_disposition(options.content_disposition.clone())
+			.set_response_content_type(options.content_type.as_ref().map(ToString::to_string))
+			.set_response_cache_control(options.cache_control.clone())
+			.presigned(self.presigning_config(expires_in)?)
 			.await;
 
-		match response {
-			Ok(response) => Ok(response.into()),
+		match request {
+			Ok(presigned_req) => Ok(presigned_req.uri().parse().map_err(Error::other)?),
 			Err(SdkError::ServiceError(error)) => {
 				if error.err().is_no_such_key() {
 					return Err(Error::from(ErrorKind::NotFound));
 				}
 
-				Err(Error::new(ErrorKind::Other, error.into_err()))
+				Err(Error::other(error.into_err()))
 			},
-			Err(e) => Err(Error::new(ErrorKind::Other, e)),
+			Err(e) => Err(Error::other(e)),
 		}
 	}
 
-	async fn mime_type(&self, path: &Path) -> Result<Mime> {
-		let response = self
-			.client
-			.head_object()
-			.bucket(&self.bucket)
-			.key(
-				path.to_str()
-					.ok_or_else(|| Error::new(ErrorKind::InvalidData, "path is not valid utf-8"))?,
-			)
-			.send()
-			.await;
-
-		match response {
-			Ok(response) => Ok(response
-				.content_type()
-				.map(Mime::from_str)
-				.ok_or_else(|| Error::from(ErrorKind::NotFound))?
-				.map_err(|e| Error::new(ErrorKind::Other, e))?),
-			Err(SdkError::ServiceError(error)) => {
-				if error.err().is_not_found() {
-					return Err(Error::from(ErrorKind::NotFound));
-				}
+	fn path_model(&self) -> PathModel {
+		PathModel::FlatKeys
+	}
 
-				Err(Error::new(ErrorKind::Other, error.into_err()))
-			},
-			Err(e) => Err(Error::new(ErrorKind::Other, e)),
+	fn layers(&self) -> Vec<AdapterLayer> {
+		let mut layer = AdapterLayer::new("s3").with("bucket", &self.bucket);
+		if let Some(region) = self.client.config().region() {
+			layer = layer.with("region", region);
 		}
+
+		vec![layer.with("part_size", self.part_size)]
 	}
+}
 
-	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
-		let response = self
-			.client
-			.head_object()
+impl ObjectLock for S3Adapter {
+	async fn set_retention(&self, path: &Path, retention: Retention) -> Result<()> {
+		self.client
+			.put_object_retention()
 			.bucket(&self.bucket)
 			.key(
 				path.to_str()
 					.ok_or_else(|| Error::new(ErrorKind::InvalidData, "path is not valid utf-8"))?,
 			)
+			.retention(
+				ObjectLockRetention::builder()
+					.mode(match retention.mode {
+						RetentionMode::Governance => ObjectLockRetentionMode::Governance,
+						RetentionMode::Compliance => ObjectLockRetentionMode::Compliance,
+					})
+					.retain_until_date(DateTime::from(retention.retain_until))
+					.build(),
+			)
 			.send()
-			.await;
-
-		match response {
-			Ok(response) => Ok(SystemTime::try_from(response.last_modified.ok_or_else(|| {
-				Error::new(
-					ErrorKind::Other,
-					"S3 did not return a Last-Modified header.",
-				)
-			})?)
-			.map_err(|e| Error::new(ErrorKind::Other, e))?),
-			Err(SdkError::ServiceError(error)) => {
-				if error.err().is_not_found() {
-					return Err(Error::from(ErrorKind::NotFound));
-				}
+			.await
+			.map_err(Error::other)?;
 
-				Err(Error::new(ErrorKind::Other, error.into_err()))
-			},
-			Err(e) => Err(Error::new(ErrorKind::Other, e)),
-		}
+		Ok(())
 	}
 
-	async fn file_size(&self, path: &Path) -> Result<u64> {
+	async fn retention(&self, path: &Path) -> Result<Option<Retention>> {
 		let response = self
 			.client
-			.head_object()
+			.get_object_retention()
 			.bucket(&self.bucket)
 			.key(
 				path.to_str()
@@ -335,133 +2233,61 @@ impl Adapter for S3Adapter {
 			.send()
 			.await;
 
-		match response {
-			#[allow(clippy::cast_sign_loss)]
-			Ok(response) => Ok(response.content_length.ok_or_else(|| {
-				Error::new(
-					ErrorKind::Other,
-					"S3 did not return a Content-Length header",
-				)
-			})? as u64),
+		let retention = match response {
+			Ok(response) => response.retention,
 			Err(SdkError::ServiceError(error)) => {
-				if error.err().is_not_found() {
-					return Err(Error::from(ErrorKind::NotFound));
+				if error.err().meta().code() == Some("NoSuchObjectLockConfiguration") {
+					return Ok(None);
 				}
 
-				Err(Error::new(ErrorKind::Other, error.into_err()))
+				return Err(Error::other(error.into_err()));
 			},
-			Err(e) => Err(Error::new(ErrorKind::Other, e)),
-		}
+			Err(e) => return Err(Error::other(e)),
+		};
+
+		let Some((mode, retain_until)) =
+			retention.and_then(|retention| Some((retention.mode?, retention.retain_until_date?)))
+		else {
+			return Ok(None);
+		};
+
+		Ok(Some(Retention {
+			mode: match mode {
+				ObjectLockRetentionMode::Compliance => RetentionMode::Compliance,
+				_ => RetentionMode::Governance,
+			},
+			retain_until: SystemTime::try_from(retain_until).map_err(Error::other)?,
+		}))
 	}
 
-	/// Delete a file from the filesystem.
-	///
-	/// Note that some S3 providers will return a success response even if the file does not exist.
-	async fn delete(&mut self, path: &Path) -> Result<()> {
+	async fn set_legal_hold(&self, path: &Path, enabled: bool) -> Result<()> {
 		self.client
-			.delete_object()
+			.put_object_legal_hold()
 			.bucket(&self.bucket)
 			.key(
 				path.to_str()
 					.ok_or_else(|| Error::new(ErrorKind::InvalidData, "path is not valid utf-8"))?,
 			)
+			.legal_hold(
+				ObjectLockLegalHold::builder()
+					.status(if enabled {
+						ObjectLockLegalHoldStatus::On
+					} else {
+						ObjectLockLegalHoldStatus::Off
+					})
+					.build(),
+			)
 			.send()
 			.await
-			.map_err(|e| Error::new(ErrorKind::Other, e))?;
-
-		Ok(())
-	}
-
-	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<std::path::PathBuf>> {
-		let mut paths = Vec::new();
-
-		let mut request = self
-			.client
-			.list_objects_v2()
-			.bucket(&self.bucket)
-			.prefix(format!(
-				"{}/",
-				path.to_str()
-					.ok_or_else(
-						|| Error::new(ErrorKind::InvalidData, "path is not valid utf-8",)
-					)?
-			));
-
-		if !deep {
-			request = request.delimiter('/');
-		}
-
-		let mut response = request.into_paginator().send();
-
-		while let Some(result) = response.next().await {
-			let contents = match result {
-				Ok(result) => result.contents.unwrap_or_default(),
-				Err(SdkError::ServiceError(error)) => {
-					if error.err().meta().code() == Some("NoSuchKey") {
-						continue;
-					}
-
-					return Err(Error::new(ErrorKind::Other, error.into_err()));
-				},
-				Err(e) => return Err(Error::new(ErrorKind::Other, e)),
-			};
-
-			paths.extend(
-				contents
-					.iter()
-					.filter_map(|content| content.key())
-					.map(|s| PathBuf::from_str(s).unwrap()),
-			);
-		}
-
-		Ok(paths)
-	}
-
-	async fn r#move(&mut self, source: &Path, destination: &Path) -> Result<()> {
-		self.copy(source, destination).await?;
-		self.delete(source).await?;
+			.map_err(Error::other)?;
 
 		Ok(())
 	}
 
-	async fn copy(&mut self, source: &Path, destination: &Path) -> Result<()> {
-		let request =
-			self.client
-				.copy_object()
-				.copy_source(format!(
-					"{}/{}",
-					self.bucket,
-					source.to_str().ok_or_else(|| Error::new(
-						ErrorKind::InvalidData,
-						"path is not valid utf-8",
-					))?
-				))
-				.bucket(&self.bucket)
-				.key(
-					destination.to_str().ok_or_else(|| {
-						Error::new(ErrorKind::InvalidData, "path is not valid utf-8")
-					})?,
-				)
-				.send()
-				.await;
-
-		match request {
-			Ok(_) => Ok(()),
-			Err(SdkError::ServiceError(error)) => {
-				if error.err().meta().code() == Some("NoSuchKey") {
-					return Err(Error::from(ErrorKind::NotFound));
-				}
-
-				Err(Error::new(ErrorKind::Other, error.into_err()))
-			},
-			Err(e) => Err(Error::new(ErrorKind::Other, e)),
-		}
-	}
-
-	async fn checksum(&self, path: &Path) -> Result<String> {
-		let request = self
+	async fn legal_hold(&self, path: &Path) -> Result<bool> {
+		let response = self
 			.client
-			.head_object()
+			.get_object_legal_hold()
 			.bucket(&self.bucket)
 			.key(
 				path.to_str()
@@ -470,52 +2296,72 @@ impl Adapter for S3Adapter {
 			.send()
 			.await;
 
-		match request {
-			Ok(request) => request
-				.e_tag
-				.ok_or_else(|| Error::new(ErrorKind::Other, "S3 did not return an ETag header")),
+		match response {
+			Ok(response) => Ok(response.legal_hold.and_then(|legal_hold| legal_hold.status)
+				== Some(ObjectLockLegalHoldStatus::On)),
 			Err(SdkError::ServiceError(error)) => {
-				if error.err().is_not_found() {
-					return Err(Error::from(ErrorKind::NotFound));
+				if error.err().meta().code() == Some("NoSuchObjectLockConfiguration") {
+					return Ok(false);
 				}
 
-				Err(Error::new(ErrorKind::Other, error.into_err()))
+				Err(Error::other(error.into_err()))
 			},
-			Err(e) => Err(Error::new(ErrorKind::Other, e)),
+			Err(e) => Err(Error::other(e)),
 		}
 	}
 }
 
 impl TemporaryUrlGenerator for S3Adapter {
 	async fn temporary_url(&self, path: &Path, expires_in: Duration) -> Result<Url> {
-		let request = self
-			.client
-			.get_object()
-			.bucket(&self.bucket)
-			.key(
-				path.to_str()
-					.ok_or_else(|| Error::new(ErrorKind::InvalidData, "path is not valid utf-8"))?,
-			)
-			.presigned(
-				PresigningConfig::expires_in(expires_in)
+		Adapter::temporary_url(self, path, expires_in).await
+	}
+}
+
+impl TemporaryUploadUrlGenerator for S3Adapter {
+	async fn temporary_upload_url(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+		constraints: &UploadConstraints,
+	) -> Result<TemporaryUpload> {
+		let mut request =
+			self.client
+				.put_object()
+				.bucket(&self.bucket)
+				.key(path.to_str().ok_or_else(|| {
+					Error::new(ErrorKind::InvalidData, "path is not valid utf-8")
+				})?);
+		if let Some(content_type) = &constraints.content_type {
+			request = request.content_type(content_type.to_string());
+		}
+		if let Some(content_length) = constraints.content_length {
+			request = request.content_length(
+				i64::try_from(content_length)
 					.map_err(|e| Error::new(ErrorKind::InvalidInput, e))?,
-			)
-			.await;
+			);
+		}
 
-		match request {
-			Ok(presigned_req) => Ok(presigned_req
-				.uri()
-				.parse()
-				.map_err(|e| Error::new(ErrorKind::Other, e))?),
-			Err(SdkError::ServiceError(error)) => {
-				if error.err().is_no_such_key() {
-					return Err(Error::from(ErrorKind::NotFound));
-				}
+		let presigned = request
+			.presigned(self.presigning_config(expires_in)?)
+			.await
+			.map_err(Error::other)?;
+
+		Ok(TemporaryUpload {
+			method: presigned.method().to_string(),
+			url: presigned.uri().parse().map_err(Error::other)?,
+			headers: presigned
+				.headers()
+				.map(|(name, value)| (name.to_string(), value.to_string()))
+				.collect(),
+		})
+	}
+}
 
-				Err(Error::new(ErrorKind::Other, error.into_err()))
-			},
-			Err(e) => Err(Error::new(ErrorKind::Other, e)),
-		}
+impl PublicUrlGenerator for S3Adapter {
+	type Error = Error;
+
+	async fn public_url(&self, path: &Path) -> Result<String> {
+		Adapter::public_url(self, path).await.map(String::from)
 	}
 }
 
@@ -547,27 +2393,67 @@ impl From<GetObjectAclOutput> for Visibility {
 	}
 }
 
+/// Turn a failed write into an error, classifying full disks and exceeded quotas as [`ErrorKind::StorageFull`].
+///
+/// S3 itself never runs out of space, but compatible servers do: `MinIO` answers `507 Insufficient Storage`,
+/// while Ceph and others return a `QuotaExceeded` error code.
+fn write_error<E>(error: SdkError<E, HttpResponse>) -> Error
+where
+	E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
+{
+	let insufficient_storage = error
+		.raw_response()
+		.is_some_and(|response| response.status().as_u16() == 507);
+	let quota_exceeded = matches!(
+		error.code(),
+		Some("QuotaExceeded" | "XMinioStorageFull" | "XMinioAdminBucketQuotaExceeded")
+	);
+
+	if insufficient_storage || quota_exceeded {
+		return Error::new(ErrorKind::StorageFull, error);
+	}
+
+	// the precondition of a conditional write doesn't hold, or another conditional write to the same key is in progress
+	if matches!(
+		error.code(),
+		Some("PreconditionFailed" | "ConditionalRequestConflict")
+	) {
+		return Error::new(ErrorKind::AlreadyExists, error);
+	}
+
+	Error::other(error)
+}
+
+/// Read the next part of a multipart upload, which is only shorter than `part_size` if the reader ends.
+async fn read_part(reader: &mut (dyn AsyncRead + Send + Unpin), part_size: u64) -> Result<Vec<u8>> {
+	let mut part = Vec::new();
+	reader.take(part_size).read_to_end(&mut part).await?;
+
+	Ok(part)
+}
+
 #[cfg(test)]
 mod tests {
-	use std::env;
+	use std::sync::atomic::{AtomicUsize, Ordering};
 
 	use super::*;
+	use crate::{Filesystem, FlysystemError};
 
 	async fn get_client() -> S3Adapter {
-		S3Adapter::new(Config {
-			bucket: env::var("S3_BUCKET").unwrap(),
-			region: env::var("S3_REGION").unwrap(),
-			endpoint: env::var("S3_ENDPOINT").unwrap(),
-			access_key: env::var("S3_ACCESS_KEY").unwrap(),
-			secret_key: env::var("S3_SECRET_KEY").unwrap(),
-		})
+		S3Adapter::new(
+			ConfigBuilder::from_env()
+				.detect_mime_on_move(true)
+				.validation(Validation::Eager)
+				.build()
+				.unwrap(),
+		)
 		.await
 		.unwrap()
 	}
 
 	#[tokio::test]
 	async fn test_file_exists() {
-		let mut client = get_client().await;
+		let client = get_client().await;
 
 		assert!(!client
 			.file_exists(Path::new("test_file_exists.txt"))
@@ -592,7 +2478,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_directory_exists() {
-		let mut client = get_client().await;
+		let client = get_client().await;
 
 		assert!(!client
 			.directory_exists(Path::new("test_directory_exists"))
@@ -609,54 +2495,172 @@ mod tests {
 			.await
 			.unwrap());
 
-		client
-			.delete_directory(Path::new("test_directory_exists"))
-			.await
-			.unwrap();
+		client
+			.delete_directory(Path::new("test_directory_exists"))
+			.await
+			.unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_write() {
+		let client = get_client().await;
+
+		assert!(!client
+			.file_exists(Path::new("test_write.txt"))
+			.await
+			.unwrap());
+
+		client
+			.write(Path::new("test_write.txt"), b"Hello, world!")
+			.await
+			.unwrap();
+
+		assert_eq!(
+			client.read(Path::new("test_write.txt")).await.unwrap().data,
+			b"Hello, world!"
+		);
+
+		client.delete(Path::new("test_write.txt")).await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_write_with() {
+		let client = get_client().await;
+
+		client
+			.write_with(
+				Path::new("test_write_with.txt"),
+				b"{}",
+				&WriteOptions {
+					content_type: Some(mime::APPLICATION_JSON),
+					cache_control: Some("max-age=60".to_string()),
+					metadata: HashMap::from([("owner".to_string(), "alice".to_string())]),
+					..WriteOptions::default()
+				},
+			)
+			.await
+			.unwrap();
+
+		let head = client
+			.head_object(Path::new("test_write_with.txt"))
+			.await
+			.unwrap();
+		assert_eq!(head.content_type(), Some("application/json"));
+		assert_eq!(head.cache_control(), Some("max-age=60"));
+		assert_eq!(
+			head.metadata().and_then(|metadata| metadata.get("owner")),
+			Some(&"alice".to_string())
+		);
+
+		client
+			.delete(Path::new("test_write_with.txt"))
+			.await
+			.unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_write_if() {
+		let client = get_client().await;
+		let path = Path::new("test_write_if.json");
+
+		client
+			.write_if(path, b"{}", &Precondition::NotExists)
+			.await
+			.unwrap();
+		assert_eq!(
+			client
+				.write_if(path, b"{}", &Precondition::NotExists)
+				.await
+				.unwrap_err()
+				.kind(),
+			ErrorKind::AlreadyExists
+		);
+
+		let etag = client.checksum(path).await.unwrap();
+		client
+			.write_if(path, b"[]", &Precondition::MatchesChecksum(etag.clone()))
+			.await
+			.unwrap();
+		assert!(client
+			.write_if(path, b"{}", &Precondition::MatchesChecksum(etag))
+			.await
+			.is_err());
+
+		client.delete(path).await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_read() {
+		let client = get_client().await;
+
+		client
+			.write(Path::new("test_read.txt"), b"Hello, world!")
+			.await
+			.unwrap();
+
+		assert_eq!(
+			client.read(Path::new("test_read.txt")).await.unwrap().data,
+			b"Hello, world!"
+		);
+
+		client.delete(Path::new("test_read.txt")).await.unwrap();
 	}
 
 	#[tokio::test]
-	async fn test_write() {
-		let mut client = get_client().await;
+	async fn test_read_into() {
+		let client = get_client().await;
 
-		assert!(!client
-			.file_exists(Path::new("test_write.txt"))
+		client
+			.write(Path::new("test_read_into.txt"), b"Hello, world!")
 			.await
-			.unwrap());
+			.unwrap();
 
+		let mut buf = b"> ".to_vec();
 		client
-			.write(Path::new("test_write.txt"), b"Hello, world!")
+			.read_into(Path::new("test_read_into.txt"), &mut buf)
 			.await
 			.unwrap();
 
-		assert_eq!(
-			client.read(Path::new("test_write.txt")).await.unwrap().data,
-			b"Hello, world!"
-		);
+		assert_eq!(buf, b"> Hello, world!");
 
-		client.delete(Path::new("test_write.txt")).await.unwrap();
+		client
+			.delete(Path::new("test_read_into.txt"))
+			.await
+			.unwrap();
 	}
 
 	#[tokio::test]
-	async fn test_read() {
-		let mut client = get_client().await;
+	async fn test_read_range() {
+		let client = get_client().await;
 
 		client
-			.write(Path::new("test_read.txt"), b"Hello, world!")
+			.write(Path::new("test_read_range.txt"), b"Hello, world!")
 			.await
 			.unwrap();
 
 		assert_eq!(
-			client.read(Path::new("test_read.txt")).await.unwrap().data,
-			b"Hello, world!"
+			client
+				.read_range(Path::new("test_read_range.txt"), 7, 5)
+				.await
+				.unwrap()
+				.data,
+			b"world"
 		);
+		assert!(client
+			.read_range(Path::new("test_read_range.txt"), 100, 5)
+			.await
+			.unwrap()
+			.is_empty());
 
-		client.delete(Path::new("test_read.txt")).await.unwrap();
+		client
+			.delete(Path::new("test_read_range.txt"))
+			.await
+			.unwrap();
 	}
 
 	#[tokio::test]
 	async fn test_delete() {
-		let mut client = get_client().await;
+		let client = get_client().await;
 
 		client
 			.write(Path::new("test_delete.txt"), b"Hello, world!")
@@ -671,9 +2675,28 @@ mod tests {
 			.unwrap());
 	}
 
+	#[tokio::test]
+	async fn test_write_and_delete_many() {
+		let client = get_client().await;
+		let paths: Vec<PathBuf> = (0..3)
+			.map(|i| PathBuf::from(format!("test_delete_many/{i}.txt")))
+			.collect();
+		let files: Vec<(&Path, &[u8])> = paths
+			.iter()
+			.map(|path| (path.as_path(), b"Hello, world!".as_slice()))
+			.collect();
+
+		assert!(client.write_many(&files).await.is_empty());
+		assert!(client.delete_many(&paths).await.is_empty());
+
+		for path in &paths {
+			assert!(!client.file_exists(path).await.unwrap());
+		}
+	}
+
 	#[tokio::test]
 	async fn test_delete_directory() {
-		let mut client = get_client().await;
+		let client = get_client().await;
 
 		client
 			.create_directory(Path::new("test_delete_directory"))
@@ -698,7 +2721,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_create_directory() {
-		let mut client = get_client().await;
+		let client = get_client().await;
 
 		assert!(!client
 			.directory_exists(Path::new("test_create_directory"))
@@ -723,7 +2746,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_create_directory_with_parents() {
-		let mut client = get_client().await;
+		let client = get_client().await;
 
 		assert!(!client
 			.directory_exists(Path::new("test_create_directory_with_parents/test_dir"))
@@ -747,9 +2770,9 @@ mod tests {
 	}
 
 	#[tokio::test]
-	#[ignore] // not supported by MinIO
+	#[ignore = "not supported by MinIO"]
 	async fn test_set_visibility() {
-		let mut client = get_client().await;
+		let client = get_client().await;
 
 		client
 			.write(Path::new("test_set_visibility.txt"), &[])
@@ -784,9 +2807,9 @@ mod tests {
 	}
 
 	#[tokio::test]
-	#[ignore] // not supported by MinIO
+	#[ignore = "not supported by MinIO"]
 	async fn test_visibility() {
-		let mut client = get_client().await;
+		let client = get_client().await;
 
 		client
 			.write(Path::new("test_visibility.txt"), &[])
@@ -822,7 +2845,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_mime_type() {
-		let mut client = get_client().await;
+		let client = get_client().await;
 
 		client
 			.write(Path::new("test_mime.txt"), b"Hello, world!")
@@ -839,7 +2862,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_last_modified() {
-		let mut client = get_client().await;
+		let client = get_client().await;
 
 		client
 			.write(Path::new("test_last_modified.txt"), &[])
@@ -866,7 +2889,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_file_size() {
-		let mut client = get_client().await;
+		let client = get_client().await;
 
 		client
 			.write(Path::new("test_file_size.txt"), b"Hello, world!")
@@ -889,7 +2912,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_list_contents() {
-		let mut client = get_client().await;
+		let client = get_client().await;
 
 		client
 			.write(
@@ -905,13 +2928,21 @@ mod tests {
 			)
 			.await
 			.unwrap();
+		client
+			.create_directory(Path::new("test_list_contents/test_empty_dir"))
+			.await
+			.unwrap();
 
 		assert_eq!(
 			client
 				.list_contents(Path::new("test_list_contents"), false)
 				.await
 				.unwrap(),
-			vec![PathBuf::from("test_list_contents/test_file.txt")]
+			vec![
+				PathBuf::from("test_list_contents/test_empty_dir"),
+				PathBuf::from("test_list_contents/test_file.txt"),
+				PathBuf::from("test_list_contents/test_recursive_dir")
+			]
 		);
 		assert_eq!(
 			client
@@ -924,6 +2955,42 @@ mod tests {
 			]
 		);
 
+		let entries = client
+			.list_contents_with_metadata(Path::new("test_list_contents"), false)
+			.await
+			.unwrap();
+		assert_eq!(
+			entries
+				.iter()
+				.map(|entry| (entry.path.as_path(), entry.is_dir, entry.size))
+				.collect::<Vec<_>>(),
+			vec![
+				(Path::new("test_list_contents/test_empty_dir"), true, None),
+				(
+					Path::new("test_list_contents/test_file.txt"),
+					false,
+					Some(13)
+				),
+				(
+					Path::new("test_list_contents/test_recursive_dir"),
+					true,
+					None
+				)
+			]
+		);
+		assert!(entries[1].last_modified.is_some());
+		assert_eq!(
+			client
+				.list_stream(Path::new("test_list_contents"), true)
+				.try_collect::<Vec<_>>()
+				.await
+				.unwrap(),
+			vec![
+				PathBuf::from("test_list_contents/test_file.txt"),
+				PathBuf::from("test_list_contents/test_recursive_dir/test_file.txt")
+			]
+		);
+
 		client
 			.delete_directory(Path::new("test_list_contents"))
 			.await
@@ -932,7 +2999,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_move() {
-		let mut client = get_client().await;
+		let client = get_client().await;
 
 		client
 			.write(Path::new("test_move.txt"), b"Hello, world!")
@@ -969,7 +3036,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_copy() {
-		let mut client = get_client().await;
+		let client = get_client().await;
 
 		client
 			.write(Path::new("test_copy.txt"), b"Hello, world!")
@@ -1006,7 +3073,7 @@ mod tests {
 
 	#[tokio::test]
 	async fn test_checksum() {
-		let mut client = get_client().await;
+		let client = get_client().await;
 
 		client
 			.write(Path::new("test_checksum.txt"), b"Hello, world!")
@@ -1023,4 +3090,426 @@ mod tests {
 
 		client.delete(Path::new("test_checksum.txt")).await.unwrap();
 	}
+
+	#[tokio::test]
+	#[ignore = "requires a bucket with versioning enabled"]
+	async fn test_list_versions() {
+		let client = get_client().await;
+
+		client
+			.write(Path::new("test_list_versions.txt"), b"first")
+			.await
+			.unwrap();
+		client
+			.write(Path::new("test_list_versions.txt"), b"second")
+			.await
+			.unwrap();
+
+		let mut versions = client
+			.list_versions(Path::new("test_list_versions.txt"))
+			.await
+			.unwrap();
+		versions.sort_by_key(|version| version.last_modified);
+
+		assert_eq!(versions.len(), 2);
+		assert_eq!(
+			client
+				.read_version(Path::new("test_list_versions.txt"), &versions[0].version_id)
+				.await
+				.unwrap()
+				.data,
+			b"first"
+		);
+
+		client
+			.restore_version(Path::new("test_list_versions.txt"), &versions[0].version_id)
+			.await
+			.unwrap();
+		assert_eq!(
+			client
+				.read(Path::new("test_list_versions.txt"))
+				.await
+				.unwrap()
+				.data,
+			b"first"
+		);
+
+		client
+			.delete(Path::new("test_list_versions.txt"))
+			.await
+			.unwrap();
+	}
+
+	#[test]
+	fn test_config_builder() {
+		let config = Config::builder("flysystem").build().unwrap();
+		assert_eq!(config.region, DEFAULT_REGION);
+		assert_eq!(config.credentials, Credentials::Anonymous);
+
+		for builder in [
+			Config::builder(""),
+			Config::builder("flysystem").endpoint("localhost:9000"),
+			Config::builder("flysystem").endpoint("ftp://localhost"),
+			Config::builder("flysystem")
+				.endpoint("http://localhost:9000")
+				.accelerate(true),
+			Config::builder("flysystem").public_url("cdn.example.com"),
+			Config::builder("flysystem").part_size(1024),
+			Config::builder("flysystem").part_concurrency(0),
+			Config::builder("flysystem").profile(""),
+			Config::builder("flysystem").credentials_from(Credentials::AssumeRole {
+				role_arn: String::new(),
+				session_name: None,
+				external_id: None,
+				duration: None,
+			}),
+			Config::builder("flysystem").kms_encryption(Some(String::new())),
+			Config::builder("flysystem").encryption(ServerSideEncryption::Customer {
+				key: STANDARD.encode([0; 16]),
+			}),
+			Config::builder("flysystem").storage_class(""),
+		] {
+			assert_eq!(builder.build().unwrap_err().kind(), ErrorKind::InvalidInput);
+		}
+
+		let config = Config {
+			bucket: "flysystem".to_string(),
+			credentials: Credentials::Static {
+				access_key: "access".to_string(),
+				secret_key: String::new(),
+				session_token: None,
+			},
+			..Config::default()
+		};
+		assert_eq!(
+			config.validate().unwrap_err().kind(),
+			ErrorKind::InvalidInput
+		);
+	}
+
+	#[tokio::test]
+	async fn test_public_url() {
+		let client = S3Adapter::new(
+			Config::builder("flysystem")
+				.public_url("https://cdn.example.com/assets")
+				.build()
+				.unwrap(),
+		)
+		.await
+		.unwrap();
+		assert_eq!(
+			PublicUrlGenerator::public_url(&client, Path::new("images/logo 2.png"))
+				.await
+				.unwrap(),
+			"https://cdn.example.com/assets/images/logo%202.png"
+		);
+
+		let filesystem =
+			Filesystem::new::<S3Adapter>(Config::builder("flysystem").build().unwrap())
+				.await
+				.unwrap();
+		assert!(matches!(
+			filesystem.public_url(Path::new("logo.png")).await,
+			Err(FlysystemError::PublicUrlNotSupported { .. })
+		));
+	}
+
+	#[tokio::test]
+	async fn test_temporary_upload_url() {
+		let client = S3Adapter::new(
+			Config::builder("flysystem")
+				.endpoint("http://localhost:9000")
+				.credentials("access", "secret")
+				.build()
+				.unwrap(),
+		)
+		.await
+		.unwrap();
+
+		let upload = client
+			.temporary_upload_url(
+				Path::new("uploads/avatar.png"),
+				Duration::from_mins(10),
+				&UploadConstraints {
+					content_type: Some(mime::IMAGE_PNG),
+					content_length: Some(1024),
+				},
+			)
+			.await
+			.unwrap();
+
+		assert_eq!(upload.method, "PUT");
+		assert_eq!(upload.url.path(), "/flysystem/uploads/avatar.png");
+		assert!(upload
+			.headers
+			.contains(&("content-type".to_string(), "image/png".to_string())));
+		assert!(upload
+			.url
+			.query_pairs()
+			.any(|(key, value)| key == "X-Amz-SignedHeaders"
+				&& value.contains("content-length")
+				&& value.contains("content-type")));
+	}
+
+	#[tokio::test]
+	async fn test_temporary_url_response_headers() {
+		let client = S3Adapter::new(
+			Config::builder("flysystem")
+				.endpoint("http://localhost:9000")
+				.credentials("access", "secret")
+				.build()
+				.unwrap(),
+		)
+		.await
+		.unwrap();
+
+		let url = client
+			.temporary_url_with(
+				Path::new("uploads/0b6e5b1c.pdf"),
+				Duration::from_mins(10),
+				&TemporaryUrlOptions::attachment("Invoice March.pdf"),
+			)
+			.await
+			.unwrap();
+
+		assert!(url.query_pairs().any(|(key, value)| {
+			key == "response-content-disposition"
+				&& value.starts_with("attachment; filename=\"Invoice March.pdf\"")
+		}));
+	}
+
+	#[tokio::test]
+	async fn test_temporary_url_clock_skew() {
+		let client = S3Adapter::new(
+			Config::builder("flysystem")
+				.endpoint("http://localhost:9000")
+				.credentials("access", "secret")
+				.clock_skew(Duration::from_mins(5))
+				.build()
+				.unwrap(),
+		)
+		.await
+		.unwrap();
+
+		let url = TemporaryUrlGenerator::temporary_url(
+			&client,
+			Path::new("report.pdf"),
+			Duration::from_mins(10),
+		)
+		.await
+		.unwrap();
+
+		assert!(url
+			.query_pairs()
+			.any(|(key, value)| key == "X-Amz-Expires" && value == "900"));
+	}
+
+	#[derive(Debug)]
+	struct TraceHook;
+
+	impl RequestHook for TraceHook {
+		fn before_send(&self, request: &mut HttpRequest) -> Result<()> {
+			let mut uri =
+				Url::parse(request.uri()).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+			uri.query_pairs_mut().append_pair("trace-id", "abc123");
+
+			request
+				.set_uri(uri.as_str())
+				.map_err(|e| Error::new(ErrorKind::InvalidInput, e))
+		}
+	}
+
+	#[tokio::test]
+	async fn test_request_hook() {
+		let client = S3Adapter::new(
+			Config::builder("flysystem")
+				.endpoint("http://localhost:9000")
+				.credentials("access", "secret")
+				.hook(TraceHook)
+				.build()
+				.unwrap(),
+		)
+		.await
+		.unwrap();
+
+		let url = TemporaryUrlGenerator::temporary_url(
+			&client,
+			Path::new("report.pdf"),
+			Duration::from_mins(10),
+		)
+		.await
+		.unwrap();
+
+		assert!(url
+			.query_pairs()
+			.any(|(key, value)| key == "trace-id" && value == "abc123"));
+	}
+
+	#[tokio::test]
+	async fn test_read_after_write() {
+		let client = S3Adapter::new(
+			Config::builder("flysystem")
+				.endpoint("http://localhost:9000")
+				.credentials("access", "secret")
+				.read_after_write(ReadAfterWrite {
+					delay: Duration::from_millis(1),
+					..ReadAfterWrite::default()
+				})
+				.build()
+				.unwrap(),
+		)
+		.await
+		.unwrap();
+		client.record_write(Path::new("fresh.txt"));
+
+		// the store only shows the file on the third lookup
+		let lookups = AtomicUsize::new(0);
+		let lookup = || async {
+			if lookups.fetch_add(1, Ordering::SeqCst) < 2 {
+				return Err(Error::from(ErrorKind::NotFound));
+			}
+
+			Ok(())
+		};
+
+		client
+			.consistent(Path::new("fresh.txt"), lookup)
+			.await
+			.unwrap();
+		assert_eq!(lookups.load(Ordering::SeqCst), 3);
+
+		// files this adapter didn't write aren't retried
+		lookups.store(0, Ordering::SeqCst);
+		assert_eq!(
+			client
+				.consistent(Path::new("missing.txt"), lookup)
+				.await
+				.unwrap_err()
+				.kind(),
+			ErrorKind::NotFound
+		);
+		assert_eq!(lookups.load(Ordering::SeqCst), 1);
+	}
+
+	#[tokio::test]
+	async fn test_move_detects_mime_type() {
+		let client = get_client().await;
+
+		client
+			.write(Path::new("test_move_detects_mime_type.tmp"), b"%PDF")
+			.await
+			.unwrap();
+		client
+			.r#move(
+				Path::new("test_move_detects_mime_type.tmp"),
+				Path::new("test_move_detects_mime_type.pdf"),
+			)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			client
+				.mime_type(Path::new("test_move_detects_mime_type.pdf"))
+				.await
+				.unwrap(),
+			mime::APPLICATION_PDF
+		);
+
+		client
+			.delete(Path::new("test_move_detects_mime_type.pdf"))
+			.await
+			.unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_skip_directory_markers() {
+		let mut client = get_client().await;
+		client.skip_directory_markers = true;
+
+		client
+			.create_directory(Path::new("test_skip_directory_markers"))
+			.await
+			.unwrap();
+
+		assert!(!client
+			.directory_exists(Path::new("test_skip_directory_markers"))
+			.await
+			.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_multipart_write() {
+		let part_size = *PART_SIZE_LIMITS.start();
+		let client = S3Adapter::new(
+			ConfigBuilder::from_env()
+				.part_size(part_size)
+				.part_concurrency(2)
+				.build()
+				.unwrap(),
+		)
+		.await
+		.unwrap();
+		let contents: Vec<u8> = (0..part_size * 2 + 1024)
+			.map(|i| u8::try_from(i % 251).unwrap())
+			.collect();
+
+		client
+			.write_with(
+				Path::new("test_multipart_write.csv"),
+				&contents,
+				&WriteOptions {
+					cache_control: Some("no-cache".to_string()),
+					..WriteOptions::default()
+				},
+			)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			client
+				.read(Path::new("test_multipart_write.csv"))
+				.await
+				.unwrap()
+				.data,
+			contents
+		);
+		assert_eq!(
+			client
+				.mime_type(Path::new("test_multipart_write.csv"))
+				.await
+				.unwrap(),
+			mime::TEXT_CSV
+		);
+
+		client
+			.delete(Path::new("test_multipart_write.csv"))
+			.await
+			.unwrap();
+	}
+
+	#[tokio::test]
+	async fn test_streams() {
+		let client = get_client().await;
+		let contents = vec![7; usize::try_from(PART_SIZE).unwrap() + 1024];
+
+		client
+			.write_stream(
+				Path::new("test_streams.bin"),
+				&mut futures::io::Cursor::new(contents.clone()),
+			)
+			.await
+			.unwrap();
+
+		let mut read = Vec::new();
+		client
+			.read_stream(Path::new("test_streams.bin"))
+			.await
+			.unwrap()
+			.read_to_end(&mut read)
+			.await
+			.unwrap();
+		assert_eq!(read, contents);
+
+		client.delete(Path::new("test_streams.bin")).await.unwrap();
+	}
 }
@@ -0,0 +1,420 @@
+use bytes::BufMut;
+use futures::io::AsyncRead;
+use mime::Mime;
+use std::{
+	collections::{BTreeSet, HashMap},
+	io::{Error, Result},
+	path::{Path, PathBuf},
+	time::{Duration, SystemTime},
+};
+use url::Url;
+
+use super::{Adapter, AdapterLayer, TemporaryUrlOptions};
+use crate::{contents::Contents, PathModel, Precondition, Visibility, WriteOptions};
+
+/// How files are spread over nested directories named after the hash of their name.
+///
+/// With the default two levels of two characters, `images/cat.jpg` is stored as `images/4f/a2/cat.jpg`.
+/// Only the file name is hashed, so moving a directory keeps the files under it valid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sharding {
+	levels: usize,
+	width: usize,
+}
+
+impl Default for Sharding {
+	fn default() -> Self {
+		Self::new(2, 2)
+	}
+}
+
+impl Sharding {
+	/// Shard files over `levels` nested directories, each named after `width` characters of the hash.
+	///
+	/// # Panics
+	///
+	/// Panics if the shards need more than the 64 characters of a SHA-256 hash.
+	#[must_use]
+	pub const fn new(levels: usize, width: usize) -> Self {
+		assert!(
+			levels * width <= 64,
+			"The shards can't be longer than the hash."
+		);
+
+		Self { levels, width }
+	}
+
+	/// Get the path a file is stored at.
+	#[must_use]
+	pub fn path(&self, path: &Path) -> PathBuf {
+		let Some(name) = path.file_name() else {
+			return path.to_path_buf();
+		};
+
+		path.with_file_name(self.shards(name.as_encoded_bytes()).join("/"))
+			.join(name)
+	}
+
+	/// Get the path a file was stored for, or `None` if `path` doesn't follow the sharding layout.
+	#[must_use]
+	pub fn original(&self, path: &Path) -> Option<PathBuf> {
+		let name = path.file_name()?;
+		let mut directory = path.parent()?;
+
+		for shard in self.shards(name.as_encoded_bytes()).iter().rev() {
+			if directory.file_name()? != shard.as_str() {
+				return None;
+			}
+
+			directory = directory.parent()?;
+		}
+
+		Some(directory.join(name))
+	}
+
+	fn shards(&self, name: &[u8]) -> Vec<String> {
+		let hash = sha256::digest(name);
+
+		(0..self.levels)
+			.map(|level| hash[level * self.width..(level + 1) * self.width].to_string())
+			.collect()
+	}
+}
+
+/// An adapter spreading files over nested shard directories (see [`Sharding`]), so no single directory
+/// ends up holding millions of files and S3 keys are spread over many prefixes.
+///
+/// Paths are sharded transparently, so callers keep using the original ones. Use [`ShardedAdapter::sharding`]
+/// (or [`public_url::Sharded`](crate::public_url::Sharded)) to derive the real storage paths, like for public URLs.
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct ShardedAdapter<A> {
+	inner: A,
+	sharding: Sharding,
+}
+
+impl<A: Adapter> ShardedAdapter<A> {
+	/// Shard the files stored on `inner`.
+	pub const fn new(inner: A, sharding: Sharding) -> Self {
+		Self { inner, sharding }
+	}
+
+	/// Get the mapping between paths and the ones files are stored at.
+	#[must_use]
+	pub const fn sharding(&self) -> Sharding {
+		self.sharding
+	}
+}
+
+impl<A: Adapter> Adapter for ShardedAdapter<A> {
+	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		self.inner.file_exists(&self.sharding.path(path)).await
+	}
+
+	async fn directory_exists(&self, path: &Path) -> Result<bool> {
+		self.inner.directory_exists(path).await
+	}
+
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.inner.write(&self.sharding.path(path), content).await
+	}
+
+	async fn write_with(&self, path: &Path, content: &[u8], options: &WriteOptions) -> Result<()> {
+		self.inner
+			.write_with(&self.sharding.path(path), content, options)
+			.await
+	}
+
+	async fn write_if(
+		&self,
+		path: &Path,
+		content: &[u8],
+		precondition: &Precondition,
+	) -> Result<()> {
+		self.inner
+			.write_if(&self.sharding.path(path), content, precondition)
+			.await
+	}
+
+	async fn write_atomic(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.inner
+			.write_atomic(&self.sharding.path(path), content)
+			.await
+	}
+
+	async fn write_stream(
+		&self,
+		path: &Path,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		self.inner
+			.write_stream(&self.sharding.path(path), reader)
+			.await
+	}
+
+	async fn write_range(&self, path: &Path, offset: u64, data: &[u8]) -> Result<()> {
+		self.inner
+			.write_range(&self.sharding.path(path), offset, data)
+			.await
+	}
+
+	async fn append(&self, path: &Path, data: &[u8]) -> Result<()> {
+		self.inner.append(&self.sharding.path(path), data).await
+	}
+
+	fn native_range_writes(&self) -> bool {
+		self.inner.native_range_writes()
+	}
+
+	async fn read(&self, path: &Path) -> Result<Contents> {
+		self.inner.read(&self.sharding.path(path)).await
+	}
+
+	async fn read_stream(&self, path: &Path) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+		self.inner.read_stream(&self.sharding.path(path)).await
+	}
+
+	async fn read_into(&self, path: &Path, buf: &mut (dyn BufMut + Send)) -> Result<()> {
+		self.inner.read_into(&self.sharding.path(path), buf).await
+	}
+
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		self.inner
+			.read_range(&self.sharding.path(path), offset, len)
+			.await
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
+		self.inner.delete(&self.sharding.path(path)).await
+	}
+
+	async fn delete_many(&self, paths: &[PathBuf]) -> Vec<(PathBuf, Error)> {
+		let mut originals: HashMap<PathBuf, &PathBuf> = paths
+			.iter()
+			.map(|path| (self.sharding.path(path), path))
+			.collect();
+		let sharded: Vec<PathBuf> = originals.keys().cloned().collect();
+
+		self.inner
+			.delete_many(&sharded)
+			.await
+			.into_iter()
+			.map(|(path, error)| {
+				let original = originals.remove(&path).map_or(path, Clone::clone);
+				(original, error)
+			})
+			.collect()
+	}
+
+	async fn write_many(&self, files: &[(&Path, &[u8])]) -> Vec<(PathBuf, Error)> {
+		let sharded: Vec<(PathBuf, &Path, &[u8])> = files
+			.iter()
+			.map(|&(path, content)| (self.sharding.path(path), path, content))
+			.collect();
+		let mut originals: HashMap<&Path, &Path> = sharded
+			.iter()
+			.map(|(key, path, _)| (key.as_path(), *path))
+			.collect();
+		let files: Vec<(&Path, &[u8])> = sharded
+			.iter()
+			.map(|(key, _, content)| (key.as_path(), *content))
+			.collect();
+
+		self.inner
+			.write_many(&files)
+			.await
+			.into_iter()
+			.map(|(path, error)| {
+				let original = originals
+					.remove(path.as_path())
+					.map_or(path, Path::to_path_buf);
+				(original, error)
+			})
+			.collect()
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
+		self.inner.delete_directory(path).await
+	}
+
+	async fn create_directory(&self, path: &Path) -> Result<()> {
+		self.inner.create_directory(path).await
+	}
+
+	async fn set_mime_type(&self, path: &Path, mime_type: &Mime) -> Result<()> {
+		self.inner
+			.set_mime_type(&self.sharding.path(path), mime_type)
+			.await
+	}
+
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
+		self.inner
+			.set_visibility(&self.sharding.path(path), visibility)
+			.await
+	}
+
+	async fn visibility(&self, path: &Path) -> Result<Visibility> {
+		self.inner.visibility(&self.sharding.path(path)).await
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		self.inner.mime_type(&self.sharding.path(path)).await
+	}
+
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		self.inner.metadata(&self.sharding.path(path)).await
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		self.inner.last_modified(&self.sharding.path(path)).await
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		self.inner.file_size(&self.sharding.path(path)).await
+	}
+
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		// the shard directories have to be walked even for shallow listings
+		let contents: BTreeSet<PathBuf> = self
+			.inner
+			.list_contents(path, true)
+			.await?
+			.iter()
+			.map(|entry| path.join(crate::relative_to(entry, path)))
+			.map(|entry| self.sharding.original(&entry).unwrap_or(entry))
+			.filter(|entry| deep || entry.parent() == Some(path))
+			.collect();
+
+		Ok(contents.into_iter().collect())
+	}
+
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		let sharded = self.sharding.path(source);
+
+		if !self.inner.file_exists(&sharded).await? && self.inner.directory_exists(source).await? {
+			return self.inner.r#move(source, destination).await;
+		}
+
+		self.inner
+			.r#move(&sharded, &self.sharding.path(destination))
+			.await
+	}
+
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.inner
+			.copy(
+				&self.sharding.path(source),
+				&self.sharding.path(destination),
+			)
+			.await
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		self.inner.checksum(&self.sharding.path(path)).await
+	}
+
+	async fn public_url(&self, path: &Path) -> Result<Url> {
+		self.inner.public_url(&self.sharding.path(path)).await
+	}
+
+	async fn temporary_url(&self, path: &Path, expires_in: Duration) -> Result<Url> {
+		self.inner
+			.temporary_url(&self.sharding.path(path), expires_in)
+			.await
+	}
+
+	async fn temporary_url_with(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+		options: &TemporaryUrlOptions,
+	) -> Result<Url> {
+		self.inner
+			.temporary_url_with(&self.sharding.path(path), expires_in, options)
+			.await
+	}
+
+	fn path_model(&self) -> PathModel {
+		self.inner.path_model()
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		AdapterLayer::new("sharded")
+			.with("levels", self.sharding.levels)
+			.with("width", self.sharding.width)
+			.wrapping(&self.inner)
+	}
+
+	async fn prefetch(&self, path: &Path) -> Result<()> {
+		self.inner.prefetch(&self.sharding.path(path)).await
+	}
+
+	async fn flush(&self) -> Result<()> {
+		self.inner.flush().await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{adapters::MemoryAdapter, Filesystem};
+
+	#[test]
+	fn test_sharding() {
+		let sharding = Sharding::default();
+		let hash = sha256::digest("cat.jpg");
+
+		let path = sharding.path(Path::new("images/cat.jpg"));
+		assert_eq!(
+			path,
+			PathBuf::from(format!("images/{}/{}/cat.jpg", &hash[..2], &hash[2..4]))
+		);
+		assert_eq!(
+			sharding.original(&path),
+			Some(PathBuf::from("images/cat.jpg"))
+		);
+		assert_eq!(sharding.original(Path::new("images/cat.jpg")), None);
+	}
+
+	#[tokio::test]
+	async fn test_sharded_adapter() {
+		let inner = MemoryAdapter::default();
+		inner
+			.write(Path::new("images/README.md"), b"# Images")
+			.await
+			.unwrap();
+
+		let filesystem = Filesystem::from_adapter(ShardedAdapter::new(inner, Sharding::new(1, 3)));
+
+		filesystem
+			.write(Path::new("images/cat.jpg"), b"meow")
+			.await
+			.unwrap();
+		filesystem
+			.r#move(Path::new("images/cat.jpg"), Path::new("images/kitten.jpg"))
+			.await
+			.unwrap();
+
+		assert_eq!(
+			filesystem
+				.read::<String>(Path::new("images/kitten.jpg"))
+				.await
+				.unwrap(),
+			"meow"
+		);
+		assert!(!filesystem
+			.file_exists(Path::new("images/cat.jpg"))
+			.await
+			.unwrap());
+		assert_eq!(
+			filesystem
+				.list_contents(Path::new("images"), false)
+				.await
+				.unwrap(),
+			vec![
+				PathBuf::from("images/README.md"),
+				PathBuf::from("images/kitten.jpg")
+			]
+		);
+	}
+}
@@ -0,0 +1,404 @@
+use bytes::BufMut;
+use futures::io::AsyncRead;
+use mime::Mime;
+use std::{
+	collections::{BTreeMap, BTreeSet, HashMap},
+	io::{Error, ErrorKind, Result},
+	path::{Path, PathBuf},
+	time::{Duration, SystemTime},
+};
+use url::Url;
+
+use super::{Adapter, AdapterLayer, TemporaryUrlOptions};
+use crate::{
+	contents::Contents, trait_object_hackyness::AdapterObject, PathModel, Precondition,
+	StorageEntry, Visibility, WriteOptions,
+};
+
+/// How many points each stripe gets on the hash ring, so paths are spread evenly between them.
+const VIRTUAL_NODES: usize = 64;
+
+/// An adapter spreading files over several adapters, for workloads exceeding the throughput or object count limits of a single backend.
+///
+/// **Experimental.** Each file lives on a single stripe, picked by consistent hashing of its path, so adding a stripe
+/// only relocates about `1/n` of the paths (which then have to be moved over, this adapter doesn't rebalance existing files).
+/// Directories exist on every stripe holding files under them, and listings combine the ones of every stripe.
+/// Moving or copying a file to a path on another stripe reads it and writes it there.
+#[derive(Debug)]
+#[allow(clippy::module_name_repetitions)]
+pub struct StripedAdapter {
+	stripes: Vec<Box<dyn AdapterObject>>,
+	/// The points of the hash ring, sorted, along with the stripe they belong to.
+	ring: Vec<(u64, usize)>,
+}
+
+impl StripedAdapter {
+	/// Start striping files over `first`, and the stripes added with [`with_stripe`](Self::with_stripe).
+	pub fn new<A: Adapter + 'static>(first: A) -> Self {
+		Self {
+			stripes: Vec::new(),
+			ring: Vec::new(),
+		}
+		.with_stripe(first)
+	}
+
+	/// Add a stripe.
+	#[must_use]
+	pub fn with_stripe<A: Adapter + 'static>(mut self, stripe: A) -> Self {
+		let index = self.stripes.len();
+		self.stripes.push(Box::new(stripe));
+
+		self.ring.extend(
+			(0..VIRTUAL_NODES).map(|node| (hash(format!("{index}-{node}").as_bytes()), index)),
+		);
+		self.ring.sort_unstable();
+		self
+	}
+
+	/// Get the index of the stripe the file at `path` is stored on, in the order they were added.
+	#[must_use]
+	pub fn stripe_of(&self, path: &Path) -> usize {
+		let hash = hash(path.as_os_str().as_encoded_bytes());
+		let point = self.ring.partition_point(|&(point, _)| point < hash);
+
+		self.ring[point % self.ring.len()].1
+	}
+
+	fn stripe(&self, path: &Path) -> &dyn AdapterObject {
+		self.stripes[self.stripe_of(path)].as_ref()
+	}
+
+	/// Get the stripes holding the directory at `path`, failing if there are none.
+	async fn holding(&self, path: &Path) -> Result<Vec<&dyn AdapterObject>> {
+		let mut holding = Vec::new();
+		for stripe in &self.stripes {
+			if stripe.directory_exists(path).await? {
+				holding.push(stripe.as_ref());
+			}
+		}
+
+		if holding.is_empty() {
+			return Err(Error::from(ErrorKind::NotFound));
+		}
+
+		Ok(holding)
+	}
+
+	/// Copy a file to a path on another stripe, along with the metadata both stripes support.
+	async fn transfer(
+		&self,
+		source: &Path,
+		destination: &Path,
+		from: &dyn AdapterObject,
+		to: &dyn AdapterObject,
+	) -> Result<()> {
+		let contents = from.read(source).await?;
+		let options = WriteOptions {
+			visibility: from.visibility(source).await.ok(),
+			content_type: from.mime_type(source).await.ok(),
+			metadata: from.metadata(source).await.unwrap_or_default(),
+			..WriteOptions::default()
+		};
+
+		to.write_with(destination, &contents, &options).await
+	}
+}
+
+fn hash(bytes: &[u8]) -> u64 {
+	let digest = sha256::digest(bytes);
+
+	u64::from_str_radix(&digest[..16], 16).unwrap_or_default()
+}
+
+impl Adapter for StripedAdapter {
+	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		self.stripe(path).file_exists(path).await
+	}
+
+	async fn directory_exists(&self, path: &Path) -> Result<bool> {
+		for stripe in &self.stripes {
+			if stripe.directory_exists(path).await? {
+				return Ok(true);
+			}
+		}
+
+		Ok(false)
+	}
+
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.stripe(path).write(path, content).await
+	}
+
+	async fn write_with(&self, path: &Path, content: &[u8], options: &WriteOptions) -> Result<()> {
+		self.stripe(path).write_with(path, content, options).await
+	}
+
+	async fn write_if(
+		&self,
+		path: &Path,
+		content: &[u8],
+		precondition: &Precondition,
+	) -> Result<()> {
+		self.stripe(path)
+			.write_if(path, content, precondition)
+			.await
+	}
+
+	async fn write_atomic(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.stripe(path).write_atomic(path, content).await
+	}
+
+	async fn write_stream(
+		&self,
+		path: &Path,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		self.stripe(path).write_stream(path, reader).await
+	}
+
+	async fn write_range(&self, path: &Path, offset: u64, data: &[u8]) -> Result<()> {
+		self.stripe(path).write_range(path, offset, data).await
+	}
+
+	async fn append(&self, path: &Path, data: &[u8]) -> Result<()> {
+		self.stripe(path).append(path, data).await
+	}
+
+	fn native_range_writes(&self) -> bool {
+		self.stripes
+			.iter()
+			.all(|stripe| stripe.native_range_writes())
+	}
+
+	async fn read(&self, path: &Path) -> Result<Contents> {
+		self.stripe(path).read(path).await
+	}
+
+	async fn read_stream(&self, path: &Path) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+		self.stripe(path).read_stream(path).await
+	}
+
+	async fn read_into(&self, path: &Path, buf: &mut (dyn BufMut + Send)) -> Result<()> {
+		self.stripe(path).read_into(path, buf).await
+	}
+
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		self.stripe(path).read_range(path, offset, len).await
+	}
+
+	async fn read_version(&self, path: &Path, version_id: &str) -> Result<Contents> {
+		self.stripe(path).read_version(path, version_id).await
+	}
+
+	async fn restore_version(&self, path: &Path, version_id: &str) -> Result<()> {
+		self.stripe(path).restore_version(path, version_id).await
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
+		self.stripe(path).delete(path).await
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
+		for stripe in self.holding(path).await? {
+			stripe.delete_directory(path).await?;
+		}
+
+		Ok(())
+	}
+
+	async fn create_directory(&self, path: &Path) -> Result<()> {
+		for stripe in &self.stripes {
+			stripe.create_directory(path).await?;
+		}
+
+		Ok(())
+	}
+
+	async fn set_mime_type(&self, path: &Path, mime_type: &Mime) -> Result<()> {
+		self.stripe(path).set_mime_type(path, mime_type).await
+	}
+
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
+		self.stripe(path).set_visibility(path, visibility).await
+	}
+
+	async fn visibility(&self, path: &Path) -> Result<Visibility> {
+		self.stripe(path).visibility(path).await
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		self.stripe(path).mime_type(path).await
+	}
+
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		self.stripe(path).metadata(path).await
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		self.stripe(path).last_modified(path).await
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		self.stripe(path).file_size(path).await
+	}
+
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		let mut contents = BTreeSet::new();
+
+		for stripe in self.holding(path).await? {
+			contents.extend(
+				stripe
+					.list_contents(path, deep)
+					.await?
+					.iter()
+					.map(|entry| path.join(crate::relative_to(entry, path))),
+			);
+		}
+
+		Ok(contents.into_iter().collect())
+	}
+
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		let mut entries = BTreeMap::new();
+
+		for stripe in self.holding(path).await? {
+			for mut entry in stripe.list_contents_with_metadata(path, deep).await? {
+				entry.path = path.join(crate::relative_to(&entry.path, path));
+				entries.entry(entry.path.clone()).or_insert(entry);
+			}
+		}
+
+		Ok(entries.into_values().collect())
+	}
+
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		let (from, to) = (self.stripe(source), self.stripe(destination));
+		if std::ptr::addr_eq(from, to) {
+			return from.r#move(source, destination).await;
+		}
+
+		self.transfer(source, destination, from, to).await?;
+		from.delete(source).await
+	}
+
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		let (from, to) = (self.stripe(source), self.stripe(destination));
+		if std::ptr::addr_eq(from, to) {
+			return from.copy(source, destination).await;
+		}
+
+		self.transfer(source, destination, from, to).await
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		self.stripe(path).checksum(path).await
+	}
+
+	async fn public_url(&self, path: &Path) -> Result<Url> {
+		self.stripe(path).public_url(path).await
+	}
+
+	async fn temporary_url(&self, path: &Path, expires_in: Duration) -> Result<Url> {
+		self.stripe(path).temporary_url(path, expires_in).await
+	}
+
+	async fn temporary_url_with(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+		options: &TemporaryUrlOptions,
+	) -> Result<Url> {
+		self.stripe(path)
+			.temporary_url_with(path, expires_in, options)
+			.await
+	}
+
+	/// Flat if any of the stripes is.
+	fn path_model(&self) -> PathModel {
+		if self
+			.stripes
+			.iter()
+			.any(|stripe| stripe.path_model() == PathModel::FlatKeys)
+		{
+			PathModel::FlatKeys
+		} else {
+			PathModel::Hierarchical
+		}
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		let stripes: Vec<String> = self
+			.stripes
+			.iter()
+			.map(|stripe| {
+				let stack: Vec<String> = stripe.layers().iter().map(ToString::to_string).collect();
+				stack.join(" > ")
+			})
+			.collect();
+
+		vec![AdapterLayer::new("striped").with("stripes", format!("[{}]", stripes.join(", ")))]
+	}
+
+	async fn prefetch(&self, path: &Path) -> Result<()> {
+		self.stripe(path).prefetch(path).await
+	}
+
+	async fn flush(&self) -> Result<()> {
+		for stripe in &self.stripes {
+			stripe.flush().await?;
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{adapters::MemoryAdapter, Filesystem};
+
+	#[tokio::test]
+	async fn test_striping() {
+		let adapter = StripedAdapter::new(MemoryAdapter::default())
+			.with_stripe(MemoryAdapter::default())
+			.with_stripe(MemoryAdapter::default());
+
+		let paths: Vec<PathBuf> = (0..30)
+			.map(|i| PathBuf::from(format!("uploads/{i}.txt")))
+			.collect();
+		let used: BTreeSet<usize> = paths.iter().map(|path| adapter.stripe_of(path)).collect();
+		assert_eq!(used.len(), 3);
+
+		let filesystem = Filesystem::from_adapter(adapter);
+		for path in &paths {
+			filesystem
+				.write(path, path.to_str().unwrap().as_bytes())
+				.await
+				.unwrap();
+		}
+
+		let mut listed = filesystem
+			.list_contents(Path::new("uploads"), false)
+			.await
+			.unwrap();
+		listed.sort();
+		let mut expected = paths.clone();
+		expected.sort();
+		assert_eq!(listed, expected);
+
+		// moving a file between stripes
+		for (i, path) in paths.iter().enumerate() {
+			let destination = PathBuf::from(format!("archive/{i}.txt"));
+			filesystem.r#move(path, &destination).await.unwrap();
+			assert_eq!(
+				filesystem.read::<String>(&destination).await.unwrap(),
+				path.to_str().unwrap()
+			);
+			assert!(!filesystem.file_exists(path).await.unwrap());
+		}
+	}
+}
@@ -0,0 +1,735 @@
+use bytes::BufMut;
+use futures::{
+	io::AsyncRead,
+	stream::{BoxStream, StreamExt, TryStreamExt},
+};
+use mime::Mime;
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::{BTreeMap, HashMap},
+	io::{Error, ErrorKind, Result},
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use url::Url;
+
+use super::{Adapter, AdapterLayer, TemporaryUrlOptions};
+use crate::{
+	clock::{Clock, SystemClock},
+	contents::Contents,
+	versioning::FileVersion,
+	DirectoryStat, PathModel, StorageEntry, Visibility, WriteOptions,
+};
+
+/// The directory deleted files are kept in, unless [`TrashAdapter::with_trash`] says otherwise.
+pub const DEFAULT_TRASH: &str = ".flysystem-deleted";
+
+/// What a deleted file was, recorded when it was moved to the trash and checked again when it's restored.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tombstone {
+	/// Identifies the deleted file in the trash.
+	pub id: String,
+	/// Where the file was before it was deleted.
+	pub path: PathBuf,
+	/// The SHA-256 checksum of its contents.
+	pub checksum: String,
+	pub size: u64,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub content_type: Option<String>,
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub metadata: BTreeMap<String, String>,
+	/// The visibility of the file, unless its adapter doesn't support visibility.
+	#[serde(
+		default,
+		skip_serializing_if = "Option::is_none",
+		with = "stored_visibility"
+	)]
+	pub visibility: Option<Visibility>,
+	pub deleted_at: SystemTime,
+}
+
+/// Stores visibilities in tombstones as `"public"` or `"private"`.
+mod stored_visibility {
+	use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+	use crate::Visibility;
+
+	// serde passes the field by reference
+	#[allow(clippy::ref_option, clippy::trivially_copy_pass_by_ref)]
+	pub fn serialize<S: Serializer>(
+		visibility: &Option<Visibility>,
+		serializer: S,
+	) -> Result<S::Ok, S::Error> {
+		match visibility {
+			Some(Visibility::Public) => serializer.serialize_str("public"),
+			Some(Visibility::Private) => serializer.serialize_str("private"),
+			None => serializer.serialize_none(),
+		}
+	}
+
+	pub fn deserialize<'de, D: Deserializer<'de>>(
+		deserializer: D,
+	) -> Result<Option<Visibility>, D::Error> {
+		match Option::<String>::deserialize(deserializer)?.as_deref() {
+			Some("public") => Ok(Some(Visibility::Public)),
+			Some("private") => Ok(Some(Visibility::Private)),
+			Some(other) => Err(D::Error::unknown_variant(other, &["public", "private"])),
+			None => Ok(None),
+		}
+	}
+}
+
+/// What to do when restoring a file whose original path has been reused since it was deleted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Resolution {
+	/// Fail with a [`RestoreError::PathTaken`] error.
+	#[default]
+	Fail,
+	/// Replace the file now at the original path.
+	Overwrite,
+	/// Restore the file at another path instead, failing if that one is taken too.
+	RestoreTo(PathBuf),
+}
+
+/// The error returned (wrapped in an [`io::Error`](Error)) when a deleted file can't be restored.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RestoreError {
+	/// Another file was written where the deleted one would be restored.
+	#[error("{} is taken, so {id} can't be restored there. Restore it with `Resolution::Overwrite` or `Resolution::RestoreTo` instead.", path.display())]
+	PathTaken { id: String, path: PathBuf },
+	/// The trashed contents don't match the checksum recorded when the file was deleted.
+	#[error("The contents of {id} don't match its tombstone (expected checksum {expected}, got {actual}).")]
+	ChecksumMismatch {
+		id: String,
+		expected: String,
+		actual: String,
+	},
+}
+
+/// An adapter moving deleted files to a trash directory instead of deleting them, so they can be [restored](Self::restore).
+///
+/// Each deleted file gets a [`Tombstone`] next to it in the trash, recording its original path, checksum and metadata.
+/// Files deleted from the trash itself are deleted for good, and overwriting a file (by writing, moving or copying over it)
+/// doesn't trash the previous version. The trash lives in the inner adapter, so files can be restored by another `TrashAdapter`
+/// over the same storage after handing this one to a [`Filesystem`](crate::Filesystem).
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct TrashAdapter<A> {
+	inner: A,
+	trash: PathBuf,
+	clock: Arc<dyn Clock>,
+}
+
+impl<A: Adapter> TrashAdapter<A> {
+	/// Keep the files deleted from an adapter in its [`DEFAULT_TRASH`] directory.
+	pub fn new(inner: A) -> Self {
+		Self {
+			inner,
+			trash: PathBuf::from(DEFAULT_TRASH),
+			clock: Arc::new(SystemClock),
+		}
+	}
+
+	/// Keep deleted files in `trash` instead of [`DEFAULT_TRASH`].
+	#[must_use]
+	pub fn with_trash(mut self, trash: impl Into<PathBuf>) -> Self {
+		self.trash = trash.into();
+		self
+	}
+
+	/// Timestamp deletions with the given clock instead of the system's, so tests can control time.
+	#[must_use]
+	pub fn with_clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+		self.clock = Arc::new(clock);
+		self
+	}
+
+	/// Get the tombstones of every file in the trash, oldest deletion first.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the trash can't be listed, or a tombstone can't be read.
+	pub async fn trashed(&self) -> Result<Vec<Tombstone>> {
+		if !self.inner.directory_exists(&self.trash).await? {
+			return Ok(Vec::new());
+		}
+
+		let mut tombstones = Vec::new();
+		for path in self.inner.list_contents(&self.trash, false).await? {
+			if path
+				.extension()
+				.is_some_and(|extension| extension == "json")
+			{
+				if let Some(id) = path.file_stem().and_then(|id| id.to_str()) {
+					tombstones.push(self.tombstone(id).await?);
+				}
+			}
+		}
+
+		tombstones.sort_by(|a, b| a.deleted_at.cmp(&b.deleted_at).then(a.id.cmp(&b.id)));
+		Ok(tombstones)
+	}
+
+	/// Get the tombstone of a file in the trash.
+	///
+	/// # Errors
+	///
+	/// This function will return a `NotFound` error if there's no such file in the trash, or an `InvalidData` one if its tombstone is malformed.
+	pub async fn tombstone(&self, id: &str) -> Result<Tombstone> {
+		let contents = self.inner.read(&self.tombstone_path(id)).await?;
+
+		serde_json::from_slice(&contents).map_err(|error| Error::new(ErrorKind::InvalidData, error))
+	}
+
+	/// Put a deleted file back where it was (or where `resolution` says), returning its new path.
+	///
+	/// Its contents are checked against the checksum in its tombstone, and it's written back with the content type, metadata and visibility it had.
+	///
+	/// # Errors
+	///
+	/// This function will return an `AlreadyExists` error wrapping a [`RestoreError::PathTaken`] if the path is taken and `resolution` doesn't allow replacing it,
+	/// an `InvalidData` one wrapping a [`RestoreError::ChecksumMismatch`] if the trashed contents changed, or an error if the adapter fails.
+	pub async fn restore(&self, id: &str, resolution: Resolution) -> Result<PathBuf> {
+		let tombstone = self.tombstone(id).await?;
+		let contents = self.inner.read(&self.contents_path(id)).await?;
+
+		let actual = sha256::digest(&*contents);
+		if actual != tombstone.checksum {
+			return Err(Error::new(
+				ErrorKind::InvalidData,
+				RestoreError::ChecksumMismatch {
+					id: id.to_string(),
+					expected: tombstone.checksum,
+					actual,
+				},
+			));
+		}
+
+		let (path, overwrite) = match resolution {
+			Resolution::Fail => (tombstone.path, false),
+			Resolution::Overwrite => (tombstone.path, true),
+			Resolution::RestoreTo(path) => (path, false),
+		};
+		if !overwrite && self.inner.file_exists(&path).await? {
+			return Err(Error::new(
+				ErrorKind::AlreadyExists,
+				RestoreError::PathTaken {
+					id: id.to_string(),
+					path,
+				},
+			));
+		}
+
+		let options = WriteOptions {
+			content_type: tombstone
+				.content_type
+				.and_then(|content_type| content_type.parse().ok()),
+			metadata: tombstone.metadata.into_iter().collect(),
+			visibility: tombstone.visibility,
+			..WriteOptions::default()
+		};
+		self.inner.write_with(&path, &contents, &options).await?;
+		self.purge(id).await?;
+
+		Ok(path)
+	}
+
+	/// Delete a file from the trash for good.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to delete the file or its tombstone.
+	pub async fn purge(&self, id: &str) -> Result<()> {
+		self.inner.delete(&self.contents_path(id)).await?;
+		self.inner.delete(&self.tombstone_path(id)).await
+	}
+
+	fn contents_path(&self, id: &str) -> PathBuf {
+		self.trash.join(id)
+	}
+
+	fn tombstone_path(&self, id: &str) -> PathBuf {
+		self.trash.join(format!("{id}.json"))
+	}
+
+	/// Pick an id for a file deleted now, skipping the ones already in the trash.
+	async fn next_id(&self) -> Result<String> {
+		let mut nanos = self
+			.clock
+			.now()
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_nanos();
+
+		loop {
+			let id = format!("{nanos:x}");
+			if !self.inner.file_exists(&self.tombstone_path(&id)).await? {
+				return Ok(id);
+			}
+
+			nanos += 1;
+		}
+	}
+
+	/// Check if `entry` is in the trash, which is left out of listings of `path` unless it's in the trash itself.
+	fn hides(&self, path: &Path, entry: &Path) -> bool {
+		!path.starts_with(&self.trash) && entry.starts_with(&self.trash)
+	}
+
+	/// Move the file at `path` to the trash, along with its tombstone.
+	async fn trash_file(&self, path: &Path) -> Result<()> {
+		let contents = self.inner.read(path).await?;
+		let content_type = match self.inner.mime_type(path).await {
+			Ok(mime_type) => Some(mime_type.to_string()),
+			Err(error) if error.kind() == ErrorKind::Unsupported => None,
+			Err(error) => return Err(error),
+		};
+		let metadata = match self.inner.metadata(path).await {
+			Ok(metadata) => metadata.into_iter().collect(),
+			Err(error) if error.kind() == ErrorKind::Unsupported => BTreeMap::new(),
+			Err(error) => return Err(error),
+		};
+		let visibility = match self.inner.visibility(path).await {
+			Ok(visibility) => Some(visibility),
+			Err(error) if error.kind() == ErrorKind::Unsupported => None,
+			Err(error) => return Err(error),
+		};
+
+		let id = self.next_id().await?;
+		let tombstone = Tombstone {
+			path: path.to_path_buf(),
+			checksum: sha256::digest(&*contents),
+			size: contents.len() as u64,
+			content_type,
+			metadata,
+			visibility,
+			deleted_at: self.clock.now(),
+			id,
+		};
+		let serialized = serde_json::to_vec_pretty(&tombstone)
+			.map_err(|error| Error::new(ErrorKind::InvalidData, error))?;
+
+		let trashed = self.contents_path(&tombstone.id);
+		self.inner.r#move(path, &trashed).await?;
+		if let Err(error) = self
+			.inner
+			.write(&self.tombstone_path(&tombstone.id), &serialized)
+			.await
+		{
+			// put the file back, so it isn't left in the trash without a way to restore it
+			let _ = self.inner.r#move(&trashed, path).await;
+			return Err(error);
+		}
+
+		Ok(())
+	}
+}
+
+impl<A: Adapter> Adapter for TrashAdapter<A> {
+	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		self.inner.file_exists(path).await
+	}
+
+	async fn directory_exists(&self, path: &Path) -> Result<bool> {
+		self.inner.directory_exists(path).await
+	}
+
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.inner.write(path, content).await
+	}
+
+	async fn write_with(&self, path: &Path, content: &[u8], options: &WriteOptions) -> Result<()> {
+		self.inner.write_with(path, content, options).await
+	}
+
+	async fn write_atomic(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.inner.write_atomic(path, content).await
+	}
+
+	async fn write_stream(
+		&self,
+		path: &Path,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		self.inner.write_stream(path, reader).await
+	}
+
+	async fn write_range(&self, path: &Path, offset: u64, data: &[u8]) -> Result<()> {
+		self.inner.write_range(path, offset, data).await
+	}
+
+	async fn append(&self, path: &Path, data: &[u8]) -> Result<()> {
+		self.inner.append(path, data).await
+	}
+
+	fn native_range_writes(&self) -> bool {
+		self.inner.native_range_writes()
+	}
+
+	async fn read(&self, path: &Path) -> Result<Contents> {
+		self.inner.read(path).await
+	}
+
+	async fn read_stream(&self, path: &Path) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+		self.inner.read_stream(path).await
+	}
+
+	async fn read_into(&self, path: &Path, buf: &mut (dyn BufMut + Send)) -> Result<()> {
+		self.inner.read_into(path, buf).await
+	}
+
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		self.inner.read_range(path, offset, len).await
+	}
+
+	async fn list_versions(&self, prefix: &Path) -> Result<Vec<FileVersion>> {
+		self.inner.list_versions(prefix).await
+	}
+
+	async fn read_version(&self, path: &Path, version_id: &str) -> Result<Contents> {
+		self.inner.read_version(path, version_id).await
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
+		if path.starts_with(&self.trash) {
+			return self.inner.delete(path).await;
+		}
+
+		self.trash_file(path).await
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
+		if path.starts_with(&self.trash) {
+			return self.inner.delete_directory(path).await;
+		}
+
+		for entry in self.inner.list_contents_with_metadata(path, true).await? {
+			if entry.is_dir || entry.path.starts_with(&self.trash) {
+				continue;
+			}
+
+			// bail out instead of skipping files, since the directory is deleted afterwards
+			if !entry.path.starts_with(path) {
+				return Err(Error::new(
+					ErrorKind::InvalidData,
+					format!(
+						"The adapter listed {} outside of {}.",
+						entry.path.display(),
+						path.display()
+					),
+				));
+			}
+
+			self.trash_file(&entry.path).await?;
+		}
+
+		if self.trash.starts_with(path) {
+			return Ok(());
+		}
+
+		self.inner.delete_directory(path).await
+	}
+
+	async fn create_directory(&self, path: &Path) -> Result<()> {
+		self.inner.create_directory(path).await
+	}
+
+	async fn set_mime_type(&self, path: &Path, mime_type: &Mime) -> Result<()> {
+		self.inner.set_mime_type(path, mime_type).await
+	}
+
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
+		self.inner.set_visibility(path, visibility).await
+	}
+
+	async fn set_visibility_many(
+		&self,
+		paths: &[PathBuf],
+		visibility: Visibility,
+	) -> Vec<(PathBuf, Error)> {
+		self.inner.set_visibility_many(paths, visibility).await
+	}
+
+	async fn visibility(&self, path: &Path) -> Result<Visibility> {
+		self.inner.visibility(path).await
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		self.inner.mime_type(path).await
+	}
+
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		self.inner.metadata(path).await
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		self.inner.last_modified(path).await
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		self.inner.file_size(path).await
+	}
+
+	async fn directory_stat(&self, path: &Path) -> Result<DirectoryStat> {
+		self.inner.directory_stat(path).await
+	}
+
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		let mut contents = self.inner.list_contents(path, deep).await?;
+		contents.retain(|entry| !self.hides(path, entry));
+
+		Ok(contents)
+	}
+
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		let mut entries = self.inner.list_contents_with_metadata(path, deep).await?;
+		entries.retain(|entry| !self.hides(path, &entry.path));
+
+		Ok(entries)
+	}
+
+	fn list_stream<'a>(&'a self, path: &Path, deep: bool) -> BoxStream<'a, Result<PathBuf>> {
+		let path = path.to_path_buf();
+
+		self.inner
+			.list_stream(&path, deep)
+			.try_filter(move |entry| std::future::ready(!self.hides(&path, entry)))
+			.boxed()
+	}
+
+	fn list_stream_after<'a>(
+		&'a self,
+		path: &Path,
+		deep: bool,
+		after: Option<&Path>,
+	) -> BoxStream<'a, Result<PathBuf>> {
+		let path = path.to_path_buf();
+
+		self.inner
+			.list_stream_after(&path, deep, after)
+			.try_filter(move |entry| std::future::ready(!self.hides(&path, entry)))
+			.boxed()
+	}
+
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.inner.r#move(source, destination).await
+	}
+
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.inner.copy(source, destination).await
+	}
+
+	async fn move_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.inner.move_directory(source, destination).await
+	}
+
+	async fn copy_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.inner.copy_directory(source, destination).await
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		self.inner.checksum(path).await
+	}
+
+	async fn public_url(&self, path: &Path) -> Result<Url> {
+		self.inner.public_url(path).await
+	}
+
+	async fn temporary_url(&self, path: &Path, expires_in: Duration) -> Result<Url> {
+		self.inner.temporary_url(path, expires_in).await
+	}
+
+	async fn temporary_url_with(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+		options: &TemporaryUrlOptions,
+	) -> Result<Url> {
+		self.inner
+			.temporary_url_with(path, expires_in, options)
+			.await
+	}
+
+	fn path_model(&self) -> PathModel {
+		self.inner.path_model()
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		AdapterLayer::new("trash")
+			.with("trash", self.trash.display())
+			.wrapping(&self.inner)
+	}
+
+	async fn prefetch(&self, path: &Path) -> Result<()> {
+		self.inner.prefetch(path).await
+	}
+
+	async fn flush(&self) -> Result<()> {
+		self.inner.flush().await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::adapters::MemoryAdapter;
+
+	#[tokio::test]
+	async fn test_restore() {
+		let trash = TrashAdapter::new(MemoryAdapter::default());
+
+		let mut options = WriteOptions::default();
+		options
+			.metadata
+			.insert("author".to_string(), "ada".to_string());
+		options.content_type = Some(mime::TEXT_CSV);
+		trash
+			.write_with(Path::new("reports/q1.csv"), b"1,2,3", &options)
+			.await
+			.unwrap();
+		trash.delete(Path::new("reports/q1.csv")).await.unwrap();
+		assert!(!trash
+			.file_exists(Path::new("reports/q1.csv"))
+			.await
+			.unwrap());
+
+		let [tombstone] = trash.trashed().await.unwrap().try_into().unwrap();
+		assert_eq!(tombstone.path, Path::new("reports/q1.csv"));
+		assert_eq!(tombstone.checksum, sha256::digest(b"1,2,3".as_slice()));
+
+		// the path was reused since
+		trash
+			.write(Path::new("reports/q1.csv"), b"4,5,6")
+			.await
+			.unwrap();
+		let error = trash
+			.restore(&tombstone.id, Resolution::Fail)
+			.await
+			.unwrap_err();
+		assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+		assert_eq!(
+			error
+				.get_ref()
+				.and_then(|error| error.downcast_ref::<RestoreError>()),
+			Some(&RestoreError::PathTaken {
+				id: tombstone.id.clone(),
+				path: PathBuf::from("reports/q1.csv"),
+			})
+		);
+
+		let restored = trash
+			.restore(
+				&tombstone.id,
+				Resolution::RestoreTo(PathBuf::from("reports/q1-restored.csv")),
+			)
+			.await
+			.unwrap();
+		assert_eq!(*trash.read(&restored).await.unwrap(), *b"1,2,3");
+		assert_eq!(
+			Adapter::metadata(&trash, &restored).await.unwrap()["author"],
+			"ada"
+		);
+		assert_eq!(trash.mime_type(&restored).await.unwrap(), mime::TEXT_CSV);
+		assert!(trash.trashed().await.unwrap().is_empty());
+	}
+
+	#[tokio::test]
+	async fn test_listings_skip_trash() {
+		let trash = TrashAdapter::new(MemoryAdapter::default());
+
+		trash.write(Path::new("a.txt"), b"a").await.unwrap();
+		trash.write(Path::new("b.txt"), b"b").await.unwrap();
+		trash.delete(Path::new("b.txt")).await.unwrap();
+
+		assert_eq!(
+			trash.list_contents(Path::new(""), true).await.unwrap(),
+			vec![PathBuf::from("a.txt")]
+		);
+		assert_eq!(
+			trash
+				.list_contents_with_metadata(Path::new(""), true)
+				.await
+				.unwrap()
+				.into_iter()
+				.map(|entry| entry.path)
+				.collect::<Vec<_>>(),
+			vec![PathBuf::from("a.txt")]
+		);
+		assert_eq!(
+			trash
+				.list_stream(Path::new(""), true)
+				.try_collect::<Vec<_>>()
+				.await
+				.unwrap(),
+			vec![PathBuf::from("a.txt")]
+		);
+
+		// the trash can still be listed on its own
+		assert_eq!(
+			trash
+				.list_contents(Path::new(DEFAULT_TRASH), false)
+				.await
+				.unwrap()
+				.len(),
+			2
+		);
+	}
+
+	#[tokio::test]
+	async fn test_restore_keeps_visibility() {
+		let trash = TrashAdapter::new(MemoryAdapter::default());
+
+		trash.write(Path::new("secret.txt"), b"shh").await.unwrap();
+		trash
+			.set_visibility(Path::new("secret.txt"), Visibility::Private)
+			.await
+			.unwrap();
+		trash.delete(Path::new("secret.txt")).await.unwrap();
+
+		let [tombstone] = trash.trashed().await.unwrap().try_into().unwrap();
+		assert_eq!(tombstone.visibility, Some(Visibility::Private));
+
+		trash
+			.restore(&tombstone.id, Resolution::Fail)
+			.await
+			.unwrap();
+		assert_eq!(
+			trash.visibility(Path::new("secret.txt")).await.unwrap(),
+			Visibility::Private
+		);
+	}
+
+	#[tokio::test]
+	async fn test_restore_detects_tampering() {
+		let trash = TrashAdapter::new(MemoryAdapter::default());
+
+		trash
+			.write(Path::new("notes.txt"), b"original")
+			.await
+			.unwrap();
+		trash.delete(Path::new("notes.txt")).await.unwrap();
+
+		let [tombstone] = trash.trashed().await.unwrap().try_into().unwrap();
+		trash
+			.write(&Path::new(DEFAULT_TRASH).join(&tombstone.id), b"tampered")
+			.await
+			.unwrap();
+
+		let error = trash
+			.restore(&tombstone.id, Resolution::Overwrite)
+			.await
+			.unwrap_err();
+		assert_eq!(error.kind(), ErrorKind::InvalidData);
+		assert!(matches!(
+			error
+				.get_ref()
+				.and_then(|error| error.downcast_ref::<RestoreError>()),
+			Some(RestoreError::ChecksumMismatch { .. })
+		));
+	}
+}
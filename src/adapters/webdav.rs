@@ -0,0 +1,865 @@
+use mime::Mime;
+use percent_encoding::percent_decode_str;
+use quick_xml::{events::Event, Reader};
+use reqwest::{header, Method, RequestBuilder, Response, StatusCode};
+use std::{
+	fmt,
+	io::{Error, ErrorKind, Result},
+	path::{Component, Path, PathBuf},
+	time::SystemTime,
+};
+use url::Url;
+
+use super::{Adapter, AdapterInit, AdapterLayer, Validation};
+use crate::{contents::Contents, StorageEntry, Visibility, REDACTED};
+
+/// The properties requested when looking up files and listing collections.
+///
+/// Visibility has no standard property, so it's stored as a dead property under flysystem's own namespace.
+const PROPFIND: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:" xmlns:f="https://github.com/m1guelpf/flysystem">
+	<d:prop>
+		<d:resourcetype/>
+		<d:getcontentlength/>
+		<d:getcontenttype/>
+		<d:getlastmodified/>
+		<f:visibility/>
+	</d:prop>
+</d:propfind>"#;
+
+#[derive(Clone)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+pub struct Config {
+	/// The collection files are stored in, like `https://cloud.example.com/remote.php/dav/files/alice/`.
+	pub url: Url,
+	#[cfg_attr(feature = "config", serde(default))]
+	pub username: Option<String>,
+	#[cfg_attr(feature = "config", serde(default))]
+	pub password: Option<String>,
+	/// Whether to check that the collection exists when the adapter is created, or on the first operation.
+	#[cfg_attr(feature = "config", serde(default))]
+	pub validation: Validation,
+}
+
+impl fmt::Debug for Config {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Config")
+			.field("url", &self.url)
+			.field("username", &self.username)
+			.field("password", &self.password.as_ref().map(|_| REDACTED))
+			.field("validation", &self.validation)
+			.finish()
+	}
+}
+
+/// An adapter storing files on a DAV server, like Nextcloud.
+///
+/// Missing parent collections are created when writing, moving or copying files,
+/// and deep listings walk the collections one level at a time, since many servers refuse `Depth: infinity`.
+#[derive(Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct WebDavAdapter {
+	url: Url,
+	username: Option<String>,
+	password: Option<String>,
+	client: reqwest::Client,
+}
+
+impl fmt::Debug for WebDavAdapter {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("WebDavAdapter")
+			.field("url", &self.url)
+			.field("username", &self.username)
+			.field("password", &self.password.as_ref().map(|_| REDACTED))
+			.finish_non_exhaustive()
+	}
+}
+
+/// A file or collection, as described by a PROPFIND response.
+#[derive(Debug, Default, PartialEq)]
+struct Resource {
+	href: String,
+	collection: bool,
+	size: Option<u64>,
+	content_type: Option<String>,
+	last_modified: Option<SystemTime>,
+	visibility: Option<Visibility>,
+	/// Whether the server refused to set or return any of the properties.
+	failed_properties: bool,
+}
+
+impl AdapterInit for WebDavAdapter {
+	type Config = Config;
+	type Error = Error;
+
+	async fn new(config: Self::Config) -> Result<Self> {
+		if config.url.cannot_be_a_base() {
+			return Err(Error::new(
+				ErrorKind::InvalidInput,
+				format!("{} is not a valid WebDAV collection URL.", config.url),
+			));
+		}
+
+		let adapter = Self {
+			url: config.url,
+			username: config.username,
+			password: config.password,
+			client: reqwest::Client::new(),
+		};
+
+		if config.validation == Validation::Eager
+			&& !adapter.directory_exists(Path::new("")).await?
+		{
+			return Err(Error::new(
+				ErrorKind::NotFound,
+				format!("The collection {} does not exist.", adapter.url),
+			));
+		}
+
+		Ok(adapter)
+	}
+}
+
+impl WebDavAdapter {
+	/// Get the URL of a file, or of a collection if `collection` is set.
+	fn url(&self, path: &Path, collection: bool) -> Url {
+		let mut url = self.url.clone();
+
+		{
+			let mut segments = url
+				.path_segments_mut()
+				.expect("The URL was checked to be a base when creating the adapter.");
+			segments.pop_if_empty();
+
+			for component in path.components() {
+				if let Component::Normal(name) = component {
+					segments.push(&name.to_string_lossy());
+				}
+			}
+
+			if collection {
+				segments.push("");
+			}
+		}
+
+		url
+	}
+
+	/// Get the path of a file from the `href` the server described it with.
+	fn path_of(&self, href: &str) -> Option<PathBuf> {
+		let url = self.url.join(href).ok()?;
+		let base = percent_decode_str(self.url.path()).decode_utf8().ok()?;
+		let path = percent_decode_str(url.path()).decode_utf8().ok()?;
+
+		let relative = path.strip_prefix(base.trim_end_matches('/'))?;
+		if !relative.is_empty() && !relative.starts_with('/') {
+			return None;
+		}
+
+		Some(PathBuf::from(relative.trim_matches('/')))
+	}
+
+	fn request(&self, method: Method, url: Url) -> RequestBuilder {
+		let request = self.client.request(method, url);
+
+		match &self.username {
+			Some(username) => request.basic_auth(username, self.password.as_ref()),
+			None => request,
+		}
+	}
+
+	async fn send(request: RequestBuilder, path: &Path) -> Result<Response> {
+		let response = request.send().await.map_err(Error::other)?;
+
+		check(response, path)
+	}
+
+	/// Send a request creating `path`, creating its parent collections and retrying if they're missing.
+	async fn send_creating_parents(
+		&self,
+		request: impl Fn() -> RequestBuilder + Send + Sync,
+		path: &Path,
+	) -> Result<Response> {
+		let response = request().send().await.map_err(Error::other)?;
+
+		// servers answer with a conflict when the parent collection doesn't exist
+		if response.status() != StatusCode::CONFLICT {
+			return check(response, path);
+		}
+
+		if let Some(parent) = path.parent() {
+			self.create_collections(parent).await?;
+		}
+
+		Self::send(request(), path).await
+	}
+
+	async fn create_collections(&self, path: &Path) -> Result<()> {
+		let mut collection = PathBuf::new();
+
+		for component in path.components() {
+			collection.push(component);
+
+			let response = self
+				.request(method("MKCOL"), self.url(&collection, true))
+				.send()
+				.await
+				.map_err(Error::other)?;
+
+			// the collection already exists
+			if response.status() != StatusCode::METHOD_NOT_ALLOWED {
+				check(response, &collection)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	async fn propfind(&self, path: &Path, collection: bool, depth: &str) -> Result<Vec<Resource>> {
+		let response = Self::send(
+			self.request(method("PROPFIND"), self.url(path, collection))
+				.header("Depth", depth)
+				.header(header::CONTENT_TYPE, "application/xml; charset=utf-8")
+				.body(PROPFIND),
+			path,
+		)
+		.await?;
+
+		parse_multistatus(&response.text().await.map_err(Error::other)?)
+	}
+
+	/// Look up a file or collection, returning `None` if it doesn't exist.
+	async fn stat(&self, path: &Path) -> Result<Option<Resource>> {
+		match self.propfind(path, false, "0").await {
+			Ok(resources) => Ok(resources.into_iter().next()),
+			Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+			Err(error) => Err(error),
+		}
+	}
+
+	async fn file(&self, path: &Path) -> Result<Resource> {
+		self.stat(path)
+			.await?
+			.filter(|resource| !resource.collection)
+			.ok_or_else(|| {
+				Error::new(
+					ErrorKind::NotFound,
+					format!("The file {} does not exist.", path.display()),
+				)
+			})
+	}
+}
+
+impl Adapter for WebDavAdapter {
+	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		Ok(self
+			.stat(path)
+			.await?
+			.is_some_and(|resource| !resource.collection))
+	}
+
+	async fn directory_exists(&self, path: &Path) -> Result<bool> {
+		Ok(self
+			.stat(path)
+			.await?
+			.is_some_and(|resource| resource.collection))
+	}
+
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+		let url = self.url(path, false);
+
+		self.send_creating_parents(
+			|| {
+				self.request(Method::PUT, url.clone())
+					.body(content.to_vec())
+			},
+			path,
+		)
+		.await?;
+
+		Ok(())
+	}
+
+	async fn read(&self, path: &Path) -> Result<Contents> {
+		let response = Self::send(self.request(Method::GET, self.url(path, false)), path).await?;
+
+		Ok(Contents::from(
+			response.bytes().await.map_err(Error::other)?.to_vec(),
+		))
+	}
+
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		if len == 0 {
+			return Ok(Contents::from(Vec::new()));
+		}
+
+		let response = self
+			.request(Method::GET, self.url(path, false))
+			.header(
+				header::RANGE,
+				format!("bytes={offset}-{}", offset.saturating_add(len) - 1),
+			)
+			.send()
+			.await
+			.map_err(Error::other)?;
+
+		// the range starts past the end of the file
+		if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+			return Ok(Contents::from(Vec::new()));
+		}
+
+		let partial = response.status() == StatusCode::PARTIAL_CONTENT;
+		let contents = check(response, path)?.bytes().await.map_err(Error::other)?;
+
+		if partial {
+			return Ok(Contents::from(contents.to_vec()));
+		}
+
+		// the server ignored the range and sent the whole file
+		let start = usize::try_from(offset)
+			.unwrap_or(usize::MAX)
+			.min(contents.len());
+		let end = usize::try_from(offset.saturating_add(len))
+			.unwrap_or(usize::MAX)
+			.min(contents.len());
+
+		Ok(Contents::from(contents[start..end].to_vec()))
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
+		Self::send(self.request(Method::DELETE, self.url(path, false)), path).await?;
+
+		Ok(())
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
+		Self::send(self.request(Method::DELETE, self.url(path, true)), path).await?;
+
+		Ok(())
+	}
+
+	async fn create_directory(&self, path: &Path) -> Result<()> {
+		self.create_collections(path).await
+	}
+
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
+		let visibility = match visibility {
+			Visibility::Public => "public",
+			Visibility::Private => "private",
+		};
+
+		let response = Self::send(
+			self.request(method("PROPPATCH"), self.url(path, false))
+				.header(header::CONTENT_TYPE, "application/xml; charset=utf-8")
+				.body(format!(
+					r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propertyupdate xmlns:d="DAV:" xmlns:f="https://github.com/m1guelpf/flysystem">
+	<d:set><d:prop><f:visibility>{visibility}</f:visibility></d:prop></d:set>
+</d:propertyupdate>"#
+				)),
+			path,
+		)
+		.await?;
+
+		let resources = parse_multistatus(&response.text().await.map_err(Error::other)?)?;
+		if resources.iter().any(|resource| resource.failed_properties) {
+			return Err(Error::new(
+				ErrorKind::PermissionDenied,
+				format!(
+					"The server refused to store the visibility of {}.",
+					path.display()
+				),
+			));
+		}
+
+		Ok(())
+	}
+
+	async fn visibility(&self, path: &Path) -> Result<Visibility> {
+		// files are only shared once explicitly made public
+		Ok(self
+			.file(path)
+			.await?
+			.visibility
+			.unwrap_or(Visibility::Private))
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		let resource = self.file(path).await?;
+
+		Ok(resource
+			.content_type
+			.and_then(|content_type| content_type.parse().ok())
+			.unwrap_or_else(|| mime_guess::from_path(path).first_or_octet_stream()))
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		self.file(path).await?.last_modified.ok_or_else(|| {
+			Error::new(
+				ErrorKind::InvalidData,
+				format!(
+					"The server did not return when {} was last modified.",
+					path.display()
+				),
+			)
+		})
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		self.file(path).await?.size.ok_or_else(|| {
+			Error::new(
+				ErrorKind::InvalidData,
+				format!("The server did not return the size of {}.", path.display()),
+			)
+		})
+	}
+
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		Ok(self
+			.list_contents_with_metadata(path, deep)
+			.await?
+			.into_iter()
+			.map(|entry| entry.path)
+			.collect())
+	}
+
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		let mut files = Vec::new();
+		let mut pending = vec![path.to_path_buf()];
+
+		while let Some(collection) = pending.pop() {
+			for resource in self.propfind(&collection, true, "1").await? {
+				let Some(entry) = self.path_of(&resource.href) else {
+					continue;
+				};
+
+				// the collection describes itself too
+				if entry == collection {
+					continue;
+				}
+
+				if !resource.collection {
+					files.push(StorageEntry {
+						is_dir: false,
+						size: resource.size,
+						last_modified: resource.last_modified,
+						mime: Some(
+							resource
+								.content_type
+								.and_then(|content_type| content_type.parse().ok())
+								.unwrap_or_else(|| {
+									mime_guess::from_path(&entry).first_or_octet_stream()
+								}),
+						),
+						visibility: Some(resource.visibility.unwrap_or(Visibility::Private)),
+						path: entry,
+					});
+				} else if deep {
+					pending.push(entry);
+				}
+			}
+		}
+
+		files.sort_by(|a, b| a.path.cmp(&b.path));
+
+		Ok(files)
+	}
+
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		let (source_url, destination_url) = (self.url(source, false), self.url(destination, false));
+
+		self.send_creating_parents(
+			|| {
+				self.request(method("MOVE"), source_url.clone())
+					.header("Destination", destination_url.as_str())
+					.header("Overwrite", "T")
+			},
+			destination,
+		)
+		.await?;
+
+		Ok(())
+	}
+
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		let (source_url, destination_url) = (self.url(source, false), self.url(destination, false));
+
+		self.send_creating_parents(
+			|| {
+				self.request(method("COPY"), source_url.clone())
+					.header("Destination", destination_url.as_str())
+					.header("Overwrite", "T")
+			},
+			destination,
+		)
+		.await?;
+
+		Ok(())
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		Ok(sha256::digest(self.read(path).await?.data))
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		let mut url = self.url.clone();
+		// credentials embedded in the URL mustn't end up on a debug page
+		let _ = url.set_username("");
+		let _ = url.set_password(None);
+
+		vec![AdapterLayer::new("webdav").with("url", url)]
+	}
+}
+
+fn method(name: &'static str) -> Method {
+	Method::from_bytes(name.as_bytes()).expect("WebDAV methods are valid HTTP methods.")
+}
+
+/// Turn unsuccessful responses into errors.
+fn check(response: Response, path: &Path) -> Result<Response> {
+	let status = response.status();
+	if status.is_success() {
+		return Ok(response);
+	}
+
+	let kind = match status {
+		StatusCode::NOT_FOUND | StatusCode::GONE => ErrorKind::NotFound,
+		StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => ErrorKind::PermissionDenied,
+		StatusCode::PRECONDITION_FAILED => ErrorKind::AlreadyExists,
+		StatusCode::LOCKED => ErrorKind::ResourceBusy,
+		StatusCode::INSUFFICIENT_STORAGE => ErrorKind::StorageFull,
+		_ => ErrorKind::Other,
+	};
+
+	Err(Error::new(
+		kind,
+		format!(
+			"The server answered {status} for {}.",
+			if path.as_os_str().is_empty() {
+				Path::new("/")
+			} else {
+				path
+			}
+			.display()
+		),
+	))
+}
+
+/// Parse the resources described by a `207 Multi-Status` response.
+fn parse_multistatus(xml: &str) -> Result<Vec<Resource>> {
+	let mut reader = Reader::from_str(xml);
+	let mut resources = Vec::new();
+	let mut resource: Option<Resource> = None;
+	let mut element = Vec::new();
+
+	loop {
+		match reader
+			.read_event()
+			.map_err(|e| Error::new(ErrorKind::InvalidData, e))?
+		{
+			Event::Start(start) => {
+				element = start.local_name().as_ref().to_vec();
+
+				match element.as_slice() {
+					b"response" => resource = Some(Resource::default()),
+					b"collection" => {
+						if let Some(resource) = &mut resource {
+							resource.collection = true;
+						}
+					},
+					_ => {},
+				}
+			},
+			Event::Empty(empty) => {
+				if let (b"collection", Some(resource)) =
+					(empty.local_name().as_ref(), &mut resource)
+				{
+					resource.collection = true;
+				}
+			},
+			Event::Text(text) => {
+				let Some(resource) = &mut resource else {
+					continue;
+				};
+				let text = text
+					.unescape()
+					.map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+				let text = text.trim();
+
+				match element.as_slice() {
+					b"href" => resource.href = text.to_string(),
+					b"getcontentlength" => resource.size = text.parse().ok(),
+					b"getcontenttype" => resource.content_type = Some(text.to_string()),
+					b"getlastmodified" => {
+						resource.last_modified = httpdate::parse_http_date(text).ok();
+					},
+					b"visibility" => {
+						resource.visibility = match text {
+							"public" => Some(Visibility::Public),
+							"private" => Some(Visibility::Private),
+							_ => None,
+						};
+					},
+					// a propstat status (like `HTTP/1.1 200 OK`)
+					b"status" => {
+						resource.failed_properties |= !text
+							.split(' ')
+							.nth(1)
+							.is_some_and(|code| code.starts_with('2'));
+					},
+					_ => {},
+				}
+			},
+			Event::End(end) => {
+				element.clear();
+
+				if end.local_name().as_ref() == b"response" {
+					resources.extend(resource.take());
+				}
+			},
+			Event::Eof => break,
+			_ => {},
+		}
+	}
+
+	Ok(resources)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::{Arc, Mutex};
+	use tokio::{
+		io::{AsyncReadExt, AsyncWriteExt},
+		net::TcpListener,
+	};
+
+	use super::*;
+
+	const LISTING: &str = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:f="https://github.com/m1guelpf/flysystem">
+	<d:response>
+		<d:href>/dav/files/alice/</d:href>
+		<d:propstat>
+			<d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop>
+			<d:status>HTTP/1.1 200 OK</d:status>
+		</d:propstat>
+	</d:response>
+	<d:response>
+		<d:href>/dav/files/alice/cat%20photo.jpg</d:href>
+		<d:propstat>
+			<d:prop>
+				<d:resourcetype/>
+				<d:getcontentlength>4</d:getcontentlength>
+				<d:getcontenttype>image/jpeg</d:getcontenttype>
+				<d:getlastmodified>Tue, 01 Oct 2024 10:00:00 GMT</d:getlastmodified>
+				<f:visibility>public</f:visibility>
+			</d:prop>
+			<d:status>HTTP/1.1 200 OK</d:status>
+		</d:propstat>
+	</d:response>
+	<d:response>
+		<d:href>/dav/files/alice/images/</d:href>
+		<d:propstat>
+			<d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop>
+			<d:status>HTTP/1.1 200 OK</d:status>
+		</d:propstat>
+		<d:propstat>
+			<d:prop><f:visibility/></d:prop>
+			<d:status>HTTP/1.1 404 Not Found</d:status>
+		</d:propstat>
+	</d:response>
+</d:multistatus>"#;
+
+	const IMAGES: &str = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:">
+	<d:response>
+		<d:href>/dav/files/alice/images/</d:href>
+		<d:propstat>
+			<d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop>
+			<d:status>HTTP/1.1 200 OK</d:status>
+		</d:propstat>
+	</d:response>
+	<d:response>
+		<d:href>http://localhost/dav/files/alice/images/dog.png</d:href>
+		<d:propstat>
+			<d:prop><d:resourcetype/><d:getcontentlength>3</d:getcontentlength></d:prop>
+			<d:status>HTTP/1.1 200 OK</d:status>
+		</d:propstat>
+	</d:response>
+</d:multistatus>"#;
+
+	/// A tiny DAV server serving the listings above, recording the requests it receives.
+	///
+	/// Uploads to `new/` fail with a conflict until the collection is created.
+	async fn server() -> (WebDavAdapter, Arc<Mutex<Vec<String>>>) {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let url = Url::parse(&format!(
+			"http://{}/dav/files/alice/",
+			listener.local_addr().unwrap()
+		))
+		.unwrap();
+		let requests = Arc::new(Mutex::new(Vec::new()));
+
+		let received = requests.clone();
+		tokio::spawn(async move {
+			let mut created = false;
+
+			while let Ok((mut socket, _)) = listener.accept().await {
+				let mut request = Vec::new();
+				let mut buf = [0u8; 4096];
+
+				let head = loop {
+					let n = socket.read(&mut buf).await.unwrap();
+					request.extend_from_slice(&buf[..n]);
+
+					let text = String::from_utf8_lossy(&request).to_string();
+					if let Some((head, body)) = text.split_once("\r\n\r\n") {
+						let content_length = head
+							.lines()
+							.find_map(|line| {
+								line.to_lowercase()
+									.strip_prefix("content-length: ")
+									.map(str::to_string)
+							})
+							.map_or(0, |len| len.parse::<usize>().unwrap());
+
+						if body.len() >= content_length {
+							break head.to_string();
+						}
+					}
+				};
+
+				let line = head.lines().next().unwrap().trim_end_matches(" HTTP/1.1");
+				received.lock().unwrap().push(line.to_string());
+
+				let (status, body) = match line {
+					"PROPFIND /dav/files/alice/" => ("207 Multi-Status", LISTING),
+					"PROPFIND /dav/files/alice/images/" => ("207 Multi-Status", IMAGES),
+					"MKCOL /dav/files/alice/new/" => {
+						created = true;
+						("201 Created", "")
+					},
+					"PUT /dav/files/alice/new/file.txt" if !created => ("409 Conflict", ""),
+					"PUT /dav/files/alice/new/file.txt" => ("201 Created", ""),
+					_ => ("404 Not Found", ""),
+				};
+
+				socket
+					.write_all(
+						format!(
+							"HTTP/1.1 {status}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+							body.len()
+						)
+						.as_bytes(),
+					)
+					.await
+					.unwrap();
+			}
+		});
+
+		let adapter = WebDavAdapter::new(Config {
+			url,
+			username: Some("alice".to_string()),
+			password: Some("secret".to_string()),
+			validation: Validation::Lazy,
+		})
+		.await
+		.unwrap();
+
+		(adapter, requests)
+	}
+
+	#[test]
+	fn test_parse_multistatus() {
+		let resources = parse_multistatus(LISTING).unwrap();
+
+		assert_eq!(resources.len(), 3);
+		assert!(resources[0].collection);
+		assert_eq!(
+			resources[1],
+			Resource {
+				href: "/dav/files/alice/cat%20photo.jpg".to_string(),
+				collection: false,
+				size: Some(4),
+				content_type: Some("image/jpeg".to_string()),
+				last_modified: httpdate::parse_http_date("Tue, 01 Oct 2024 10:00:00 GMT").ok(),
+				visibility: Some(Visibility::Public),
+				failed_properties: false,
+			}
+		);
+		assert!(resources[2].collection);
+		assert!(resources[2].failed_properties);
+	}
+
+	#[tokio::test]
+	async fn test_urls() {
+		let (adapter, _) = server().await;
+
+		let url = adapter.url(Path::new("images/cat photo.jpg"), false);
+		assert!(url
+			.as_str()
+			.ends_with("/dav/files/alice/images/cat%20photo.jpg"));
+		assert_eq!(
+			adapter.path_of(url.path()),
+			Some(PathBuf::from("images/cat photo.jpg"))
+		);
+		assert_eq!(adapter.path_of("/dav/files/bob/cat.jpg"), None);
+		assert_eq!(adapter.path_of("/dav/files/alicia/cat.jpg"), None);
+	}
+
+	#[tokio::test]
+	async fn test_list_contents() {
+		let (adapter, _) = server().await;
+
+		assert_eq!(
+			adapter.list_contents(Path::new(""), false).await.unwrap(),
+			vec![PathBuf::from("cat photo.jpg")]
+		);
+		assert_eq!(
+			adapter.list_contents(Path::new(""), true).await.unwrap(),
+			vec![
+				PathBuf::from("cat photo.jpg"),
+				PathBuf::from("images/dog.png")
+			]
+		);
+
+		let entries = adapter
+			.list_contents_with_metadata(Path::new(""), false)
+			.await
+			.unwrap();
+		assert_eq!(
+			entries,
+			vec![StorageEntry {
+				path: PathBuf::from("cat photo.jpg"),
+				is_dir: false,
+				size: Some(4),
+				last_modified: httpdate::parse_http_date("Tue, 01 Oct 2024 10:00:00 GMT").ok(),
+				mime: Some(mime::IMAGE_JPEG),
+				visibility: Some(Visibility::Public),
+			}]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_write_creates_parents() {
+		let (adapter, requests) = server().await;
+
+		adapter
+			.write(Path::new("new/file.txt"), b"hello")
+			.await
+			.unwrap();
+
+		assert_eq!(
+			*requests.lock().unwrap(),
+			vec![
+				"PUT /dav/files/alice/new/file.txt",
+				"MKCOL /dav/files/alice/new/",
+				"PUT /dav/files/alice/new/file.txt",
+			]
+		);
+	}
+}
@@ -0,0 +1,106 @@
+use std::path::{Path, PathBuf};
+use url::Url;
+
+use crate::{Filesystem, Result};
+
+/// The `Cache-Control` header for fingerprinted assets: their contents never change, so they can be cached forever.
+pub const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+/// How many hex characters of the checksum are added to fingerprinted file names.
+const FINGERPRINT_LEN: usize = 16;
+
+/// An asset stored under a path derived from its contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FingerprintedAsset {
+	/// The path the asset was stored at, like `css/app.3f2a9c1b7d4e8f60.css`.
+	pub path: PathBuf,
+	/// The public URL of the asset.
+	pub url: Url,
+	/// The `Cache-Control` header the asset should be served with.
+	pub cache_control: &'static str,
+}
+
+impl Filesystem {
+	/// Store an asset under a fingerprinted version of `path` (like `css/app.css` as `css/app.3f2a9c1b7d4e8f60.css`) and get its public URL.
+	///
+	/// Since the path changes whenever the contents do, the URL can be cached forever (see [`IMMUTABLE_CACHE_CONTROL`])
+	/// and never needs purging. Storing the same contents again reuses the existing file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if no URL strategy is configured, or if the adapter fails to write the file.
+	pub async fn write_fingerprinted(
+		&self,
+		path: &Path,
+		contents: &[u8],
+	) -> Result<FingerprintedAsset> {
+		let path = fingerprinted_path(&self.normalize(path)?, &sha256::digest(contents));
+
+		if !self.file_exists(&path).await? {
+			self.write(&path, contents).await?;
+		}
+
+		Ok(FingerprintedAsset {
+			url: self.public_url(&path).await?,
+			cache_control: IMMUTABLE_CACHE_CONTROL,
+			path,
+		})
+	}
+}
+
+/// Insert the fingerprint before the file's extension, or at the end of its name if it has none.
+fn fingerprinted_path(path: &Path, checksum: &str) -> PathBuf {
+	let fingerprint = &checksum[..FINGERPRINT_LEN];
+	let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+	let name = path.extension().map_or_else(
+		|| format!("{stem}.{fingerprint}"),
+		|extension| format!("{stem}.{fingerprint}.{}", extension.to_string_lossy()),
+	);
+
+	path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{adapters::MemoryAdapter, public_url::BaseUrl};
+
+	#[tokio::test]
+	async fn test_write_fingerprinted() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default()).with_url_strategy(
+			BaseUrl::new(Url::parse("https://cdn.example.com/").unwrap()),
+		);
+
+		let asset = filesystem
+			.write_fingerprinted(Path::new("css/app.css"), b"body {}")
+			.await
+			.unwrap();
+
+		let fingerprint = &sha256::digest("body {}")[..FINGERPRINT_LEN];
+		assert_eq!(
+			asset.path,
+			PathBuf::from(format!("css/app.{fingerprint}.css"))
+		);
+		assert_eq!(
+			asset.url.as_str(),
+			format!("https://cdn.example.com/css/app.{fingerprint}.css")
+		);
+		assert_eq!(asset.cache_control, IMMUTABLE_CACHE_CONTROL);
+
+		let changed = filesystem
+			.write_fingerprinted(Path::new("css/app.css"), b"body { margin: 0 }")
+			.await
+			.unwrap();
+		assert_ne!(changed.path, asset.path);
+		assert!(filesystem.file_exists(&asset.path).await.unwrap());
+	}
+
+	#[test]
+	fn test_fingerprinted_path_without_extension() {
+		assert_eq!(
+			fingerprinted_path(Path::new("bin/LICENSE"), &"a".repeat(64)),
+			PathBuf::from(format!("bin/LICENSE.{}", "a".repeat(FINGERPRINT_LEN)))
+		);
+	}
+}
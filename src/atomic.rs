@@ -0,0 +1,243 @@
+use std::{
+	path::{Path, PathBuf},
+	sync::atomic::{AtomicU64, Ordering},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+#[cfg(feature = "temp-files")]
+use futures::{io::AsyncRead, AsyncReadExt};
+
+#[cfg(feature = "temp-files")]
+use crate::FlysystemError;
+use crate::{Filesystem, Result};
+
+/// Tells apart the temporary files created in the same nanosecond.
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Get a hidden, unique path next to `path` to write it to before moving it into place,
+/// so it's on the same disk (where moves are renames) and under the same prefix.
+pub fn temp_path(path: &Path) -> PathBuf {
+	let now = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default()
+		.as_nanos();
+	let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+	path.with_file_name(format!(
+		".{name}.{now:x}-{:x}.tmp",
+		COUNTER.fetch_add(1, Ordering::Relaxed)
+	))
+}
+
+impl Filesystem {
+	/// Write a file so readers only ever see it whole, never half-written, even if the process crashes midway.
+	///
+	/// The file is written to a temporary sibling and moved into place, unless the adapter's writes are already atomic
+	/// (like the in-memory one, or S3 where objects only appear once fully uploaded). Moves are atomic on a local disk,
+	/// but might not be on other adapters.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to write the file, if the configured scanner flags it as infected,
+	/// or if the configured CDN purger fails to invalidate the previous version (in which case the file has already been written).
+	pub async fn write_atomic(&self, path: &Path, contents: &[u8]) -> Result<()> {
+		self.ensure_clean(path, contents).await?;
+
+		let path = self.normalize(path)?;
+		self.write_file(&path, true, || self.adapter.write_atomic(&path, contents))
+			.await
+	}
+
+	/// Start writing a file at a temporary location, to only move it to `path` once it's [complete](TempFile::persist).
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the path is invalid.
+	#[cfg(feature = "temp-files")]
+	pub fn temp_file(&self, path: &Path) -> Result<TempFile> {
+		let path = self.normalize(path)?;
+
+		Ok(TempFile {
+			filesystem: self.clone(),
+			temp: temp_path(&path),
+			path,
+			done: false,
+		})
+	}
+}
+
+/// A file being written at a temporary location, only moved to its final path once [persisted](TempFile::persist).
+///
+/// A crashed or failed upload then never leaves a half-written file behind for other jobs to pick up.
+/// Dropping it without persisting it deletes the temporary file in the background (on the current Tokio runtime, if any).
+/// Temporary files are hidden siblings of the final path, like `.report.csv.<id>.tmp`.
+#[cfg(feature = "temp-files")]
+#[derive(Debug)]
+pub struct TempFile {
+	filesystem: Filesystem,
+	path: PathBuf,
+	temp: PathBuf,
+	done: bool,
+}
+
+#[cfg(feature = "temp-files")]
+impl TempFile {
+	/// Get the path the file is moved to once persisted.
+	#[must_use]
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Get the path the file is written to until it's persisted.
+	#[must_use]
+	pub fn temp_path(&self) -> &Path {
+		&self.temp
+	}
+
+	/// Write (or overwrite) the temporary file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to write the file, or if the configured scanner flags it as infected.
+	pub async fn write(&self, contents: &[u8]) -> Result<()> {
+		self.filesystem.ensure_clean(&self.path, contents).await?;
+
+		self.filesystem
+			.adapter
+			.write(&self.temp, contents)
+			.await
+			.map_err(|error| FlysystemError::writing(&self.temp, error))
+	}
+
+	/// Write (or overwrite) the temporary file from a reader, which is buffered in memory if a scanner is configured.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the reader or the adapter fails, or if the configured scanner flags the file as infected.
+	pub async fn write_stream(&self, reader: &mut (dyn AsyncRead + Send + Unpin)) -> Result<()> {
+		if self.filesystem.scanner.is_some() {
+			let mut contents = Vec::new();
+			reader
+				.read_to_end(&mut contents)
+				.await
+				.map_err(|error| FlysystemError::writing(&self.temp, error))?;
+
+			return self.write(&contents).await;
+		}
+
+		self.filesystem
+			.adapter
+			.write_stream(&self.temp, reader)
+			.await
+			.map_err(|error| FlysystemError::writing(&self.temp, error))
+	}
+
+	/// Move the temporary file to its final path, replacing any file there.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if nothing was written, or if the adapter fails to move the file (in which case the
+	/// temporary file is deleted). Also fails if the configured CDN purger fails to invalidate the previous version.
+	pub async fn persist(mut self) -> Result<()> {
+		let (filesystem, temp, path) = (&self.filesystem, &self.temp, &self.path);
+		let mut moved = false;
+		let result = filesystem
+			.write_file(path, true, || async {
+				let result = filesystem.adapter.r#move(temp, path).await;
+				moved = result.is_ok();
+				result
+			})
+			.await;
+
+		// once moved, there's no temporary file left to delete (even if purging the CDN failed)
+		self.done = moved;
+		result
+	}
+
+	/// Delete the temporary file without persisting it.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to delete the file. Nothing having been written isn't an error.
+	pub async fn discard(mut self) -> Result<()> {
+		self.done = true;
+
+		match self.filesystem.adapter.delete(&self.temp).await {
+			Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+				Err(FlysystemError::deleting(&self.temp, error))
+			},
+			_ => Ok(()),
+		}
+	}
+}
+
+#[cfg(feature = "temp-files")]
+impl Drop for TempFile {
+	fn drop(&mut self) {
+		if self.done {
+			return;
+		}
+
+		if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+			let filesystem = self.filesystem.clone();
+			let temp = std::mem::take(&mut self.temp);
+
+			runtime.spawn(async move {
+				let _ = filesystem.adapter.delete(&temp).await;
+			});
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::adapters::MemoryAdapter;
+
+	#[tokio::test]
+	async fn test_write_atomic() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default());
+		let path = Path::new("exports/report.csv");
+
+		filesystem.write_atomic(path, b"a,b").await.unwrap();
+		assert_eq!(filesystem.read::<String>(path).await.unwrap(), "a,b");
+
+		let temp = temp_path(path);
+		assert_eq!(temp.parent(), path.parent());
+		assert!(temp
+			.file_name()
+			.unwrap()
+			.to_string_lossy()
+			.starts_with(".report.csv."));
+		assert_ne!(temp, temp_path(path));
+	}
+
+	#[cfg(feature = "temp-files")]
+	#[tokio::test]
+	async fn test_temp_file() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default());
+		let path = Path::new("exports/report.csv");
+
+		let file = filesystem.temp_file(path).unwrap();
+		file.write(b"a,b").await.unwrap();
+		// nothing shows up at the final path until the file is persisted
+		assert!(!filesystem.file_exists(path).await.unwrap());
+		file.persist().await.unwrap();
+		assert_eq!(filesystem.read::<String>(path).await.unwrap(), "a,b");
+		assert_eq!(
+			filesystem
+				.list_contents(Path::new("exports"), false)
+				.await
+				.unwrap(),
+			vec![path]
+		);
+
+		let file = filesystem.temp_file(path).unwrap();
+		file.write(b"half-writ").await.unwrap();
+		let temp = file.temp_path().to_path_buf();
+		drop(file);
+		tokio::task::yield_now().await;
+		assert!(!filesystem.file_exists(&temp).await.unwrap());
+		assert_eq!(filesystem.read::<String>(path).await.unwrap(), "a,b");
+	}
+}
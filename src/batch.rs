@@ -0,0 +1,672 @@
+use futures::{stream::BoxStream, Stream, StreamExt, TryStreamExt};
+use std::{
+	collections::HashMap,
+	io::Error,
+	path::{Path, PathBuf},
+	pin::pin,
+};
+
+use crate::{relative_to, Filesystem, FilesystemEvent, FlysystemError, IgnoreRules, Result};
+
+/// What a batch operation does when one of its items fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BatchMode {
+	/// Stop at the first failure, skipping the remaining items. Useful when the batch is all-or-nothing, like in request handlers.
+	#[default]
+	FailFast,
+	/// Keep going after failures, so they can all be reported (and retried) at once, like in migration jobs.
+	CollectAll,
+}
+
+/// A cheaper way for [`Filesystem::sync_with_options`] to tell whether a file changed, before comparing full checksums.
+///
+/// Files whose fingerprints match are skipped, so a file changed without changing its fingerprint isn't synced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Fingerprint {
+	/// Always compare the full checksums.
+	#[default]
+	Checksum,
+	/// Files with the same size whose copy isn't older than the source are unchanged.
+	SizeAndModified,
+	/// Files with the same size and the same BLAKE3 hash of their first and last `len` bytes are unchanged.
+	#[cfg(feature = "blake3")]
+	Sampled {
+		/// How many bytes are hashed at each end of the file.
+		len: u64,
+	},
+}
+
+/// How a sync job runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncOptions {
+	/// What to do when a file fails to sync.
+	pub mode: BatchMode,
+	/// How to tell whether a file changed.
+	pub fingerprint: Fingerprint,
+	/// Skip the files matched by these rules (relative to the synced directory), like `node_modules/` in backups.
+	/// Ignored files are neither copied, nor deleted from the destination when [mirroring](crate::sync::sync).
+	pub ignore: Option<IgnoreRules>,
+	/// Carry the content type and visibility of copied files over, recording whatever the destination can't store
+	/// in [sidecar files](crate::sidecar) (which aren't synced as files themselves). Ignored without the `sidecar` feature.
+	pub sidecars: bool,
+}
+
+impl SyncOptions {
+	/// Check if a file under `prefix` is left out of the sync, because it's ignored or a sidecar.
+	pub(crate) fn skips(&self, prefix: &Path, file: &Path) -> bool {
+		#[cfg(feature = "sidecar")]
+		if self.sidecars && crate::sidecar::is_sidecar(file) {
+			return true;
+		}
+
+		self.ignore
+			.as_ref()
+			.is_some_and(|rules| rules.is_ignored(relative_to(file, prefix)))
+	}
+}
+
+/// The outcome of each item of a batch operation.
+#[derive(Debug, Default)]
+pub struct BatchReport {
+	/// The items that succeeded.
+	pub succeeded: Vec<PathBuf>,
+	/// The items that failed, along with their errors.
+	pub failed: Vec<(PathBuf, FlysystemError)>,
+	/// The items that weren't attempted, because the batch stopped at a failure.
+	pub skipped: Vec<PathBuf>,
+}
+
+impl BatchReport {
+	/// Check if every item succeeded.
+	#[must_use]
+	pub const fn is_success(&self) -> bool {
+		self.failed.is_empty() && self.skipped.is_empty()
+	}
+
+	/// Get the items that succeeded, or the first error.
+	///
+	/// # Errors
+	///
+	/// This function will return the error of the first item that failed, if any.
+	pub fn into_result(self) -> Result<Vec<PathBuf>> {
+		match self.failed.into_iter().next() {
+			Some((_, error)) => Err(error),
+			None => Ok(self.succeeded),
+		}
+	}
+
+	/// Record the outcome of an item, returning whether the batch should go on.
+	fn record<E: Into<FlysystemError>>(
+		&mut self,
+		path: PathBuf,
+		result: std::result::Result<(), E>,
+		mode: BatchMode,
+	) -> bool {
+		match result {
+			Ok(()) => self.succeeded.push(path),
+			Err(error) => {
+				self.failed.push((path, error.into()));
+
+				return mode == BatchMode::CollectAll;
+			},
+		}
+
+		true
+	}
+}
+
+impl Filesystem {
+	/// Write many files, one after the other.
+	///
+	/// With [`BatchMode::CollectAll`] (and no virus scanner configured), the adapter writes them all at once instead,
+	/// concurrently or in batches when it supports it.
+	pub async fn write_many<P, C>(
+		&self,
+		files: impl IntoIterator<Item = (P, C)> + Send,
+		mode: BatchMode,
+	) -> BatchReport
+	where
+		P: AsRef<Path> + Send,
+		C: AsRef<[u8]> + Send + Sync,
+	{
+		if mode == BatchMode::CollectAll && self.scanner.is_none() {
+			let mut rejected = Vec::new();
+			let files: Vec<(PathBuf, C)> = files
+				.into_iter()
+				.filter_map(|(path, contents)| match self.normalize(path.as_ref()) {
+					Ok(normalized) => Some((normalized, contents)),
+					Err(error) => {
+						rejected.push((path.as_ref().to_path_buf(), error));
+						None
+					},
+				})
+				.collect();
+
+			let mut batch = Vec::with_capacity(files.len());
+			for (path, _) in &files {
+				self.emit_before(FilesystemEvent::WriteOccurred { path: path.clone() })
+					.await;
+				// purging a file that didn't exist is harmless, unlike not purging one that did
+				let overwritten = self.needs_purge(path).await.unwrap_or(true);
+				batch.push((path.clone(), overwritten));
+			}
+
+			let files: Vec<(&Path, &[u8])> = files
+				.iter()
+				.map(|(path, contents)| (path.as_path(), contents.as_ref()))
+				.collect();
+			let failed = self.adapter.write_many(&files).await;
+
+			let mut report = self
+				.finish_batch(
+					batch,
+					failed,
+					|path| FilesystemEvent::WriteOccurred { path },
+					|path, error| FilesystemEvent::WriteFailed { path, error },
+					FlysystemError::writing,
+				)
+				.await;
+			report.failed.extend(rejected);
+			return report;
+		}
+
+		let mut report = BatchReport::default();
+		let mut files = files.into_iter();
+
+		for (path, contents) in files.by_ref() {
+			let result = self.write(path.as_ref(), contents.as_ref()).await;
+
+			if !report.record(path.as_ref().to_path_buf(), result, mode) {
+				break;
+			}
+		}
+
+		report
+			.skipped
+			.extend(files.map(|(path, _)| path.as_ref().to_path_buf()));
+		report
+	}
+
+	/// Warm the caches of the adapter (like a [`CachedAdapter`](crate::adapters::CachedAdapter), or the OS page cache of a
+	/// [`LocalAdapter`](crate::adapters::LocalAdapter)) with many files ahead of known traffic, like a new release's assets before a deploy.
+	///
+	/// At most `concurrency` files are prefetched at once, and every file is attempted. Adapters without caches do nothing.
+	pub async fn prefetch<I>(&self, paths: I, concurrency: usize) -> BatchReport
+	where
+		I: IntoIterator,
+		I::Item: AsRef<Path> + Send,
+		I::IntoIter: Send,
+	{
+		let mut report = BatchReport::default();
+		let mut prefetched = pin!(futures::stream::iter(paths)
+			.map(|path| async move {
+				let path = path.as_ref().to_path_buf();
+				let result = async {
+					let normalized = self.normalize(&path)?;
+
+					self.adapter
+						.prefetch(&normalized)
+						.await
+						.map_err(|error| FlysystemError::reading(&normalized, error))
+				}
+				.await;
+
+				(path, result)
+			})
+			.buffer_unordered(concurrency.max(1)));
+
+		while let Some((path, result)) = prefetched.next().await {
+			report.record(path, result, BatchMode::CollectAll);
+		}
+
+		report
+	}
+
+	/// Delete many files, one after the other.
+	///
+	/// With [`BatchMode::CollectAll`], the adapter deletes them all at once instead, concurrently or in batches
+	/// (like S3's `DeleteObjects`) when it supports it. [Protected](Filesystem::protect) files fail either way.
+	pub async fn delete_many<P: AsRef<Path> + Send>(
+		&self,
+		paths: impl IntoIterator<Item = P> + Send,
+		mode: BatchMode,
+	) -> BatchReport {
+		if mode == BatchMode::CollectAll {
+			let mut rejected = Vec::new();
+			let paths: Vec<PathBuf> = paths
+				.into_iter()
+				.filter_map(|path| match self.normalize(path.as_ref()) {
+					Ok(normalized) => Some(normalized),
+					Err(error) => {
+						rejected.push((path.as_ref().to_path_buf(), error));
+						None
+					},
+				})
+				.collect();
+
+			let mut deletable = Vec::with_capacity(paths.len());
+			for path in paths {
+				match self.ensure_deletable(&path).await {
+					Ok(()) => deletable.push(path),
+					Err(error) => rejected.push((path, error)),
+				}
+			}
+			let paths = deletable;
+
+			for path in &paths {
+				self.emit_before(FilesystemEvent::FileDeleted { path: path.clone() })
+					.await;
+			}
+
+			let failed = self.adapter.delete_many(&paths).await;
+
+			let mut report = self
+				.finish_batch(
+					paths.into_iter().map(|path| (path, true)).collect(),
+					failed,
+					|path| FilesystemEvent::FileDeleted { path },
+					|path, error| FilesystemEvent::DeleteFailed { path, error },
+					FlysystemError::deleting,
+				)
+				.await;
+			report.failed.extend(rejected);
+			return report;
+		}
+
+		let mut report = BatchReport::default();
+		let mut paths = paths.into_iter();
+
+		for path in paths.by_ref() {
+			let result = self.delete(path.as_ref()).await;
+
+			if !report.record(path.as_ref().to_path_buf(), result, mode) {
+				break;
+			}
+		}
+
+		report
+			.skipped
+			.extend(paths.map(|path| path.as_ref().to_path_buf()));
+		report
+	}
+
+	/// Report a batch the adapter ran at once, purging the files it changed (when flagged) and emitting their events.
+	async fn finish_batch(
+		&self,
+		paths: Vec<(PathBuf, bool)>,
+		failed: Vec<(PathBuf, Error)>,
+		succeeded: fn(PathBuf) -> FilesystemEvent,
+		failure: fn(PathBuf, String) -> FilesystemEvent,
+		classify: fn(&Path, Error) -> FlysystemError,
+	) -> BatchReport {
+		let mut report = BatchReport::default();
+		let mut failed: HashMap<PathBuf, Error> = failed.into_iter().collect();
+
+		for (path, purge) in paths {
+			let result = match failed.remove(&path) {
+				Some(error) => {
+					self.emit(failure(path.clone(), error.to_string())).await;
+					Err(classify(&path, error))
+				},
+				None if purge => self.purge(&path).await,
+				None => Ok(()),
+			};
+			if result.is_ok() {
+				self.emit(succeeded(path.clone())).await;
+			}
+
+			report.record(path, result, BatchMode::CollectAll);
+		}
+
+		report
+	}
+
+	/// Copy every file under `source` to the same relative path under `destination`.
+	///
+	/// The report lists the source paths of the files. The listing is streamed, so huge prefixes are copied without holding all of their paths in memory.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to list the source directory. Failures for individual files are reported instead.
+	pub async fn copy_prefix(
+		&self,
+		source: &Path,
+		destination: &Path,
+		mode: BatchMode,
+	) -> Result<BatchReport> {
+		let (source, destination) = (self.normalize(source)?, self.normalize(destination)?);
+		let mut report = BatchReport::default();
+		let mut files = self.files_under(&source);
+
+		while let Some(file) = files.try_next().await? {
+			let result = self
+				.copy(&file, &destination.join(relative_to(&file, &source)))
+				.await;
+
+			if !report.record(file, result, mode) {
+				report.skipped = files.try_collect().await?;
+				break;
+			}
+		}
+
+		Ok(report)
+	}
+
+	/// Copy the files under `prefix` to another filesystem, skipping the ones it already has with the same checksum.
+	///
+	/// The report only lists the files that needed to be copied.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to list the directory. Failures for individual files are reported instead.
+	pub async fn sync(
+		&self,
+		prefix: &Path,
+		destination: &Self,
+		mode: BatchMode,
+	) -> Result<BatchReport> {
+		let options = SyncOptions {
+			mode,
+			..SyncOptions::default()
+		};
+
+		self.sync_with_options(prefix, destination, options).await
+	}
+
+	/// Copy the files under `prefix` to another filesystem, skipping the ones it already has, as configured by `options`.
+	///
+	/// The report only lists the files that needed to be copied. Like [`Filesystem::copy_prefix`], the listing is streamed.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to list the directory. Failures for individual files are reported instead.
+	/// With sidecars enabled, it also returns an error if the destination's sidecars can't be read or written.
+	pub async fn sync_with_options(
+		&self,
+		prefix: &Path,
+		destination: &Self,
+		options: SyncOptions,
+	) -> Result<BatchReport> {
+		let prefix = self.normalize(prefix)?;
+		let files = self
+			.files_under(&prefix)
+			.try_filter(|file| std::future::ready(!options.skips(&prefix, file)));
+
+		self.sync_files(files, destination, &options).await
+	}
+
+	/// Copy the given files to another filesystem, skipping the ones it already has.
+	pub(crate) async fn sync_files(
+		&self,
+		files: impl Stream<Item = Result<PathBuf>> + Send,
+		destination: &Self,
+		options: &SyncOptions,
+	) -> Result<BatchReport> {
+		let mut report = BatchReport::default();
+		#[cfg(feature = "sidecar")]
+		let mut metadata = crate::sidecar::MetadataSync::default();
+
+		let mut files = pin!(files);
+		let listed = loop {
+			let file = match files.try_next().await {
+				Ok(Some(file)) => file,
+				Ok(None) => break Ok(()),
+				Err(error) => break Err(error),
+			};
+
+			let result = match self
+				.sync_file(&file, destination, options.fingerprint)
+				.await
+			{
+				Ok(false) => continue,
+				#[cfg(feature = "sidecar")]
+				Ok(true) if options.sidecars => metadata.sync(self, destination, &file).await,
+				Ok(true) => Ok(()),
+				Err(error) => Err(error),
+			};
+
+			if !report.record(file, result, options.mode) {
+				break files
+					.try_collect()
+					.await
+					.map(|skipped| report.skipped = skipped);
+			}
+		};
+
+		// the metadata of the files copied before a listing failure is still recorded
+		#[cfg(feature = "sidecar")]
+		metadata.finish(destination).await?;
+		listed?;
+
+		Ok(report)
+	}
+
+	/// Copy a file to another filesystem unless it already has it, returning whether it was copied.
+	async fn sync_file(
+		&self,
+		path: &Path,
+		destination: &Self,
+		fingerprint: Fingerprint,
+	) -> Result<bool> {
+		if destination.file_exists(path).await?
+			&& (self
+				.fingerprint_matches(path, destination, fingerprint)
+				.await? || destination.checksum(path).await? == self.checksum(path).await?)
+		{
+			return Ok(false);
+		}
+
+		let contents = self
+			.adapter
+			.read(path)
+			.await
+			.map_err(|error| FlysystemError::reading(path, error))?;
+		destination.write(path, &contents.data).await?;
+
+		Ok(true)
+	}
+
+	/// Check if the fingerprints of a file and its copy on another filesystem match.
+	async fn fingerprint_matches(
+		&self,
+		path: &Path,
+		destination: &Self,
+		fingerprint: Fingerprint,
+	) -> Result<bool> {
+		match fingerprint {
+			Fingerprint::Checksum => Ok(false),
+			Fingerprint::SizeAndModified => Ok(self.file_size(path).await?
+				== destination.file_size(path).await?
+				&& destination.last_modified(path).await? >= self.last_modified(path).await?),
+			#[cfg(feature = "blake3")]
+			Fingerprint::Sampled { len } => {
+				Ok(self.sampled_hash(path, len).await?
+					== destination.sampled_hash(path, len).await?)
+			},
+		}
+	}
+
+	/// Hash the size and the first and last `len` bytes of a file.
+	#[cfg(feature = "blake3")]
+	async fn sampled_hash(&self, path: &Path, len: u64) -> Result<blake3::Hash> {
+		let size = self.file_size(path).await?;
+		let mut hasher = blake3::Hasher::new();
+
+		let read_range = |offset| async move {
+			self.adapter
+				.read_range(path, offset, len)
+				.await
+				.map_err(|error| FlysystemError::reading(path, error))
+		};
+
+		hasher.update(&size.to_le_bytes());
+		hasher.update(&read_range(0).await?.data);
+		hasher.update(&read_range(size.saturating_sub(len)).await?.data);
+
+		Ok(hasher.finalize())
+	}
+
+	/// List the files under a directory lazily, so jobs over huge prefixes don't hold the whole listing in memory.
+	pub(crate) fn files_under(&self, directory: &Path) -> BoxStream<'_, Result<PathBuf>> {
+		let directory = directory.to_path_buf();
+
+		self.adapter
+			.list_stream(&directory, true)
+			.map(move |entry| match entry {
+				Ok(entry) => Ok(directory.join(relative_to(&entry, &directory))),
+				Err(error) => Err(FlysystemError::listing(&directory, error)),
+			})
+			.boxed()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::ErrorKind;
+
+	use super::*;
+	use crate::adapters::MemoryAdapter;
+
+	#[tokio::test]
+	async fn test_delete_many_modes() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default());
+		let report = filesystem
+			.write_many([("a.txt", "a"), ("c.txt", "c")], BatchMode::FailFast)
+			.await;
+		assert!(report.is_success());
+
+		let report = filesystem
+			.delete_many(["a.txt", "b.txt", "c.txt"], BatchMode::FailFast)
+			.await;
+		assert_eq!(report.succeeded, vec![PathBuf::from("a.txt")]);
+		assert_eq!(report.failed[0].0, PathBuf::from("b.txt"));
+		assert_eq!(report.skipped, vec![PathBuf::from("c.txt")]);
+		assert_eq!(
+			report.into_result().unwrap_err().kind(),
+			ErrorKind::NotFound
+		);
+
+		let report = filesystem
+			.delete_many(["b.txt", "c.txt"], BatchMode::CollectAll)
+			.await;
+		assert_eq!(report.succeeded, vec![PathBuf::from("c.txt")]);
+		assert_eq!(report.failed.len(), 1);
+		assert!(report.skipped.is_empty());
+	}
+
+	#[tokio::test]
+	async fn test_copy_prefix_and_sync() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default());
+		filesystem
+			.write_many(
+				[
+					("releases/v1/app.js", "v1"),
+					("releases/v1/app.css", "body {}"),
+				],
+				BatchMode::FailFast,
+			)
+			.await
+			.into_result()
+			.unwrap();
+
+		let report = filesystem
+			.copy_prefix(
+				Path::new("releases/v1"),
+				Path::new("releases/latest"),
+				BatchMode::FailFast,
+			)
+			.await
+			.unwrap();
+		assert_eq!(report.succeeded.len(), 2);
+		assert_eq!(
+			filesystem
+				.read::<String>(Path::new("releases/latest/app.js"))
+				.await
+				.unwrap(),
+			"v1"
+		);
+
+		let mirror = Filesystem::from_adapter(MemoryAdapter::default());
+		mirror
+			.write(Path::new("releases/v1/app.js"), b"v1")
+			.await
+			.unwrap();
+
+		let report = filesystem
+			.sync(Path::new("releases/v1"), &mirror, BatchMode::CollectAll)
+			.await
+			.unwrap();
+		assert_eq!(
+			report.into_result().unwrap(),
+			vec![PathBuf::from("releases/v1/app.css")]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_sync_fingerprints() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default());
+		let mirror = Filesystem::from_adapter(MemoryAdapter::default());
+
+		filesystem
+			.write(Path::new("data/report.csv"), b"1,2,3")
+			.await
+			.unwrap();
+		mirror
+			.write(Path::new("data/report.csv"), b"4,5,6")
+			.await
+			.unwrap();
+
+		// same size and a newer copy, so the file is assumed unchanged
+		let options = SyncOptions {
+			fingerprint: Fingerprint::SizeAndModified,
+			..SyncOptions::default()
+		};
+		let report = filesystem
+			.sync_with_options(Path::new("data"), &mirror, options)
+			.await
+			.unwrap();
+		assert!(report.succeeded.is_empty());
+
+		let report = filesystem
+			.sync(Path::new("data"), &mirror, BatchMode::FailFast)
+			.await
+			.unwrap();
+		assert_eq!(report.succeeded, vec![PathBuf::from("data/report.csv")]);
+	}
+
+	#[cfg(feature = "blake3")]
+	#[tokio::test]
+	async fn test_sync_sampled_fingerprint() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default());
+		let mirror = Filesystem::from_adapter(MemoryAdapter::default());
+
+		filesystem
+			.write(Path::new("data/video.bin"), b"head-middle-tail")
+			.await
+			.unwrap();
+		mirror
+			.write(Path::new("data/video.bin"), b"head-MIDDLE-tail")
+			.await
+			.unwrap();
+
+		let options = SyncOptions {
+			fingerprint: Fingerprint::Sampled { len: 4 },
+			..SyncOptions::default()
+		};
+		let report = filesystem
+			.sync_with_options(Path::new("data"), &mirror, options.clone())
+			.await
+			.unwrap();
+		assert!(report.succeeded.is_empty());
+
+		mirror
+			.write(Path::new("data/video.bin"), b"head-middle-TAIL")
+			.await
+			.unwrap();
+		let report = filesystem
+			.sync_with_options(Path::new("data"), &mirror, options)
+			.await
+			.unwrap();
+		assert_eq!(report.succeeded, vec![PathBuf::from("data/video.bin")]);
+	}
+}
@@ -0,0 +1,420 @@
+//! Measure how fast adapters (and stacks of decorators) are on common operation mixes, to pick a backend or tune settings with data.
+//!
+//! ```rust,ignore
+//! use flysystem::{bench::{Bench, Workload}, adapters::{CachedAdapter, MemoryAdapter}, Filesystem};
+//!
+//! let mut bench = Bench::new("flysystem-bench").with_workloads(Workload::standard());
+//! bench.measure("memory", &Filesystem::from_adapter(MemoryAdapter::default())).await?;
+//! bench.measure("cached", &Filesystem::from_adapter(CachedAdapter::new(MemoryAdapter::default(), 1 << 20))).await?;
+//!
+//! println!("{bench}");
+//! ```
+//!
+//! The same workloads run under criterion with [`Bench::criterion`], which `cargo bench --features bench --bench workloads` uses
+//! to track them across changes. The `flysystem-bench` binary (built with the `bench` feature) runs the standard workloads
+//! against the adapters given on the command line.
+
+use std::{
+	fmt,
+	path::{Path, PathBuf},
+	time::{Duration, Instant},
+};
+
+use criterion::{Criterion, Throughput};
+use futures::io::{copy, sink, Cursor};
+use tokio::runtime::Runtime;
+
+use crate::{Filesystem, Result};
+
+/// A mix of operations to measure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Workload {
+	/// Write, then read back, `count` files of `size` bytes each.
+	SmallWrites { count: usize, size: usize },
+	/// Stream a file of `size` bytes in, then out, `count` times.
+	LargeStreams { count: usize, size: usize },
+	/// Deeply list a tree `depth` directories deep, with `fanout` directories and files in each, `count` times.
+	DeepListings {
+		count: usize,
+		depth: usize,
+		fanout: usize,
+	},
+}
+
+impl Workload {
+	/// Small writes of 1 KiB, 16 MiB streams and listings of a tree of 1,364 files.
+	#[must_use]
+	pub fn standard() -> Vec<Self> {
+		vec![
+			Self::SmallWrites {
+				count: 1000,
+				size: 1024,
+			},
+			Self::LargeStreams {
+				count: 4,
+				size: 16 * 1024 * 1024,
+			},
+			Self::DeepListings {
+				count: 20,
+				depth: 5,
+				fanout: 4,
+			},
+		]
+	}
+
+	/// A short name for the workload, like `small writes (1 KiB)`.
+	#[must_use]
+	pub fn name(&self) -> String {
+		match self {
+			Self::SmallWrites { size, .. } => format!("small writes ({})", human_size(*size)),
+			Self::LargeStreams { size, .. } => format!("large streams ({})", human_size(*size)),
+			Self::DeepListings { depth, fanout, .. } => {
+				format!("deep listings ({depth}x{fanout})")
+			},
+		}
+	}
+
+	/// How much each iteration moves, for criterion to report throughput in.
+	const fn throughput(&self) -> Throughput {
+		match *self {
+			Self::SmallWrites { count, .. } => Throughput::Elements(2 * count as u64),
+			Self::LargeStreams { size, .. } => Throughput::Bytes(2 * size as u64),
+			Self::DeepListings { .. } => Throughput::Elements(1),
+		}
+	}
+
+	/// How many times [`iterate`](Self::iterate) runs when measured by [`Bench::measure`].
+	const fn repetitions(&self) -> usize {
+		match *self {
+			Self::SmallWrites { .. } => 1,
+			Self::LargeStreams { count, .. } | Self::DeepListings { count, .. } => count,
+		}
+	}
+
+	/// Write the files the workload needs under `prefix` before it's iterated.
+	async fn prepare(&self, filesystem: &Filesystem, prefix: &Path) -> Result<()> {
+		if let Self::DeepListings { depth, fanout, .. } = *self {
+			let mut directories = vec![prefix.to_path_buf()];
+			for _ in 0..depth {
+				directories = directories
+					.iter()
+					.flat_map(|directory| (0..fanout).map(move |i| directory.join(format!("d{i}"))))
+					.collect();
+
+				for directory in &directories {
+					filesystem.write(&directory.join("file.txt"), b"x").await?;
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Run the workload's operations once, timing each of them.
+	async fn iterate(&self, filesystem: &Filesystem, prefix: &Path) -> Result<Vec<Sample>> {
+		let mut samples = Vec::new();
+
+		match *self {
+			Self::SmallWrites { count, size } => {
+				let content = vec![b'x'; size];
+
+				for i in 0..count {
+					let path = prefix.join(format!("{i}.bin"));
+					samples.push(timed(size, filesystem.write(&path, &content)).await?);
+				}
+				for i in 0..count {
+					let path = prefix.join(format!("{i}.bin"));
+					samples.push(timed(size, filesystem.read::<Vec<u8>>(&path)).await?);
+				}
+			},
+			Self::LargeStreams { size, .. } => {
+				let content = vec![b'x'; size];
+				let path = prefix.join("stream.bin");
+
+				let mut reader = Cursor::new(content.as_slice());
+				samples.push(timed(size, filesystem.write_stream(&path, &mut reader)).await?);
+
+				samples.push(
+					timed(size, async {
+						let reader = filesystem.read_stream(&path).await?;
+						copy(reader, &mut sink())
+							.await
+							.map_err(|error| crate::FlysystemError::reading(&path, error))
+					})
+					.await?,
+				);
+			},
+			Self::DeepListings { .. } => {
+				samples.push(timed(0, filesystem.list_contents(prefix, true)).await?);
+			},
+		}
+
+		Ok(samples)
+	}
+
+	/// Run the workload under `prefix`, deleting everything it wrote afterwards.
+	async fn run(&self, filesystem: &Filesystem, prefix: &Path) -> Result<Vec<Sample>> {
+		self.prepare(filesystem, prefix).await?;
+
+		let mut samples = Vec::new();
+		for _ in 0..self.repetitions() {
+			samples.extend(self.iterate(filesystem, prefix).await?);
+		}
+
+		filesystem.delete_directory(prefix).await?;
+
+		Ok(samples)
+	}
+}
+
+fn human_size(bytes: usize) -> String {
+	match bytes {
+		bytes if bytes >= 1024 * 1024 => format!("{} MiB", bytes / (1024 * 1024)),
+		bytes if bytes >= 1024 => format!("{} KiB", bytes / 1024),
+		bytes => format!("{bytes} B"),
+	}
+}
+
+/// How long an operation took, and how many bytes it moved.
+struct Sample {
+	bytes: usize,
+	elapsed: Duration,
+}
+
+async fn timed<T>(
+	bytes: usize,
+	operation: impl std::future::Future<Output = Result<T>>,
+) -> Result<Sample> {
+	let start = Instant::now();
+	operation.await?;
+
+	Ok(Sample {
+		bytes,
+		elapsed: start.elapsed(),
+	})
+}
+
+/// The results of running a workload against an adapter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Measurement {
+	/// The name the adapter was measured under.
+	pub adapter: String,
+	/// The name of the workload.
+	pub workload: String,
+	/// How many operations ran.
+	pub operations: usize,
+	/// How many bytes were written and read.
+	pub bytes: u64,
+	/// How long all the operations took.
+	pub elapsed: Duration,
+	/// The median latency of an operation.
+	pub p50: Duration,
+	/// The 99th percentile latency of an operation.
+	pub p99: Duration,
+}
+
+impl Measurement {
+	fn new(adapter: &str, workload: &Workload, mut samples: Vec<Sample>) -> Self {
+		samples.sort_unstable_by_key(|sample| sample.elapsed);
+		let percentile = |percent: usize| {
+			samples
+				.get((samples.len() * percent / 100).min(samples.len().saturating_sub(1)))
+				.map_or(Duration::ZERO, |sample| sample.elapsed)
+		};
+
+		Self {
+			adapter: adapter.to_string(),
+			workload: workload.name(),
+			operations: samples.len(),
+			bytes: samples.iter().map(|sample| sample.bytes as u64).sum(),
+			elapsed: samples.iter().map(|sample| sample.elapsed).sum(),
+			p50: percentile(50),
+			p99: percentile(99),
+		}
+	}
+
+	/// How many operations ran per second.
+	#[must_use]
+	#[allow(clippy::cast_precision_loss)]
+	pub fn operations_per_second(&self) -> f64 {
+		self.operations as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+	}
+
+	/// How many bytes were moved per second.
+	#[must_use]
+	#[allow(clippy::cast_precision_loss)]
+	pub fn bytes_per_second(&self) -> f64 {
+		self.bytes as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+	}
+}
+
+/// Runs workloads against adapters, collecting the measurements into a comparison table (its [`Display`](fmt::Display) output).
+#[derive(Debug, Clone)]
+pub struct Bench {
+	prefix: PathBuf,
+	workloads: Vec<Workload>,
+	measurements: Vec<Measurement>,
+}
+
+impl Bench {
+	/// Create a bench writing under `prefix`, which is deleted after each workload.
+	#[must_use]
+	pub fn new(prefix: impl Into<PathBuf>) -> Self {
+		Self {
+			prefix: prefix.into(),
+			workloads: Vec::new(),
+			measurements: Vec::new(),
+		}
+	}
+
+	/// Also run the given workload.
+	#[must_use]
+	pub fn with_workload(mut self, workload: Workload) -> Self {
+		self.workloads.push(workload);
+		self
+	}
+
+	/// Also run the given workloads.
+	#[must_use]
+	pub fn with_workloads(mut self, workloads: impl IntoIterator<Item = Workload>) -> Self {
+		self.workloads.extend(workloads);
+		self
+	}
+
+	/// Run every workload against `filesystem`, recording the measurements under `adapter`.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if any operation fails.
+	pub async fn measure(
+		&mut self,
+		adapter: &str,
+		filesystem: &Filesystem,
+	) -> Result<&[Measurement]> {
+		let start = self.measurements.len();
+
+		for workload in &self.workloads {
+			let samples = workload.run(filesystem, &self.prefix).await?;
+			self.measurements
+				.push(Measurement::new(adapter, workload, samples));
+		}
+
+		Ok(&self.measurements[start..])
+	}
+
+	/// Register every workload with criterion, as a benchmark group named after the workload with `adapter` as its function,
+	/// so runs of `cargo bench` are compared with the previous ones.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if preparing or cleaning up after a workload fails.
+	///
+	/// # Panics
+	///
+	/// Panics if an operation fails while criterion measures it.
+	pub fn criterion(
+		&self,
+		c: &mut Criterion,
+		runtime: &Runtime,
+		adapter: &str,
+		filesystem: &Filesystem,
+	) -> Result<()> {
+		for workload in &self.workloads {
+			runtime.block_on(workload.prepare(filesystem, &self.prefix))?;
+
+			c.benchmark_group(workload.name())
+				.throughput(workload.throughput())
+				.bench_function(adapter, |b| {
+					b.to_async(runtime).iter(|| async {
+						workload.iterate(filesystem, &self.prefix).await.unwrap()
+					});
+				});
+
+			runtime.block_on(filesystem.delete_directory(&self.prefix))?;
+		}
+
+		Ok(())
+	}
+
+	/// Get every measurement taken so far.
+	#[must_use]
+	pub fn measurements(&self) -> &[Measurement] {
+		&self.measurements
+	}
+}
+
+impl fmt::Display for Bench {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(
+			f,
+			"| {:<24} | {:<12} | {:>12} | {:>12} | {:>10} | {:>10} |",
+			"workload", "adapter", "ops/s", "MiB/s", "p50", "p99"
+		)?;
+		writeln!(
+			f,
+			"|{:-<26}|{:-<14}|{:->14}|{:->14}|{:->12}|{:->12}|",
+			"", "", "", "", "", ""
+		)?;
+
+		let mut measurements: Vec<&Measurement> = self.measurements.iter().collect();
+		measurements.sort_by(|a, b| a.workload.cmp(&b.workload));
+
+		for measurement in measurements {
+			writeln!(
+				f,
+				"| {:<24} | {:<12} | {:>12.0} | {:>12.1} | {:>10} | {:>10} |",
+				measurement.workload,
+				measurement.adapter,
+				measurement.operations_per_second(),
+				measurement.bytes_per_second() / (1024.0 * 1024.0),
+				format!("{:.2?}", measurement.p50),
+				format!("{:.2?}", measurement.p99),
+			)?;
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::adapters::MemoryAdapter;
+
+	#[tokio::test]
+	async fn test_bench() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default());
+		let mut bench = Bench::new("bench").with_workloads([
+			Workload::SmallWrites {
+				count: 10,
+				size: 16,
+			},
+			Workload::LargeStreams {
+				count: 1,
+				size: 1024,
+			},
+			Workload::DeepListings {
+				count: 2,
+				depth: 2,
+				fanout: 2,
+			},
+		]);
+
+		let measurements = bench.measure("memory", &filesystem).await.unwrap();
+		assert_eq!(measurements.len(), 3);
+		assert_eq!(measurements[0].operations, 20);
+		assert_eq!(measurements[0].bytes, 320);
+		assert_eq!(measurements[1].bytes, 2048);
+		assert_eq!(measurements[2].operations, 2);
+
+		// everything written is cleaned up
+		assert!(!filesystem
+			.directory_exists(Path::new("bench"))
+			.await
+			.unwrap());
+
+		let table = bench.to_string();
+		assert!(table.contains("| small writes (16 B)"));
+		assert_eq!(table.lines().count(), 5);
+	}
+}
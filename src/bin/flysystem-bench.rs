@@ -0,0 +1,81 @@
+//! Compare adapters on the standard workloads, printing a table of the results.
+//!
+//! Usage: `flysystem-bench [memory] [local=<directory>] [s3]`, measuring the memory adapter and a temporary local directory
+//! when no adapters are given. S3 needs the `s3` feature and is configured from the environment (see `s3::Config::from_env`).
+
+use std::{env, process::ExitCode};
+
+use flysystem::{
+	adapters::MemoryAdapter,
+	bench::{Bench, Workload},
+	Filesystem,
+};
+
+async fn filesystem(adapter: &str) -> Result<Filesystem, String> {
+	match adapter.split_once('=') {
+		None if adapter == "memory" => Ok(Filesystem::from_adapter(MemoryAdapter::default())),
+		#[cfg(feature = "local")]
+		Some(("local", location)) => {
+			use flysystem::adapters::{local::Config, LocalAdapter, Validation};
+
+			Filesystem::new::<LocalAdapter>(Config {
+				location: location.into(),
+				lazy_root_creation: true,
+				validation: Validation::default(),
+				public_url: None,
+			})
+			.await
+			.map_err(|error| error.to_string())
+		},
+		#[cfg(feature = "s3")]
+		None if adapter == "s3" => {
+			use flysystem::adapters::{s3::ConfigBuilder, S3Adapter};
+
+			let config = ConfigBuilder::from_env()
+				.build()
+				.map_err(|error| error.to_string())?;
+			Filesystem::new::<S3Adapter>(config)
+				.await
+				.map_err(|error| error.to_string())
+		},
+		_ => Err(format!("unknown or disabled adapter {adapter:?}")),
+	}
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+	let mut adapters: Vec<String> = env::args().skip(1).collect();
+	if adapters.is_empty() {
+		adapters.push("memory".to_string());
+		#[cfg(feature = "local")]
+		adapters.push(format!(
+			"local={}",
+			env::temp_dir().join("flysystem-bench").display()
+		));
+	}
+
+	let mut bench = Bench::new("flysystem-bench").with_workloads(Workload::standard());
+	for adapter in &adapters {
+		let result = match filesystem(adapter).await {
+			Ok(filesystem) => bench
+				.measure(
+					adapter
+						.split_once('=')
+						.map_or(adapter.as_str(), |(name, _)| name),
+					&filesystem,
+				)
+				.await
+				.map(|_| ())
+				.map_err(|error| error.to_string()),
+			Err(error) => Err(error),
+		};
+
+		if let Err(error) = result {
+			eprintln!("{adapter}: {error}");
+			return ExitCode::FAILURE;
+		}
+	}
+
+	println!("{bench}");
+	ExitCode::SUCCESS
+}
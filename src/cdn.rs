@@ -0,0 +1,357 @@
+use std::{fmt::Debug, io::Result, path::Path};
+
+/// A CDN cache invalidator, invoked when a file is overwritten or deleted through a [`Filesystem`](crate::Filesystem).
+#[async_trait::async_trait]
+pub trait CdnPurger: Debug + Send + Sync {
+	/// Invalidate the cached copies of a file.
+	async fn purge(&self, path: &Path) -> Result<()>;
+}
+
+#[cfg(any(feature = "cloudflare", feature = "fastly"))]
+use std::io::{Error, ErrorKind};
+#[cfg(any(feature = "cloudflare", feature = "fastly"))]
+use url::Url;
+
+/// Resolve the public URL of a file, relative to the base URL it's served from.
+#[cfg(any(feature = "cloudflare", feature = "fastly"))]
+fn public_url(base_url: &Url, path: &Path) -> Result<Url> {
+	crate::public_url::join_path(base_url, path)
+}
+
+/// Resolve an API path (which is already a valid URL path) relative to the API endpoint.
+#[cfg(any(feature = "cloudflare", feature = "fastly"))]
+fn api_url(endpoint: &Url, path: &str) -> Result<Url> {
+	let mut url = endpoint.clone();
+	if !url.path().ends_with('/') {
+		url.set_path(&format!("{}/", url.path()));
+	}
+
+	url.join(path.trim_start_matches('/'))
+		.map_err(|e| Error::new(ErrorKind::InvalidInput, e))
+}
+
+/// Turn a failed purge API response into an error.
+#[cfg(any(feature = "cloudflare", feature = "fastly"))]
+async fn check_response(response: reqwest::Response) -> Result<()> {
+	if response.status().is_success() {
+		return Ok(());
+	}
+
+	let status = response.status();
+	let body = response.text().await.unwrap_or_default();
+
+	Err(Error::other(format!(
+		"The CDN purge failed ({status}): {body}"
+	)))
+}
+
+#[cfg(feature = "cloudflare")]
+pub use cloudflare::CloudflarePurger;
+
+#[cfg(feature = "cloudflare")]
+mod cloudflare {
+	use std::{fmt, io::Result, path::Path};
+	use url::Url;
+
+	use super::{api_url, check_response, public_url, CdnPurger};
+	use crate::REDACTED;
+
+	/// Purges files from a Cloudflare zone by URL.
+	#[derive(Clone)]
+	pub struct CloudflarePurger {
+		zone_id: String,
+		api_token: String,
+		base_url: Url,
+		endpoint: Url,
+		client: reqwest::Client,
+	}
+
+	impl fmt::Debug for CloudflarePurger {
+		fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+			f.debug_struct("CloudflarePurger")
+				.field("zone_id", &self.zone_id)
+				.field("api_token", &REDACTED)
+				.field("base_url", &self.base_url)
+				.finish_non_exhaustive()
+		}
+	}
+
+	impl CloudflarePurger {
+		/// Create a purger for the files served from `base_url` (like `https://cdn.example.com/`) through the given zone.
+		///
+		/// # Panics
+		///
+		/// Never, the default API endpoint is a valid URL.
+		#[must_use]
+		pub fn new(
+			zone_id: impl Into<String>,
+			api_token: impl Into<String>,
+			base_url: Url,
+		) -> Self {
+			Self {
+				base_url,
+				zone_id: zone_id.into(),
+				api_token: api_token.into(),
+				client: reqwest::Client::new(),
+				endpoint: Url::parse("https://api.cloudflare.com/client/v4/").unwrap(),
+			}
+		}
+
+		/// Send requests to a different API endpoint.
+		#[must_use]
+		pub fn with_endpoint(mut self, endpoint: Url) -> Self {
+			self.endpoint = endpoint;
+			self
+		}
+
+		fn request(&self, url: &Url) -> Result<reqwest::RequestBuilder> {
+			let endpoint = api_url(
+				&self.endpoint,
+				&format!("zones/{}/purge_cache", self.zone_id),
+			)?;
+
+			Ok(self
+				.client
+				.post(endpoint)
+				.bearer_auth(&self.api_token)
+				.json(&serde_json::json!({ "files": [url.as_str()] })))
+		}
+	}
+
+	#[async_trait::async_trait]
+	impl CdnPurger for CloudflarePurger {
+		async fn purge(&self, path: &Path) -> Result<()> {
+			let url = public_url(&self.base_url, path)?;
+			let response = self
+				.request(&url)?
+				.send()
+				.await
+				.map_err(std::io::Error::other)?;
+
+			check_response(response).await
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		#[test]
+		fn test_request() {
+			let purger = CloudflarePurger::new(
+				"zone",
+				"token",
+				Url::parse("https://cdn.example.com/assets").unwrap(),
+			);
+
+			let request = purger
+				.request(&public_url(&purger.base_url, Path::new("css/app.css")).unwrap())
+				.unwrap()
+				.build()
+				.unwrap();
+
+			assert_eq!(
+				request.url().as_str(),
+				"https://api.cloudflare.com/client/v4/zones/zone/purge_cache"
+			);
+			assert_eq!(request.headers()["authorization"], "Bearer token");
+			assert_eq!(
+				request.body().unwrap().as_bytes().unwrap(),
+				br#"{"files":["https://cdn.example.com/assets/css/app.css"]}"#
+			);
+		}
+	}
+}
+
+#[cfg(feature = "fastly")]
+pub use fastly::FastlyPurger;
+
+#[cfg(feature = "fastly")]
+mod fastly {
+	use std::{fmt, io::Result, path::Path};
+	use url::Url;
+
+	use super::{api_url, check_response, public_url, CdnPurger};
+	use crate::REDACTED;
+
+	/// Purges files from a Fastly service by URL.
+	#[derive(Clone)]
+	pub struct FastlyPurger {
+		api_token: String,
+		base_url: Url,
+		endpoint: Url,
+		soft: bool,
+		client: reqwest::Client,
+	}
+
+	impl fmt::Debug for FastlyPurger {
+		fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+			f.debug_struct("FastlyPurger")
+				.field("api_token", &REDACTED)
+				.field("base_url", &self.base_url)
+				.field("soft", &self.soft)
+				.finish_non_exhaustive()
+		}
+	}
+
+	impl FastlyPurger {
+		/// Create a purger for the files served from `base_url` (like `https://cdn.example.com/`).
+		///
+		/// # Panics
+		///
+		/// Never, the default API endpoint is a valid URL.
+		#[must_use]
+		pub fn new(api_token: impl Into<String>, base_url: Url) -> Self {
+			Self {
+				base_url,
+				soft: false,
+				api_token: api_token.into(),
+				client: reqwest::Client::new(),
+				endpoint: Url::parse("https://api.fastly.com/").unwrap(),
+			}
+		}
+
+		/// Mark cached copies as stale instead of evicting them, so they can still be served if the origin is down.
+		#[must_use]
+		pub const fn soft(mut self) -> Self {
+			self.soft = true;
+			self
+		}
+
+		/// Send requests to a different API endpoint.
+		#[must_use]
+		pub fn with_endpoint(mut self, endpoint: Url) -> Self {
+			self.endpoint = endpoint;
+			self
+		}
+
+		fn request(&self, url: &Url) -> Result<reqwest::RequestBuilder> {
+			let cached_url = url
+				.as_str()
+				.trim_start_matches(&format!("{}://", url.scheme()));
+			let endpoint = api_url(&self.endpoint, &format!("purge/{cached_url}"))?;
+
+			let request = self
+				.client
+				.post(endpoint)
+				.header("Fastly-Key", &self.api_token);
+
+			Ok(if self.soft {
+				request.header("Fastly-Soft-Purge", "1")
+			} else {
+				request
+			})
+		}
+	}
+
+	#[async_trait::async_trait]
+	impl CdnPurger for FastlyPurger {
+		async fn purge(&self, path: &Path) -> Result<()> {
+			let url = public_url(&self.base_url, path)?;
+			let response = self
+				.request(&url)?
+				.send()
+				.await
+				.map_err(std::io::Error::other)?;
+
+			check_response(response).await
+		}
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use super::*;
+
+		#[test]
+		fn test_request() {
+			let purger =
+				FastlyPurger::new("token", Url::parse("https://cdn.example.com/").unwrap()).soft();
+
+			let request = purger
+				.request(&public_url(&purger.base_url, Path::new("css/app.css")).unwrap())
+				.unwrap()
+				.build()
+				.unwrap();
+
+			assert_eq!(
+				request.url().as_str(),
+				"https://api.fastly.com/purge/cdn.example.com/css/app.css"
+			);
+			assert_eq!(request.headers()["fastly-key"], "token");
+			assert_eq!(request.headers()["fastly-soft-purge"], "1");
+
+			// file names are escaped once in the purged URL, which is passed to the API as is
+			let request = purger
+				.request(&public_url(&purger.base_url, Path::new("css/app?v=1 #2.css")).unwrap())
+				.unwrap()
+				.build()
+				.unwrap();
+			assert_eq!(
+				request.url().as_str(),
+				"https://api.fastly.com/purge/cdn.example.com/css/app%3Fv=1%20%232.css"
+			);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{
+		path::PathBuf,
+		sync::{Arc, Mutex},
+	};
+
+	use super::*;
+	use crate::{adapters::MemoryAdapter, Filesystem};
+
+	#[derive(Debug, Clone, Default)]
+	struct RecordingPurger {
+		purged: Arc<Mutex<Vec<PathBuf>>>,
+	}
+
+	#[async_trait::async_trait]
+	impl CdnPurger for RecordingPurger {
+		async fn purge(&self, path: &Path) -> Result<()> {
+			self.purged.lock().unwrap().push(path.to_path_buf());
+			Ok(())
+		}
+	}
+
+	#[tokio::test]
+	async fn test_purges_overwritten_and_deleted_files() {
+		let purger = RecordingPurger::default();
+		let filesystem = Filesystem::new::<MemoryAdapter>(())
+			.await
+			.unwrap()
+			.with_cdn_purger(purger.clone());
+
+		filesystem
+			.write(Path::new("/app.css"), b"body {}")
+			.await
+			.unwrap();
+		assert!(purger.purged.lock().unwrap().is_empty());
+
+		filesystem
+			.write(Path::new("app.css"), b"body { color: red }")
+			.await
+			.unwrap();
+		filesystem
+			.copy(Path::new("app.css"), Path::new("copy.css"))
+			.await
+			.unwrap();
+		filesystem
+			.r#move(Path::new("copy.css"), Path::new("moved.css"))
+			.await
+			.unwrap();
+		filesystem.delete(Path::new("app.css")).await.unwrap();
+
+		assert_eq!(
+			*purger.purged.lock().unwrap(),
+			vec![
+				PathBuf::from("app.css"),
+				PathBuf::from("copy.css"),
+				PathBuf::from("app.css")
+			]
+		);
+	}
+}
@@ -0,0 +1,161 @@
+//! Resume huge listings (like scans of a whole bucket) where they were interrupted, instead of starting over.
+
+use futures::{stream::BoxStream, Stream, StreamExt, TryStreamExt};
+use std::{
+	fmt,
+	io::Result,
+	path::{Path, PathBuf},
+};
+
+use crate::{relative_to, Filesystem, FlysystemError};
+
+/// A position in a listing, from which [`Filesystem::list_checkpointed`] can resume it.
+///
+/// It's the last listed entry relative to the listed directory, so it can be saved anywhere (as its [`Display`](fmt::Display) form)
+/// and used with any adapter holding the same files.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ListingCheckpoint {
+	/// The last entry listed before the checkpoint, relative to the listed directory.
+	pub after: PathBuf,
+}
+
+impl ListingCheckpoint {
+	/// Resume a listing after the given entry, relative to the listed directory.
+	pub fn new(after: impl Into<PathBuf>) -> Self {
+		Self {
+			after: after.into(),
+		}
+	}
+}
+
+impl fmt::Display for ListingCheckpoint {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.after.display().fmt(f)
+	}
+}
+
+/// An item of a [checkpointed listing](Filesystem::list_checkpointed).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListingItem {
+	/// A listed file or directory.
+	Entry(PathBuf),
+	/// Every entry before this was listed, so the listing can be resumed from here once they were handled.
+	Checkpoint(ListingCheckpoint),
+}
+
+impl Filesystem {
+	/// Lazily list the files in a directory (optionally recursively) in the order of their keys' bytes, emitting a checkpoint every `every` entries.
+	///
+	/// Passing the last checkpoint an interrupted listing emitted as `resume` continues it from there.
+	/// Adapters listing in sorted pages (like S3) start right after the checkpoint, while others list and sort the whole directory first.
+	pub fn list_checkpointed(
+		&self,
+		path: &Path,
+		deep: bool,
+		resume: Option<&ListingCheckpoint>,
+		every: usize,
+	) -> impl Stream<Item = crate::Result<ListingItem>> + Send + '_ {
+		let path = match self.normalize(path) {
+			Ok(path) => path,
+			Err(error) => return futures::stream::once(async { Err(error) }).boxed(),
+		};
+		let every = every.max(1);
+		let mut listed = 0;
+
+		self.entries_after(
+			&path,
+			deep,
+			resume.map(|checkpoint| checkpoint.after.as_path()),
+		)
+		.map_err(move |error| FlysystemError::listing(&path, error))
+		.map_ok(move |(entry, relative)| {
+			listed += 1;
+			let checkpoint = (listed % every == 0)
+				.then(|| ListingItem::Checkpoint(ListingCheckpoint::new(relative)));
+
+			futures::stream::iter(
+				std::iter::once(ListingItem::Entry(entry))
+					.chain(checkpoint)
+					.map(Ok),
+			)
+		})
+		.try_flatten()
+		.boxed()
+	}
+
+	/// List the entries of a directory in key order after `after`, along with their path relative to it.
+	pub(crate) fn entries_after(
+		&self,
+		directory: &Path,
+		deep: bool,
+		after: Option<&Path>,
+	) -> BoxStream<'_, Result<(PathBuf, PathBuf)>> {
+		let directory = directory.to_path_buf();
+
+		self.adapter
+			.list_stream_after(&directory, deep, after)
+			.map_ok(move |entry| {
+				let relative = relative_to(&entry, &directory).to_path_buf();
+
+				(directory.join(&relative), relative)
+			})
+			.boxed()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{adapters::MemoryAdapter, BatchMode};
+
+	#[tokio::test]
+	async fn test_resume_listing_from_checkpoint() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default());
+		filesystem
+			.write_many(
+				[
+					("logs/a.log", "a"),
+					("logs/b.log", "b"),
+					("logs/c.log", "c"),
+					("logs/d.log", "d"),
+					("logs/e.log", "e"),
+				],
+				BatchMode::FailFast,
+			)
+			.await
+			.into_result()
+			.unwrap();
+
+		let items: Vec<ListingItem> = filesystem
+			.list_checkpointed(Path::new("logs"), true, None, 2)
+			.take(3)
+			.try_collect()
+			.await
+			.unwrap();
+		assert_eq!(
+			items,
+			vec![
+				ListingItem::Entry(PathBuf::from("logs/a.log")),
+				ListingItem::Entry(PathBuf::from("logs/b.log")),
+				ListingItem::Checkpoint(ListingCheckpoint::new("b.log")),
+			]
+		);
+
+		// the checkpoint survives being saved as text
+		let ListingItem::Checkpoint(checkpoint) = &items[2] else {
+			unreachable!()
+		};
+		let checkpoint = ListingCheckpoint::new(checkpoint.to_string());
+		let entries: Vec<ListingItem> = filesystem
+			.list_checkpointed(Path::new("logs"), true, Some(&checkpoint), 2)
+			.try_filter(|item| std::future::ready(matches!(item, ListingItem::Entry(_))))
+			.try_collect()
+			.await
+			.unwrap();
+		assert_eq!(
+			entries,
+			["c.log", "d.log", "e.log"]
+				.map(|file| ListingItem::Entry(Path::new("logs").join(file)))
+		);
+	}
+}
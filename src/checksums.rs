@@ -0,0 +1,167 @@
+//! Storing several checksums of a file as its metadata when it's written, so they can be retrieved later without reading it
+//! (unlike `ETag`s, which aren't content hashes for multipart uploads or encrypted objects).
+
+use md5::{Digest, Md5};
+use std::{collections::BTreeMap, path::Path};
+
+use crate::{Filesystem, FlysystemError, Result, WriteOptions};
+
+/// The prefix of the metadata keys checksums are stored under, followed by the [name](ChecksumAlgorithm::name) of their algorithm.
+const METADATA_PREFIX: &str = "checksum-";
+
+/// A hash function whose checksum can be stored alongside a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChecksumAlgorithm {
+	Md5,
+	Sha256,
+	#[cfg(feature = "blake3")]
+	Blake3,
+}
+
+/// The checksums stored alongside a file, hex-encoded.
+pub type Checksums = BTreeMap<ChecksumAlgorithm, String>;
+
+impl ChecksumAlgorithm {
+	/// Every supported algorithm.
+	pub const ALL: &'static [Self] = &[
+		Self::Md5,
+		Self::Sha256,
+		#[cfg(feature = "blake3")]
+		Self::Blake3,
+	];
+
+	/// A short, stable name for the algorithm, used in the metadata key of its checksums (like `checksum-sha256`).
+	#[must_use]
+	pub const fn name(self) -> &'static str {
+		match self {
+			Self::Md5 => "md5",
+			Self::Sha256 => "sha256",
+			#[cfg(feature = "blake3")]
+			Self::Blake3 => "blake3",
+		}
+	}
+
+	/// Get the hex-encoded checksum of some contents.
+	#[must_use]
+	pub fn digest(self, contents: &[u8]) -> String {
+		match self {
+			Self::Md5 => format!("{:x}", Md5::digest(contents)),
+			Self::Sha256 => sha256::digest(contents),
+			#[cfg(feature = "blake3")]
+			Self::Blake3 => blake3::hash(contents).to_hex().to_string(),
+		}
+	}
+
+	fn from_metadata_key(key: &str) -> Option<Self> {
+		let name = key.strip_prefix(METADATA_PREFIX)?;
+
+		Self::ALL
+			.iter()
+			.copied()
+			.find(|algorithm| algorithm.name() == name)
+	}
+}
+
+impl Filesystem {
+	/// Write a file, storing its checksums with the given algorithms in its metadata (alongside the one in `options`).
+	///
+	/// Returns the stored checksums, which [`stored_checksums`](Self::stored_checksums) retrieves later.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to write the file.
+	pub async fn write_with_checksums(
+		&self,
+		path: &Path,
+		contents: &[u8],
+		mut options: WriteOptions,
+		algorithms: &[ChecksumAlgorithm],
+	) -> Result<Checksums> {
+		let checksums: Checksums = algorithms
+			.iter()
+			.map(|&algorithm| (algorithm, algorithm.digest(contents)))
+			.collect();
+
+		options
+			.metadata
+			.extend(checksums.iter().map(|(algorithm, checksum)| {
+				(
+					format!("{METADATA_PREFIX}{}", algorithm.name()),
+					checksum.clone(),
+				)
+			}));
+
+		self.write_with(path, contents, options).await?;
+		Ok(checksums)
+	}
+
+	/// Get the checksums stored alongside a file by [`write_with_checksums`](Self::write_with_checksums), without reading it.
+	///
+	/// Files written without checksums (or with algorithms this build doesn't support) have none.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter doesn't store metadata or fails to get it.
+	pub async fn stored_checksums(&self, path: &Path) -> Result<Checksums> {
+		let path = self.normalize(path)?;
+		let metadata = self
+			.adapter
+			.metadata(&path)
+			.await
+			.map_err(|error| FlysystemError::retrieving_metadata(&path, error))?;
+
+		Ok(metadata
+			.into_iter()
+			.filter_map(|(key, checksum)| {
+				Some((ChecksumAlgorithm::from_metadata_key(&key)?, checksum))
+			})
+			.collect())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::adapters::MemoryAdapter;
+
+	#[tokio::test]
+	async fn test_store_and_retrieve_checksums() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default());
+
+		let written = filesystem
+			.write_with_checksums(
+				Path::new("report.csv"),
+				b"id,total\n1,42\n",
+				WriteOptions::default(),
+				ChecksumAlgorithm::ALL,
+			)
+			.await
+			.unwrap();
+		assert_eq!(
+			written[&ChecksumAlgorithm::Md5],
+			"53df501a4b4c1400e4d2256cf7b907f7"
+		);
+		assert_eq!(
+			written[&ChecksumAlgorithm::Sha256],
+			sha256::digest(b"id,total\n1,42\n".as_slice())
+		);
+
+		assert_eq!(
+			filesystem
+				.stored_checksums(Path::new("report.csv"))
+				.await
+				.unwrap(),
+			written
+		);
+
+		filesystem
+			.write(Path::new("plain.txt"), b"no checksums")
+			.await
+			.unwrap();
+		assert!(filesystem
+			.stored_checksums(Path::new("plain.txt"))
+			.await
+			.unwrap()
+			.is_empty());
+	}
+}
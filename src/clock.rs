@@ -0,0 +1,64 @@
+//! Where adapters get the current time from, so time-dependent behavior (like timestamps or retention periods) can be tested deterministically.
+
+use std::{
+	fmt::Debug,
+	sync::{Arc, Mutex, PoisonError},
+	time::{Duration, SystemTime},
+};
+
+/// A source of the current time.
+pub trait Clock: Debug + Send + Sync {
+	/// Get the current time.
+	fn now(&self) -> SystemTime;
+}
+
+/// The system's clock, which adapters use by default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now(&self) -> SystemTime {
+		SystemTime::now()
+	}
+}
+
+/// A clock that only moves when told to, for tests.
+///
+/// Clones share the same time, so a clone can be kept around to move the clock after handing it to an adapter.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+	now: Arc<Mutex<SystemTime>>,
+}
+
+impl Default for MockClock {
+	/// A clock stopped at the Unix epoch.
+	fn default() -> Self {
+		Self::new(SystemTime::UNIX_EPOCH)
+	}
+}
+
+impl MockClock {
+	/// Create a clock stopped at `now`.
+	#[must_use]
+	pub fn new(now: SystemTime) -> Self {
+		Self {
+			now: Arc::new(Mutex::new(now)),
+		}
+	}
+
+	/// Move the clock forward.
+	pub fn advance(&self, duration: Duration) {
+		*self.now.lock().unwrap_or_else(PoisonError::into_inner) += duration;
+	}
+
+	/// Move the clock to the given time, which can be in the past.
+	pub fn set(&self, now: SystemTime) {
+		*self.now.lock().unwrap_or_else(PoisonError::into_inner) = now;
+	}
+}
+
+impl Clock for MockClock {
+	fn now(&self) -> SystemTime {
+		*self.now.lock().unwrap_or_else(PoisonError::into_inner)
+	}
+}
@@ -0,0 +1,162 @@
+use std::{fmt::Debug, io, path::Path};
+
+use crate::{normalize, Contents, Filesystem, FlysystemError, Result, WriteOptions};
+
+/// Transforms the contents of files on their way to the adapter and back, like a custom framing or encryption scheme.
+///
+/// Codecs can be used for a whole filesystem with [`Filesystem::with_codec`], or for single calls with
+/// [`Filesystem::write_with_codec`] and [`Filesystem::read_with_codec`]. The compression algorithms and the AES-GCM cipher
+/// of the `compression` and `encryption` features are codecs too.
+pub trait Codec: Debug + Send + Sync {
+	/// Encode the contents of a file before it's written.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the contents can't be encoded.
+	fn encode(&self, contents: &[u8]) -> io::Result<Vec<u8>>;
+
+	/// Decode the contents of a file once it's read, failing with an [`InvalidData`](io::ErrorKind::InvalidData) error if they're malformed.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the contents can't be decoded.
+	fn decode(&self, contents: &[u8]) -> io::Result<Vec<u8>>;
+}
+
+impl Filesystem {
+	/// Encode the contents of a file with the given codec, then write it.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the codec fails to encode the contents, or if writing the file fails (see [`Filesystem::write`]).
+	pub async fn write_with_codec<C: Codec + ?Sized>(
+		&self,
+		path: &Path,
+		contents: &[u8],
+		codec: &C,
+	) -> Result<()> {
+		let encoded = codec
+			.encode(contents)
+			.map_err(|error| FlysystemError::writing(&normalize(path), error))?;
+
+		self.write_with(path, &encoded, WriteOptions::default())
+			.await
+	}
+
+	/// Read a file, then decode its contents with the given codec.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to read the file, or if the codec fails to decode it.
+	pub async fn read_with_codec<R: TryFrom<Contents>, C: Codec + ?Sized>(
+		&self,
+		path: &Path,
+		codec: &C,
+	) -> Result<R> {
+		let path = self.normalize(path)?;
+		let contents: Contents = self.read(&path).await?;
+		let decoded = codec
+			.decode(&contents)
+			.map_err(|error| FlysystemError::reading(&path, error))?;
+
+		Contents::from(decoded)
+			.try_into()
+			.map_err(|_| FlysystemError::UnableToReadFile {
+				path,
+				source: io::Error::new(io::ErrorKind::InvalidData, "Could not decode contents."),
+			})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::adapters::MemoryAdapter;
+
+	/// A codec framing contents with their length, as an example of a custom envelope.
+	#[derive(Debug)]
+	struct LengthPrefixed;
+
+	impl Codec for LengthPrefixed {
+		fn encode(&self, contents: &[u8]) -> io::Result<Vec<u8>> {
+			let mut encoded = (contents.len() as u64).to_be_bytes().to_vec();
+			encoded.extend_from_slice(contents);
+
+			Ok(encoded)
+		}
+
+		fn decode(&self, contents: &[u8]) -> io::Result<Vec<u8>> {
+			let (len, contents) = contents
+				.split_first_chunk::<8>()
+				.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing length."))?;
+			if u64::from_be_bytes(*len) != contents.len() as u64 {
+				return Err(io::Error::new(io::ErrorKind::InvalidData, "Wrong length."));
+			}
+
+			Ok(contents.to_vec())
+		}
+	}
+
+	#[tokio::test]
+	async fn test_custom_codec() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default());
+
+		filesystem
+			.write_with_codec(Path::new("framed.bin"), b"Hello, world!", &LengthPrefixed)
+			.await
+			.unwrap();
+
+		let stored: Vec<u8> = filesystem.read(Path::new("framed.bin")).await.unwrap();
+		assert_eq!(stored[..8], 13u64.to_be_bytes());
+		let decoded: String = filesystem
+			.read_with_codec(Path::new("framed.bin"), &LengthPrefixed)
+			.await
+			.unwrap();
+		assert_eq!(decoded, "Hello, world!");
+
+		// the same codec, for the whole filesystem
+		let filesystem = filesystem.with_codec(LengthPrefixed);
+		filesystem
+			.write(Path::new("other.bin"), b"Bye!")
+			.await
+			.unwrap();
+		assert_eq!(
+			filesystem.file_size(Path::new("other.bin")).await.unwrap(),
+			4
+		);
+		assert_eq!(
+			filesystem
+				.read::<String>(Path::new("framed.bin"))
+				.await
+				.unwrap(),
+			"Hello, world!"
+		);
+	}
+
+	#[tokio::test]
+	async fn test_version_sizes() {
+		let filesystem =
+			Filesystem::from_adapter(MemoryAdapter::versioned()).with_codec(LengthPrefixed);
+
+		filesystem
+			.write(Path::new("framed.bin"), b"Hello")
+			.await
+			.unwrap();
+		filesystem
+			.write(Path::new("framed.bin"), b"Hello, world!")
+			.await
+			.unwrap();
+
+		let versions = filesystem
+			.list_versions(Path::new("framed.bin"))
+			.await
+			.unwrap();
+		assert_eq!(
+			versions
+				.iter()
+				.map(|version| version.size)
+				.collect::<Vec<_>>(),
+			vec![13, 5]
+		);
+	}
+}
@@ -0,0 +1,111 @@
+//! Choosing the adapter of a filesystem at runtime, from a config file (like the disks of a Laravel app).
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "archive")]
+use crate::adapters::archive::{self, ArchiveAdapter, HttpSource};
+#[cfg(feature = "http-adapter")]
+use crate::adapters::http::{self, HttpAdapter};
+#[cfg(feature = "local")]
+use crate::adapters::local::{self, LocalAdapter};
+#[cfg(feature = "s3")]
+use crate::adapters::s3::{self, S3Adapter};
+#[cfg(feature = "webdav")]
+use crate::adapters::webdav::{self, WebDavAdapter};
+use crate::{adapters::MemoryAdapter, Filesystem, Result};
+
+/// The adapter of a filesystem along with its config, tagged with its `driver`, so it can be deserialized from a config file.
+///
+/// ```toml
+/// [disk]
+/// driver = "s3"
+/// bucket = "my-bucket"
+/// region = "eu-west-1"
+/// ```
+///
+/// Only the drivers whose features are enabled are available.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "driver", rename_all = "lowercase")]
+#[allow(clippy::module_name_repetitions, clippy::large_enum_variant)]
+pub enum AdapterConfig {
+	#[cfg(feature = "local")]
+	Local(local::Config),
+	#[cfg(feature = "s3")]
+	S3(s3::Config),
+	#[cfg(feature = "webdav")]
+	WebDav(webdav::Config),
+	#[cfg(feature = "archive")]
+	Archive(archive::Config),
+	#[cfg(feature = "http-adapter")]
+	Http(http::Config),
+	Memory,
+}
+
+impl Filesystem {
+	/// Create a new filesystem instance, initializing the adapter picked by the config.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to initialize.
+	pub async fn from_config(config: AdapterConfig) -> Result<Self> {
+		match config {
+			#[cfg(feature = "local")]
+			AdapterConfig::Local(config) => Ok(Self::new::<LocalAdapter>(config).await?),
+			#[cfg(feature = "s3")]
+			AdapterConfig::S3(config) => Ok(Self::new::<S3Adapter>(config).await?),
+			#[cfg(feature = "webdav")]
+			AdapterConfig::WebDav(config) => Ok(Self::new::<WebDavAdapter>(config).await?),
+			#[cfg(feature = "archive")]
+			AdapterConfig::Archive(config) => Ok(Self::new::<ArchiveAdapter<HttpSource>>(config).await?),
+			#[cfg(feature = "http-adapter")]
+			AdapterConfig::Http(config) => Ok(Self::new::<HttpAdapter>(config).await?),
+			AdapterConfig::Memory => Ok(Self::from_adapter(MemoryAdapter::default())),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::path::Path;
+
+	use super::*;
+
+	#[tokio::test]
+	async fn test_from_config() {
+		let config: AdapterConfig = serde_json::from_str(r#"{ "driver": "memory" }"#).unwrap();
+		let filesystem = Filesystem::from_config(config).await.unwrap();
+		filesystem
+			.write(Path::new("hello.txt"), b"Hello, world!")
+			.await
+			.unwrap();
+		assert!(filesystem
+			.file_exists(Path::new("hello.txt"))
+			.await
+			.unwrap());
+
+		assert!(serde_json::from_str::<AdapterConfig>(r#"{ "driver": "floppy" }"#).is_err());
+	}
+
+	#[cfg(feature = "local")]
+	#[tokio::test]
+	async fn test_local_from_config() {
+		let location = std::env::temp_dir().join("flysystem-test-local-from-config");
+
+		let config: AdapterConfig = serde_json::from_value(serde_json::json!({
+			"driver": "local",
+			"location": location,
+			"lazy_root_creation": true,
+			"validation": "eager",
+		}))
+		.unwrap();
+		let AdapterConfig::Local(local) = &config else {
+			panic!("expected a local config, got {config:?}");
+		};
+		assert_eq!(local.public_url, None);
+
+		Filesystem::from_config(config).await.unwrap();
+		assert!(location.is_dir());
+
+		std::fs::remove_dir_all(location).unwrap();
+	}
+}
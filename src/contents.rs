@@ -1,5 +1,7 @@
 use std::ops::Deref;
 
+/// The contents of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Contents {
 	pub data: Vec<u8>,
 }
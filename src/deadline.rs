@@ -0,0 +1,256 @@
+//! Bounding storage work by the time left to handle a request.
+//!
+//! Operations run through [`Filesystem::with_deadline`] fail with a `TimedOut` error once the deadline passes,
+//! and anything they do along the way (adapter calls, event listeners, webhook retries) can check [`remaining`]
+//! to avoid starting work that can't finish in time.
+
+use futures::io::AsyncRead;
+use std::{
+	future::Future,
+	io::{self, Error, ErrorKind},
+	path::{Path, PathBuf},
+	time::{Duration, Instant},
+};
+
+use crate::{contents::Contents, Filesystem, Result};
+
+tokio::task_local! {
+	static DEADLINE: Instant;
+}
+
+/// Get the time left until the deadline of the operation being run, if it has one.
+#[must_use]
+pub fn remaining() -> Option<Duration> {
+	DEADLINE
+		.try_with(|deadline| deadline.saturating_duration_since(Instant::now()))
+		.ok()
+}
+
+/// Fail if the deadline of the operation being run has already passed.
+///
+/// # Errors
+///
+/// This function will return a `TimedOut` error if there's no time left.
+pub fn check() -> io::Result<()> {
+	if remaining() == Some(Duration::ZERO) {
+		return Err(timed_out());
+	}
+
+	Ok(())
+}
+
+fn timed_out() -> Error {
+	Error::new(
+		ErrorKind::TimedOut,
+		"The operation did not finish before its deadline.",
+	)
+}
+
+/// A [`Filesystem`] whose operations must finish before a deadline.
+#[derive(Debug)]
+pub struct Deadlined<'a> {
+	filesystem: &'a Filesystem,
+	deadline: Instant,
+}
+
+impl Filesystem {
+	/// Run the following operations with a deadline, like the time left to answer the request that triggered them.
+	///
+	/// The deadline covers the whole operation, including CDN purges and event listeners, and is visible to them through [`remaining`].
+	#[must_use]
+	pub const fn with_deadline(&self, deadline: Instant) -> Deadlined<'_> {
+		Deadlined {
+			filesystem: self,
+			deadline,
+		}
+	}
+
+	/// Run the following operations with a time budget, starting now.
+	#[must_use]
+	pub fn with_budget(&self, budget: Duration) -> Deadlined<'_> {
+		self.with_deadline(Instant::now() + budget)
+	}
+}
+
+impl Deadlined<'_> {
+	/// The point in time the operations must finish by.
+	#[must_use]
+	pub const fn deadline(&self) -> Instant {
+		self.deadline
+	}
+
+	async fn run<T>(deadline: Instant, operation: impl Future<Output = Result<T>>) -> Result<T> {
+		if Instant::now() >= deadline {
+			return Err(timed_out().into());
+		}
+
+		DEADLINE
+			.scope(
+				deadline,
+				tokio::time::timeout_at(deadline.into(), operation),
+			)
+			.await
+			.unwrap_or_else(|_| Err(timed_out().into()))
+	}
+
+	/// Check if a file exists.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to check, or if the deadline passes first.
+	pub async fn file_exists(&self, path: &Path) -> Result<bool> {
+		Self::run(self.deadline, self.filesystem.file_exists(path)).await
+	}
+
+	/// Get the contents of a file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the file can't be read or converted to the requested type, or if the deadline passes first.
+	pub async fn read<R: TryFrom<Contents>>(&self, path: &Path) -> Result<R> {
+		Self::run(self.deadline, self.filesystem.read(path)).await
+	}
+
+	/// Read `len` bytes of a file starting at `offset`.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the file can't be read, or if the deadline passes first.
+	pub async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		Self::run(self.deadline, self.filesystem.read_range(path, offset, len)).await
+	}
+
+	/// Write a file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if [`Filesystem::write`] fails, or if the deadline passes first (in which case the file may have been written).
+	pub async fn write(&self, path: &Path, contents: &[u8]) -> Result<()> {
+		Self::run(self.deadline, self.filesystem.write(path, contents)).await
+	}
+
+	/// Add data to the end of a file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if [`Filesystem::append`] fails, or if the deadline passes first (in which case the data may have been appended).
+	pub async fn append(&self, path: &Path, data: &[u8]) -> Result<()> {
+		Self::run(self.deadline, self.filesystem.append(path, data)).await
+	}
+
+	/// Write a file from a reader.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if [`Filesystem::write_stream`] fails, or if the deadline passes first.
+	pub async fn write_stream(
+		&self,
+		path: &Path,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		Self::run(self.deadline, self.filesystem.write_stream(path, reader)).await
+	}
+
+	/// Delete a file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if [`Filesystem::delete`] fails, or if the deadline passes first.
+	pub async fn delete(&self, path: &Path) -> Result<()> {
+		Self::run(self.deadline, self.filesystem.delete(path)).await
+	}
+
+	/// List the contents of a directory.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the directory can't be listed, or if the deadline passes first.
+	pub async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		Self::run(self.deadline, self.filesystem.list_contents(path, deep)).await
+	}
+
+	/// Move a file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if [`Filesystem::move`] fails, or if the deadline passes first.
+	pub async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		Self::run(self.deadline, self.filesystem.r#move(source, destination)).await
+	}
+
+	/// Copy a file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if [`Filesystem::copy`] fails, or if the deadline passes first.
+	pub async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		Self::run(self.deadline, self.filesystem.copy(source, destination)).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::{Arc, Mutex};
+
+	use super::*;
+	use crate::{adapters::MemoryAdapter, EventListener, FilesystemEvent};
+
+	#[derive(Debug)]
+	struct SlowListener(Arc<Mutex<Option<Duration>>>);
+
+	#[async_trait::async_trait]
+	impl EventListener for SlowListener {
+		async fn handle(&self, _: &FilesystemEvent) {
+			*self.0.lock().unwrap() = remaining();
+			tokio::time::sleep(Duration::from_secs(5)).await;
+		}
+	}
+
+	#[tokio::test]
+	async fn test_deadline_bounds_operations() {
+		let seen = Arc::new(Mutex::new(None));
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default())
+			.with_listener(SlowListener(seen.clone()));
+
+		let error = filesystem
+			.with_budget(Duration::from_millis(50))
+			.write(Path::new("report.txt"), b"Hello, world!")
+			.await
+			.unwrap_err();
+
+		assert_eq!(error.kind(), ErrorKind::TimedOut);
+		assert!(seen
+			.lock()
+			.unwrap()
+			.is_some_and(|remaining| remaining <= Duration::from_millis(50)));
+		assert_eq!(remaining(), None);
+	}
+
+	#[tokio::test]
+	async fn test_expired_deadline() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default());
+		filesystem
+			.write(Path::new("report.txt"), b"Hello, world!")
+			.await
+			.unwrap();
+
+		let bounded = filesystem.with_deadline(Instant::now());
+		assert_eq!(
+			bounded
+				.read::<String>(Path::new("report.txt"))
+				.await
+				.unwrap_err()
+				.kind(),
+			ErrorKind::TimedOut
+		);
+
+		assert_eq!(
+			filesystem
+				.with_budget(Duration::from_secs(5))
+				.read::<String>(Path::new("report.txt"))
+				.await
+				.unwrap(),
+			"Hello, world!"
+		);
+	}
+}
@@ -0,0 +1,101 @@
+use std::fmt;
+
+use crate::{adapters::AdapterLayer, Filesystem};
+
+/// How a filesystem is set up, as returned by [`Filesystem::describe`], like for showing on an ops or debug page.
+///
+/// Its [`Display`](fmt::Display) implementation prints the adapter stack on one line, like
+/// `read_only > path_prefixed(prefix=tenants/acme) > s3(bucket=uploads, region=eu-west-1, part_size=8388608)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct FilesystemInfo {
+	/// The adapters the filesystem goes through, outermost first.
+	pub layers: Vec<AdapterLayer>,
+	/// Whether files are scanned for viruses before being written.
+	pub scanner: bool,
+	/// Whether changed files are purged from a CDN.
+	pub cdn_purger: bool,
+	/// Whether public URLs come from a [`UrlStrategy`](crate::UrlStrategy) rather than the adapter.
+	pub url_strategy: bool,
+	/// How many listeners are notified of changes.
+	pub listeners: usize,
+	/// Whether listings are sorted by path.
+	pub sorted_listings: bool,
+}
+
+impl fmt::Display for FilesystemInfo {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for (i, layer) in self.layers.iter().enumerate() {
+			if i > 0 {
+				f.write_str(" > ")?;
+			}
+			write!(f, "{layer}")?;
+		}
+
+		let mut extras = Vec::new();
+		if self.scanner {
+			extras.push("scanner".to_string());
+		}
+		if self.cdn_purger {
+			extras.push("cdn purger".to_string());
+		}
+		if self.url_strategy {
+			extras.push("url strategy".to_string());
+		}
+		if self.listeners > 0 {
+			extras.push(format!("{} listeners", self.listeners));
+		}
+		if !self.sorted_listings {
+			extras.push("unsorted listings".to_string());
+		}
+
+		if !extras.is_empty() {
+			write!(f, " [{}]", extras.join(", "))?;
+		}
+
+		Ok(())
+	}
+}
+
+impl Filesystem {
+	/// Describe how this filesystem is set up: the stack of adapters it wraps (which is otherwise opaque at runtime) and its other options.
+	#[must_use]
+	pub fn describe(&self) -> FilesystemInfo {
+		FilesystemInfo {
+			layers: self.adapter.layers(),
+			scanner: self.scanner.is_some(),
+			cdn_purger: self.purger.is_some(),
+			url_strategy: self.url_strategy.is_some(),
+			listeners: self.listeners.len(),
+			sorted_listings: self.sorted_listings,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::adapters::{CachedAdapter, MemoryAdapter};
+
+	#[test]
+	fn test_describe() {
+		let filesystem =
+			Filesystem::from_adapter(CachedAdapter::new(MemoryAdapter::default(), 1024))
+				.scoped("tenants/acme")
+				.read_only()
+				.with_unsorted_listings();
+
+		let info = filesystem.describe();
+		assert_eq!(
+			info.layers
+				.iter()
+				.map(|layer| layer.name)
+				.collect::<Vec<_>>(),
+			["read_only", "path_prefixed", "cached", "memory"]
+		);
+		assert_eq!(
+			info.to_string(),
+			"read_only > path_prefixed(prefix=tenants/acme) > cached(capacity=1024) > memory [unsorted listings]"
+		);
+	}
+}
@@ -0,0 +1,433 @@
+//! Download several files at once, as a zip archive streamed straight into an HTTP response.
+
+use ::http::{header, Response, StatusCode};
+use bytes::Bytes;
+use futures::{
+	io::AsyncRead,
+	stream::{self, BoxStream},
+	AsyncReadExt, StreamExt,
+};
+use std::{
+	io::{Error, ErrorKind, Result},
+	path::{Path, PathBuf},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{adapters::content_disposition, Filesystem};
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x0807_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+
+const LOCAL_HEADER_LEN: u64 = 30;
+const DATA_DESCRIPTOR_LEN: u64 = 16;
+const CENTRAL_HEADER_LEN: u64 = 46;
+const END_OF_CENTRAL_DIRECTORY_LEN: u64 = 22;
+
+/// Zip 2.0, the oldest version supporting the features used here.
+const VERSION: u16 = 20;
+/// The checksum and sizes follow the contents (bit 3), and names are UTF-8 (bit 11).
+const FLAGS: u16 = 0x0808;
+/// How many bytes of a file are read at once.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The CRC-32 lookup table, for checksumming the archived files.
+const CRC_TABLE: [u32; 256] = crc_table();
+
+impl Filesystem {
+	/// Build an HTTP response streaming a zip archive of the given files, named `file_name` when downloaded.
+	///
+	/// Files are stored uncompressed and read chunk by chunk as the body is polled, so the archive is never held in memory
+	/// (or on disk), and its size is known up front and sent as `Content-Length`. Archives are limited to 65535 files and 4 GiB.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if any of the files doesn't exist, if the adapter fails to get their metadata,
+	/// or if the archive would be too large. Failing to read a file once the response is sent ends the body with an error.
+	pub async fn zip_response<P: AsRef<Path>>(
+		&self,
+		paths: impl IntoIterator<Item = P>,
+		file_name: &str,
+	) -> crate::Result<Response<BoxStream<'static, Result<Bytes>>>> {
+		let mut entries = Vec::new();
+		let mut offset = 0;
+
+		for path in paths {
+			let path = self.normalize(path.as_ref())?;
+			let (size, last_modified) =
+				futures::future::join(self.file_size(&path), self.last_modified(&path)).await;
+			let name = path
+				.to_str()
+				.ok_or_else(|| {
+					Error::new(
+						ErrorKind::InvalidData,
+						format!("{} is not valid UTF-8.", path.display()),
+					)
+				})?
+				.replace('\\', "/");
+			let (time, date) = dos_date_time(last_modified?);
+
+			let entry = ZipEntry {
+				size: too_large(size?)?,
+				offset: too_large(offset)?,
+				crc: 0,
+				time,
+				date,
+				name,
+				path,
+			};
+			offset += LOCAL_HEADER_LEN
+				+ entry.name.len() as u64
+				+ u64::from(entry.size)
+				+ DATA_DESCRIPTOR_LEN;
+			entries.push(entry);
+		}
+
+		if u16::try_from(entries.len()).is_err() {
+			return Err(Error::new(
+				ErrorKind::FileTooLarge,
+				"Zip archives can't hold more than 65535 files.",
+			)
+			.into());
+		}
+		let central_directory_len: u64 = entries
+			.iter()
+			.map(|entry| CENTRAL_HEADER_LEN + entry.name.len() as u64)
+			.sum();
+		too_large(offset + central_directory_len)?;
+
+		let archive = ZipStream {
+			filesystem: self.clone(),
+			entries,
+			current: 0,
+			reader: None,
+			crc: 0,
+			read: 0,
+			finished: false,
+		};
+		let body = stream::try_unfold(archive, |mut archive| async move {
+			Ok(archive.next_chunk().await?.map(|chunk| (chunk, archive)))
+		})
+		.boxed();
+
+		Response::builder()
+			.status(StatusCode::OK)
+			.header(header::CONTENT_TYPE, "application/zip")
+			.header(
+				header::CONTENT_LENGTH,
+				offset + central_directory_len + END_OF_CENTRAL_DIRECTORY_LEN,
+			)
+			.header(header::CONTENT_DISPOSITION, content_disposition(file_name))
+			.body(body)
+			.map_err(|error| Error::other(error).into())
+	}
+}
+
+/// A file of the archive, along with where it starts in the archive.
+#[derive(Debug)]
+struct ZipEntry {
+	path: PathBuf,
+	name: String,
+	size: u32,
+	offset: u32,
+	crc: u32,
+	time: u16,
+	date: u16,
+}
+
+impl ZipEntry {
+	fn local_header(&self) -> Bytes {
+		let mut header = Vec::new();
+		header.extend(LOCAL_HEADER_SIGNATURE.to_le_bytes());
+		header.extend(VERSION.to_le_bytes());
+		header.extend(FLAGS.to_le_bytes());
+		// stored, without compression
+		header.extend(0u16.to_le_bytes());
+		header.extend(self.time.to_le_bytes());
+		header.extend(self.date.to_le_bytes());
+		// the checksum and sizes are in the data descriptor
+		header.extend([0; 12]);
+		header.extend(name_len(&self.name).to_le_bytes());
+		header.extend(0u16.to_le_bytes());
+		header.extend(self.name.as_bytes());
+
+		header.into()
+	}
+
+	fn data_descriptor(&self) -> Bytes {
+		let mut descriptor = Vec::new();
+		descriptor.extend(DATA_DESCRIPTOR_SIGNATURE.to_le_bytes());
+		descriptor.extend(self.crc.to_le_bytes());
+		descriptor.extend(self.size.to_le_bytes());
+		descriptor.extend(self.size.to_le_bytes());
+
+		descriptor.into()
+	}
+
+	fn central_header(&self, directory: &mut Vec<u8>) {
+		directory.extend(CENTRAL_HEADER_SIGNATURE.to_le_bytes());
+		directory.extend(VERSION.to_le_bytes());
+		directory.extend(VERSION.to_le_bytes());
+		directory.extend(FLAGS.to_le_bytes());
+		directory.extend(0u16.to_le_bytes());
+		directory.extend(self.time.to_le_bytes());
+		directory.extend(self.date.to_le_bytes());
+		directory.extend(self.crc.to_le_bytes());
+		directory.extend(self.size.to_le_bytes());
+		directory.extend(self.size.to_le_bytes());
+		directory.extend(name_len(&self.name).to_le_bytes());
+		// no extra field, comment, disk number or attributes
+		directory.extend([0; 12]);
+		directory.extend(self.offset.to_le_bytes());
+		directory.extend(self.name.as_bytes());
+	}
+}
+
+/// The body of a zip response, producing the archive one chunk at a time.
+struct ZipStream {
+	filesystem: Filesystem,
+	entries: Vec<ZipEntry>,
+	/// The entry being written.
+	current: usize,
+	/// The contents of the current entry, once its local header was written.
+	reader: Option<Box<dyn AsyncRead + Send + Unpin>>,
+	crc: u32,
+	read: u64,
+	finished: bool,
+}
+
+impl ZipStream {
+	async fn next_chunk(&mut self) -> Result<Option<Bytes>> {
+		if let Some(reader) = &mut self.reader {
+			let mut chunk = vec![0; CHUNK_SIZE];
+			let len = reader.read(&mut chunk).await?;
+
+			if len > 0 {
+				chunk.truncate(len);
+				self.crc = crc32(self.crc, &chunk);
+				self.read += len as u64;
+				return Ok(Some(chunk.into()));
+			}
+
+			self.reader = None;
+			let entry = &mut self.entries[self.current];
+			// the sizes were already announced, so a file changing mid-download would corrupt the archive
+			if self.read != u64::from(entry.size) {
+				return Err(Error::new(
+					ErrorKind::InvalidData,
+					format!("{} changed while being archived.", entry.path.display()),
+				));
+			}
+			entry.crc = self.crc;
+			self.current += 1;
+
+			return Ok(Some(entry.data_descriptor()));
+		}
+
+		if let Some(entry) = self.entries.get(self.current) {
+			self.reader = Some(Box::new(self.filesystem.read_stream(&entry.path).await?));
+			self.crc = 0;
+			self.read = 0;
+
+			return Ok(Some(entry.local_header()));
+		}
+
+		if self.finished {
+			return Ok(None);
+		}
+		self.finished = true;
+
+		Ok(Some(self.central_directory()))
+	}
+
+	// the sizes and number of files were checked before starting the response
+	#[allow(clippy::cast_possible_truncation)]
+	fn central_directory(&self) -> Bytes {
+		let mut directory = Vec::new();
+		for entry in &self.entries {
+			entry.central_header(&mut directory);
+		}
+		let start = self.entries.last().map_or(0, |entry| {
+			entry.offset
+				+ LOCAL_HEADER_LEN as u32
+				+ u32::from(name_len(&entry.name))
+				+ entry.size + DATA_DESCRIPTOR_LEN as u32
+		});
+		let len = directory.len() as u32;
+		let count = self.entries.len() as u16;
+
+		directory.extend(END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+		// a single disk
+		directory.extend([0; 4]);
+		directory.extend(count.to_le_bytes());
+		directory.extend(count.to_le_bytes());
+		directory.extend(len.to_le_bytes());
+		directory.extend(start.to_le_bytes());
+		// no comment
+		directory.extend(0u16.to_le_bytes());
+
+		directory.into()
+	}
+}
+
+/// Check that a size or offset fits in a (non-zip64) archive.
+fn too_large(value: u64) -> Result<u32> {
+	u32::try_from(value).map_err(|_| {
+		Error::new(
+			ErrorKind::FileTooLarge,
+			"Zip archives can't be larger than 4 GiB.",
+		)
+	})
+}
+
+#[allow(clippy::cast_possible_truncation)]
+const fn name_len(name: &str) -> u16 {
+	name.len() as u16
+}
+
+/// Convert a time to the MS-DOS time and date zip archives use, clamped to the years they can represent (1980 to 2107).
+#[allow(
+	clippy::cast_possible_truncation,
+	clippy::cast_possible_wrap,
+	clippy::cast_sign_loss
+)]
+fn dos_date_time(time: SystemTime) -> (u16, u16) {
+	let secs = time
+		.duration_since(UNIX_EPOCH)
+		.map_or(0, |duration| duration.as_secs());
+	let (days, secs) = ((secs / 86_400) as i64, secs % 86_400);
+
+	// https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+	let z = days + 719_468;
+	let era = z.div_euclid(146_097);
+	let doe = z.rem_euclid(146_097);
+	let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = doy - (153 * mp + 2) / 5 + 1;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 };
+	let year = yoe + era * 400 + i64::from(month <= 2);
+
+	match year {
+		// midnight on January 1st, 1980
+		..=1979 => (0, 0x0021),
+		// 23:59:58 on December 31st, 2107
+		2108.. => (0xBF7D, 0xFF9F),
+		_ => (
+			(((secs / 3600) << 11) | ((secs % 3600 / 60) << 5) | ((secs % 60) / 2)) as u16,
+			(((year - 1980) << 9) | (month << 5) | day) as u16,
+		),
+	}
+}
+
+const fn crc_table() -> [u32; 256] {
+	let mut table = [0; 256];
+	let mut i = 0;
+
+	while i < 256 {
+		let mut crc = i;
+		let mut bit = 0;
+		while bit < 8 {
+			crc = if crc & 1 == 0 {
+				crc >> 1
+			} else {
+				0xEDB8_8320 ^ (crc >> 1)
+			};
+			bit += 1;
+		}
+		table[i as usize] = crc;
+		i += 1;
+	}
+
+	table
+}
+
+fn crc32(crc: u32, data: &[u8]) -> u32 {
+	let crc = data.iter().fold(!crc, |crc, byte| {
+		CRC_TABLE[usize::from(crc.to_le_bytes()[0] ^ byte)] ^ (crc >> 8)
+	});
+
+	!crc
+}
+
+#[cfg(test)]
+mod tests {
+	use futures::TryStreamExt;
+	use std::io::{Cursor, Read};
+
+	use super::*;
+	use crate::adapters::MemoryAdapter;
+
+	#[tokio::test]
+	async fn test_zip_response() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default());
+		filesystem
+			.write(Path::new("invoices/january.pdf"), b"%PDF january")
+			.await
+			.unwrap();
+		filesystem
+			.write(Path::new("invoices/february.pdf"), &vec![7; 200_000])
+			.await
+			.unwrap();
+
+		let response = filesystem
+			.zip_response(
+				["invoices/january.pdf", "invoices/february.pdf"],
+				"invoices (2024).zip",
+			)
+			.await
+			.unwrap();
+
+		assert_eq!(response.headers()[header::CONTENT_TYPE], "application/zip");
+		assert_eq!(
+			response.headers()[header::CONTENT_DISPOSITION],
+			"attachment; filename=\"invoices (2024).zip\"; filename*=UTF-8''invoices%20%282024%29%2Ezip"
+		);
+		let content_length: usize = response.headers()[header::CONTENT_LENGTH]
+			.to_str()
+			.unwrap()
+			.parse()
+			.unwrap();
+
+		let body: Vec<Bytes> = response.into_body().try_collect().await.unwrap();
+		let body = body.concat();
+		assert_eq!(body.len(), content_length);
+
+		let mut archive = zip::ZipArchive::new(Cursor::new(body)).unwrap();
+		assert_eq!(archive.len(), 2);
+
+		let mut contents = String::new();
+		archive
+			.by_name("invoices/january.pdf")
+			.unwrap()
+			.read_to_string(&mut contents)
+			.unwrap();
+		assert_eq!(contents, "%PDF january");
+
+		let mut contents = Vec::new();
+		archive
+			.by_name("invoices/february.pdf")
+			.unwrap()
+			.read_to_end(&mut contents)
+			.unwrap();
+		assert_eq!(contents, vec![7; 200_000]);
+
+		// missing files fail before anything is sent
+		assert_eq!(
+			filesystem
+				.zip_response(["invoices/march.pdf"], "invoices.zip")
+				.await
+				.map(|_| ())
+				.unwrap_err()
+				.kind(),
+			ErrorKind::NotFound
+		);
+	}
+
+	#[test]
+	fn test_crc32() {
+		assert_eq!(crc32(0, b"123456789"), 0xCBF4_3926);
+		assert_eq!(crc32(crc32(0, b"1234"), b"56789"), 0xCBF4_3926);
+	}
+}
@@ -0,0 +1,338 @@
+use std::{
+	fmt,
+	io::{self, ErrorKind},
+	path::{Path, PathBuf},
+};
+
+use crate::redaction::Redacted;
+
+/// A [`Result`](std::result::Result) failing with a [`FlysystemError`].
+pub type Result<T, E = FlysystemError> = std::result::Result<T, E>;
+
+/// Why a [`Filesystem`](crate::Filesystem) operation failed, so callers can match on the cause (and retry selectively).
+///
+/// Adapters report failures as [`io::Error`]s, which are classified by the operation that failed. Adapters can also wrap one of these
+/// in an [`io::Error`] (like [`FlysystemError::PathTraversal`]) to report it as-is. [`FlysystemError::kind`] returns the
+/// [`ErrorKind`] of the underlying failure, and converting back into an [`io::Error`] keeps it.
+#[derive(thiserror::Error)]
+#[non_exhaustive]
+pub enum FlysystemError {
+	/// The file doesn't exist.
+	#[error("The file {} does not exist.", path.display())]
+	FileNotFound { path: PathBuf },
+	/// The directory doesn't exist.
+	#[error("The directory {} does not exist.", path.display())]
+	DirectoryNotFound { path: PathBuf },
+	/// The path escapes the directory the filesystem is confined to.
+	#[error("{} is outside of the filesystem.", path.display())]
+	PathTraversal { path: PathBuf },
+	/// The path contains control characters, which are likely the result of corrupted or malicious input.
+	#[error("{path:?} contains control characters.")]
+	CorruptedPath { path: PathBuf },
+	/// The file is private, so its public URL would be refused by the storage.
+	#[error("{} is private, so it has no public URL.", path.display())]
+	FileNotPublic { path: PathBuf },
+	/// The file or directory is protected from deletion, see [`Filesystem::protect`](crate::Filesystem::protect).
+	#[error("{} is protected from deletion.", path.display())]
+	Protected { path: PathBuf },
+	/// The precondition of a [conditional write](crate::Filesystem::write_if) doesn't hold, since the file was created or changed in the meantime.
+	#[error("The precondition of the write to {} doesn't hold.", path.display())]
+	PreconditionFailed { path: PathBuf },
+	/// The adapter can't store the visibility of files.
+	#[error("The adapter can't set the visibility of {}.", path.display())]
+	VisibilityNotSupported { path: PathBuf },
+	/// The adapter can't generate public URLs, and no [`UrlStrategy`](crate::UrlStrategy) is configured.
+	#[error("The adapter can't generate a public URL for {}.", path.display())]
+	PublicUrlNotSupported { path: PathBuf },
+	/// The adapter can't generate temporary URLs.
+	#[error("The adapter can't generate a temporary URL for {}.", path.display())]
+	TemporaryUrlNotSupported { path: PathBuf },
+	#[error("Unable to read {}: {}", path.display(), Redacted(source))]
+	UnableToReadFile { path: PathBuf, source: io::Error },
+	#[error("Unable to write {}: {}", path.display(), Redacted(source))]
+	UnableToWriteFile { path: PathBuf, source: io::Error },
+	#[error("Unable to delete {}: {}", path.display(), Redacted(source))]
+	UnableToDeleteFile { path: PathBuf, source: io::Error },
+	#[error("Unable to move {} to {}: {}", from.display(), to.display(), Redacted(source))]
+	UnableToMoveFile {
+		from: PathBuf,
+		to: PathBuf,
+		source: io::Error,
+	},
+	#[error("Unable to copy {} to {}: {}", from.display(), to.display(), Redacted(source))]
+	UnableToCopyFile {
+		from: PathBuf,
+		to: PathBuf,
+		source: io::Error,
+	},
+	#[error("Unable to create the directory {}: {}", path.display(), Redacted(source))]
+	UnableToCreateDirectory { path: PathBuf, source: io::Error },
+	#[error("Unable to delete the directory {}: {}", path.display(), Redacted(source))]
+	UnableToDeleteDirectory { path: PathBuf, source: io::Error },
+	#[error("Unable to list the contents of {}: {}", path.display(), Redacted(source))]
+	UnableToListContents { path: PathBuf, source: io::Error },
+	/// Getting the size, last modified time, MIME type, visibility or checksum of a file failed.
+	#[error("Unable to get the metadata of {}: {}", path.display(), Redacted(source))]
+	UnableToRetrieveMetadata { path: PathBuf, source: io::Error },
+	#[error("Unable to set the visibility of {}: {}", path.display(), Redacted(source))]
+	UnableToSetVisibility { path: PathBuf, source: io::Error },
+	/// Any other failure, like the storage being unreachable or the filesystem missing a scanner or URL strategy.
+	#[error("{}", Redacted(.0))]
+	AdapterError(io::Error),
+}
+
+impl FlysystemError {
+	/// Get the kind of the underlying failure, like [`ErrorKind::NotFound`] or [`ErrorKind::StorageFull`].
+	#[must_use]
+	pub fn kind(&self) -> ErrorKind {
+		match self {
+			Self::FileNotFound { .. } | Self::DirectoryNotFound { .. } => ErrorKind::NotFound,
+			Self::PathTraversal { .. } | Self::FileNotPublic { .. } | Self::Protected { .. } => {
+				ErrorKind::PermissionDenied
+			},
+			Self::CorruptedPath { .. } => ErrorKind::InvalidInput,
+			Self::PreconditionFailed { .. } => ErrorKind::AlreadyExists,
+			Self::VisibilityNotSupported { .. }
+			| Self::PublicUrlNotSupported { .. }
+			| Self::TemporaryUrlNotSupported { .. } => ErrorKind::Unsupported,
+			Self::UnableToReadFile { source, .. }
+			| Self::UnableToWriteFile { source, .. }
+			| Self::UnableToDeleteFile { source, .. }
+			| Self::UnableToMoveFile { source, .. }
+			| Self::UnableToCopyFile { source, .. }
+			| Self::UnableToCreateDirectory { source, .. }
+			| Self::UnableToDeleteDirectory { source, .. }
+			| Self::UnableToListContents { source, .. }
+			| Self::UnableToRetrieveMetadata { source, .. }
+			| Self::UnableToSetVisibility { source, .. }
+			| Self::AdapterError(source) => source.kind(),
+		}
+	}
+
+	/// Classify a failure to read a file.
+	pub(crate) fn reading(path: &Path, error: io::Error) -> Self {
+		classify(
+			error,
+			path,
+			|path| Self::FileNotFound { path },
+			|path, source| Self::UnableToReadFile { path, source },
+		)
+	}
+
+	/// Classify a failure to write a file.
+	pub(crate) fn writing(path: &Path, error: io::Error) -> Self {
+		unwrap(error).unwrap_or_else(|source| Self::UnableToWriteFile {
+			path: path.to_path_buf(),
+			source,
+		})
+	}
+
+	/// Classify a failure to delete a file.
+	pub(crate) fn deleting(path: &Path, error: io::Error) -> Self {
+		classify(
+			error,
+			path,
+			|path| Self::FileNotFound { path },
+			|path, source| Self::UnableToDeleteFile { path, source },
+		)
+	}
+
+	/// Classify a failure to move a file.
+	pub(crate) fn moving(from: &Path, to: &Path, error: io::Error) -> Self {
+		classify(
+			error,
+			from,
+			|path| Self::FileNotFound { path },
+			|from, source| Self::UnableToMoveFile {
+				from,
+				to: to.to_path_buf(),
+				source,
+			},
+		)
+	}
+
+	/// Classify a failure to copy a file.
+	pub(crate) fn copying(from: &Path, to: &Path, error: io::Error) -> Self {
+		classify(
+			error,
+			from,
+			|path| Self::FileNotFound { path },
+			|from, source| Self::UnableToCopyFile {
+				from,
+				to: to.to_path_buf(),
+				source,
+			},
+		)
+	}
+
+	/// Classify a failure to create a directory.
+	pub(crate) fn creating_directory(path: &Path, error: io::Error) -> Self {
+		unwrap(error).unwrap_or_else(|source| Self::UnableToCreateDirectory {
+			path: path.to_path_buf(),
+			source,
+		})
+	}
+
+	/// Classify a failure to delete a directory.
+	pub(crate) fn deleting_directory(path: &Path, error: io::Error) -> Self {
+		classify(
+			error,
+			path,
+			|path| Self::DirectoryNotFound { path },
+			|path, source| Self::UnableToDeleteDirectory { path, source },
+		)
+	}
+
+	/// Classify a failure to list a directory.
+	pub(crate) fn listing(path: &Path, error: io::Error) -> Self {
+		classify(
+			error,
+			path,
+			|path| Self::DirectoryNotFound { path },
+			|path, source| Self::UnableToListContents { path, source },
+		)
+	}
+
+	/// Classify a failure to get the metadata of a file.
+	pub(crate) fn retrieving_metadata(path: &Path, error: io::Error) -> Self {
+		classify(
+			error,
+			path,
+			|path| Self::FileNotFound { path },
+			|path, source| Self::UnableToRetrieveMetadata { path, source },
+		)
+	}
+
+	/// Classify a failure to set the visibility of a file.
+	pub(crate) fn setting_visibility(path: &Path, error: io::Error) -> Self {
+		if error.kind() == ErrorKind::Unsupported {
+			return Self::VisibilityNotSupported {
+				path: path.to_path_buf(),
+			};
+		}
+
+		classify(
+			error,
+			path,
+			|path| Self::FileNotFound { path },
+			|path, source| Self::UnableToSetVisibility { path, source },
+		)
+	}
+
+	/// Classify a failure to get the public URL of a file.
+	pub(crate) fn generating_public_url(path: &Path, error: io::Error) -> Self {
+		if error.kind() == ErrorKind::Unsupported {
+			return Self::PublicUrlNotSupported {
+				path: path.to_path_buf(),
+			};
+		}
+
+		classify(
+			error,
+			path,
+			|path| Self::FileNotFound { path },
+			|_, source| Self::AdapterError(source),
+		)
+	}
+
+	/// Classify a failure to get a temporary URL of a file.
+	pub(crate) fn generating_temporary_url(path: &Path, error: io::Error) -> Self {
+		if error.kind() == ErrorKind::Unsupported {
+			return Self::TemporaryUrlNotSupported {
+				path: path.to_path_buf(),
+			};
+		}
+
+		classify(
+			error,
+			path,
+			|path| Self::FileNotFound { path },
+			|_, source| Self::AdapterError(source),
+		)
+	}
+}
+
+impl fmt::Debug for FlysystemError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		// the derived output would include the unredacted messages of the underlying errors
+		f.debug_struct("FlysystemError")
+			.field("kind", &self.kind())
+			.field("message", &self.to_string())
+			.finish()
+	}
+}
+
+/// Unwrap a [`FlysystemError`] an adapter reported as-is, or classify the failure as not found or as another failure of the operation.
+fn classify(
+	error: io::Error,
+	path: &Path,
+	not_found: impl FnOnce(PathBuf) -> FlysystemError,
+	failed: impl FnOnce(PathBuf, io::Error) -> FlysystemError,
+) -> FlysystemError {
+	match unwrap(error) {
+		Ok(error) => error,
+		Err(error) if error.kind() == ErrorKind::NotFound => not_found(path.to_path_buf()),
+		Err(error) => failed(path.to_path_buf(), error),
+	}
+}
+
+/// Get the [`FlysystemError`] an [`io::Error`] wraps, if any.
+fn unwrap(error: io::Error) -> std::result::Result<FlysystemError, io::Error> {
+	if !matches!(error.get_ref(), Some(inner) if inner.is::<FlysystemError>()) {
+		return Err(error);
+	}
+
+	let inner = error
+		.into_inner()
+		.expect("the error wraps a FlysystemError");
+	Ok(*inner
+		.downcast::<FlysystemError>()
+		.expect("the error wraps a FlysystemError"))
+}
+
+impl From<io::Error> for FlysystemError {
+	fn from(error: io::Error) -> Self {
+		unwrap(error).unwrap_or_else(Self::AdapterError)
+	}
+}
+
+impl From<FlysystemError> for io::Error {
+	fn from(error: FlysystemError) -> Self {
+		match error {
+			FlysystemError::AdapterError(error) => error,
+			error => Self::new(error.kind(), error),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_classify() {
+		assert!(matches!(
+			FlysystemError::reading(Path::new("a.txt"), io::Error::from(ErrorKind::NotFound)),
+			FlysystemError::FileNotFound { path } if path == Path::new("a.txt")
+		));
+		assert!(matches!(
+			FlysystemError::listing(Path::new("docs"), io::Error::from(ErrorKind::NotFound)),
+			FlysystemError::DirectoryNotFound { .. }
+		));
+
+		let error =
+			FlysystemError::writing(Path::new("a.txt"), io::Error::from(ErrorKind::StorageFull));
+		assert!(matches!(error, FlysystemError::UnableToWriteFile { .. }));
+		assert_eq!(error.kind(), ErrorKind::StorageFull);
+
+		// errors reported as-is by adapters survive the round trip through io::Error
+		let wrapped = io::Error::from(FlysystemError::PathTraversal {
+			path: PathBuf::from("../secret.txt"),
+		});
+		assert_eq!(wrapped.kind(), ErrorKind::PermissionDenied);
+		assert!(matches!(
+			FlysystemError::reading(Path::new("../secret.txt"), wrapped),
+			FlysystemError::PathTraversal { .. }
+		));
+	}
+}
@@ -0,0 +1,150 @@
+use std::{
+	fmt::Debug,
+	path::{Path, PathBuf},
+};
+
+/// An event emitted by a [`Filesystem`](crate::Filesystem) after a change, or after a change failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilesystemEvent {
+	WriteOccurred {
+		path: PathBuf,
+	},
+	FileDeleted {
+		path: PathBuf,
+	},
+	DirectoryDeleted {
+		path: PathBuf,
+	},
+	DirectoryCreated {
+		path: PathBuf,
+	},
+	FileMoved {
+		source: PathBuf,
+		destination: PathBuf,
+	},
+	FileCopied {
+		source: PathBuf,
+		destination: PathBuf,
+	},
+	DirectoryMoved {
+		source: PathBuf,
+		destination: PathBuf,
+	},
+	DirectoryCopied {
+		source: PathBuf,
+		destination: PathBuf,
+	},
+	WriteFailed {
+		path: PathBuf,
+		error: String,
+	},
+	DeleteFailed {
+		path: PathBuf,
+		error: String,
+	},
+	MoveFailed {
+		source: PathBuf,
+		destination: PathBuf,
+		error: String,
+	},
+	CopyFailed {
+		source: PathBuf,
+		destination: PathBuf,
+		error: String,
+	},
+	DirectoryCreationFailed {
+		path: PathBuf,
+		error: String,
+	},
+}
+
+impl FilesystemEvent {
+	/// The path affected by the event. For moves and copies, this is the destination.
+	#[must_use]
+	pub fn path(&self) -> &Path {
+		match self {
+			Self::WriteOccurred { path }
+			| Self::FileDeleted { path }
+			| Self::DirectoryDeleted { path }
+			| Self::DirectoryCreated { path }
+			| Self::WriteFailed { path, .. }
+			| Self::DeleteFailed { path, .. }
+			| Self::DirectoryCreationFailed { path, .. } => path,
+			Self::FileMoved { destination, .. }
+			| Self::FileCopied { destination, .. }
+			| Self::DirectoryMoved { destination, .. }
+			| Self::DirectoryCopied { destination, .. }
+			| Self::MoveFailed { destination, .. }
+			| Self::CopyFailed { destination, .. } => destination,
+		}
+	}
+
+	/// A short, stable name for the event.
+	#[must_use]
+	pub const fn name(&self) -> &'static str {
+		match self {
+			Self::WriteOccurred { .. } => "write",
+			Self::FileDeleted { .. } => "delete",
+			Self::DirectoryDeleted { .. } => "delete_directory",
+			Self::DirectoryCreated { .. } => "create_directory",
+			Self::FileMoved { .. } => "move",
+			Self::FileCopied { .. } => "copy",
+			Self::DirectoryMoved { .. } => "move_directory",
+			Self::DirectoryCopied { .. } => "copy_directory",
+			Self::WriteFailed { .. } => "write_failed",
+			Self::DeleteFailed { .. } => "delete_failed",
+			Self::MoveFailed { .. } => "move_failed",
+			Self::CopyFailed { .. } => "copy_failed",
+			Self::DirectoryCreationFailed { .. } => "create_directory_failed",
+		}
+	}
+
+	/// The error the operation failed with, if it did.
+	#[must_use]
+	pub fn error(&self) -> Option<&str> {
+		match self {
+			Self::WriteFailed { error, .. }
+			| Self::DeleteFailed { error, .. }
+			| Self::MoveFailed { error, .. }
+			| Self::CopyFailed { error, .. }
+			| Self::DirectoryCreationFailed { error, .. } => Some(error),
+			_ => None,
+		}
+	}
+}
+
+/// A listener notified of the events emitted by a [`Filesystem`](crate::Filesystem).
+#[async_trait::async_trait]
+pub trait EventListener: Debug + Send + Sync {
+	/// Called before an operation starts, with the event it will emit if it succeeds. Does nothing by default.
+	async fn before(&self, _event: &FilesystemEvent) {}
+
+	/// Handle an event. The operation that emitted it has already completed, so this can't fail it.
+	async fn handle(&self, event: &FilesystemEvent);
+
+	/// Wait for the events still being handled in the background to be delivered. Does nothing by default.
+	async fn flush(&self) {}
+}
+
+/// A listener calling a closure for the events with a given [name](FilesystemEvent::name), as registered by [`Filesystem::on`](crate::Filesystem::on).
+pub struct CallbackListener<F> {
+	pub name: &'static str,
+	pub callback: F,
+}
+
+impl<F> Debug for CallbackListener<F> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("CallbackListener")
+			.field("name", &self.name)
+			.finish_non_exhaustive()
+	}
+}
+
+#[async_trait::async_trait]
+impl<F: Fn(&FilesystemEvent) + Send + Sync> EventListener for CallbackListener<F> {
+	async fn handle(&self, event: &FilesystemEvent) {
+		if event.name() == self.name {
+			(self.callback)(event);
+		}
+	}
+}
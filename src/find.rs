@@ -0,0 +1,293 @@
+use std::{
+	fmt,
+	path::{Component, Path, PathBuf},
+	sync::Arc,
+	time::SystemTime,
+};
+
+use crate::{ignore::glob_match, relative_to, Filesystem, FlysystemError, Result, StorageEntry};
+
+/// A custom condition of a [`ListFilter`].
+type Predicate = Arc<dyn Fn(&StorageEntry) -> bool + Send + Sync>;
+
+/// Which files [`Filesystem::find`] returns. Every condition set must match.
+///
+/// ```rust,ignore
+/// let photos = filesystem
+///     .find(Path::new("uploads"), &ListFilter::new().glob("**/*.jpg").min_size(1024))
+///     .await?;
+/// ```
+#[derive(Clone, Default)]
+pub struct ListFilter {
+	glob: Option<String>,
+	min_size: Option<u64>,
+	max_size: Option<u64>,
+	modified_after: Option<SystemTime>,
+	predicate: Option<Predicate>,
+}
+
+impl ListFilter {
+	/// Create a filter matching every file.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Only match files whose path relative to the listed directory matches a glob pattern, like `**/*.jpg`.
+	///
+	/// `*` and `?` don't cross `/` boundaries, `**` does, and `[a-z]` matches a character class.
+	/// Only the directories the pattern can match are listed, so `photos/2024/*.jpg` only lists `photos/2024`.
+	#[must_use]
+	pub fn glob(mut self, pattern: impl Into<String>) -> Self {
+		self.glob = Some(pattern.into());
+		self
+	}
+
+	/// Only match files of at least `size` bytes.
+	#[must_use]
+	pub const fn min_size(mut self, size: u64) -> Self {
+		self.min_size = Some(size);
+		self
+	}
+
+	/// Only match files of at most `size` bytes.
+	#[must_use]
+	pub const fn max_size(mut self, size: u64) -> Self {
+		self.max_size = Some(size);
+		self
+	}
+
+	/// Only match files last modified after `time`.
+	#[must_use]
+	pub const fn modified_after(mut self, time: SystemTime) -> Self {
+		self.modified_after = Some(time);
+		self
+	}
+
+	/// Only match files for which `predicate` returns `true`, checked after every other condition.
+	#[must_use]
+	pub fn matching(
+		mut self,
+		predicate: impl Fn(&StorageEntry) -> bool + Send + Sync + 'static,
+	) -> Self {
+		self.predicate = Some(Arc::new(predicate));
+		self
+	}
+
+	/// Split the glob into the literal directories it starts with, and whether matching the rest needs a deep listing.
+	fn scope(&self) -> (PathBuf, bool) {
+		let Some(glob) = &self.glob else {
+			return (PathBuf::new(), true);
+		};
+
+		let segments: Vec<&str> = glob.split('/').filter(|s| !s.is_empty()).collect();
+		let literal = segments
+			.iter()
+			.take(segments.len().saturating_sub(1))
+			.take_while(|segment| !segment.contains(['*', '?', '[', '\\']))
+			.count();
+
+		(
+			segments[..literal].iter().collect(),
+			segments.len() - literal > 1 || glob.contains("**"),
+		)
+	}
+
+	fn matches_glob(&self, relative: &Path) -> bool {
+		self.glob.as_ref().is_none_or(|glob| {
+			let relative = relative
+				.components()
+				.filter_map(|component| match component {
+					Component::Normal(name) => name.to_str(),
+					_ => None,
+				})
+				.collect::<Vec<_>>()
+				.join("/");
+
+			glob_match(glob.trim_start_matches('/').as_bytes(), relative.as_bytes())
+		})
+	}
+
+	fn matches_metadata(&self, entry: &StorageEntry) -> bool {
+		let size = entry.size.unwrap_or_default();
+
+		self.min_size.is_none_or(|min| size >= min)
+			&& self.max_size.is_none_or(|max| size <= max)
+			&& self
+				.modified_after
+				.is_none_or(|after| entry.last_modified.is_some_and(|modified| modified > after))
+			&& self
+				.predicate
+				.as_ref()
+				.is_none_or(|predicate| predicate(entry))
+	}
+
+	const fn needs_size(&self) -> bool {
+		self.min_size.is_some() || self.max_size.is_some()
+	}
+}
+
+impl fmt::Debug for ListFilter {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("ListFilter")
+			.field("glob", &self.glob)
+			.field("min_size", &self.min_size)
+			.field("max_size", &self.max_size)
+			.field("modified_after", &self.modified_after)
+			.field("predicate", &self.predicate.as_ref().map(|_| ".."))
+			.finish()
+	}
+}
+
+impl Filesystem {
+	/// Find the files under a directory matching a [`ListFilter`], sorted by path.
+	///
+	/// The literal directories a glob starts with are listed directly (a narrower prefix query on object stores like S3),
+	/// and the rest of the filter is applied to the listing. Metadata the adapter doesn't return while listing is fetched per file,
+	/// only for the files the glob matched.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the directory doesn't exist, or if the adapter fails to list it or get a file's metadata.
+	pub async fn find(&self, path: &Path, filter: &ListFilter) -> Result<Vec<StorageEntry>> {
+		let path = self.normalize(path)?;
+		let (prefix, deep) = filter.scope();
+		let directory = path.join(&prefix);
+
+		let entries = match self.list_contents_with_metadata(&directory, deep).await {
+			// the glob's directories not existing just means nothing matches
+			Err(FlysystemError::DirectoryNotFound { .. })
+				if !prefix.as_os_str().is_empty() && self.has_directory(&path).await? =>
+			{
+				Vec::new()
+			},
+			result => result?,
+		};
+
+		let mut found = Vec::new();
+		for mut entry in entries {
+			if entry.is_dir || !filter.matches_glob(relative_to(&entry.path, &path)) {
+				continue;
+			}
+
+			if entry.size.is_none() && filter.needs_size() {
+				entry.size =
+					Some(self.adapter.file_size(&entry.path).await.map_err(|error| {
+						FlysystemError::retrieving_metadata(&entry.path, error)
+					})?);
+			}
+			if entry.last_modified.is_none() && filter.modified_after.is_some() {
+				entry.last_modified = Some(
+					self.adapter
+						.last_modified(&entry.path)
+						.await
+						.map_err(|error| FlysystemError::retrieving_metadata(&entry.path, error))?,
+				);
+			}
+
+			if filter.matches_metadata(&entry) {
+				found.push(entry);
+			}
+		}
+
+		Ok(found)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::*;
+	use crate::adapters::MemoryAdapter;
+
+	#[tokio::test]
+	async fn test_find() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default());
+		filesystem
+			.write(Path::new("photos/2024/beach.jpg"), &[0; 2048])
+			.await
+			.unwrap();
+		filesystem
+			.write(Path::new("photos/2024/icon.jpg"), &[0; 16])
+			.await
+			.unwrap();
+		filesystem
+			.write(Path::new("photos/notes.txt"), b"notes")
+			.await
+			.unwrap();
+		filesystem
+			.write(Path::new("cover.jpg"), &[0; 4096])
+			.await
+			.unwrap();
+
+		let paths = |entries: Vec<StorageEntry>| {
+			entries
+				.into_iter()
+				.map(|entry| entry.path)
+				.collect::<Vec<_>>()
+		};
+
+		let found = filesystem
+			.find(Path::new(""), &ListFilter::new().glob("**/*.jpg"))
+			.await
+			.unwrap();
+		assert_eq!(
+			paths(found),
+			vec![
+				PathBuf::from("cover.jpg"),
+				PathBuf::from("photos/2024/beach.jpg"),
+				PathBuf::from("photos/2024/icon.jpg"),
+			]
+		);
+
+		let found = filesystem
+			.find(
+				Path::new("photos"),
+				&ListFilter::new().glob("*/*.jpg").min_size(1024),
+			)
+			.await
+			.unwrap();
+		assert_eq!(paths(found), vec![PathBuf::from("photos/2024/beach.jpg")]);
+
+		let found = filesystem
+			.find(
+				Path::new(""),
+				&ListFilter::new()
+					.max_size(2048)
+					.modified_after(SystemTime::now() - Duration::from_mins(1))
+					.matching(|entry| entry.path.starts_with("photos")),
+			)
+			.await
+			.unwrap();
+		assert_eq!(found.len(), 3);
+
+		// a glob starting with missing directories matches nothing, but the listed directory must exist
+		assert!(filesystem
+			.find(Path::new(""), &ListFilter::new().glob("videos/*.mp4"))
+			.await
+			.unwrap()
+			.is_empty());
+		assert!(matches!(
+			filesystem
+				.find(Path::new("videos"), &ListFilter::new().glob("2024/*.mp4"))
+				.await,
+			Err(FlysystemError::DirectoryNotFound { .. })
+		));
+	}
+
+	#[test]
+	fn test_scope() {
+		let scope = |glob: &str| ListFilter::new().glob(glob).scope();
+
+		assert_eq!(scope("**/*.jpg"), (PathBuf::new(), true));
+		assert_eq!(scope("*.jpg"), (PathBuf::new(), false));
+		assert_eq!(
+			scope("photos/2024/*.jpg"),
+			(PathBuf::from("photos/2024"), false)
+		);
+		assert_eq!(scope("photos/*/raw/*.cr2"), (PathBuf::from("photos"), true));
+		assert_eq!(scope("photos/**"), (PathBuf::from("photos"), true));
+		assert_eq!(scope("a/b.txt"), (PathBuf::from("a"), false));
+	}
+}
@@ -0,0 +1,341 @@
+use mime::Mime;
+use std::{
+	path::{Path, PathBuf},
+	time::{Duration, SystemTime},
+};
+use url::Url;
+
+use crate::{
+	normalize, Contents, DirectoryStat, FileStat, Filesystem, Result, StorageEntry, Visibility,
+	WriteOptions,
+};
+
+/// A file of a [`Filesystem`], only exposing the operations that make sense on files. See [`Filesystem::file`].
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct FileHandle<'a> {
+	filesystem: &'a Filesystem,
+	path: PathBuf,
+}
+
+/// A directory of a [`Filesystem`], only exposing the operations that make sense on directories. See [`Filesystem::dir`].
+#[derive(Debug, Clone)]
+#[allow(clippy::module_name_repetitions)]
+pub struct DirHandle<'a> {
+	filesystem: &'a Filesystem,
+	path: PathBuf,
+}
+
+impl Filesystem {
+	/// Get a handle on the file at `path`, which doesn't have to exist yet.
+	pub fn file(&self, path: impl AsRef<Path>) -> FileHandle<'_> {
+		FileHandle {
+			path: normalize(path.as_ref()),
+			filesystem: self,
+		}
+	}
+
+	/// Get a handle on the directory at `path`, which doesn't have to exist yet.
+	pub fn dir(&self, path: impl AsRef<Path>) -> DirHandle<'_> {
+		DirHandle {
+			path: normalize(path.as_ref()),
+			filesystem: self,
+		}
+	}
+}
+
+impl FileHandle<'_> {
+	/// Get the path of the file.
+	#[must_use]
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Check if the file exists.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to check for the file.
+	pub async fn exists(&self) -> Result<bool> {
+		self.filesystem.file_exists(&self.path).await
+	}
+
+	/// Get the contents of the file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to read the file.
+	pub async fn read<R: TryFrom<Contents>>(&self) -> Result<R> {
+		self.filesystem.read(&self.path).await
+	}
+
+	/// Write the file, overwriting it if it exists.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to write the file.
+	pub async fn write(&self, contents: &[u8]) -> Result<()> {
+		self.filesystem.write(&self.path, contents).await
+	}
+
+	/// Write the file with the given options. See [`Filesystem::write_with`].
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to write the file or to apply the options.
+	pub async fn write_with(&self, contents: &[u8], options: WriteOptions) -> Result<()> {
+		self.filesystem
+			.write_with(&self.path, contents, options)
+			.await
+	}
+
+	/// Add `contents` to the end of the file, creating it if it doesn't exist. See [`Filesystem::append`].
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to update the file.
+	pub async fn append(&self, contents: &[u8]) -> Result<()> {
+		self.filesystem.append(&self.path, contents).await
+	}
+
+	/// Read `len` bytes of the file starting at `offset`. See [`Filesystem::read_range`].
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to read the file.
+	pub async fn read_range(&self, offset: u64, len: u64) -> Result<Contents> {
+		self.filesystem.read_range(&self.path, offset, len).await
+	}
+
+	/// Delete the file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to delete the file.
+	pub async fn delete(self) -> Result<()> {
+		self.filesystem.delete(&self.path).await
+	}
+
+	/// Copy the file to `destination`.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to copy the file.
+	pub async fn copy_to(&self, destination: impl AsRef<Path>) -> Result<()> {
+		self.filesystem.copy(&self.path, destination.as_ref()).await
+	}
+
+	/// Move the file to `destination`, returning a handle on its new location.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to move the file.
+	pub async fn move_to(self, destination: impl AsRef<Path>) -> Result<Self> {
+		let destination = normalize(destination.as_ref());
+		self.filesystem.r#move(&self.path, &destination).await?;
+
+		Ok(Self {
+			filesystem: self.filesystem,
+			path: destination,
+		})
+	}
+
+	/// Get the size, last modified time, MIME type and revision of the file. See [`Filesystem::stat`].
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to get the metadata of the file.
+	pub async fn stat(&self) -> Result<FileStat> {
+		self.filesystem.stat(&self.path).await
+	}
+
+	/// Get the size of the file, in bytes.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to get the size of the file.
+	pub async fn size(&self) -> Result<u64> {
+		self.filesystem.file_size(&self.path).await
+	}
+
+	/// Get the last modified time of the file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to get the last modified time of the file.
+	pub async fn last_modified(&self) -> Result<SystemTime> {
+		self.filesystem.last_modified(&self.path).await
+	}
+
+	/// Get the MIME type of the file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to get the MIME type of the file.
+	pub async fn mime_type(&self) -> Result<Mime> {
+		self.filesystem.mime_type(&self.path).await
+	}
+
+	/// Get the visibility of the file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to get the visibility of the file.
+	pub async fn visibility(&self) -> Result<Visibility> {
+		self.filesystem.visibility(&self.path).await
+	}
+
+	/// Set the visibility of the file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to set the visibility of the file.
+	pub async fn set_visibility(&self, visibility: Visibility) -> Result<()> {
+		self.filesystem.set_visibility(&self.path, visibility).await
+	}
+
+	/// Get the checksum of the file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to compute the checksum of the file.
+	pub async fn checksum(&self) -> Result<String> {
+		self.filesystem.checksum(&self.path).await
+	}
+
+	/// Get the public URL of the file. See [`Filesystem::public_url`].
+	///
+	/// # Errors
+	///
+	/// This function will return an error if public URLs aren't supported, or if generating the URL fails.
+	pub async fn public_url(&self) -> Result<Url> {
+		self.filesystem.public_url(&self.path).await
+	}
+
+	/// Get a URL granting access to the file until `expires_in` elapses. See [`Filesystem::temporary_url`].
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter can't generate temporary URLs, or if generating the URL fails.
+	pub async fn temporary_url(&self, expires_in: Duration) -> Result<Url> {
+		self.filesystem.temporary_url(&self.path, expires_in).await
+	}
+}
+
+impl DirHandle<'_> {
+	/// Get the path of the directory.
+	#[must_use]
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Check if the directory exists.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to check for the directory.
+	pub async fn exists(&self) -> Result<bool> {
+		self.filesystem.directory_exists(&self.path).await
+	}
+
+	/// Get when the directory was last modified and how many entries it directly contains.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to get the metadata of the directory.
+	pub async fn stat(&self) -> Result<DirectoryStat> {
+		self.filesystem.directory_stat(&self.path).await
+	}
+
+	/// Get a list of files in the directory (optionally recursively).
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to list the contents of the directory.
+	pub async fn list(&self, deep: bool) -> Result<Vec<PathBuf>> {
+		self.filesystem.list_contents(&self.path, deep).await
+	}
+
+	/// Get a list of files in the directory (optionally recursively), along with their metadata.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to list the contents of the directory.
+	pub async fn list_with_metadata(&self, deep: bool) -> Result<Vec<StorageEntry>> {
+		self.filesystem
+			.list_contents_with_metadata(&self.path, deep)
+			.await
+	}
+
+	/// Create the directory.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to create the directory.
+	pub async fn create(&self) -> Result<()> {
+		self.filesystem.create_directory(&self.path).await
+	}
+
+	/// Delete the directory, along with everything in it.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to delete the directory.
+	pub async fn delete(self) -> Result<()> {
+		self.filesystem.delete_directory(&self.path).await
+	}
+
+	/// Get a handle on a file in the directory.
+	pub fn file(&self, name: impl AsRef<Path>) -> FileHandle<'_> {
+		let path = self.path.join(name);
+		self.filesystem.file(path)
+	}
+
+	/// Get a handle on a subdirectory.
+	pub fn dir(&self, name: impl AsRef<Path>) -> DirHandle<'_> {
+		let path = self.path.join(name);
+		self.filesystem.dir(path)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::adapters::MemoryAdapter;
+
+	#[tokio::test]
+	async fn test_handles() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default());
+
+		let docs = filesystem.dir("docs");
+		docs.file("invoice.pdf").write(b"%PDF").await.unwrap();
+		docs.dir("drafts")
+			.file("letter.txt")
+			.write(b"Dear")
+			.await
+			.unwrap();
+
+		assert!(docs.exists().await.unwrap());
+		let mut contents = docs.list(true).await.unwrap();
+		contents.sort();
+		assert_eq!(
+			contents,
+			vec![
+				PathBuf::from("docs/drafts/letter.txt"),
+				PathBuf::from("docs/invoice.pdf")
+			]
+		);
+
+		let invoice = filesystem.file("docs/invoice.pdf");
+		assert_eq!(invoice.size().await.unwrap(), 4);
+		assert_eq!(invoice.read::<String>().await.unwrap(), "%PDF");
+
+		let archived = invoice.move_to("archive/invoice.pdf").await.unwrap();
+		assert_eq!(archived.path(), Path::new("archive/invoice.pdf"));
+		assert!(archived.exists().await.unwrap());
+		assert!(!filesystem.file("docs/invoice.pdf").exists().await.unwrap());
+
+		filesystem.dir("docs").delete().await.unwrap();
+		assert!(!filesystem.dir("docs").exists().await.unwrap());
+	}
+}
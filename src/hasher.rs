@@ -0,0 +1,129 @@
+use sha2::Digest;
+use std::path::Path;
+
+use crate::{Filesystem, FlysystemError, Result};
+
+/// How many bytes are read at once while hashing a file.
+const CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
+/// A hash function files can be checksummed with, like a faster non-cryptographic one for deduplication.
+pub trait Hasher: Send + Sync {
+	/// The state of a hash being computed.
+	type State: Send;
+
+	/// Start hashing a file.
+	fn begin(&self) -> Self::State;
+
+	/// Feed the next bytes of the file.
+	fn update(&self, state: &mut Self::State, data: &[u8]);
+
+	/// Get the hash of the file, hex-encoded.
+	fn finish(&self, state: Self::State) -> String;
+}
+
+/// The SHA-256 hash function, matching the checksums of the local and memory adapters.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sha256;
+
+impl Hasher for Sha256 {
+	type State = sha2::Sha256;
+
+	fn begin(&self) -> Self::State {
+		sha2::Sha256::new()
+	}
+
+	fn update(&self, state: &mut Self::State, data: &[u8]) {
+		state.update(data);
+	}
+
+	fn finish(&self, state: Self::State) -> String {
+		format!("{:x}", state.finalize())
+	}
+}
+
+impl Filesystem {
+	/// Get the checksum of a file using the given hash function, instead of the adapter's.
+	///
+	/// The file is read in chunks, so adapters supporting ranged reads never hold all of it in memory.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to get the file's size or read it.
+	pub async fn checksum_with_hasher<H: Hasher>(&self, path: &Path, hasher: &H) -> Result<String> {
+		let path = self.normalize(path)?;
+		let size = self
+			.adapter
+			.file_size(&path)
+			.await
+			.map_err(|error| FlysystemError::retrieving_metadata(&path, error))?;
+		let mut state = hasher.begin();
+
+		let mut offset = 0;
+		while offset < size {
+			let chunk = self
+				.adapter
+				.read_range(&path, offset, CHUNK_SIZE)
+				.await
+				.map_err(|error| FlysystemError::reading(&path, error))?;
+			if chunk.is_empty() {
+				break;
+			}
+
+			hasher.update(&mut state, &chunk.data);
+			offset += chunk.len() as u64;
+		}
+
+		Ok(hasher.finish(state))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::adapters::MemoryAdapter;
+
+	/// The 64-bit FNV-1a hash, as an example of a custom hash function.
+	struct Fnv;
+
+	impl Hasher for Fnv {
+		type State = u64;
+
+		fn begin(&self) -> u64 {
+			0xcbf2_9ce4_8422_2325
+		}
+
+		fn update(&self, state: &mut u64, data: &[u8]) {
+			for byte in data {
+				*state = (*state ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+			}
+		}
+
+		fn finish(&self, state: u64) -> String {
+			format!("{state:016x}")
+		}
+	}
+
+	#[tokio::test]
+	async fn test_checksum_with_hasher() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default());
+		filesystem
+			.write(Path::new("hello.txt"), b"Hello, world!")
+			.await
+			.unwrap();
+
+		assert_eq!(
+			filesystem
+				.checksum_with_hasher(Path::new("hello.txt"), &Sha256)
+				.await
+				.unwrap(),
+			filesystem.checksum(Path::new("hello.txt")).await.unwrap()
+		);
+		assert_eq!(
+			filesystem
+				.checksum_with_hasher(Path::new("hello.txt"), &Fnv)
+				.await
+				.unwrap(),
+			"38d1334144987bf4"
+		);
+	}
+}
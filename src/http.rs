@@ -0,0 +1,350 @@
+use ::http::{header, HeaderMap, Response, StatusCode};
+use std::{
+	io::{Error, ErrorKind},
+	path::Path,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{Filesystem, FlysystemError, Result};
+
+impl Filesystem {
+	/// Build an HTTP response serving a file.
+	///
+	/// Conditional requests are honored using the file's checksum as its `ETag` and its last modified date:
+	/// if `If-None-Match` matches (or, when absent, `If-Modified-Since` is not older than the file), a `304 Not Modified` response without a body is returned.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to read the file or its metadata.
+	pub async fn response(
+		&self,
+		path: &Path,
+		request_headers: &HeaderMap,
+	) -> Result<Response<Vec<u8>>> {
+		let (checksum, last_modified) =
+			futures::future::join(self.checksum(path), self.last_modified(path)).await;
+		let etag = format!("\"{}\"", checksum?.trim_matches('"'));
+		let last_modified = last_modified?;
+
+		let response = Response::builder().header(header::ETAG, &etag).header(
+			header::LAST_MODIFIED,
+			httpdate::fmt_http_date(last_modified),
+		);
+
+		if is_not_modified(request_headers, &etag, last_modified) {
+			return response
+				.status(StatusCode::NOT_MODIFIED)
+				.body(Vec::new())
+				.map_err(|error| Error::other(error).into());
+		}
+
+		let normalized = self.normalize(path)?;
+		let (contents, mime_type) =
+			futures::future::join(self.adapter.read(&normalized), self.mime_type(path)).await;
+		let contents = contents
+			.map_err(|error| FlysystemError::reading(&normalized, error))?
+			.data;
+
+		response
+			.status(StatusCode::OK)
+			.header(header::CONTENT_TYPE, mime_type?.as_ref())
+			.header(header::CONTENT_LENGTH, contents.len())
+			.body(contents)
+			.map_err(|error| Error::other(error).into())
+	}
+}
+
+/// Get the HTTP status code that best describes a failed [`Filesystem`] operation.
+///
+/// Adapters surface failures of the storage backend itself (like S3 being unreachable) as `Other` errors, so those map to `502 Bad Gateway`.
+#[must_use]
+pub fn status_code(error: &FlysystemError) -> StatusCode {
+	match error {
+		FlysystemError::FileNotFound { .. } | FlysystemError::DirectoryNotFound { .. } => {
+			StatusCode::NOT_FOUND
+		},
+		FlysystemError::PathTraversal { .. }
+		| FlysystemError::FileNotPublic { .. }
+		| FlysystemError::Protected { .. } => StatusCode::FORBIDDEN,
+		FlysystemError::CorruptedPath { .. } => StatusCode::BAD_REQUEST,
+		FlysystemError::PreconditionFailed { .. } => StatusCode::PRECONDITION_FAILED,
+		FlysystemError::VisibilityNotSupported { .. }
+		| FlysystemError::PublicUrlNotSupported { .. }
+		| FlysystemError::TemporaryUrlNotSupported { .. } => StatusCode::NOT_IMPLEMENTED,
+		FlysystemError::UnableToReadFile { source, .. }
+		| FlysystemError::UnableToWriteFile { source, .. }
+		| FlysystemError::UnableToDeleteFile { source, .. }
+		| FlysystemError::UnableToMoveFile { source, .. }
+		| FlysystemError::UnableToCopyFile { source, .. }
+		| FlysystemError::UnableToCreateDirectory { source, .. }
+		| FlysystemError::UnableToDeleteDirectory { source, .. }
+		| FlysystemError::UnableToListContents { source, .. }
+		| FlysystemError::UnableToRetrieveMetadata { source, .. }
+		| FlysystemError::UnableToSetVisibility { source, .. }
+		| FlysystemError::AdapterError(source) => status_for_kind(source.kind()),
+	}
+}
+
+/// Get the HTTP status code of an adapter failure.
+const fn status_for_kind(kind: ErrorKind) -> StatusCode {
+	match kind {
+		ErrorKind::NotFound => StatusCode::NOT_FOUND,
+		ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
+		ErrorKind::AlreadyExists | ErrorKind::DirectoryNotEmpty => StatusCode::CONFLICT,
+		ErrorKind::InvalidInput => StatusCode::BAD_REQUEST,
+		ErrorKind::InvalidData => StatusCode::UNPROCESSABLE_ENTITY,
+		ErrorKind::FileTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+		ErrorKind::StorageFull | ErrorKind::QuotaExceeded => StatusCode::INSUFFICIENT_STORAGE,
+		ErrorKind::Unsupported => StatusCode::NOT_IMPLEMENTED,
+		ErrorKind::TimedOut => StatusCode::GATEWAY_TIMEOUT,
+		ErrorKind::Interrupted | ErrorKind::ResourceBusy => StatusCode::SERVICE_UNAVAILABLE,
+		ErrorKind::Other
+		| ErrorKind::ConnectionRefused
+		| ErrorKind::ConnectionReset
+		| ErrorKind::ConnectionAborted
+		| ErrorKind::NotConnected
+		| ErrorKind::BrokenPipe
+		| ErrorKind::UnexpectedEof => StatusCode::BAD_GATEWAY,
+		_ => StatusCode::INTERNAL_SERVER_ERROR,
+	}
+}
+
+/// A failed [`Filesystem`] operation, to be returned from an HTTP handler.
+///
+/// Client errors include the error's message in the response, while server errors only include the status' reason so backend details aren't leaked.
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+pub struct HttpError(#[from] pub FlysystemError);
+
+impl From<Error> for HttpError {
+	fn from(error: Error) -> Self {
+		Self(error.into())
+	}
+}
+
+impl HttpError {
+	/// The status code of the response.
+	#[must_use]
+	pub fn status(&self) -> StatusCode {
+		status_code(&self.0)
+	}
+
+	/// Build the response for this error.
+	#[must_use]
+	pub fn into_response(self) -> Response<String> {
+		let status = self.status();
+		let body = if status.is_client_error() {
+			self.0.to_string()
+		} else {
+			status.canonical_reason().unwrap_or_default().to_string()
+		};
+
+		let mut response = Response::new(body);
+		*response.status_mut() = status;
+		response.headers_mut().insert(
+			header::CONTENT_TYPE,
+			header::HeaderValue::from_static("text/plain; charset=utf-8"),
+		);
+
+		response
+	}
+}
+
+#[cfg(feature = "axum")]
+impl axum_core::response::IntoResponse for HttpError {
+	fn into_response(self) -> axum_core::response::Response {
+		Self::into_response(self).map(axum_core::body::Body::from)
+	}
+}
+
+/// Evaluate the `If-None-Match` and `If-Modified-Since` preconditions (RFC 9110, section 13.2.2).
+fn is_not_modified(headers: &HeaderMap, etag: &str, last_modified: SystemTime) -> bool {
+	if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH) {
+		return if_none_match.to_str().is_ok_and(|value| {
+			value.split(',').map(str::trim).any(|candidate| {
+				candidate == "*"
+					|| candidate.trim_start_matches("W/") == etag.trim_start_matches("W/")
+			})
+		});
+	}
+
+	headers
+		.get(header::IF_MODIFIED_SINCE)
+		.and_then(|value| value.to_str().ok())
+		.and_then(|value| httpdate::parse_http_date(value).ok())
+		.is_some_and(|since| truncate_to_seconds(last_modified) <= since)
+}
+
+/// HTTP dates have a one second resolution, so sub-second precision must be dropped before comparing.
+fn truncate_to_seconds(time: SystemTime) -> SystemTime {
+	time.duration_since(UNIX_EPOCH).map_or(time, |elapsed| {
+		UNIX_EPOCH + Duration::from_secs(elapsed.as_secs())
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use ::http::HeaderValue;
+	use std::path::PathBuf;
+
+	use super::*;
+	use crate::adapters::MemoryAdapter;
+
+	async fn filesystem() -> Filesystem {
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+
+		filesystem
+			.write(Path::new("index.html"), b"<h1>Hello, world!</h1>")
+			.await
+			.unwrap();
+
+		filesystem
+	}
+
+	#[tokio::test]
+	async fn test_response() {
+		let filesystem = filesystem().await;
+
+		let response = filesystem
+			.response(Path::new("index.html"), &HeaderMap::new())
+			.await
+			.unwrap();
+
+		assert_eq!(response.status(), StatusCode::OK);
+		assert_eq!(response.headers()[header::CONTENT_TYPE], "text/html");
+		assert_eq!(response.headers()[header::CONTENT_LENGTH], "22");
+		assert!(response.headers().contains_key(header::ETAG));
+		assert_eq!(response.body(), b"<h1>Hello, world!</h1>");
+	}
+
+	#[tokio::test]
+	async fn test_response_if_none_match() {
+		let filesystem = filesystem().await;
+		let etag = filesystem
+			.response(Path::new("index.html"), &HeaderMap::new())
+			.await
+			.unwrap()
+			.headers()[header::ETAG]
+			.clone();
+
+		let mut headers = HeaderMap::new();
+		headers.insert(header::IF_NONE_MATCH, etag);
+		let response = filesystem
+			.response(Path::new("index.html"), &headers)
+			.await
+			.unwrap();
+
+		assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+		assert!(response.body().is_empty());
+
+		headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"stale\""));
+		let response = filesystem
+			.response(Path::new("index.html"), &headers)
+			.await
+			.unwrap();
+
+		assert_eq!(response.status(), StatusCode::OK);
+	}
+
+	#[tokio::test]
+	async fn test_response_if_modified_since() {
+		let filesystem = filesystem().await;
+
+		let mut headers = HeaderMap::new();
+		headers.insert(
+			header::IF_MODIFIED_SINCE,
+			HeaderValue::from_str(&httpdate::fmt_http_date(SystemTime::now())).unwrap(),
+		);
+		let response = filesystem
+			.response(Path::new("index.html"), &headers)
+			.await
+			.unwrap();
+
+		assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+		headers.insert(
+			header::IF_MODIFIED_SINCE,
+			HeaderValue::from_static("Thu, 01 Jan 1970 00:00:00 GMT"),
+		);
+		let response = filesystem
+			.response(Path::new("index.html"), &headers)
+			.await
+			.unwrap();
+
+		assert_eq!(response.status(), StatusCode::OK);
+	}
+
+	#[test]
+	fn test_status_code() {
+		let path = PathBuf::from("index.html");
+
+		for (error, status) in [
+			(
+				FlysystemError::FileNotFound { path: path.clone() },
+				StatusCode::NOT_FOUND,
+			),
+			(
+				FlysystemError::PathTraversal { path: path.clone() },
+				StatusCode::FORBIDDEN,
+			),
+			(
+				FlysystemError::PreconditionFailed { path: path.clone() },
+				StatusCode::PRECONDITION_FAILED,
+			),
+			(
+				FlysystemError::PublicUrlNotSupported { path: path.clone() },
+				StatusCode::NOT_IMPLEMENTED,
+			),
+			(
+				FlysystemError::UnableToWriteFile {
+					path: path.clone(),
+					source: Error::from(ErrorKind::StorageFull),
+				},
+				StatusCode::INSUFFICIENT_STORAGE,
+			),
+			(
+				FlysystemError::UnableToReadFile {
+					path,
+					source: Error::from(ErrorKind::TimedOut),
+				},
+				StatusCode::GATEWAY_TIMEOUT,
+			),
+			(
+				FlysystemError::AdapterError(Error::from(ErrorKind::AlreadyExists)),
+				StatusCode::CONFLICT,
+			),
+			(
+				FlysystemError::AdapterError(Error::from(ErrorKind::Other)),
+				StatusCode::BAD_GATEWAY,
+			),
+		] {
+			assert_eq!(status_code(&error), status);
+		}
+	}
+
+	#[tokio::test]
+	async fn test_http_error_response() {
+		let filesystem = filesystem().await;
+
+		let error = HttpError::from(
+			filesystem
+				.read::<String>(Path::new("missing.html"))
+				.await
+				.unwrap_err(),
+		);
+		assert_eq!(error.into_response().status(), StatusCode::NOT_FOUND);
+
+		let error = HttpError::from(
+			filesystem
+				.response(Path::new("missing.html"), &HeaderMap::new())
+				.await
+				.unwrap_err(),
+		);
+		assert_eq!(error.into_response().status(), StatusCode::NOT_FOUND);
+
+		let response =
+			HttpError::from(Error::other("connection to 10.0.0.1 refused")).into_response();
+		assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+		assert_eq!(response.body(), "Bad Gateway");
+	}
+}
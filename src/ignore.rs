@@ -0,0 +1,241 @@
+use std::path::{Component, Path};
+
+/// The name of the file ignore rules are loaded from.
+pub const IGNORE_FILE: &str = ".flyignore";
+
+/// A set of gitignore-style rules used to skip paths while listing or syncing.
+///
+/// Supported syntax:
+/// - blank lines and lines starting with `#` are skipped
+/// - `*` matches anything except `/`, `?` matches a single character and `[a-z]` matches a character class
+/// - `**` matches across directories (`**/logs`, `logs/**`, `a/**/b`)
+/// - a leading `!` re-includes paths excluded by a previous rule
+/// - a trailing `/` only matches directories
+/// - patterns containing a `/` are anchored to the listed directory, others match at any depth
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IgnoreRules {
+	rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+	pattern: String,
+	negated: bool,
+	anchored: bool,
+	directory_only: bool,
+}
+
+impl IgnoreRules {
+	/// Create an empty set of rules.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self { rules: Vec::new() }
+	}
+
+	/// Parse the contents of an ignore file (one pattern per line).
+	#[must_use]
+	pub fn parse(contents: &str) -> Self {
+		let mut rules = Self::new();
+
+		for line in contents.lines() {
+			rules.add(line);
+		}
+
+		rules
+	}
+
+	/// Add a single pattern to the rules.
+	pub fn add(&mut self, pattern: &str) -> &mut Self {
+		let mut pattern = pattern.trim_end();
+		if pattern.is_empty() || pattern.starts_with('#') {
+			return self;
+		}
+
+		let negated = pattern.starts_with('!');
+		if negated {
+			pattern = &pattern[1..];
+		}
+		if let Some(escaped) = pattern.strip_prefix('\\') {
+			pattern = escaped;
+		}
+
+		let directory_only = pattern.ends_with('/');
+		let pattern = pattern.trim_end_matches('/');
+		let anchored = pattern.contains('/');
+		let pattern = pattern.trim_start_matches('/');
+
+		if !pattern.is_empty() {
+			self.rules.push(Rule {
+				negated,
+				anchored,
+				directory_only,
+				pattern: pattern.to_string(),
+			});
+		}
+
+		self
+	}
+
+	/// Whether there are no rules in this set.
+	#[must_use]
+	pub const fn is_empty(&self) -> bool {
+		self.rules.is_empty()
+	}
+
+	/// Check if a file (given relative to the listed directory) should be ignored.
+	///
+	/// Files inside an ignored directory are always ignored, like git does.
+	#[must_use]
+	pub fn is_ignored(&self, path: &Path) -> bool {
+		let components = path
+			.components()
+			.filter_map(|component| match component {
+				Component::Normal(name) => name.to_str(),
+				_ => None,
+			})
+			.collect::<Vec<_>>();
+
+		(1..=components.len()).any(|i| self.matches(&components[..i], i < components.len()))
+	}
+
+	fn matches(&self, components: &[&str], is_dir: bool) -> bool {
+		let mut ignored = false;
+
+		for rule in &self.rules {
+			if rule.directory_only && !is_dir {
+				continue;
+			}
+
+			if rule.matches(components) {
+				ignored = !rule.negated;
+			}
+		}
+
+		ignored
+	}
+}
+
+impl Rule {
+	fn matches(&self, components: &[&str]) -> bool {
+		if self.anchored {
+			glob_match(self.pattern.as_bytes(), components.join("/").as_bytes())
+		} else {
+			components
+				.last()
+				.is_some_and(|name| glob_match(self.pattern.as_bytes(), name.as_bytes()))
+		}
+	}
+}
+
+/// Match `text` against a glob `pattern`, where `*` and `?` don't cross `/` boundaries and `**` does.
+pub fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+	match pattern {
+		[] => text.is_empty(),
+		[b'*', b'*', b'/', rest @ ..] => {
+			// `**/` matches zero or more leading directories.
+			glob_match(rest, text)
+				|| text
+					.iter()
+					.enumerate()
+					.any(|(i, c)| *c == b'/' && glob_match(rest, &text[i + 1..]))
+		},
+		[b'*', b'*', rest @ ..] => (0..=text.len()).any(|i| glob_match(rest, &text[i..])),
+		[b'*', rest @ ..] => (0..=text.len())
+			.take_while(|&i| i == 0 || text[i - 1] != b'/')
+			.any(|i| glob_match(rest, &text[i..])),
+		[b'?', rest @ ..] => matches!(text, [c, ..] if *c != b'/') && glob_match(rest, &text[1..]),
+		[b'[', rest @ ..] => {
+			let Some(end) = rest.iter().skip(1).position(|c| *c == b']').map(|i| i + 1) else {
+				return text.first() == Some(&b'[') && glob_match(rest, &text[1..]);
+			};
+
+			match text {
+				[c, remaining @ ..] if *c != b'/' && class_matches(&rest[..end], *c) => {
+					glob_match(&rest[end + 1..], remaining)
+				},
+				_ => false,
+			}
+		},
+		[b'\\', c, rest @ ..] | [c, rest @ ..] => {
+			text.first() == Some(c) && glob_match(rest, &text[1..])
+		},
+	}
+}
+
+fn class_matches(class: &[u8], c: u8) -> bool {
+	let (negated, mut class) = match class {
+		[b'!' | b'^', rest @ ..] => (true, rest),
+		_ => (false, class),
+	};
+
+	let mut found = false;
+	while let Some(&start) = class.first() {
+		if let [_, b'-', end, rest @ ..] = class {
+			found |= (start..=*end).contains(&c);
+			class = rest;
+		} else {
+			found |= start == c;
+			class = &class[1..];
+		}
+	}
+
+	found != negated
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_glob_match() {
+		assert!(glob_match(b"*.txt", b"file.txt"));
+		assert!(!glob_match(b"*.txt", b"dir/file.txt"));
+		assert!(glob_match(b"**/*.txt", b"file.txt"));
+		assert!(glob_match(b"**/*.txt", b"dir/nested/file.txt"));
+		assert!(glob_match(b"logs/**", b"logs/2024/app.log"));
+		assert!(glob_match(b"a/**/b", b"a/b"));
+		assert!(glob_match(b"a/**/b", b"a/x/y/b"));
+		assert!(glob_match(b"file?.txt", b"file1.txt"));
+		assert!(!glob_match(b"file?.txt", b"file10.txt"));
+		assert!(glob_match(b"[a-c]at", b"bat"));
+		assert!(!glob_match(b"[!a-c]at", b"bat"));
+	}
+
+	#[test]
+	fn test_ignores_directories_at_any_depth() {
+		let rules = IgnoreRules::parse("node_modules/\ntmp/");
+
+		assert!(rules.is_ignored(Path::new("node_modules/react/index.js")));
+		assert!(rules.is_ignored(Path::new("packages/app/node_modules/react/index.js")));
+		assert!(rules.is_ignored(Path::new("tmp/cache.bin")));
+		assert!(!rules.is_ignored(Path::new("src/tmp")));
+		assert!(!rules.is_ignored(Path::new("src/index.js")));
+	}
+
+	#[test]
+	fn test_anchored_patterns() {
+		let rules = IgnoreRules::parse("/build\ndocs/*.pdf");
+
+		assert!(rules.is_ignored(Path::new("build/output.bin")));
+		assert!(!rules.is_ignored(Path::new("src/build/output.bin")));
+		assert!(rules.is_ignored(Path::new("docs/manual.pdf")));
+		assert!(!rules.is_ignored(Path::new("docs/nested/manual.pdf")));
+	}
+
+	#[test]
+	fn test_negated_patterns() {
+		let rules = IgnoreRules::parse("# logs\n*.log\n!important.log\n\n");
+
+		assert!(rules.is_ignored(Path::new("debug.log")));
+		assert!(rules.is_ignored(Path::new("nested/debug.log")));
+		assert!(!rules.is_ignored(Path::new("important.log")));
+		assert!(!rules.is_ignored(Path::new("readme.md")));
+	}
+
+	#[test]
+	fn test_cannot_reinclude_file_in_ignored_directory() {
+		let rules = IgnoreRules::parse("cache/\n!cache/keep.txt");
+
+		assert!(rules.is_ignored(Path::new("cache/keep.txt")));
+	}
+}
@@ -0,0 +1,163 @@
+use futures::{StreamExt, TryStreamExt};
+use std::{
+	collections::BTreeMap,
+	path::{Path, PathBuf},
+};
+
+use crate::{relative_to, Filesystem, FlysystemError, Result};
+
+/// How many files are read at once while hashing a tree.
+const CONCURRENCY: usize = 8;
+
+/// A node of the tree being hashed.
+enum Node {
+	File(String),
+	Directory(BTreeMap<String, Self>),
+}
+
+impl Filesystem {
+	/// Compute a deterministic, merkle-style digest of every file under `prefix`.
+	///
+	/// Files are hashed by content (not with the adapter's checksum, which differs between backends), and each directory hashes
+	/// the sorted names and digests of its children, so two trees have the same digest only if they hold the same files at the same paths.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to list the directory or read any of its files.
+	pub async fn tree_checksum(&self, prefix: &Path) -> Result<String> {
+		let prefix = self.normalize(prefix)?;
+		let mut entries = self
+			.adapter
+			.list_contents(&prefix, true)
+			.await
+			.map_err(|error| FlysystemError::listing(&prefix, error))?;
+		entries.sort();
+		entries.dedup();
+
+		let leaves: Vec<(PathBuf, String)> = futures::stream::iter(entries)
+			.map(|entry| {
+				let prefix = &prefix;
+				async move {
+					let path = prefix.join(relative_to(&entry, prefix));
+					let contents = self
+						.adapter
+						.read(&path)
+						.await
+						.map_err(|error| FlysystemError::reading(&path, error))?;
+
+					Ok::<_, FlysystemError>((
+						relative_to(&entry, prefix).to_path_buf(),
+						sha256::digest(contents.data),
+					))
+				}
+			})
+			.buffered(CONCURRENCY)
+			.try_collect()
+			.await?;
+
+		let mut root = BTreeMap::new();
+		for (path, digest) in leaves {
+			insert(&mut root, &path, digest);
+		}
+
+		Ok(hash_directory(&root))
+	}
+}
+
+fn insert(directory: &mut BTreeMap<String, Node>, path: &Path, digest: String) {
+	let mut components = path
+		.iter()
+		.map(|component| component.to_string_lossy().into_owned());
+	let Some(first) = components.next() else {
+		return;
+	};
+	let rest: PathBuf = components.collect();
+
+	if rest.as_os_str().is_empty() {
+		directory.insert(first, Node::File(digest));
+		return;
+	}
+
+	if let Node::Directory(children) = directory
+		.entry(first)
+		.or_insert_with(|| Node::Directory(BTreeMap::new()))
+	{
+		insert(children, &rest, digest);
+	}
+}
+
+/// Hash a directory like git hashes trees: one `<kind> <digest> <name>` line per child, in name order.
+fn hash_directory(directory: &BTreeMap<String, Node>) -> String {
+	let listing: String = directory
+		.iter()
+		.map(|(name, node)| match node {
+			Node::File(digest) => format!("file {digest} {name}\n"),
+			Node::Directory(children) => format!("tree {} {name}\n", hash_directory(children)),
+		})
+		.collect();
+
+	sha256::digest(listing)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_utils::filesystem;
+
+	#[tokio::test]
+	async fn test_tree_checksum_is_deterministic() {
+		let first = filesystem(&[
+			("site/index.html", "<h1>Hello</h1>"),
+			("site/css/app.css", "body {}"),
+			("site/css/vendor/reset.css", "* {}"),
+		])
+		.await;
+		let second = filesystem(&[
+			("site/css/vendor/reset.css", "* {}"),
+			("site/css/app.css", "body {}"),
+			("site/index.html", "<h1>Hello</h1>"),
+		])
+		.await;
+
+		assert_eq!(
+			first.tree_checksum(Path::new("site")).await.unwrap(),
+			second.tree_checksum(Path::new("site/")).await.unwrap()
+		);
+	}
+
+	#[tokio::test]
+	async fn test_tree_checksum_detects_changes() {
+		let original = filesystem(&[
+			("site/index.html", "<h1>Hello</h1>"),
+			("site/app.css", "body {}"),
+		])
+		.await
+		.tree_checksum(Path::new("site"))
+		.await
+		.unwrap();
+
+		for files in [
+			[
+				("site/index.html", "<h1>Hello!</h1>"),
+				("site/app.css", "body {}"),
+			],
+			[
+				("site/index.html", "<h1>Hello</h1>"),
+				("site/main.css", "body {}"),
+			],
+			[
+				("site/index.html", "<h1>Hello</h1>"),
+				("site/css/app.css", "body {}"),
+			],
+		] {
+			assert_ne!(
+				filesystem(&files)
+					.await
+					.tree_checksum(Path::new("site"))
+					.await
+					.unwrap(),
+				original
+			);
+		}
+	}
+}
@@ -9,39 +9,137 @@
 //!
 //! ## Getting Started
 //!
-//! ```rust
+//! ```rust,no_run
+//! # #[cfg(feature = "s3")]
+//! # mod example {
 //! use flysystem::{Filesystem, adapters::{S3Adapter, s3::Config}};
 //! # use std::{env, path::Path};
 //!
 //! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
 //! // instantly swap between storage backends (like S3/Local/FTP)
 //! // by changing the type here 👇👇👇
-//! let mut filesystem = Filesystem::new::<S3Adapter>(Config {
-//!     region: env::var("S3_REGION").ok(),
-//!     bucket: env::var("S3_BUCKET").unwrap(),
-//!     endpoint: env::var("S3_ENDPOINT").unwrap(),
-//!     access_key: env::var("S3_ACCESS_KEY").unwrap(),
-//!     secret_key: env::var("S3_SECRET_KEY").unwrap(),
-//! }).await?;
+//! let filesystem = Filesystem::new::<S3Adapter>(
+//!     Config::builder("my-bucket")
+//!         .region("eu-west-1")
+//!         .credentials(env::var("S3_ACCESS_KEY")?, env::var("S3_SECRET_KEY")?)
+//!         .build()?,
+//! ).await?;
 //!
-//! filesystem.write(Path::new("my-first-file.txt"), "Hello, world!").await?;
+//! filesystem.write(Path::new("my-first-file.txt"), b"Hello, world!").await?;
 //! # Ok(())
 //! # }
+//! # }
 //! ```
 
-use adapters::{Adapter, AdapterInit};
-use contents::Contents;
+use adapters::{
+	Adapter, AdapterInit, IdempotentAdapter, PathPrefixedAdapter, ReadOnlyAdapter,
+	RevisionedAdapter,
+};
+pub use assets::{FingerprintedAsset, IMMUTABLE_CACHE_CONTROL};
+#[cfg(feature = "temp-files")]
+pub use atomic::TempFile;
+pub use batch::{BatchMode, BatchReport, Fingerprint, SyncOptions};
+use bytes::BufMut;
+pub use cdn::CdnPurger;
+pub use checkpoint::{ListingCheckpoint, ListingItem};
+pub use checksums::{ChecksumAlgorithm, Checksums};
+pub use codec::Codec;
+#[cfg(feature = "config")]
+pub use config::AdapterConfig;
+pub use contents::Contents;
+#[cfg(feature = "deadlines")]
+pub use deadline::Deadlined;
+pub use describe::FilesystemInfo;
+pub use error::{FlysystemError, Result};
+pub use events::{EventListener, FilesystemEvent};
+pub use find::ListFilter;
+use futures::{io::AsyncRead, AsyncReadExt, Stream, StreamExt, TryStreamExt};
+pub use handle::{DirHandle, FileHandle};
+pub use hasher::{Hasher, Sha256};
+#[cfg(feature = "http")]
+pub use http::{status_code, HttpError};
+pub use ignore::IgnoreRules;
 use mime::Mime;
+use normalizer::normalize;
+pub use path_model::PathModel;
+pub use precondition::Precondition;
+#[cfg(feature = "probe")]
+pub use probe::{MediaInfo, MediaKind};
+use protection::Protection;
+pub use protection::PROTECTED_METADATA;
+pub use prune::PruneOptions;
+pub use public_url::{PrivateFiles, UrlStrategy};
+pub use redaction::{Redacted, Redactor, REDACTED};
+pub use sanitizer::{FilenameSanitizer, SanitizeFilename, UnicodeHandling};
+pub use scanner::{ScanResult, Scanner};
 use std::{
-	io::{Error, ErrorKind, Result},
+	collections::HashMap,
+	future::Future,
+	io::{Error, ErrorKind},
 	path::{Path, PathBuf},
-	time::SystemTime,
+	sync::Arc,
+	time::{Duration, SystemTime},
 };
-use trait_object_hackyness::AdapterObject;
+use trait_object_hackyness::{AdapterObject, BoxedAdapter};
+pub use transaction::{Transaction, TransactionError};
+pub use upload::StoredFile;
+use url::Url;
+pub use versioning::{FileVersion, Snapshot};
 
 pub mod adapters;
+mod assets;
+mod atomic;
+mod batch;
+#[cfg(feature = "bench")]
+pub mod bench;
+pub mod cdn;
+mod checkpoint;
+mod checksums;
+pub mod clock;
+mod codec;
+#[cfg(feature = "config")]
+mod config;
 mod contents;
+#[cfg(feature = "deadlines")]
+pub mod deadline;
+mod describe;
+#[cfg(feature = "http")]
+mod download;
+mod error;
+mod events;
+mod find;
+mod handle;
+mod hasher;
+#[cfg(feature = "http")]
+mod http;
+mod ignore;
+mod integrity;
+mod normalizer;
+mod path_model;
+#[cfg(feature = "php")]
+pub mod php;
+mod precondition;
+pub mod prelude;
+#[cfg(feature = "probe")]
+mod probe;
+mod protection;
+mod prune;
+pub mod public_url;
+mod redaction;
+pub mod replication;
+mod sanitizer;
+pub mod scanner;
+#[cfg(feature = "sidecar")]
+pub mod sidecar;
+pub mod sync;
+#[cfg(test)]
+mod test_utils;
 mod trait_object_hackyness;
+mod transaction;
+mod upload;
+mod versioning;
+#[cfg(feature = "webhooks")]
+pub mod webhook;
 
 /// The visibility of a file.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -50,48 +148,444 @@ pub enum Visibility {
 	Private,
 }
 
+/// The visibility a copied or moved file ends up with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CopyVisibility {
+	/// Whatever the adapter gives new files (S3 resets ACLs to the bucket's default, local copies keep their permissions).
+	#[default]
+	AdapterDefault,
+	/// The visibility of the source file.
+	Preserve,
+	/// The given visibility.
+	Set(Visibility),
+}
+
+/// The outcome of setting the visibility of every file under a prefix.
+#[derive(Debug)]
+pub struct VisibilityReport {
+	/// How many files were updated.
+	pub updated: usize,
+	/// The files that couldn't be updated, along with their errors.
+	pub failed: Vec<(PathBuf, Error)>,
+}
+
+/// Options for writing a file, overriding what the adapter would pick on its own.
+///
+/// Adapters that can't store the MIME type, cache control or metadata of files (like the local one) ignore them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriteOptions {
+	/// The visibility of the file, instead of the adapter's default.
+	pub visibility: Option<Visibility>,
+	/// The MIME type to serve the file with, instead of guessing it from the extension.
+	pub content_type: Option<Mime>,
+	/// The `Cache-Control` header to serve the file with.
+	pub cache_control: Option<String>,
+	/// Custom metadata stored alongside the file (like `x-amz-meta-*` headers on S3).
+	pub metadata: HashMap<String, String>,
+	/// The storage class of the file on object stores (like `STANDARD_IA` on S3), instead of the adapter's default.
+	pub storage_class: Option<String>,
+	/// A key identifying the operation, so decorators like [`IdempotentAdapter`](adapters::IdempotentAdapter)
+	/// can skip it when it's replayed (after a crash, or on retry). Adapters storing files ignore it.
+	pub idempotency_key: Option<String>,
+}
+
+/// An entry of a directory listing, along with the metadata the adapter returned for it.
+///
+/// Metadata the adapter doesn't return while listing (like the visibility of S3 objects) is `None`, and so is the metadata of directories.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageEntry {
+	/// The path of the entry, like the ones returned by [`Filesystem::list_contents`].
+	pub path: PathBuf,
+	/// Whether the entry is a directory (or a common prefix, on object stores).
+	pub is_dir: bool,
+	/// The size of the file.
+	pub size: Option<u64>,
+	/// When the file was last modified.
+	pub last_modified: Option<SystemTime>,
+	/// The MIME type of the file.
+	pub mime: Option<Mime>,
+	/// The visibility of the file.
+	pub visibility: Option<Visibility>,
+}
+
+impl StorageEntry {
+	/// An entry for a directory, which carries no metadata.
+	#[must_use]
+	pub const fn directory(path: PathBuf) -> Self {
+		Self {
+			path,
+			is_dir: true,
+			size: None,
+			last_modified: None,
+			mime: None,
+			visibility: None,
+		}
+	}
+}
+
+/// The metadata of a directory, from [`Filesystem::directory_stat`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirectoryStat {
+	/// When the directory was last modified, or `None` for empty directories on storages that can't tell.
+	pub last_modified: Option<SystemTime>,
+	/// The number of files and subdirectories directly inside the directory.
+	pub children: usize,
+}
+
+/// The metadata of a file, from [`Filesystem::stat`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStat {
+	pub size: u64,
+	pub last_modified: SystemTime,
+	pub mime_type: Mime,
+	/// The revision of the file, if it was written through [`Filesystem::with_revisions`] on an adapter storing metadata.
+	pub revision: Option<u64>,
+}
+
 /// The type of resource.
 enum Resource {
 	File,
 	Directory,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 /// Abstraction over a filesystem.
+///
+/// Every operation takes `&self`, and clones share the same adapter, so a filesystem can be used concurrently (like from the handlers of a web server).
 pub struct Filesystem {
-	adapter: Box<dyn AdapterObject>,
+	adapter: Arc<dyn AdapterObject>,
+	scanner: Option<Arc<dyn Scanner>>,
+	purger: Option<Arc<dyn CdnPurger>>,
+	url_strategy: Option<Arc<dyn UrlStrategy>>,
+	listeners: Vec<Arc<dyn EventListener>>,
+	sorted_listings: bool,
+	private_files: PrivateFiles,
+	trusted_paths: bool,
+	protection: Protection,
 }
 
 impl Filesystem {
+	/// Create a new filesystem instance, initializing the adapter from its config.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to initialize.
 	pub async fn new<T: AdapterInit>(config: T::Config) -> std::result::Result<Self, T::Error> {
-		Ok(Self {
-			adapter: Box::new(T::new(config).await?),
-		})
+		Ok(Self::from_adapter(T::new(config).await?))
 	}
 
 	/// Create a new filesystem instance.
 	pub fn from_adapter<T: Adapter + 'static>(adapter: T) -> Self {
 		Self {
-			adapter: Box::new(adapter),
+			adapter: Arc::new(adapter),
+			scanner: None,
+			purger: None,
+			url_strategy: None,
+			listeners: Vec::new(),
+			sorted_listings: true,
+			private_files: PrivateFiles::default(),
+			trusted_paths: false,
+			protection: Protection::default(),
+		}
+	}
+
+	/// Scan every file with the given virus scanner before writing it, rejecting infected ones.
+	#[must_use]
+	pub fn with_scanner<S: Scanner + 'static>(mut self, scanner: S) -> Self {
+		self.scanner = Some(Arc::new(scanner));
+		self
+	}
+
+	/// Purge files from the given CDN when they're overwritten or deleted.
+	#[must_use]
+	pub fn with_cdn_purger<P: CdnPurger + 'static>(mut self, purger: P) -> Self {
+		self.purger = Some(Arc::new(purger));
+		self
+	}
+
+	/// Generate public URLs with the given strategy.
+	#[must_use]
+	pub fn with_url_strategy<S: UrlStrategy + 'static>(mut self, strategy: S) -> Self {
+		self.url_strategy = Some(Arc::new(strategy));
+		self
+	}
+
+	/// Check whether changing `path` will leave a stale copy on the CDN, so it must be purged afterwards.
+	async fn needs_purge(&self, path: &Path) -> Result<bool> {
+		match self.purger {
+			Some(_) => Ok(self.adapter.file_exists(path).await?),
+			None => Ok(false),
+		}
+	}
+
+	async fn purge(&self, path: &Path) -> Result<()> {
+		match &self.purger {
+			Some(purger) => Ok(purger.purge(path).await?),
+			None => Ok(()),
+		}
+	}
+
+	/// Return listings in the order the adapter produces them, instead of sorting them by path.
+	///
+	/// This saves sorting huge listings, but the order then differs between adapters (the memory adapter's is arbitrary,
+	/// S3's is lexicographic and the local one's depends on the OS).
+	#[must_use]
+	pub const fn with_unsorted_listings(mut self) -> Self {
+		self.sorted_listings = false;
+		self
+	}
+
+	/// Hand paths to the adapter without resolving `..` segments or rejecting the ones escaping the root (or containing control characters).
+	///
+	/// Only use this when every path comes from trusted code, since adapters like the local one would follow them outside of their root.
+	#[must_use]
+	pub const fn with_trusted_paths(mut self) -> Self {
+		self.trusted_paths = true;
+		self
+	}
+
+	/// Normalize a path before handing it to the adapter, rejecting the ones escaping the root unless paths are trusted.
+	fn normalize(&self, path: &Path) -> Result<PathBuf> {
+		if self.trusted_paths {
+			return Ok(normalize(path));
+		}
+
+		normalizer::normalize_untrusted(path)
+	}
+
+	/// Choose what [`Filesystem::public_url`] does for private files, instead of refusing to generate their URL.
+	#[must_use]
+	pub const fn with_private_files(mut self, private_files: PrivateFiles) -> Self {
+		self.private_files = private_files;
+		self
+	}
+
+	/// Notify the given listener of every change made through this filesystem.
+	#[must_use]
+	pub fn with_listener<L: EventListener + 'static>(mut self, listener: L) -> Self {
+		self.listeners.push(Arc::new(listener));
+		self
+	}
+
+	/// Only allow reading from this filesystem, failing every change with a `PermissionDenied` error.
+	#[must_use]
+	pub fn read_only(self) -> Self {
+		Self {
+			adapter: Arc::new(ReadOnlyAdapter::new(BoxedAdapter(self.adapter))),
+			..self
+		}
+	}
+
+	/// Confine this filesystem to the directory at `prefix`, so every path is relative to it and can't escape it with `..`.
+	#[must_use]
+	pub fn scoped(self, prefix: impl Into<PathBuf>) -> Self {
+		Self {
+			adapter: Arc::new(PathPrefixedAdapter::new(BoxedAdapter(self.adapter), prefix)),
+			..self
+		}
+	}
+
+	/// Skip writes whose [idempotency key](WriteOptions::idempotency_key) was already applied, so replaying them is safe.
+	#[must_use]
+	pub fn idempotent(self) -> Self {
+		Self {
+			adapter: Arc::new(IdempotentAdapter::new(BoxedAdapter(self.adapter))),
+			..self
+		}
+	}
+
+	/// Number the revisions of files in their metadata, so [`Filesystem::stat`] can be used for optimistic concurrency checks.
+	/// See [`RevisionedAdapter`](adapters::RevisionedAdapter).
+	#[must_use]
+	pub fn with_revisions(self) -> Self {
+		Self {
+			adapter: Arc::new(RevisionedAdapter::new(BoxedAdapter(self.adapter))),
+			..self
+		}
+	}
+
+	/// Retry the operations failing with a transient error (like a dropped connection) according to `policy`.
+	/// See [`RetryAdapter`](adapters::RetryAdapter) for which operations are retried.
+	#[cfg(feature = "retry")]
+	#[must_use]
+	pub fn with_retries(self, policy: adapters::RetryPolicy) -> Self {
+		Self {
+			adapter: Arc::new(adapters::RetryAdapter::new(
+				BoxedAdapter(self.adapter),
+				policy,
+			)),
+			..self
+		}
+	}
+
+	/// Call `callback` with every event of the given [name](FilesystemEvent::name) (like `"write"` or `"move_failed"`).
+	#[must_use]
+	pub fn on<F>(self, name: &'static str, callback: F) -> Self
+	where
+		F: Fn(&FilesystemEvent) + Send + Sync + 'static,
+	{
+		self.with_listener(events::CallbackListener { name, callback })
+	}
+
+	/// Encode the contents of every file with the given codec before it reaches the adapter, and decode them once read.
+	/// See [`CodecAdapter`](adapters::CodecAdapter) for what isn't encoded.
+	#[must_use]
+	pub fn with_codec<C: Codec + 'static>(self, codec: C) -> Self {
+		Self {
+			adapter: Arc::new(adapters::CodecAdapter::new(
+				BoxedAdapter(self.adapter),
+				codec,
+			)),
+			..self
+		}
+	}
+
+	/// Compress the contents of every file with the given algorithm before it reaches the adapter, storing them under their own path.
+	/// See [`CompressedAdapter`](adapters::CompressedAdapter) for storing them under another extension instead.
+	#[cfg(feature = "compression")]
+	#[must_use]
+	pub fn compressed(self, compression: adapters::Compression) -> Self {
+		Self {
+			adapter: Arc::new(adapters::CompressedAdapter::new(
+				BoxedAdapter(self.adapter),
+				compression,
+			)),
+			..self
+		}
+	}
+
+	/// Encrypt the contents of every file with the given cipher (or the key the given [`KeyProvider`](adapters::KeyProvider)
+	/// picks for it) before it reaches the adapter. See [`EncryptedAdapter`](adapters::EncryptedAdapter) for what isn't encrypted.
+	#[cfg(feature = "encryption")]
+	#[must_use]
+	pub fn encrypted<K: adapters::KeyProvider + 'static>(self, keys: K) -> Self {
+		Self {
+			adapter: Arc::new(adapters::EncryptedAdapter::new(
+				BoxedAdapter(self.adapter),
+				keys,
+			)),
+			..self
+		}
+	}
+
+	/// Trace every adapter call in a `tracing` span. See [`InstrumentedAdapter`](adapters::InstrumentedAdapter) for the recorded fields.
+	#[cfg(feature = "instrumentation")]
+	#[must_use]
+	pub fn instrumented(self) -> Self {
+		Self {
+			adapter: Arc::new(adapters::InstrumentedAdapter::new(BoxedAdapter(
+				self.adapter,
+			))),
+			..self
+		}
+	}
+
+	/// Trace every adapter call in a `tracing` span, and report it to the given metrics recorder.
+	#[cfg(feature = "instrumentation")]
+	#[must_use]
+	pub fn with_metrics<R: adapters::MetricsRecorder + 'static>(self, recorder: R) -> Self {
+		Self {
+			adapter: Arc::new(
+				adapters::InstrumentedAdapter::new(BoxedAdapter(self.adapter))
+					.with_recorder(recorder),
+			),
+			..self
 		}
 	}
 
+	async fn emit_before(&self, event: FilesystemEvent) {
+		for listener in &self.listeners {
+			listener.before(&event).await;
+		}
+	}
+
+	async fn emit(&self, event: FilesystemEvent) {
+		for listener in &self.listeners {
+			listener.handle(&event).await;
+		}
+	}
+
+	/// Emit the event built by `failed` if the adapter call failed, passing the result through.
+	async fn observe<T>(
+		&self,
+		result: std::io::Result<T>,
+		failed: impl FnOnce(String) -> FilesystemEvent + Send,
+	) -> std::io::Result<T> {
+		if let Err(error) = &result {
+			self.emit(failed(error.to_string())).await;
+		}
+
+		result
+	}
+
+	/// Run the adapter call writing the (normalized) file at `path`, emitting its events and purging the version it replaced from the CDN.
+	///
+	/// The previous version is only looked up when `may_overwrite` is set, so writes that can't replace a file skip it.
+	async fn write_file<F>(
+		&self,
+		path: &Path,
+		may_overwrite: bool,
+		write: impl FnOnce() -> F + Send,
+	) -> Result<()>
+	where
+		F: Future<Output = std::io::Result<()>> + Send,
+	{
+		let overwritten = may_overwrite && self.needs_purge(path).await?;
+		self.emit_before(FilesystemEvent::WriteOccurred {
+			path: path.to_path_buf(),
+		})
+		.await;
+		self.observe(write().await, |error| FilesystemEvent::WriteFailed {
+			path: path.to_path_buf(),
+			error,
+		})
+		.await
+		.map_err(|error| FlysystemError::writing(path, error))?;
+
+		if overwritten {
+			self.purge(path).await?;
+		}
+
+		self.emit(FilesystemEvent::WriteOccurred {
+			path: path.to_path_buf(),
+		})
+		.await;
+		Ok(())
+	}
+
+	/// Wait for the background work of the adapter stack and the listeners to complete, like before the process exits.
+	///
+	/// Clones share the same adapter, so this flushes the work started through any of them.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to flush its pending operations.
+	pub async fn shutdown(&self) -> Result<()> {
+		self.adapter.flush().await?;
+
+		for listener in &self.listeners {
+			listener.flush().await;
+		}
+
+		Ok(())
+	}
+
 	/// Check if a file exists.
 	///
 	/// # Errors
 	///
 	/// This function will return an error if the adapter fails to check if the file exists.
 	pub async fn file_exists(&self, path: &Path) -> Result<bool> {
-		self.adapter.file_exists(path).await
+		Ok(self.adapter.file_exists(&self.normalize(path)?).await?)
 	}
 
-	/// Check if a directory exists.
+	/// Check if a directory exists. On [flat](PathModel::FlatKeys) adapters, it does if any file is under it.
 	///
 	/// # Errors
 	///
 	/// This function will return an error if the adapter fails to check if the directory exists.
 	pub async fn directory_exists(&self, path: &Path) -> Result<bool> {
-		self.adapter.directory_exists(path).await
+		Ok(self.has_directory(&self.normalize(path)?).await?)
 	}
 
 	/// Check if a file or directory exists.
@@ -100,11 +594,9 @@ impl Filesystem {
 	///
 	/// This function will return an error if the adapter fails to check if the file or directory exists.
 	pub async fn has(&self, path: &Path) -> Result<bool> {
-		let (file_exists, directory_exists) = futures::future::join(
-			self.adapter.file_exists(path),
-			self.adapter.directory_exists(path),
-		)
-		.await;
+		let path = self.normalize(path)?;
+		let (file_exists, directory_exists) =
+			futures::future::join(self.adapter.file_exists(&path), self.has_directory(&path)).await;
 
 		Ok(file_exists? || directory_exists?)
 	}
@@ -113,9 +605,250 @@ impl Filesystem {
 	///
 	/// # Errors
 	///
-	/// This function will return an error if the adapter fails to write the file.
-	pub async fn write(&mut self, path: &Path, contents: &[u8]) -> Result<()> {
-		self.adapter.write(path, contents).await
+	/// This function will return an error if the adapter fails to write the file, if the configured scanner flags it as infected,
+	/// or if the configured CDN purger fails to invalidate the previous version (in which case the file has already been written).
+	pub async fn write(&self, path: &Path, contents: &[u8]) -> Result<()> {
+		self.write_with(path, contents, WriteOptions::default())
+			.await
+	}
+
+	/// Write a file with the given visibility, MIME type, cache control and metadata.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to write the file, if the configured scanner flags it as infected,
+	/// or if the configured CDN purger fails to invalidate the previous version (in which case the file has already been written).
+	pub async fn write_with(
+		&self,
+		path: &Path,
+		contents: &[u8],
+		options: WriteOptions,
+	) -> Result<()> {
+		self.ensure_clean(path, contents).await?;
+
+		let path = self.normalize(path)?;
+		self.write_file(&path, true, || {
+			self.adapter.write_with(&path, contents, &options)
+		})
+		.await
+	}
+
+	/// Fail if the configured scanner flags the contents about to be written to `path` as infected.
+	async fn ensure_clean(&self, path: &Path, contents: &[u8]) -> Result<()> {
+		if let Some(scanner) = &self.scanner {
+			if let ScanResult::Infected(signature) = scanner.scan(contents).await? {
+				return Err(FlysystemError::UnableToWriteFile {
+					path: self.normalize(path)?,
+					source: Error::new(
+						ErrorKind::InvalidData,
+						format!("The file was rejected by the virus scanner ({signature})."),
+					),
+				});
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Overwrite part of a file starting at `offset`, growing it if needed, like when applying a binary patch.
+	///
+	/// Adapters that can't update files in place (see [`Filesystem::native_range_writes`]) read and rewrite the whole file instead.
+	/// When a virus scanner is configured the whole file has to be scanned, so it's always rewritten.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to update the file, if the configured scanner flags it as infected,
+	/// or if the configured CDN purger fails to invalidate the previous version (in which case the file has already been written).
+	pub async fn write_range(&self, path: &Path, offset: u64, data: &[u8]) -> Result<()> {
+		let path = self.normalize(path)?;
+
+		if self.scanner.is_some() {
+			let mut contents = match self.adapter.read(&path).await {
+				Ok(contents) => contents.data,
+				Err(error) if error.kind() == ErrorKind::NotFound => Vec::new(),
+				Err(error) => return Err(FlysystemError::reading(&path, error)),
+			};
+			adapters::splice(&mut contents, offset, data)
+				.map_err(|error| FlysystemError::writing(&path, error))?;
+
+			return self.write(&path, &contents).await;
+		}
+
+		self.write_file(&path, true, || {
+			self.adapter.write_range(&path, offset, data)
+		})
+		.await
+	}
+
+	/// Add `data` to the end of a file, creating it if it doesn't exist, like when writing logs.
+	///
+	/// The local and memory adapters append in place, while others (like S3, whose objects are immutable) read and rewrite the whole file.
+	/// When a virus scanner is configured the whole file has to be scanned, so it's always rewritten.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to update the file, if the configured scanner flags it as infected,
+	/// or if the configured CDN purger fails to invalidate the previous version (in which case the file has already been written).
+	pub async fn append(&self, path: &Path, data: &[u8]) -> Result<()> {
+		let path = self.normalize(path)?;
+
+		if self.scanner.is_some() {
+			let mut contents = match self.adapter.read(&path).await {
+				Ok(contents) => contents.data,
+				Err(error) if error.kind() == ErrorKind::NotFound => Vec::new(),
+				Err(error) => return Err(FlysystemError::reading(&path, error)),
+			};
+			contents.extend_from_slice(data);
+
+			return self.write(&path, &contents).await;
+		}
+
+		self.write_file(&path, true, || self.adapter.append(&path, data))
+			.await
+	}
+
+	/// Check if [`Filesystem::write_range`] updates files in place with this adapter, instead of rewriting them whole.
+	#[must_use]
+	pub fn native_range_writes(&self) -> bool {
+		self.adapter.native_range_writes()
+	}
+
+	/// Write a file from a reader, without buffering it in memory if the adapter supports streaming.
+	///
+	/// When a virus scanner is configured the file has to be scanned as a whole, so it's buffered before being written.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the reader or the adapter fails, if the configured scanner flags the file as infected,
+	/// or if the configured CDN purger fails to invalidate the previous version (in which case the file has already been written).
+	pub async fn write_stream(
+		&self,
+		path: &Path,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		if self.scanner.is_some() {
+			let mut contents = Vec::new();
+			reader
+				.read_to_end(&mut contents)
+				.await
+				.map_err(|error| FlysystemError::writing(path, error))?;
+
+			return self.write(path, &contents).await;
+		}
+
+		let path = self.normalize(path)?;
+		self.write_file(&path, true, || self.adapter.write_stream(&path, reader))
+			.await
+	}
+
+	/// Scan an existing file with the configured virus scanner.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if no scanner is configured, or if the adapter fails to read the file.
+	pub async fn scan(&self, path: &Path) -> Result<ScanResult> {
+		let Some(scanner) = &self.scanner else {
+			return Err(
+				Error::new(ErrorKind::Unsupported, "No virus scanner is configured.").into(),
+			);
+		};
+
+		let path = self.normalize(path)?;
+		let contents = self
+			.adapter
+			.read(&path)
+			.await
+			.map_err(|error| FlysystemError::reading(&path, error))?;
+
+		Ok(scanner.scan(&contents).await?)
+	}
+
+	/// Get the public URL of a file, using the configured URL strategy or, without one, the adapter's [`PublicUrlGenerator`](adapters::PublicUrlGenerator).
+	///
+	/// Private files are refused with a [`FlysystemError::FileNotPublic`] error, unless [`Filesystem::with_private_files`] says otherwise.
+	///
+	/// # Errors
+	///
+	/// This function will return a [`FlysystemError::PublicUrlNotSupported`] error if no URL strategy is configured and the adapter can't generate public URLs,
+	/// a [`FlysystemError::FileNotPublic`] error if the file is private, or an error if getting its visibility or generating the URL fails.
+	pub async fn public_url(&self, path: &Path) -> Result<Url> {
+		let path = self.normalize(path)?;
+		let url = match &self.url_strategy {
+			Some(strategy) => strategy.public_url(self, &path).await?,
+			None => self
+				.adapter
+				.public_url(&path)
+				.await
+				.map_err(|error| FlysystemError::generating_public_url(&path, error))?,
+		};
+
+		if self.private_files == PrivateFiles::Allow {
+			return Ok(url);
+		}
+
+		// only looked up once the URL could be generated, so unsupported adapters fail without a request
+		match self.adapter.visibility(&path).await {
+			Ok(Visibility::Private) => match self.private_files {
+				PrivateFiles::TemporaryUrl(expires_in) => {
+					self.temporary_url(&path, expires_in).await
+				},
+				_ => Err(FlysystemError::FileNotPublic { path }),
+			},
+			Err(error) if !matches!(error.kind(), ErrorKind::NotFound | ErrorKind::Unsupported) => {
+				Err(FlysystemError::retrieving_metadata(&path, error))
+			},
+			_ => Ok(url),
+		}
+	}
+
+	/// Get a URL granting access to a file until `expires_in` elapses, using the adapter's [`TemporaryUrlGenerator`](adapters::TemporaryUrlGenerator).
+	///
+	/// # Errors
+	///
+	/// This function will return a [`FlysystemError::TemporaryUrlNotSupported`] error if the adapter can't generate temporary URLs,
+	/// or an error if generating the URL fails.
+	pub async fn temporary_url(&self, path: &Path, expires_in: Duration) -> Result<Url> {
+		let path = self.normalize(path)?;
+
+		self.adapter
+			.temporary_url(&path, expires_in)
+			.await
+			.map_err(|error| FlysystemError::generating_temporary_url(&path, error))
+	}
+
+	/// Get a URL granting access to a file until `expires_in` elapses, overriding the headers it's served with,
+	/// like [`TemporaryUrlOptions::attachment`](adapters::TemporaryUrlOptions::attachment) to download it with a friendly name.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter can't generate temporary URLs (or override their headers), or if generating the URL fails.
+	pub async fn temporary_url_with(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+		options: &adapters::TemporaryUrlOptions,
+	) -> Result<Url> {
+		let path = self.normalize(path)?;
+
+		self.adapter
+			.temporary_url_with(&path, expires_in, options)
+			.await
+			.map_err(|error| FlysystemError::generating_temporary_url(&path, error))
+	}
+
+	/// Recover the path of a file from its public URL, validating its signature when the URL strategy signs them.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if no URL strategy is configured, or if the URL wasn't generated by it (or its signature is invalid or expired).
+	pub fn path_for_url(&self, url: &Url) -> Result<PathBuf> {
+		let Some(strategy) = &self.url_strategy else {
+			return Err(
+				Error::new(ErrorKind::Unsupported, "No URL strategy is configured.").into(),
+			);
+		};
+
+		self.normalize(&strategy.path_for_url(url)?)
 	}
 
 	/// Get the contents of a file.
@@ -123,65 +856,590 @@ impl Filesystem {
 	/// # Errors
 	///
 	/// This function will return an error if the adapter fails to read the file.
-	pub async fn read<R: TryFrom<Contents>>(&mut self, path: &Path) -> Result<R> {
-		self.adapter.read(path).await.and_then(|c| {
-			c.try_into()
-				.map_err(|_| Error::new(ErrorKind::InvalidData, "Could not decode contents."))
-		})
+	pub async fn read<R: TryFrom<Contents>>(&self, path: &Path) -> Result<R> {
+		let path = self.normalize(path)?;
+		let contents = self
+			.adapter
+			.read(&path)
+			.await
+			.map_err(|error| FlysystemError::reading(&path, error))?;
+
+		contents
+			.try_into()
+			.map_err(|_| FlysystemError::UnableToReadFile {
+				path,
+				source: Error::new(ErrorKind::InvalidData, "Could not decode contents."),
+			})
+	}
+
+	/// Get the contents of a file, or `None` if it doesn't exist.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to read a file that exists.
+	pub async fn try_read(&self, path: &Path) -> Result<Option<Contents>> {
+		let path = self.normalize(path)?;
+
+		match self.adapter.read(&path).await {
+			Ok(contents) => Ok(Some(contents)),
+			Err(error) => match FlysystemError::reading(&path, error) {
+				FlysystemError::FileNotFound { .. } => Ok(None),
+				error => Err(error),
+			},
+		}
+	}
+
+	/// Open a file for reading, streaming it instead of buffering it in memory if the adapter supports it.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to open the file.
+	pub async fn read_stream(&self, path: &Path) -> Result<impl AsyncRead + Send + Unpin> {
+		let path = self.normalize(path)?;
+
+		self.adapter
+			.read_stream(&path)
+			.await
+			.map_err(|error| FlysystemError::reading(&path, error))
+	}
+
+	/// Read a file into a caller-provided buffer, so hot loops can reuse allocations.
+	///
+	/// The contents are appended to the buffer, so clear it between reads if needed.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to read the file.
+	pub async fn read_into(&self, path: &Path, buf: &mut Vec<u8>) -> Result<()> {
+		self.read_into_buf(path, buf).await
+	}
+
+	/// Read a file into any [`BufMut`], appending to its existing contents.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to read the file, or if the buffer doesn't have enough capacity left to hold it.
+	pub async fn read_into_buf<B: BufMut + Send>(&self, path: &Path, buf: &mut B) -> Result<()> {
+		let path = self.normalize(path)?;
+
+		self.adapter
+			.read_into(&path, buf)
+			.await
+			.map_err(|error| FlysystemError::reading(&path, error))
+	}
+
+	/// Read `len` bytes of a file starting at `offset`, or fewer if the file ends first.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to read the file.
+	pub async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		let path = self.normalize(path)?;
+
+		self.adapter
+			.read_range(&path, offset, len)
+			.await
+			.map_err(|error| FlysystemError::reading(&path, error))
+	}
+
+	/// Read many files concurrently, with at most `concurrency` reads in flight at once.
+	///
+	/// Results are yielded as soon as each read completes, so they may not be in the same order as `paths`.
+	pub fn read_many<'a, I>(
+		&'a self,
+		paths: I,
+		concurrency: usize,
+	) -> impl Stream<Item = (PathBuf, Result<Contents>)> + Send + 'a
+	where
+		I: IntoIterator<Item = PathBuf>,
+		I::IntoIter: Send + 'a,
+	{
+		futures::stream::iter(paths)
+			.map(move |path| async move {
+				let contents = async {
+					let normalized = self.normalize(&path)?;
+
+					self.adapter
+						.read(&normalized)
+						.await
+						.map_err(|error| FlysystemError::reading(&normalized, error))
+				}
+				.await;
+
+				(path, contents)
+			})
+			.buffer_unordered(concurrency.max(1))
 	}
 
 	/// Delete a file.
 	///
 	/// # Errors
 	///
-	/// This function will return an error if the adapter fails to delete the file or directory.
-	pub async fn delete(&mut self, path: &Path) -> Result<()> {
-		self.adapter.delete(path).await
+	/// This function will return an error if the file is [protected](Filesystem::protect), if the adapter fails to delete the file or directory,
+	/// or if the configured CDN purger fails to invalidate it.
+	pub async fn delete(&self, path: &Path) -> Result<()> {
+		let path = self.normalize(path)?;
+		self.ensure_deletable(&path).await?;
+		self.emit_before(FilesystemEvent::FileDeleted { path: path.clone() })
+			.await;
+		self.observe(self.adapter.delete(&path).await, |error| {
+			FilesystemEvent::DeleteFailed {
+				path: path.clone(),
+				error,
+			}
+		})
+		.await
+		.map_err(|error| FlysystemError::deleting(&path, error))?;
+		self.purge(&path).await?;
+
+		self.emit(FilesystemEvent::FileDeleted { path }).await;
+		Ok(())
 	}
 
 	/// Delete a directory.
 	///
 	/// # Errors
 	///
-	/// This function will return an error if the adapter fails to delete the directory.
-	pub async fn delete_directory(&mut self, path: &Path) -> Result<()> {
-		self.adapter.delete_directory(path).await
+	/// This function will return an error if the directory is (or contains) a [protected](Filesystem::protect) path,
+	/// or if the adapter fails to delete the directory.
+	pub async fn delete_directory(&self, path: &Path) -> Result<()> {
+		let path = self.normalize(path)?;
+		self.ensure_directory_deletable(&path)?;
+		self.ensure_deleted_directory(&path)
+			.await
+			.map_err(|error| FlysystemError::deleting_directory(&path, error))?;
+		self.emit_before(FilesystemEvent::DirectoryDeleted { path: path.clone() })
+			.await;
+		self.observe(self.adapter.delete_directory(&path).await, |error| {
+			FilesystemEvent::DeleteFailed {
+				path: path.clone(),
+				error,
+			}
+		})
+		.await
+		.map_err(|error| FlysystemError::deleting_directory(&path, error))?;
+
+		self.emit(FilesystemEvent::DirectoryDeleted { path }).await;
+		Ok(())
 	}
 
 	/// Create a directory.
 	///
+	/// On [flat](PathModel::FlatKeys) adapters, the directory only exists right away if the adapter keeps directory markers,
+	/// and otherwise once a file is written under it.
+	///
 	/// # Errors
 	///
 	/// This function will return an error if the adapter fails to create the directory.
-	pub async fn create_directory(&mut self, path: &Path) -> Result<()> {
-		self.adapter.create_directory(path).await
+	pub async fn create_directory(&self, path: &Path) -> Result<()> {
+		let path = self.normalize(path)?;
+
+		self.emit_before(FilesystemEvent::DirectoryCreated { path: path.clone() })
+			.await;
+		self.observe(self.adapter.create_directory(&path).await, |error| {
+			FilesystemEvent::DirectoryCreationFailed {
+				path: path.clone(),
+				error,
+			}
+		})
+		.await
+		.map_err(|error| FlysystemError::creating_directory(&path, error))?;
+
+		self.emit(FilesystemEvent::DirectoryCreated { path }).await;
+		Ok(())
 	}
 
-	/// Get a list of files in a directory (optionally recursively).
+	/// Get a list of files in a directory (optionally recursively), sorted by path unless [`Filesystem::with_unsorted_listings`] was used.
 	///
 	/// # Errors
 	///
 	/// This function will return an error if the adapter fails to list the contents of the directory.
 	pub async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
-		self.adapter.list_contents(path, deep).await
+		let path = self.normalize(path)?;
+
+		let mut contents = self
+			.adapter
+			.list_contents(&path, deep)
+			.await
+			.map_err(|error| FlysystemError::listing(&path, error))?;
+		self.ensure_listed_directory(&path, contents.is_empty())
+			.await
+			.map_err(|error| FlysystemError::listing(&path, error))?;
+
+		if self.sorted_listings {
+			contents.sort_unstable();
+		}
+
+		Ok(contents)
+	}
+
+	/// Lazily list the files in a directory (optionally recursively), so huge directories never have to fit in memory.
+	///
+	/// Adapters listing in pages (like S3) only fetch the next page once the previous one was consumed.
+	/// Unlike [`Filesystem::list_contents`], entries aren't sorted.
+	pub fn list_stream(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> impl Stream<Item = Result<PathBuf>> + Send + '_ {
+		let path = match self.normalize(path) {
+			Ok(path) => path,
+			Err(error) => return futures::stream::once(async { Err(error) }).boxed(),
+		};
+
+		self.adapter
+			.list_stream(&path, deep)
+			.map_err(move |error| FlysystemError::listing(&path, error))
+			.boxed()
+	}
+
+	/// Get a list of files in a directory (optionally recursively), along with their size, last modified time, MIME type and visibility.
+	/// Like [`Filesystem::list_contents`], entries are sorted by path.
+	///
+	/// Adapters returning metadata while listing (like S3 and the local one) fetch it in a single pass,
+	/// instead of the request per file it would take to ask for it afterwards.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to list the contents of the directory.
+	pub async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		let path = self.normalize(path)?;
+
+		let mut entries = self
+			.adapter
+			.list_contents_with_metadata(&path, deep)
+			.await
+			.map_err(|error| FlysystemError::listing(&path, error))?;
+		self.ensure_listed_directory(&path, entries.is_empty())
+			.await
+			.map_err(|error| FlysystemError::listing(&path, error))?;
+
+		if self.sorted_listings {
+			entries.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+		}
+
+		Ok(entries)
 	}
 
-	/// Move a file.
+	/// Get a list of files in a directory (optionally recursively), skipping the ones matched by the given ignore rules.
+	/// Like [`Filesystem::list_contents`], files are sorted by path.
+	///
+	/// Anchored patterns are matched relative to the listed directory.
 	///
 	/// # Errors
 	///
-	/// This function will return an error if the adapter fails to move the file.
-	pub async fn r#move(&mut self, source: &Path, destination: &Path) -> Result<()> {
-		self.adapter.r#move(source, destination).await
+	/// This function will return an error if the adapter fails to list the contents of the directory.
+	pub async fn list_contents_ignoring(
+		&self,
+		path: &Path,
+		deep: bool,
+		rules: &IgnoreRules,
+	) -> Result<Vec<PathBuf>> {
+		let path = self.normalize(path)?;
+		let mut contents = self.list_contents(&path, deep).await?;
+
+		if !rules.is_empty() {
+			contents.retain(|entry| !rules.is_ignored(relative_to(entry, &path)));
+		}
+
+		Ok(contents)
+	}
+
+	/// Load the ignore rules from the `.flyignore` file in a directory, if there is one.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to read the ignore file.
+	pub async fn ignore_rules(&self, path: &Path) -> Result<IgnoreRules> {
+		let ignore_file = self.normalize(path)?.join(ignore::IGNORE_FILE);
+
+		if !self.adapter.file_exists(&ignore_file).await? {
+			return Ok(IgnoreRules::new());
+		}
+
+		let contents: String = self
+			.adapter
+			.read(&ignore_file)
+			.await
+			.map_err(|error| FlysystemError::reading(&ignore_file, error))?
+			.try_into()
+			.map_err(|_| FlysystemError::UnableToReadFile {
+				path: ignore_file.clone(),
+				source: Error::new(
+					ErrorKind::InvalidData,
+					"The ignore file is not valid UTF-8.",
+				),
+			})?;
+
+		Ok(IgnoreRules::parse(&contents))
+	}
+
+	/// Move a file, or a directory along with everything in it.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to move the file (or doesn't support moving directories), or if the configured CDN purger fails to invalidate it.
+	pub async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		let (source, destination) = (self.normalize(source)?, self.normalize(destination)?);
+		let overwritten = self.needs_purge(&destination).await?;
+		let event = FilesystemEvent::FileMoved {
+			source: source.clone(),
+			destination: destination.clone(),
+		};
+
+		self.emit_before(event.clone()).await;
+		self.observe(self.adapter.r#move(&source, &destination).await, |error| {
+			FilesystemEvent::MoveFailed {
+				source: source.clone(),
+				destination: destination.clone(),
+				error,
+			}
+		})
+		.await
+		.map_err(|error| FlysystemError::moving(&source, &destination, error))?;
+
+		self.purge(&source).await?;
+		if overwritten {
+			self.purge(&destination).await?;
+		}
+
+		self.emit(event).await;
+		Ok(())
 	}
 
 	/// Copy a file.
 	///
 	/// # Errors
 	///
-	/// This function will return an error if the adapter fails to copy the file.
-	pub async fn copy(&mut self, source: &Path, destination: &Path) -> Result<()> {
-		self.adapter.copy(source, destination).await
+	/// This function will return an error if the adapter fails to copy the file, or if the configured CDN purger fails to invalidate it.
+	pub async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		let (source, destination) = (self.normalize(source)?, self.normalize(destination)?);
+		let overwritten = self.needs_purge(&destination).await?;
+		let event = FilesystemEvent::FileCopied {
+			source: source.clone(),
+			destination: destination.clone(),
+		};
+
+		self.emit_before(event.clone()).await;
+		self.observe(self.adapter.copy(&source, &destination).await, |error| {
+			FilesystemEvent::CopyFailed {
+				source: source.clone(),
+				destination: destination.clone(),
+				error,
+			}
+		})
+		.await
+		.map_err(|error| FlysystemError::copying(&source, &destination, error))?;
+
+		if overwritten {
+			self.purge(&destination).await?;
+		}
+
+		self.emit(event).await;
+		Ok(())
+	}
+
+	/// Copy a file, choosing the visibility of the copy.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if [`Filesystem::copy`] fails, or if the adapter fails to get or set the visibility (in which case the file has already been copied).
+	pub async fn copy_with_visibility(
+		&self,
+		source: &Path,
+		destination: &Path,
+		visibility: CopyVisibility,
+	) -> Result<()> {
+		let visibility = self.copy_visibility(source, visibility).await?;
+		self.copy(source, destination).await?;
+
+		if let Some(visibility) = visibility {
+			self.set_visibility(destination, visibility).await?;
+		}
+
+		Ok(())
+	}
+
+	/// Move a file, choosing the visibility it ends up with.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if [`Filesystem::move`] fails, or if the adapter fails to get or set the visibility (in which case the file has already been moved).
+	pub async fn move_with_visibility(
+		&self,
+		source: &Path,
+		destination: &Path,
+		visibility: CopyVisibility,
+	) -> Result<()> {
+		let visibility = self.copy_visibility(source, visibility).await?;
+		self.r#move(source, destination).await?;
+
+		if let Some(visibility) = visibility {
+			self.set_visibility(destination, visibility).await?;
+		}
+
+		Ok(())
+	}
+
+	/// Copy a file to another filesystem (which can use another adapter), streaming its contents.
+	///
+	/// The visibility and MIME type of the file are preserved where the destination's adapter supports them.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if reading the file or writing the copy fails, or if the destination fails to set
+	/// its visibility or MIME type (in which case the file has already been copied).
+	pub async fn copy_to(
+		&self,
+		source: &Path,
+		filesystem: &Self,
+		destination: &Path,
+	) -> Result<()> {
+		let source = self.normalize(source)?;
+		let (mime_type, visibility) =
+			futures::future::join(self.mime_type(&source), self.adapter.visibility(&source)).await;
+		let mime_type = mime_type?;
+
+		let mut reader = self.read_stream(&source).await?;
+		filesystem.write_stream(destination, &mut reader).await?;
+
+		if let Ok(visibility) = visibility {
+			unless_unsupported(filesystem.set_visibility(destination, visibility).await)?;
+		}
+		// adapters guessing the type from the extension already agree, and setting it can rewrite the file
+		if filesystem.mime_type(destination).await? != mime_type {
+			unless_unsupported(filesystem.set_mime_type(destination, &mime_type).await)?;
+		}
+
+		Ok(())
+	}
+
+	/// Move a file to another filesystem (which can use another adapter), copying it with [`Filesystem::copy_to`] before deleting it.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if [`Filesystem::copy_to`] fails (in which case the file is left in place), or if deleting it fails.
+	pub async fn move_to(
+		&self,
+		source: &Path,
+		filesystem: &Self,
+		destination: &Path,
+	) -> Result<()> {
+		self.copy_to(source, filesystem, destination).await?;
+
+		self.delete(source).await
+	}
+
+	/// Resolve the visibility to apply after copying `source`, reading it before the source can be moved away.
+	async fn copy_visibility(
+		&self,
+		source: &Path,
+		visibility: CopyVisibility,
+	) -> Result<Option<Visibility>> {
+		match visibility {
+			CopyVisibility::AdapterDefault => Ok(None),
+			CopyVisibility::Preserve => Ok(Some(self.visibility(source).await?)),
+			CopyVisibility::Set(visibility) => Ok(Some(visibility)),
+		}
+	}
+
+	/// Move a directory, along with everything in it.
+	///
+	/// The local adapter renames the directory at once, while object stores move the files under it one by one
+	/// (so a failure can leave it partially moved).
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the directory doesn't exist, if the destination is inside it, or if the adapter fails to move it.
+	pub async fn move_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		let (source, destination) = (self.normalize(source)?, self.normalize(destination)?);
+		let event = FilesystemEvent::DirectoryMoved {
+			source: source.clone(),
+			destination: destination.clone(),
+		};
+
+		self.emit_before(event.clone()).await;
+		self.observe(
+			self.adapter.move_directory(&source, &destination).await,
+			|error| FilesystemEvent::MoveFailed {
+				source: source.clone(),
+				destination: destination.clone(),
+				error,
+			},
+		)
+		.await
+		.map_err(|error| FlysystemError::moving(&source, &destination, error))?;
+
+		self.emit(event).await;
+		Ok(())
+	}
+
+	/// Copy a directory, along with everything in it.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the directory doesn't exist, if the destination is inside it, or if the adapter fails to copy it.
+	pub async fn copy_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		let (source, destination) = (self.normalize(source)?, self.normalize(destination)?);
+		let event = FilesystemEvent::DirectoryCopied {
+			source: source.clone(),
+			destination: destination.clone(),
+		};
+
+		self.emit_before(event.clone()).await;
+		self.observe(
+			self.adapter.copy_directory(&source, &destination).await,
+			|error| FilesystemEvent::CopyFailed {
+				source: source.clone(),
+				destination: destination.clone(),
+				error,
+			},
+		)
+		.await
+		.map_err(|error| FlysystemError::copying(&source, &destination, error))?;
+
+		self.emit(event).await;
+		Ok(())
+	}
+
+	/// Get the size, last modified time, MIME type and visibility of a file, or `None` if it doesn't exist.
+	///
+	/// The visibility is left out for adapters that don't support it.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to get the metadata of a file that exists.
+	pub async fn try_stat(&self, path: &Path) -> Result<Option<StorageEntry>> {
+		let path = self.normalize(path)?;
+
+		let size = match self.file_size(&path).await {
+			Ok(size) => size,
+			Err(FlysystemError::FileNotFound { .. }) => return Ok(None),
+			Err(error) => return Err(error),
+		};
+		let (last_modified, mime, visibility) = futures::future::join3(
+			self.last_modified(&path),
+			self.mime_type(&path),
+			self.adapter.visibility(&path),
+		)
+		.await;
+
+		Ok(Some(StorageEntry {
+			is_dir: false,
+			size: Some(size),
+			last_modified: Some(last_modified?),
+			mime: Some(mime?),
+			visibility: match visibility {
+				Ok(visibility) => Some(visibility),
+				Err(error) if error.kind() == ErrorKind::Unsupported => None,
+				Err(error) => return Err(FlysystemError::retrieving_metadata(&path, error)),
+			},
+			path,
+		}))
 	}
 
 	/// Get the date and time the file was last modified at.
@@ -190,7 +1448,60 @@ impl Filesystem {
 	///
 	/// This function will return an error if the adapter fails to get the last modified date and time.
 	pub async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
-		self.adapter.last_modified(path).await
+		let path = self.normalize(path)?;
+
+		self.adapter
+			.last_modified(&path)
+			.await
+			.map_err(|error| FlysystemError::retrieving_metadata(&path, error))
+	}
+
+	/// Get when a directory was last modified and how many files and subdirectories it directly contains.
+	///
+	/// Local directories report their own modification time, which changes when entries are added to or removed from them.
+	/// Object stores have no directories, so the latest modification time of the files under the prefix is used instead
+	/// (which takes listing it recursively).
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to get the metadata of the directory.
+	pub async fn directory_stat(&self, path: &Path) -> Result<DirectoryStat> {
+		let path = self.normalize(path)?;
+
+		self.adapter
+			.directory_stat(&path)
+			.await
+			.map_err(|error| FlysystemError::retrieving_metadata(&path, error))
+	}
+
+	/// Get when a directory was last modified (see [`Filesystem::directory_stat`]), or `None` for empty directories on object stores.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to get the metadata of the directory.
+	pub async fn directory_last_modified(&self, prefix: &Path) -> Result<Option<SystemTime>> {
+		Ok(self.directory_stat(prefix).await?.last_modified)
+	}
+
+	/// Get the size, last modified time, MIME type and revision of a file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to get the metadata of the file.
+	pub async fn stat(&self, path: &Path) -> Result<FileStat> {
+		let path = self.normalize(path)?;
+		let metadata = match self.adapter.metadata(&path).await {
+			Ok(metadata) => Some(metadata),
+			Err(error) if error.kind() == ErrorKind::Unsupported => None,
+			Err(error) => return Err(FlysystemError::retrieving_metadata(&path, error)),
+		};
+
+		Ok(FileStat {
+			size: self.file_size(&path).await?,
+			last_modified: self.last_modified(&path).await?,
+			mime_type: self.mime_type(&path).await?,
+			revision: metadata.as_ref().and_then(adapters::revisioned::revision),
+		})
 	}
 
 	/// Get the size of the file.
@@ -199,7 +1510,12 @@ impl Filesystem {
 	///
 	/// This function will return an error if the adapter fails to get the file size.
 	pub async fn file_size(&self, path: &Path) -> Result<u64> {
-		self.adapter.file_size(path).await
+		let path = self.normalize(path)?;
+
+		self.adapter
+			.file_size(&path)
+			.await
+			.map_err(|error| FlysystemError::retrieving_metadata(&path, error))
 	}
 
 	/// Get the mime type of the file.
@@ -208,7 +1524,40 @@ impl Filesystem {
 	///
 	/// This function will return an error if the adapter fails to get the mime type.
 	pub async fn mime_type(&self, path: &Path) -> Result<Mime> {
-		self.adapter.mime_type(path).await
+		let path = self.normalize(path)?;
+
+		self.adapter
+			.mime_type(&path)
+			.await
+			.map_err(|error| FlysystemError::retrieving_metadata(&path, error))
+	}
+
+	/// Get the custom metadata the file was written with, as passed in [`WriteOptions::metadata`].
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter doesn't store metadata or fails to get it.
+	pub async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		let path = self.normalize(path)?;
+
+		self.adapter
+			.metadata(&path)
+			.await
+			.map_err(|error| FlysystemError::retrieving_metadata(&path, error))
+	}
+
+	/// Change the MIME type the file is served with.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to change it, or doesn't store MIME types at all.
+	pub async fn set_mime_type(&self, path: &Path, mime_type: &Mime) -> Result<()> {
+		let path = self.normalize(path)?;
+
+		self.adapter
+			.set_mime_type(&path, mime_type)
+			.await
+			.map_err(|error| FlysystemError::writing(&path, error))
 	}
 
 	/// Set the visibility of the file.
@@ -216,8 +1565,45 @@ impl Filesystem {
 	/// # Errors
 	///
 	/// This function will return an error if the adapter fails to set the visibility.
-	pub async fn set_visibility(&mut self, path: &Path, visibility: Visibility) -> Result<()> {
-		self.adapter.set_visibility(path, visibility).await
+	pub async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
+		let path = self.normalize(path)?;
+
+		self.adapter
+			.set_visibility(&path, visibility)
+			.await
+			.map_err(|error| FlysystemError::setting_visibility(&path, error))
+	}
+
+	/// Set the visibility of every file under `prefix`, like making a whole release public.
+	///
+	/// Files are updated concurrently when the adapter supports it, and failures don't stop the others from being updated.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to list the directory. Failures for individual files are reported instead.
+	pub async fn set_visibility_prefix(
+		&self,
+		prefix: &Path,
+		visibility: Visibility,
+	) -> Result<VisibilityReport> {
+		let prefix = self.normalize(prefix)?;
+		let mut paths: Vec<PathBuf> = self
+			.adapter
+			.list_contents(&prefix, true)
+			.await
+			.map_err(|error| FlysystemError::listing(&prefix, error))?
+			.iter()
+			.map(|entry| prefix.join(relative_to(entry, &prefix)))
+			.collect();
+		paths.sort();
+		paths.dedup();
+
+		let failed = self.adapter.set_visibility_many(&paths, visibility).await;
+
+		Ok(VisibilityReport {
+			updated: paths.len() - failed.len(),
+			failed,
+		})
 	}
 
 	/// Get the visibility of the file.
@@ -226,7 +1612,12 @@ impl Filesystem {
 	///
 	/// This function will return an error if the adapter fails to get the visibility.
 	pub async fn visibility(&self, path: &Path) -> Result<Visibility> {
-		self.adapter.visibility(path).await
+		let path = self.normalize(path)?;
+
+		self.adapter
+			.visibility(&path)
+			.await
+			.map_err(|error| FlysystemError::retrieving_metadata(&path, error))
 	}
 
 	/// Get the checksum of the file.
@@ -235,6 +1626,589 @@ impl Filesystem {
 	///
 	/// This function will return an error if the adapter fails to get the checksum.
 	pub async fn checksum(&self, path: &Path) -> Result<String> {
-		self.adapter.checksum(path).await
+		let path = self.normalize(path)?;
+
+		self.adapter
+			.checksum(&path)
+			.await
+			.map_err(|error| FlysystemError::retrieving_metadata(&path, error))
+	}
+}
+
+/// Treat a best-effort operation the adapter doesn't support as done.
+fn unless_unsupported(result: Result<()>) -> Result<()> {
+	match result {
+		Err(error) if error.kind() == ErrorKind::Unsupported => Ok(()),
+		result => result,
+	}
+}
+
+/// Get the path of a listed entry relative to the listed directory.
+///
+/// Some adapters return paths including their root, so fall back to the closest ancestor ending with the listed directory.
+fn relative_to<'a>(entry: &'a Path, directory: &Path) -> &'a Path {
+	if let Ok(relative) = entry.strip_prefix(directory) {
+		return relative;
+	}
+
+	entry
+		.ancestors()
+		.skip(1)
+		.find(|ancestor| ancestor.ends_with(directory))
+		.and_then(|ancestor| entry.strip_prefix(ancestor).ok())
+		.unwrap_or(entry)
+}
+
+#[cfg(test)]
+mod tests {
+	use futures::TryStreamExt;
+
+	use super::*;
+	use adapters::MemoryAdapter;
+
+	#[derive(Debug)]
+	struct EicarScanner;
+
+	#[async_trait::async_trait]
+	impl Scanner for EicarScanner {
+		async fn scan(&self, contents: &[u8]) -> std::io::Result<ScanResult> {
+			if contents.windows(5).any(|window| window == b"EICAR") {
+				return Ok(ScanResult::Infected("Eicar-Signature".to_string()));
+			}
+
+			Ok(ScanResult::Clean)
+		}
+	}
+
+	#[tokio::test]
+	async fn test_write_with_scanner() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(())
+			.await
+			.unwrap()
+			.with_scanner(EicarScanner);
+
+		filesystem
+			.write(Path::new("clean.txt"), b"Hello, world!")
+			.await
+			.unwrap();
+		assert_eq!(
+			filesystem.scan(Path::new("clean.txt")).await.unwrap(),
+			ScanResult::Clean
+		);
+
+		let error = filesystem
+			.write(
+				Path::new("infected.txt"),
+				b"X5O!P%@AP[4\\PZX54(P^)7CC)7}$EICAR",
+			)
+			.await
+			.unwrap_err();
+
+		assert_eq!(error.kind(), ErrorKind::InvalidData);
+		assert!(!filesystem
+			.file_exists(Path::new("infected.txt"))
+			.await
+			.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_scan_without_scanner() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+
+		assert_eq!(
+			filesystem
+				.scan(Path::new("file.txt"))
+				.await
+				.unwrap_err()
+				.kind(),
+			ErrorKind::Unsupported
+		);
+	}
+
+	#[tokio::test]
+	async fn test_read_many() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+
+		for i in 0..10 {
+			filesystem
+				.write(
+					Path::new(&format!("test_read_many/{i}.json")),
+					i.to_string().as_bytes(),
+				)
+				.await
+				.unwrap();
+		}
+
+		let paths = (0..10)
+			.map(|i| PathBuf::from(format!("test_read_many/{i}.json")))
+			.chain([PathBuf::from("test_read_many/missing.json")]);
+
+		let mut results = filesystem.read_many(paths, 4).collect::<Vec<_>>().await;
+		results.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+		assert_eq!(results.len(), 11);
+		for (path, contents) in results {
+			if path.ends_with("missing.json") {
+				assert_eq!(contents.unwrap_err().kind(), ErrorKind::NotFound);
+			} else {
+				let expected = path
+					.file_stem()
+					.unwrap()
+					.to_str()
+					.unwrap()
+					.as_bytes()
+					.to_vec();
+				assert_eq!(contents.unwrap().data, expected);
+			}
+		}
+	}
+
+	#[tokio::test]
+	async fn test_concurrent_writes() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default());
+
+		let uploads = (0..8).map(|i| {
+			let filesystem = filesystem.clone();
+			tokio::spawn(async move {
+				filesystem
+					.write(Path::new(&format!("uploads/{i}.txt")), b"upload")
+					.await
+			})
+		});
+		for upload in futures::future::join_all(uploads).await {
+			upload.unwrap().unwrap();
+		}
+
+		assert_eq!(
+			filesystem
+				.list_contents(Path::new("uploads"), false)
+				.await
+				.unwrap()
+				.len(),
+			8
+		);
+	}
+
+	#[tokio::test]
+	async fn test_try_read_and_stat() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+		filesystem
+			.write(Path::new("config.json"), b"{}")
+			.await
+			.unwrap();
+
+		assert_eq!(
+			filesystem
+				.try_read(Path::new("config.json"))
+				.await
+				.unwrap()
+				.unwrap()
+				.data,
+			b"{}"
+		);
+		assert!(filesystem
+			.try_read(Path::new("missing.json"))
+			.await
+			.unwrap()
+			.is_none());
+
+		let entry = filesystem
+			.try_stat(Path::new("config.json"))
+			.await
+			.unwrap()
+			.unwrap();
+		assert_eq!(entry.size, Some(2));
+		assert_eq!(entry.mime, Some(mime::APPLICATION_JSON));
+		assert!(filesystem
+			.try_stat(Path::new("missing.json"))
+			.await
+			.unwrap()
+			.is_none());
+	}
+
+	#[tokio::test]
+	async fn test_move_and_copy_directory() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+		for path in [
+			"uploads/2023/a.jpg",
+			"uploads/2023/march/b.jpg",
+			"uploads/2024/c.jpg",
+		] {
+			filesystem.write(Path::new(path), b"jpeg").await.unwrap();
+		}
+
+		filesystem
+			.move_directory(Path::new("uploads/2023"), Path::new("archive/2023"))
+			.await
+			.unwrap();
+		assert!(!filesystem
+			.directory_exists(Path::new("uploads/2023"))
+			.await
+			.unwrap());
+		assert_eq!(
+			filesystem
+				.list_contents(Path::new("archive/2023"), true)
+				.await
+				.unwrap(),
+			[
+				PathBuf::from("archive/2023/a.jpg"),
+				PathBuf::from("archive/2023/march/b.jpg")
+			]
+		);
+
+		filesystem
+			.copy_directory(Path::new("archive/2023"), Path::new("backup/2023"))
+			.await
+			.unwrap();
+		assert!(filesystem
+			.file_exists(Path::new("backup/2023/march/b.jpg"))
+			.await
+			.unwrap());
+		assert!(filesystem
+			.file_exists(Path::new("archive/2023/march/b.jpg"))
+			.await
+			.unwrap());
+
+		let error = filesystem
+			.move_directory(Path::new("uploads"), Path::new("uploads/old"))
+			.await
+			.unwrap_err();
+		assert_eq!(error.kind(), ErrorKind::InvalidInput);
+	}
+
+	#[tokio::test]
+	async fn test_directory_stat() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+		for path in ["photos/a.jpg", "photos/2024/b.jpg", "photos/2024/c.jpg"] {
+			filesystem.write(Path::new(path), b"jpeg").await.unwrap();
+		}
+
+		let stat = filesystem
+			.directory_stat(Path::new("photos"))
+			.await
+			.unwrap();
+		assert_eq!(stat.children, 2);
+		assert_eq!(
+			stat.last_modified,
+			Some(
+				filesystem
+					.last_modified(Path::new("photos/2024/c.jpg"))
+					.await
+					.unwrap()
+			)
+		);
+		assert_eq!(
+			filesystem
+				.directory_last_modified(Path::new("photos/2024"))
+				.await
+				.unwrap(),
+			stat.last_modified
+		);
+	}
+
+	#[tokio::test]
+	async fn test_sorted_listings() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+
+		for path in ["logs/c.log", "logs/a.log", "logs/2024/b.log", "logs/b.log"] {
+			filesystem.write(Path::new(path), b"").await.unwrap();
+		}
+
+		let expected = vec![
+			PathBuf::from("logs/2024/b.log"),
+			PathBuf::from("logs/a.log"),
+			PathBuf::from("logs/b.log"),
+			PathBuf::from("logs/c.log"),
+		];
+		assert_eq!(
+			filesystem
+				.list_contents(Path::new("logs"), true)
+				.await
+				.unwrap(),
+			expected
+		);
+		assert_eq!(
+			filesystem
+				.list_contents_with_metadata(Path::new("logs"), true)
+				.await
+				.unwrap()
+				.into_iter()
+				.map(|entry| entry.path)
+				.collect::<Vec<_>>(),
+			expected
+		);
+
+		let mut unsorted = filesystem
+			.with_unsorted_listings()
+			.list_contents(Path::new("logs"), true)
+			.await
+			.unwrap();
+		unsorted.sort();
+		assert_eq!(unsorted, expected);
+	}
+
+	#[tokio::test]
+	async fn test_list_stream() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+
+		for path in ["logs/a.log", "logs/b.log", "logs/2024/c.log"] {
+			filesystem.write(Path::new(path), b"").await.unwrap();
+		}
+
+		let mut shallow: Vec<PathBuf> = filesystem
+			.list_stream(Path::new("logs"), false)
+			.try_collect()
+			.await
+			.unwrap();
+		shallow.sort();
+		assert_eq!(
+			shallow,
+			vec![PathBuf::from("logs/a.log"), PathBuf::from("logs/b.log")]
+		);
+
+		let deep = filesystem
+			.list_stream(Path::new("logs"), true)
+			.try_collect::<Vec<_>>()
+			.await
+			.unwrap();
+		assert_eq!(deep.len(), 3);
+
+		let mut missing = filesystem.list_stream(Path::new("missing"), false);
+		assert_eq!(
+			missing.next().await.unwrap().unwrap_err().kind(),
+			ErrorKind::NotFound
+		);
+	}
+
+	#[tokio::test]
+	async fn test_copy_with_visibility() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+		filesystem
+			.write(Path::new("release/app.zip"), b"zip")
+			.await
+			.unwrap();
+		filesystem
+			.set_visibility(Path::new("release/app.zip"), Visibility::Private)
+			.await
+			.unwrap();
+
+		filesystem
+			.copy_with_visibility(
+				Path::new("release/app.zip"),
+				Path::new("public/app.zip"),
+				CopyVisibility::Set(Visibility::Public),
+			)
+			.await
+			.unwrap();
+		filesystem
+			.move_with_visibility(
+				Path::new("release/app.zip"),
+				Path::new("archive/app.zip"),
+				CopyVisibility::Preserve,
+			)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			filesystem
+				.visibility(Path::new("public/app.zip"))
+				.await
+				.unwrap(),
+			Visibility::Public
+		);
+		assert_eq!(
+			filesystem
+				.visibility(Path::new("archive/app.zip"))
+				.await
+				.unwrap(),
+			Visibility::Private
+		);
+	}
+
+	#[tokio::test]
+	async fn test_copy_to_another_filesystem() {
+		let source = Filesystem::from_adapter(MemoryAdapter::default());
+		let destination = Filesystem::from_adapter(MemoryAdapter::default());
+		source
+			.write_with(
+				Path::new("export"),
+				b"id,total",
+				WriteOptions {
+					visibility: Some(Visibility::Public),
+					content_type: Some(mime::TEXT_CSV),
+					..WriteOptions::default()
+				},
+			)
+			.await
+			.unwrap();
+
+		source
+			.move_to(Path::new("export"), &destination, Path::new("in/export"))
+			.await
+			.unwrap();
+
+		assert!(!source.file_exists(Path::new("export")).await.unwrap());
+		let copy = destination
+			.try_stat(Path::new("in/export"))
+			.await
+			.unwrap()
+			.unwrap();
+		assert_eq!(copy.size, Some(8));
+		assert_eq!(copy.mime, Some(mime::TEXT_CSV));
+		assert_eq!(copy.visibility, Some(Visibility::Public));
+	}
+
+	#[tokio::test]
+	async fn test_set_visibility_prefix() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+		for path in [
+			"release/app.zip",
+			"release/docs/index.html",
+			"drafts/app.zip",
+		] {
+			filesystem.write(Path::new(path), b"").await.unwrap();
+			filesystem
+				.set_visibility(Path::new(path), Visibility::Private)
+				.await
+				.unwrap();
+		}
+
+		let report = filesystem
+			.set_visibility_prefix(Path::new("release"), Visibility::Public)
+			.await
+			.unwrap();
+
+		assert_eq!(report.updated, 2);
+		assert!(report.failed.is_empty());
+		assert_eq!(
+			filesystem
+				.visibility(Path::new("release/docs/index.html"))
+				.await
+				.unwrap(),
+			Visibility::Public
+		);
+		assert_eq!(
+			filesystem
+				.visibility(Path::new("drafts/app.zip"))
+				.await
+				.unwrap(),
+			Visibility::Private
+		);
+	}
+
+	#[tokio::test]
+	async fn test_event_hooks() {
+		let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+		let (moves, failures) = (events.clone(), events.clone());
+		let filesystem = Filesystem::new::<MemoryAdapter>(())
+			.await
+			.unwrap()
+			.on("move", move |event| {
+				moves.lock().unwrap().push(event.clone());
+			})
+			.on("move_failed", move |event| {
+				failures.lock().unwrap().push(event.clone());
+			});
+
+		filesystem
+			.write(Path::new("a.txt"), b"Hello, world!")
+			.await
+			.unwrap();
+		filesystem
+			.r#move(Path::new("a.txt"), Path::new("b.txt"))
+			.await
+			.unwrap();
+		filesystem
+			.r#move(Path::new("a.txt"), Path::new("c.txt"))
+			.await
+			.unwrap_err();
+
+		let events = events.lock().unwrap().clone();
+		assert_eq!(events.len(), 2);
+		assert_eq!(
+			events[0],
+			FilesystemEvent::FileMoved {
+				source: PathBuf::from("a.txt"),
+				destination: PathBuf::from("b.txt"),
+			}
+		);
+		assert_eq!(events[1].name(), "move_failed");
+		assert!(events[1].error().is_some());
+	}
+
+	#[tokio::test]
+	async fn test_copy_and_create_directory_failure_events() {
+		let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+		let (copies, directories) = (events.clone(), events.clone());
+		let filesystem =
+			Filesystem::from_adapter(adapters::ReadOnlyAdapter::new(MemoryAdapter::default()))
+				.on("copy_failed", move |event| {
+					copies.lock().unwrap().push(event.clone());
+				})
+				.on("create_directory_failed", move |event| {
+					directories.lock().unwrap().push(event.clone());
+				});
+
+		filesystem
+			.copy(Path::new("a.txt"), Path::new("b.txt"))
+			.await
+			.unwrap_err();
+		filesystem
+			.create_directory(Path::new("nested"))
+			.await
+			.unwrap_err();
+
+		let events = events.lock().unwrap().clone();
+		assert_eq!(events.len(), 2);
+		assert!(matches!(
+			&events[0],
+			FilesystemEvent::CopyFailed { source, destination, .. }
+				if source == Path::new("a.txt") && destination == Path::new("b.txt")
+		));
+		assert_eq!(events[1].name(), "create_directory_failed");
+		assert_eq!(events[1].path(), Path::new("nested"));
+		assert!(events[1].error().is_some());
+	}
+
+	#[tokio::test]
+	async fn test_shutdown_flushes_listeners() {
+		#[derive(Debug, Default)]
+		struct Buffered {
+			pending: std::sync::Mutex<Vec<PathBuf>>,
+			delivered: std::sync::Mutex<Vec<PathBuf>>,
+		}
+
+		#[async_trait::async_trait]
+		impl EventListener for Arc<Buffered> {
+			async fn handle(&self, event: &FilesystemEvent) {
+				self.pending
+					.lock()
+					.unwrap()
+					.push(event.path().to_path_buf());
+			}
+
+			async fn flush(&self) {
+				let pending = std::mem::take(&mut *self.pending.lock().unwrap());
+				self.delivered.lock().unwrap().extend(pending);
+			}
+		}
+
+		let listener = Arc::new(Buffered::default());
+		let filesystem = Filesystem::new::<MemoryAdapter>(())
+			.await
+			.unwrap()
+			.with_listener(listener.clone());
+
+		filesystem
+			.write(Path::new("a.txt"), b"Hello, world!")
+			.await
+			.unwrap();
+		assert!(listener.delivered.lock().unwrap().is_empty());
+
+		filesystem.shutdown().await.unwrap();
+		assert_eq!(
+			*listener.delivered.lock().unwrap(),
+			vec![PathBuf::from("a.txt")]
+		);
 	}
 }
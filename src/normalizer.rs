@@ -0,0 +1,199 @@
+use std::{
+	ffi::OsString,
+	path::{Component, Path, PathBuf},
+};
+
+use crate::{FlysystemError, Result};
+
+/// Normalize a path before handing it to an adapter.
+///
+/// Removes empty segments (`dir//file.txt`), current directory markers (`./file`), trailing slashes (`dir/`) and leading slashes (`/file`),
+/// so every adapter receives the same relative path regardless of how it was written.
+///
+/// Segments are always joined with `/`, so paths written with `\` on Windows produce the same keys as everywhere else.
+pub fn normalize(path: &Path) -> PathBuf {
+	let mut key = OsString::new();
+
+	for component in path.components() {
+		if matches!(
+			component,
+			Component::Prefix(_) | Component::RootDir | Component::CurDir
+		) {
+			continue;
+		}
+
+		if !key.is_empty() {
+			key.push("/");
+		}
+		key.push(component.as_os_str());
+	}
+
+	PathBuf::from(key)
+}
+
+/// Normalize a path like [`normalize`], also resolving `..` segments, for paths that can't be trusted (like user input).
+///
+/// Paths escaping the root of the filesystem (like `../../etc/passwd`) or starting with a Windows drive or share
+/// are rejected with a [`FlysystemError::PathTraversal`] error, and paths containing control characters with a
+/// [`FlysystemError::CorruptedPath`] one.
+pub fn normalize_untrusted(path: &Path) -> Result<PathBuf> {
+	if path.to_string_lossy().chars().any(char::is_control) {
+		return Err(FlysystemError::CorruptedPath {
+			path: path.to_path_buf(),
+		});
+	}
+
+	let mut resolved = PathBuf::new();
+	for component in path.components() {
+		match component {
+			Component::Normal(name) => resolved.push(name),
+			Component::ParentDir if resolved.pop() => {},
+			Component::ParentDir | Component::Prefix(_) => {
+				return Err(FlysystemError::PathTraversal {
+					path: path.to_path_buf(),
+				})
+			},
+			Component::RootDir | Component::CurDir => {},
+		}
+	}
+
+	Ok(normalize(&resolved))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{adapters::MemoryAdapter, Filesystem};
+
+	#[test]
+	fn test_normalize() {
+		assert_eq!(
+			normalize(Path::new("dir//file.txt")),
+			Path::new("dir/file.txt")
+		);
+		assert_eq!(normalize(Path::new("./file.txt")), Path::new("file.txt"));
+		assert_eq!(
+			normalize(Path::new("dir/./file.txt")),
+			Path::new("dir/file.txt")
+		);
+		assert_eq!(normalize(Path::new("dir/")), Path::new("dir"));
+		assert_eq!(
+			normalize(Path::new("/dir/file.txt")),
+			Path::new("dir/file.txt")
+		);
+		assert_eq!(normalize(Path::new("")), Path::new(""));
+	}
+
+	#[test]
+	fn test_normalized_keys_have_no_empty_segments() {
+		for path in [
+			"dir//file.txt",
+			"./dir/file.txt",
+			"/dir/file.txt",
+			"dir///file.txt/",
+		] {
+			let key = normalize(Path::new(path));
+
+			assert_eq!(key.to_str().unwrap(), "dir/file.txt");
+			assert!(!key.to_str().unwrap().split('/').any(str::is_empty));
+		}
+	}
+
+	#[test]
+	fn test_normalized_keys_use_forward_slashes() {
+		assert_eq!(
+			normalize(Path::new("dir/nested/file.txt"))
+				.to_str()
+				.unwrap(),
+			"dir/nested/file.txt"
+		);
+	}
+
+	#[cfg(windows)]
+	#[test]
+	fn test_normalize_converts_backslashes() {
+		assert_eq!(
+			normalize(Path::new(r"dir\nested\file.txt"))
+				.to_str()
+				.unwrap(),
+			"dir/nested/file.txt"
+		);
+		assert_eq!(
+			normalize(Path::new(r"C:\dir\file.txt")).to_str().unwrap(),
+			"dir/file.txt"
+		);
+	}
+
+	#[tokio::test]
+	async fn test_untrusted_paths() {
+		assert_eq!(
+			normalize_untrusted(Path::new("dir/../other/./file.txt")).unwrap(),
+			Path::new("other/file.txt")
+		);
+		assert_eq!(
+			normalize_untrusted(Path::new("/dir/sub/../../file.txt")).unwrap(),
+			Path::new("file.txt")
+		);
+		assert!(matches!(
+			normalize_untrusted(Path::new("../../etc/passwd")),
+			Err(FlysystemError::PathTraversal { .. })
+		));
+		assert!(matches!(
+			normalize_untrusted(Path::new("dir/\u{0}file.txt")),
+			Err(FlysystemError::CorruptedPath { .. })
+		));
+
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+		assert!(matches!(
+			filesystem
+				.read::<Vec<u8>>(Path::new("uploads/../../etc/passwd"))
+				.await,
+			Err(FlysystemError::PathTraversal { .. })
+		));
+		filesystem
+			.write(Path::new("uploads/../avatar.png"), b"png")
+			.await
+			.unwrap();
+		assert!(filesystem
+			.file_exists(Path::new("avatar.png"))
+			.await
+			.unwrap());
+
+		// trusted callers get paths through as they are
+		let filesystem = filesystem.with_trusted_paths();
+		filesystem
+			.write(Path::new("uploads/../avatar.png"), b"png")
+			.await
+			.unwrap();
+		assert!(filesystem
+			.file_exists(Path::new("uploads/../avatar.png"))
+			.await
+			.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_filesystem_normalizes_paths() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+
+		filesystem
+			.write(Path::new("dir//file.txt"), b"Hello, world!")
+			.await
+			.unwrap();
+
+		assert!(filesystem
+			.file_exists(Path::new("./dir/file.txt"))
+			.await
+			.unwrap());
+		assert!(filesystem
+			.directory_exists(Path::new("dir/"))
+			.await
+			.unwrap());
+		assert_eq!(
+			filesystem
+				.list_contents(Path::new("dir/"), false)
+				.await
+				.unwrap(),
+			vec![PathBuf::from("dir/file.txt")]
+		);
+	}
+}
@@ -0,0 +1,235 @@
+use std::{
+	io::{self, ErrorKind},
+	path::Path,
+};
+
+use futures::StreamExt;
+
+use crate::Filesystem;
+
+/// How an adapter stores directories, as reported by [`Adapter::path_model`](crate::adapters::Adapter::path_model).
+///
+/// [`Filesystem`] bridges the differences, so callers see the same behavior on both:
+///
+/// - writing a file makes its parent directories exist, and deleting a directory deletes everything under it;
+/// - listing or deleting a directory that doesn't exist fails with [`FlysystemError::DirectoryNotFound`](crate::FlysystemError::DirectoryNotFound).
+///
+/// What differs is empty directories: on flat adapters, [`Filesystem::create_directory`] only makes the directory exist
+/// if the adapter keeps directory markers (like S3 does unless told not to), and it stops existing once its last file is deleted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PathModel {
+	/// Directories are entries of their own, which exist (even empty) until they're deleted, like on a local disk or a DAV server.
+	#[default]
+	Hierarchical,
+	/// Files are keys in a flat namespace and directories are only the prefixes they share, like on S3.
+	FlatKeys,
+}
+
+impl Filesystem {
+	/// Check how the adapter stores directories.
+	#[must_use]
+	pub fn path_model(&self) -> PathModel {
+		self.adapter.path_model()
+	}
+
+	/// Check if the directory at the (normalized) `path` exists, counting the prefixes of files on flat adapters.
+	pub(crate) async fn has_directory(&self, path: &Path) -> io::Result<bool> {
+		if self.adapter.directory_exists(path).await? {
+			return Ok(true);
+		}
+
+		match self.path_model() {
+			PathModel::Hierarchical => Ok(false),
+			PathModel::FlatKeys => Ok(self
+				.adapter
+				.list_stream(path, true)
+				.next()
+				.await
+				.transpose()?
+				.is_some()),
+		}
+	}
+
+	/// Fail with `NotFound` if a flat adapter returned an empty listing for a directory that doesn't exist,
+	/// like hierarchical adapters do. The root always exists.
+	pub(crate) async fn ensure_listed_directory(&self, path: &Path, empty: bool) -> io::Result<()> {
+		if !empty || self.path_model() == PathModel::Hierarchical || path.as_os_str().is_empty() {
+			return Ok(());
+		}
+
+		if self.adapter.directory_exists(path).await? {
+			Ok(())
+		} else {
+			Err(io::Error::from(ErrorKind::NotFound))
+		}
+	}
+
+	/// Fail with `NotFound` before deleting a directory that doesn't exist on a flat adapter, where it would otherwise be a no-op.
+	pub(crate) async fn ensure_deleted_directory(&self, path: &Path) -> io::Result<()> {
+		if self.path_model() == PathModel::Hierarchical || self.has_directory(path).await? {
+			Ok(())
+		} else {
+			Err(io::Error::from(ErrorKind::NotFound))
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{adapters::MemoryAdapter, FlysystemError};
+
+	/// Check that `filesystem` follows the directory semantics documented on [`PathModel`].
+	async fn conformance(filesystem: &Filesystem) {
+		let hierarchical = filesystem.path_model() == PathModel::Hierarchical;
+
+		filesystem
+			.write(Path::new("a/b/c.txt"), b"hello")
+			.await
+			.unwrap();
+		assert!(filesystem.directory_exists(Path::new("a")).await.unwrap());
+		assert!(filesystem.directory_exists(Path::new("a/b")).await.unwrap());
+		assert!(filesystem.has(Path::new("a")).await.unwrap());
+		// the local adapter lists absolute paths
+		let listed = filesystem
+			.list_contents(Path::new("a"), true)
+			.await
+			.unwrap();
+		assert_eq!(listed.len(), 1);
+		assert!(listed[0].ends_with("a/b/c.txt"));
+
+		// overwriting a file lists it once, with its new size
+		filesystem
+			.write(Path::new("a/b/c.txt"), b"hello, world")
+			.await
+			.unwrap();
+		let entries = filesystem
+			.list_contents_with_metadata(Path::new("a"), true)
+			.await
+			.unwrap();
+		let files = entries
+			.iter()
+			.filter(|entry| !entry.is_dir)
+			.collect::<Vec<_>>();
+		assert_eq!(files.len(), 1);
+		assert!(files[0].path.ends_with("a/b/c.txt"));
+		assert_eq!(files[0].size, Some(12));
+
+		// deleted files are gone from listings, while their siblings stay
+		filesystem
+			.write(Path::new("a/b/d.txt"), b"bye")
+			.await
+			.unwrap();
+		filesystem.delete(Path::new("a/b/d.txt")).await.unwrap();
+		let listed = filesystem
+			.list_contents(Path::new("a"), true)
+			.await
+			.unwrap();
+		assert_eq!(listed.len(), 1);
+		assert!(listed[0].ends_with("a/b/c.txt"));
+		assert!(filesystem.directory_exists(Path::new("a/b")).await.unwrap());
+
+		filesystem
+			.create_directory(Path::new("empty"))
+			.await
+			.unwrap();
+		if hierarchical {
+			assert!(filesystem
+				.directory_exists(Path::new("empty"))
+				.await
+				.unwrap());
+			assert!(filesystem
+				.list_contents(Path::new("empty"), false)
+				.await
+				.unwrap()
+				.is_empty());
+		}
+
+		assert!(matches!(
+			filesystem.list_contents(Path::new("missing"), false).await,
+			Err(FlysystemError::DirectoryNotFound { .. })
+		));
+		assert!(matches!(
+			filesystem
+				.list_contents_with_metadata(Path::new("missing"), true)
+				.await,
+			Err(FlysystemError::DirectoryNotFound { .. })
+		));
+		assert!(matches!(
+			filesystem.delete_directory(Path::new("missing")).await,
+			Err(FlysystemError::DirectoryNotFound { .. })
+		));
+		assert!(!filesystem
+			.directory_exists(Path::new("missing"))
+			.await
+			.unwrap());
+
+		filesystem.delete_directory(Path::new("a")).await.unwrap();
+		assert!(!filesystem.directory_exists(Path::new("a")).await.unwrap());
+		assert!(!filesystem
+			.file_exists(Path::new("a/b/c.txt"))
+			.await
+			.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_memory_conformance() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default());
+
+		assert_eq!(filesystem.path_model(), PathModel::Hierarchical);
+		conformance(&filesystem).await;
+	}
+
+	#[cfg(feature = "local")]
+	#[tokio::test]
+	async fn test_local_conformance() {
+		use crate::adapters::{local::Config, LocalAdapter, Validation};
+
+		let location = std::env::temp_dir().join("flysystem_path_model_conformance");
+		let filesystem = Filesystem::new::<LocalAdapter>(Config {
+			location: location.clone(),
+			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
+		})
+		.await
+		.unwrap();
+
+		conformance(&filesystem).await;
+		std::fs::remove_dir_all(location).unwrap();
+	}
+
+	#[cfg(feature = "s3")]
+	#[tokio::test]
+	async fn test_s3_conformance() {
+		use crate::adapters::{s3::ConfigBuilder, S3Adapter, Validation};
+
+		let filesystem = Filesystem::new::<S3Adapter>(
+			ConfigBuilder::from_env()
+				.validation(Validation::Eager)
+				.build()
+				.unwrap(),
+		)
+		.await
+		.unwrap();
+
+		assert_eq!(filesystem.path_model(), PathModel::FlatKeys);
+		conformance(&filesystem).await;
+	}
+
+	#[cfg(feature = "object_store")]
+	#[tokio::test]
+	async fn test_object_store_conformance() {
+		use object_store::memory::InMemory;
+		use std::sync::Arc;
+
+		use crate::adapters::ObjectStoreAdapter;
+
+		let filesystem = Filesystem::new::<ObjectStoreAdapter>(Arc::new(InMemory::new()) as Arc<_>)
+			.await
+			.unwrap();
+
+		assert_eq!(filesystem.path_model(), PathModel::FlatKeys);
+		conformance(&filesystem).await;
+	}
+}
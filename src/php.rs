@@ -0,0 +1,548 @@
+//! An API mirroring the method names and semantics of [PHP's Flysystem](https://flysystem.thephpleague.com/docs/usage/filesystem-api/),
+//! to ease porting Laravel or Symfony codebases line by line.
+//!
+//! ```ignore
+//! let filesystem = filesystem.php();
+//!
+//! if !filesystem.fileExists("reports/latest.csv").await? {
+//!     filesystem.write("reports/latest.csv", csv, &[("visibility", "private")]).await?;
+//! }
+//!
+//! for item in filesystem.listContents("reports", LIST_DEEP).await? {
+//!     if item.isFile() {
+//!         println!("{} ({} bytes)", item.path(), item.fileSize().unwrap_or_default());
+//!     }
+//! }
+//! ```
+#![allow(non_snake_case)]
+
+use futures::io::AsyncRead;
+use mime::Mime;
+use std::{
+	io::{Error, ErrorKind},
+	path::Path,
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{Contents, FlysystemError, Result, StorageEntry, Visibility, WriteOptions};
+
+/// List the contents of subdirectories too, like `Filesystem::LIST_DEEP`.
+pub const LIST_DEEP: bool = true;
+/// Only list the direct contents of a directory, like `Filesystem::LIST_SHALLOW`.
+pub const LIST_SHALLOW: bool = false;
+
+/// A [`Filesystem`](crate::Filesystem) behind PHP Flysystem's `FilesystemOperator` interface. See [`Filesystem::php`](crate::Filesystem::php).
+///
+/// Paths are strings, timestamps are seconds since the Unix epoch and visibilities are `"public"` or `"private"`, like in PHP.
+/// Write configs are key-value pairs, of which `visibility` and `mimetype` are supported (other keys are ignored, as in PHP).
+/// Deleting a file or directory that doesn't exist succeeds, like it does with PHP's adapters.
+#[derive(Debug, Clone)]
+pub struct Filesystem {
+	inner: crate::Filesystem,
+}
+
+impl crate::Filesystem {
+	/// Get a view of this filesystem with the method names and semantics of PHP's Flysystem.
+	#[must_use]
+	pub fn php(&self) -> Filesystem {
+		Filesystem {
+			inner: self.clone(),
+		}
+	}
+}
+
+impl Filesystem {
+	/// Check if a file exists.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to check for the file.
+	pub async fn fileExists(&self, location: &str) -> Result<bool> {
+		self.inner.file_exists(Path::new(location)).await
+	}
+
+	/// Check if a directory exists.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to check for the directory.
+	pub async fn directoryExists(&self, location: &str) -> Result<bool> {
+		self.inner.directory_exists(Path::new(location)).await
+	}
+
+	/// Check if a file or directory exists.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to check for the path.
+	pub async fn has(&self, location: &str) -> Result<bool> {
+		self.inner.has(Path::new(location)).await
+	}
+
+	/// Get the contents of a file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to read the file.
+	pub async fn read(&self, location: &str) -> Result<Vec<u8>> {
+		self.inner
+			.read::<Contents>(Path::new(location))
+			.await
+			.map(Vec::from)
+	}
+
+	/// Open a file for reading.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to open the file.
+	pub async fn readStream(&self, location: &str) -> Result<impl AsyncRead + Send + Unpin> {
+		self.inner.read_stream(Path::new(location)).await
+	}
+
+	/// Get a list of the files and directories in a directory, recursively with [`LIST_DEEP`].
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to list the directory.
+	pub async fn listContents(&self, location: &str, deep: bool) -> Result<Vec<StorageAttributes>> {
+		let entries = self
+			.inner
+			.list_contents_with_metadata(Path::new(location), deep)
+			.await?;
+
+		Ok(entries.into_iter().map(StorageAttributes::from).collect())
+	}
+
+	/// Get when a file was last modified, in seconds since the Unix epoch.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to get the last modified time of the file.
+	pub async fn lastModified(&self, path: &str) -> Result<i64> {
+		Ok(timestamp(self.inner.last_modified(Path::new(path)).await?))
+	}
+
+	/// Get the size of a file, in bytes.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to get the size of the file.
+	pub async fn fileSize(&self, path: &str) -> Result<u64> {
+		self.inner.file_size(Path::new(path)).await
+	}
+
+	/// Get the MIME type of a file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to get the MIME type of the file.
+	pub async fn mimeType(&self, path: &str) -> Result<String> {
+		Ok(self.inner.mime_type(Path::new(path)).await?.to_string())
+	}
+
+	/// Get the visibility of a file, `"public"` or `"private"`.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to get the visibility of the file.
+	pub async fn visibility(&self, path: &str) -> Result<String> {
+		Ok(visibility_name(self.inner.visibility(Path::new(path)).await?).to_string())
+	}
+
+	/// Get the checksum of a file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to compute the checksum of the file.
+	pub async fn checksum(&self, path: &str) -> Result<String> {
+		self.inner.checksum(Path::new(path)).await
+	}
+
+	/// Get the public URL of a file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if public URLs aren't supported, or if generating the URL fails.
+	pub async fn publicUrl(&self, path: &str) -> Result<String> {
+		Ok(self.inner.public_url(Path::new(path)).await?.to_string())
+	}
+
+	/// Get a URL granting access to a file until `expires_at`.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter can't generate temporary URLs, or if generating the URL fails.
+	pub async fn temporaryUrl(&self, path: &str, expires_at: SystemTime) -> Result<String> {
+		let expires_in = expires_at
+			.duration_since(SystemTime::now())
+			.unwrap_or_default();
+
+		Ok(self
+			.inner
+			.temporary_url(Path::new(path), expires_in)
+			.await?
+			.to_string())
+	}
+
+	/// Write a file, overwriting it if it exists.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the config is invalid, or if the adapter fails to write the file.
+	pub async fn write(
+		&self,
+		location: &str,
+		contents: impl AsRef<[u8]> + Send,
+		config: &[(&str, &str)],
+	) -> Result<()> {
+		let path = Path::new(location);
+		let options = write_options(path, config)?;
+
+		self.inner
+			.write_with(path, contents.as_ref(), options)
+			.await
+	}
+
+	/// Write a file from a reader, overwriting it if it exists.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the config is invalid, or if the adapter fails to write the file or apply the config.
+	pub async fn writeStream(
+		&self,
+		location: &str,
+		contents: &mut (dyn AsyncRead + Send + Unpin),
+		config: &[(&str, &str)],
+	) -> Result<()> {
+		let path = Path::new(location);
+		let options = write_options(path, config)?;
+
+		self.inner.write_stream(path, contents).await?;
+		if let Some(mime_type) = &options.content_type {
+			self.inner.set_mime_type(path, mime_type).await?;
+		}
+		if let Some(visibility) = options.visibility {
+			self.inner.set_visibility(path, visibility).await?;
+		}
+
+		Ok(())
+	}
+
+	/// Set the visibility of a file, `"public"` or `"private"`.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the visibility is invalid, or if the adapter fails to set it.
+	pub async fn setVisibility(&self, path: &str, visibility: &str) -> Result<()> {
+		let path = Path::new(path);
+
+		self.inner
+			.set_visibility(path, parse_visibility(path, visibility)?)
+			.await
+	}
+
+	/// Delete a file, if it exists.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to delete the file.
+	pub async fn delete(&self, location: &str) -> Result<()> {
+		match self.inner.delete(Path::new(location)).await {
+			Err(FlysystemError::FileNotFound { .. }) => Ok(()),
+			result => result,
+		}
+	}
+
+	/// Delete a directory along with everything in it, if it exists.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to delete the directory.
+	pub async fn deleteDirectory(&self, location: &str) -> Result<()> {
+		match self.inner.delete_directory(Path::new(location)).await {
+			Err(FlysystemError::DirectoryNotFound { .. }) => Ok(()),
+			result => result,
+		}
+	}
+
+	/// Create a directory.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to create the directory.
+	pub async fn createDirectory(&self, location: &str) -> Result<()> {
+		self.inner.create_directory(Path::new(location)).await
+	}
+
+	/// Move a file, overwriting the destination if it exists.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to move the file.
+	pub async fn r#move(&self, source: &str, destination: &str) -> Result<()> {
+		self.inner
+			.r#move(Path::new(source), Path::new(destination))
+			.await
+	}
+
+	/// Copy a file, overwriting the destination if it exists.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to copy the file.
+	pub async fn copy(&self, source: &str, destination: &str) -> Result<()> {
+		self.inner
+			.copy(Path::new(source), Path::new(destination))
+			.await
+	}
+}
+
+/// A file listed by [`Filesystem::listContents`], like PHP's `FileAttributes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileAttributes {
+	path: String,
+	file_size: Option<u64>,
+	visibility: Option<Visibility>,
+	last_modified: Option<i64>,
+	mime_type: Option<String>,
+}
+
+impl FileAttributes {
+	#[must_use]
+	pub fn path(&self) -> &str {
+		&self.path
+	}
+
+	#[must_use]
+	pub const fn fileSize(&self) -> Option<u64> {
+		self.file_size
+	}
+
+	#[must_use]
+	pub fn visibility(&self) -> Option<&'static str> {
+		self.visibility.map(visibility_name)
+	}
+
+	#[must_use]
+	pub const fn lastModified(&self) -> Option<i64> {
+		self.last_modified
+	}
+
+	#[must_use]
+	pub fn mimeType(&self) -> Option<&str> {
+		self.mime_type.as_deref()
+	}
+}
+
+/// A directory listed by [`Filesystem::listContents`], like PHP's `DirectoryAttributes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectoryAttributes {
+	path: String,
+}
+
+impl DirectoryAttributes {
+	#[must_use]
+	pub fn path(&self) -> &str {
+		&self.path
+	}
+}
+
+/// An entry listed by [`Filesystem::listContents`], like PHP's `StorageAttributes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageAttributes {
+	File(FileAttributes),
+	Directory(DirectoryAttributes),
+}
+
+impl StorageAttributes {
+	#[must_use]
+	pub fn path(&self) -> &str {
+		match self {
+			Self::File(file) => file.path(),
+			Self::Directory(directory) => directory.path(),
+		}
+	}
+
+	/// Get the type of the entry, `"file"` or `"dir"`.
+	#[must_use]
+	pub const fn r#type(&self) -> &'static str {
+		match self {
+			Self::File(_) => "file",
+			Self::Directory(_) => "dir",
+		}
+	}
+
+	#[must_use]
+	pub const fn isFile(&self) -> bool {
+		matches!(self, Self::File(_))
+	}
+
+	#[must_use]
+	pub const fn isDir(&self) -> bool {
+		matches!(self, Self::Directory(_))
+	}
+
+	#[must_use]
+	pub fn visibility(&self) -> Option<&'static str> {
+		match self {
+			Self::File(file) => file.visibility(),
+			Self::Directory(_) => None,
+		}
+	}
+
+	#[must_use]
+	pub const fn lastModified(&self) -> Option<i64> {
+		match self {
+			Self::File(file) => file.lastModified(),
+			Self::Directory(_) => None,
+		}
+	}
+
+	#[must_use]
+	pub const fn fileSize(&self) -> Option<u64> {
+		match self {
+			Self::File(file) => file.fileSize(),
+			Self::Directory(_) => None,
+		}
+	}
+
+	#[must_use]
+	pub fn mimeType(&self) -> Option<&str> {
+		match self {
+			Self::File(file) => file.mimeType(),
+			Self::Directory(_) => None,
+		}
+	}
+}
+
+impl From<StorageEntry> for StorageAttributes {
+	fn from(entry: StorageEntry) -> Self {
+		let path = entry.path.to_string_lossy().into_owned();
+
+		if entry.is_dir {
+			return Self::Directory(DirectoryAttributes { path });
+		}
+
+		Self::File(FileAttributes {
+			path,
+			file_size: entry.size,
+			visibility: entry.visibility,
+			last_modified: entry.last_modified.map(timestamp),
+			mime_type: entry.mime.map(|mime| mime.to_string()),
+		})
+	}
+}
+
+#[allow(clippy::cast_possible_wrap)]
+fn timestamp(time: SystemTime) -> i64 {
+	match time.duration_since(UNIX_EPOCH) {
+		Ok(elapsed) => elapsed.as_secs() as i64,
+		Err(error) => -(error.duration().as_secs() as i64),
+	}
+}
+
+const fn visibility_name(visibility: Visibility) -> &'static str {
+	match visibility {
+		Visibility::Public => "public",
+		Visibility::Private => "private",
+	}
+}
+
+fn parse_visibility(path: &Path, visibility: &str) -> Result<Visibility> {
+	match visibility {
+		"public" => Ok(Visibility::Public),
+		"private" => Ok(Visibility::Private),
+		_ => Err(FlysystemError::UnableToSetVisibility {
+			path: path.to_path_buf(),
+			source: Error::new(
+				ErrorKind::InvalidInput,
+				format!("{visibility} is not a valid visibility."),
+			),
+		}),
+	}
+}
+
+/// Get the options a PHP write config asks for.
+fn write_options(path: &Path, config: &[(&str, &str)]) -> Result<WriteOptions> {
+	let mut options = WriteOptions::default();
+
+	for (key, value) in config {
+		match *key {
+			"visibility" => options.visibility = Some(parse_visibility(path, value)?),
+			"mimetype" => {
+				options.content_type = Some(value.parse::<Mime>().map_err(|error| {
+					FlysystemError::UnableToWriteFile {
+						path: path.to_path_buf(),
+						source: Error::new(ErrorKind::InvalidInput, error),
+					}
+				})?);
+			},
+			_ => {},
+		}
+	}
+
+	Ok(options)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::adapters::MemoryAdapter;
+
+	#[tokio::test]
+	async fn test_php_api() {
+		let filesystem = crate::Filesystem::from_adapter(MemoryAdapter::default()).php();
+
+		filesystem
+			.write(
+				"reports/2024/q1.csv",
+				"revenue,42",
+				&[("visibility", "private"), ("mimetype", "text/csv")],
+			)
+			.await
+			.unwrap();
+		filesystem
+			.write("reports/summary.txt", b"fine", &[])
+			.await
+			.unwrap();
+
+		assert!(filesystem.fileExists("reports/2024/q1.csv").await.unwrap());
+		assert!(filesystem.directoryExists("reports/2024").await.unwrap());
+		assert_eq!(
+			filesystem.visibility("reports/2024/q1.csv").await.unwrap(),
+			"private"
+		);
+		assert_eq!(
+			filesystem.mimeType("reports/2024/q1.csv").await.unwrap(),
+			"text/csv"
+		);
+		assert_eq!(
+			filesystem.read("reports/summary.txt").await.unwrap(),
+			b"fine"
+		);
+
+		let listing = filesystem.listContents("reports", LIST_DEEP).await.unwrap();
+		let files: Vec<&str> = listing
+			.iter()
+			.filter(|item| item.isFile())
+			.map(StorageAttributes::path)
+			.collect();
+		assert_eq!(files, ["reports/2024/q1.csv", "reports/summary.txt"]);
+		assert!(listing
+			.iter()
+			.filter(|item| item.isFile())
+			.all(|item| item.r#type() == "file" && item.fileSize().is_some()));
+
+		assert!(matches!(
+			filesystem
+				.setVisibility("reports/summary.txt", "secret")
+				.await,
+			Err(FlysystemError::UnableToSetVisibility { .. })
+		));
+
+		// deleting what doesn't exist is fine, like in PHP
+		filesystem.delete("reports/missing.csv").await.unwrap();
+		filesystem.deleteDirectory("archive").await.unwrap();
+	}
+}
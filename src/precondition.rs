@@ -0,0 +1,135 @@
+use std::{
+	io::{self, ErrorKind},
+	path::Path,
+	time::SystemTime,
+};
+
+use crate::{adapters::Adapter, Filesystem, FlysystemError, Result};
+
+/// What must hold for a [conditional write](Filesystem::write_if) to go through, so concurrent writers can't silently overwrite each other.
+///
+/// A file that doesn't exist only satisfies [`Precondition::NotExists`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Precondition {
+	/// The file doesn't exist yet.
+	NotExists,
+	/// The file's [checksum](Filesystem::checksum) (its `ETag` on S3) is still this one.
+	MatchesChecksum(String),
+	/// The file hasn't been modified since this time.
+	NotModifiedSince(SystemTime),
+}
+
+impl Precondition {
+	/// Check the precondition against the file at `path`, failing with [`FlysystemError::PreconditionFailed`] if it doesn't hold.
+	///
+	/// The file could still change between the check and the write, so adapters able to write conditionally should do that instead.
+	pub(crate) async fn check<A: Adapter + ?Sized>(
+		&self,
+		adapter: &A,
+		path: &Path,
+	) -> io::Result<()> {
+		let holds = match self {
+			Self::NotExists => !adapter.file_exists(path).await?,
+			Self::MatchesChecksum(checksum) => match adapter.checksum(path).await {
+				Ok(actual) => actual == *checksum,
+				Err(error) if error.kind() == ErrorKind::NotFound => false,
+				Err(error) => return Err(error),
+			},
+			Self::NotModifiedSince(time) => match adapter.last_modified(path).await {
+				Ok(last_modified) => last_modified <= *time,
+				Err(error) if error.kind() == ErrorKind::NotFound => false,
+				Err(error) => return Err(error),
+			},
+		};
+
+		if holds {
+			Ok(())
+		} else {
+			Err(failed(path))
+		}
+	}
+}
+
+/// The error adapters report when a precondition doesn't hold.
+pub fn failed(path: &Path) -> io::Error {
+	FlysystemError::PreconditionFailed {
+		path: path.to_path_buf(),
+	}
+	.into()
+}
+
+impl Filesystem {
+	/// Write a file only if `precondition` holds, like only creating it if it doesn't exist yet,
+	/// or only overwriting it if nobody changed it since it was read.
+	///
+	/// The in-memory and S3 adapters check the precondition atomically with the write, and so does the local adapter for
+	/// [`Precondition::NotExists`]. Otherwise it's checked right before writing, so a concurrent write can still slip in between.
+	///
+	/// # Errors
+	///
+	/// This function will return a [`FlysystemError::PreconditionFailed`] error if the precondition doesn't hold, or an error if
+	/// the adapter fails to write the file, if the configured scanner flags it as infected, or if the configured CDN purger fails
+	/// to invalidate the previous version (in which case the file has already been written).
+	pub async fn write_if(
+		&self,
+		path: &Path,
+		contents: &[u8],
+		precondition: Precondition,
+	) -> Result<()> {
+		self.ensure_clean(path, contents).await?;
+
+		let path = self.normalize(path)?;
+		// creating a file can't replace one, so there's nothing to purge
+		self.write_file(&path, precondition != Precondition::NotExists, || {
+			self.adapter.write_if(&path, contents, &precondition)
+		})
+		.await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::adapters::MemoryAdapter;
+
+	#[tokio::test]
+	async fn test_write_if() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default());
+		let path = Path::new("jobs/lock.json");
+
+		filesystem
+			.write_if(path, b"worker-1", Precondition::NotExists)
+			.await
+			.unwrap();
+		let error = filesystem
+			.write_if(path, b"worker-2", Precondition::NotExists)
+			.await
+			.unwrap_err();
+		assert!(matches!(error, FlysystemError::PreconditionFailed { .. }));
+
+		let checksum = filesystem.checksum(path).await.unwrap();
+		filesystem
+			.write_if(
+				path,
+				b"worker-1, again",
+				Precondition::MatchesChecksum(checksum.clone()),
+			)
+			.await
+			.unwrap();
+		// the file changed since its checksum was read
+		assert!(filesystem
+			.write_if(path, b"worker-2", Precondition::MatchesChecksum(checksum))
+			.await
+			.is_err());
+
+		let read_at = SystemTime::UNIX_EPOCH;
+		assert!(filesystem
+			.write_if(path, b"worker-2", Precondition::NotModifiedSince(read_at))
+			.await
+			.is_err());
+		assert_eq!(
+			filesystem.read::<String>(path).await.unwrap(),
+			"worker-1, again"
+		);
+	}
+}
@@ -0,0 +1,70 @@
+//! The types most code needs, in one import.
+//!
+//! ```rust,ignore
+//! use flysystem::prelude::*;
+//! # use std::path::Path;
+//!
+//! # async fn run() -> FlysystemResult<()> {
+//! let filesystem = Filesystem::from_adapter(CachedAdapter::new(MemoryAdapter::default(), 64 * 1024 * 1024));
+//! filesystem.write(Path::new("hello.txt"), b"Hello, world!").await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Filesystem`] is the same type whatever adapter it's built from, so there are no per-adapter filesystem types.
+//! The configs of the bundled adapters are all named `Config` in their modules, so they're aliased here by adapter instead.
+
+#[cfg(feature = "instrumentation")]
+pub use crate::adapters::InstrumentedAdapter;
+#[cfg(feature = "local")]
+pub use crate::adapters::LocalAdapter;
+#[cfg(feature = "object_store")]
+pub use crate::adapters::ObjectStoreAdapter;
+#[cfg(feature = "s3")]
+pub use crate::adapters::S3Adapter;
+#[cfg(feature = "webdav")]
+pub use crate::adapters::WebDavAdapter;
+#[cfg(feature = "retry")]
+pub use crate::adapters::{RetryAdapter, RetryPolicy};
+pub use crate::{
+	adapters::{
+		Adapter, AdapterInit, CachedAdapter, MemoryAdapter, PathPrefixedAdapter, ReadOnlyAdapter,
+	},
+	BatchMode, Contents, Filesystem, FlysystemError, ListFilter, PathModel, Precondition,
+	StorageEntry, Visibility, WriteOptions,
+};
+
+/// A [`Result`](std::result::Result) failing with a [`FlysystemError`], named so it doesn't shadow the standard one.
+pub type FlysystemResult<T> = crate::Result<T>;
+
+/// The config of a [`LocalAdapter`].
+#[cfg(feature = "local")]
+pub type LocalConfig = crate::adapters::local::Config;
+
+/// The config of an [`S3Adapter`].
+#[cfg(feature = "s3")]
+pub type S3Config = crate::adapters::s3::Config;
+
+/// The config of a [`WebDavAdapter`].
+#[cfg(feature = "webdav")]
+pub type WebDavConfig = crate::adapters::webdav::Config;
+
+#[cfg(test)]
+mod tests {
+	use std::path::Path;
+
+	use super::*;
+
+	#[tokio::test]
+	async fn test_prelude() -> FlysystemResult<()> {
+		let filesystem = Filesystem::from_adapter(ReadOnlyAdapter::new(MemoryAdapter::default()));
+
+		assert_eq!(filesystem.path_model(), PathModel::Hierarchical);
+		assert!(matches!(
+			filesystem.write(Path::new("a.txt"), b"a").await,
+			Err(FlysystemError::UnableToWriteFile { .. })
+		));
+
+		Ok(())
+	}
+}
@@ -0,0 +1,368 @@
+use std::{path::Path, time::Duration};
+
+use crate::{Filesystem, FlysystemError, Result};
+
+/// How many bytes to read from the start of a file when probing it.
+const HEADER_LEN: u64 = 64 * 1024;
+
+/// The largest `moov` box read when probing a video, so a corrupt or hostile size doesn't load the whole file into memory.
+const MAX_MOOV_LEN: u64 = 8 * 1024 * 1024;
+
+/// The kind of media a file contains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+	Image,
+	Video,
+}
+
+/// Basic metadata about an image or video.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MediaInfo {
+	pub kind: MediaKind,
+	pub width: Option<u32>,
+	pub height: Option<u32>,
+	pub duration: Option<Duration>,
+}
+
+impl Filesystem {
+	/// Get the dimensions of an image, or the dimensions and duration of an MP4/MOV video.
+	///
+	/// Only the header of the file is fetched (plus the `moov` box for videos), so probing large videos doesn't require downloading them.
+	/// Returns `None` if the file isn't in a recognized media format.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to read the file.
+	pub async fn probe_media(&self, path: &Path) -> Result<Option<MediaInfo>> {
+		let path = self.normalize(path)?;
+		let header = self
+			.adapter
+			.read_range(&path, 0, HEADER_LEN)
+			.await
+			.map_err(|error| FlysystemError::reading(&path, error))?;
+
+		if let Ok(size) = imagesize::blob_size(&header) {
+			return Ok(Some(MediaInfo {
+				kind: MediaKind::Image,
+				width: u32::try_from(size.width).ok(),
+				height: u32::try_from(size.height).ok(),
+				duration: None,
+			}));
+		}
+
+		if header.get(4..8) == Some(b"ftyp") {
+			return self.probe_mp4(&path, &header).await;
+		}
+
+		Ok(None)
+	}
+
+	/// Walk the top-level boxes of an MP4 file until the `moov` box is found, reading each box header with a ranged read.
+	///
+	/// Returns `None` if a box has an impossible size, since the file is then malformed, or if the `moov` box is larger than [`MAX_MOOV_LEN`].
+	async fn probe_mp4(&self, path: &Path, header: &[u8]) -> Result<Option<MediaInfo>> {
+		let mut info = MediaInfo {
+			kind: MediaKind::Video,
+			width: None,
+			height: None,
+			duration: None,
+		};
+
+		let file_size = self
+			.adapter
+			.file_size(path)
+			.await
+			.map_err(|error| FlysystemError::retrieving_metadata(path, error))?;
+		let read_range = |offset, len| async move {
+			self.adapter
+				.read_range(path, offset, len)
+				.await
+				.map_err(|error| FlysystemError::reading(path, error))
+		};
+		let mut offset: u64 = 0;
+
+		while offset.saturating_add(8) <= file_size {
+			let box_header = match slice(header, offset, 16) {
+				Some(bytes) if bytes.len() == 16 => bytes.to_vec(),
+				_ => read_range(offset, 16).await?.data,
+			};
+
+			let Some((kind, header_len, box_size)) = parse_box_header(&box_header) else {
+				break;
+			};
+			let box_size = box_size.unwrap_or(file_size - offset);
+
+			if &kind == b"moov" {
+				if box_size > MAX_MOOV_LEN {
+					return Ok(None);
+				}
+
+				let moov = match slice(header, offset, box_size) {
+					Some(bytes) if bytes.len() as u64 == box_size => bytes.to_vec(),
+					_ => read_range(offset, box_size).await?.data,
+				};
+
+				if let Some(body) = moov.get(header_len..) {
+					parse_moov(body, &mut info);
+				}
+
+				break;
+			}
+
+			if box_size < header_len as u64 {
+				return Ok(None);
+			}
+			let Some(next) = offset.checked_add(box_size) else {
+				return Ok(None);
+			};
+			offset = next;
+		}
+
+		Ok(Some(info))
+	}
+}
+
+fn slice(data: &[u8], offset: u64, len: u64) -> Option<&[u8]> {
+	let start = usize::try_from(offset).ok()?;
+	let end = usize::try_from(offset.saturating_add(len)).ok()?;
+
+	data.get(start..end.min(data.len()))
+}
+
+/// Parse a box header, returning its type, the length of the header and the size of the whole box (`None` if it extends to the end of the file).
+fn parse_box_header(data: &[u8]) -> Option<([u8; 4], usize, Option<u64>)> {
+	let size = u32::from_be_bytes(data.get(0..4)?.try_into().ok()?);
+	let kind = data.get(4..8)?.try_into().ok()?;
+
+	match size {
+		0 => Some((kind, 8, None)),
+		1 => Some((
+			kind,
+			16,
+			Some(u64::from_be_bytes(data.get(8..16)?.try_into().ok()?)),
+		)),
+		size => Some((kind, 8, Some(u64::from(size)))),
+	}
+}
+
+/// Iterate over the child boxes contained in `data`.
+fn boxes(mut data: &[u8]) -> impl Iterator<Item = ([u8; 4], &[u8])> {
+	std::iter::from_fn(move || {
+		let (kind, header_len, size) = parse_box_header(data)?;
+		let size = size.map_or(data.len(), |size| {
+			usize::try_from(size).unwrap_or(usize::MAX).min(data.len())
+		});
+		let body = data.get(header_len..size)?;
+		data = &data[size..];
+
+		Some((kind, body))
+	})
+}
+
+fn parse_moov(moov: &[u8], info: &mut MediaInfo) {
+	for (kind, body) in boxes(moov) {
+		match &kind {
+			b"mvhd" => info.duration = parse_mvhd(body),
+			b"trak" if info.width.is_none() => {
+				if let Some((width, height)) = boxes(body)
+					.find(|(kind, _)| kind == b"tkhd")
+					.and_then(|(_, tkhd)| parse_tkhd(tkhd))
+				{
+					info.width = Some(width);
+					info.height = Some(height);
+				}
+			},
+			_ => {},
+		}
+	}
+}
+
+fn parse_mvhd(body: &[u8]) -> Option<Duration> {
+	let (timescale, duration) = match body.first()? {
+		1 => (
+			u32::from_be_bytes(body.get(20..24)?.try_into().ok()?),
+			u64::from_be_bytes(body.get(24..32)?.try_into().ok()?),
+		),
+		_ => (
+			u32::from_be_bytes(body.get(12..16)?.try_into().ok()?),
+			u64::from(u32::from_be_bytes(body.get(16..20)?.try_into().ok()?)),
+		),
+	};
+
+	if timescale == 0 {
+		return None;
+	}
+
+	let nanos = u128::from(duration) * 1_000_000_000 / u128::from(timescale);
+	Some(Duration::from_nanos(u64::try_from(nanos).ok()?))
+}
+
+/// Audio tracks have a zero width and height, so only return dimensions for visual tracks.
+fn parse_tkhd(body: &[u8]) -> Option<(u32, u32)> {
+	let end = body.len();
+	let width = u32::from_be_bytes(body.get(end.checked_sub(8)?..end - 4)?.try_into().ok()?) >> 16;
+	let height = u32::from_be_bytes(body.get(end - 4..)?.try_into().ok()?) >> 16;
+
+	(width > 0 && height > 0).then_some((width, height))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::adapters::MemoryAdapter;
+
+	fn mp4_box(kind: &[u8], body: &[u8]) -> Vec<u8> {
+		let mut data = u32::try_from(body.len() + 8)
+			.unwrap()
+			.to_be_bytes()
+			.to_vec();
+		data.extend_from_slice(kind);
+		data.extend_from_slice(body);
+		data
+	}
+
+	fn moov() -> Vec<u8> {
+		let mut mvhd = vec![0; 100];
+		mvhd[12..16].copy_from_slice(&1000u32.to_be_bytes());
+		mvhd[16..20].copy_from_slice(&5500u32.to_be_bytes());
+
+		let mut tkhd = vec![0; 84];
+		tkhd[76..80].copy_from_slice(&(1920u32 << 16).to_be_bytes());
+		tkhd[80..84].copy_from_slice(&(1080u32 << 16).to_be_bytes());
+
+		let mut body = mp4_box(b"mvhd", &mvhd);
+		body.extend(mp4_box(b"trak", &mp4_box(b"tkhd", &[0; 84])));
+		body.extend(mp4_box(b"trak", &mp4_box(b"tkhd", &tkhd)));
+
+		mp4_box(b"moov", &body)
+	}
+
+	#[tokio::test]
+	async fn test_probe_image() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+
+		let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+		png.extend_from_slice(&640u32.to_be_bytes());
+		png.extend_from_slice(&480u32.to_be_bytes());
+		png.extend_from_slice(&[8, 6, 0, 0, 0, 0, 0, 0, 0]);
+		filesystem
+			.write(Path::new("image.png"), &png)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			filesystem
+				.probe_media(Path::new("image.png"))
+				.await
+				.unwrap(),
+			Some(MediaInfo {
+				kind: MediaKind::Image,
+				width: Some(640),
+				height: Some(480),
+				duration: None,
+			})
+		);
+	}
+
+	#[tokio::test]
+	async fn test_probe_video_with_moov_after_media_data() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+
+		let mut mp4 = mp4_box(b"ftyp", b"isom\0\0\x02\0isomiso2mp41");
+		mp4.extend(mp4_box(
+			b"mdat",
+			&vec![0; 2 * usize::try_from(HEADER_LEN).unwrap()],
+		));
+		mp4.extend(moov());
+		filesystem
+			.write(Path::new("video.mp4"), &mp4)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			filesystem
+				.probe_media(Path::new("video.mp4"))
+				.await
+				.unwrap(),
+			Some(MediaInfo {
+				kind: MediaKind::Video,
+				width: Some(1920),
+				height: Some(1080),
+				duration: Some(Duration::from_millis(5500)),
+			})
+		);
+	}
+
+	#[tokio::test]
+	async fn test_probe_video_with_malformed_box() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+
+		// a 64-bit box size that overflows the offset of the next box
+		let mut mp4 = mp4_box(b"ftyp", b"isom\0\0\x02\0isomiso2mp41");
+		mp4.extend(1u32.to_be_bytes());
+		mp4.extend(b"free");
+		mp4.extend(u64::MAX.to_be_bytes());
+		mp4.extend(moov());
+		filesystem
+			.write(Path::new("overflow.mp4"), &mp4)
+			.await
+			.unwrap();
+
+		// a zero-sized box
+		let mut zero = mp4_box(b"ftyp", b"isom\0\0\x02\0isomiso2mp41");
+		zero.extend(1u32.to_be_bytes());
+		zero.extend(b"free");
+		zero.extend(0u64.to_be_bytes());
+		zero.extend(moov());
+		filesystem
+			.write(Path::new("zero.mp4"), &zero)
+			.await
+			.unwrap();
+
+		for path in ["overflow.mp4", "zero.mp4"] {
+			assert_eq!(filesystem.probe_media(Path::new(path)).await.unwrap(), None);
+		}
+	}
+
+	#[tokio::test]
+	async fn test_probe_video_with_oversized_moov() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+
+		// a 64-bit box size just over the limit, on a file that's much smaller
+		let mut mp4 = mp4_box(b"ftyp", b"isom\0\0\x02\0isomiso2mp41");
+		mp4.extend(1u32.to_be_bytes());
+		mp4.extend(b"moov");
+		mp4.extend((MAX_MOOV_LEN + 1).to_be_bytes());
+		mp4.extend(&moov()[8..]);
+		filesystem
+			.write(Path::new("oversized.mp4"), &mp4)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			filesystem
+				.probe_media(Path::new("oversized.mp4"))
+				.await
+				.unwrap(),
+			None
+		);
+	}
+
+	#[tokio::test]
+	async fn test_probe_unknown_format() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+
+		filesystem
+			.write(Path::new("notes.txt"), b"Hello, world!")
+			.await
+			.unwrap();
+
+		assert_eq!(
+			filesystem
+				.probe_media(Path::new("notes.txt"))
+				.await
+				.unwrap(),
+			None
+		);
+	}
+}
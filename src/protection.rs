@@ -0,0 +1,170 @@
+use std::{
+	io::ErrorKind,
+	path::{Path, PathBuf},
+};
+
+use crate::{normalizer::normalize, Filesystem, FlysystemError, Result};
+
+/// The metadata key marking a file as protected from deletion, when set to `true` (see [`Filesystem::protect_flagged_files`]).
+pub const PROTECTED_METADATA: &str = "protected";
+
+/// The paths a [`Filesystem`] refuses to delete.
+#[derive(Debug, Clone, Default)]
+pub struct Protection {
+	prefixes: Vec<PathBuf>,
+	flagged_files: bool,
+	overridden: bool,
+}
+
+impl Filesystem {
+	/// Refuse to delete the file or directory at `prefix` (and everything under it) with a [`FlysystemError::Protected`] error,
+	/// like templates a cleanup job should never touch. Deleting a directory containing it is refused too.
+	///
+	/// This covers [`Filesystem::delete`], [`Filesystem::delete_directory`], [`Filesystem::delete_many`], [`Filesystem::prune`]
+	/// and transactions, unless the deletion goes through [`Filesystem::overriding_protection`].
+	#[must_use]
+	pub fn protect(mut self, prefix: impl AsRef<Path>) -> Self {
+		self.protection.prefixes.push(normalize(prefix.as_ref()));
+		self
+	}
+
+	/// Also refuse to delete files whose [`PROTECTED_METADATA`] is `true`, on adapters storing metadata.
+	///
+	/// This costs a metadata lookup per deleted file. Flagged files are only checked when deleted one by one,
+	/// so deleting a directory containing them isn't refused.
+	#[must_use]
+	pub const fn protect_flagged_files(mut self) -> Self {
+		self.protection.flagged_files = true;
+		self
+	}
+
+	/// Get a clone of this filesystem allowing protected files to be deleted, for the rare times they really have to be.
+	#[must_use]
+	pub fn overriding_protection(&self) -> Self {
+		let mut filesystem = self.clone();
+		filesystem.protection.overridden = true;
+
+		filesystem
+	}
+
+	/// Fail if the file at `path` (already normalized) is protected from deletion.
+	pub(crate) async fn ensure_deletable(&self, path: &Path) -> Result<()> {
+		let protection = &self.protection;
+		if protection.overridden {
+			return Ok(());
+		}
+
+		if protection
+			.prefixes
+			.iter()
+			.any(|prefix| path.starts_with(prefix))
+		{
+			return Err(FlysystemError::Protected {
+				path: path.to_path_buf(),
+			});
+		}
+
+		if !protection.flagged_files {
+			return Ok(());
+		}
+
+		match self.adapter.metadata(path).await {
+			Ok(metadata)
+				if metadata
+					.get(PROTECTED_METADATA)
+					.is_some_and(|flag| flag == "true") =>
+			{
+				Err(FlysystemError::Protected {
+					path: path.to_path_buf(),
+				})
+			},
+			Err(error) if !matches!(error.kind(), ErrorKind::NotFound | ErrorKind::Unsupported) => {
+				Err(FlysystemError::retrieving_metadata(path, error))
+			},
+			_ => Ok(()),
+		}
+	}
+
+	/// Fail if the directory at `path` (already normalized) is protected, or contains a protected path.
+	pub(crate) fn ensure_directory_deletable(&self, path: &Path) -> Result<()> {
+		let protection = &self.protection;
+
+		if !protection.overridden
+			&& protection
+				.prefixes
+				.iter()
+				.any(|prefix| path.starts_with(prefix) || prefix.starts_with(path))
+		{
+			return Err(FlysystemError::Protected {
+				path: path.to_path_buf(),
+			});
+		}
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{collections::HashMap, time::SystemTime};
+
+	use super::*;
+	use crate::{adapters::MemoryAdapter, BatchMode, PruneOptions, WriteOptions};
+
+	#[tokio::test]
+	async fn test_protected_paths() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default())
+			.protect("/templates")
+			.protect_flagged_files();
+
+		for path in ["templates/email.html", "cache/a.bin", "cache/b.bin"] {
+			filesystem.write(Path::new(path), b"").await.unwrap();
+		}
+		filesystem
+			.write_with(
+				Path::new("cache/keep.bin"),
+				b"",
+				WriteOptions {
+					metadata: HashMap::from([(PROTECTED_METADATA.to_string(), "true".to_string())]),
+					..WriteOptions::default()
+				},
+			)
+			.await
+			.unwrap();
+
+		let error = filesystem
+			.delete(Path::new("templates/email.html"))
+			.await
+			.unwrap_err();
+		assert!(matches!(error, FlysystemError::Protected { .. }));
+		assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+		assert!(filesystem.delete_directory(Path::new("/")).await.is_err());
+
+		let report = filesystem
+			.delete_many(["cache/a.bin", "cache/keep.bin"], BatchMode::CollectAll)
+			.await;
+		assert_eq!(report.succeeded, [PathBuf::from("cache/a.bin")]);
+		assert_eq!(report.failed[0].0, Path::new("cache/keep.bin"));
+
+		// pruning skips protected files instead of failing
+		let pruned = filesystem
+			.prune(
+				Path::new("cache"),
+				SystemTime::now(),
+				PruneOptions::default(),
+			)
+			.await
+			.unwrap();
+		assert_eq!(pruned, [PathBuf::from("cache/b.bin")]);
+		assert!(filesystem
+			.file_exists(Path::new("cache/keep.bin"))
+			.await
+			.unwrap());
+
+		filesystem
+			.overriding_protection()
+			.delete_directory(Path::new("templates"))
+			.await
+			.unwrap();
+	}
+}
@@ -0,0 +1,204 @@
+use futures::{StreamExt, TryStreamExt};
+use std::{
+	collections::BTreeMap,
+	path::{Path, PathBuf},
+	time::SystemTime,
+};
+
+use crate::{relative_to, Filesystem, FlysystemError, Result};
+
+/// How many files have their last modified time fetched at once while pruning.
+const CONCURRENCY: usize = 8;
+
+/// How old files are pruned.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PruneOptions {
+	/// How many of the newest files of each directory are kept, even if they're older than the cutoff.
+	pub keep_at_least: usize,
+	/// Only report which files would be deleted, without deleting them.
+	pub dry_run: bool,
+}
+
+impl Filesystem {
+	/// Delete the files under `prefix` last modified before `older_than`, like rotated logs or exports.
+	///
+	/// The newest `keep_at_least` files of each directory are always kept, so a directory whose files all went stale
+	/// (for example because the job writing them stopped) is never emptied. [Protected](Filesystem::protect) files are skipped.
+	/// Returns the pruned files, sorted.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter fails to list the directory, get a file's last modified time, or delete a file.
+	/// Files deleted before the error are not restored.
+	pub async fn prune(
+		&self,
+		prefix: &Path,
+		older_than: SystemTime,
+		options: PruneOptions,
+	) -> Result<Vec<PathBuf>> {
+		let prefix = self.normalize(prefix)?;
+		let mut entries: Vec<PathBuf> = self
+			.adapter
+			.list_contents(&prefix, true)
+			.await
+			.map_err(|error| FlysystemError::listing(&prefix, error))?
+			.iter()
+			.map(|entry| prefix.join(relative_to(entry, &prefix)))
+			.collect();
+		entries.sort();
+		entries.dedup();
+
+		let adapter = &self.adapter;
+		let files: Vec<(PathBuf, SystemTime)> = futures::stream::iter(entries)
+			.map(|path| async move {
+				let last_modified = adapter
+					.last_modified(&path)
+					.await
+					.map_err(|error| FlysystemError::retrieving_metadata(&path, error))?;
+
+				Ok::<_, FlysystemError>((path, last_modified))
+			})
+			.buffered(CONCURRENCY)
+			.try_collect()
+			.await?;
+
+		let mut directories: BTreeMap<PathBuf, Vec<(PathBuf, SystemTime)>> = BTreeMap::new();
+		for (path, last_modified) in files {
+			directories
+				.entry(path.parent().map(Path::to_path_buf).unwrap_or_default())
+				.or_default()
+				.push((path, last_modified));
+		}
+
+		let mut pruned: Vec<PathBuf> = directories
+			.into_values()
+			.flat_map(|mut files| {
+				// newest first, so the files kept regardless of their age are the most recent ones
+				files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+				files
+					.into_iter()
+					.skip(options.keep_at_least)
+					.filter(|(_, last_modified)| *last_modified < older_than)
+					.map(|(path, _)| path)
+			})
+			.collect();
+		pruned.sort();
+
+		// protected files are left alone, so they don't stop the cleanup of the rest
+		let mut deletable = Vec::with_capacity(pruned.len());
+		for path in pruned {
+			match self.ensure_deletable(&path).await {
+				Ok(()) => deletable.push(path),
+				Err(FlysystemError::Protected { .. }) => {},
+				Err(error) => return Err(error),
+			}
+		}
+		let pruned = deletable;
+
+		if !options.dry_run {
+			for path in &pruned {
+				self.delete(path).await?;
+			}
+		}
+
+		Ok(pruned)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::*;
+	use crate::{
+		adapters::MemoryAdapter,
+		clock::{Clock, MockClock},
+	};
+
+	async fn filesystem() -> (Filesystem, SystemTime) {
+		let clock = MockClock::default();
+		let filesystem =
+			Filesystem::from_adapter(MemoryAdapter::default().with_clock(clock.clone()));
+
+		for path in [
+			"logs/api/1.log",
+			"logs/api/2.log",
+			"logs/api/3.log",
+			"logs/worker/1.log",
+		] {
+			filesystem
+				.write(Path::new(path), b"log line")
+				.await
+				.unwrap();
+			clock.advance(Duration::from_mins(1));
+		}
+
+		let cutoff = clock.now();
+		clock.advance(Duration::from_mins(1));
+		for path in ["logs/api/4.log", "logs/index.txt"] {
+			filesystem
+				.write(Path::new(path), b"log line")
+				.await
+				.unwrap();
+		}
+
+		(filesystem, cutoff)
+	}
+
+	#[tokio::test]
+	async fn test_prune() {
+		let (filesystem, cutoff) = filesystem().await;
+
+		let pruned = filesystem
+			.prune(Path::new("logs"), cutoff, PruneOptions::default())
+			.await
+			.unwrap();
+
+		assert_eq!(
+			pruned,
+			vec![
+				PathBuf::from("logs/api/1.log"),
+				PathBuf::from("logs/api/2.log"),
+				PathBuf::from("logs/api/3.log"),
+				PathBuf::from("logs/worker/1.log"),
+			]
+		);
+		assert_eq!(
+			filesystem
+				.list_contents(Path::new("logs"), true)
+				.await
+				.unwrap(),
+			vec![
+				PathBuf::from("logs/api/4.log"),
+				PathBuf::from("logs/index.txt")
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_prune_keeps_newest_files_per_directory() {
+		let (filesystem, cutoff) = filesystem().await;
+
+		let options = PruneOptions {
+			keep_at_least: 2,
+			dry_run: true,
+		};
+		let pruned = filesystem
+			.prune(Path::new("logs"), cutoff, options)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			pruned,
+			vec![
+				PathBuf::from("logs/api/1.log"),
+				PathBuf::from("logs/api/2.log")
+			]
+		);
+		assert!(filesystem
+			.file_exists(Path::new("logs/api/1.log"))
+			.await
+			.unwrap());
+	}
+}
@@ -0,0 +1,714 @@
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use std::{
+	fmt::Debug,
+	io::{Error, ErrorKind, Result},
+	path::{Path, PathBuf},
+	time::Duration,
+};
+use url::Url;
+
+use crate::{adapters::Sharding, Filesystem};
+
+/// The characters escaped in each segment of a path turned into a URL, so a file name can't end the path early
+/// (`?`, `#`), read as an escape (`%`) or a scheme (`name:`). `*` is kept, for `CloudFront` wildcard policies.
+const PATH_SEGMENT: &AsciiSet = &CONTROLS
+	.add(b' ')
+	.add(b'"')
+	.add(b'#')
+	.add(b'%')
+	.add(b'/')
+	.add(b':')
+	.add(b'<')
+	.add(b'>')
+	.add(b'?')
+	.add(b'[')
+	.add(b'\\')
+	.add(b']')
+	.add(b'^')
+	.add(b'`')
+	.add(b'{')
+	.add(b'|')
+	.add(b'}');
+
+/// Get the URL of a file relative to `base_url`, percent-encoding each segment of its path.
+pub(crate) fn join_path(base_url: &Url, path: &Path) -> Result<Url> {
+	let path = path
+		.to_str()
+		.ok_or_else(|| Error::new(ErrorKind::InvalidData, "path is not valid utf-8"))?;
+	let encoded = path
+		.split('/')
+		.filter(|segment| !segment.is_empty())
+		.map(|segment| utf8_percent_encode(segment, PATH_SEGMENT).to_string())
+		.collect::<Vec<_>>()
+		.join("/");
+
+	let mut url = base_url.clone();
+	if !url.path().ends_with('/') {
+		url.set_path(&format!("{}/", url.path()));
+	}
+
+	url.join(&encoded)
+		.map_err(|e| Error::new(ErrorKind::InvalidInput, e))
+}
+
+/// What [`Filesystem::public_url`] does for private files, whose public URL would be refused by the storage.
+///
+/// Files whose visibility is unknown (because they don't exist, or the adapter doesn't store it) always get a public URL.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PrivateFiles {
+	/// Fail with a [`FlysystemError::FileNotPublic`](crate::FlysystemError::FileNotPublic) error.
+	#[default]
+	Refuse,
+	/// Return a temporary URL expiring after the given duration instead.
+	TemporaryUrl(Duration),
+	/// Return the public URL anyway, without looking up the visibility (like when a CDN with its own access to the bucket serves them).
+	Allow,
+}
+
+/// A strategy for turning storage paths into public URLs, configured per [`Filesystem`].
+#[async_trait::async_trait]
+pub trait UrlStrategy: Debug + Send + Sync {
+	/// Get the public URL of a file.
+	async fn public_url(&self, filesystem: &Filesystem, path: &Path) -> Result<Url>;
+
+	/// Recover the path of a file from one of its public URLs, validating its signature if it has one.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the URL wasn't generated by this strategy, or if its signature is invalid or expired.
+	fn path_for_url(&self, url: &Url) -> Result<PathBuf> {
+		Err(Error::new(
+			ErrorKind::Unsupported,
+			format!("The URL strategy can't map {url} back to a path."),
+		))
+	}
+}
+
+/// Serve files from a base URL, like a CDN domain (`https://cdn.example.com/`) or a path-style bucket URL (`https://s3.amazonaws.com/bucket/`).
+#[derive(Debug, Clone)]
+pub struct BaseUrl {
+	base_url: Url,
+}
+
+impl BaseUrl {
+	/// Map paths to URLs relative to `base_url`.
+	#[must_use]
+	pub fn new(mut base_url: Url) -> Self {
+		if !base_url.path().ends_with('/') {
+			base_url.set_path(&format!("{}/", base_url.path()));
+		}
+
+		Self { base_url }
+	}
+
+	/// Get the URL of a file, without accessing the filesystem.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the path is not valid UTF-8.
+	pub fn url(&self, path: &Path) -> Result<Url> {
+		join_path(&self.base_url, path)
+	}
+
+	/// Get the path of a file from its URL, ignoring the query string.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the URL isn't under the base URL.
+	pub fn path(&self, url: &Url) -> Result<PathBuf> {
+		let path = (url.origin() == self.base_url.origin())
+			.then(|| url.path().strip_prefix(self.base_url.path()))
+			.flatten()
+			.filter(|path| !path.is_empty())
+			.ok_or_else(|| {
+				Error::new(
+					ErrorKind::InvalidInput,
+					format!("{url} is not under {}.", self.base_url),
+				)
+			})?;
+
+		percent_encoding::percent_decode_str(path)
+			.decode_utf8()
+			.map(|path| PathBuf::from(path.as_ref()))
+			.map_err(|e| Error::new(ErrorKind::InvalidData, e))
+	}
+}
+
+#[async_trait::async_trait]
+impl UrlStrategy for BaseUrl {
+	async fn public_url(&self, _: &Filesystem, path: &Path) -> Result<Url> {
+		self.url(path)
+	}
+
+	fn path_for_url(&self, url: &Url) -> Result<PathBuf> {
+		self.path(url)
+	}
+}
+
+/// Append the file's checksum to the URLs of another strategy (as `?v=<checksum>`), so caches are busted whenever the file changes.
+#[derive(Debug, Clone)]
+pub struct Versioned<S> {
+	inner: S,
+	param: String,
+}
+
+impl<S: UrlStrategy> Versioned<S> {
+	/// Version the URLs generated by `inner`.
+	#[must_use]
+	pub fn new(inner: S) -> Self {
+		Self {
+			inner,
+			param: "v".to_string(),
+		}
+	}
+
+	/// Use a different query parameter for the version.
+	#[must_use]
+	pub fn with_param(mut self, param: impl Into<String>) -> Self {
+		self.param = param.into();
+		self
+	}
+}
+
+#[async_trait::async_trait]
+impl<S: UrlStrategy> UrlStrategy for Versioned<S> {
+	async fn public_url(&self, filesystem: &Filesystem, path: &Path) -> Result<Url> {
+		let (url, checksum) = futures::future::join(
+			self.inner.public_url(filesystem, path),
+			filesystem.checksum(path),
+		)
+		.await;
+
+		let mut url = url?;
+		url.query_pairs_mut()
+			.append_pair(&self.param, checksum?.trim_matches('"'));
+
+		Ok(url)
+	}
+
+	fn path_for_url(&self, url: &Url) -> Result<PathBuf> {
+		self.inner.path_for_url(url)
+	}
+}
+
+/// Map paths to the ones a [`ShardedAdapter`](crate::adapters::ShardedAdapter) stores them at before generating URLs with another strategy.
+#[derive(Debug, Clone)]
+pub struct Sharded<S> {
+	inner: S,
+	sharding: Sharding,
+}
+
+impl<S: UrlStrategy> Sharded<S> {
+	/// Shard the paths given to `inner`, the same way the adapter does.
+	#[must_use]
+	pub const fn new(inner: S, sharding: Sharding) -> Self {
+		Self { inner, sharding }
+	}
+}
+
+#[async_trait::async_trait]
+impl<S: UrlStrategy> UrlStrategy for Sharded<S> {
+	async fn public_url(&self, filesystem: &Filesystem, path: &Path) -> Result<Url> {
+		self.inner
+			.public_url(filesystem, &self.sharding.path(path))
+			.await
+	}
+
+	fn path_for_url(&self, url: &Url) -> Result<PathBuf> {
+		let path = self.inner.path_for_url(url)?;
+
+		self.sharding.original(&path).ok_or_else(|| {
+			Error::new(
+				ErrorKind::InvalidInput,
+				format!("{url} doesn't point to a sharded path."),
+			)
+		})
+	}
+}
+
+#[cfg(feature = "cloudfront")]
+pub use cloudfront::CloudFrontSigner;
+
+#[cfg(feature = "cloudfront")]
+mod cloudfront {
+	use base64::{engine::general_purpose::STANDARD, Engine};
+	use rsa::{
+		pkcs1::DecodeRsaPrivateKey,
+		pkcs1v15::{Signature, SigningKey},
+		pkcs8::DecodePrivateKey,
+		signature::{Keypair, SignatureEncoding, Signer, Verifier},
+		RsaPrivateKey,
+	};
+	use sha1::Sha1;
+	use std::{
+		fmt,
+		io::{Error, ErrorKind, Result},
+		path::{Path, PathBuf},
+		time::{Duration, SystemTime, UNIX_EPOCH},
+	};
+	use url::Url;
+
+	use super::{BaseUrl, UrlStrategy};
+	use crate::{Filesystem, REDACTED};
+
+	/// Serve files through `CloudFront` with signed URLs (using a canned policy), or grant access to many files at once with signed cookies.
+	#[derive(Clone)]
+	pub struct CloudFrontSigner {
+		base_url: BaseUrl,
+		key_pair_id: String,
+		expires_in: Duration,
+		signing_key: SigningKey<Sha1>,
+	}
+
+	impl fmt::Debug for CloudFrontSigner {
+		fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+			f.debug_struct("CloudFrontSigner")
+				.field("base_url", &self.base_url)
+				.field("key_pair_id", &self.key_pair_id)
+				.field("expires_in", &self.expires_in)
+				.field("signing_key", &REDACTED)
+				.finish()
+		}
+	}
+
+	impl CloudFrontSigner {
+		/// Create a signer for the distribution at `base_url`, using the PEM-encoded (PKCS#1 or PKCS#8) private key of a `CloudFront` key pair.
+		///
+		/// # Errors
+		///
+		/// This function will return an error if the private key can't be parsed.
+		pub fn new(
+			base_url: Url,
+			key_pair_id: impl Into<String>,
+			private_key: &str,
+			expires_in: Duration,
+		) -> Result<Self> {
+			let private_key = RsaPrivateKey::from_pkcs1_pem(private_key)
+				.or_else(|_| RsaPrivateKey::from_pkcs8_pem(private_key))
+				.map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+
+			Ok(Self {
+				expires_in,
+				base_url: BaseUrl::new(base_url),
+				key_pair_id: key_pair_id.into(),
+				signing_key: SigningKey::new(private_key),
+			})
+		}
+
+		/// Sign the URL of a file, valid until `expires_at`.
+		///
+		/// # Errors
+		///
+		/// This function will return an error if the path is not valid UTF-8.
+		pub fn signed_url(&self, path: &Path, expires_at: SystemTime) -> Result<Url> {
+			let mut url = self.base_url.url(path)?;
+			let expires_at = epoch_seconds(expires_at);
+			let policy = canned_policy(&url, expires_at);
+
+			url.query_pairs_mut()
+				.append_pair("Expires", &expires_at.to_string())
+				.append_pair("Signature", &self.sign(&policy))
+				.append_pair("Key-Pair-Id", &self.key_pair_id);
+
+			Ok(url)
+		}
+
+		/// Build the `CloudFront-Policy`, `CloudFront-Signature` and `CloudFront-Key-Pair-Id` cookies granting access to every file matching `pattern` (like `videos/*`) until `expires_at`.
+		///
+		/// # Errors
+		///
+		/// This function will return an error if the pattern is not valid UTF-8.
+		pub fn signed_cookies(
+			&self,
+			pattern: &Path,
+			expires_at: SystemTime,
+		) -> Result<Vec<(&'static str, String)>> {
+			let resource = self.base_url.url(pattern)?;
+			let policy = format!(
+				r#"{{"Statement":[{{"Resource":"{}","Condition":{{"DateLessThan":{{"AWS:EpochTime":{}}}}}}}]}}"#,
+				resource.as_str().replace("%2A", "*"),
+				epoch_seconds(expires_at)
+			);
+
+			Ok(vec![
+				("CloudFront-Policy", encode(policy.as_bytes())),
+				("CloudFront-Signature", self.sign(&policy)),
+				("CloudFront-Key-Pair-Id", self.key_pair_id.clone()),
+			])
+		}
+
+		/// Validate a signed URL, returning the path of the file it grants access to.
+		///
+		/// # Errors
+		///
+		/// This function will return an error if the URL isn't under the distribution, or if its signature is missing, invalid or expired.
+		pub fn verify_url(&self, url: &Url) -> Result<PathBuf> {
+			let param = |name: &str| {
+				url.query_pairs()
+					.find(|(key, _)| key == name)
+					.map(|(_, value)| value.into_owned())
+			};
+			let denied =
+				|message: &str| Error::new(ErrorKind::PermissionDenied, message.to_string());
+
+			let expires_at = param("Expires")
+				.and_then(|expires_at| expires_at.parse::<u64>().ok())
+				.ok_or_else(|| denied("The URL is not signed."))?;
+			if param("Key-Pair-Id").as_deref() != Some(self.key_pair_id.as_str()) {
+				return Err(denied("The URL was signed with a different key pair."));
+			}
+
+			let mut unsigned = url.clone();
+			unsigned.set_query(None);
+			let signature = param("Signature")
+				.and_then(|signature| decode(&signature))
+				.and_then(|signature| Signature::try_from(signature.as_slice()).ok())
+				.ok_or_else(|| denied("The URL signature is malformed."))?;
+
+			self.signing_key
+				.verifying_key()
+				.verify(canned_policy(&unsigned, expires_at).as_bytes(), &signature)
+				.map_err(|_| denied("The URL signature is invalid."))?;
+
+			if expires_at <= epoch_seconds(SystemTime::now()) {
+				return Err(denied("The signed URL has expired."));
+			}
+
+			self.base_url.path(&unsigned)
+		}
+
+		fn sign(&self, policy: &str) -> String {
+			encode(&self.signing_key.sign(policy.as_bytes()).to_vec())
+		}
+	}
+
+	#[async_trait::async_trait]
+	impl UrlStrategy for CloudFrontSigner {
+		async fn public_url(&self, _: &Filesystem, path: &Path) -> Result<Url> {
+			self.signed_url(path, SystemTime::now() + self.expires_in)
+		}
+
+		fn path_for_url(&self, url: &Url) -> Result<PathBuf> {
+			self.verify_url(url)
+		}
+	}
+
+	fn canned_policy(url: &Url, expires_at: u64) -> String {
+		format!(
+			r#"{{"Statement":[{{"Resource":"{url}","Condition":{{"DateLessThan":{{"AWS:EpochTime":{expires_at}}}}}}}]}}"#
+		)
+	}
+
+	fn epoch_seconds(time: SystemTime) -> u64 {
+		time.duration_since(UNIX_EPOCH)
+			.map_or(0, |elapsed| elapsed.as_secs())
+	}
+
+	/// `CloudFront`'s URL-safe base64 variant, which swaps `+`, `=` and `/` for `-`, `_` and `~`.
+	fn encode(data: &[u8]) -> String {
+		STANDARD
+			.encode(data)
+			.replace('+', "-")
+			.replace('=', "_")
+			.replace('/', "~")
+	}
+
+	fn decode(data: &str) -> Option<Vec<u8>> {
+		STANDARD
+			.decode(data.replace('-', "+").replace('_', "=").replace('~', "/"))
+			.ok()
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use rsa::pkcs1v15::VerifyingKey;
+
+		use super::*;
+
+		const PRIVATE_KEY: &str = include_str!("../tests/fixtures/cloudfront.pem");
+
+		fn signer() -> CloudFrontSigner {
+			CloudFrontSigner::new(
+				Url::parse("https://d111111abcdef8.cloudfront.net").unwrap(),
+				"K2JCJMDEHXQW5F",
+				PRIVATE_KEY,
+				Duration::from_hours(1),
+			)
+			.unwrap()
+		}
+
+		#[test]
+		fn test_signed_url() {
+			let signer = signer();
+			let url = signer
+				.signed_url(
+					Path::new("videos/intro.mp4"),
+					UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+				)
+				.unwrap();
+
+			assert!(url
+				.as_str()
+				.starts_with("https://d111111abcdef8.cloudfront.net/videos/intro.mp4?Expires=1700000000&Signature="));
+			assert!(url.as_str().ends_with("&Key-Pair-Id=K2JCJMDEHXQW5F"));
+
+			let signature = url
+				.query_pairs()
+				.find(|(key, _)| key == "Signature")
+				.unwrap()
+				.1;
+			let policy = r#"{"Statement":[{"Resource":"https://d111111abcdef8.cloudfront.net/videos/intro.mp4","Condition":{"DateLessThan":{"AWS:EpochTime":1700000000}}}]}"#;
+
+			VerifyingKey::<Sha1>::new(signer.signing_key.as_ref().to_public_key())
+				.verify(
+					policy.as_bytes(),
+					&Signature::try_from(decode(&signature).unwrap().as_slice()).unwrap(),
+				)
+				.unwrap();
+		}
+
+		#[test]
+		fn test_verify_url() {
+			let signer = signer();
+			let expires_at = SystemTime::now() + Duration::from_mins(5);
+			let url = signer
+				.signed_url(Path::new("videos/intro.mp4"), expires_at)
+				.unwrap();
+
+			assert_eq!(
+				signer.verify_url(&url).unwrap(),
+				PathBuf::from("videos/intro.mp4")
+			);
+
+			let mut tampered = url;
+			tampered.set_path("/videos/secret.mp4");
+			assert_eq!(
+				signer.verify_url(&tampered).unwrap_err().kind(),
+				ErrorKind::PermissionDenied
+			);
+
+			let expired = signer
+				.signed_url(
+					Path::new("videos/intro.mp4"),
+					UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+				)
+				.unwrap();
+			assert_eq!(
+				signer.verify_url(&expired).unwrap_err().to_string(),
+				"The signed URL has expired."
+			);
+		}
+
+		#[test]
+		fn test_signed_cookies() {
+			let cookies = signer()
+				.signed_cookies(
+					Path::new("videos/*"),
+					UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+				)
+				.unwrap();
+
+			assert_eq!(cookies[0].0, "CloudFront-Policy");
+			assert_eq!(
+				String::from_utf8(decode(&cookies[0].1).unwrap()).unwrap(),
+				r#"{"Statement":[{"Resource":"https://d111111abcdef8.cloudfront.net/videos/*","Condition":{"DateLessThan":{"AWS:EpochTime":1700000000}}}]}"#
+			);
+			assert_eq!(
+				cookies[2],
+				("CloudFront-Key-Pair-Id", "K2JCJMDEHXQW5F".to_string())
+			);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{adapters::MemoryAdapter, FlysystemError, Visibility, WriteOptions};
+
+	#[tokio::test]
+	async fn test_base_url() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(())
+			.await
+			.unwrap()
+			.with_url_strategy(BaseUrl::new(
+				Url::parse("https://cdn.example.com/assets").unwrap(),
+			));
+
+		assert_eq!(
+			filesystem
+				.public_url(Path::new("/css/app.css"))
+				.await
+				.unwrap()
+				.as_str(),
+			"https://cdn.example.com/assets/css/app.css"
+		);
+	}
+
+	#[test]
+	fn test_special_characters() {
+		let base_url = BaseUrl::new(Url::parse("https://cdn.example.com/assets/").unwrap());
+
+		for (path, url) in [
+			(
+				"docs/notes?draft#1 100%.txt",
+				"https://cdn.example.com/assets/docs/notes%3Fdraft%231%20100%25.txt",
+			),
+			(
+				"mailto:report.pdf",
+				"https://cdn.example.com/assets/mailto%3Areport.pdf",
+			),
+			(
+				"caf\u{e9}.txt",
+				"https://cdn.example.com/assets/caf%C3%A9.txt",
+			),
+		] {
+			let generated = base_url.url(Path::new(path)).unwrap();
+
+			assert_eq!(generated.as_str(), url);
+			assert_eq!(base_url.path(&generated).unwrap(), PathBuf::from(path));
+		}
+	}
+
+	#[tokio::test]
+	async fn test_path_for_url() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(())
+			.await
+			.unwrap()
+			.with_url_strategy(Versioned::new(BaseUrl::new(
+				Url::parse("https://cdn.example.com/assets/").unwrap(),
+			)));
+
+		assert_eq!(
+			filesystem
+				.path_for_url(
+					&Url::parse("https://cdn.example.com/assets/css/my%20app.css?v=abc").unwrap()
+				)
+				.unwrap(),
+			PathBuf::from("css/my app.css")
+		);
+		assert_eq!(
+			filesystem
+				.path_for_url(&Url::parse("https://example.com/assets/css/app.css").unwrap())
+				.unwrap_err()
+				.kind(),
+			ErrorKind::InvalidInput
+		);
+		assert!(filesystem
+			.path_for_url(&Url::parse("https://cdn.example.com/other/app.css").unwrap())
+			.is_err());
+	}
+
+	#[tokio::test]
+	async fn test_versioned() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(())
+			.await
+			.unwrap()
+			.with_url_strategy(Versioned::new(BaseUrl::new(
+				Url::parse("https://cdn.example.com").unwrap(),
+			)));
+
+		filesystem
+			.write(Path::new("app.css"), b"body {}")
+			.await
+			.unwrap();
+		let first = filesystem.public_url(Path::new("app.css")).await.unwrap();
+		assert!(first
+			.as_str()
+			.starts_with("https://cdn.example.com/app.css?v="));
+
+		filesystem
+			.write(Path::new("app.css"), b"body { color: red }")
+			.await
+			.unwrap();
+		assert_ne!(
+			filesystem.public_url(Path::new("app.css")).await.unwrap(),
+			first
+		);
+	}
+
+	#[tokio::test]
+	async fn test_sharded() {
+		let sharding = Sharding::default();
+		let filesystem = Filesystem::new::<MemoryAdapter>(())
+			.await
+			.unwrap()
+			.with_url_strategy(Sharded::new(
+				BaseUrl::new(Url::parse("https://cdn.example.com").unwrap()),
+				sharding,
+			));
+
+		let url = filesystem
+			.public_url(Path::new("images/cat.jpg"))
+			.await
+			.unwrap();
+		assert_eq!(
+			url.as_str(),
+			format!(
+				"https://cdn.example.com/{}",
+				sharding.path(Path::new("images/cat.jpg")).display()
+			)
+		);
+		assert_eq!(
+			filesystem.path_for_url(&url).unwrap(),
+			PathBuf::from("images/cat.jpg")
+		);
+	}
+
+	#[tokio::test]
+	async fn test_public_url_without_strategy() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+
+		assert_eq!(
+			filesystem
+				.public_url(Path::new("app.css"))
+				.await
+				.unwrap_err()
+				.kind(),
+			ErrorKind::Unsupported
+		);
+	}
+
+	#[tokio::test]
+	async fn test_private_files() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(())
+			.await
+			.unwrap()
+			.with_url_strategy(BaseUrl::new(Url::parse("https://cdn.example.com").unwrap()));
+		filesystem
+			.write_with(
+				Path::new("invoice.pdf"),
+				b"%PDF",
+				WriteOptions {
+					visibility: Some(Visibility::Private),
+					..WriteOptions::default()
+				},
+			)
+			.await
+			.unwrap();
+
+		assert!(matches!(
+			filesystem.public_url(Path::new("invoice.pdf")).await,
+			Err(FlysystemError::FileNotPublic { .. })
+		));
+
+		// the memory adapter can't sign URLs, so the fallback fails too
+		let fallback = filesystem
+			.clone()
+			.with_private_files(PrivateFiles::TemporaryUrl(Duration::from_mins(5)));
+		assert!(matches!(
+			fallback.public_url(Path::new("invoice.pdf")).await,
+			Err(FlysystemError::TemporaryUrlNotSupported { .. })
+		));
+
+		let allowed = filesystem.with_private_files(PrivateFiles::Allow);
+		assert_eq!(
+			allowed
+				.public_url(Path::new("invoice.pdf"))
+				.await
+				.unwrap()
+				.as_str(),
+			"https://cdn.example.com/invoice.pdf"
+		);
+	}
+}
@@ -0,0 +1,239 @@
+//! Check that a replica holds the same files as its source.
+
+use futures::{StreamExt, TryStreamExt};
+use std::{
+	collections::BTreeSet,
+	hash::{DefaultHasher, Hash, Hasher},
+	path::{Path, PathBuf},
+};
+
+use crate::{relative_to, Filesystem, FlysystemError, Result};
+
+/// How a replica is verified.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplicationOptions {
+	/// Only compare this many randomly picked files instead of the whole tree.
+	/// Files that only exist on the replica are not detected when sampling.
+	pub sample: Option<usize>,
+	/// The seed used to pick the sampled files, so a verification can be reproduced.
+	pub seed: u64,
+	/// How many files are compared at once.
+	pub concurrency: usize,
+}
+
+impl Default for ReplicationOptions {
+	fn default() -> Self {
+		Self {
+			sample: None,
+			seed: 0,
+			concurrency: 8,
+		}
+	}
+}
+
+/// A difference between a source and its replica.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Discrepancy {
+	/// The file exists on the source but not on the replica.
+	Missing(PathBuf),
+	/// The file exists on the replica but not on the source.
+	Unexpected(PathBuf),
+	/// The file exists on both, but its contents differ.
+	Mismatch {
+		path: PathBuf,
+		source_checksum: String,
+		replica_checksum: String,
+	},
+}
+
+/// The outcome of a replication verification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplicationReport {
+	/// How many files were compared.
+	pub checked: usize,
+	pub discrepancies: Vec<Discrepancy>,
+}
+
+impl ReplicationReport {
+	/// Check if the replica matched the source.
+	#[must_use]
+	pub const fn is_consistent(&self) -> bool {
+		self.discrepancies.is_empty()
+	}
+}
+
+/// Compare the files under `prefix` on `source` with the ones on `replica`.
+///
+/// Files are compared by the SHA-256 of their contents rather than the adapters' checksums, which differ between backends.
+///
+/// # Errors
+///
+/// This function will return an error if either adapter fails to list the directory or to read a file (other than the replica missing it).
+pub async fn verify_replication(
+	source: &Filesystem,
+	replica: &Filesystem,
+	prefix: &Path,
+	options: &ReplicationOptions,
+) -> Result<ReplicationReport> {
+	let prefix = source.normalize(prefix)?;
+	let source_files = list(source, &prefix).await?;
+
+	let (checked, unexpected) = if let Some(sample) = options.sample {
+		(pick(source_files, sample, options.seed), Vec::new())
+	} else {
+		let replica_files = list(replica, &prefix).await?;
+		let unexpected = replica_files
+			.difference(&source_files)
+			.cloned()
+			.map(Discrepancy::Unexpected)
+			.collect();
+
+		(source_files.into_iter().collect(), unexpected)
+	};
+
+	let mut discrepancies: Vec<Discrepancy> = futures::stream::iter(&checked)
+		.map(|path| compare(source, replica, prefix.join(path), path))
+		.buffered(options.concurrency.max(1))
+		.try_filter_map(|discrepancy| async move { Ok(discrepancy) })
+		.try_collect()
+		.await?;
+	discrepancies.extend(unexpected);
+
+	Ok(ReplicationReport {
+		checked: checked.len(),
+		discrepancies,
+	})
+}
+
+async fn list(filesystem: &Filesystem, prefix: &Path) -> Result<BTreeSet<PathBuf>> {
+	Ok(filesystem
+		.adapter
+		.list_contents(prefix, true)
+		.await
+		.map_err(|error| FlysystemError::listing(prefix, error))?
+		.iter()
+		.map(|entry| relative_to(entry, prefix).to_path_buf())
+		.collect())
+}
+
+/// Deterministically pick `count` files, by keeping the ones with the lowest seeded hashes.
+fn pick(files: BTreeSet<PathBuf>, count: usize, seed: u64) -> Vec<PathBuf> {
+	let mut files: Vec<(u64, PathBuf)> = files
+		.into_iter()
+		.map(|path| {
+			let mut hasher = DefaultHasher::new();
+			(seed, &path).hash(&mut hasher);
+
+			(hasher.finish(), path)
+		})
+		.collect();
+	files.sort_unstable();
+
+	files
+		.into_iter()
+		.take(count)
+		.map(|(_, path)| path)
+		.collect()
+}
+
+async fn compare(
+	source: &Filesystem,
+	replica: &Filesystem,
+	path: PathBuf,
+	relative: &Path,
+) -> Result<Option<Discrepancy>> {
+	let (source_contents, replica_contents) =
+		futures::future::join(source.adapter.read(&path), replica.adapter.read(&path)).await;
+
+	let replica_contents = match replica_contents {
+		Ok(contents) => contents,
+		Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+			return Ok(Some(Discrepancy::Missing(relative.to_path_buf())));
+		},
+		Err(error) => return Err(FlysystemError::reading(&path, error)),
+	};
+	let source_contents = source_contents.map_err(|error| FlysystemError::reading(&path, error))?;
+
+	let source_checksum = sha256::digest(source_contents.data);
+	let replica_checksum = sha256::digest(replica_contents.data);
+
+	Ok(
+		(source_checksum != replica_checksum).then(|| Discrepancy::Mismatch {
+			path: relative.to_path_buf(),
+			source_checksum,
+			replica_checksum,
+		}),
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_utils::filesystem;
+
+	#[tokio::test]
+	async fn test_verify_replication() {
+		let source = filesystem(&[
+			("backups/a.txt", "a"),
+			("backups/b.txt", "b"),
+			("backups/nested/c.txt", "c"),
+		])
+		.await;
+		let replica = filesystem(&[
+			("backups/a.txt", "a"),
+			("backups/b.txt", "B"),
+			("backups/d.txt", "d"),
+		])
+		.await;
+
+		let report = verify_replication(
+			&source,
+			&replica,
+			Path::new("backups"),
+			&ReplicationOptions::default(),
+		)
+		.await
+		.unwrap();
+
+		assert_eq!(report.checked, 3);
+		assert!(!report.is_consistent());
+		assert_eq!(
+			report.discrepancies,
+			vec![
+				Discrepancy::Mismatch {
+					path: PathBuf::from("b.txt"),
+					source_checksum: sha256::digest("b"),
+					replica_checksum: sha256::digest("B"),
+				},
+				Discrepancy::Missing(PathBuf::from("nested/c.txt")),
+				Discrepancy::Unexpected(PathBuf::from("d.txt")),
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_verify_replication_sample() {
+		let files: Vec<(String, String)> = (0..20)
+			.map(|i| (format!("data/{i}.txt"), i.to_string()))
+			.collect();
+		let files: Vec<(&str, &str)> = files
+			.iter()
+			.map(|(path, contents)| (path.as_str(), contents.as_str()))
+			.collect();
+
+		let source = filesystem(&files).await;
+		let replica = filesystem(&files).await;
+		let options = ReplicationOptions {
+			sample: Some(5),
+			seed: 42,
+			..ReplicationOptions::default()
+		};
+
+		let report = verify_replication(&source, &replica, Path::new("data"), &options)
+			.await
+			.unwrap();
+
+		assert_eq!(report.checked, 5);
+		assert!(report.is_consistent());
+	}
+}
@@ -0,0 +1,276 @@
+/// The file names Windows reserves for devices, whatever their extension.
+const WINDOWS_RESERVED: [&str; 22] = [
+	"CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+	"COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Turns user-supplied file names into ones safe to store, on any adapter and in a URL.
+///
+/// Implemented for [`FilenameSanitizer`] and for closures, to plug in other rules.
+pub trait SanitizeFilename: Send + Sync {
+	/// Sanitize `name`, which may contain anything (including a full client-side path).
+	fn sanitize(&self, name: &str) -> String;
+}
+
+impl<F: Fn(&str) -> String + Send + Sync> SanitizeFilename for F {
+	fn sanitize(&self, name: &str) -> String {
+		self(name)
+	}
+}
+
+/// How a [`FilenameSanitizer`] handles non-ASCII characters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnicodeHandling {
+	/// Keep letters and digits as is (they'll be percent-encoded in URLs).
+	Keep,
+	/// Strip the diacritics of Latin letters (`é` becomes `e`, `ß` becomes `ss`), and replace every other non-ASCII character.
+	#[default]
+	Ascii,
+}
+
+/// A configurable file name sanitizer.
+///
+/// By default, it:
+/// - drops any client-side directories (`C:\fakepath\report.pdf` becomes `report.pdf`)
+/// - folds non-ASCII letters to ASCII (see [`UnicodeHandling`]), dropping combining marks and control characters
+/// - replaces whitespace and characters reserved on Windows or in URLs (`<>:"/\|?*#%&+` and friends) with `-`, collapsing runs of them
+/// - trims leading and trailing dots and replacements, so names can't be hidden files or end in a dot
+/// - suffixes names Windows reserves for devices (`CON`, `NUL`, `COM1`...) with `_`
+/// - falls back to `file` for names with nothing left
+/// - truncates names to 255 bytes, keeping their extension
+#[derive(Debug, Clone)]
+pub struct FilenameSanitizer {
+	unicode: UnicodeHandling,
+	replacement: char,
+	max_length: usize,
+	lowercase: bool,
+}
+
+impl Default for FilenameSanitizer {
+	fn default() -> Self {
+		Self {
+			unicode: UnicodeHandling::default(),
+			replacement: '-',
+			max_length: 255,
+			lowercase: false,
+		}
+	}
+}
+
+impl FilenameSanitizer {
+	/// Create a sanitizer with the default rules.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Set how non-ASCII characters are handled.
+	#[must_use]
+	pub const fn with_unicode(mut self, unicode: UnicodeHandling) -> Self {
+		self.unicode = unicode;
+		self
+	}
+
+	/// Set the character replacing whitespace and reserved characters. It must be safe itself (like `-` or `_`).
+	#[must_use]
+	pub const fn with_replacement(mut self, replacement: char) -> Self {
+		self.replacement = replacement;
+		self
+	}
+
+	/// Set the maximum length of sanitized names, in bytes.
+	#[must_use]
+	pub const fn with_max_length(mut self, max_length: usize) -> Self {
+		self.max_length = max_length;
+		self
+	}
+
+	/// Lowercase sanitized names, for case-insensitive backends or slug-like URLs.
+	#[must_use]
+	pub const fn lowercase(mut self) -> Self {
+		self.lowercase = true;
+		self
+	}
+
+	fn replace(&self, cleaned: &mut String) {
+		if !cleaned.ends_with(self.replacement) {
+			cleaned.push(self.replacement);
+		}
+	}
+
+	/// Replace unsafe characters, collapsing runs of replacements and trimming them (and dots) from both ends.
+	fn clean(&self, part: &str) -> String {
+		let mut cleaned = String::with_capacity(part.len());
+
+		for c in part.chars() {
+			if c.is_control() || is_combining_mark(c) {
+				continue;
+			}
+
+			if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+				cleaned.push(c);
+			} else if !c.is_ascii() && c.is_alphanumeric() {
+				match (self.unicode, fold(c)) {
+					(UnicodeHandling::Keep, _) => cleaned.push(c),
+					(UnicodeHandling::Ascii, Some(folded)) => cleaned.push_str(folded),
+					(UnicodeHandling::Ascii, None) => self.replace(&mut cleaned),
+				}
+			} else {
+				self.replace(&mut cleaned);
+			}
+		}
+
+		let cleaned = if self.lowercase {
+			cleaned.to_lowercase()
+		} else {
+			cleaned
+		};
+
+		cleaned
+			.trim_matches(|c| c == '.' || c == self.replacement)
+			.to_string()
+	}
+}
+
+impl SanitizeFilename for FilenameSanitizer {
+	fn sanitize(&self, name: &str) -> String {
+		let name = display_name(name);
+		let (stem, extension) = match name.rsplit_once('.') {
+			Some((stem, extension)) if !stem.is_empty() => {
+				(self.clean(stem), self.clean(extension))
+			},
+			_ => (self.clean(name), String::new()),
+		};
+
+		let mut stem = if stem.is_empty() {
+			"file".to_string()
+		} else {
+			stem
+		};
+		let device = stem.split('.').next().unwrap_or_default();
+		if WINDOWS_RESERVED
+			.iter()
+			.any(|reserved| device.eq_ignore_ascii_case(reserved))
+		{
+			stem.insert(device.len(), '_');
+		}
+
+		if extension.is_empty() {
+			return truncate(&stem, self.max_length).to_string();
+		}
+
+		let extension = truncate(&extension, self.max_length / 2);
+		let stem = truncate(
+			&stem,
+			self.max_length.saturating_sub(extension.len() + 1).max(1),
+		);
+		format!("{stem}.{extension}")
+	}
+}
+
+/// Get the name of a user-supplied file name to show, without any client-side directories or control characters.
+pub fn display_name(name: &str) -> &str {
+	name.rsplit(['/', '\\'])
+		.next()
+		.unwrap_or_default()
+		.trim_matches(char::is_control)
+		.trim()
+}
+
+/// Truncate `value` to at most `max` bytes, on a character boundary.
+fn truncate(value: &str, max: usize) -> &str {
+	let mut end = value.len().min(max);
+	while !value.is_char_boundary(end) {
+		end -= 1;
+	}
+
+	&value[..end]
+}
+
+/// Whether `c` is a combining diacritical mark, left over by names in decomposed form (like `e\u{301}`).
+const fn is_combining_mark(c: char) -> bool {
+	matches!(c, '\u{0300}'..='\u{036F}' | '\u{1AB0}'..='\u{1AFF}' | '\u{1DC0}'..='\u{1DFF}' | '\u{20D0}'..='\u{20FF}' | '\u{FE20}'..='\u{FE2F}')
+}
+
+/// Get the ASCII spelling of a Latin letter with diacritics.
+const fn fold(c: char) -> Option<&'static str> {
+	Some(match c {
+		'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+		'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' | 'Ā' | 'Ă' | 'Ą' => "A",
+		'æ' => "ae",
+		'Æ' => "AE",
+		'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+		'Ç' | 'Ć' | 'Ĉ' | 'Ċ' | 'Č' => "C",
+		'ď' | 'đ' | 'ð' => "d",
+		'Ď' | 'Đ' | 'Ð' => "D",
+		'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+		'È' | 'É' | 'Ê' | 'Ë' | 'Ē' | 'Ĕ' | 'Ė' | 'Ę' | 'Ě' => "E",
+		'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+		'Ĝ' | 'Ğ' | 'Ġ' | 'Ģ' => "G",
+		'ĥ' | 'ħ' => "h",
+		'Ĥ' | 'Ħ' => "H",
+		'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+		'Ì' | 'Í' | 'Î' | 'Ï' | 'Ĩ' | 'Ī' | 'Ĭ' | 'Į' | 'İ' => "I",
+		'ĵ' => "j",
+		'Ĵ' => "J",
+		'ķ' => "k",
+		'Ķ' => "K",
+		'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+		'Ĺ' | 'Ļ' | 'Ľ' | 'Ŀ' | 'Ł' => "L",
+		'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+		'Ñ' | 'Ń' | 'Ņ' | 'Ň' => "N",
+		'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+		'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' | 'Ō' | 'Ŏ' | 'Ő' => "O",
+		'œ' => "oe",
+		'Œ' => "OE",
+		'ŕ' | 'ŗ' | 'ř' => "r",
+		'Ŕ' | 'Ŗ' | 'Ř' => "R",
+		'ś' | 'ŝ' | 'ş' | 'š' => "s",
+		'Ś' | 'Ŝ' | 'Ş' | 'Š' => "S",
+		'ß' => "ss",
+		'ţ' | 'ť' | 'ŧ' => "t",
+		'Ţ' | 'Ť' | 'Ŧ' => "T",
+		'þ' => "th",
+		'Þ' => "TH",
+		'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+		'Ù' | 'Ú' | 'Û' | 'Ü' | 'Ũ' | 'Ū' | 'Ŭ' | 'Ů' | 'Ű' | 'Ų' => "U",
+		'ŵ' => "w",
+		'Ŵ' => "W",
+		'ý' | 'ÿ' | 'ŷ' => "y",
+		'Ý' | 'Ÿ' | 'Ŷ' => "Y",
+		'ź' | 'ż' | 'ž' => "z",
+		'Ź' | 'Ż' | 'Ž' => "Z",
+		_ => return None,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_sanitize() {
+		let sanitizer = FilenameSanitizer::new();
+
+		assert_eq!(sanitizer.sanitize("Résumé (final).pdf"), "Resume-final.pdf");
+		assert_eq!(sanitizer.sanitize("Cafe\u{301} #1?.txt"), "Cafe-1.txt");
+		assert_eq!(sanitizer.sanitize("C:\\fakepath\\photo.jpg"), "photo.jpg");
+		assert_eq!(sanitizer.sanitize("../../etc/passwd"), "passwd");
+		assert_eq!(sanitizer.sanitize("con.txt"), "con_.txt");
+		assert_eq!(sanitizer.sanitize("..."), "file");
+		assert_eq!(sanitizer.sanitize("漢字.png"), "file.png");
+		assert_eq!(
+			FilenameSanitizer::new()
+				.with_unicode(UnicodeHandling::Keep)
+				.lowercase()
+				.sanitize("漢字 Ü.png"),
+			"漢字-ü.png"
+		);
+
+		let long = format!("{}.jpeg", "a".repeat(300));
+		assert_eq!(
+			FilenameSanitizer::new().with_max_length(20).sanitize(&long),
+			format!("{}.jpeg", "a".repeat(15))
+		);
+	}
+}
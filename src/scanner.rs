@@ -0,0 +1,156 @@
+use std::{fmt::Debug, io::Result};
+
+/// The outcome of scanning a file for viruses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScanResult {
+	Clean,
+	/// The file is infected, with the name of the detected signature.
+	Infected(String),
+}
+
+/// A virus scanner, invoked on every write when configured on a [`Filesystem`](crate::Filesystem).
+#[async_trait::async_trait]
+pub trait Scanner: Debug + Send + Sync {
+	/// Scan the contents of a file.
+	async fn scan(&self, contents: &[u8]) -> Result<ScanResult>;
+}
+
+#[cfg(feature = "clamav")]
+pub use clamav::ClamAvScanner;
+
+#[cfg(feature = "clamav")]
+mod clamav {
+	use std::io::{Error, ErrorKind, Result};
+	use tokio::{
+		io::{AsyncReadExt, AsyncWriteExt},
+		net::TcpStream,
+	};
+
+	use super::{ScanResult, Scanner};
+
+	/// The maximum chunk size sent to clamd, which must stay below its `StreamMaxLength`.
+	const CHUNK_SIZE: usize = 64 * 1024;
+
+	/// A scanner talking to a `ClamAV` daemon over TCP, using the `INSTREAM` command.
+	#[derive(Debug, Clone)]
+	#[allow(clippy::module_name_repetitions)]
+	pub struct ClamAvScanner {
+		address: String,
+	}
+
+	impl ClamAvScanner {
+		/// Create a scanner for the clamd instance listening at `address` (like `127.0.0.1:3310`).
+		#[must_use]
+		pub fn new(address: impl Into<String>) -> Self {
+			Self {
+				address: address.into(),
+			}
+		}
+	}
+
+	#[async_trait::async_trait]
+	impl Scanner for ClamAvScanner {
+		async fn scan(&self, contents: &[u8]) -> Result<ScanResult> {
+			let mut stream = TcpStream::connect(&self.address).await?;
+
+			stream.write_all(b"zINSTREAM\0").await?;
+			for chunk in contents.chunks(CHUNK_SIZE) {
+				let len = u32::try_from(chunk.len())
+					.map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+
+				stream.write_all(&len.to_be_bytes()).await?;
+				stream.write_all(chunk).await?;
+			}
+			stream.write_all(&0u32.to_be_bytes()).await?;
+
+			let mut response = Vec::new();
+			stream.read_to_end(&mut response).await?;
+
+			parse_response(&response)
+		}
+	}
+
+	/// Parse a clamd reply, like `stream: OK` or `stream: Eicar-Signature FOUND`.
+	fn parse_response(response: &[u8]) -> Result<ScanResult> {
+		let response = String::from_utf8_lossy(response);
+		let response = response.trim_end_matches(['\0', '\n']);
+		let result = response.strip_prefix("stream: ").unwrap_or(response);
+
+		if result == "OK" {
+			return Ok(ScanResult::Clean);
+		}
+
+		if let Some(signature) = result.strip_suffix(" FOUND") {
+			return Ok(ScanResult::Infected(signature.to_string()));
+		}
+
+		Err(Error::other(format!(
+			"ClamAV failed to scan the file: {result}"
+		)))
+	}
+
+	#[cfg(test)]
+	mod tests {
+		use tokio::net::TcpListener;
+
+		use super::*;
+
+		/// A fake clamd that flags any stream containing `EICAR`.
+		async fn fake_clamd() -> String {
+			let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+			let address = listener.local_addr().unwrap().to_string();
+
+			tokio::spawn(async move {
+				while let Ok((mut socket, _)) = listener.accept().await {
+					let mut command = [0u8; 10];
+					socket.read_exact(&mut command).await.unwrap();
+					assert_eq!(&command, b"zINSTREAM\0");
+
+					let mut contents = Vec::new();
+					loop {
+						let len = socket.read_u32().await.unwrap() as usize;
+						if len == 0 {
+							break;
+						}
+
+						let mut chunk = vec![0; len];
+						socket.read_exact(&mut chunk).await.unwrap();
+						contents.extend(chunk);
+					}
+
+					let infected = contents.windows(5).any(|window| window == b"EICAR");
+					let reply: &[u8] = if infected {
+						b"stream: Eicar-Signature FOUND\0"
+					} else {
+						b"stream: OK\0"
+					};
+					socket.write_all(reply).await.unwrap();
+				}
+			});
+
+			address
+		}
+
+		#[tokio::test]
+		async fn test_scan() {
+			let scanner = ClamAvScanner::new(fake_clamd().await);
+
+			assert_eq!(
+				scanner.scan(b"Hello, world!").await.unwrap(),
+				ScanResult::Clean
+			);
+			assert_eq!(
+				scanner
+					.scan(b"X5O!P%@AP[4\\PZX54(P^)7CC)7}$EICAR")
+					.await
+					.unwrap(),
+				ScanResult::Infected("Eicar-Signature".to_string())
+			);
+		}
+
+		#[test]
+		fn test_parse_error_response() {
+			assert!(parse_response(b"INSTREAM size limit exceeded. ERROR\0").is_err());
+		}
+	}
+}
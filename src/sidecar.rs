@@ -0,0 +1,258 @@
+//! Keeping the metadata a destination can't store (like the content type on local disks) in sidecar files while syncing,
+//! so syncing the files back restores it.
+
+use mime::Mime;
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::BTreeMap,
+	ffi::OsStr,
+	io::{Error, ErrorKind},
+	path::{Path, PathBuf},
+};
+
+use crate::{Filesystem, FlysystemError, Result, Visibility};
+
+/// The name of the sidecar files, which hold the metadata of the files in the same directory.
+pub const SIDECAR_NAME: &str = ".flysystem-meta.json";
+
+/// Check if a path points to a sidecar file.
+#[must_use]
+pub fn is_sidecar(path: &Path) -> bool {
+	path.file_name() == Some(OsStr::new(SIDECAR_NAME))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum StoredVisibility {
+	Public,
+	Private,
+}
+
+impl From<Visibility> for StoredVisibility {
+	fn from(visibility: Visibility) -> Self {
+		match visibility {
+			Visibility::Public => Self::Public,
+			Visibility::Private => Self::Private,
+		}
+	}
+}
+
+impl From<StoredVisibility> for Visibility {
+	fn from(visibility: StoredVisibility) -> Self {
+		match visibility {
+			StoredVisibility::Public => Self::Public,
+			StoredVisibility::Private => Self::Private,
+		}
+	}
+}
+
+/// The metadata of a file that its adapter couldn't store.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct Entry {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	content_type: Option<String>,
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	visibility: Option<StoredVisibility>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Sidecar {
+	files: BTreeMap<String, Entry>,
+}
+
+/// Carries the metadata of synced files over to the destination, through sidecars where it can't store it.
+#[derive(Debug, Default)]
+pub(crate) struct MetadataSync {
+	sources: BTreeMap<PathBuf, Sidecar>,
+	destinations: BTreeMap<PathBuf, Sidecar>,
+}
+
+impl MetadataSync {
+	/// Give a synced file the metadata it has on the source, recording whatever the destination doesn't keep.
+	pub(crate) async fn sync(
+		&mut self,
+		source: &Filesystem,
+		destination: &Filesystem,
+		path: &Path,
+	) -> Result<()> {
+		let directory = path.parent().map(Path::to_path_buf).unwrap_or_default();
+		let name = path
+			.file_name()
+			.unwrap_or_default()
+			.to_string_lossy()
+			.to_string();
+
+		if !self.sources.contains_key(&directory) {
+			let sidecar = source.read_sidecar(&directory).await?;
+			self.sources.insert(directory.clone(), sidecar);
+		}
+		let stored = self.sources[&directory]
+			.files
+			.get(&name)
+			.cloned()
+			.unwrap_or_default();
+
+		let mime_type = match stored.content_type {
+			Some(content_type) => content_type.parse::<Mime>().map_err(|e| {
+				FlysystemError::retrieving_metadata(path, Error::new(ErrorKind::InvalidData, e))
+			})?,
+			None => source.mime_type(path).await?,
+		};
+		let visibility = match stored.visibility {
+			Some(visibility) => visibility.into(),
+			None => source.visibility(path).await?,
+		};
+
+		// the MIME type goes first, since changing it may reset the visibility
+		unless_unsupported(destination.adapter.set_mime_type(path, &mime_type).await)
+			.map_err(|error| FlysystemError::writing(path, error))?;
+		unless_unsupported(destination.adapter.set_visibility(path, visibility).await)
+			.map_err(|error| FlysystemError::setting_visibility(path, error))?;
+
+		let entry = Entry {
+			content_type: (destination.mime_type(path).await? != mime_type)
+				.then(|| mime_type.to_string()),
+			visibility: (destination.visibility(path).await? != visibility)
+				.then_some(visibility.into()),
+		};
+
+		if !self.destinations.contains_key(&directory) {
+			let sidecar = destination.read_sidecar(&directory).await?;
+			self.destinations.insert(directory.clone(), sidecar);
+		}
+		let sidecar = self.destinations.entry(directory).or_default();
+		if entry == Entry::default() {
+			sidecar.files.remove(&name);
+		} else {
+			sidecar.files.insert(name, entry);
+		}
+
+		Ok(())
+	}
+
+	/// Write the updated sidecars to the destination, removing the ones left empty.
+	pub(crate) async fn finish(self, destination: &Filesystem) -> Result<()> {
+		for (directory, sidecar) in self.destinations {
+			let path = directory.join(SIDECAR_NAME);
+
+			if !sidecar.files.is_empty() {
+				let contents = serde_json::to_vec_pretty(&sidecar).map_err(|e| {
+					FlysystemError::writing(&path, Error::new(ErrorKind::InvalidData, e))
+				})?;
+
+				destination
+					.adapter
+					.write(&path, &contents)
+					.await
+					.map_err(|error| FlysystemError::writing(&path, error))?;
+			} else if destination.file_exists(&path).await? {
+				destination
+					.adapter
+					.delete(&path)
+					.await
+					.map_err(|error| FlysystemError::deleting(&path, error))?;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+fn unless_unsupported(result: std::io::Result<()>) -> std::io::Result<()> {
+	match result {
+		Err(error) if error.kind() == ErrorKind::Unsupported => Ok(()),
+		result => result,
+	}
+}
+
+impl Filesystem {
+	/// Read the sidecar of a directory, if it has one.
+	async fn read_sidecar(&self, directory: &Path) -> Result<Sidecar> {
+		let path = directory.join(SIDECAR_NAME);
+
+		match self.adapter.read(&path).await {
+			Ok(contents) => serde_json::from_slice(&contents.data)
+				.map_err(|e| FlysystemError::reading(&path, Error::new(ErrorKind::InvalidData, e))),
+			Err(error) if error.kind() == ErrorKind::NotFound => Ok(Sidecar::default()),
+			Err(error) => Err(FlysystemError::reading(&path, error)),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{adapters::MemoryAdapter, BatchMode, SyncOptions};
+
+	#[cfg(feature = "local")]
+	#[tokio::test]
+	async fn test_sidecars_round_trip() {
+		use crate::adapters::{local, LocalAdapter, Validation};
+
+		let options = SyncOptions {
+			mode: BatchMode::FailFast,
+			sidecars: true,
+			..SyncOptions::default()
+		};
+
+		// a file whose content type can't be guessed from its name, like one synced from S3 before
+		let source = Filesystem::from_adapter(MemoryAdapter::default());
+		source
+			.write(Path::new("docs/invoice"), b"%PDF")
+			.await
+			.unwrap();
+		source
+			.set_mime_type(Path::new("docs/invoice"), &mime::APPLICATION_PDF)
+			.await
+			.unwrap();
+		source
+			.set_visibility(Path::new("docs/invoice"), Visibility::Private)
+			.await
+			.unwrap();
+
+		// the local adapter guesses content types from file names, so it can't store it
+		let _ = std::fs::remove_dir_all("/tmp/flysystem_tests/sidecars");
+		let local = Filesystem::new::<LocalAdapter>(local::Config {
+			location: "/tmp/flysystem_tests/sidecars".into(),
+			lazy_root_creation: true,
+			validation: Validation::Eager,
+			public_url: None,
+		})
+		.await
+		.unwrap();
+
+		let report = source
+			.sync_with_options(Path::new("docs"), &local, options.clone())
+			.await
+			.unwrap();
+		assert_eq!(
+			report.into_result().unwrap(),
+			vec![PathBuf::from("docs/invoice")]
+		);
+		assert!(local
+			.read::<String>(Path::new("docs/.flysystem-meta.json"))
+			.await
+			.unwrap()
+			.contains("application/pdf"));
+
+		let back = Filesystem::from_adapter(MemoryAdapter::default());
+		local
+			.sync_with_options(Path::new("docs"), &back, options)
+			.await
+			.unwrap();
+		assert_eq!(
+			back.mime_type(Path::new("docs/invoice")).await.unwrap(),
+			mime::APPLICATION_PDF
+		);
+		assert_eq!(
+			back.visibility(Path::new("docs/invoice")).await.unwrap(),
+			Visibility::Private
+		);
+		assert!(!back
+			.file_exists(Path::new("docs/.flysystem-meta.json"))
+			.await
+			.unwrap());
+
+		std::fs::remove_dir_all("/tmp/flysystem_tests/sidecars").unwrap();
+	}
+}
@@ -0,0 +1,440 @@
+//! Mirror the files of a filesystem onto another one, like backing up a bucket to a local disk.
+
+use futures::{future, stream, StreamExt, TryStreamExt};
+use std::{
+	collections::BTreeSet,
+	io::{Error, ErrorKind},
+	path::{Path, PathBuf},
+};
+
+use crate::{adapters::key_order, BatchMode, Filesystem, FlysystemError, Result, SyncOptions};
+
+/// How many files are synced between two updates of the journal.
+const CHECKPOINT_INTERVAL: usize = 100;
+
+/// How a filesystem is mirrored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MirrorOptions {
+	/// How files are compared and copied.
+	pub sync: SyncOptions,
+	/// Delete the files on the destination that don't exist on the source, so it ends up as an exact copy.
+	/// Nothing is deleted if copying stopped at a failure.
+	pub delete_extraneous: bool,
+	/// Record the progress in this file on the destination, so an interrupted sync (like a crashed migration) resumes where it left off
+	/// instead of comparing every file again. The journal is removed once the sync succeeded.
+	pub journal: Option<PathBuf>,
+}
+
+/// The outcome of mirroring a filesystem.
+#[derive(Debug, Default)]
+pub struct SyncSummary {
+	/// The files copied to the destination, because they were missing or had changed.
+	pub copied: Vec<PathBuf>,
+	/// How many files the destination already had.
+	pub unchanged: usize,
+	/// How many files were skipped because the interrupted sync recorded in the journal already handled them.
+	pub resumed: usize,
+	/// The files deleted from the destination, because they don't exist on the source.
+	pub deleted: Vec<PathBuf>,
+	/// The files that failed to be copied or deleted, along with their errors.
+	pub failed: Vec<(PathBuf, FlysystemError)>,
+	/// The files that weren't copied or deleted, because the sync stopped at a failure.
+	pub skipped: Vec<PathBuf>,
+}
+
+impl SyncSummary {
+	/// Check if the destination now mirrors the source.
+	#[must_use]
+	pub const fn is_success(&self) -> bool {
+		self.failed.is_empty() && self.skipped.is_empty()
+	}
+}
+
+/// Copy the new and changed files under `prefix` from `source` to `destination`, optionally deleting the ones the source doesn't have.
+///
+/// Files are compared as configured by [`SyncOptions::fingerprint`], falling back to their checksums.
+/// Listings are streamed in batches, so mirroring huge prefixes doesn't hold every path in memory. Extraneous files are found by
+/// checking each file of the destination on the source, and with a [journal](MirrorOptions::journal), the source's listing is
+/// made in key order (see [`Filesystem::list_checkpointed`]) so the progress can be saved every few files.
+///
+/// # Errors
+///
+/// This function will return an error if either adapter fails to list the directory, or to read or write the journal.
+/// Failures for individual files are reported instead.
+pub async fn sync(
+	source: &Filesystem,
+	destination: &Filesystem,
+	prefix: &Path,
+	options: &MirrorOptions,
+) -> Result<SyncSummary> {
+	let prefix = source.normalize(prefix)?;
+	let journal_path = options
+		.journal
+		.as_deref()
+		.map(|path| destination.normalize(path))
+		.transpose()?;
+
+	let (resume, files) = match &journal_path {
+		Some(path) => {
+			// resuming needs a stable order, which only a listing in key order guarantees
+			let files = source
+				.entries_after(&prefix, true, None)
+				.map(|entry| match entry {
+					Ok((file, _)) => Ok(file),
+					Err(error) => Err(FlysystemError::listing(&prefix, error)),
+				})
+				.boxed();
+
+			(Journal::load(destination, path).await?, files)
+		},
+		None => (Journal::default(), source.files_under(&prefix)),
+	};
+
+	let mut journal = resume.clone();
+	let mut summary = SyncSummary::default();
+	let mut resumed = 0;
+	let mut chunks = files
+		.try_filter(|file| {
+			if options.sync.skips(&prefix, file) {
+				return future::ready(false);
+			}
+
+			let done = resume.is_done(file);
+			resumed += usize::from(done);
+			future::ready(!done)
+		})
+		.try_chunks(CHECKPOINT_INTERVAL)
+		.map_err(|error| error.1);
+
+	while let Some(chunk) = chunks.try_next().await? {
+		let report = source
+			.sync_files(
+				stream::iter(chunk.iter().cloned().map(Ok)),
+				destination,
+				&options.sync,
+			)
+			.await?;
+		let stopped = !report.skipped.is_empty();
+
+		summary.unchanged +=
+			chunk.len() - report.succeeded.len() - report.failed.len() - report.skipped.len();
+		summary.copied.extend(report.succeeded);
+		summary.failed.extend(report.failed);
+		summary.skipped.extend(report.skipped);
+
+		if stopped {
+			while let Some(chunk) = chunks.try_next().await? {
+				summary.skipped.extend(chunk);
+			}
+			break;
+		}
+
+		if let Some(path) = &journal_path {
+			journal.checkpoint(&chunk, &summary.failed);
+			journal.save(destination, path).await?;
+		}
+	}
+	drop(chunks);
+	summary.resumed = resumed;
+
+	if options.delete_extraneous {
+		let mut extraneous = extraneous(source, destination, &prefix).await?;
+		extraneous.retain(|file| {
+			Some(file) != journal_path.as_ref() && !options.sync.skips(&prefix, file)
+		});
+
+		if options.sync.mode == BatchMode::FailFast && !summary.failed.is_empty() {
+			summary.skipped.extend(extraneous);
+		} else {
+			let deletions = destination.delete_many(extraneous, options.sync.mode).await;
+			summary.deleted = deletions.succeeded;
+			summary.failed.extend(deletions.failed);
+			summary.skipped.extend(deletions.skipped);
+		}
+	}
+
+	if let Some(path) = &journal_path {
+		if summary.is_success() && destination.file_exists(path).await? {
+			destination
+				.adapter
+				.delete(path)
+				.await
+				.map_err(|error| FlysystemError::deleting(path, error))?;
+		}
+	}
+
+	Ok(summary)
+}
+
+/// The progress of a sync, saved on the destination as the last synced file followed by the ones that failed before it, one per line.
+#[derive(Debug, Clone, Default)]
+struct Journal {
+	cursor: Option<PathBuf>,
+	failed: BTreeSet<PathBuf>,
+}
+
+impl Journal {
+	async fn load(destination: &Filesystem, path: &Path) -> Result<Self> {
+		let Some(contents) = destination.try_read(path).await? else {
+			return Ok(Self::default());
+		};
+		let contents = String::from_utf8(contents.data)
+			.map_err(|_| Error::new(ErrorKind::InvalidData, "The sync journal is corrupted."))?;
+
+		let mut lines = contents.lines();
+		Ok(Self {
+			cursor: lines
+				.next()
+				.filter(|line| !line.is_empty())
+				.map(PathBuf::from),
+			failed: lines.map(PathBuf::from).collect(),
+		})
+	}
+
+	/// Check if a previous sync already handled the file.
+	fn is_done(&self, file: &Path) -> bool {
+		self.cursor
+			.as_deref()
+			.is_some_and(|cursor| key_order(file, cursor).is_le())
+			&& !self.failed.contains(file)
+	}
+
+	/// Record that the files of `chunk` were handled, the ones in `failed` unsuccessfully.
+	fn checkpoint(&mut self, chunk: &[PathBuf], failed: &[(PathBuf, FlysystemError)]) {
+		self.failed.retain(|file| !chunk.contains(file));
+		self.failed.extend(
+			failed
+				.iter()
+				.filter(|(file, _)| chunk.contains(file))
+				.map(|(file, _)| file.clone()),
+		);
+		self.cursor = match (chunk.last(), self.cursor.take()) {
+			(Some(last), Some(cursor)) if key_order(&cursor, last).is_gt() => Some(cursor),
+			(last, cursor) => last.cloned().or(cursor),
+		};
+	}
+
+	async fn save(&self, destination: &Filesystem, path: &Path) -> Result<()> {
+		let mut contents = self
+			.cursor
+			.as_deref()
+			.map(|cursor| cursor.display().to_string())
+			.unwrap_or_default();
+		for file in &self.failed {
+			contents.push('\n');
+			contents.push_str(&file.display().to_string());
+		}
+
+		destination
+			.adapter
+			.write(path, contents.as_bytes())
+			.await
+			.map_err(|error| FlysystemError::writing(path, error))
+	}
+}
+
+/// List the files under `prefix` on the destination that don't exist on the source.
+async fn extraneous(
+	source: &Filesystem,
+	destination: &Filesystem,
+	prefix: &Path,
+) -> Result<Vec<PathBuf>> {
+	let mut destination_files = destination.files_under(prefix);
+	let mut extraneous = Vec::new();
+	let mut listed = false;
+
+	loop {
+		let file = match destination_files.try_next().await {
+			Ok(Some(file)) => file,
+			Ok(None) => break,
+			// a destination without the directory has nothing to delete
+			Err(error) if error.kind() == ErrorKind::NotFound && !listed => break,
+			Err(error) => return Err(error),
+		};
+		listed = true;
+
+		if !source.file_exists(&file).await? {
+			extraneous.push(file);
+		}
+	}
+
+	Ok(extraneous)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{adapters::MemoryAdapter, IgnoreRules};
+
+	#[tokio::test]
+	async fn test_sync() {
+		let source = Filesystem::from_adapter(MemoryAdapter::default());
+		let backup = Filesystem::from_adapter(MemoryAdapter::default());
+
+		source
+			.write_many(
+				[
+					("site/index.html", "v2"),
+					("site/about.html", "about"),
+					("site/new.html", "new"),
+				],
+				BatchMode::FailFast,
+			)
+			.await
+			.into_result()
+			.unwrap();
+		backup
+			.write_many(
+				[
+					("site/index.html", "v1"),
+					("site/about.html", "about"),
+					("site/old.html", "old"),
+				],
+				BatchMode::FailFast,
+			)
+			.await
+			.into_result()
+			.unwrap();
+
+		let options = MirrorOptions {
+			delete_extraneous: true,
+			..MirrorOptions::default()
+		};
+		let summary = sync(&source, &backup, Path::new("site"), &options)
+			.await
+			.unwrap();
+
+		assert!(summary.is_success());
+		assert_eq!(
+			summary.copied,
+			vec![
+				PathBuf::from("site/index.html"),
+				PathBuf::from("site/new.html")
+			]
+		);
+		assert_eq!(summary.unchanged, 1);
+		assert_eq!(summary.deleted, vec![PathBuf::from("site/old.html")]);
+
+		assert_eq!(
+			backup
+				.read::<String>(Path::new("site/index.html"))
+				.await
+				.unwrap(),
+			"v2"
+		);
+		assert!(!backup
+			.file_exists(Path::new("site/old.html"))
+			.await
+			.unwrap());
+
+		// syncing to an empty destination copies everything
+		let empty = Filesystem::from_adapter(MemoryAdapter::default());
+		let summary = sync(&source, &empty, Path::new("site"), &options)
+			.await
+			.unwrap();
+		assert_eq!(summary.copied.len(), 3);
+		assert!(summary.deleted.is_empty());
+	}
+
+	#[tokio::test]
+	async fn test_sync_ignoring() {
+		let source = Filesystem::from_adapter(MemoryAdapter::default());
+		let backup = Filesystem::from_adapter(MemoryAdapter::default());
+
+		source
+			.write_many(
+				[
+					("app/src/main.rs", "fn main() {}"),
+					("app/node_modules/left-pad/index.js", "module.exports = 0"),
+					("app/web/node_modules/react/index.js", "module.exports = 1"),
+					("app/tmp/cache.bin", "cache"),
+					("app/build.log", "log"),
+				],
+				BatchMode::FailFast,
+			)
+			.await
+			.into_result()
+			.unwrap();
+		backup
+			.write(Path::new("app/tmp/local.bin"), b"local")
+			.await
+			.unwrap();
+
+		let options = MirrorOptions {
+			sync: SyncOptions {
+				ignore: Some(IgnoreRules::parse("node_modules/\n/tmp/\n*.log")),
+				..SyncOptions::default()
+			},
+			delete_extraneous: true,
+			..MirrorOptions::default()
+		};
+		let summary = sync(&source, &backup, Path::new("app"), &options)
+			.await
+			.unwrap();
+
+		assert_eq!(summary.copied, vec![PathBuf::from("app/src/main.rs")]);
+		// ignored files aren't deleted from the destination either
+		assert!(summary.deleted.is_empty());
+		assert_eq!(
+			backup.list_contents(Path::new("app"), true).await.unwrap(),
+			vec![
+				PathBuf::from("app/src/main.rs"),
+				PathBuf::from("app/tmp/local.bin")
+			]
+		);
+
+		// the same rules apply to one-way syncs
+		let empty = Filesystem::from_adapter(MemoryAdapter::default());
+		let report = source
+			.sync_with_options(Path::new("app"), &empty, options.sync)
+			.await
+			.unwrap();
+		assert_eq!(report.succeeded, vec![PathBuf::from("app/src/main.rs")]);
+	}
+
+	#[tokio::test]
+	async fn test_resume_from_journal() {
+		let source = Filesystem::from_adapter(MemoryAdapter::default());
+		let backup = Filesystem::from_adapter(MemoryAdapter::default());
+
+		source
+			.write_many(
+				[
+					("data/a.csv", "a"),
+					("data/b.csv", "b"),
+					("data/c.csv", "c"),
+					("data/d.csv", "d"),
+				],
+				BatchMode::FailFast,
+			)
+			.await
+			.into_result()
+			.unwrap();
+		// an interrupted sync got up to c.csv, failing to copy a.csv
+		backup
+			.write(Path::new(".sync-journal"), b"data/c.csv\ndata/a.csv")
+			.await
+			.unwrap();
+
+		let options = MirrorOptions {
+			delete_extraneous: true,
+			journal: Some(PathBuf::from(".sync-journal")),
+			..MirrorOptions::default()
+		};
+		let summary = sync(&source, &backup, Path::new("data"), &options)
+			.await
+			.unwrap();
+
+		assert!(summary.is_success());
+		assert_eq!(summary.resumed, 2);
+		assert_eq!(
+			summary.copied,
+			vec![PathBuf::from("data/a.csv"), PathBuf::from("data/d.csv")]
+		);
+		assert!(summary.deleted.is_empty());
+		assert!(!backup
+			.file_exists(Path::new(".sync-journal"))
+			.await
+			.unwrap());
+	}
+}
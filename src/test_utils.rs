@@ -0,0 +1,19 @@
+//! Helpers shared between the tests of different modules.
+
+use std::path::Path;
+
+use crate::{adapters::MemoryAdapter, Filesystem};
+
+/// Create an in-memory filesystem holding the given files, as `(path, contents)` pairs.
+pub async fn filesystem(files: &[(&str, &str)]) -> Filesystem {
+	let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+
+	for (path, contents) in files {
+		filesystem
+			.write(Path::new(path), contents.as_bytes())
+			.await
+			.unwrap();
+	}
+
+	filesystem
+}
@@ -1,12 +1,22 @@
+use bytes::BufMut;
+use futures::{io::AsyncRead, stream::BoxStream};
 use mime::Mime;
 use std::{
+	collections::HashMap,
 	fmt::Debug,
-	io::Result,
+	io::{Error, Result},
 	path::{Path, PathBuf},
-	time::SystemTime,
+	sync::Arc,
+	time::{Duration, SystemTime},
 };
+use url::Url;
 
-use crate::{adapters::Adapter, contents::Contents, Visibility};
+use crate::{
+	adapters::{Adapter, AdapterLayer, TemporaryUrlOptions},
+	contents::Contents,
+	versioning::FileVersion,
+	DirectoryStat, PathModel, Precondition, StorageEntry, Visibility, WriteOptions,
+};
 
 #[async_trait::async_trait]
 pub trait AdapterObject: Debug + Send + Sync {
@@ -17,22 +27,89 @@ pub trait AdapterObject: Debug + Send + Sync {
 	async fn directory_exists(&self, path: &Path) -> Result<bool>;
 
 	/// Write to a file.
-	async fn write(&mut self, path: &Path, content: &[u8]) -> Result<()>;
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()>;
+
+	/// Write to a file with the given options.
+	async fn write_with(&self, path: &Path, content: &[u8], options: &WriteOptions) -> Result<()>;
+
+	/// Write to a file only if a precondition holds.
+	async fn write_if(
+		&self,
+		path: &Path,
+		content: &[u8],
+		precondition: &Precondition,
+	) -> Result<()>;
+
+	/// Write a file atomically.
+	async fn write_atomic(&self, path: &Path, content: &[u8]) -> Result<()>;
+
+	/// Write to a file from a reader.
+	async fn write_stream(
+		&self,
+		path: &Path,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()>;
+
+	/// Overwrite part of a file.
+	async fn write_range(&self, path: &Path, offset: u64, data: &[u8]) -> Result<()>;
+
+	/// Add data to the end of a file.
+	async fn append(&self, path: &Path, data: &[u8]) -> Result<()>;
+
+	/// Check if range writes update files in place.
+	fn native_range_writes(&self) -> bool;
+
+	/// Check how directories are stored.
+	fn path_model(&self) -> PathModel;
 
 	/// Read a file.
 	async fn read(&self, path: &Path) -> Result<Contents>;
 
+	/// Open a file for reading.
+	async fn read_stream(&self, path: &Path) -> Result<Box<dyn AsyncRead + Send + Unpin>>;
+
+	/// Read a file into a caller-provided buffer.
+	async fn read_into(&self, path: &Path, buf: &mut (dyn BufMut + Send)) -> Result<()>;
+
+	/// Read a range of a file.
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents>;
+
+	/// List the versions of the files under a prefix.
+	async fn list_versions(&self, prefix: &Path) -> Result<Vec<FileVersion>>;
+
+	/// Read a specific version of a file.
+	async fn read_version(&self, path: &Path, version_id: &str) -> Result<Contents>;
+
+	/// Make a previous version of a file its current one.
+	async fn restore_version(&self, path: &Path, version_id: &str) -> Result<()>;
+
 	/// Delete a file.
-	async fn delete(&mut self, path: &Path) -> Result<()>;
+	async fn delete(&self, path: &Path) -> Result<()>;
+
+	/// Delete many files, returning the ones that failed.
+	async fn delete_many(&self, paths: &[PathBuf]) -> Vec<(PathBuf, Error)>;
+
+	/// Write many files, returning the ones that failed.
+	async fn write_many(&self, files: &[(&Path, &[u8])]) -> Vec<(PathBuf, Error)>;
 
 	/// Delete a directory.
-	async fn delete_directory(&mut self, path: &Path) -> Result<()>;
+	async fn delete_directory(&self, path: &Path) -> Result<()>;
 
 	/// Create a directory.
-	async fn create_directory(&mut self, path: &Path) -> Result<()>;
+	async fn create_directory(&self, path: &Path) -> Result<()>;
+
+	/// Change the MIME type of a file.
+	async fn set_mime_type(&self, path: &Path, mime_type: &Mime) -> Result<()>;
 
 	/// Set the visibility of a file.
-	async fn set_visibility(&mut self, path: &Path, visibility: Visibility) -> Result<()>;
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()>;
+
+	/// Set the visibility of many files, returning the ones that failed.
+	async fn set_visibility_many(
+		&self,
+		paths: &[PathBuf],
+		visibility: Visibility,
+	) -> Vec<(PathBuf, Error)>;
 
 	/// Get the visibility of a file.
 	async fn visibility(&self, path: &Path) -> Result<Visibility>;
@@ -40,23 +117,76 @@ pub trait AdapterObject: Debug + Send + Sync {
 	/// Get the MIME type of a file.
 	async fn mime_type(&self, path: &Path) -> Result<Mime>;
 
+	/// Get the custom metadata a file was written with.
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>>;
+
 	/// Get the last modified time of a file.
 	async fn last_modified(&self, path: &Path) -> Result<SystemTime>;
 
 	/// Get the size of a file.
 	async fn file_size(&self, path: &Path) -> Result<u64>;
 
+	/// Get when a directory was last modified and how many entries it directly contains.
+	async fn directory_stat(&self, path: &Path) -> Result<DirectoryStat>;
+
 	/// List the contents of a directory.
 	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>>;
 
+	/// List the contents of a directory along with their metadata.
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>>;
+
+	/// List the contents of a directory lazily.
+	fn list_stream<'a>(&'a self, path: &Path, deep: bool) -> BoxStream<'a, Result<PathBuf>>;
+
+	/// List the contents of a directory lazily in key order, resuming after an entry.
+	fn list_stream_after<'a>(
+		&'a self,
+		path: &Path,
+		deep: bool,
+		after: Option<&Path>,
+	) -> BoxStream<'a, Result<PathBuf>>;
+
 	/// Move a file.
-	async fn r#move(&mut self, source: &Path, destination: &Path) -> Result<()>;
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()>;
 
 	/// Copy a file.
-	async fn copy(&mut self, source: &Path, destination: &Path) -> Result<()>;
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()>;
+
+	/// Move a directory, along with everything in it.
+	async fn move_directory(&self, source: &Path, destination: &Path) -> Result<()>;
+
+	/// Copy a directory, along with everything in it.
+	async fn copy_directory(&self, source: &Path, destination: &Path) -> Result<()>;
 
 	/// Get the checksum of a file.
 	async fn checksum(&self, path: &Path) -> Result<String>;
+
+	/// Get the public URL of a file.
+	async fn public_url(&self, path: &Path) -> Result<Url>;
+
+	/// Get a temporary URL of a file.
+	async fn temporary_url(&self, path: &Path, expires_in: Duration) -> Result<Url>;
+
+	/// Get a temporary URL of a file, overriding the headers it's served with.
+	async fn temporary_url_with(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+		options: &TemporaryUrlOptions,
+	) -> Result<Url>;
+
+	/// Describe the adapter stack, outermost first.
+	fn layers(&self) -> Vec<AdapterLayer>;
+
+	/// Pull a file into the adapter's caches.
+	async fn prefetch(&self, path: &Path) -> Result<()>;
+
+	/// Wait for the adapter's background work to complete.
+	async fn flush(&self) -> Result<()>;
 }
 
 #[async_trait::async_trait]
@@ -69,30 +199,115 @@ impl<A: Adapter + Send> AdapterObject for A {
 		self.directory_exists(path).await
 	}
 
-	async fn write(&mut self, path: &Path, content: &[u8]) -> Result<()> {
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
 		self.write(path, content).await
 	}
 
+	async fn write_with(&self, path: &Path, content: &[u8], options: &WriteOptions) -> Result<()> {
+		self.write_with(path, content, options).await
+	}
+
+	async fn write_if(
+		&self,
+		path: &Path,
+		content: &[u8],
+		precondition: &Precondition,
+	) -> Result<()> {
+		self.write_if(path, content, precondition).await
+	}
+
+	async fn write_atomic(&self, path: &Path, content: &[u8]) -> Result<()> {
+		self.write_atomic(path, content).await
+	}
+
+	async fn write_stream(
+		&self,
+		path: &Path,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		self.write_stream(path, reader).await
+	}
+
+	async fn write_range(&self, path: &Path, offset: u64, data: &[u8]) -> Result<()> {
+		self.write_range(path, offset, data).await
+	}
+
+	async fn append(&self, path: &Path, data: &[u8]) -> Result<()> {
+		self.append(path, data).await
+	}
+
+	fn native_range_writes(&self) -> bool {
+		self.native_range_writes()
+	}
+
+	fn path_model(&self) -> PathModel {
+		self.path_model()
+	}
+
 	async fn read(&self, path: &Path) -> Result<Contents> {
 		self.read(path).await
 	}
 
-	async fn delete(&mut self, path: &Path) -> Result<()> {
+	async fn read_stream(&self, path: &Path) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+		self.read_stream(path).await
+	}
+
+	async fn read_into(&self, path: &Path, buf: &mut (dyn BufMut + Send)) -> Result<()> {
+		self.read_into(path, buf).await
+	}
+
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		self.read_range(path, offset, len).await
+	}
+
+	async fn list_versions(&self, prefix: &Path) -> Result<Vec<FileVersion>> {
+		self.list_versions(prefix).await
+	}
+
+	async fn read_version(&self, path: &Path, version_id: &str) -> Result<Contents> {
+		self.read_version(path, version_id).await
+	}
+
+	async fn restore_version(&self, path: &Path, version_id: &str) -> Result<()> {
+		self.restore_version(path, version_id).await
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
 		self.delete(path).await
 	}
 
-	async fn delete_directory(&mut self, path: &Path) -> Result<()> {
+	async fn delete_many(&self, paths: &[PathBuf]) -> Vec<(PathBuf, Error)> {
+		self.delete_many(paths).await
+	}
+
+	async fn write_many(&self, files: &[(&Path, &[u8])]) -> Vec<(PathBuf, Error)> {
+		self.write_many(files).await
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
 		self.delete_directory(path).await
 	}
 
-	async fn create_directory(&mut self, path: &Path) -> Result<()> {
+	async fn create_directory(&self, path: &Path) -> Result<()> {
 		self.create_directory(path).await
 	}
 
-	async fn set_visibility(&mut self, path: &Path, visibility: Visibility) -> Result<()> {
+	async fn set_mime_type(&self, path: &Path, mime_type: &Mime) -> Result<()> {
+		self.set_mime_type(path, mime_type).await
+	}
+
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
 		self.set_visibility(path, visibility).await
 	}
 
+	async fn set_visibility_many(
+		&self,
+		paths: &[PathBuf],
+		visibility: Visibility,
+	) -> Vec<(PathBuf, Error)> {
+		self.set_visibility_many(paths, visibility).await
+	}
+
 	async fn visibility(&self, path: &Path) -> Result<Visibility> {
 		self.visibility(path).await
 	}
@@ -101,6 +316,10 @@ impl<A: Adapter + Send> AdapterObject for A {
 		self.mime_type(path).await
 	}
 
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		self.metadata(path).await
+	}
+
 	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
 		self.last_modified(path).await
 	}
@@ -109,19 +328,302 @@ impl<A: Adapter + Send> AdapterObject for A {
 		self.file_size(path).await
 	}
 
+	async fn directory_stat(&self, path: &Path) -> Result<DirectoryStat> {
+		self.directory_stat(path).await
+	}
+
 	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
 		self.list_contents(path, deep).await
 	}
 
-	async fn r#move(&mut self, source: &Path, destination: &Path) -> Result<()> {
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		self.list_contents_with_metadata(path, deep).await
+	}
+
+	fn list_stream<'a>(&'a self, path: &Path, deep: bool) -> BoxStream<'a, Result<PathBuf>> {
+		self.list_stream(path, deep)
+	}
+
+	fn list_stream_after<'a>(
+		&'a self,
+		path: &Path,
+		deep: bool,
+		after: Option<&Path>,
+	) -> BoxStream<'a, Result<PathBuf>> {
+		self.list_stream_after(path, deep, after)
+	}
+
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
 		self.r#move(source, destination).await
 	}
 
-	async fn copy(&mut self, source: &Path, destination: &Path) -> Result<()> {
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
 		self.copy(source, destination).await
 	}
 
+	async fn move_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.move_directory(source, destination).await
+	}
+
+	async fn copy_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		self.copy_directory(source, destination).await
+	}
+
 	async fn checksum(&self, path: &Path) -> Result<String> {
 		self.checksum(path).await
 	}
+
+	async fn public_url(&self, path: &Path) -> Result<Url> {
+		self.public_url(path).await
+	}
+
+	async fn temporary_url(&self, path: &Path, expires_in: Duration) -> Result<Url> {
+		self.temporary_url(path, expires_in).await
+	}
+
+	async fn temporary_url_with(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+		options: &TemporaryUrlOptions,
+	) -> Result<Url> {
+		self.temporary_url_with(path, expires_in, options).await
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		self.layers()
+	}
+
+	async fn prefetch(&self, path: &Path) -> Result<()> {
+		self.prefetch(path).await
+	}
+
+	async fn flush(&self) -> Result<()> {
+		self.flush().await
+	}
+}
+
+/// The type-erased adapter of a [`Filesystem`](crate::Filesystem), so decorators can wrap it.
+#[derive(Debug)]
+pub struct BoxedAdapter(pub Arc<dyn AdapterObject>);
+
+impl Adapter for BoxedAdapter {
+	async fn file_exists(&self, path: &Path) -> Result<bool> {
+		AdapterObject::file_exists(&*self.0, path).await
+	}
+
+	async fn directory_exists(&self, path: &Path) -> Result<bool> {
+		AdapterObject::directory_exists(&*self.0, path).await
+	}
+
+	async fn write(&self, path: &Path, content: &[u8]) -> Result<()> {
+		AdapterObject::write(&*self.0, path, content).await
+	}
+
+	async fn write_with(&self, path: &Path, content: &[u8], options: &WriteOptions) -> Result<()> {
+		AdapterObject::write_with(&*self.0, path, content, options).await
+	}
+
+	async fn write_if(
+		&self,
+		path: &Path,
+		content: &[u8],
+		precondition: &Precondition,
+	) -> Result<()> {
+		AdapterObject::write_if(&*self.0, path, content, precondition).await
+	}
+
+	async fn write_atomic(&self, path: &Path, content: &[u8]) -> Result<()> {
+		AdapterObject::write_atomic(&*self.0, path, content).await
+	}
+
+	async fn write_stream(
+		&self,
+		path: &Path,
+		reader: &mut (dyn AsyncRead + Send + Unpin),
+	) -> Result<()> {
+		AdapterObject::write_stream(&*self.0, path, reader).await
+	}
+
+	async fn write_range(&self, path: &Path, offset: u64, data: &[u8]) -> Result<()> {
+		AdapterObject::write_range(&*self.0, path, offset, data).await
+	}
+
+	async fn append(&self, path: &Path, data: &[u8]) -> Result<()> {
+		AdapterObject::append(&*self.0, path, data).await
+	}
+
+	fn native_range_writes(&self) -> bool {
+		AdapterObject::native_range_writes(&*self.0)
+	}
+
+	fn path_model(&self) -> PathModel {
+		AdapterObject::path_model(&*self.0)
+	}
+
+	async fn read(&self, path: &Path) -> Result<Contents> {
+		AdapterObject::read(&*self.0, path).await
+	}
+
+	async fn read_stream(&self, path: &Path) -> Result<Box<dyn AsyncRead + Send + Unpin>> {
+		AdapterObject::read_stream(&*self.0, path).await
+	}
+
+	async fn read_into(&self, path: &Path, buf: &mut (dyn BufMut + Send)) -> Result<()> {
+		AdapterObject::read_into(&*self.0, path, buf).await
+	}
+
+	async fn read_range(&self, path: &Path, offset: u64, len: u64) -> Result<Contents> {
+		AdapterObject::read_range(&*self.0, path, offset, len).await
+	}
+
+	async fn list_versions(&self, prefix: &Path) -> Result<Vec<FileVersion>> {
+		AdapterObject::list_versions(&*self.0, prefix).await
+	}
+
+	async fn read_version(&self, path: &Path, version_id: &str) -> Result<Contents> {
+		AdapterObject::read_version(&*self.0, path, version_id).await
+	}
+
+	async fn restore_version(&self, path: &Path, version_id: &str) -> Result<()> {
+		AdapterObject::restore_version(&*self.0, path, version_id).await
+	}
+
+	async fn delete(&self, path: &Path) -> Result<()> {
+		AdapterObject::delete(&*self.0, path).await
+	}
+
+	async fn delete_many(&self, paths: &[PathBuf]) -> Vec<(PathBuf, Error)> {
+		AdapterObject::delete_many(&*self.0, paths).await
+	}
+
+	async fn write_many(&self, files: &[(&Path, &[u8])]) -> Vec<(PathBuf, Error)> {
+		AdapterObject::write_many(&*self.0, files).await
+	}
+
+	async fn delete_directory(&self, path: &Path) -> Result<()> {
+		AdapterObject::delete_directory(&*self.0, path).await
+	}
+
+	async fn create_directory(&self, path: &Path) -> Result<()> {
+		AdapterObject::create_directory(&*self.0, path).await
+	}
+
+	async fn set_mime_type(&self, path: &Path, mime_type: &Mime) -> Result<()> {
+		AdapterObject::set_mime_type(&*self.0, path, mime_type).await
+	}
+
+	async fn set_visibility(&self, path: &Path, visibility: Visibility) -> Result<()> {
+		AdapterObject::set_visibility(&*self.0, path, visibility).await
+	}
+
+	async fn set_visibility_many(
+		&self,
+		paths: &[PathBuf],
+		visibility: Visibility,
+	) -> Vec<(PathBuf, Error)> {
+		AdapterObject::set_visibility_many(&*self.0, paths, visibility).await
+	}
+
+	async fn visibility(&self, path: &Path) -> Result<Visibility> {
+		AdapterObject::visibility(&*self.0, path).await
+	}
+
+	async fn mime_type(&self, path: &Path) -> Result<Mime> {
+		AdapterObject::mime_type(&*self.0, path).await
+	}
+
+	async fn metadata(&self, path: &Path) -> Result<HashMap<String, String>> {
+		AdapterObject::metadata(&*self.0, path).await
+	}
+
+	async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		AdapterObject::last_modified(&*self.0, path).await
+	}
+
+	async fn file_size(&self, path: &Path) -> Result<u64> {
+		AdapterObject::file_size(&*self.0, path).await
+	}
+
+	async fn directory_stat(&self, path: &Path) -> Result<DirectoryStat> {
+		AdapterObject::directory_stat(&*self.0, path).await
+	}
+
+	async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		AdapterObject::list_contents(&*self.0, path, deep).await
+	}
+
+	async fn list_contents_with_metadata(
+		&self,
+		path: &Path,
+		deep: bool,
+	) -> Result<Vec<StorageEntry>> {
+		AdapterObject::list_contents_with_metadata(&*self.0, path, deep).await
+	}
+
+	fn list_stream<'a>(&'a self, path: &Path, deep: bool) -> BoxStream<'a, Result<PathBuf>> {
+		AdapterObject::list_stream(&*self.0, path, deep)
+	}
+
+	fn list_stream_after<'a>(
+		&'a self,
+		path: &Path,
+		deep: bool,
+		after: Option<&Path>,
+	) -> BoxStream<'a, Result<PathBuf>> {
+		AdapterObject::list_stream_after(&*self.0, path, deep, after)
+	}
+
+	async fn r#move(&self, source: &Path, destination: &Path) -> Result<()> {
+		AdapterObject::r#move(&*self.0, source, destination).await
+	}
+
+	async fn copy(&self, source: &Path, destination: &Path) -> Result<()> {
+		AdapterObject::copy(&*self.0, source, destination).await
+	}
+
+	async fn move_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		AdapterObject::move_directory(&*self.0, source, destination).await
+	}
+
+	async fn copy_directory(&self, source: &Path, destination: &Path) -> Result<()> {
+		AdapterObject::copy_directory(&*self.0, source, destination).await
+	}
+
+	async fn checksum(&self, path: &Path) -> Result<String> {
+		AdapterObject::checksum(&*self.0, path).await
+	}
+
+	async fn public_url(&self, path: &Path) -> Result<Url> {
+		AdapterObject::public_url(&*self.0, path).await
+	}
+
+	async fn temporary_url(&self, path: &Path, expires_in: Duration) -> Result<Url> {
+		AdapterObject::temporary_url(&*self.0, path, expires_in).await
+	}
+
+	async fn temporary_url_with(
+		&self,
+		path: &Path,
+		expires_in: Duration,
+		options: &TemporaryUrlOptions,
+	) -> Result<Url> {
+		AdapterObject::temporary_url_with(&*self.0, path, expires_in, options).await
+	}
+
+	fn layers(&self) -> Vec<AdapterLayer> {
+		AdapterObject::layers(&*self.0)
+	}
+
+	async fn prefetch(&self, path: &Path) -> Result<()> {
+		AdapterObject::prefetch(&*self.0, path).await
+	}
+
+	async fn flush(&self) -> Result<()> {
+		AdapterObject::flush(&*self.0).await
+	}
 }
@@ -0,0 +1,430 @@
+use std::{
+	io::Error,
+	path::{Path, PathBuf},
+	time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{normalize, Filesystem, FlysystemError, Result};
+
+/// The directory files replaced or deleted by a transaction are kept in until it's committed.
+pub const DEFAULT_TRASH: &str = ".flysystem-trash";
+
+#[derive(Debug, Clone)]
+enum Operation {
+	Write {
+		path: PathBuf,
+		contents: Vec<u8>,
+	},
+	Delete {
+		path: PathBuf,
+	},
+	Copy {
+		source: PathBuf,
+		destination: PathBuf,
+	},
+	Move {
+		source: PathBuf,
+		destination: PathBuf,
+	},
+}
+
+impl Operation {
+	fn path(&self) -> &Path {
+		match self {
+			Self::Write { path, .. } | Self::Delete { path } => path,
+			Self::Copy { destination, .. } | Self::Move { destination, .. } => destination,
+		}
+	}
+}
+
+/// How to undo an applied step.
+#[derive(Debug)]
+enum Compensation {
+	/// The file didn't exist before, so it's deleted.
+	Created(PathBuf),
+	/// The file was set aside in the trash, so it's put back.
+	Trashed { path: PathBuf, trashed: PathBuf },
+	/// The file was moved, so it's moved back.
+	Moved {
+		source: PathBuf,
+		destination: PathBuf,
+	},
+}
+
+/// A sequence of operations applied with [`Filesystem::commit`], undoing the applied ones if any of them fails.
+///
+/// Files overwritten or deleted by the transaction are moved to a trash directory instead, and only removed once every operation succeeded,
+/// so they can be put back. Rolling back is best-effort: the storage isn't locked, and a rollback step can fail too
+/// (see [`TransactionError::rollback_failures`]).
+#[derive(Debug, Clone)]
+pub struct Transaction {
+	operations: Vec<Operation>,
+	trash: PathBuf,
+}
+
+impl Default for Transaction {
+	fn default() -> Self {
+		Self {
+			operations: Vec::new(),
+			trash: PathBuf::from(DEFAULT_TRASH),
+		}
+	}
+}
+
+impl Transaction {
+	/// Start an empty transaction.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Keep the files replaced or deleted by the transaction in `trash` until it's committed, instead of [`DEFAULT_TRASH`].
+	#[must_use]
+	pub fn with_trash(mut self, trash: impl Into<PathBuf>) -> Self {
+		self.trash = trash.into();
+		self
+	}
+
+	/// Write a file, overwriting it if it exists.
+	pub fn write(&mut self, path: impl Into<PathBuf>, contents: impl Into<Vec<u8>>) -> &mut Self {
+		self.operations.push(Operation::Write {
+			path: path.into(),
+			contents: contents.into(),
+		});
+		self
+	}
+
+	/// Delete a file.
+	pub fn delete(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+		self.operations
+			.push(Operation::Delete { path: path.into() });
+		self
+	}
+
+	/// Copy a file, overwriting the destination if it exists.
+	pub fn copy(
+		&mut self,
+		source: impl Into<PathBuf>,
+		destination: impl Into<PathBuf>,
+	) -> &mut Self {
+		self.operations.push(Operation::Copy {
+			source: source.into(),
+			destination: destination.into(),
+		});
+		self
+	}
+
+	/// Move a file, overwriting the destination if it exists.
+	pub fn r#move(
+		&mut self,
+		source: impl Into<PathBuf>,
+		destination: impl Into<PathBuf>,
+	) -> &mut Self {
+		self.operations.push(Operation::Move {
+			source: source.into(),
+			destination: destination.into(),
+		});
+		self
+	}
+
+	/// Get the number of operations in the transaction.
+	#[must_use]
+	pub const fn len(&self) -> usize {
+		self.operations.len()
+	}
+
+	/// Check if the transaction has no operations.
+	#[must_use]
+	pub const fn is_empty(&self) -> bool {
+		self.operations.is_empty()
+	}
+}
+
+/// A failed [`Transaction`], after rolling back the operations applied before the failure.
+#[derive(Debug, thiserror::Error)]
+#[error("Operation {step} of the transaction failed on {}: {error}", path.display())]
+pub struct TransactionError {
+	/// The index of the operation that failed.
+	pub step: usize,
+	/// The path the failed operation was writing to.
+	pub path: PathBuf,
+	/// Why the operation failed.
+	#[source]
+	pub error: FlysystemError,
+	/// The files that couldn't be restored while rolling back, along with their errors.
+	/// These are left as the transaction changed them (or in the trash, if they were deleted or overwritten).
+	pub rollback_failures: Vec<(PathBuf, FlysystemError)>,
+}
+
+impl TransactionError {
+	/// Check if every applied operation was undone.
+	#[must_use]
+	pub const fn is_rolled_back(&self) -> bool {
+		self.rollback_failures.is_empty()
+	}
+}
+
+impl From<TransactionError> for Error {
+	fn from(error: TransactionError) -> Self {
+		Self::new(error.error.kind(), error)
+	}
+}
+
+impl Filesystem {
+	/// Apply every operation of a transaction in order, rolling back the applied ones (on a best-effort basis) if one fails.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if any of the operations fails, describing which one and what couldn't be rolled back.
+	pub async fn commit(
+		&self,
+		transaction: Transaction,
+	) -> std::result::Result<(), TransactionError> {
+		let root = normalize(&transaction.trash);
+		let trash = root.join(transaction_id());
+		let mut applied = Vec::new();
+
+		for (step, operation) in transaction.operations.into_iter().enumerate() {
+			if let Err(error) = self.apply(&operation, &trash, &mut applied).await {
+				let rollback_failures = self.roll_back(applied).await;
+				// whatever couldn't be restored is left in the trash, so it can be recovered by hand
+				if rollback_failures.is_empty() {
+					self.empty_trash(&root, &trash).await;
+				}
+
+				return Err(TransactionError {
+					step,
+					path: operation.path().to_path_buf(),
+					error,
+					rollback_failures,
+				});
+			}
+		}
+
+		if applied
+			.iter()
+			.any(|compensation| matches!(compensation, Compensation::Trashed { .. }))
+		{
+			// the transaction went through, so failing to empty the trash only leaves stale copies behind
+			self.empty_trash(&root, &trash).await;
+		}
+
+		Ok(())
+	}
+
+	/// Delete the trash directory of a transaction, along with the shared trash directory once no other transaction uses it.
+	async fn empty_trash(&self, root: &Path, trash: &Path) {
+		let _ = self.delete_directory(trash).await;
+
+		if self
+			.list_contents(root, true)
+			.await
+			.is_ok_and(|contents| contents.is_empty())
+		{
+			let _ = self.delete_directory(root).await;
+		}
+	}
+
+	async fn apply(
+		&self,
+		operation: &Operation,
+		trash: &Path,
+		applied: &mut Vec<Compensation>,
+	) -> Result<()> {
+		match operation {
+			Operation::Write { path, contents } => {
+				self.set_aside(path, trash, applied).await?;
+				self.write(path, contents).await?;
+				applied.push(Compensation::Created(self.normalize(path)?));
+			},
+			Operation::Delete { path } => {
+				let path = self.normalize(path)?;
+				if !self.file_exists(&path).await? {
+					return Err(FlysystemError::FileNotFound { path });
+				}
+				self.ensure_deletable(&path).await?;
+
+				self.set_aside(&path, trash, applied).await?;
+			},
+			Operation::Copy {
+				source,
+				destination,
+			} => {
+				self.set_aside(destination, trash, applied).await?;
+				self.copy(source, destination).await?;
+				applied.push(Compensation::Created(self.normalize(destination)?));
+			},
+			Operation::Move {
+				source,
+				destination,
+			} => {
+				self.set_aside(destination, trash, applied).await?;
+				self.r#move(source, destination).await?;
+				applied.push(Compensation::Moved {
+					source: self.normalize(source)?,
+					destination: self.normalize(destination)?,
+				});
+			},
+		}
+
+		Ok(())
+	}
+
+	/// Move a file about to be replaced or deleted to the trash, if it exists.
+	async fn set_aside(
+		&self,
+		path: &Path,
+		trash: &Path,
+		applied: &mut Vec<Compensation>,
+	) -> Result<()> {
+		let path = self.normalize(path)?;
+		if !self.file_exists(&path).await? {
+			return Ok(());
+		}
+
+		let trashed = trash.join(&path);
+		self.r#move(&path, &trashed).await?;
+		applied.push(Compensation::Trashed { path, trashed });
+
+		Ok(())
+	}
+
+	/// Undo the applied steps, most recent first, returning the files that couldn't be restored.
+	async fn roll_back(&self, applied: Vec<Compensation>) -> Vec<(PathBuf, FlysystemError)> {
+		let mut failures = Vec::new();
+		// files created by the transaction weren't there before, so removing them is fine even under protected paths
+		let filesystem = self.overriding_protection();
+
+		for compensation in applied.into_iter().rev() {
+			let (path, result) = match compensation {
+				Compensation::Created(path) => {
+					let result = filesystem.delete(&path).await;
+					(path, result)
+				},
+				Compensation::Trashed { path, trashed } => {
+					let result = self.r#move(&trashed, &path).await;
+					(path, result)
+				},
+				Compensation::Moved {
+					source,
+					destination,
+				} => {
+					let result = self.r#move(&destination, &source).await;
+					(source, result)
+				},
+			};
+
+			if let Err(error) = result {
+				failures.push((path, error));
+			}
+		}
+
+		failures
+	}
+}
+
+/// Get a name for the trash directory of a transaction, unique enough that concurrent transactions don't share it.
+fn transaction_id() -> String {
+	let now = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.unwrap_or_default();
+
+	format!("{:x}", now.as_nanos())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::ErrorKind;
+
+	use super::*;
+	use crate::adapters::MemoryAdapter;
+
+	#[tokio::test]
+	async fn test_commit() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default());
+		filesystem
+			.write(Path::new("site/index.html"), b"v1")
+			.await
+			.unwrap();
+		filesystem
+			.write(Path::new("site/old.html"), b"old")
+			.await
+			.unwrap();
+
+		let mut transaction = Transaction::new();
+		transaction
+			.write("site/index.html", "v2")
+			.write("site/about.html", "about")
+			.delete("site/old.html");
+		filesystem.commit(transaction).await.unwrap();
+
+		assert_eq!(
+			filesystem
+				.read::<String>(Path::new("site/index.html"))
+				.await
+				.unwrap(),
+			"v2"
+		);
+		assert!(filesystem
+			.file_exists(Path::new("site/about.html"))
+			.await
+			.unwrap());
+		assert!(!filesystem
+			.file_exists(Path::new("site/old.html"))
+			.await
+			.unwrap());
+		assert!(!filesystem
+			.directory_exists(Path::new(DEFAULT_TRASH))
+			.await
+			.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_rollback() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::default());
+		filesystem
+			.write(Path::new("site/index.html"), b"v1")
+			.await
+			.unwrap();
+		filesystem
+			.write(Path::new("site/old.html"), b"old")
+			.await
+			.unwrap();
+
+		let mut transaction = Transaction::new();
+		transaction
+			.write("site/index.html", "v2")
+			.write("site/about.html", "about")
+			.delete("site/old.html")
+			.copy("site/missing.css", "site/style.css");
+		let error = filesystem.commit(transaction).await.unwrap_err();
+
+		assert_eq!(error.step, 3);
+		assert_eq!(error.path, PathBuf::from("site/style.css"));
+		assert_eq!(error.error.kind(), ErrorKind::NotFound);
+		assert!(error.is_rolled_back());
+
+		assert_eq!(
+			filesystem
+				.read::<String>(Path::new("site/index.html"))
+				.await
+				.unwrap(),
+			"v1"
+		);
+		assert_eq!(
+			filesystem
+				.read::<String>(Path::new("site/old.html"))
+				.await
+				.unwrap(),
+			"old"
+		);
+		assert!(!filesystem
+			.file_exists(Path::new("site/about.html"))
+			.await
+			.unwrap());
+		assert!(!filesystem
+			.directory_exists(Path::new(DEFAULT_TRASH))
+			.await
+			.unwrap());
+	}
+}
@@ -0,0 +1,205 @@
+use bytes::Bytes;
+use futures::{Stream, StreamExt, TryStreamExt};
+use mime::Mime;
+use std::{
+	error::Error as StdError,
+	io::{Error, ErrorKind},
+	path::{Path, PathBuf},
+	sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{
+	sanitizer::{display_name, SanitizeFilename},
+	Filesystem, Result,
+};
+
+/// A file stored from an upload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredFile {
+	pub path: PathBuf,
+	/// The name of the file as uploaded, to show to users (the stored path may have been sanitized).
+	pub display_name: String,
+	pub size: u64,
+	pub mime_type: Mime,
+}
+
+impl Filesystem {
+	/// Store an upload streamed by a web framework (like an `axum` or `actix-multipart` field), writing it as it's received.
+	///
+	/// Uploads larger than `max_size` bytes are rejected without leaving a partial file behind.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the upload stream fails, if it exceeds `max_size` (with `ErrorKind::InvalidData`), or if the adapter fails to write the file.
+	pub async fn store_upload<S, E>(
+		&self,
+		path: &Path,
+		field: S,
+		max_size: u64,
+	) -> Result<StoredFile>
+	where
+		S: Stream<Item = std::result::Result<Bytes, E>> + Send,
+		E: Into<Box<dyn StdError + Send + Sync>>,
+	{
+		let path = self.normalize(path)?;
+		let size = AtomicU64::new(0);
+
+		let mut reader = Box::pin(field.map(|chunk| {
+			let chunk = chunk.map_err(Error::other)?;
+			let total = size.fetch_add(chunk.len() as u64, Ordering::Relaxed) + chunk.len() as u64;
+
+			if total > max_size {
+				return Err(Error::new(
+					ErrorKind::InvalidData,
+					format!("The upload exceeds the maximum size of {max_size} bytes."),
+				));
+			}
+
+			Ok(chunk)
+		}))
+		.into_async_read();
+
+		self.write_stream(&path, &mut reader).await?;
+		drop(reader);
+
+		Ok(StoredFile {
+			mime_type: self.mime_type(&path).await?,
+			display_name: path
+				.file_name()
+				.unwrap_or_default()
+				.to_string_lossy()
+				.into_owned(),
+			size: size.into_inner(),
+			path,
+		})
+	}
+
+	/// Store an upload under `directory`, naming it after the (untrusted) `file_name` sent by the client once sanitized.
+	///
+	/// The returned file has both the path it was stored at and the original name, to show to users.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the upload stream fails, if it exceeds `max_size` (with `ErrorKind::InvalidData`), or if the adapter fails to write the file.
+	pub async fn store_named_upload<S, E>(
+		&self,
+		directory: &Path,
+		file_name: &str,
+		field: S,
+		max_size: u64,
+		sanitizer: &impl SanitizeFilename,
+	) -> Result<StoredFile>
+	where
+		S: Stream<Item = std::result::Result<Bytes, E>> + Send,
+		E: Into<Box<dyn StdError + Send + Sync>>,
+	{
+		let path = directory.join(sanitizer.sanitize(file_name));
+
+		Ok(StoredFile {
+			display_name: display_name(file_name).to_string(),
+			..self.store_upload(&path, field, max_size).await?
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{adapters::MemoryAdapter, FilenameSanitizer};
+
+	fn field(
+		chunks: &'static [&'static [u8]],
+	) -> impl Stream<Item = std::io::Result<Bytes>> + Send {
+		futures::stream::iter(chunks.iter().map(|chunk| Ok(Bytes::from_static(chunk))))
+	}
+
+	#[tokio::test]
+	async fn test_store_upload() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+
+		let stored = filesystem
+			.store_upload(
+				Path::new("/uploads/hello.txt"),
+				field(&[b"Hello, ", b"world!"]),
+				1024,
+			)
+			.await
+			.unwrap();
+
+		assert_eq!(
+			stored,
+			StoredFile {
+				path: PathBuf::from("uploads/hello.txt"),
+				display_name: "hello.txt".to_string(),
+				size: 13,
+				mime_type: mime::TEXT_PLAIN,
+			}
+		);
+		assert_eq!(
+			filesystem
+				.read::<String>(Path::new("uploads/hello.txt"))
+				.await
+				.unwrap(),
+			"Hello, world!"
+		);
+	}
+
+	#[tokio::test]
+	async fn test_store_named_upload() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+
+		let stored = filesystem
+			.store_named_upload(
+				Path::new("uploads"),
+				"C:\\Users\\Zoë\\Quarterly report?.txt",
+				field(&[b"Hello, world!"]),
+				1024,
+				&FilenameSanitizer::new(),
+			)
+			.await
+			.unwrap();
+
+		assert_eq!(stored.path, PathBuf::from("uploads/Quarterly-report.txt"));
+		assert_eq!(stored.display_name, "Quarterly report?.txt");
+		assert!(filesystem.file_exists(&stored.path).await.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_store_upload_too_large() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+
+		let error = filesystem
+			.store_upload(
+				Path::new("uploads/hello.txt"),
+				field(&[b"Hello, ", b"world!"]),
+				10,
+			)
+			.await
+			.unwrap_err();
+
+		assert_eq!(error.kind(), ErrorKind::InvalidData);
+		assert!(!filesystem
+			.file_exists(Path::new("uploads/hello.txt"))
+			.await
+			.unwrap());
+	}
+
+	#[tokio::test]
+	async fn test_store_upload_stream_error() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+
+		let field = futures::stream::iter(vec![
+			Ok(Bytes::from_static(b"Hello")),
+			Err(Error::other("connection reset")),
+		]);
+
+		assert!(filesystem
+			.store_upload(Path::new("uploads/hello.txt"), field, 1024)
+			.await
+			.is_err());
+		assert!(!filesystem
+			.file_exists(Path::new("uploads/hello.txt"))
+			.await
+			.unwrap());
+	}
+}
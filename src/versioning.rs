@@ -0,0 +1,463 @@
+use std::{
+	cmp::Reverse,
+	collections::{BTreeMap, BTreeSet},
+	io::{Error, ErrorKind},
+	path::{Path, PathBuf},
+	time::SystemTime,
+};
+
+use crate::{contents::Contents, Filesystem, FlysystemError, Result};
+
+/// A version of a file, on adapters that keep previous versions around.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileVersion {
+	pub path: PathBuf,
+	pub version_id: String,
+	pub last_modified: SystemTime,
+	pub size: u64,
+	/// Whether this version marks the file as deleted, instead of holding its contents.
+	pub deleted: bool,
+	/// Whether this is the file's current version (or the marker it was deleted with), which breaks ties between versions
+	/// modified within the same second on storage with one-second timestamps, like S3.
+	pub is_latest: bool,
+}
+
+/// A read-only view of a [`Filesystem`] as it was at a point in time, for adapters that support versioning.
+#[derive(Debug, Clone, Copy)]
+pub struct Snapshot<'a> {
+	filesystem: &'a Filesystem,
+	at: SystemTime,
+}
+
+impl Filesystem {
+	/// Get a read-only view of the filesystem as it was at the given time, resolving each path to its latest version at that time.
+	///
+	/// Only adapters backed by versioned storage (like S3 buckets with versioning enabled) can be viewed as of a past time,
+	/// the snapshot's methods return an `Unsupported` error otherwise.
+	#[must_use]
+	pub const fn as_of(&self, at: SystemTime) -> Snapshot<'_> {
+		Snapshot {
+			filesystem: self,
+			at,
+		}
+	}
+
+	/// List the versions of a file, newest first, including the markers left by deletions.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter doesn't support versioning or fails to list the versions.
+	pub async fn list_versions(&self, path: &Path) -> Result<Vec<FileVersion>> {
+		let path = self.normalize(path)?;
+		let mut versions: Vec<FileVersion> = self
+			.adapter
+			.list_versions(&path)
+			.await
+			.map_err(|error| FlysystemError::reading(&path, error))?
+			.into_iter()
+			.filter(|version| version.path == path)
+			.collect();
+
+		versions.sort_by_key(|version| Reverse((version.last_modified, version.is_latest)));
+		Ok(versions)
+	}
+
+	/// Get the contents of a previous version of a file.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the version doesn't exist (or marks a deletion), if the adapter doesn't support versioning,
+	/// or if the contents can't be converted to the requested type.
+	pub async fn read_version<R: TryFrom<Contents>>(
+		&self,
+		path: &Path,
+		version_id: &str,
+	) -> Result<R> {
+		let path = self.normalize(path)?;
+		let contents = self
+			.adapter
+			.read_version(&path, version_id)
+			.await
+			.map_err(|error| FlysystemError::reading(&path, error))?;
+
+		contents
+			.try_into()
+			.map_err(|_| FlysystemError::UnableToReadFile {
+				path,
+				source: Error::new(ErrorKind::InvalidData, "Could not decode contents."),
+			})
+	}
+
+	/// Make a previous version of a file its current one. It's written as a new version, so the versions after it are kept.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the version doesn't exist, if the adapter doesn't support versioning or fails to write the file,
+	/// or if the configured CDN purger fails to invalidate the replaced version (in which case the file has already been restored).
+	pub async fn restore_version(&self, path: &Path, version_id: &str) -> Result<()> {
+		let path = self.normalize(path)?;
+		self.write_file(&path, true, || {
+			self.adapter.restore_version(&path, version_id)
+		})
+		.await
+	}
+}
+
+impl Snapshot<'_> {
+	/// The point in time this snapshot shows.
+	#[must_use]
+	pub const fn timestamp(&self) -> SystemTime {
+		self.at
+	}
+
+	/// Check if a file existed.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter doesn't support versioning or fails to list the file's versions.
+	pub async fn file_exists(&self, path: &Path) -> Result<bool> {
+		Ok(self.version(path).await?.is_some())
+	}
+
+	/// Get the contents of a file at the time of the snapshot.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the file didn't exist, if the adapter doesn't support versioning or fails to read the version,
+	/// or if the contents can't be converted to the requested type.
+	pub async fn read<R: TryFrom<Contents>>(&self, path: &Path) -> Result<R> {
+		let version = self.existing_version(path).await?;
+
+		self.filesystem
+			.adapter
+			.read_version(&version.path, &version.version_id)
+			.await
+			.map_err(|error| FlysystemError::reading(&version.path, error))?
+			.try_into()
+			.map_err(|_| FlysystemError::UnableToReadFile {
+				path: version.path,
+				source: Error::new(ErrorKind::InvalidData, "Could not decode contents."),
+			})
+	}
+
+	/// Get the size of a file at the time of the snapshot.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the file didn't exist, or if the adapter doesn't support versioning.
+	pub async fn file_size(&self, path: &Path) -> Result<u64> {
+		Ok(self.existing_version(path).await?.size)
+	}
+
+	/// Get the date and time the file had last been modified at, as of the snapshot.
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the file didn't exist, or if the adapter doesn't support versioning.
+	pub async fn last_modified(&self, path: &Path) -> Result<SystemTime> {
+		Ok(self.existing_version(path).await?.last_modified)
+	}
+
+	/// Get the list of files that were in a directory (optionally recursively), sorted by path.
+	///
+	/// Like [`Filesystem::list_contents`], shallow listings include the subdirectories (that had files in them).
+	///
+	/// # Errors
+	///
+	/// This function will return an error if the adapter doesn't support versioning or fails to list the versions.
+	pub async fn list_contents(&self, path: &Path, deep: bool) -> Result<Vec<PathBuf>> {
+		let path = self.filesystem.normalize(path)?;
+		let versions = self
+			.filesystem
+			.adapter
+			.list_versions(&path)
+			.await
+			.map_err(|error| FlysystemError::listing(&path, error))?;
+
+		let contents: BTreeSet<PathBuf> = latest_versions(versions, self.at)
+			.into_keys()
+			.filter_map(|entry| {
+				let relative = entry.strip_prefix(&path).ok()?;
+				if deep {
+					return Some(entry.clone());
+				}
+
+				relative.components().next().map(|child| path.join(child))
+			})
+			.collect();
+
+		Ok(contents.into_iter().collect())
+	}
+
+	async fn version(&self, path: &Path) -> Result<Option<FileVersion>> {
+		let path = self.filesystem.normalize(path)?;
+		let versions = self
+			.filesystem
+			.adapter
+			.list_versions(&path)
+			.await
+			.map_err(|error| FlysystemError::reading(&path, error))?;
+
+		Ok(latest_versions(versions, self.at).remove(&path))
+	}
+
+	async fn existing_version(&self, path: &Path) -> Result<FileVersion> {
+		let path = self.filesystem.normalize(path)?;
+
+		self.version(&path)
+			.await?
+			.ok_or(FlysystemError::FileNotFound { path })
+	}
+}
+
+/// Resolve every path to its latest version at the given time, skipping the ones that were deleted by then.
+///
+/// Versions modified at the same time are told apart by [`FileVersion::is_latest`], then by the order the adapter listed them in
+/// (newest first, see [`Adapter::list_versions`](crate::adapters::Adapter::list_versions)).
+fn latest_versions(versions: Vec<FileVersion>, at: SystemTime) -> BTreeMap<PathBuf, FileVersion> {
+	let mut latest: BTreeMap<PathBuf, FileVersion> = BTreeMap::new();
+
+	for version in versions
+		.into_iter()
+		.filter(|version| version.last_modified <= at)
+	{
+		match latest.get(&version.path) {
+			Some(current)
+				if (current.last_modified, current.is_latest)
+					>= (version.last_modified, version.is_latest) => {},
+			_ => {
+				latest.insert(version.path.clone(), version);
+			},
+		}
+	}
+
+	latest.retain(|_, version| !version.deleted);
+	latest
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::*;
+	use crate::adapters::{Adapter, MemoryAdapter, ObjectLockAdapter, SupportsVersioning};
+
+	async fn tick() -> SystemTime {
+		tokio::time::sleep(Duration::from_millis(5)).await;
+		let now = SystemTime::now();
+		tokio::time::sleep(Duration::from_millis(5)).await;
+
+		now
+	}
+
+	#[tokio::test]
+	async fn test_as_of() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::versioned());
+
+		filesystem
+			.write(Path::new("reports/march.txt"), b"draft")
+			.await
+			.unwrap();
+		let drafted = tick().await;
+
+		filesystem
+			.write(Path::new("reports/march.txt"), b"final")
+			.await
+			.unwrap();
+		filesystem
+			.write(Path::new("reports/april.txt"), b"draft")
+			.await
+			.unwrap();
+		let finalized = tick().await;
+
+		filesystem
+			.delete(Path::new("reports/march.txt"))
+			.await
+			.unwrap();
+
+		let snapshot = filesystem.as_of(drafted);
+		assert_eq!(
+			snapshot
+				.read::<String>(Path::new("reports/march.txt"))
+				.await
+				.unwrap(),
+			"draft"
+		);
+		assert!(!snapshot
+			.file_exists(Path::new("reports/april.txt"))
+			.await
+			.unwrap());
+		assert_eq!(
+			snapshot
+				.list_contents(Path::new("reports"), false)
+				.await
+				.unwrap(),
+			vec![PathBuf::from("reports/march.txt")]
+		);
+
+		let snapshot = filesystem.as_of(finalized);
+		assert_eq!(
+			snapshot
+				.read::<String>(Path::new("reports/march.txt"))
+				.await
+				.unwrap(),
+			"final"
+		);
+		assert_eq!(
+			snapshot.list_contents(Path::new("/"), true).await.unwrap(),
+			vec![
+				PathBuf::from("reports/april.txt"),
+				PathBuf::from("reports/march.txt")
+			]
+		);
+
+		let snapshot = filesystem.as_of(SystemTime::now());
+		assert_eq!(
+			snapshot
+				.read::<String>(Path::new("reports/march.txt"))
+				.await
+				.unwrap_err()
+				.kind(),
+			ErrorKind::NotFound
+		);
+	}
+
+	#[test]
+	fn test_latest_versions_within_the_same_second() {
+		let at = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+		let version = |path: &str, version_id: &str, deleted, is_latest| FileVersion {
+			path: PathBuf::from(path),
+			version_id: version_id.to_string(),
+			last_modified: at,
+			size: 0,
+			deleted,
+			is_latest,
+		};
+
+		// S3 lists delete markers after the versions, so a file deleted right after being written is listed as written last
+		let versions = vec![
+			version("deleted.txt", "1", false, false),
+			version("deleted.txt", "2", true, true),
+			version("rewritten.txt", "3", false, true),
+			version("rewritten.txt", "4", true, false),
+			version("listed.txt", "6", false, false),
+			version("listed.txt", "5", false, false),
+		];
+
+		let latest = latest_versions(versions, at);
+		assert_eq!(
+			latest.keys().collect::<Vec<_>>(),
+			vec![Path::new("listed.txt"), Path::new("rewritten.txt")]
+		);
+		assert_eq!(latest[Path::new("rewritten.txt")].version_id, "3");
+		// without a latest version, the one listed first (the newest) wins
+		assert_eq!(latest[Path::new("listed.txt")].version_id, "6");
+	}
+
+	#[tokio::test]
+	async fn test_as_of_lists_subdirectories() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::versioned());
+
+		filesystem
+			.write(Path::new("reports/2024/march.txt"), b"march")
+			.await
+			.unwrap();
+		filesystem
+			.write(Path::new("reports/2024/april.txt"), b"april")
+			.await
+			.unwrap();
+		filesystem
+			.write(Path::new("reports/summary.txt"), b"summary")
+			.await
+			.unwrap();
+		let snapshot = filesystem.as_of(tick().await);
+
+		assert_eq!(
+			snapshot
+				.list_contents(Path::new("reports"), false)
+				.await
+				.unwrap(),
+			vec![
+				PathBuf::from("reports/2024"),
+				PathBuf::from("reports/summary.txt")
+			]
+		);
+		assert_eq!(
+			snapshot
+				.list_contents(Path::new("reports"), true)
+				.await
+				.unwrap(),
+			vec![
+				PathBuf::from("reports/2024/april.txt"),
+				PathBuf::from("reports/2024/march.txt"),
+				PathBuf::from("reports/summary.txt")
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn test_restore_version() {
+		let filesystem = Filesystem::from_adapter(MemoryAdapter::versioned());
+		let path = Path::new("notes.txt");
+
+		filesystem.write(path, b"first").await.unwrap();
+		tick().await;
+		filesystem.write(path, b"second").await.unwrap();
+		filesystem
+			.write(Path::new("notes.txt.bak"), b"backup")
+			.await
+			.unwrap();
+
+		let versions = filesystem.list_versions(path).await.unwrap();
+		assert_eq!(versions.len(), 2);
+		assert_eq!(
+			filesystem
+				.read_version::<String>(path, &versions[1].version_id)
+				.await
+				.unwrap(),
+			"first"
+		);
+
+		tick().await;
+		filesystem
+			.restore_version(path, &versions[1].version_id)
+			.await
+			.unwrap();
+		assert_eq!(filesystem.read::<String>(path).await.unwrap(), "first");
+		assert_eq!(filesystem.list_versions(path).await.unwrap().len(), 3);
+	}
+
+	#[tokio::test]
+	async fn test_as_of_unversioned_adapter() {
+		let filesystem = Filesystem::new::<MemoryAdapter>(()).await.unwrap();
+
+		assert_eq!(
+			filesystem
+				.as_of(SystemTime::now())
+				.file_exists(Path::new("file.txt"))
+				.await
+				.unwrap_err()
+				.kind(),
+			ErrorKind::Unsupported
+		);
+	}
+
+	#[tokio::test]
+	async fn test_supports_versioning_marker() {
+		async fn history<A: SupportsVersioning>(adapter: &A, path: &Path) -> Vec<FileVersion> {
+			adapter.list_versions(path).await.unwrap()
+		}
+
+		let adapter = ObjectLockAdapter::new(MemoryAdapter::versioned());
+		adapter
+			.write(Path::new("notes.txt"), b"first")
+			.await
+			.unwrap();
+		adapter
+			.write(Path::new("notes.txt"), b"second")
+			.await
+			.unwrap();
+
+		assert_eq!(history(&adapter, Path::new("notes.txt")).await.len(), 2);
+	}
+}
@@ -0,0 +1,403 @@
+use hmac::{digest::KeyInit, Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::{
+	fmt::{self, Write},
+	path::Path,
+	sync::{Arc, OnceLock},
+	time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::{mpsc, oneshot};
+use url::Url;
+
+use crate::{
+	events::{EventListener, FilesystemEvent},
+	REDACTED,
+};
+
+/// The header containing the hex-encoded HMAC-SHA256 signature of the request body.
+pub const SIGNATURE_HEADER: &str = "X-Flysystem-Signature";
+
+/// How long a delivery attempt can take by default, before it's considered failed.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The longest wait between two delivery attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// An event listener posting every event as JSON to a URL.
+///
+/// Payloads look like `{"event":"write","path":"avatars/1.png","timestamp":1700000000}`.
+/// When a secret is configured, they're signed with HMAC-SHA256 in the `X-Flysystem-Signature` header (as `sha256=<hex digest>`).
+/// Failed deliveries are retried with exponential backoff, and dropped once all retries are exhausted.
+///
+/// Events are delivered in order from a background task, so a slow endpoint never holds up filesystem operations.
+/// Use [`Filesystem::shutdown`](crate::Filesystem::shutdown) to wait for pending deliveries before exiting.
+#[derive(Clone)]
+pub struct Webhook {
+	url: Url,
+	max_retries: u32,
+	timeout: Duration,
+	client: reqwest::Client,
+	secret: Option<Vec<u8>>,
+	queue: Arc<OnceLock<mpsc::UnboundedSender<Job>>>,
+}
+
+/// Work for the background task delivering the events of a webhook.
+enum Job {
+	/// Deliver a payload, giving up on retries past the deadline of the operation that emitted it.
+	Deliver {
+		body: Vec<u8>,
+		deadline: Option<Instant>,
+	},
+	/// Report back once every earlier delivery is done.
+	Flush(oneshot::Sender<()>),
+}
+
+impl fmt::Debug for Webhook {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Webhook")
+			.field("url", &self.url)
+			.field("max_retries", &self.max_retries)
+			.field("timeout", &self.timeout)
+			.field("secret", &self.secret.as_ref().map(|_| REDACTED))
+			.finish_non_exhaustive()
+	}
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+	event: &'a str,
+	path: &'a Path,
+	timestamp: u64,
+}
+
+impl Webhook {
+	/// Create a webhook posting events to the given URL.
+	#[must_use]
+	pub fn new(url: Url) -> Self {
+		Self {
+			url,
+			secret: None,
+			max_retries: 3,
+			timeout: DEFAULT_TIMEOUT,
+			client: reqwest::Client::new(),
+			queue: Arc::new(OnceLock::new()),
+		}
+	}
+
+	/// Sign every payload with the given secret.
+	#[must_use]
+	pub fn with_secret(mut self, secret: impl Into<Vec<u8>>) -> Self {
+		self.secret = Some(secret.into());
+		self
+	}
+
+	/// Set how many times a failed delivery is retried (defaults to 3).
+	#[must_use]
+	pub const fn with_max_retries(mut self, max_retries: u32) -> Self {
+		self.max_retries = max_retries;
+		self
+	}
+
+	/// Set how long a delivery attempt can take before it's retried (defaults to 10 seconds).
+	#[must_use]
+	pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+		self.timeout = timeout;
+		self
+	}
+
+	/// Get the queue of the background delivery task, starting it on the first event.
+	fn queue(&self) -> &mpsc::UnboundedSender<Job> {
+		self.queue.get_or_init(|| {
+			let (sender, mut receiver) = mpsc::unbounded_channel();
+			// the task keeps its own copy of the webhook without the queue, so it ends once every clone
+			// of the webhook (and so every sender) is dropped
+			let webhook = Self {
+				queue: Arc::new(OnceLock::new()),
+				..self.clone()
+			};
+
+			tokio::spawn(async move {
+				while let Some(job) = receiver.recv().await {
+					match job {
+						Job::Deliver { body, deadline } => webhook.deliver(&body, deadline).await,
+						Job::Flush(done) => {
+							let _ = done.send(());
+						},
+					}
+				}
+			});
+
+			sender
+		})
+	}
+
+	/// Deliver a payload, retrying failed attempts.
+	async fn deliver(&self, body: &[u8], deadline: Option<Instant>) {
+		for attempt in 0..=self.max_retries {
+			if attempt > 0 {
+				let backoff = backoff(attempt);
+
+				// don't start a retry that can't finish before the deadline of the operation that triggered the event
+				if deadline.is_some_and(|deadline| {
+					deadline.saturating_duration_since(Instant::now()) <= backoff
+				}) {
+					return;
+				}
+
+				tokio::time::sleep(backoff).await;
+			}
+
+			if self.attempt(body, deadline).await {
+				return;
+			}
+		}
+	}
+
+	async fn attempt(&self, body: &[u8], deadline: Option<Instant>) -> bool {
+		let timeout = deadline.map_or(self.timeout, |deadline| {
+			deadline
+				.saturating_duration_since(Instant::now())
+				.min(self.timeout)
+		});
+
+		let mut request = self
+			.client
+			.post(self.url.clone())
+			.header(reqwest::header::CONTENT_TYPE, "application/json")
+			.timeout(timeout)
+			.body(body.to_vec());
+
+		if let Some(secret) = &self.secret {
+			request = request.header(SIGNATURE_HEADER, format!("sha256={}", sign(secret, body)));
+		}
+
+		request
+			.send()
+			.await
+			.is_ok_and(|response| response.status().is_success())
+	}
+}
+
+/// How long to wait before the given retry: 100ms, doubling with each attempt up to [`MAX_BACKOFF`].
+fn backoff(attempt: u32) -> Duration {
+	Duration::from_millis(100)
+		.saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+		.min(MAX_BACKOFF)
+}
+
+#[async_trait::async_trait]
+impl EventListener for Webhook {
+	async fn handle(&self, event: &FilesystemEvent) {
+		let Ok(body) = serde_json::to_vec(&Payload {
+			event: event.name(),
+			path: event.path(),
+			timestamp: SystemTime::now()
+				.duration_since(UNIX_EPOCH)
+				.map_or(0, |elapsed| elapsed.as_secs()),
+		}) else {
+			return;
+		};
+
+		#[cfg(feature = "deadlines")]
+		let deadline = crate::deadline::remaining().map(|remaining| Instant::now() + remaining);
+		#[cfg(not(feature = "deadlines"))]
+		let deadline = None;
+
+		let _ = self.queue().send(Job::Deliver { body, deadline });
+	}
+
+	async fn flush(&self) {
+		let Some(queue) = self.queue.get() else {
+			return;
+		};
+
+		let (done, flushed) = oneshot::channel();
+		if queue.send(Job::Flush(done)).is_ok() {
+			let _ = flushed.await;
+		}
+	}
+}
+
+/// Compute the hex-encoded HMAC-SHA256 of `body`, as sent in the signature header.
+#[must_use]
+pub fn sign(secret: &[u8], body: &[u8]) -> String {
+	let mut mac = <Hmac<Sha256> as KeyInit>::new_from_slice(secret)
+		.unwrap_or_else(|_| unreachable!("HMAC accepts keys of any size"));
+	mac.update(body);
+
+	mac.finalize()
+		.into_bytes()
+		.iter()
+		.fold(String::new(), |mut hex, byte| {
+			let _ = write!(hex, "{byte:02x}");
+			hex
+		})
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::{Arc, Mutex};
+	use tokio::{
+		io::{AsyncReadExt, AsyncWriteExt},
+		net::TcpListener,
+	};
+
+	use super::*;
+	use crate::{adapters::MemoryAdapter, Filesystem};
+
+	/// A tiny HTTP server recording request bodies and signatures, failing the first `failures` requests.
+	async fn receiver(failures: usize) -> (Url, Arc<Mutex<Vec<(String, String)>>>) {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let url = Url::parse(&format!("http://{}/hook", listener.local_addr().unwrap())).unwrap();
+		let requests = Arc::new(Mutex::new(Vec::new()));
+
+		let received = requests.clone();
+		tokio::spawn(async move {
+			let mut remaining_failures = failures;
+
+			while let Ok((mut socket, _)) = listener.accept().await {
+				let mut request = Vec::new();
+				let mut buf = [0u8; 4096];
+
+				let (head, body) = loop {
+					let n = socket.read(&mut buf).await.unwrap();
+					request.extend_from_slice(&buf[..n]);
+
+					let text = String::from_utf8_lossy(&request).to_string();
+					if let Some((head, body)) = text.split_once("\r\n\r\n") {
+						let content_length = head
+							.lines()
+							.find_map(|line| {
+								line.to_lowercase()
+									.strip_prefix("content-length: ")
+									.map(str::to_string)
+							})
+							.map_or(0, |len| len.parse::<usize>().unwrap());
+
+						if body.len() >= content_length {
+							break (head.to_string(), body.to_string());
+						}
+					}
+				};
+
+				let signature = head
+					.lines()
+					.find_map(|line| line.strip_prefix("x-flysystem-signature: "))
+					.unwrap_or_default()
+					.to_string();
+
+				let status = if remaining_failures > 0 {
+					remaining_failures -= 1;
+					"500 Internal Server Error"
+				} else {
+					received.lock().unwrap().push((body, signature));
+					"200 OK"
+				};
+
+				socket
+					.write_all(
+						format!(
+							"HTTP/1.1 {status}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n"
+						)
+						.as_bytes(),
+					)
+					.await
+					.unwrap();
+			}
+		});
+
+		(url, requests)
+	}
+
+	#[tokio::test]
+	async fn test_webhook_posts_signed_events() {
+		let (url, requests) = receiver(0).await;
+		let filesystem = Filesystem::new::<MemoryAdapter>(())
+			.await
+			.unwrap()
+			.with_listener(Webhook::new(url).with_secret("secret"));
+
+		filesystem
+			.write(Path::new("avatars/1.png"), b"Hello, world!")
+			.await
+			.unwrap();
+		filesystem.delete(Path::new("avatars/1.png")).await.unwrap();
+		filesystem.shutdown().await.unwrap();
+
+		let requests = requests.lock().unwrap().clone();
+		assert_eq!(requests.len(), 2);
+
+		let (body, signature) = &requests[0];
+		let payload: serde_json::Value = serde_json::from_str(body).unwrap();
+		assert_eq!(payload["event"], "write");
+		assert_eq!(payload["path"], "avatars/1.png");
+		assert_eq!(
+			signature,
+			&format!("sha256={}", sign(b"secret", body.as_bytes()))
+		);
+
+		let payload: serde_json::Value = serde_json::from_str(&requests[1].0).unwrap();
+		assert_eq!(payload["event"], "delete");
+	}
+
+	#[tokio::test]
+	async fn test_webhook_retries_failed_deliveries() {
+		let (url, requests) = receiver(2).await;
+		let filesystem = Filesystem::new::<MemoryAdapter>(())
+			.await
+			.unwrap()
+			.with_listener(Webhook::new(url).with_max_retries(2));
+
+		filesystem
+			.write(Path::new("file.txt"), b"Hello, world!")
+			.await
+			.unwrap();
+		filesystem.shutdown().await.unwrap();
+
+		assert_eq!(requests.lock().unwrap().len(), 1);
+	}
+
+	#[tokio::test]
+	async fn test_webhook_doesnt_block_on_hung_endpoints() {
+		// accept connections, but never answer
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let url = Url::parse(&format!("http://{}/hook", listener.local_addr().unwrap())).unwrap();
+		tokio::spawn(async move {
+			while let Ok((socket, _)) = listener.accept().await {
+				tokio::spawn(async move {
+					let _socket = socket;
+					std::future::pending::<()>().await;
+				});
+			}
+		});
+
+		let filesystem = Filesystem::new::<MemoryAdapter>(())
+			.await
+			.unwrap()
+			.with_listener(
+				Webhook::new(url)
+					.with_max_retries(1)
+					.with_timeout(Duration::from_millis(100)),
+			);
+
+		let start = Instant::now();
+		filesystem
+			.write(Path::new("file.txt"), b"Hello, world!")
+			.await
+			.unwrap();
+		assert!(start.elapsed() < Duration::from_millis(100));
+
+		// both attempts time out, with the backoff in between
+		filesystem.shutdown().await.unwrap();
+		assert!(start.elapsed() >= Duration::from_millis(300));
+	}
+
+	#[test]
+	fn test_backoff() {
+		assert_eq!(backoff(1), Duration::from_millis(100));
+		assert_eq!(backoff(3), Duration::from_millis(400));
+		assert_eq!(backoff(64), MAX_BACKOFF);
+		assert_eq!(backoff(u32::MAX), MAX_BACKOFF);
+	}
+}